@@ -0,0 +1,128 @@
+//! Hot path of the monitoring & of the distribution: evaluation of the health
+//! factor of the positions, parsing of the indexed events & payout math.
+
+use std::{hint::black_box, path::PathBuf};
+
+use bigdecimal::BigDecimal;
+use cainome::cairo_serde::U256;
+use criterion::{Criterion, criterion_group, criterion_main};
+use starknet::core::types::Felt;
+use vesu_liquidator::{
+    cli::NetworkName,
+    config::{Config, LiquidationMode, MODIFY_POSITION_EVENT},
+    protocols::Protocols,
+    services::oracle::LatestOraclePrices,
+    types::{
+        asset::Asset,
+        distribution::{EarningsSplit, decayed_score, proportional_share},
+        position::Position,
+    },
+};
+
+fn config() -> Config {
+    Config::new(
+        NetworkName::Mainnet,
+        LiquidationMode::Full,
+        &PathBuf::from("./config.yaml"),
+    )
+    .expect("config.yaml is valid")
+}
+
+/// Two monitored assets & their priced oracle.
+fn priced_assets(config: &Config) -> (Asset, Asset, LatestOraclePrices) {
+    let mut addresses: Vec<Felt> = config.asset_map.keys().copied().collect();
+    addresses.sort();
+    let collateral = Asset::from_address(config, addresses[0]).unwrap();
+    let debt = Asset::from_address(config, addresses[1]).unwrap();
+    let prices = LatestOraclePrices::from_config(config);
+    prices.update(&collateral.name.to_lowercase(), BigDecimal::from(3_000));
+    prices.update(&debt.name.to_lowercase(), BigDecimal::from(1));
+    (collateral, debt, prices)
+}
+
+fn bench_health_factor(c: &mut Criterion) {
+    let config = config();
+    let (collateral, debt, prices) = priced_assets(&config);
+    let position = Position {
+        user_address: Felt::ONE,
+        pool_id: Felt::ONE,
+        collateral: Asset {
+            amount: "1.5".parse().unwrap(),
+            ..collateral
+        },
+        debt: Asset {
+            amount: "3200".parse().unwrap(),
+            ..debt
+        },
+        lltv: "0.8".parse().unwrap(),
+        protocol: Default::default(),
+    };
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    c.bench_function("health_factor", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { black_box(&position).health_factor(&prices).await })
+    });
+    c.bench_function("is_liquidable", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { black_box(&position).is_liquidable(&prices).await })
+    });
+}
+
+fn bench_event_parsing(c: &mut Criterion) {
+    let config = config();
+    let protocols = Protocols::from_config(&config);
+    let mut addresses: Vec<Felt> = config.asset_map.keys().copied().collect();
+    addresses.sort();
+    let keys = [
+        *MODIFY_POSITION_EVENT,
+        Felt::ONE,
+        addresses[0],
+        addresses[1],
+        Felt::from(0xabc_u64),
+    ];
+
+    c.bench_function("positions_from_event", |b| {
+        b.iter(|| {
+            protocols.positions_from_event(
+                &config,
+                black_box(config.singleton_address),
+                black_box(&keys),
+                &[],
+            )
+        })
+    });
+}
+
+fn bench_payout_math(c: &mut Criterion) {
+    let config = config();
+    let total = U256 {
+        low: 123_456_789_000_000_000_000,
+        high: 0,
+    };
+
+    c.bench_function("earnings_split", |b| {
+        b.iter(|| {
+            EarningsSplit::new(
+                &config.distribution,
+                black_box(total),
+                black_box(4_200),
+                black_box(10_000),
+            )
+        })
+    });
+    c.bench_function("proportional_share", |b| {
+        b.iter(|| proportional_share(black_box(total), black_box(7), black_box(13)))
+    });
+    c.bench_function("decayed_score", |b| {
+        b.iter(|| decayed_score(black_box(10_000), black_box(2_000_000), 86_400, 604_800))
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_health_factor,
+    bench_event_parsing,
+    bench_payout_math
+);
+criterion_main!(benches);
@@ -0,0 +1,66 @@
+//! Cost of finding the positions to check after a price move, scanning all
+//! of them vs reading the shards of the moved asset.
+
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use starknet::core::types::Felt;
+use vesu_liquidator::{
+    protocols::ProtocolKind,
+    types::{
+        asset::Asset,
+        position::{Position, PositionKey, PositionsMap},
+    },
+};
+
+const COLLATERAL_ASSETS: u64 = 20;
+
+fn positions(count: u64) -> PositionsMap {
+    let positions = PositionsMap::new();
+    for user in 0..count {
+        let collateral = user % COLLATERAL_ASSETS;
+        positions.insert(Position {
+            user_address: Felt::from(user),
+            pool_id: Felt::ONE,
+            collateral: Asset::new(
+                format!("asset{collateral}"),
+                Felt::from(0x100 + collateral),
+                18,
+            ),
+            debt: Asset::new("usdc".to_string(), Felt::from(0x20_u64), 6),
+            lltv: "0.8".parse().unwrap(),
+            protocol: ProtocolKind::Vesu,
+        });
+    }
+    positions
+}
+
+fn full_scan(positions: &PositionsMap, asset: &str) -> Vec<PositionKey> {
+    positions
+        .by_key
+        .iter()
+        .filter(|entry| {
+            let position = entry.value();
+            position.collateral.name.eq_ignore_ascii_case(asset)
+                || position.debt.name.eq_ignore_ascii_case(asset)
+        })
+        .map(|entry| *entry.key())
+        .collect()
+}
+
+fn bench_price_move(c: &mut Criterion) {
+    let mut group = c.benchmark_group("price_move");
+    for count in [1_000, 10_000, 50_000] {
+        let positions = positions(count);
+        group.bench_with_input(BenchmarkId::new("full_scan", count), &positions, |b, p| {
+            b.iter(|| full_scan(p, black_box("asset3")))
+        });
+        group.bench_with_input(BenchmarkId::new("sharded", count), &positions, |b, p| {
+            b.iter(|| p.keys_with_assets([black_box("asset3")]))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_price_move);
+criterion_main!(benches);
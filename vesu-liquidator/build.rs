@@ -14,17 +14,31 @@ fn main() {
     let strk_bind_base = current_dir()
         .expect("failed to get current dir")
         .join("src/bindings");
-    let strk_deployments = [("Liquidate", "liquidate")];
+    // ABI file, contract name & module of the bindings
+    let strk_deployments = [
+        (
+            "vesu_periphery_Liquidate.contract_class.json",
+            "Liquidate",
+            "liquidate",
+        ),
+        (
+            "vesu_periphery_v2_Liquidate.abi.json",
+            "LiquidateV2",
+            "liquidate_v2",
+        ),
+        ("shoot_it_Actions.abi.json", "Actions", "actions"),
+    ];
 
     // create destination folders if they doesn't exist
     fs::create_dir_all(strk_bind_base.clone()).expect("error creating output folders");
     let mut file = File::create(strk_bind_base.join("mod.rs")).expect("failed to create mod.rs");
+    file.write_all(b"#![allow(clippy::all, unused_assignments, unreachable_patterns)]\n")
+        .expect("failed to write into mod.rs");
 
-    for (abi_file, bind_out) in strk_deployments {
-        let contract_files =
-            strk_abi_base.join(format!("vesu_periphery_{abi_file}.contract_class.json"));
+    for (abi_file, contract_name, bind_out) in strk_deployments {
+        let contract_files = strk_abi_base.join(abi_file);
         let contract_files = contract_files.to_str().unwrap();
-        let abigen = cainome::rs::Abigen::new(abi_file, contract_files)
+        let abigen = cainome::rs::Abigen::new(contract_name, contract_files)
             .with_execution_version(ExecutionVersion::V3)
             .with_derives(vec![
                 "Debug".into(),
@@ -44,9 +58,11 @@ fn main() {
             )
             .unwrap_or_else(|_| panic!("Fail to write bindings to file in {:?}", strk_bind_base));
 
-        file.write_all(b"#![allow(clippy::all, unused_assignments, unreachable_patterns)]\n")
-            .expect("failed to write into mod.rs");
-        file.write_all(format!("pub mod {};", bind_out).as_bytes())
+        file.write_all(format!("pub mod {};\n", bind_out).as_bytes())
             .expect("failed to write into mod.rs");
     }
+
+    // Generate the gRPC control plane
+    tonic_build::compile_protos("proto/control.proto")
+        .expect("failed to compile the control plane protos");
 }
@@ -0,0 +1,8 @@
+pub mod models;
+pub mod systems {
+    pub mod game;
+}
+pub mod mocks {
+    pub mod liquidate;
+    pub mod singleton;
+}
@@ -0,0 +1,165 @@
+use starknet::ContractAddress;
+
+// Same layout as the parameters of the Liquidate contract of vesu_periphery,
+// so the calldata built by the bot deserializes as is.
+#[derive(Copy, Drop, Serde)]
+pub struct PoolKey {
+    pub token0: ContractAddress,
+    pub token1: ContractAddress,
+    pub fee: u128,
+    pub tick_spacing: u128,
+    pub extension: ContractAddress,
+}
+
+#[derive(Copy, Drop, Serde)]
+pub struct RouteNode {
+    pub pool_key: PoolKey,
+    pub sqrt_ratio_limit: u256,
+    pub skip_ahead: u128,
+}
+
+#[derive(Copy, Drop, Serde)]
+pub struct i129 {
+    pub mag: u128,
+    pub sign: bool,
+}
+
+#[derive(Copy, Drop, Serde)]
+pub struct TokenAmount {
+    pub token: ContractAddress,
+    pub amount: i129,
+}
+
+#[derive(Drop, Serde)]
+pub struct Swap {
+    pub route: Array<RouteNode>,
+    pub token_amount: TokenAmount,
+}
+
+#[derive(Drop, Serde)]
+pub struct LiquidateParams {
+    pub pool_id: felt252,
+    pub collateral_asset: ContractAddress,
+    pub debt_asset: ContractAddress,
+    pub user: ContractAddress,
+    pub recipient: ContractAddress,
+    pub min_collateral_to_receive: u256,
+    pub debt_to_repay: u256,
+    pub liquidate_swap: Array<Swap>,
+    pub liquidate_swap_limit_amount: u128,
+    pub liquidate_swap_weights: Array<u128>,
+    pub withdraw_swap: Array<Swap>,
+    pub withdraw_swap_limit_amount: u128,
+    pub withdraw_swap_weights: Array<u128>,
+}
+
+#[derive(Copy, Drop, Serde)]
+pub struct LiquidateResponse {
+    pub liquidated_collateral: u256,
+    pub repaid_debt: u256,
+    pub residual_collateral: u256,
+    pub residual_token: ContractAddress,
+}
+
+#[starknet::interface]
+pub trait IMockLiquidate<T> {
+    fn liquidate(ref self: T, params: LiquidateParams) -> LiquidateResponse;
+}
+
+#[starknet::interface]
+pub trait IERC20<T> {
+    fn transfer(ref self: T, recipient: ContractAddress, amount: u256) -> bool;
+}
+
+// Liquidate contract without the Ekubo swaps: the collateral covering the
+// debt is swapped 1:1 & the rest is paid to the recipient from the balance of
+// the mock, funded by the test.
+#[starknet::contract]
+pub mod MockLiquidate {
+    use starknet::ContractAddress;
+    use starknet::storage::{StoragePointerReadAccess, StoragePointerWriteAccess};
+    use crate::mocks::singleton::{IMockSingletonDispatcher, IMockSingletonDispatcherTrait};
+    use super::{
+        IERC20Dispatcher, IERC20DispatcherTrait, IMockLiquidate, LiquidateParams,
+        LiquidateResponse,
+    };
+
+    #[storage]
+    struct Storage {
+        singleton: ContractAddress,
+    }
+
+    #[event]
+    #[derive(Drop, starknet::Event)]
+    pub enum Event {
+        LiquidatePosition: LiquidatePosition,
+    }
+
+    #[derive(Drop, starknet::Event)]
+    pub struct LiquidatePosition {
+        #[key]
+        pub pool_id: felt252,
+        #[key]
+        pub collateral_asset: ContractAddress,
+        #[key]
+        pub debt_asset: ContractAddress,
+        #[key]
+        pub user: ContractAddress,
+        pub residual: u256,
+        pub collateral_delta: u256,
+        pub debt_delta: u256,
+    }
+
+    #[constructor]
+    fn constructor(ref self: ContractState, singleton: ContractAddress) {
+        self.singleton.write(singleton);
+    }
+
+    #[abi(embed_v0)]
+    impl MockLiquidateImpl of IMockLiquidate<ContractState> {
+        fn liquidate(ref self: ContractState, params: LiquidateParams) -> LiquidateResponse {
+            let singleton = IMockSingletonDispatcher { contract_address: self.singleton.read() };
+            let (_, collateral, debt) = singleton
+                .position_unsafe(
+                    params.pool_id, params.collateral_asset, params.debt_asset, params.user,
+                );
+            assert(debt > 0, 'no debt to repay');
+            assert(collateral >= debt, 'bad debt');
+            let residual = collateral - debt;
+
+            let ltv_config = singleton
+                .ltv_config(params.pool_id, params.collateral_asset, params.debt_asset);
+            singleton
+                .set_position(
+                    params.pool_id,
+                    params.collateral_asset,
+                    params.debt_asset,
+                    params.user,
+                    0,
+                    0,
+                    ltv_config.max_ltv,
+                );
+            IERC20Dispatcher { contract_address: params.collateral_asset }
+                .transfer(params.recipient, residual);
+
+            self
+                .emit(
+                    LiquidatePosition {
+                        pool_id: params.pool_id,
+                        collateral_asset: params.collateral_asset,
+                        debt_asset: params.debt_asset,
+                        user: params.user,
+                        residual,
+                        collateral_delta: collateral,
+                        debt_delta: debt,
+                    },
+                );
+            LiquidateResponse {
+                liquidated_collateral: collateral,
+                repaid_debt: debt,
+                residual_collateral: residual,
+                residual_token: params.collateral_asset,
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use starknet::ContractAddress;
+
+#[derive(Copy, Drop, Serde)]
+pub struct Position {
+    pub collateral_shares: u256,
+    pub nominal_debt: u256,
+}
+
+#[derive(Copy, Drop, Serde)]
+pub struct LTVConfig {
+    pub max_ltv: u64,
+}
+
+#[starknet::interface]
+pub trait IMockSingleton<T> {
+    fn set_position(
+        ref self: T,
+        pool_id: felt252,
+        collateral_asset: ContractAddress,
+        debt_asset: ContractAddress,
+        user: ContractAddress,
+        collateral: u256,
+        debt: u256,
+        max_ltv: u64,
+    );
+    fn position_unsafe(
+        self: @T,
+        pool_id: felt252,
+        collateral_asset: ContractAddress,
+        debt_asset: ContractAddress,
+        user: ContractAddress,
+    ) -> (Position, u256, u256);
+    fn ltv_config(
+        self: @T, pool_id: felt252, collateral_asset: ContractAddress, debt_asset: ContractAddress,
+    ) -> LTVConfig;
+}
+
+// Vesu singleton reduced to what the bot reads: positions are set as is &
+// announced by a `ModifyPosition` event with the keys of the real one.
+#[starknet::contract]
+pub mod MockSingleton {
+    use starknet::ContractAddress;
+    use starknet::storage::{Map, StorageMapReadAccess, StorageMapWriteAccess};
+    use super::{IMockSingleton, LTVConfig, Position};
+
+    #[storage]
+    struct Storage {
+        collaterals: Map<(felt252, ContractAddress, ContractAddress, ContractAddress), u256>,
+        debts: Map<(felt252, ContractAddress, ContractAddress, ContractAddress), u256>,
+        max_ltvs: Map<(felt252, ContractAddress, ContractAddress), u64>,
+    }
+
+    #[event]
+    #[derive(Drop, starknet::Event)]
+    pub enum Event {
+        ModifyPosition: ModifyPosition,
+    }
+
+    #[derive(Drop, starknet::Event)]
+    pub struct ModifyPosition {
+        #[key]
+        pub pool_id: felt252,
+        #[key]
+        pub collateral_asset: ContractAddress,
+        #[key]
+        pub debt_asset: ContractAddress,
+        #[key]
+        pub user: ContractAddress,
+        pub collateral: u256,
+        pub debt: u256,
+    }
+
+    #[abi(embed_v0)]
+    impl MockSingletonImpl of IMockSingleton<ContractState> {
+        fn set_position(
+            ref self: ContractState,
+            pool_id: felt252,
+            collateral_asset: ContractAddress,
+            debt_asset: ContractAddress,
+            user: ContractAddress,
+            collateral: u256,
+            debt: u256,
+            max_ltv: u64,
+        ) {
+            let key = (pool_id, collateral_asset, debt_asset, user);
+            self.collaterals.write(key, collateral);
+            self.debts.write(key, debt);
+            self.max_ltvs.write((pool_id, collateral_asset, debt_asset), max_ltv);
+            self
+                .emit(
+                    ModifyPosition {
+                        pool_id, collateral_asset, debt_asset, user, collateral, debt,
+                    },
+                );
+        }
+
+        fn position_unsafe(
+            self: @ContractState,
+            pool_id: felt252,
+            collateral_asset: ContractAddress,
+            debt_asset: ContractAddress,
+            user: ContractAddress,
+        ) -> (Position, u256, u256) {
+            let key = (pool_id, collateral_asset, debt_asset, user);
+            let collateral = self.collaterals.read(key);
+            let debt = self.debts.read(key);
+            (Position { collateral_shares: collateral, nominal_debt: debt }, collateral, debt)
+        }
+
+        fn ltv_config(
+            self: @ContractState,
+            pool_id: felt252,
+            collateral_asset: ContractAddress,
+            debt_asset: ContractAddress,
+        ) -> LTVConfig {
+            LTVConfig { max_ltv: self.max_ltvs.read((pool_id, collateral_asset, debt_asset)) }
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use starknet::ContractAddress;
+
+// Models of the game read by the bot through Torii. The scores are u64 so
+// Torii serves them as JSON numbers.
+
+// A player waiting for a share of the next liquidation.
+#[derive(Copy, Drop, Serde)]
+#[dojo::model]
+pub struct Redeem {
+    #[key]
+    pub player: ContractAddress,
+    pub score: u64,
+    // Unix timestamp of the redeem, decaying the score of stale entries.
+    pub timestamp: u64,
+}
+
+#[derive(Copy, Drop, Serde)]
+#[dojo::model]
+pub struct HighestScore {
+    #[key]
+    pub player: ContractAddress,
+    pub score: u64,
+}
+
+// Address a player gets paid on instead of its account.
+#[derive(Copy, Drop, Serde)]
+#[dojo::model]
+pub struct PayoutAddress {
+    #[key]
+    pub player: ContractAddress,
+    pub payout_address: ContractAddress,
+    pub set_by: ContractAddress,
+}
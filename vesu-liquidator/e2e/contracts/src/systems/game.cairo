@@ -0,0 +1,32 @@
+#[starknet::interface]
+pub trait IGame<T> {
+    fn redeem(ref self: T, score: u64);
+}
+
+#[dojo::contract]
+pub mod game {
+    use dojo::model::ModelStorage;
+    use dojo::world::{WorldStorage, WorldStorageTrait};
+    use crate::models::{HighestScore, Redeem};
+    use super::IGame;
+
+    #[abi(embed_v0)]
+    impl GameImpl of IGame<ContractState> {
+        // Queues the caller for the next payout with the given score.
+        fn redeem(ref self: ContractState, score: u64) {
+            let player = starknet::get_caller_address();
+            let mut world = self.world_default();
+
+            let timestamp = starknet::get_block_timestamp();
+            world.write_model(@Redeem { player, score, timestamp });
+            world.write_model(@HighestScore { player, score });
+        }
+    }
+
+    #[generate_trait]
+    impl InternalImpl of InternalTrait {
+        fn world_default(self: @ContractState) -> WorldStorage {
+            self.world(@"e2e")
+        }
+    }
+}
@@ -0,0 +1,72 @@
+#!/bin/bash
+
+# Runs the end-to-end test of the liquidator on a Katana devnet: starts
+# Katana, migrates the game world of `contracts` & starts Torii on it, then
+# runs the ignored `e2e` test, which deploys the Vesu mocks, opens an
+# undercollateralized position & runs the bot binary against it.
+# All services shut down on script exit.
+
+set -euo pipefail
+
+SCRIPT_DIR="$( cd "$( dirname "${BASH_SOURCE[0]}" )" && pwd )"
+CONTRACTS_DIR="$SCRIPT_DIR/contracts"
+LOGS_DIR="${E2E_LOGS_DIR:-$(mktemp -d)}"
+KATANA_PORT="${KATANA_PORT:-5050}"
+TORII_PORT="${TORII_PORT:-8080}"
+
+KATANA_PID=""
+TORII_PID=""
+
+cleanup() {
+    for pid in "$TORII_PID" "$KATANA_PID"; do
+        if [ -n "$pid" ] && kill -0 "$pid" 2>/dev/null; then
+            kill "$pid" 2>/dev/null || true
+            wait "$pid" 2>/dev/null || true
+        fi
+    done
+    echo "Logs: $LOGS_DIR"
+}
+trap cleanup EXIT
+
+wait_for() {
+    local name="$1" url="$2" log="$3"
+    for _ in {1..30}; do
+        if curl -s "$url" > /dev/null 2>&1; then
+            return 0
+        fi
+        sleep 1
+    done
+    echo "Error: $name failed to start"
+    cat "$log"
+    exit 1
+}
+
+for tool in katana sozo torii scarb; do
+    if ! command -v "$tool" &> /dev/null; then
+        echo "Error: $tool is not installed, see https://book.dojoengine.org/installation"
+        exit 1
+    fi
+done
+
+echo "Starting Katana..."
+katana --dev --http.port "$KATANA_PORT" > "$LOGS_DIR/katana.log" 2>&1 &
+KATANA_PID=$!
+wait_for Katana "http://localhost:$KATANA_PORT" "$LOGS_DIR/katana.log"
+
+echo "Building & migrating the game world..."
+cd "$CONTRACTS_DIR"
+sozo build
+sozo migrate --profile dev --rpc-url "http://localhost:$KATANA_PORT"
+WORLD_ADDRESS=$(grep -o '"address": "0x[^"]*"' manifest_dev.json | head -1 | cut -d'"' -f4)
+
+echo "Starting Torii on world $WORLD_ADDRESS..."
+torii --world "$WORLD_ADDRESS" --rpc "http://localhost:$KATANA_PORT" \
+    --http.port "$TORII_PORT" > "$LOGS_DIR/torii.log" 2>&1 &
+TORII_PID=$!
+wait_for Torii "http://localhost:$TORII_PORT/graphql" "$LOGS_DIR/torii.log"
+
+echo "Running the e2e test..."
+cd "$SCRIPT_DIR/.."
+E2E_RPC_URL="http://localhost:$KATANA_PORT" \
+E2E_TORII_GRAPHQL_URL="http://localhost:$TORII_PORT/graphql" \
+    cargo test --test e2e -- --ignored --nocapture
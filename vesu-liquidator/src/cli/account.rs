@@ -3,11 +3,31 @@ use std::{path::PathBuf, str::FromStr};
 use anyhow::{Result, anyhow};
 use clap::Args;
 use starknet::core::types::Felt;
+use url::Url;
 
-fn parse_felt(s: &str) -> Result<Felt> {
+pub fn parse_felt(s: &str) -> Result<Felt> {
     Felt::from_str(s).map_err(|_| anyhow!("Could not convert {s} to Felt"))
 }
 
+/// Parses an extra account given as `ADDRESS:PRIVATE_KEY`.
+pub fn parse_extra_account(s: &str) -> Result<ExtraAccount> {
+    let (address, private_key) = s
+        .split_once(':')
+        .ok_or_else(|| anyhow!("Extra accounts are expected as ADDRESS:PRIVATE_KEY"))?;
+    Ok(ExtraAccount {
+        address: parse_felt(address)?,
+        private_key: parse_felt(private_key)?,
+    })
+}
+
+/// Funded account the liquidations are rotated to, along with the liquidator
+/// account.
+#[derive(Clone, Debug)]
+pub struct ExtraAccount {
+    pub address: Felt,
+    pub private_key: Felt,
+}
+
 #[derive(Clone, Debug, Args)]
 pub struct AccountParams {
     /// Account address of the liquidator account
@@ -22,23 +42,104 @@ pub struct AccountParams {
     #[clap(long, value_name = "LIQUIDATOR KEYSTORE")]
     pub keystore_path: Option<PathBuf>,
 
-    /// Keystore password for the liquidator account
+    /// Keystore password for the liquidator account. Prompted for when
+    /// neither it nor `--keystore-password-file` is given.
     #[clap(long, value_name = "LIQUIDATOR KEYSTORE PASSWORD")]
     pub keystore_password: Option<String>,
+
+    /// File holding the keystore password for the liquidator account, e.g. a
+    /// mounted secret.
+    #[clap(long, value_name = "LIQUIDATOR KEYSTORE PASSWORD FILE")]
+    pub keystore_password_file: Option<PathBuf>,
+
+    /// URL of a remote signer holding the key of the liquidator account, so
+    /// the key is never loaded by the bot.
+    #[clap(long, value_name = "REMOTE SIGNER URL", env = "REMOTE_SIGNER_URL")]
+    pub remote_signer_url: Option<Url>,
+
+    /// File holding the bearer token authenticating to the remote signer.
+    #[clap(long, value_name = "REMOTE SIGNER TOKEN FILE")]
+    pub remote_signer_token_file: Option<PathBuf>,
+
+    /// Session authorized by the owner of the liquidator account, as json:
+    /// the key given is then the session key, restricted to the calls of the
+    /// session, and the owner key stays offline.
+    #[clap(long, value_name = "SESSION PATH", env = "SESSION_PATH")]
+    pub session_path: Option<PathBuf>,
+
+    /// Extra funded accounts the liquidations are rotated across, so a
+    /// pending transaction doesn't hold the next ones. The earnings are still
+    /// sent to the liquidator account.
+    #[clap(long = "extra-account", value_parser = parse_extra_account, value_name = "ADDRESS:PRIVATE KEY", env = "EXTRA_ACCOUNTS", value_delimiter = ',')]
+    pub extra_accounts: Vec<ExtraAccount>,
 }
 
 impl AccountParams {
     pub fn validate(&self) -> Result<()> {
+        let mut addresses = vec![self.account_address];
+        for extra_account in self.extra_accounts.iter() {
+            if addresses.contains(&extra_account.address) {
+                return Err(anyhow!(
+                    "Account {:#x} is given more than once",
+                    extra_account.address
+                ));
+            }
+            addresses.push(extra_account.address);
+        }
+        if self.remote_signer_token_file.is_some() && self.remote_signer_url.is_none() {
+            return Err(anyhow!(
+                "--remote-signer-token-file requires --remote-signer-url."
+            ));
+        }
         match (
             &self.private_key,
             &self.keystore_path,
             &self.keystore_password,
+            &self.keystore_password_file,
+            &self.remote_signer_url,
         ) {
-            (Some(_), None, None) => Ok(()),
-            (None, Some(_), Some(_)) => Ok(()),
+            (Some(_), None, None, None, None) => Ok(()),
+            (None, None, None, None, Some(_)) => Ok(()),
+            (None, Some(_), Some(_), Some(_), None) => Err(anyhow!(
+                "Use either --keystore-password or --keystore-password-file, not both."
+            )),
+            (None, Some(_), _, _, None) => Ok(()),
             _ => Err(anyhow!(
-                "Missing liquidator account key. Use either (--private-key) or (--keystore-path + --keystore-password)."
+                "Missing liquidator account key. Use either (--private-key), (--keystore-path + optionally --keystore-password or --keystore-password-file) or (--remote-signer-url)."
             )),
         }
     }
+
+    /// Returns the bearer token of the remote signer, read from its file.
+    pub fn remote_signer_token(&self) -> Result<Option<String>> {
+        let Some(token_file) = &self.remote_signer_token_file else {
+            return Ok(None);
+        };
+        let token = std::fs::read_to_string(token_file).map_err(|e| {
+            anyhow!(
+                "Could not read the remote signer token file {}: {e}",
+                token_file.display()
+            )
+        })?;
+        Ok(Some(token.trim().to_string()))
+    }
+
+    /// Returns the password of the keystore: the given one, the content of
+    /// the password file, or else the one typed in the terminal.
+    pub fn keystore_password(&self) -> Result<String> {
+        if let Some(password) = &self.keystore_password {
+            return Ok(password.clone());
+        }
+        if let Some(password_file) = &self.keystore_password_file {
+            let password = std::fs::read_to_string(password_file).map_err(|e| {
+                anyhow!(
+                    "Could not read the keystore password file {}: {e}",
+                    password_file.display()
+                )
+            })?;
+            return Ok(password.trim_end_matches(['\r', '\n']).to_string());
+        }
+        rpassword::prompt_password("🔑 Keystore password: ")
+            .map_err(|e| anyhow!("Could not prompt for the keystore password: {e}"))
+    }
 }
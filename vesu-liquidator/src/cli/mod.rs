@@ -6,17 +6,93 @@ use url::Url;
 use anyhow::{Result, anyhow};
 use strum::Display;
 
-use account::AccountParams;
+use account::{AccountParams, parse_felt};
+use starknet::core::types::Felt;
 
-use crate::config::LiquidationMode;
+use crate::{
+    config::LiquidationMode,
+    types::history::{HistoryFilter, LiquidationStatus},
+    utils::{constants::OZ_ACCOUNT_CLASS_HASH, export::ExportFormat},
+};
 
 fn parse_url(s: &str) -> Result<Url> {
     s.parse()
         .map_err(|_| anyhow!("Could not convert {s} to Url"))
 }
 
-#[derive(Clone, Debug, clap::Parser)]
-pub struct RunCmd {
+/// Vesu Liquidator Bot.
+#[derive(Debug, clap::Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+
+    /// Format of the logs: text, or json to be ingested by a log aggregator.
+    #[clap(long, global = true, value_enum, default_value_t = LogFormat::Text, env = "LOG_FORMAT")]
+    pub log_format: LogFormat,
+
+    /// OTLP (gRPC) endpoint of the OpenTelemetry collector the spans are
+    /// exported to, e.g. http://localhost:4317. Not exported if unset.
+    #[clap(
+        long,
+        global = true,
+        value_name = "OTLP ENDPOINT",
+        env = "OTEL_EXPORTER_OTLP_ENDPOINT"
+    )]
+    pub otlp_endpoint: Option<String>,
+}
+
+/// Format of the logs.
+#[derive(Debug, Clone, Copy, clap::ValueEnum, PartialEq)]
+pub enum LogFormat {
+    Text,
+    /// One JSON object per line, with the fields of the events at the top level.
+    Json,
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum Command {
+    /// Starts all the services of the bot.
+    Run(RunCmd),
+    /// Prints the tracked positions & the pending payouts from the storage.
+    Status(StatusCmd),
+    /// Re-indexes the positions from a given block, then runs the bot.
+    Replay(ReplayCmd),
+    /// Indexes the positions of a historical block range into the storage,
+    /// without running the bot.
+    Backfill(BackfillCmd),
+    /// Replays a historical block range & reports the liquidations the bot
+    /// would have executed, with what they would have earned.
+    Backtest(BacktestCmd),
+    /// Forces the liquidation of a tracked position.
+    Liquidate(LiquidateCmd),
+    /// Re-runs the distribution of the earnings of a past liquidation.
+    Distribute(DistributeCmd),
+    /// Refreshes the stored positions once & lists the liquidable ones,
+    /// without liquidating them.
+    Scan(ScanCmd),
+    /// Prints the exposure to the stored positions per owner & per pool,
+    /// valued at the latest oracle prices.
+    Exposure(ExposureCmd),
+    /// Prints the cumulative PnL from the storage & exports its entries to CSV.
+    Pnl(PnlCmd),
+    /// Lists the liquidations attempted by the bot, most recent first.
+    History(HistoryCmd),
+    /// Exports the positions or the payouts of the storage to CSV or Parquet.
+    #[command(subcommand)]
+    Export(ExportCmd),
+    /// Saves the whole storage to a snapshot file, or restores it from one.
+    #[command(subcommand)]
+    Snapshot(SnapshotCmd),
+    /// Pauses or resumes the transactions of the running bot, or sweeps its
+    /// funds to the cold address, through its API.
+    #[command(subcommand)]
+    Operator(OperatorCmd),
+}
+
+/// Parameters shared by the commands interacting with the network.
+#[derive(Clone, Debug, clap::Args)]
+pub struct BotParams {
     #[allow(missing_docs)]
     #[clap(flatten)]
     pub account_params: AccountParams,
@@ -33,21 +109,413 @@ pub struct RunCmd {
     #[clap(long, default_value = "config.yaml", value_name = "VESU CONFIG PATH")]
     pub config_path: Option<PathBuf>,
 
-    /// Configuration file path.
+    /// Storage file path.
     #[clap(long, default_value = "data.json", value_name = "STORAGE PATH")]
     pub storage_path: Option<PathBuf>,
 
+    /// Audit log file path.
+    #[clap(long, default_value = "audit.log", value_name = "AUDIT LOG PATH")]
+    pub audit_log_path: PathBuf,
+
+    /// Liquidation mode, full or partial.
+    #[clap(long, value_enum, default_value_t = LiquidationMode::Full, value_name = "LIQUIDATION MODE")]
+    pub liquidation_mode: LiquidationMode,
+}
+
+impl BotParams {
+    pub fn validate(&self) -> Result<()> {
+        self.account_params.validate()
+    }
+}
+
+#[derive(Clone, Debug, clap::Args)]
+pub struct RunCmd {
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub bot_params: BotParams,
+
     /// The block you want to start syncing from.
-    #[clap(long, short, value_name = "BLOCK NUMBER")]
+    #[clap(long, short, alias = "from-block", value_name = "BLOCK NUMBER")]
     pub starting_block: u64,
 
     /// Apibara API Key for indexing.
     #[clap(long, value_name = "APIBARA API KEY")]
     pub apibara_api_key: Option<String>,
 
-    /// Configuration file path.
-    #[clap(long, value_enum, default_value_t = LiquidationMode::Full, value_name = "LIQUIDATION MODE")]
-    pub liquidation_mode: LiquidationMode,
+    /// Logs the time spent refreshing, evaluating & liquidating the positions
+    /// at each check of the monitoring.
+    #[clap(long)]
+    pub profile: bool,
+
+    /// Deploys the liquidator account at startup if it isn't deployed yet, as
+    /// an OpenZeppelin account owned by the key of the bot.
+    #[clap(long)]
+    pub deploy_account: bool,
+
+    /// Class hash of the account deployed by `--deploy-account`, already
+    /// declared on the network.
+    #[clap(long, value_parser = parse_felt, value_name = "CLASS HASH", default_value = OZ_ACCOUNT_CLASS_HASH)]
+    pub account_class_hash: Felt,
+
+    /// Salt of the address of the account deployed by `--deploy-account`, the
+    /// public key of the account by default.
+    #[clap(long, value_parser = parse_felt, value_name = "SALT")]
+    pub account_salt: Option<Felt>,
+
+    /// Run under the Windows Service Control Manager: to be set in the command
+    /// of the registered service only.
+    #[cfg(windows)]
+    #[clap(long)]
+    pub windows_service: bool,
+}
+
+#[derive(Clone, Debug, clap::Args)]
+pub struct StatusCmd {
+    /// Storage file path.
+    #[clap(long, default_value = "data.json", value_name = "STORAGE PATH")]
+    pub storage_path: PathBuf,
+}
+
+#[derive(Clone, Debug, clap::Args)]
+pub struct PnlCmd {
+    /// Storage file path.
+    #[clap(long, default_value = "data.json", value_name = "STORAGE PATH")]
+    pub storage_path: PathBuf,
+
+    /// Path of the CSV file the liquidations & distributions are exported to.
+    #[clap(long, value_name = "CSV PATH")]
+    pub csv: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, clap::Args)]
+pub struct HistoryCmd {
+    /// Storage file path.
+    #[clap(long, default_value = "data.json", value_name = "STORAGE PATH")]
+    pub storage_path: PathBuf,
+
+    /// Only lists the liquidations of the positions of this user.
+    #[clap(long, value_parser = parse_felt, value_name = "USER ADDRESS")]
+    pub user: Option<Felt>,
+
+    /// Only lists the liquidations in this pool.
+    #[clap(long, value_parser = parse_felt, value_name = "POOL ID")]
+    pub pool: Option<Felt>,
+
+    /// Only lists the liquidations with this collateral or debt, e.g. ETH.
+    #[clap(long, value_name = "TICKER")]
+    pub asset: Option<String>,
+
+    /// Only lists the liquidations that ended this way.
+    #[clap(long, value_enum)]
+    pub status: Option<LiquidationStatus>,
+
+    /// Only lists the liquidations started from this unix timestamp.
+    #[clap(long, value_name = "TIMESTAMP")]
+    pub since: Option<u64>,
+
+    /// Only lists the liquidations started up to this unix timestamp.
+    #[clap(long, value_name = "TIMESTAMP")]
+    pub until: Option<u64>,
+
+    /// Maximum number of liquidations listed.
+    #[clap(long, default_value_t = 50)]
+    pub limit: usize,
+
+    /// Prints the liquidations as JSON.
+    #[clap(long)]
+    pub json: bool,
+}
+
+impl HistoryCmd {
+    pub fn filter(&self) -> HistoryFilter {
+        HistoryFilter {
+            user: self.user,
+            pool_id: self.pool,
+            asset: self.asset.clone(),
+            status: self.status,
+            since: self.since,
+            until: self.until,
+            limit: Some(self.limit),
+        }
+    }
+}
+
+#[derive(Clone, Debug, clap::Subcommand)]
+pub enum ExportCmd {
+    /// Exports the tracked, closed & liquidated positions.
+    Positions(ExportParams),
+    /// Exports the payouts sent to the players.
+    Payouts(ExportParams),
+}
+
+/// Parameters of the export commands.
+#[derive(Clone, Debug, clap::Args)]
+pub struct ExportParams {
+    /// Storage file path.
+    #[clap(long, default_value = "data.json", value_name = "STORAGE PATH")]
+    pub storage_path: PathBuf,
+
+    /// Path of the exported file.
+    #[clap(long, short, value_name = "OUTPUT PATH")]
+    pub output: PathBuf,
+
+    /// Format of the exported file.
+    #[clap(long, value_enum, default_value_t = ExportFormat::Csv)]
+    pub format: ExportFormat,
+
+    /// Only exports the rows from this block. Rows without a block, e.g. the
+    /// positions still open, are left out by a block range.
+    #[clap(long = "from", value_name = "BLOCK NUMBER")]
+    pub from_block: Option<u64>,
+
+    /// Only exports the rows up to this block, included.
+    #[clap(long = "to", value_name = "BLOCK NUMBER")]
+    pub to_block: Option<u64>,
+
+    /// Only exports the rows of this address: the user of the positions, or
+    /// the recipient of the payouts.
+    #[clap(long, value_parser = parse_felt, value_name = "ADDRESS")]
+    pub address: Option<Felt>,
+}
+
+impl ExportParams {
+    pub fn validate(&self) -> Result<()> {
+        if let (Some(from_block), Some(to_block)) = (self.from_block, self.to_block) {
+            if from_block > to_block {
+                return Err(anyhow!(
+                    "--from ({from_block}) must not be after --to ({to_block})"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, clap::Subcommand)]
+pub enum SnapshotCmd {
+    /// Saves the whole storage to a versioned snapshot file.
+    Save(SnapshotParams),
+    /// Restores the whole storage from a snapshot file.
+    Restore(SnapshotParams),
+}
+
+#[derive(Clone, Debug, clap::Subcommand)]
+pub enum OperatorCmd {
+    /// Halts all the transactions of the bot.
+    Pause(OperatorActionParams),
+    /// Resumes the transactions of the bot.
+    Resume(OperatorActionParams),
+    /// Halts the transactions & sweeps the funds of the bot to the cold
+    /// address.
+    Sweep(OperatorActionParams),
+    /// Lists the actions of the operators, oldest first.
+    Actions(OperatorApiParams),
+}
+
+/// Parameters reaching the `/operator` routes of the API of the bot.
+#[derive(Clone, Debug, clap::Args)]
+pub struct OperatorApiParams {
+    /// Url of the API of the running bot.
+    #[clap(long, value_parser = parse_url, default_value = "http://127.0.0.1:3000", value_name = "API URL")]
+    pub api_url: Url,
+
+    /// Bearer token of the `/operator` routes, the `operator.api_token` of
+    /// the config.
+    #[clap(
+        long,
+        value_name = "OPERATOR API TOKEN",
+        env = "OPERATOR_API_TOKEN",
+        hide_env_values = true
+    )]
+    pub token: String,
+}
+
+/// Parameters of the runbook actions.
+#[derive(Clone, Debug, clap::Args)]
+pub struct OperatorActionParams {
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub api: OperatorApiParams,
+
+    /// Why the action is taken, recorded for audit.
+    #[clap(long, value_name = "REASON")]
+    pub reason: String,
+}
+
+/// Parameters of the snapshot commands.
+#[derive(Clone, Debug, clap::Args)]
+pub struct SnapshotParams {
+    /// Path of the snapshot file.
+    #[clap(value_name = "SNAPSHOT PATH")]
+    pub path: PathBuf,
+
+    /// Storage file path.
+    #[clap(long, default_value = "data.json", value_name = "STORAGE PATH")]
+    pub storage_path: PathBuf,
+
+    /// Overwrites an existing storage when restoring.
+    #[clap(long)]
+    pub force: bool,
+}
+
+#[derive(Clone, Debug, clap::Args)]
+pub struct ReplayCmd {
+    /// Same parameters as the run command, `--from-block` being an alias of `--starting-block`.
+    #[clap(flatten)]
+    pub run_cmd: RunCmd,
+}
+
+#[derive(Clone, Debug, clap::Args)]
+pub struct BackfillCmd {
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub bot_params: BotParams,
+
+    /// First block of the range to index.
+    #[clap(long = "from", value_name = "BLOCK NUMBER")]
+    pub from_block: u64,
+
+    /// Last block of the range to index, included.
+    #[clap(long = "to", value_name = "BLOCK NUMBER")]
+    pub to_block: u64,
+
+    /// Blocks scanned per chunk of `getEvents` requests.
+    #[clap(long, default_value_t = 10_000, value_name = "BLOCKS")]
+    pub blocks_per_chunk: u64,
+
+    /// Maximum number of RPC requests sent per second.
+    #[clap(long, default_value_t = 5, value_name = "REQUESTS")]
+    pub requests_per_second: u32,
+}
+
+impl BackfillCmd {
+    pub fn validate(&self) -> Result<()> {
+        if self.from_block > self.to_block {
+            return Err(anyhow!(
+                "--from ({}) must not be after --to ({})",
+                self.from_block,
+                self.to_block
+            ));
+        }
+        if self.blocks_per_chunk == 0 || self.requests_per_second == 0 {
+            return Err(anyhow!(
+                "--blocks-per-chunk & --requests-per-second must be positive"
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, clap::Args)]
+pub struct BacktestCmd {
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub bot_params: BotParams,
+
+    /// First block of the range to replay.
+    #[clap(long = "from", value_name = "BLOCK NUMBER")]
+    pub from_block: u64,
+
+    /// Last block of the range to replay, included.
+    #[clap(long = "to", value_name = "BLOCK NUMBER")]
+    pub to_block: u64,
+
+    /// The positions are checked every this many blocks.
+    #[clap(long, default_value_t = 100, value_name = "BLOCKS")]
+    pub step_blocks: u64,
+
+    /// Liquidation bonus in basis points of the debt repaid, used to estimate
+    /// the earnings of the liquidations.
+    #[clap(long, default_value_t = 500, value_name = "BPS")]
+    pub bonus_bps: u32,
+
+    /// Blocks scanned per chunk of `getEvents` requests.
+    #[clap(long, default_value_t = 10_000, value_name = "BLOCKS")]
+    pub blocks_per_chunk: u64,
+
+    /// Maximum number of RPC requests sent per second.
+    #[clap(long, default_value_t = 5, value_name = "REQUESTS")]
+    pub requests_per_second: u32,
+
+    /// Path of the JSON report.
+    #[clap(long, default_value = "backtest.json", value_name = "PATH")]
+    pub output: PathBuf,
+}
+
+impl BacktestCmd {
+    pub fn validate(&self) -> Result<()> {
+        if self.from_block > self.to_block {
+            return Err(anyhow!(
+                "--from ({}) must not be after --to ({})",
+                self.from_block,
+                self.to_block
+            ));
+        }
+        if self.step_blocks == 0 || self.blocks_per_chunk == 0 || self.requests_per_second == 0 {
+            return Err(anyhow!(
+                "--step-blocks, --blocks-per-chunk & --requests-per-second must be positive"
+            ));
+        }
+        if self.bonus_bps > 10_000 {
+            return Err(anyhow!("--bonus-bps can't be more than 10000"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, clap::Args)]
+pub struct LiquidateCmd {
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub bot_params: BotParams,
+
+    /// Pool id of the position to liquidate.
+    #[clap(long, value_parser = parse_felt, value_name = "POOL ID")]
+    pub pool: Felt,
+
+    /// Address of the user owning the position to liquidate.
+    #[clap(long, value_parser = parse_felt, value_name = "USER ADDRESS")]
+    pub user: Felt,
+}
+
+#[derive(Clone, Debug, clap::Args)]
+pub struct DistributeCmd {
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub bot_params: BotParams,
+
+    /// Hash of the liquidation transaction to distribute.
+    #[clap(long, value_parser = parse_felt, value_name = "TX HASH")]
+    pub tx_hash: Felt,
+
+    /// Distributes the earnings even if the storage records them as already
+    /// distributed.
+    #[clap(long)]
+    pub force: bool,
+}
+
+#[derive(Clone, Debug, clap::Args)]
+pub struct ScanCmd {
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub bot_params: BotParams,
+
+    /// Positions whose health factor is within this margin of a liquidation
+    /// are listed as near liquidation, `monitoring.watchlist_margin_bps` by
+    /// default.
+    #[clap(long, value_name = "BPS")]
+    pub margin_bps: Option<u32>,
+}
+
+#[derive(Clone, Debug, clap::Args)]
+pub struct ExposureCmd {
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub bot_params: BotParams,
+
+    /// Number of owners listed, the ones with the most debt.
+    #[clap(long, default_value_t = 10, value_name = "OWNERS")]
+    pub top: usize,
 }
 
 /// First blocks with Vesu activity. Not necessary to index before.
@@ -56,7 +524,7 @@ const FIRST_SEPOLIA_BLOCK: u64 = 77860;
 
 impl RunCmd {
     pub fn validate(&mut self) -> Result<()> {
-        self.account_params.validate()?;
+        self.bot_params.validate()?;
         if self.apibara_api_key.is_none() {
             self.apibara_api_key = env::var("APIBARA_API_KEY").ok();
         }
@@ -66,7 +534,7 @@ impl RunCmd {
             ));
         }
 
-        match self.network {
+        match self.bot_params.network {
             NetworkName::Mainnet => {
                 if self.starting_block <= FIRST_MAINNET_BLOCK {
                     self.starting_block = FIRST_MAINNET_BLOCK;
@@ -77,6 +545,8 @@ impl RunCmd {
                     self.starting_block = FIRST_SEPOLIA_BLOCK;
                 }
             }
+            // Devnets start from scratch, everything has to be indexed.
+            NetworkName::Katana => {}
         }
         Ok(())
     }
@@ -91,4 +561,14 @@ pub enum NetworkName {
     #[strum(serialize = "Sepolia")]
     #[value(alias("sepolia"))]
     Sepolia,
+    #[strum(serialize = "Katana")]
+    #[value(alias("katana"))]
+    Katana,
+}
+
+impl NetworkName {
+    /// Key of the network in the `vesu` section of the config file.
+    pub fn config_key(&self) -> String {
+        self.to_string().to_lowercase()
+    }
 }
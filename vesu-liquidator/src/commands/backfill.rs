@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use starknet::providers::{JsonRpcClient, jsonrpc::HttpTransport};
+
+use crate::{
+    cli::BackfillCmd,
+    config::Config,
+    protocols::Protocols,
+    services::indexer::{BackfillRange, backfill_positions, rate_limiter},
+    storages::Storage,
+    types::position::{Position, PositionsMap},
+};
+
+use super::open_storage;
+
+/// Indexes the positions of the `--from`..`--to` block range into the storage,
+/// without starting the monitoring. The last indexed block is only moved
+/// forward when the range follows it, so the bot doesn't skip any block.
+pub async fn backfill(backfill_cmd: BackfillCmd) -> Result<()> {
+    backfill_cmd.validate()?;
+    let bot_params = &backfill_cmd.bot_params;
+    let rpc_client = Arc::new(JsonRpcClient::new(HttpTransport::new(
+        bot_params.rpc_url.clone(),
+    )));
+    let config = Config::from_cli(bot_params)?;
+
+    let mut storage = open_storage(&bot_params.storage_path.clone().unwrap_or_default());
+    let (last_block_indexed, _) = storage.load().await?;
+
+    tracing::info!(
+        "⏮️ Backfilling blocks {} to {}",
+        backfill_cmd.from_block,
+        backfill_cmd.to_block
+    );
+    let found = backfill_positions(
+        &config,
+        &rpc_client,
+        BackfillRange {
+            from_block: backfill_cmd.from_block,
+            to_block: backfill_cmd.to_block,
+            blocks_per_chunk: backfill_cmd.blocks_per_chunk,
+            requests_per_second: backfill_cmd.requests_per_second,
+        },
+    )
+    .await?;
+
+    // Positions are refreshed at the latest block, the closed ones dropped.
+    let mut found: Vec<Position> = found.into_values().map(|(_, position)| position).collect();
+    let mut rate_limiter = rate_limiter(backfill_cmd.requests_per_second);
+    let protocols = Protocols::from_config(&config);
+    for batch in found.chunks_mut(config.position_update.batch_size) {
+        rate_limiter.tick().await;
+        Position::update_batch(
+            batch,
+            rpc_client.as_ref(),
+            &protocols,
+            config.position_update.batch_size,
+        )
+        .await?;
+    }
+
+    let positions = PositionsMap::from_storage(&storage);
+    let mut closed = 0;
+    for position in found {
+        if position.is_closed() {
+            positions.remove(&position.key());
+            closed += 1;
+        } else {
+            positions.insert(position);
+        }
+    }
+
+    let last_block_indexed = if backfill_cmd.from_block <= last_block_indexed + 1 {
+        last_block_indexed.max(backfill_cmd.to_block)
+    } else {
+        last_block_indexed
+    };
+    storage.save(&positions.by_key, last_block_indexed).await?;
+    println!(
+        "  ⏮️ Backfilled blocks {} to {}: {} position(s) tracked, {} closed, last block indexed {}",
+        backfill_cmd.from_block,
+        backfill_cmd.to_block,
+        positions.len(),
+        closed,
+        last_block_indexed
+    );
+    Ok(())
+}
@@ -0,0 +1,176 @@
+use std::{collections::HashSet, sync::Arc};
+
+use anyhow::Result;
+use starknet::{
+    core::types::BlockId,
+    providers::{JsonRpcClient, jsonrpc::HttpTransport},
+};
+use tokio::sync::mpsc::unbounded_channel;
+
+use crate::{
+    cli::BacktestCmd,
+    config::Config,
+    protocols::Protocols,
+    services::{
+        indexer::{BackfillRange, backfill_positions, rate_limiter},
+        notifier::Notifier,
+        oracle::{LatestOraclePrices, OracleService},
+    },
+    types::{
+        backtest::{BacktestReport, SimulatedLiquidation, UsdSplit, estimate_earnings},
+        position::Position,
+    },
+    utils::{http::HttpClient, rpc::BlockRpc},
+};
+
+/// Replays the `--from`..`--to` block range: the positions with an event in
+/// the range are checked every `--step-blocks` blocks, at the Pragma onchain
+/// prices of the block, & the liquidable ones are liquidated in full once.
+/// Prints the totals & writes the liquidations to a JSON report.
+/// The positions & prices are read at past blocks, which needs an archive node.
+pub async fn backtest(backtest_cmd: BacktestCmd) -> Result<()> {
+    backtest_cmd.validate()?;
+    let bot_params = &backtest_cmd.bot_params;
+    let rpc_client = Arc::new(JsonRpcClient::new(HttpTransport::new(
+        bot_params.rpc_url.clone(),
+    )));
+    let config = Config::from_cli(bot_params)?;
+
+    tracing::info!(
+        "⏪ Backtesting blocks {} to {}",
+        backtest_cmd.from_block,
+        backtest_cmd.to_block
+    );
+    let found = backfill_positions(
+        &config,
+        &rpc_client,
+        BackfillRange {
+            from_block: backtest_cmd.from_block,
+            to_block: backtest_cmd.to_block,
+            blocks_per_chunk: backtest_cmd.blocks_per_chunk,
+            requests_per_second: backtest_cmd.requests_per_second,
+        },
+    )
+    .await?;
+    let positions: Vec<Position> = found.into_values().map(|(_, position)| position).collect();
+
+    // Notifications are not sent outside of the bot.
+    let (notifications_sender, _) = unbounded_channel();
+    let oracle = OracleService::new(
+        config.clone(),
+        rpc_client.clone(),
+        HttpClient::new(&config.rate_limits),
+        LatestOraclePrices::from_config(&config),
+        Notifier::new(notifications_sender),
+    );
+    let protocols = Protocols::from_config(&config);
+    let batch_size = config.position_update.batch_size;
+    let mut rate_limiter = rate_limiter(backtest_cmd.requests_per_second);
+    let mut report = BacktestReport::new(
+        backtest_cmd.from_block,
+        backtest_cmd.to_block,
+        backtest_cmd.step_blocks,
+        positions.len(),
+    );
+    let mut liquidated = HashSet::new();
+
+    let mut block_number = backtest_cmd.from_block;
+    loop {
+        report.steps += 1;
+        let prices = LatestOraclePrices::from_config(&config);
+        for asset in prices.assets() {
+            rate_limiter.tick().await;
+            match oracle.get_historical_price(&asset, block_number).await {
+                Ok(price) => prices.update(&asset, price),
+                Err(e) => tracing::warn!(
+                    error = %e,
+                    "⏪ No price for {} at block {}",
+                    asset.to_uppercase(),
+                    block_number
+                ),
+            }
+        }
+
+        let mut open: Vec<Position> = positions
+            .iter()
+            .filter(|position| !liquidated.contains(&position.key()))
+            .cloned()
+            .collect();
+        let rpc = BlockRpc::new(rpc_client.clone(), BlockId::Number(block_number));
+        for batch in open.chunks_mut(batch_size) {
+            rate_limiter.tick().await;
+            Position::update_batch(batch, &rpc, &protocols, batch_size).await?;
+        }
+
+        for position in open.iter().filter(|position| !position.is_closed()) {
+            let health_factor = match position.health_factor(&prices).await {
+                Ok(Some(health_factor)) => health_factor,
+                Ok(None) => continue,
+                Err(_) => {
+                    report.unpriced_checks += 1;
+                    continue;
+                }
+            };
+            if !position.is_liquidable(&prices).await? {
+                continue;
+            }
+            let collateral_usd = &position.collateral.amount
+                * prices.fresh_price(&position.collateral.name.to_lowercase())?;
+            let debt_usd =
+                &position.debt.amount * prices.fresh_price(&position.debt.name.to_lowercase())?;
+            let earnings_usd =
+                estimate_earnings(&collateral_usd, &debt_usd, backtest_cmd.bonus_bps);
+            tracing::info!(
+                "⏪ Position #{} liquidable at block {}, ${} of debt, ${} earned",
+                position.key(),
+                block_number,
+                debt_usd.round(2),
+                earnings_usd.round(2)
+            );
+            liquidated.insert(position.key());
+            report.record(SimulatedLiquidation {
+                block_number,
+                key: position.key(),
+                user: position.user_address,
+                collateral: position.collateral.name.clone(),
+                debt: position.debt.name.clone(),
+                health_factor,
+                collateral_usd,
+                debt_usd,
+                split: UsdSplit::new(&config.distribution, &earnings_usd),
+                earnings_usd,
+            });
+        }
+
+        if block_number >= backtest_cmd.to_block {
+            break;
+        }
+        block_number = (block_number + backtest_cmd.step_blocks).min(backtest_cmd.to_block);
+    }
+
+    std::fs::write(&backtest_cmd.output, serde_json::to_string_pretty(&report)?)?;
+    println!(
+        "  ⏪ Backtested blocks {} to {} in {} step(s): {} liquidation(s) of {} position(s)",
+        report.from_block,
+        report.to_block,
+        report.steps,
+        report.liquidations.len(),
+        report.positions
+    );
+    println!(
+        "     ${} of debt repaid, ${} earned: ${} to the players, ${} to the world, ${} to the operator",
+        report.debt_repaid_usd.round(2),
+        report.earnings_usd.round(2),
+        report.split.players_usd.round(2),
+        report.split.world_usd.round(2),
+        report.split.operator_usd.round(2)
+    );
+    if report.unpriced_checks > 0 {
+        println!(
+            "  ⚠️  {} check(s) skipped for lack of a price",
+            report.unpriced_checks
+        );
+    }
+    println!("  📄 Report written to {}", backtest_cmd.output.display());
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow, bail};
+use futures_util::lock::Mutex;
+use starknet::providers::Provider;
+use tokio::sync::mpsc::unbounded_channel;
+
+use crate::{
+    cli::DistributeCmd,
+    protocols::Protocols,
+    services::{
+        distribution::DistributionService, notifier::Notifier, oracle::LatestOraclePrices,
+        tokens::TokenRegistry,
+    },
+    storages::{SharedStorage, Storage},
+    types::{
+        distribution::{LiquidationEarnings, PendingDistribution},
+        feed::EventFeed,
+        game::GameMirror,
+    },
+    utils::{http::HttpClient, slo::SloTracker, unix_now},
+};
+
+use super::{open_storage, setup};
+
+/// Re-runs the distribution of the earnings of the liquidation `--tx-hash`.
+pub async fn distribute(distribute_cmd: DistributeCmd) -> Result<()> {
+    distribute_cmd.bot_params.validate()?;
+    let (rpc_client, account, config) = setup(&distribute_cmd.bot_params)?;
+    let tx_hash = distribute_cmd.tx_hash;
+
+    let receipt = rpc_client.get_transaction_receipt(tx_hash).await?;
+    let liquidation = Protocols::from_config(&config)
+        .iter()
+        .find_map(|protocol| protocol.find_liquidation(receipt.receipt.events()))
+        .ok_or_else(|| anyhow!("Could not find a liquidation event in tx {:#x}", tx_hash))?;
+    let (token, amount) = (liquidation.collateral, liquidation.earnings);
+
+    let mut pending = PendingDistribution::default();
+    pending.push(
+        LiquidationEarnings {
+            liquidation_tx: tx_hash,
+            token,
+            amount,
+            liquidated_at: unix_now(),
+            span: None,
+        },
+        unix_now(),
+    );
+
+    let mut storage = open_storage(
+        &distribute_cmd
+            .bot_params
+            .storage_path
+            .clone()
+            .unwrap_or_default(),
+    );
+    storage.load().await?;
+    let already_distributed = storage
+        .get_pnl_ledger()
+        .distributions
+        .iter()
+        .any(|distribution| distribution.liquidation_txs.contains(&tx_hash));
+    if already_distributed && !distribute_cmd.force {
+        bail!(
+            "The earnings of tx {:#x} were already distributed, use --force to distribute them again",
+            tx_hash
+        );
+    }
+    let game_mirror = GameMirror::new(storage.get_game_state());
+    let token_metadata = storage.get_token_metadata();
+    let storage: SharedStorage = Arc::new(Mutex::new(Box::new(storage) as Box<dyn Storage>));
+    let tokens = TokenRegistry::new(config.clone(), rpc_client.clone(), token_metadata)
+        .with_storage(storage.clone());
+
+    let (_, earnings_receiver) = unbounded_channel();
+    // Notifications are not sent outside of the bot.
+    let (notifications_sender, _) = unbounded_channel();
+    let notifier = Notifier::new(notifications_sender);
+    let slo = SloTracker::new(config.slo.clone(), notifier.clone());
+    // The oracle is not running: the PnL of the distribution is left unpriced.
+    let latest_oracle_prices = LatestOraclePrices::from_config(&config);
+    let http_client = HttpClient::new(&config.rate_limits);
+    let distribution_service = DistributionService::new(
+        config,
+        rpc_client,
+        account,
+        earnings_receiver,
+        PendingDistribution::default(),
+        storage.clone(),
+        http_client,
+        notifier,
+        slo,
+        game_mirror,
+        // Events are not pushed outside of the bot.
+        EventFeed::new(),
+        latest_oracle_prices,
+        tokens,
+    );
+
+    if !distribution_service.distribute(&pending).await? {
+        bail!("Nothing was distributed for tx {:#x}", tx_hash);
+    }
+
+    // Earnings still waiting in the batching window of the bot, e.g. after a
+    // failed distribution, are not paid a second time.
+    let mut storage = storage.lock().await;
+    let mut stored_pending = storage.get_pending_distribution();
+    if stored_pending.remove(tx_hash) {
+        storage.save_pending_distribution(&stored_pending).await?;
+        tracing::info!(
+            "[💸 Distribution] Removed the earnings of tx {:#x} from the pending distribution",
+            tx_hash
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use anyhow::Result;
+
+use crate::{
+    cli::{ExportCmd, ExportParams},
+    storages::{Storage, json::JsonStorage},
+    utils::{
+        conversions::u256_to_big_uint,
+        export::{Column, ExportFilter, Table, Value},
+    },
+};
+
+use super::open_storage;
+
+const POSITION_COLUMNS: &[Column] = &[
+    Column::text("status"),
+    Column::text("pool_id"),
+    Column::text("user"),
+    Column::text("collateral_token"),
+    Column::text("collateral_ticker"),
+    Column::text("collateral_amount"),
+    Column::text("debt_token"),
+    Column::text("debt_ticker"),
+    Column::text("debt_amount"),
+    Column::integer("block_number"),
+    Column::text("tx_hash"),
+];
+
+const PAYOUT_COLUMNS: &[Column] = &[
+    Column::text("tx_hash"),
+    Column::integer("block_number"),
+    Column::text("token"),
+    Column::text("recipient"),
+    Column::text("amount"),
+    Column::text("checked"),
+];
+
+/// Exports the positions or the payouts of the storage to `--output`.
+pub async fn export(export_cmd: ExportCmd) -> Result<()> {
+    let (params, build_table): (_, fn(&JsonStorage, &ExportFilter) -> Table) = match export_cmd {
+        ExportCmd::Positions(params) => (params, positions_table),
+        ExportCmd::Payouts(params) => (params, payouts_table),
+    };
+    params.validate()?;
+    let mut storage = open_storage(&params.storage_path);
+    storage.load().await?;
+
+    let table = build_table(&storage, &filter(&params));
+    table.write(&params.output, params.format)?;
+    println!(
+        "  🧾 Exported {} row(s) to {}",
+        table.len(),
+        params.output.display()
+    );
+    Ok(())
+}
+
+fn filter(params: &ExportParams) -> ExportFilter {
+    ExportFilter {
+        from_block: params.from_block,
+        to_block: params.to_block,
+        address: params.address,
+    }
+}
+
+/// Returns the tracked positions, `open` or `closed` at a block, followed by
+/// the positions liquidated by the bot, with the amounts seized & repaid.
+fn positions_table(storage: &JsonStorage, filter: &ExportFilter) -> Table {
+    let mut table = Table::new(POSITION_COLUMNS);
+    let closed_since = storage.get_pruning_state().closed_since;
+    let mut positions: Vec<_> = storage.get_positions().into_iter().collect();
+    positions.sort_by_key(|(key, _)| *key);
+    for (key, position) in positions {
+        let closed_at = closed_since.get(&key).copied();
+        if !filter.accepts(closed_at, position.user_address) {
+            continue;
+        }
+        let status = if closed_at.is_some() {
+            "closed"
+        } else {
+            "open"
+        };
+        table.push(vec![
+            Value::from(status.to_string()),
+            Value::from(position.pool_id),
+            Value::from(position.user_address),
+            Value::from(position.collateral.address),
+            Value::from(position.collateral.name),
+            Value::from(position.collateral.amount.to_plain_string()),
+            Value::from(position.debt.address),
+            Value::from(position.debt.name),
+            Value::from(position.debt.amount.to_plain_string()),
+            Value::from(closed_at),
+            Value::Null,
+        ]);
+    }
+    for liquidation in storage.get_pnl_ledger().liquidations {
+        if !filter.accepts(Some(liquidation.block_number), liquidation.user) {
+            continue;
+        }
+        let (collateral, debt) = (liquidation.collateral_seized, liquidation.debt_repaid);
+        table.push(vec![
+            Value::from("liquidated".to_string()),
+            Value::from(liquidation.pool_id),
+            Value::from(liquidation.user),
+            Value::from(collateral.token),
+            Value::from(collateral.ticker),
+            Value::from(collateral.amount.to_plain_string()),
+            Value::from(debt.token),
+            Value::from(debt.ticker),
+            Value::from(debt.amount.to_plain_string()),
+            Value::from(liquidation.block_number),
+            Value::from(liquidation.tx_hash),
+        ]);
+    }
+    table
+}
+
+/// Returns the payouts recorded in the payout ledger, amounts being in the
+/// smallest unit of their token.
+fn payouts_table(storage: &JsonStorage, filter: &ExportFilter) -> Table {
+    let mut table = Table::new(PAYOUT_COLUMNS);
+    for record in storage.get_payout_ledger().records {
+        if !filter.accepts(Some(record.block_number), record.recipient) {
+            continue;
+        }
+        table.push(vec![
+            Value::from(record.tx_hash),
+            Value::from(record.block_number),
+            Value::from(record.token),
+            Value::from(record.recipient),
+            Value::from(u256_to_big_uint(&record.amount).to_string()),
+            Value::from(record.checked.to_string()),
+        ]);
+    }
+    table
+}
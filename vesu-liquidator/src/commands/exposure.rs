@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use starknet::providers::{JsonRpcClient, jsonrpc::HttpTransport};
+use tokio::sync::mpsc::unbounded_channel;
+
+use crate::{
+    cli::ExposureCmd,
+    config::Config,
+    services::{
+        notifier::Notifier,
+        oracle::{LatestOraclePrices, OracleService},
+    },
+    storages::Storage,
+    types::{
+        exposure::{Exposure, ExposureReport},
+        position::Position,
+    },
+    utils::http::HttpClient,
+};
+
+use super::open_storage;
+
+/// Prints the exposure to the open positions of the storage per owner & per
+/// pool, valued at the latest oracle prices, without refreshing them.
+pub async fn exposure(exposure_cmd: ExposureCmd) -> Result<()> {
+    let bot_params = &exposure_cmd.bot_params;
+    let rpc_client = Arc::new(JsonRpcClient::new(HttpTransport::new(
+        bot_params.rpc_url.clone(),
+    )));
+    let config = Config::from_cli(bot_params)?;
+
+    let mut storage = open_storage(&bot_params.storage_path.clone().unwrap_or_default());
+    let (_, positions) = storage.load().await?;
+    let positions: Vec<Position> = positions
+        .into_values()
+        .filter(|position| config.position_filter.accepts(&position.key()))
+        .collect();
+
+    let latest_oracle_prices = LatestOraclePrices::from_config(&config);
+    // Notifications are not sent outside of the bot.
+    let (notifications_sender, _) = unbounded_channel();
+    OracleService::new(
+        config.clone(),
+        rpc_client,
+        HttpClient::new(&config.rate_limits),
+        latest_oracle_prices.clone(),
+        Notifier::new(notifications_sender),
+    )
+    .update_prices()
+    .await?;
+
+    let report = ExposureReport::from_prices(&positions, &latest_oracle_prices, exposure_cmd.top);
+    println!(
+        "  📊 {} open position(s) of {} owner(s): {}",
+        report.total.positions,
+        report.owner_count,
+        describe(&report.total)
+    );
+    if report.total.unpriced > 0 {
+        println!(
+            "  ⚠️  {} position(s) without a fresh price are left out of the USD values",
+            report.total.unpriced
+        );
+    }
+
+    println!("  👤 Top {} owner(s) by debt", report.owners.len());
+    for owner in report.owners.iter() {
+        println!("    - {:#066x}: {}", owner.owner, describe(&owner.exposure));
+    }
+    println!("  🏊 {} pool(s)", report.pools.len());
+    for pool in report.pools.iter() {
+        let name = pool
+            .pool_name
+            .as_ref()
+            .map(|name| format!(" ({name})"))
+            .unwrap_or_default();
+        println!(
+            "    - {:#x}{name}: {}",
+            pool.pool_id,
+            describe(&pool.exposure)
+        );
+    }
+    Ok(())
+}
+
+/// e.g. `$1200.00 of debt (35.2%) against $2000.00 of collateral, 1200 USDC`.
+fn describe(exposure: &Exposure) -> String {
+    let debt = exposure
+        .debt
+        .iter()
+        .map(|debt| format!("{} {}", debt.amount.round(4).to_plain_string(), debt.ticker))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "${} of debt ({:.1}%) against ${} of collateral, {debt}",
+        exposure.debt_usd.round(2).to_plain_string(),
+        exposure.debt_share * 100.0,
+        exposure.collateral_usd.round(2).to_plain_string(),
+    )
+}
@@ -0,0 +1,53 @@
+use anyhow::Result;
+
+use crate::{cli::HistoryCmd, storages::Storage};
+
+use super::open_storage;
+
+/// Prints the liquidations of the history of the storage matching the filters
+/// of the command, most recent first.
+pub async fn history(history_cmd: HistoryCmd) -> Result<()> {
+    let mut storage = open_storage(&history_cmd.storage_path);
+    storage.load().await?;
+    let history = storage.get_liquidation_history();
+    let records = history_cmd.filter().apply(&history);
+
+    if history_cmd.json {
+        println!("{}", serde_json::to_string_pretty(&records)?);
+        return Ok(());
+    }
+    println!(
+        "  📜 {} liquidation(s) listed, {} recorded",
+        records.len(),
+        history.len()
+    );
+    if records.is_empty() {
+        return Ok(());
+    }
+    println!(
+        "  {:<12} {:<10} {:<12} {:<10} {:>10} {:<68} {:<20}  {}",
+        "Started at", "Status", "Position", "Assets", "Duration", "Tx", "Earnings", "Error"
+    );
+    for record in records.iter() {
+        println!(
+            "  {:<12} {:<10} {:<12} {:<10} {:>10} {:<68} {:<20}  {}",
+            record.started_at,
+            record.status.to_string(),
+            record.key.to_string(),
+            format!("{}/{}", record.collateral, record.debt),
+            format!("{}ms", record.duration_ms),
+            record
+                .tx_hash
+                .map(|tx_hash| format!("{tx_hash:#066x}"))
+                .unwrap_or_else(|| "-".to_string()),
+            record
+                .earnings
+                .as_ref()
+                .map(|earnings| earnings.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            record.error.as_deref().unwrap_or_default()
+        );
+    }
+
+    Ok(())
+}
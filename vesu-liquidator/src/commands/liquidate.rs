@@ -0,0 +1,118 @@
+use anyhow::{Result, bail};
+use tokio::sync::mpsc::{channel, unbounded_channel};
+
+use crate::{
+    cli::LiquidateCmd,
+    protocols::Protocols,
+    services::{
+        monitoring::MonitoringService,
+        notifier::Notifier,
+        oracle::{LatestOraclePrices, OracleService},
+    },
+    storages::Storage,
+    types::{account::AccountPool, balance::BotBalances, feed::EventFeed, position::Position},
+    utils::{http::HttpClient, slo::SloTracker, unix_now},
+};
+
+use super::{open_storage, setup};
+
+/// Forces the liquidation of the tracked positions of `--user` in `--pool`.
+/// The earnings are kept pending & distributed by the next run of the bot,
+/// even if some of the liquidations failed.
+pub async fn liquidate(liquidate_cmd: LiquidateCmd) -> Result<()> {
+    liquidate_cmd.bot_params.validate()?;
+    let (rpc_client, account, config) = setup(&liquidate_cmd.bot_params)?;
+
+    let mut storage = open_storage(
+        &liquidate_cmd
+            .bot_params
+            .storage_path
+            .clone()
+            .unwrap_or_default(),
+    );
+    storage.load().await?;
+    let positions: Vec<Position> = storage
+        .get_positions()
+        .into_values()
+        .filter(|p| p.pool_id == liquidate_cmd.pool && p.user_address == liquidate_cmd.user)
+        .collect();
+    if positions.is_empty() {
+        bail!(
+            "No tracked position for user {:#x} in pool {:#x}",
+            liquidate_cmd.user,
+            liquidate_cmd.pool
+        );
+    }
+
+    let (_, positions_receiver) = channel(1);
+    let (earnings_sender, mut earnings_receiver) = unbounded_channel();
+    // Notifications are not sent outside of the bot.
+    let (notifications_sender, _) = unbounded_channel();
+    let notifier = Notifier::new(notifications_sender);
+    // Needed to value the debt & check the price impact of the liquidations.
+    let latest_oracle_prices = LatestOraclePrices::from_config(&config);
+    OracleService::new(
+        config.clone(),
+        rpc_client.clone(),
+        HttpClient::new(&config.rate_limits),
+        latest_oracle_prices.clone(),
+        notifier.clone(),
+    )
+    .update_prices()
+    .await?;
+    let monitoring_service = MonitoringService::new(
+        config.clone(),
+        rpc_client.clone(),
+        HttpClient::new(&config.rate_limits),
+        AccountPool::single(account),
+        positions_receiver,
+        latest_oracle_prices,
+        Box::new(storage),
+        earnings_sender,
+        notifier.clone(),
+        SloTracker::new(config.slo.clone(), notifier),
+        // Events are not pushed outside of the bot.
+        EventFeed::new(),
+        // The balances are not checked outside of the bot.
+        BotBalances::default(),
+    );
+
+    // A failure doesn't stop the liquidations of the other positions, nor
+    // lose the earnings of the ones already liquidated.
+    let protocols = Protocols::from_config(&config);
+    let total = positions.len();
+    let mut failed = 0;
+    for mut position in positions {
+        let key = position.key();
+        if let Err(e) = position
+            .update(rpc_client.as_ref(), &protocols, &config.position_update)
+            .await
+        {
+            tracing::error!(error = %e, "Could not update position #{key}, skipping it");
+            failed += 1;
+            continue;
+        }
+        if position.is_closed() {
+            tracing::warn!("Position #{key} is closed, skipping it");
+            continue;
+        }
+        tracing::info!("🔫 Liquidating {position}...");
+        if let Err(e) = monitoring_service.liquidate_position(&position).await {
+            tracing::error!(error = %e, "Could not liquidate position #{key}");
+            failed += 1;
+        }
+    }
+
+    let storage = monitoring_service.storage();
+    let mut storage = storage.lock().await;
+    let mut pending = storage.get_pending_distribution();
+    while let Ok(earnings) = earnings_receiver.try_recv() {
+        pending.push(earnings, unix_now());
+    }
+    storage.save_pending_distribution(&pending).await?;
+
+    if failed > 0 {
+        bail!("{failed} of the {total} position(s) could not be liquidated");
+    }
+    Ok(())
+}
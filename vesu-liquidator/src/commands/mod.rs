@@ -0,0 +1,41 @@
+pub mod backfill;
+pub mod backtest;
+pub mod distribute;
+pub mod export;
+pub mod exposure;
+pub mod history;
+pub mod liquidate;
+pub mod operator;
+pub mod pnl;
+pub mod replay;
+pub mod run;
+pub mod scan;
+pub mod snapshot;
+pub mod status;
+
+use std::{path::Path, sync::Arc};
+
+use anyhow::Result;
+use starknet::providers::{JsonRpcClient, jsonrpc::HttpTransport};
+
+use crate::{
+    cli::BotParams, config::Config, storages::json::JsonStorage, types::account::StarknetAccount,
+};
+
+/// Builds the rpc client, the account & the config used by the commands
+/// interacting with the network.
+fn setup(
+    bot_params: &BotParams,
+) -> Result<(Arc<JsonRpcClient<HttpTransport>>, StarknetAccount, Config)> {
+    let rpc_client = Arc::new(JsonRpcClient::new(HttpTransport::new(
+        bot_params.rpc_url.clone(),
+    )));
+    let account = StarknetAccount::from_cli(rpc_client.clone(), bot_params)?;
+    let config = Config::from_cli(bot_params)?;
+    Ok((rpc_client, account, config))
+}
+
+/// Opens the json storage located at the given path.
+fn open_storage(storage_path: &Path) -> JsonStorage {
+    JsonStorage::new(storage_path.to_str().unwrap_or_default())
+}
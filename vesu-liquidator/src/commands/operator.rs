@@ -0,0 +1,62 @@
+use anyhow::{Result, bail};
+use reqwest::{Client, RequestBuilder};
+use serde_json::json;
+
+use crate::{
+    cli::{OperatorActionParams, OperatorApiParams, OperatorCmd},
+    types::operator::OperatorAction,
+};
+
+/// Runs a runbook action on the running bot through its API, see
+/// [`crate::services::operator::Operator`].
+pub async fn operator(operator_cmd: OperatorCmd) -> Result<()> {
+    match operator_cmd {
+        OperatorCmd::Pause(params) => act(params, "pause").await,
+        OperatorCmd::Resume(params) => act(params, "resume").await,
+        OperatorCmd::Sweep(params) => act(params, "sweep").await,
+        OperatorCmd::Actions(params) => actions(params).await,
+    }
+}
+
+async fn act(params: OperatorActionParams, action: &str) -> Result<()> {
+    let request = request(&params.api, &format!("operator/{action}"), |client, url| {
+        client.post(url).json(&json!({ "reason": params.reason }))
+    })?;
+    let action: OperatorAction = send(request).await?;
+    println!("  🧑‍✈️ Done: {action}");
+    Ok(())
+}
+
+async fn actions(params: OperatorApiParams) -> Result<()> {
+    let request = request(&params, "operator/actions", |client, url| client.get(url))?;
+    let actions: Vec<OperatorAction> = send(request).await?;
+    println!("  🧑‍✈️ {} operator action(s)", actions.len());
+    for action in actions.iter() {
+        println!("    - at {}: {action}", action.at);
+    }
+    Ok(())
+}
+
+/// Builds the request to the route of the API, authenticated with the token.
+fn request(
+    params: &OperatorApiParams,
+    route: &str,
+    build: impl FnOnce(&Client, reqwest::Url) -> RequestBuilder,
+) -> Result<RequestBuilder> {
+    let url = params.api_url.join(route)?;
+    Ok(build(&Client::new(), url).bearer_auth(&params.token))
+}
+
+/// Sends the request & decodes the response, failing with the error returned
+/// by the API.
+async fn send<T: serde::de::DeserializeOwned>(request: RequestBuilder) -> Result<T> {
+    let response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        bail!(
+            "The API refused the request ({status}): {}",
+            response.text().await?
+        );
+    }
+    Ok(response.json().await?)
+}
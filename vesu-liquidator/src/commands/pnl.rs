@@ -0,0 +1,57 @@
+use anyhow::Result;
+
+use crate::{cli::PnlCmd, storages::Storage};
+
+use super::open_storage;
+
+/// Prints the cumulative PnL recorded in the storage, & exports its entries to
+/// `--csv` if set.
+pub async fn pnl(pnl_cmd: PnlCmd) -> Result<()> {
+    let mut storage = open_storage(&pnl_cmd.storage_path);
+    storage.load().await?;
+    let ledger = storage.get_pnl_ledger();
+
+    let summary = ledger.summary();
+    println!(
+        "  📈 {} liquidation(s) & {} distribution(s) recorded",
+        summary.liquidations, summary.distributions
+    );
+    println!(
+        "    - earnings: ${}",
+        summary.earnings_usd.to_plain_string()
+    );
+    println!("    - gas: ${}", summary.gas_usd.to_plain_string());
+    println!(
+        "    - swap costs: ${}",
+        summary.swap_costs_usd.to_plain_string()
+    );
+    println!("    - payouts: ${}", summary.payouts_usd.to_plain_string());
+    println!("  💰 Net PnL: ${}", summary.net_usd.to_plain_string());
+    if summary.unpriced_amounts > 0 {
+        println!(
+            "  ⚠️  {} amount(s) left out, with no price at execution time",
+            summary.unpriced_amounts
+        );
+    }
+
+    for (kind, costs) in [
+        ("liquidation", &summary.liquidation_costs),
+        ("distribution", &summary.distribution_costs),
+    ] {
+        if costs.transactions > 0 {
+            println!(
+                "  ⛽ Gas per {kind}: mean ${}, median ${}, p90 ${}, max ${}",
+                costs.mean_usd.round(4).to_plain_string(),
+                costs.median_usd.round(4).to_plain_string(),
+                costs.p90_usd.round(4).to_plain_string(),
+                costs.max_usd.round(4).to_plain_string()
+            );
+        }
+    }
+
+    if let Some(csv_path) = pnl_cmd.csv {
+        std::fs::write(&csv_path, ledger.to_csv())?;
+        println!("  🧾 Exported to {}", csv_path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,21 @@
+use anyhow::Result;
+use dashmap::DashMap;
+
+use crate::{cli::ReplayCmd, storages::Storage};
+
+use super::{open_storage, run::run};
+
+/// Re-indexes the positions from `--from-block`, then runs the bot.
+/// The tracked positions are kept, only the last indexed block is rewound.
+pub async fn replay(replay_cmd: ReplayCmd) -> Result<()> {
+    let mut run_cmd = replay_cmd.run_cmd;
+    run_cmd.validate()?;
+
+    let mut storage = open_storage(&run_cmd.bot_params.storage_path.clone().unwrap_or_default());
+    storage.load().await?;
+    let positions: DashMap<_, _> = storage.get_positions().into_iter().collect();
+    storage.save(&positions, run_cmd.starting_block).await?;
+    tracing::info!("⏪ Replaying from block {}", run_cmd.starting_block);
+
+    run(run_cmd).await
+}
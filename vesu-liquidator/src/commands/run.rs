@@ -0,0 +1,124 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use bigdecimal::{BigDecimal, FromPrimitive};
+use starknet::{
+    core::types::Felt,
+    providers::{JsonRpcClient, jsonrpc::HttpTransport},
+};
+
+use crate::{
+    cli::{NetworkName, RunCmd},
+    config::{Config, FeeToken},
+    services::start_all_services,
+    types::account::StarknetAccount,
+    utils::{
+        constants::{INTERVAL_CHECK_TX_FINALITY, MAX_RETRIES_VERIFY_TX_FINALITY},
+        get_tx_receipt,
+    },
+};
+
+use super::setup;
+
+/// Starts all the services of the bot.
+pub async fn run(mut run_cmd: RunCmd) -> Result<()> {
+    run_cmd.validate()?;
+
+    print_app_title(
+        run_cmd.bot_params.account_params.account_address,
+        run_cmd.bot_params.network,
+    );
+
+    let (rpc_client, account, config) = setup(&run_cmd.bot_params)?;
+    check_account(&run_cmd, &config, &rpc_client, &account).await?;
+    let extra_accounts = StarknetAccount::extras_from_cli(rpc_client.clone(), &run_cmd.bot_params)?;
+    start_all_services(config, rpc_client, account, extra_accounts, run_cmd).await
+}
+
+/// Checks that the liquidator account is deployed & holds enough STRK to pay
+/// the fees of the liquidations, so that the bot fails at startup with the
+/// address to fund instead of on its first transaction. With
+/// `--deploy-account`, an account not deployed yet is deployed once funded.
+async fn check_account(
+    run_cmd: &RunCmd,
+    config: &Config,
+    rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
+    account: &StarknetAccount,
+) -> Result<()> {
+    account.check_session().await?;
+    let address = account.account_address();
+    let deployed = account.is_deployed().await?;
+    anyhow::ensure!(
+        deployed || account.session().is_none(),
+        "Account {address:#x} is not deployed: it can't be deployed with a session key"
+    );
+    if !deployed && !run_cmd.deploy_account {
+        anyhow::bail!(
+            "Account {address:#x} is not deployed: deploy it, or run with --deploy-account to deploy it as an OpenZeppelin account"
+        );
+    }
+    let Some(strk) = config.get_asset_address_for_ticker("strk") else {
+        anyhow::ensure!(
+            deployed,
+            "STRK is not configured, the deployment fee can't be checked"
+        );
+        return Ok(());
+    };
+    // With the paymaster or the fees paid in ETH, the liquidations pay their
+    // fees in another token.
+    let min_fee_balance =
+        if config.paymaster.enabled || config.transactions.fee_token == FeeToken::Eth {
+            BigDecimal::from(0)
+        } else {
+            BigDecimal::from_f64(config.transactions.min_account_fee_balance).unwrap_or_default()
+        };
+    let balance = account.fee_balance(strk).await?;
+    if deployed {
+        anyhow::ensure!(
+            balance >= min_fee_balance,
+            "Account {address:#x} holds {balance} STRK: fund it with at least {} STRK to pay the fees of the liquidations",
+            min_fee_balance - &balance
+        );
+        return Ok(());
+    }
+
+    let class_hash = run_cmd.account_class_hash;
+    let salt = match run_cmd.account_salt {
+        Some(salt) => salt,
+        None => account.public_key().await?,
+    };
+    let deployment_fee = account.estimate_deployment_fee(class_hash, salt).await?;
+    let required = deployment_fee.clone() + min_fee_balance;
+    anyhow::ensure!(
+        balance >= required,
+        "Account {address:#x} holds {balance} STRK: fund it with at least {} STRK to deploy it ({deployment_fee} STRK) & pay the fees of the liquidations",
+        required - &balance
+    );
+
+    let tx_hash = account.deploy(class_hash, salt).await?;
+    tracing::info!("🚀 Deploying account {address:#x} in tx {tx_hash:#064x}");
+    for _ in 0..MAX_RETRIES_VERIFY_TX_FINALITY {
+        if get_tx_receipt(rpc_client, tx_hash).await?.is_some() {
+            tracing::info!("🚀 Deployed account {address:#x}");
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_secs(INTERVAL_CHECK_TX_FINALITY)).await;
+    }
+    anyhow::bail!(
+        "The deployment of account {address:#x} in tx {tx_hash:#064x} is not accepted yet"
+    )
+}
+
+/// Prints information about the bot parameters.
+fn print_app_title(account_address: Felt, network: NetworkName) {
+    println!("\n
+██╗   ██╗███████╗███████╗██╗   ██╗    ██╗     ██╗ ██████╗ ██╗   ██╗██╗██████╗  █████╗ ████████╗ ██████╗ ██████╗
+██║   ██║██╔════╝██╔════╝██║   ██║    ██║     ██║██╔═══██╗██║   ██║██║██╔══██╗██╔══██╗╚══██╔══╝██╔═══██╗██╔══██╗
+██║   ██║█████╗  ███████╗██║   ██║    ██║     ██║██║   ██║██║   ██║██║██║  ██║███████║   ██║   ██║   ██║██████╔╝
+╚██╗ ██╔╝██╔══╝  ╚════██║██║   ██║    ██║     ██║██║▄▄ ██║██║   ██║██║██║  ██║██╔══██║   ██║   ██║   ██║██╔══██╗
+ ╚████╔╝ ███████╗███████║╚██████╔╝    ███████╗██║╚██████╔╝╚██████╔╝██║██████╔╝██║  ██║   ██║   ╚██████╔╝██║  ██║
+  ╚═══╝  ╚══════╝╚══════╝ ╚═════╝     ╚══════╝╚═╝ ╚══▀▀═╝  ╚═════╝ ╚═╝╚═════╝ ╚═╝  ╚═╝   ╚═╝    ╚═════╝ ╚═╝  ╚═╝
+
+  🤖 Liquidator 👉 0x{:x}
+  🎯 On {}", account_address, network);
+}
@@ -0,0 +1,148 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use bigdecimal::BigDecimal;
+use starknet::providers::{JsonRpcClient, jsonrpc::HttpTransport};
+use tokio::sync::mpsc::unbounded_channel;
+
+use crate::{
+    cli::ScanCmd,
+    config::Config,
+    protocols::Protocols,
+    services::{
+        notifier::Notifier,
+        oracle::{LatestOraclePrices, OracleService},
+    },
+    storages::Storage,
+    types::position::Position,
+    utils::{http::HttpClient, swap::BPS_DENOMINATOR},
+};
+
+use super::open_storage;
+
+/// A position of the scan, liquidable or near liquidation.
+struct ScannedPosition {
+    position: Position,
+    health_factor: BigDecimal,
+    liquidable: bool,
+    /// See [`crate::types::position::LiquidationCandidate::expected_profit`].
+    expected_profit: Option<BigDecimal>,
+}
+
+/// Refreshes the stored positions & the prices once, then prints the
+/// liquidable positions & the ones near liquidation, without liquidating them
+/// nor writing the storage.
+pub async fn scan(scan_cmd: ScanCmd) -> Result<()> {
+    let bot_params = &scan_cmd.bot_params;
+    let rpc_client = Arc::new(JsonRpcClient::new(HttpTransport::new(
+        bot_params.rpc_url.clone(),
+    )));
+    let config = Config::from_cli(bot_params)?;
+
+    let mut storage = open_storage(&bot_params.storage_path.clone().unwrap_or_default());
+    let (_, positions) = storage.load().await?;
+    let mut positions: Vec<Position> = positions
+        .into_values()
+        .filter(|position| !position.is_closed())
+        .filter(|position| config.position_filter.accepts(&position.key()))
+        .collect();
+
+    let latest_oracle_prices = LatestOraclePrices::from_config(&config);
+    // Notifications are not sent outside of the bot.
+    let (notifications_sender, _) = unbounded_channel();
+    OracleService::new(
+        config.clone(),
+        rpc_client.clone(),
+        HttpClient::new(&config.rate_limits),
+        latest_oracle_prices.clone(),
+        Notifier::new(notifications_sender),
+    )
+    .update_prices()
+    .await?;
+
+    tracing::info!("🔎 Refreshing {} position(s)...", positions.len());
+    Position::update_batch(
+        &mut positions,
+        rpc_client.as_ref(),
+        &Protocols::from_config(&config),
+        config.position_update.batch_size,
+    )
+    .await?;
+
+    let margin_bps = scan_cmd
+        .margin_bps
+        .unwrap_or(config.monitoring.watchlist_margin_bps);
+    let threshold =
+        BigDecimal::from(1) + BigDecimal::from(margin_bps) / BigDecimal::from(BPS_DENOMINATOR);
+    let mut scanned = vec![];
+    let mut unpriced = 0;
+    for position in positions.into_iter().filter(|p| !p.is_closed()) {
+        let Ok(Some(health_factor)) = position.health_factor(&latest_oracle_prices).await else {
+            unpriced += 1;
+            continue;
+        };
+        let liquidable = position.is_liquidable(&latest_oracle_prices).await?;
+        if !liquidable && health_factor >= threshold {
+            continue;
+        }
+        let expected_profit = position
+            .as_liquidation_candidate(&latest_oracle_prices)
+            .await
+            .ok()
+            .map(|candidate| candidate.expected_profit);
+        scanned.push(ScannedPosition {
+            position,
+            health_factor,
+            liquidable,
+            expected_profit,
+        });
+    }
+    scanned.sort_by(|a, b| a.health_factor.cmp(&b.health_factor));
+
+    let liquidable = scanned.iter().filter(|s| s.liquidable).count();
+    println!(
+        "  🔎 {} liquidable position(s), {} near liquidation (health factor below {})",
+        liquidable,
+        scanned.len() - liquidable,
+        threshold.round(4)
+    );
+    if unpriced > 0 {
+        println!("  ⚠️  {unpriced} position(s) without a fresh price were not checked");
+    }
+    if scanned.is_empty() {
+        return Ok(());
+    }
+    println!(
+        "  {:<12} {:<68} {:<20} {:<20} {:>8} {:>14}  {}",
+        "Position", "User", "Collateral", "Debt", "HF", "Profit (USD)", "Status"
+    );
+    for s in scanned.iter() {
+        let position = &s.position;
+        println!(
+            "  {:<12} {:<68} {:<20} {:<20} {:>8} {:>14}  {}",
+            position.key().to_string(),
+            format!("{:#066x}", position.user_address),
+            format!(
+                "{} {}",
+                position.collateral.amount.round(4).to_plain_string(),
+                position.collateral.name
+            ),
+            format!(
+                "{} {}",
+                position.debt.amount.round(4).to_plain_string(),
+                position.debt.name
+            ),
+            s.health_factor.round(4).to_plain_string(),
+            s.expected_profit
+                .as_ref()
+                .map(|profit| profit.round(2).to_plain_string())
+                .unwrap_or_else(|| "?".to_string()),
+            if s.liquidable {
+                "liquidable"
+            } else {
+                "near liquidation"
+            }
+        );
+    }
+    Ok(())
+}
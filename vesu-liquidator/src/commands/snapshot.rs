@@ -0,0 +1,47 @@
+use anyhow::{Result, bail};
+
+use crate::{
+    cli::{SnapshotCmd, SnapshotParams},
+    storages::{Storage, snapshot::StorageSnapshot},
+};
+
+use super::open_storage;
+
+/// Saves the storage to a snapshot file, or restores it from one.
+pub async fn snapshot(snapshot_cmd: SnapshotCmd) -> Result<()> {
+    match snapshot_cmd {
+        SnapshotCmd::Save(params) => save(params).await,
+        SnapshotCmd::Restore(params) => restore(params).await,
+    }
+}
+
+async fn save(params: SnapshotParams) -> Result<()> {
+    let mut storage = open_storage(&params.storage_path);
+    storage.load().await?;
+    let snapshot = StorageSnapshot::capture(&storage)?;
+    snapshot.write(&params.path)?;
+    println!(
+        "  📸 Saved {} position(s) up to block {} to {}",
+        snapshot.positions,
+        snapshot.last_block_indexed,
+        params.path.display()
+    );
+    Ok(())
+}
+
+async fn restore(params: SnapshotParams) -> Result<()> {
+    if params.storage_path.exists() && !params.force {
+        bail!(
+            "{} already exists, use --force to overwrite it",
+            params.storage_path.display()
+        );
+    }
+    let snapshot = StorageSnapshot::read(&params.path)?;
+    let mut storage = open_storage(&params.storage_path);
+    snapshot.restore(&mut storage).await?;
+    println!(
+        "  📸 Restored {} position(s) up to block {} from the snapshot of {} (v{})",
+        snapshot.positions, snapshot.last_block_indexed, snapshot.created_at, snapshot.version
+    );
+    Ok(())
+}
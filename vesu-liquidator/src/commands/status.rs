@@ -0,0 +1,82 @@
+use anyhow::Result;
+
+use crate::{cli::StatusCmd, storages::Storage};
+
+use super::open_storage;
+
+/// Prints the tracked positions & the pending payouts from the storage.
+pub async fn status(status_cmd: StatusCmd) -> Result<()> {
+    let mut storage = open_storage(&status_cmd.storage_path);
+    let (last_block_indexed, positions) = storage.load().await?;
+
+    println!("  📦 Last block indexed: {last_block_indexed}");
+    println!("  🔭 {} tracked position(s)", positions.len());
+    let mut positions: Vec<_> = positions.into_iter().collect();
+    positions.sort_by_key(|(key, _)| *key);
+    for (_, position) in positions.iter() {
+        println!(
+            "    - {position} (pool {:#x}, user {:#x})",
+            position.pool_id, position.user_address
+        );
+    }
+
+    let pruning = storage.get_pruning_state();
+    println!(
+        "  🧹 {} closed position(s) waiting to be pruned, {} pruned so far",
+        pruning.closed_since.len(),
+        pruning.positions_pruned
+    );
+
+    let dead_letters = storage.get_dead_letter_positions();
+    if !dead_letters.is_empty() {
+        println!("  ⚰️  {} dead-letter position(s)", dead_letters.len());
+        for dead_letter in dead_letters.iter() {
+            println!(
+                "    - {} from block {}, failed at {}: {}",
+                dead_letter.position,
+                dead_letter.block_number,
+                dead_letter.failed_at,
+                dead_letter.error
+            );
+        }
+    }
+
+    let pending = storage.get_pending_distribution();
+    println!("  💸 {} pending payout(s)", pending.earnings.len());
+    for earnings in pending.earnings.iter() {
+        println!(
+            "    - {} of token {:#x} from liquidation {:#x}",
+            earnings.amount, earnings.token, earnings.liquidation_tx
+        );
+    }
+
+    let ledger = storage.get_payout_ledger();
+    let unchecked = ledger
+        .records
+        .iter()
+        .filter(|record| !record.checked)
+        .count();
+    println!(
+        "  🧾 {} payout(s) recorded, {} left to reconcile (reconciled up to block {})",
+        ledger.records.len(),
+        unchecked,
+        ledger.last_block_reconciled
+    );
+
+    let downtime_reports = storage.get_downtime_reports();
+    if let Some(last_report) = downtime_reports.last() {
+        println!(
+            "  💤 {} downtime report(s), last: {last_report}",
+            downtime_reports.len()
+        );
+    }
+
+    let game_state = storage.get_game_state();
+    println!(
+        "  🎮 {} player(s) in the redeem queue (synced at {})",
+        game_state.redeem_queue.len(),
+        game_state.synced_at
+    );
+
+    Ok(())
+}
@@ -1,14 +1,36 @@
-use std::fs;
-use std::{collections::HashMap, path::PathBuf};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{env, fs, path::Path};
 
 use anyhow::Result;
+use bigdecimal::BigDecimal;
+use cainome::cairo_serde::U256;
 use clap::ValueEnum;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
-use starknet::core::types::Felt;
+use serde_json::{Map, Value, json};
+use starknet::core::types::{BlockId, BlockTag, Felt};
 use starknet::core::utils::get_selector_from_name;
+use tokio::sync::watch;
 
-use crate::cli::{NetworkName, RunCmd};
+use crate::{
+    cli::{BotParams, NetworkName},
+    protocols::liquidate::LiquidateVersion,
+    types::{notification::Severity, position::PositionFilter},
+    utils::{conversions::to_token_amount, serialization::sorted_map},
+};
+
+/// Prefix of the environment variables overriding fields of the config file,
+/// e.g. `LIQUIDATOR__NOTIFICATIONS__STALE_PRICE_SECONDS=120`.
+const ENV_OVERRIDE_PREFIX: &str = "LIQUIDATOR__";
+/// Fields redacted when printing the resolved config.
+const SECRET_FIELDS: [&str; 6] = [
+    "bot_token",
+    "webhook_url",
+    "pragma_api_key",
+    "api_key",
+    "api_token",
+    "redis_url",
+];
 
 // Contract selectors
 lazy_static! {
@@ -20,8 +42,39 @@ lazy_static! {
     pub static ref VESU_LTV_CONFIG_SELECTOR: Felt = get_selector_from_name("ltv_config").unwrap();
     pub static ref FLASH_LOAN_SELECTOR: Felt = get_selector_from_name("flash_loan").unwrap();
     pub static ref LIQUIDATE_SELECTOR: Felt = get_selector_from_name("liquidate_position").unwrap();
+    pub static ref VESU_LIQUIDATE_SELECTOR: Felt = get_selector_from_name("liquidate").unwrap();
     pub static ref LIQUIDATION_CONFIG_SELECTOR: Felt =
         get_selector_from_name("liquidation_config").unwrap();
+    pub static ref TRANSFER_SELECTOR: Felt = get_selector_from_name("transfer").unwrap();
+    pub static ref BALANCE_OF_SELECTOR: Felt = get_selector_from_name("balance_of").unwrap();
+    pub static ref DECIMALS_SELECTOR: Felt = get_selector_from_name("decimals").unwrap();
+    pub static ref SYMBOL_SELECTOR: Felt = get_selector_from_name("symbol").unwrap();
+    pub static ref ALLOWANCE_SELECTOR: Felt = get_selector_from_name("allowance").unwrap();
+    pub static ref APPROVE_SELECTOR: Felt = get_selector_from_name("approve").unwrap();
+    pub static ref PUBLISH_ROOT_SELECTOR: Felt = get_selector_from_name("publish_root").unwrap();
+    pub static ref EKUBO_MULTI_MULTIHOP_SWAP_SELECTOR: Felt =
+        get_selector_from_name("multi_multihop_swap").unwrap();
+    pub static ref EKUBO_CLEAR_MINIMUM_SELECTOR: Felt =
+        get_selector_from_name("clear_minimum").unwrap();
+    pub static ref EKUBO_CLEAR_SELECTOR: Felt = get_selector_from_name("clear").unwrap();
+    pub static ref ZKLEND_BORROWING_EVENT: Felt = get_selector_from_name("Borrowing").unwrap();
+    pub static ref ZKLEND_REPAYMENT_EVENT: Felt = get_selector_from_name("Repayment").unwrap();
+    pub static ref ZKLEND_WITHDRAWAL_EVENT: Felt = get_selector_from_name("Withdrawal").unwrap();
+    pub static ref ZKLEND_COLLATERAL_DISABLED_EVENT: Felt =
+        get_selector_from_name("CollateralDisabled").unwrap();
+    pub static ref ZKLEND_LIQUIDATION_EVENT: Felt = get_selector_from_name("Liquidation").unwrap();
+    pub static ref ZKLEND_LIQUIDATE_SELECTOR: Felt = get_selector_from_name("liquidate").unwrap();
+    pub static ref ZKLEND_WITHDRAW_ALL_SELECTOR: Felt =
+        get_selector_from_name("withdraw_all").unwrap();
+    pub static ref ZKLEND_RESERVE_DATA_SELECTOR: Felt =
+        get_selector_from_name("get_reserve_data").unwrap();
+    pub static ref ZKLEND_USER_DEBT_SELECTOR: Felt =
+        get_selector_from_name("get_user_debt_for_token").unwrap();
+    pub static ref ZKLEND_IS_COLLATERAL_ENABLED_SELECTOR: Felt =
+        get_selector_from_name("is_collateral_enabled").unwrap();
+    pub static ref TRANSFER_EVENT: Felt = get_selector_from_name("Transfer").unwrap();
+    pub static ref LIQUIDATE_POSITION_EVENT: Felt =
+        get_selector_from_name("LiquidatePosition").unwrap();
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
@@ -45,19 +98,76 @@ pub struct Config {
     pub singleton_address: Felt,
     pub extension_address: Felt,
     pub liquidate_address: Felt,
+    /// Version of the contract at `liquidate_address`, see [`LiquidateVersion`].
+    pub liquidate_version: LiquidateVersion,
     pub pragma_oracle_address: Felt,
     pub assets: Vec<Asset>,
     pub asset_map: HashMap<Felt, Asset>,
     pub liquidation_mode: LiquidationMode,
     pub torii_graphql_url: String,
+    /// Torii endpoints failed over to when `torii_graphql_url` is unhealthy,
+    /// see [`ToriiConfig`].
+    pub torii_fallback_urls: Vec<String>,
     pub world_address: Felt,
+    pub apibara_url: String,
+    pub claims_address: Option<Felt>,
+    pub actions_address: Option<Felt>,
+    pub flash_loan_receiver_address: Option<Felt>,
+    pub operator_address: Option<Felt>,
+    /// Address the funds of the bot are swept to by an operator, see
+    /// [`OperatorConfig`].
+    pub cold_address: Option<Felt>,
+    /// Token the players are paid in, see [`SwapConfig`].
+    pub payout_token: Option<Felt>,
+    /// Routes of the earnings of the assets setting a `payout_route`, see
+    /// [`Config::payout_routing`].
+    pub payout_routes: HashMap<Felt, PayoutRouting>,
+    pub ekubo_router_address: Felt,
+    pub distribution: DistributionConfig,
+    pub supervisor: SupervisorConfig,
+    pub notifications: NotificationsConfig,
+    pub slo: SloConfig,
+    pub game_sync: GameSyncConfig,
+    pub torii: ToriiConfig,
+    pub claims: ClaimsConfig,
+    pub api: ApiConfig,
+    pub grpc: GrpcConfig,
+    pub position_update: PositionUpdateConfig,
+    pub liquidation_retry: LiquidationRetryConfig,
+    pub liquidation_cooldown: LiquidationCooldownConfig,
+    pub price_impact: PriceImpactConfig,
+    pub operator: OperatorConfig,
+    pub oracle: OracleConfig,
+    pub monitoring: MonitoringConfig,
+    pub reconciliation: ReconciliationConfig,
+    /// Recipients of the transfers of the bot that are not payouts, see
+    /// [`ReconciliationConfig`].
+    pub ignored_recipients: Vec<Felt>,
+    pub finality: FinalityConfig,
+    pub watchdog: WatchdogConfig,
+    pub leader_election: LeaderElectionConfig,
+    pub transactions: TransactionsConfig,
+    pub swap: SwapConfig,
+    pub allowances: AllowancesConfig,
+    pub paymaster: PaymasterConfig,
+    pub gas_guard: GasGuardConfig,
+    pub protocols: ProtocolsConfig,
+    pub rate_limits: RateLimitsConfig,
+    /// Pools & assets whose positions are tracked, resolved from the
+    /// [`FiltersConfig`].
+    pub position_filter: PositionFilter,
+    /// Names of the pools tagging their positions, see [`PoolConfig`].
+    pub pool_names: HashMap<Felt, String>,
+    pub tenants: Vec<TenantConfig>,
+    /// The tenant game this config is of, see [`Config::for_tenant`].
+    pub tenant: Option<TenantConfig>,
 }
 
 impl Config {
-    pub fn from_cli(run_cmd: &RunCmd) -> Result<Self> {
-        let config_path = run_cmd.config_path.clone().unwrap_or_default();
-        let network = run_cmd.network;
-        let liquidation_mode = run_cmd.liquidation_mode;
+    pub fn from_cli(bot_params: &BotParams) -> Result<Self> {
+        let config_path = bot_params.config_path.clone().unwrap_or_default();
+        let network = bot_params.network;
+        let liquidation_mode = bot_params.liquidation_mode;
 
         Self::new(network, liquidation_mode, &config_path)
     }
@@ -67,84 +177,1061 @@ impl Config {
         liquidation_mode: LiquidationMode,
         config_path: &PathBuf,
     ) -> Result<Self> {
-        let raw_config: RawConfig = {
-            let config_str = fs::read_to_string(config_path)?;
-            serde_yaml::from_str(&config_str)?
-        };
-
-        let network_config = match network {
-            NetworkName::Mainnet => &raw_config.vesu.mainnet,
-            NetworkName::Sepolia => &raw_config.vesu.sepolia,
-        };
+        let resolved_config = load_raw_config(config_path, network)?;
+        let raw_config: RawConfig = serde_json::from_value(resolved_config.clone())?;
+        let network_config: NetworkConfig = serde_json::from_value(
+            resolved_config["vesu"][network.config_key()].clone(),
+        )
+        .map_err(|e| anyhow::anyhow!("Invalid vesu.{} config: {e}", network.config_key()))?;
 
         let singleton_address = Felt::from_hex(&network_config.singleton_address)?;
         let extension_address = Felt::from_hex(&network_config.extension_address)?;
         let liquidate_address = Felt::from_hex(&network_config.liquidate_address)?;
         let pragma_oracle_address = Felt::from_hex(&network_config.pragma_oracle_address)?;
         let torii_graphql_url = network_config.torii_graphql_url.clone();
+        let torii_fallback_urls = network_config.torii_fallback_urls.clone();
         let world_address = Felt::from_hex(&network_config.world_address)?;
+        let apibara_url = network_config.apibara_url.clone();
+        let claims_address = network_config
+            .claims_address
+            .as_deref()
+            .map(Felt::from_hex)
+            .transpose()?;
+        let actions_address = network_config
+            .actions_address
+            .as_deref()
+            .map(Felt::from_hex)
+            .transpose()?;
+        let flash_loan_receiver_address = network_config
+            .flash_loan_receiver_address
+            .as_deref()
+            .map(Felt::from_hex)
+            .transpose()?;
+        anyhow::ensure!(
+            raw_config.monitoring.flash_loan != FlashLoanSource::Vesu
+                || flash_loan_receiver_address.is_some(),
+            "monitoring.flash_loan can only be vesu with a flash_loan_receiver_address"
+        );
+
+        let distribution = raw_config.distribution;
+        anyhow::ensure!(
+            distribution.strategy != DistributionStrategy::Claims || claims_address.is_some(),
+            "distribution.strategy can only be claims with a claims_address"
+        );
+        anyhow::ensure!(
+            !distribution.record_payouts_onchain || actions_address.is_some(),
+            "distribution.record_payouts_onchain requires an actions_address"
+        );
+        anyhow::ensure!(
+            !distribution.reserve_redeems || actions_address.is_some(),
+            "distribution.reserve_redeems requires an actions_address"
+        );
+        anyhow::ensure!(
+            distribution.leaderboard_size > 0,
+            "distribution.leaderboard_size must be greater than 0"
+        );
+        anyhow::ensure!(
+            distribution.epoch_seconds > 0,
+            "distribution.epoch_seconds must be greater than 0"
+        );
+        anyhow::ensure!(
+            distribution.batching_max_liquidations > 0,
+            "distribution.batching_max_liquidations must be greater than 0"
+        );
+        anyhow::ensure!(
+            distribution.retry_backoff_seconds > 0
+                && distribution.retry_backoff_seconds <= distribution.max_retry_backoff_seconds,
+            "distribution.retry_backoff_seconds must be greater than 0 & at most max_retry_backoff_seconds"
+        );
+        anyhow::ensure!(
+            distribution.score_decay_half_life_seconds > 0,
+            "distribution.score_decay_half_life_seconds must be greater than 0"
+        );
+        anyhow::ensure!(
+            distribution.alert_after_failures > 0,
+            "distribution.alert_after_failures must be greater than 0"
+        );
+        anyhow::ensure!(
+            distribution.max_highest_score_jump == 0.0 || distribution.max_highest_score_jump > 1.0,
+            "distribution.max_highest_score_jump must be 0 or greater than 1"
+        );
+        anyhow::ensure!(
+            distribution
+                .max_player_share_usd
+                .as_ref()
+                .is_none_or(|cap| *cap > BigDecimal::from(0)),
+            "distribution.max_player_share_usd must be greater than 0"
+        );
+        let split = [
+            distribution.player_bps,
+            distribution.world_bps,
+            distribution.operator_bps,
+        ];
+        anyhow::ensure!(
+            split.iter().map(|bps| *bps as u32).sum::<u32>() == 10_000,
+            "distribution.player_bps, world_bps & operator_bps must sum to 10000"
+        );
+        let operator_address = distribution
+            .operator_address
+            .as_deref()
+            .map(Felt::from_hex)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("Invalid distribution.operator_address: {e}"))?;
+        anyhow::ensure!(
+            distribution.operator_bps == 0 || operator_address.is_some(),
+            "distribution.operator_bps requires a distribution.operator_address"
+        );
+
+        let supervisor = raw_config.supervisor;
+        anyhow::ensure!(
+            supervisor.max_consecutive_failures > 0,
+            "supervisor.max_consecutive_failures must be greater than 0"
+        );
+
+        let notifications = raw_config.notifications;
+        for channel in notifications.channels.iter() {
+            if let NotificationBackendConfig::Discord { webhook_url } = &channel.backend {
+                url::Url::parse(webhook_url)
+                    .map_err(|e| anyhow::anyhow!("Invalid discord webhook url: {e}"))?;
+            }
+        }
+
+        let slo = raw_config.slo;
+        anyhow::ensure!(
+            slo.target > 0.0 && slo.target < 1.0,
+            "slo.target must be between 0 and 1"
+        );
+
+        anyhow::ensure!(
+            !raw_config.leader_election.enabled
+                || (raw_config.leader_election.heartbeat_interval_seconds > 0
+                    && raw_config.leader_election.lease_ttl_seconds
+                        > raw_config.leader_election.heartbeat_interval_seconds),
+            "leader_election.lease_ttl_seconds must be greater than heartbeat_interval_seconds, itself greater than 0"
+        );
+        if raw_config.leader_election.enabled {
+            let redis_url = raw_config
+                .leader_election
+                .redis_url
+                .as_deref()
+                .ok_or_else(|| {
+                    anyhow::anyhow!("leader_election.redis_url is required by the leader election")
+                })?;
+            redis::Client::open(redis_url)
+                .map_err(|e| anyhow::anyhow!("Invalid leader_election.redis_url: {e}"))?;
+        }
+        anyhow::ensure!(
+            raw_config.game_sync.interval_seconds > 0,
+            "game_sync.interval_seconds must be greater than 0"
+        );
+        anyhow::ensure!(
+            raw_config.game_sync.cache_ttl_seconds == 0
+                || raw_config.game_sync.cache_ttl_seconds >= raw_config.game_sync.interval_seconds,
+            "game_sync.cache_ttl_seconds must be 0 or at least game_sync.interval_seconds"
+        );
+        anyhow::ensure!(
+            !raw_config.oracle.sources.is_empty()
+                && raw_config
+                    .assets
+                    .iter()
+                    .all(|asset| asset.price_sources.as_ref().is_none_or(|s| !s.is_empty())),
+            "oracle.sources & the price_sources of the assets can't be empty"
+        );
+        let zero = BigDecimal::from(0);
+        anyhow::ensure!(
+            raw_config.assets.iter().all(|asset| asset
+                .min_payout
+                .as_ref()
+                .is_none_or(|min| *min >= zero)
+                && asset
+                    .max_daily_payout
+                    .as_ref()
+                    .is_none_or(|max| *max >= zero)
+                && asset
+                    .approval_amount
+                    .as_ref()
+                    .is_none_or(|amount| *amount >= zero)
+                && asset
+                    .low_balance_threshold
+                    .as_ref()
+                    .is_none_or(|threshold| *threshold >= zero)),
+            "The min_payout, max_daily_payout, approval_amount & low_balance_threshold of the assets can't be negative"
+        );
+        anyhow::ensure!(
+            raw_config
+                .filters
+                .min_position_value_usd
+                .as_ref()
+                .is_none_or(|min| *min >= zero),
+            "filters.min_position_value_usd can't be negative"
+        );
+        anyhow::ensure!(
+            raw_config
+                .oracle
+                .max_jump_percent
+                .is_none_or(|max_jump| max_jump > 0.0),
+            "oracle.max_jump_percent must be positive"
+        );
+        url::Url::parse(&raw_config.oracle.pragma_api_url)
+            .map_err(|e| anyhow::anyhow!("Invalid oracle.pragma_api_url: {e}"))?;
+        if let Some(stream_url) = &raw_config.oracle.stream_url {
+            url::Url::parse(stream_url)
+                .map_err(|e| anyhow::anyhow!("Invalid oracle.stream_url: {e}"))?;
+        }
+
+        anyhow::ensure!(
+            raw_config.monitoring.min_check_interval_seconds > 0
+                && raw_config.monitoring.min_check_interval_seconds
+                    <= raw_config.monitoring.full_check_interval_seconds,
+            "monitoring.min_check_interval_seconds must be greater than 0 & at most full_check_interval_seconds"
+        );
+        anyhow::ensure!(
+            raw_config.monitoring.watchlist_check_interval_seconds > 0,
+            "monitoring.watchlist_check_interval_seconds must be greater than 0"
+        );
+        anyhow::ensure!(
+            raw_config.monitoring.prune_interval_seconds > 0,
+            "monitoring.prune_interval_seconds must be greater than 0"
+        );
+        anyhow::ensure!(
+            raw_config.monitoring.indexer_channel_capacity > 0,
+            "monitoring.indexer_channel_capacity must be greater than 0"
+        );
+        // A position update streamed from a pending block would be refreshed
+        // at the last accepted block, without the update.
+        anyhow::ensure!(
+            !raw_config.monitoring.use_pending_block
+                || raw_config.monitoring.positions_block_tag != Some(ReadBlockTag::Latest),
+            "monitoring.positions_block_tag can't be latest with use_pending_block, the positions would miss the updates of the pending blocks"
+        );
+        if raw_config.monitoring.mixes_block_tags() {
+            tracing::warn!(
+                "The positions & the on-chain prices are read at different block tags, their LTVs can be a block off: the liquidations are still simulated at the pre-confirmed block before being sent"
+            );
+        }
+        anyhow::ensure!(
+            raw_config.reconciliation.interval_seconds > 0
+                && raw_config.reconciliation.max_blocks_per_round > 0,
+            "reconciliation.interval_seconds & max_blocks_per_round must be greater than 0"
+        );
+        let ignored_recipients = raw_config
+            .reconciliation
+            .ignored_recipients
+            .iter()
+            .map(|address| Felt::from_hex(address))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| anyhow::anyhow!("Invalid reconciliation.ignored_recipients: {e}"))?;
+        anyhow::ensure!(
+            raw_config.finality.interval_seconds > 0,
+            "finality.interval_seconds must be greater than 0"
+        );
+        anyhow::ensure!(
+            raw_config.finality.enabled || !raw_config.finality.hold_world_share,
+            "finality.hold_world_share needs finality.enabled"
+        );
+        anyhow::ensure!(
+            raw_config.transactions.timeout_seconds > 0,
+            "transactions.timeout_seconds must be greater than 0"
+        );
+        anyhow::ensure!(
+            raw_config.transactions.fee_bump_factor > 1.0,
+            "transactions.fee_bump_factor must be greater than 1"
+        );
+        anyhow::ensure!(
+            raw_config.transactions.min_account_fee_balance >= 0.0
+                && raw_config.transactions.account_refresh_seconds > 0,
+            "transactions.min_account_fee_balance can't be negative & transactions.account_refresh_seconds must be greater than 0"
+        );
+        anyhow::ensure!(
+            raw_config.position_update.max_attempts > 0,
+            "position_update.max_attempts must be greater than 0"
+        );
+        anyhow::ensure!(
+            raw_config.liquidation_retry.max_attempts > 0,
+            "liquidation_retry.max_attempts must be greater than 0"
+        );
+        anyhow::ensure!(
+            raw_config.position_update.batch_size > 0,
+            "position_update.batch_size must be greater than 0"
+        );
+        anyhow::ensure!(
+            raw_config.claims.queue_size_threshold > 0,
+            "claims.queue_size_threshold must be greater than 0"
+        );
+        raw_config
+            .api
+            .listen_address
+            .parse::<std::net::SocketAddr>()
+            .map_err(|e| anyhow::anyhow!("Invalid api.listen_address: {e}"))?;
+        raw_config
+            .grpc
+            .listen_address
+            .parse::<std::net::SocketAddr>()
+            .map_err(|e| anyhow::anyhow!("Invalid grpc.listen_address: {e}"))?;
+
+        url::Url::parse(&torii_graphql_url)
+            .map_err(|e| anyhow::anyhow!("Invalid torii_graphql_url: {e}"))?;
+        for url in torii_fallback_urls.iter() {
+            url::Url::parse(url)
+                .map_err(|e| anyhow::anyhow!("Invalid torii_fallback_urls {url}: {e}"))?;
+        }
+        anyhow::ensure!(
+            raw_config.torii.health_check_interval_seconds > 0
+                && raw_config.torii.max_head_lag_seconds > 0,
+            "torii.health_check_interval_seconds & max_head_lag_seconds must be greater than 0"
+        );
+        for (field, model) in [
+            ("redeem_model", &raw_config.torii.redeem_model),
+            ("leaderboard_model", &raw_config.torii.leaderboard_model),
+            (
+                "season_leaderboard_model",
+                &raw_config.torii.season_leaderboard_model,
+            ),
+        ] {
+            anyhow::ensure!(
+                is_graphql_name(model),
+                "Invalid torii.{field} {model:?}, expected a GraphQL field name"
+            );
+        }
+        url::Url::parse(&apibara_url).map_err(|e| anyhow::anyhow!("Invalid apibara_url: {e}"))?;
 
         let assets = raw_config.assets;
         let asset_map = assets
             .iter()
             .filter_map(|asset| {
                 let address = match network {
-                    NetworkName::Mainnet => Felt::from_hex(&asset.mainnet_address),
-                    NetworkName::Sepolia => Felt::from_hex(&asset.sepolia_address),
-                };
-                address.ok().map(|addr| (addr, asset.clone()))
+                    NetworkName::Mainnet => Some(asset.mainnet_address.as_str()),
+                    NetworkName::Sepolia => Some(asset.sepolia_address.as_str()),
+                    NetworkName::Katana => asset.katana_address.as_deref(),
+                }?;
+                Felt::from_hex(address)
+                    .ok()
+                    .map(|addr| (addr, asset.clone()))
             })
-            .collect();
+            .collect::<HashMap<Felt, Asset>>();
+        let position_filter = raw_config.filters.resolve(&asset_map)?;
+        let payout_token = match &raw_config.swap.payout_token {
+            Some(ticker) => Some(
+                asset_map
+                    .iter()
+                    .find(|(_, asset)| asset.ticker.eq_ignore_ascii_case(ticker))
+                    .map(|(address, _)| *address)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("swap.payout_token {ticker} is not an asset of the network")
+                    })?,
+            ),
+            None => None,
+        };
+        let payout_routes = resolve_payout_routes(&asset_map, payout_token)?;
+        let cold_address = raw_config
+            .operator
+            .cold_address
+            .as_deref()
+            .map(Felt::from_hex)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("Invalid operator.cold_address: {e}"))?;
+        anyhow::ensure!(
+            raw_config
+                .operator
+                .api_token
+                .as_ref()
+                .is_none_or(|token| !token.is_empty()),
+            "operator.api_token can't be empty"
+        );
+        anyhow::ensure!(
+            raw_config.operator.strk_fee_reserve >= BigDecimal::from(0),
+            "operator.strk_fee_reserve can't be negative"
+        );
+        for ticker in raw_config.operator.sweep_assets.iter() {
+            anyhow::ensure!(
+                asset_map
+                    .values()
+                    .any(|asset| asset.ticker.eq_ignore_ascii_case(ticker)),
+                "operator.sweep_assets {ticker} is not an asset of the network"
+            );
+        }
+        let pool_names = raw_config
+            .pools
+            .iter()
+            .map(|pool| {
+                Felt::from_hex(&pool.id)
+                    .map(|id| (id, pool.name.clone()))
+                    .map_err(|e| anyhow::anyhow!("Invalid pool id {}: {e}", pool.id))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+        anyhow::ensure!(
+            raw_config.swap.max_slippage_bps <= 10_000,
+            "swap.max_slippage_bps can't be more than 10000"
+        );
+        anyhow::ensure!(
+            !raw_config.swap.providers.is_empty(),
+            "swap.providers can't be empty"
+        );
+        url::Url::parse(&raw_config.swap.avnu_api_url)
+            .map_err(|e| anyhow::anyhow!("Invalid swap.avnu_api_url: {e}"))?;
+        url::Url::parse(&raw_config.swap.ekubo_quoter_url)
+            .map_err(|e| anyhow::anyhow!("Invalid swap.ekubo_quoter_url: {e}"))?;
+        let ekubo_router_address = Felt::from_hex(&raw_config.swap.ekubo_router_address)?;
+        if raw_config.paymaster.enabled {
+            url::Url::parse(&raw_config.paymaster.api_url)
+                .map_err(|e| anyhow::anyhow!("Invalid paymaster.api_url: {e}"))?;
+            anyhow::ensure!(
+                asset_map.values().any(|asset| asset
+                    .ticker
+                    .eq_ignore_ascii_case(&raw_config.paymaster.gas_token)),
+                "Paymaster gas token {} is not an asset of the network",
+                raw_config.paymaster.gas_token
+            );
+            anyhow::ensure!(
+                raw_config.paymaster.retry_after_seconds > 0
+                    && raw_config
+                        .paymaster
+                        .max_gas_token_amount
+                        .as_ref()
+                        .is_none_or(|amount| *amount > BigDecimal::from(0)),
+                "paymaster.retry_after_seconds & max_gas_token_amount must be greater than 0"
+            );
+        }
+        if raw_config.transactions.fee_token == FeeToken::Eth
+            || raw_config.transactions.fee_token_fallback
+        {
+            let eth = asset_map
+                .values()
+                .find(|asset| asset.ticker.eq_ignore_ascii_case("eth"));
+            anyhow::ensure!(
+                eth.is_some()
+                    && asset_map
+                        .values()
+                        .any(|asset| asset.ticker.eq_ignore_ascii_case("strk")),
+                "Paying the fees in ETH needs both ETH & STRK assets"
+            );
+            anyhow::ensure!(
+                !raw_config.paymaster.enabled,
+                "Paying the fees in ETH goes through the paymaster, it can't be combined with paymaster.enabled"
+            );
+            anyhow::ensure!(
+                !raw_config.transactions.fee_token_fallback
+                    || eth.is_some_and(|eth| eth.low_balance_threshold.is_some()),
+                "transactions.fee_token_fallback needs a low_balance_threshold on the ETH asset"
+            );
+        }
+        anyhow::ensure!(
+            raw_config.price_impact.max_impact_bps <= 10_000
+                && raw_config.price_impact.max_tranches > 0
+                && raw_config.price_impact.min_debt_value_usd >= BigDecimal::from(0),
+            "price_impact.max_impact_bps can't be more than 10000, price_impact.max_tranches must be greater than 0 & price_impact.min_debt_value_usd can't be negative"
+        );
+        if raw_config.gas_guard.enabled {
+            anyhow::ensure!(
+                raw_config.gas_guard.max_l2_gas_price > 0
+                    && raw_config.gas_guard.window_blocks > 0
+                    && raw_config.gas_guard.poll_interval_seconds > 0,
+                "gas_guard.max_l2_gas_price, window_blocks & poll_interval_seconds must be greater than 0"
+            );
+        }
+        for (host, limit) in raw_config.rate_limits.hosts.iter() {
+            anyhow::ensure!(
+                limit.requests_per_second > 0.0 && limit.burst > 0,
+                "rate_limits.hosts.{host}.requests_per_second & burst must be greater than 0"
+            );
+        }
+        anyhow::ensure!(
+            raw_config.protocols.vesu.enabled || raw_config.protocols.zklend.enabled,
+            "At least one lending protocol must be enabled in protocols"
+        );
+        if raw_config.protocols.zklend.enabled {
+            raw_config.protocols.zklend.validate(&asset_map)?;
+        }
 
         let config = Config {
             network,
             singleton_address,
             extension_address,
             liquidate_address,
+            liquidate_version: network_config.liquidate_version,
             pragma_oracle_address,
             assets,
             asset_map,
             liquidation_mode,
             torii_graphql_url,
+            torii_fallback_urls,
             world_address,
+            apibara_url,
+            claims_address,
+            actions_address,
+            flash_loan_receiver_address,
+            operator_address,
+            cold_address,
+            payout_token,
+            payout_routes,
+            ekubo_router_address,
+            distribution,
+            supervisor,
+            notifications,
+            slo,
+            game_sync: raw_config.game_sync,
+            torii: raw_config.torii,
+            claims: raw_config.claims,
+            api: raw_config.api,
+            grpc: raw_config.grpc,
+            position_update: raw_config.position_update,
+            liquidation_retry: raw_config.liquidation_retry,
+            liquidation_cooldown: raw_config.liquidation_cooldown,
+            price_impact: raw_config.price_impact,
+            operator: raw_config.operator,
+            oracle: raw_config.oracle,
+            monitoring: raw_config.monitoring,
+            reconciliation: raw_config.reconciliation,
+            ignored_recipients,
+            finality: raw_config.finality,
+            watchdog: raw_config.watchdog,
+            leader_election: raw_config.leader_election,
+            transactions: raw_config.transactions,
+            swap: raw_config.swap,
+            allowances: raw_config.allowances,
+            paymaster: raw_config.paymaster,
+            gas_guard: raw_config.gas_guard,
+            protocols: raw_config.protocols,
+            rate_limits: raw_config.rate_limits,
+            position_filter,
+            pool_names,
+            tenants: raw_config.tenants,
+            tenant: None,
         };
+        config.validate_tenants()?;
 
+        print_resolved_config(resolved_config)?;
         Ok(config)
     }
 
+    /// Returns the config of a tenant game: the game-specific fields are
+    /// replaced by the ones of the tenant.
+    pub fn for_tenant(&self, tenant: &TenantConfig) -> Result<Config> {
+        let mut config = self.clone();
+        config.world_address = Felt::from_hex(&tenant.world_address)?;
+        config.torii_graphql_url = tenant.torii_graphql_url.clone();
+        config.torii_fallback_urls = tenant.torii_fallback_urls.clone();
+        config.claims_address = tenant
+            .claims_address
+            .as_deref()
+            .map(Felt::from_hex)
+            .transpose()?;
+        config.actions_address = tenant
+            .actions_address
+            .as_deref()
+            .map(Felt::from_hex)
+            .transpose()?;
+        config.distribution.strategy = tenant.strategy;
+        if let Some(split) = &tenant.split {
+            split.apply(&mut config.distribution);
+        }
+        if let Some(model) = &tenant.redeem_model {
+            config.torii.redeem_model = model.clone();
+        }
+        if let Some(model) = &tenant.leaderboard_model {
+            config.torii.leaderboard_model = model.clone();
+        }
+        if let Some(model) = &tenant.season_leaderboard_model {
+            config.torii.season_leaderboard_model = model.clone();
+        }
+        config.tenants = vec![];
+        config.tenant = Some(tenant.clone());
+        Ok(config)
+    }
+
+    fn validate_tenants(&self) -> Result<()> {
+        let mut names = std::collections::HashSet::new();
+        for tenant in self.tenants.iter() {
+            anyhow::ensure!(
+                !tenant.name.is_empty()
+                    && tenant
+                        .name
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-'),
+                "Invalid tenant name {:?}, only lowercase letters, digits & - are allowed",
+                tenant.name
+            );
+            anyhow::ensure!(
+                names.insert(tenant.name.as_str()),
+                "Duplicated tenant {}",
+                tenant.name
+            );
+            anyhow::ensure!(
+                tenant.share_percentage > 0 && tenant.share_percentage <= 100,
+                "tenants.{}.share_percentage must be between 1 and 100",
+                tenant.name
+            );
+            url::Url::parse(&tenant.torii_graphql_url).map_err(|e| {
+                anyhow::anyhow!("Invalid tenants.{}.torii_graphql_url: {e}", tenant.name)
+            })?;
+            for url in tenant.torii_fallback_urls.iter() {
+                url::Url::parse(url).map_err(|e| {
+                    anyhow::anyhow!("Invalid tenants.{}.torii_fallback_urls: {e}", tenant.name)
+                })?;
+            }
+            let config = self
+                .for_tenant(tenant)
+                .map_err(|e| anyhow::anyhow!("Invalid tenants.{} address: {e}", tenant.name))?;
+            anyhow::ensure!(
+                tenant.strategy != DistributionStrategy::Claims || config.claims_address.is_some(),
+                "tenants.{}.strategy can only be claims with a claims_address",
+                tenant.name
+            );
+            if let Some(split) = &tenant.split {
+                anyhow::ensure!(
+                    split.total() == 10_000,
+                    "tenants.{}.split player_bps, world_bps & operator_bps must sum to 10000",
+                    tenant.name
+                );
+            }
+            for (field, model) in [
+                ("redeem_model", &tenant.redeem_model),
+                ("leaderboard_model", &tenant.leaderboard_model),
+                ("season_leaderboard_model", &tenant.season_leaderboard_model),
+            ] {
+                if let Some(model) = model {
+                    anyhow::ensure!(
+                        is_graphql_name(model),
+                        "Invalid tenants.{}.{field} {model:?}, expected a GraphQL field name",
+                        tenant.name
+                    );
+                }
+            }
+        }
+        let total_share: u32 = self
+            .tenants
+            .iter()
+            .map(|tenant| u32::from(tenant.share_percentage))
+            .sum();
+        anyhow::ensure!(
+            total_share <= 100,
+            "The share_percentage of the tenants add up to {total_share}%, more than 100%"
+        );
+        Ok(())
+    }
+
+    /// Returns how the earnings in `token` are paid out: the route of its
+    /// asset, or else swapped to the `payout_token`, if any.
+    pub fn payout_routing(&self, token: Felt) -> PayoutRouting {
+        if let Some(routing) = self.payout_routes.get(&token) {
+            return *routing;
+        }
+        match self.payout_token {
+            Some(payout_token) if payout_token != token => PayoutRouting::SwapTo(payout_token),
+            _ => PayoutRouting::AsIs,
+        }
+    }
+
+    pub fn get_pool_name(&self, pool_id: &Felt) -> Option<String> {
+        self.pool_names.get(pool_id).cloned()
+    }
+
     pub fn get_asset_ticker_for_address(&self, address: &Felt) -> Option<String> {
         self.asset_map
             .get(address)
             .map(|asset| asset.ticker.clone())
     }
 
+    pub fn get_asset_address_for_ticker(&self, ticker: &str) -> Option<Felt> {
+        self.asset_map
+            .iter()
+            .find(|(_, asset)| asset.ticker.eq_ignore_ascii_case(ticker))
+            .map(|(address, _)| *address)
+    }
+
     pub fn get_decimal_for_address(&self, address: &Felt) -> Option<i64> {
         self.asset_map.get(address).map(|asset| asset.decimals)
     }
+
+    /// Returns the `approval_amount` of a token in its smallest unit, if any.
+    pub fn approval_amount(&self, token: &Felt) -> Option<U256> {
+        let asset = self.asset_map.get(token)?;
+        asset
+            .approval_amount
+            .as_ref()
+            .map(|amount| asset.to_token_amount(amount))
+    }
+
+    /// Returns the paymaster gas token & the max amount of it paid per
+    /// transaction, in its smallest unit.
+    pub fn paymaster_gas_token(&self) -> Option<(Felt, Option<U256>)> {
+        let token = self.get_asset_address_for_ticker(&self.paymaster.gas_token)?;
+        let asset = self.asset_map.get(&token)?;
+        let max_amount = self
+            .paymaster
+            .max_gas_token_amount
+            .as_ref()
+            .map(|amount| asset.to_token_amount(amount));
+        Some((token, max_amount))
+    }
+
+    /// Returns this config with the [`RELOADABLE_FIELDS`] of `new`, the other
+    /// fields being only applied by a restart.
+    pub fn reloaded(&self, new: &Config) -> Config {
+        let mut config = self.clone();
+        // A tenant with its own split keeps it until a restart.
+        if self
+            .tenant
+            .as_ref()
+            .is_none_or(|tenant| tenant.split.is_none())
+        {
+            config.distribution.player_bps = new.distribution.player_bps;
+            config.distribution.world_bps = new.distribution.world_bps;
+            config.distribution.operator_bps = new.distribution.operator_bps;
+        }
+        config.distribution.operator_address = new.distribution.operator_address.clone();
+        config.operator_address = new.operator_address;
+        config.monitoring.full_check_interval_seconds = new.monitoring.full_check_interval_seconds;
+        config.monitoring.min_check_interval_seconds = new.monitoring.min_check_interval_seconds;
+        config.monitoring.near_liquidation_positions = new.monitoring.near_liquidation_positions;
+        config.monitoring.watchlist_margin_bps = new.monitoring.watchlist_margin_bps;
+        config.monitoring.watchlist_check_interval_seconds =
+            new.monitoring.watchlist_check_interval_seconds;
+        config.monitoring.at_risk_margin_bps = new.monitoring.at_risk_margin_bps;
+        config.monitoring.prune_interval_seconds = new.monitoring.prune_interval_seconds;
+        config.monitoring.full_sweep_interval_seconds = new.monitoring.full_sweep_interval_seconds;
+        config.gas_guard.action = new.gas_guard.action;
+        config.gas_guard.urgent_ltv_excess = new.gas_guard.urgent_ltv_excess.clone();
+        config.gas_guard.spike_min_expected_profit_usd =
+            new.gas_guard.spike_min_expected_profit_usd.clone();
+        config.liquidation_cooldown = new.liquidation_cooldown.clone();
+        config.position_filter = new.position_filter.clone();
+        config.notifications.channels = new.notifications.channels.clone();
+        config.notifications.low_balance_threshold = new.notifications.low_balance_threshold;
+        config
+    }
 }
 
-// Below are the structs that represents the raw config extracted from the yaml file.
+/// Fields of the config file applied by a reload of the running bot, see
+/// [`LiveConfig`]: the profit thresholds, the distribution split, the check
+/// intervals, the position filters & the notification channels.
+pub const RELOADABLE_FIELDS: &[&str] = &[
+    "distribution.player_bps",
+    "distribution.world_bps",
+    "distribution.operator_bps",
+    "distribution.operator_address",
+    "monitoring.full_check_interval_seconds",
+    "monitoring.min_check_interval_seconds",
+    "monitoring.near_liquidation_positions",
+    "monitoring.watchlist_margin_bps",
+    "monitoring.watchlist_check_interval_seconds",
+    "monitoring.at_risk_margin_bps",
+    "monitoring.prune_interval_seconds",
+    "monitoring.full_sweep_interval_seconds",
+    "gas_guard.action",
+    "gas_guard.urgent_ltv_excess",
+    "gas_guard.spike_min_expected_profit_usd",
+    "liquidation_cooldown",
+    "filters",
+    "notifications.channels",
+    "notifications.low_balance_threshold",
+];
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct RawConfig {
-    pub vesu: VesuConfig,
-    pub assets: Vec<Asset>,
+/// Returns if `name` can be formatted into a GraphQL query as a field name.
+fn is_graphql_name(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Returns if a field of the config file, e.g. `filters.assets.0`, is applied
+/// by a reload.
+pub fn is_reloadable(key: &str) -> bool {
+    RELOADABLE_FIELDS.iter().any(|field| {
+        key == *field
+            || key
+                .strip_prefix(field)
+                .is_some_and(|rest| rest.starts_with('.'))
+    })
+}
+
+/// The config of the running bot, shared by its services. Its
+/// [`RELOADABLE_FIELDS`] are replaced on a reload, the services reading them
+/// when they use them or being notified of the changes.
+#[derive(Clone)]
+pub struct LiveConfig(Arc<watch::Sender<Arc<Config>>>);
+
+impl LiveConfig {
+    pub fn new(config: Config) -> Self {
+        let (sender, _) = watch::channel(Arc::new(config));
+        Self(Arc::new(sender))
+    }
+
+    /// Returns the config currently applied.
+    pub fn current(&self) -> Arc<Config> {
+        self.0.borrow().clone()
+    }
+
+    /// Returns a receiver notified of the reloads.
+    pub fn subscribe(&self) -> watch::Receiver<Arc<Config>> {
+        self.0.subscribe()
+    }
+
+    /// Applies the [`RELOADABLE_FIELDS`] of `new`.
+    pub fn reload(&self, new: &Config) {
+        let config = self.current().reloaded(new);
+        self.0.send_replace(Arc::new(config));
+    }
+
+    /// Modifies the config currently applied, until the next reload of the
+    /// config file.
+    pub fn update(&self, modify: impl FnOnce(&mut Config)) -> Arc<Config> {
+        let mut config = (*self.current()).clone();
+        modify(&mut config);
+        let config = Arc::new(config);
+        self.0.send_replace(config.clone());
+        config
+    }
+}
+
+/// Reads the config file - TOML if its extension is `.toml`, YAML otherwise -
+/// completes it with the network profile and applies the environment
+/// variables overrides.
+pub fn load_raw_config(config_path: &Path, network: NetworkName) -> Result<Value> {
+    let config_str = fs::read_to_string(config_path)?;
+    let mut config: Value = match config_path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => toml::from_str(&config_str)?,
+        _ => serde_yaml::from_str(&config_str)?,
+    };
+    apply_network_profile(&mut config, network);
+    apply_env_overrides(&mut config, env::vars());
+    Ok(config)
 }
 
+/// Fills the fields of `vesu.<network>` missing from the config file with
+/// the built-in profile of the network.
+fn apply_network_profile(config: &mut Value, network: NetworkName) {
+    let Some(network_config) = config
+        .as_object_mut()
+        .and_then(|fields| object_field(fields, "vesu"))
+        .and_then(|vesu| object_field(vesu, &network.config_key()))
+    else {
+        return;
+    };
+    let Value::Object(fields) = network_profile(network) else {
+        unreachable!("network profiles are objects");
+    };
+    for (key, value) in fields {
+        network_config.entry(key).or_insert(value);
+    }
+}
+
+/// Returns the object at `key`, created if missing or empty. `None` if the
+/// field is not an object.
+fn object_field<'a>(
+    fields: &'a mut Map<String, Value>,
+    key: &str,
+) -> Option<&'a mut Map<String, Value>> {
+    let field = fields.entry(key).or_insert(Value::Null);
+    if field.is_null() {
+        *field = Value::Object(Map::new());
+    }
+    field.as_object_mut()
+}
+
+/// Built-in contracts & endpoints of each network. The world & claims
+/// addresses depend on the game deployment and have no default, nor do the
+/// Vesu contracts on Katana as they are deployed with the devnet.
+fn network_profile(network: NetworkName) -> Value {
+    match network {
+        NetworkName::Mainnet => json!({
+            "singleton_address": "0x000d8d6dfec4d33bfb6895de9f3852143a17c6f92fd2a21da3d6924d34870160",
+            "extension_address": "0x4e06e04b8d624d039aa1c3ca8e0aa9e21dc1ccba1d88d0d650837159e0ee054",
+            "liquidate_address": "0x58c80ed9801b32b441566d320ae236c73257981800dcda63c9f02dd154c3f39",
+            "pragma_oracle_address": "0x2a85bd616f912537c50a49a4076db02c00b29b2cdc8a197ce92ed1837fa875b",
+            "torii_graphql_url": "https://api.mainnet.dojo.com/graphql",
+            "apibara_url": "https://mainnet.starknet.a5a.ch",
+        }),
+        NetworkName::Sepolia => json!({
+            "singleton_address": "0x69d0eca40cb01eda7f3d76281ef524cecf8c35f4ca5acc862ff128e7432964b",
+            "extension_address": "0x18e0277fef34ae5687da68b7810a04230a45ff9686068868528d2e07fae705d",
+            "liquidate_address": "0x11cc615b361d445d07aac1f27882f1597ac0e02cec434d729510c2d02fdc883",
+            "pragma_oracle_address": "0x36031daa264c24520b11d93af622c848b2499b66b41d611bac95e13cfca131a",
+            "torii_graphql_url": "http://localhost:8080/graphql",
+            "apibara_url": "https://sepolia.starknet.a5a.ch",
+        }),
+        NetworkName::Katana => json!({
+            "torii_graphql_url": "http://localhost:8080/graphql",
+            "apibara_url": "http://localhost:7171",
+        }),
+    }
+}
+
+/// Overrides the config fields with the `LIQUIDATOR__<FIELD>__<SUBFIELD>` variables.
+/// Values are parsed as JSON (numbers, booleans, arrays...) unless the
+/// overridden field is a string. Only the fields of the config file can be
+/// overridden, with a value of the same shape: a scalar for a scalar, an
+/// object or an array otherwise.
+fn apply_env_overrides(config: &mut Value, vars: impl Iterator<Item = (String, String)>) {
+    for (key, raw_value) in vars {
+        let Some(path) = key.strip_prefix(ENV_OVERRIDE_PREFIX) else {
+            continue;
+        };
+
+        let segments: Vec<String> = path.split("__").map(str::to_lowercase).collect();
+        let Some(field) = field_at(config, &segments) else {
+            tracing::warn!("Ignoring {key}: no such config field");
+            continue;
+        };
+
+        let value = match field {
+            Value::String(_) => Value::String(raw_value),
+            _ => serde_json::from_str(&raw_value).unwrap_or(Value::String(raw_value)),
+        };
+        let is_scalar = |value: &Value| !value.is_object() && !value.is_array();
+        if !field.is_null() && is_scalar(field) != is_scalar(&value) {
+            tracing::warn!("Ignoring {key}: the value doesn't match the shape of the config field");
+            continue;
+        }
+        *field = value;
+        tracing::debug!("Config field {path} overridden from the environment");
+    }
+}
+
+/// Returns the field of the config at the given path, `None` if it is not in
+/// the config.
+fn field_at<'a>(value: &'a mut Value, segments: &[String]) -> Option<&'a mut Value> {
+    let Some((segment, rest)) = segments.split_first() else {
+        return Some(value);
+    };
+    let next = match value {
+        Value::Array(items) => items.get_mut(segment.parse::<usize>().ok()?)?,
+        Value::Object(fields) => fields.get_mut(segment)?,
+        _ => return None,
+    };
+    field_at(next, rest)
+}
+
+/// Returns true if the config field holds a secret.
+pub fn is_secret_field(key: &str) -> bool {
+    SECRET_FIELDS.contains(&key)
+}
+
+/// Replaces the secrets of the config by a placeholder.
+pub fn redact_secrets(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                if is_secret_field(key) {
+                    *field = Value::String("<redacted>".to_string());
+                } else {
+                    redact_secrets(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_secrets),
+        _ => {}
+    }
+}
+
+/// Logs the resolved config with its secrets redacted.
+fn print_resolved_config(mut config: Value) -> Result<()> {
+    redact_secrets(&mut config);
+    tracing::info!(
+        "⚙️  Resolved configuration:\n{}",
+        serde_yaml::to_string(&config)?
+    );
+    Ok(())
+}
+
+// Below are the structs that represents the raw config extracted from the config file.
+
+/// The `vesu.<network>` section is read separately, see [`NetworkConfig`].
 #[derive(Debug, Deserialize, Serialize)]
-pub struct VesuConfig {
-    pub mainnet: NetworkConfig,
-    pub sepolia: NetworkConfig,
+pub struct RawConfig {
+    pub assets: Vec<Asset>,
+    #[serde(default)]
+    pub distribution: DistributionConfig,
+    #[serde(default)]
+    pub supervisor: SupervisorConfig,
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    #[serde(default)]
+    pub slo: SloConfig,
+    #[serde(default)]
+    pub game_sync: GameSyncConfig,
+    #[serde(default)]
+    pub torii: ToriiConfig,
+    #[serde(default)]
+    pub claims: ClaimsConfig,
+    #[serde(default)]
+    pub api: ApiConfig,
+    #[serde(default)]
+    pub grpc: GrpcConfig,
+    #[serde(default)]
+    pub position_update: PositionUpdateConfig,
+    #[serde(default)]
+    pub liquidation_retry: LiquidationRetryConfig,
+    #[serde(default)]
+    pub liquidation_cooldown: LiquidationCooldownConfig,
+    #[serde(default)]
+    pub price_impact: PriceImpactConfig,
+    #[serde(default)]
+    pub operator: OperatorConfig,
+    #[serde(default)]
+    pub oracle: OracleConfig,
+    #[serde(default)]
+    pub monitoring: MonitoringConfig,
+    #[serde(default)]
+    pub reconciliation: ReconciliationConfig,
+    #[serde(default)]
+    pub finality: FinalityConfig,
+    #[serde(default)]
+    pub watchdog: WatchdogConfig,
+    #[serde(default)]
+    pub leader_election: LeaderElectionConfig,
+    #[serde(default)]
+    pub transactions: TransactionsConfig,
+    #[serde(default)]
+    pub swap: SwapConfig,
+    #[serde(default)]
+    pub allowances: AllowancesConfig,
+    #[serde(default)]
+    pub paymaster: PaymasterConfig,
+    #[serde(default)]
+    pub gas_guard: GasGuardConfig,
+    #[serde(default)]
+    pub protocols: ProtocolsConfig,
+    #[serde(default)]
+    pub rate_limits: RateLimitsConfig,
+    #[serde(default)]
+    pub filters: FiltersConfig,
+    #[serde(default)]
+    pub pools: Vec<PoolConfig>,
+    #[serde(default)]
+    pub tenants: Vec<TenantConfig>,
 }
 
+/// Contracts & endpoints of the selected network, pre-filled by its built-in
+/// profile.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct NetworkConfig {
     pub singleton_address: String,
     pub extension_address: String,
     pub liquidate_address: String,
+    /// Version of the Liquidate contract, `v1` by default.
+    #[serde(default)]
+    pub liquidate_version: LiquidateVersion,
     pub pragma_oracle_address: String,
     pub torii_graphql_url: String,
+    /// Torii endpoints failed over to when `torii_graphql_url` is unhealthy.
+    #[serde(default)]
+    pub torii_fallback_urls: Vec<String>,
     pub world_address: String,
+    /// Apibara DNA stream used by the indexer.
+    pub apibara_url: String,
+    /// Claims contract receiving the merkle roots of the large distributions.
+    #[serde(default)]
+    pub claims_address: Option<String>,
+    /// Actions contract of the game, clearing the redeem entries of the paid
+    /// players.
+    #[serde(default)]
+    pub actions_address: Option<String>,
+    /// Contract receiving the Vesu flash loans of the liquidations, see
+    /// [`FlashLoanSource::Vesu`].
+    #[serde(default)]
+    pub flash_loan_receiver_address: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -154,4 +1241,1417 @@ pub struct Asset {
     pub decimals: i64,
     pub mainnet_address: String,
     pub sepolia_address: String,
+    /// Address of the token on the devnet, if deployed.
+    #[serde(default)]
+    pub katana_address: Option<String>,
+    /// Price sources of the asset, replacing the default `oracle.sources`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_sources: Option<Vec<PriceSourceConfig>>,
+    /// Shares of the players below this amount of the token are kept in the
+    /// dust ledger until their total reaches it, not worth the gas otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_payout: Option<BigDecimal>,
+    /// Amount of the token a player can receive over 24 hours, the excess
+    /// going to the world.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_daily_payout: Option<BigDecimal>,
+    /// Amount of the token approved to the Liquidate contract when its
+    /// allowance runs short, raised to the debt to repay if lower.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval_amount: Option<BigDecimal>,
+    /// A warning is sent when the bot's balance of the token goes below this
+    /// amount.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low_balance_threshold: Option<BigDecimal>,
+    /// What the earnings seized in the token are paid out as, swapped to the
+    /// `swap.payout_token` if any by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payout_route: Option<PayoutRoute>,
+    /// Ticker of the token the earnings are swapped to with the `swap` route,
+    /// the `swap.payout_token` by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payout_swap_to: Option<String>,
+}
+
+/// What the earnings seized in an asset are paid out as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayoutRoute {
+    /// Paid in the seized token.
+    AsIs,
+    /// Swapped before being paid, see [`Asset::payout_swap_to`].
+    Swap,
+    /// Kept by the bot as treasury, not paid out.
+    Retain,
+}
+
+/// Route of the earnings in a token, resolved from its [`PayoutRoute`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayoutRouting {
+    AsIs,
+    SwapTo(Felt),
+    Retain,
+}
+
+/// Resolves the routes of the assets setting a `payout_route`, the swaps
+/// going to their `payout_swap_to` or else to the `payout_token`.
+fn resolve_payout_routes(
+    asset_map: &HashMap<Felt, Asset>,
+    payout_token: Option<Felt>,
+) -> Result<HashMap<Felt, PayoutRouting>> {
+    let address_of = |ticker: &str| {
+        asset_map
+            .iter()
+            .find(|(_, asset)| asset.ticker.eq_ignore_ascii_case(ticker))
+            .map(|(address, _)| *address)
+    };
+    let mut routes = HashMap::new();
+    for (address, asset) in asset_map.iter() {
+        let Some(route) = asset.payout_route else {
+            continue;
+        };
+        let routing = match route {
+            PayoutRoute::AsIs => PayoutRouting::AsIs,
+            PayoutRoute::Retain => PayoutRouting::Retain,
+            PayoutRoute::Swap => {
+                let target = match &asset.payout_swap_to {
+                    Some(ticker) => address_of(ticker).ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "payout_swap_to {ticker} of {} is not an asset of the network",
+                            asset.ticker
+                        )
+                    })?,
+                    None => payout_token.ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "The swap payout_route of {} needs a payout_swap_to or a swap.payout_token",
+                            asset.ticker
+                        )
+                    })?,
+                };
+                if target == *address {
+                    PayoutRouting::AsIs
+                } else {
+                    PayoutRouting::SwapTo(target)
+                }
+            }
+        };
+        routes.insert(*address, routing);
+    }
+    Ok(routes)
+}
+
+impl Asset {
+    /// Converts an amount in tokens to the smallest unit of the token.
+    fn to_token_amount(&self, amount: &BigDecimal) -> U256 {
+        to_token_amount(amount, self.decimals)
+    }
+}
+
+/// Batching window of the distributions: earnings are collected for up to
+/// `batching_window_seconds` or `batching_max_liquidations` liquidations,
+/// then distributed at once.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct DistributionConfig {
+    pub batching_window_seconds: u64,
+    pub batching_max_liquidations: usize,
+    /// The window closes early once no liquidation was collected for this
+    /// long, so a cascade of liquidations is paid at once right after it ends
+    /// (0 disables it).
+    pub batching_idle_seconds: u64,
+    pub strategy: DistributionStrategy,
+    /// Distributions are held & an alert is sent when the highest score is
+    /// more than this many times the last trusted one (0 disables the guard).
+    pub max_highest_score_jump: f64,
+    /// Split of the earnings in basis points, summing to 10 000. The players
+    /// receive their part of `player_bps` weighted by their score, the world
+    /// the rest.
+    pub player_bps: u16,
+    pub world_bps: u16,
+    pub operator_bps: u16,
+    /// Address receiving the `operator_bps` fee of the bot operator.
+    pub operator_address: Option<String>,
+    /// Number of players of the leaderboard paid by the `leaderboard` strategy.
+    pub leaderboard_size: usize,
+    /// Runs the distributions by the seasons of the game: the payouts are
+    /// tagged with the running season, the leaderboard ranks the scores of the
+    /// season, and the dust & daily caps are reset when it ends.
+    pub seasons: bool,
+    /// Length of the epochs settled by the `epoch` strategy, aligned on the
+    /// unix epoch, e.g. 86400 settles every day at midnight UTC.
+    pub epoch_seconds: u64,
+    /// Players of the redeem queue with a lower score are skipped.
+    pub min_score: u128,
+    /// Players whose account was first seen by Torii less than this many
+    /// seconds ago are skipped (0 disables the rule).
+    pub min_account_age_seconds: u64,
+    /// Calls `record_payout` on the actions contract for each payout, so the
+    /// payouts are provable on-chain.
+    pub record_payouts_onchain: bool,
+    /// Reserves the redeem entries of the players on the actions contract in
+    /// a transaction of its own before paying them, released if the payout
+    /// fails, so replicas of the bot don't pay the same players.
+    pub reserve_redeems: bool,
+    /// Redeem entries older than this many seconds have their score halved
+    /// every `score_decay_half_life_seconds` past it (0 disables the decay).
+    pub score_decay_after_seconds: u64,
+    pub score_decay_half_life_seconds: u64,
+    /// A failed distribution is retried after this delay, doubled after each
+    /// failure up to `max_retry_backoff_seconds`.
+    pub retry_backoff_seconds: u64,
+    pub max_retry_backoff_seconds: u64,
+    /// A critical alert is sent once a distribution failed this many times in
+    /// a row, warnings before.
+    pub alert_after_failures: u32,
+    /// A distribution whose players' share is worth more than this value (in
+    /// USD, with the oracle prices) is refused & alerted, e.g. after a bug in
+    /// the parsing of the liquidation events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_player_share_usd: Option<BigDecimal>,
+}
+
+impl Default for DistributionConfig {
+    /// Distributes the earnings right after each liquidation.
+    fn default() -> Self {
+        Self {
+            batching_window_seconds: 0,
+            batching_max_liquidations: 1,
+            batching_idle_seconds: 0,
+            strategy: DistributionStrategy::Auto,
+            max_highest_score_jump: 10.0,
+            player_bps: 10_000,
+            world_bps: 0,
+            operator_bps: 0,
+            operator_address: None,
+            leaderboard_size: 10,
+            seasons: false,
+            epoch_seconds: 86_400,
+            min_score: 0,
+            min_account_age_seconds: 0,
+            record_payouts_onchain: false,
+            reserve_redeems: false,
+            score_decay_after_seconds: 0,
+            score_decay_half_life_seconds: 604_800,
+            retry_backoff_seconds: 30,
+            max_retry_backoff_seconds: 1_800,
+            alert_after_failures: 3,
+            max_player_share_usd: None,
+        }
+    }
+}
+
+/// How the players of the queue are paid.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DistributionStrategy {
+    /// Through the claims contract once the queue holds at least
+    /// `claims.queue_size_threshold` players, by transfers otherwise.
+    #[default]
+    Auto,
+    /// Always by a transfer to the next player of the queue.
+    Transfers,
+    /// Always through the claims contract.
+    Claims,
+    /// To the `leaderboard_size` best players, weighted by their score,
+    /// regardless of the redeem queue.
+    Leaderboard,
+    /// Each liquidation to a single player of the queue, drawn with a
+    /// probability proportional to its score.
+    Raffle,
+    /// Accrued in the bot account & settled between all the players of the
+    /// queue, weighted by their score, at the end of each `epoch_seconds`.
+    Epoch,
+}
+
+/// A game paid from the liquidations earnings of the bot, receiving
+/// `share_percentage`% of each liquidation. Each tenant has its own game
+/// mirror, pending distribution & payout ledger, stored next to the main
+/// storage. Without tenants, all the earnings go to the game of the network
+/// config.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TenantConfig {
+    pub name: String,
+    pub world_address: String,
+    pub torii_graphql_url: String,
+    #[serde(default)]
+    pub torii_fallback_urls: Vec<String>,
+    #[serde(default)]
+    pub claims_address: Option<String>,
+    #[serde(default)]
+    pub actions_address: Option<String>,
+    #[serde(default)]
+    pub strategy: DistributionStrategy,
+    pub share_percentage: u8,
+    /// Split of the earnings of the tenant, instead of the one of
+    /// `distribution`.
+    #[serde(default)]
+    pub split: Option<EarningsSplit>,
+    /// Models of the game of the tenant, instead of the ones of `torii`.
+    #[serde(default)]
+    pub redeem_model: Option<String>,
+    #[serde(default)]
+    pub leaderboard_model: Option<String>,
+    #[serde(default)]
+    pub season_leaderboard_model: Option<String>,
+}
+
+/// Split of the earnings in basis points, see [`DistributionConfig`].
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct EarningsSplit {
+    pub player_bps: u16,
+    pub world_bps: u16,
+    pub operator_bps: u16,
+}
+
+impl EarningsSplit {
+    pub fn total(&self) -> u32 {
+        [self.player_bps, self.world_bps, self.operator_bps]
+            .iter()
+            .map(|bps| *bps as u32)
+            .sum()
+    }
+
+    fn apply(&self, distribution: &mut DistributionConfig) {
+        distribution.player_bps = self.player_bps;
+        distribution.world_bps = self.world_bps;
+        distribution.operator_bps = self.operator_bps;
+    }
+}
+
+/// Restart policy of the services: a failing service is restarted with an
+/// exponential backoff, and the bot stops after `max_consecutive_failures`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SupervisorConfig {
+    pub max_consecutive_failures: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            max_consecutive_failures: 5,
+            initial_backoff_ms: 1_000,
+            max_backoff_ms: 60_000,
+        }
+    }
+}
+
+/// Notifications sent by the bot & the channels they are routed to.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct NotificationsConfig {
+    pub channels: Vec<NotificationChannelConfig>,
+    /// A warning is sent when the bot's STRK balance goes below this amount,
+    /// unless STRK sets its own `low_balance_threshold`.
+    pub low_balance_threshold: f64,
+    /// A warning is sent when a price has not been updated for this long.
+    pub stale_price_seconds: u64,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            channels: vec![],
+            low_balance_threshold: 10.0,
+            stale_price_seconds: 60,
+        }
+    }
+}
+
+/// A channel receiving the notifications of the given severities.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct NotificationChannelConfig {
+    #[serde(flatten)]
+    pub backend: NotificationBackendConfig,
+    pub severities: Vec<Severity>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum NotificationBackendConfig {
+    Telegram { bot_token: String, chat_id: String },
+    Discord { webhook_url: String },
+}
+
+/// Latency objectives of the pipeline, e.g. 95% of the liquidations executed
+/// within `liquidation_latency_seconds` of the position becoming liquidable.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct SloConfig {
+    pub liquidation_latency_seconds: u64,
+    pub payout_latency_seconds: u64,
+    /// Share of the events that must meet the latency objectives.
+    pub target: f64,
+    /// Sliding window over which the objectives are computed.
+    pub window_seconds: u64,
+    /// An alert is sent when the error budget burns this many times faster than allowed.
+    pub burn_rate_alert: f64,
+}
+
+impl Default for SloConfig {
+    fn default() -> Self {
+        Self {
+            liquidation_latency_seconds: 30,
+            payout_latency_seconds: 600,
+            target: 0.95,
+            window_seconds: 3600,
+            burn_rate_alert: 2.0,
+        }
+    }
+}
+
+/// Sync of the local mirror of the game models indexed by Torii.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct GameSyncConfig {
+    pub interval_seconds: u64,
+    /// The mirror is only used when Torii is down if it is more recent than this.
+    pub max_mirror_age_seconds: u64,
+    /// The distributions read the game state from the mirror instead of
+    /// Torii when it is more recent than this (0 always queries Torii).
+    pub cache_ttl_seconds: u64,
+}
+
+impl Default for GameSyncConfig {
+    fn default() -> Self {
+        Self {
+            interval_seconds: 10,
+            max_mirror_age_seconds: 300,
+            cache_ttl_seconds: 30,
+        }
+    }
+}
+
+/// Failover between the Torii endpoints of the network: `torii_graphql_url`
+/// & `torii_fallback_urls`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ToriiConfig {
+    /// The endpoints are checked at most this often, on the next query.
+    pub health_check_interval_seconds: u64,
+    /// An endpoint whose last indexed event is older than the best endpoint's
+    /// by more than this is stale, & only queried if all the others fail.
+    pub max_head_lag_seconds: u64,
+    /// Endpoint of the relay of Torii the payouts are published to as signed
+    /// messages to the players. None publishes no message.
+    pub message_url: Option<String>,
+    /// Model of the payout messages declared by the game, as
+    /// `namespace-Model`.
+    pub payout_message_model: String,
+    /// GraphQL connections of the models of the game read by the bot, e.g.
+    /// `arcadeRedeemModels` for a model of the `arcade` namespace: the redeem
+    /// queue, the highest scores of the players & their scores per season.
+    pub redeem_model: String,
+    pub leaderboard_model: String,
+    pub season_leaderboard_model: String,
+}
+
+impl Default for ToriiConfig {
+    fn default() -> Self {
+        Self {
+            health_check_interval_seconds: 30,
+            max_head_lag_seconds: 60,
+            message_url: None,
+            payout_message_model: "liquidator-PayoutMessage".to_string(),
+            redeem_model: "redeemModels".to_string(),
+            leaderboard_model: "highestScoreModels".to_string(),
+            season_leaderboard_model: "seasonScoreModels".to_string(),
+        }
+    }
+}
+
+/// Large distributions are published as a merkle root to the claims contract
+/// instead of being sent as transfers.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ClaimsConfig {
+    /// The whole redeem queue is paid through a claims root once it holds at
+    /// least this many players. Requires a `claims_address` for the network.
+    pub queue_size_threshold: usize,
+}
+
+impl Default for ClaimsConfig {
+    fn default() -> Self {
+        Self {
+            queue_size_threshold: 50,
+        }
+    }
+}
+
+/// Local HTTP API of the bot.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ApiConfig {
+    pub listen_address: String,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            listen_address: "127.0.0.1:3000".to_string(),
+        }
+    }
+}
+
+/// gRPC control plane of the bot, for the backend services of the game, see
+/// [`crate::services::control::ControlService`].
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct GrpcConfig {
+    pub enabled: bool,
+    pub listen_address: String,
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen_address: "127.0.0.1:50051".to_string(),
+        }
+    }
+}
+
+/// Retries of the RPC calls refreshing a position. Positions received from
+/// the indexer that still fail after `max_attempts` are moved to a dead-letter
+/// list & retried on each monitoring round.
+/// The monitored positions are refreshed by batches of `batch_size` positions
+/// per RPC round-trip.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct PositionUpdateConfig {
+    pub max_attempts: u32,
+    /// Delay before the second attempt, doubled after each failure.
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub batch_size: usize,
+}
+
+impl Default for PositionUpdateConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 10_000,
+            batch_size: 100,
+        }
+    }
+}
+
+/// Liquidations failing for a transient reason, e.g. an RPC failure or a fee
+/// spike, are kept in a dead-letter queue & retried with an exponential
+/// backoff, then abandoned after `max_attempts` failures.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct LiquidationRetryConfig {
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled after each failure.
+    pub initial_backoff_seconds: u64,
+    pub max_backoff_seconds: u64,
+}
+
+impl Default for LiquidationRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff_seconds: 10,
+            max_backoff_seconds: 600,
+        }
+    }
+}
+
+/// Cooldown of a position after each liquidation attempt, whether it failed
+/// or only partially liquidated the position, so it isn't re-attempted on
+/// every check. Disabled by default.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct LiquidationCooldownConfig {
+    /// Delay before the position can be liquidated again, 0 to disable the
+    /// cooldown.
+    pub cooldown_seconds: u64,
+    /// Attempts after which the position is left alone until it is healthy
+    /// again, 0 for no limit.
+    pub max_attempts: u32,
+}
+
+/// Estimation of the price impact of selling the collateral seized by the
+/// large liquidations back to their debt, quoted by the `swap.providers`.
+/// Disabled by default.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct PriceImpactConfig {
+    pub enabled: bool,
+    /// Only the liquidations repaying at least this much debt, in USD, are
+    /// estimated.
+    pub min_debt_value_usd: BigDecimal,
+    /// A liquidation losing more than this many basis points of the value of
+    /// its collateral to the swap isn't profitable.
+    pub max_impact_bps: u16,
+    /// A liquidation above `max_impact_bps` is split in up to this many
+    /// tranches: the largest one within the bound is liquidated, the rest the
+    /// next rounds. 1 doesn't split the liquidations.
+    pub max_tranches: usize,
+}
+
+impl Default for PriceImpactConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_debt_value_usd: BigDecimal::from(10_000),
+            max_impact_bps: 200,
+            max_tranches: 1,
+        }
+    }
+}
+
+/// Runbook actions of the operators over the running bot, through the
+/// `/operator` routes of the API, see
+/// [`crate::services::operator::Operator`]. Disabled without an `api_token`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct OperatorConfig {
+    /// Bearer token of the `/operator` routes.
+    pub api_token: Option<String>,
+    /// Address the funds of the bot are swept to in an emergency.
+    pub cold_address: Option<String>,
+    /// Tickers of the assets swept, all the assets of the network if empty.
+    pub sweep_assets: Vec<String>,
+    /// STRK left to the bot by a sweep, to pay its fees.
+    pub strk_fee_reserve: BigDecimal,
+}
+
+impl Default for OperatorConfig {
+    fn default() -> Self {
+        Self {
+            api_token: None,
+            cold_address: None,
+            sweep_assets: vec![],
+            strk_fee_reserve: BigDecimal::from(5),
+        }
+    }
+}
+
+/// Sources of the oracle prices & how long they can be trusted.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct OracleConfig {
+    /// Websocket streaming the prices in real time, on top of the polling of
+    /// the price sources.
+    pub stream_url: Option<String>,
+    /// Positions are not liquidated based on prices older than this.
+    pub max_price_age_seconds: u64,
+    /// Default price sources of the assets, the first one being the primary.
+    /// The price is the median of the sources.
+    pub sources: Vec<PriceSourceConfig>,
+    /// The primary source is ignored when it deviates from the other sources
+    /// by more than this percentage.
+    pub max_deviation_percent: f64,
+    /// An asset whose price jumps by more than this percentage between two
+    /// updates is suspect: its liquidations are paused until a second source
+    /// corroborates its price or it is resumed from the API.
+    pub max_jump_percent: Option<f64>,
+    pub pragma_api_url: String,
+    pub pragma_api_key: Option<String>,
+}
+
+impl Default for OracleConfig {
+    fn default() -> Self {
+        Self {
+            stream_url: None,
+            max_price_age_seconds: 30,
+            sources: vec![PriceSourceConfig::PragmaOnchain],
+            max_deviation_percent: 5.0,
+            max_jump_percent: None,
+            pragma_api_url: "https://api.dev.pragma.build".to_string(),
+            pragma_api_key: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PriceSourceConfig {
+    /// The Pragma oracle contract.
+    PragmaOnchain,
+    /// The Pragma offchain API.
+    PragmaApi,
+    /// A fixed price, e.g. for a stablecoin or a devnet token.
+    Static { price: BigDecimal },
+}
+
+/// Positions are re-checked when the price of their collateral or debt moves
+/// by more than `price_move_threshold_bps`, and all of them periodically: the
+/// interval of these full checks is halved down to `min_check_interval_seconds`
+/// while prices move or positions are close to a liquidation, and doubled up
+/// to `full_check_interval_seconds` while the book is healthy.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct MonitoringConfig {
+    pub price_move_threshold_bps: u32,
+    pub full_check_interval_seconds: u64,
+    pub min_check_interval_seconds: u64,
+    /// The interval is tightened while at least this many positions are
+    /// liquidable or almost.
+    pub near_liquidation_positions: usize,
+    /// Positions with a health factor within this many basis points of 1 are
+    /// on a watchlist, refreshed & checked every
+    /// `watchlist_check_interval_seconds`.
+    pub watchlist_margin_bps: u32,
+    pub watchlist_check_interval_seconds: u64,
+    /// Positions with a health factor within this many basis points of 1 are
+    /// reported at risk to their owner, through the notifications & the event
+    /// feed. 0 disables the warnings.
+    pub at_risk_margin_bps: u32,
+    /// Interval at which the positions closed for more than
+    /// `prune_closed_after_blocks` blocks are pruned from the storage.
+    pub prune_interval_seconds: u64,
+    pub prune_closed_after_blocks: u64,
+    /// Where the debt repaid by the liquidations is borrowed from.
+    pub flash_loan: FlashLoanSource,
+    /// Liquidations are not attempted while the STRK balance of the bot is
+    /// below its low balance threshold, as they couldn't pay their fees.
+    pub pause_on_low_fee_balance: bool,
+    /// The stored positions are refreshed against the singleton on startup,
+    /// the ones closed while the bot was down being removed.
+    pub reconcile_on_startup: bool,
+    /// On startup, the monitoring waits up to this long for a price of every
+    /// asset, updated or restored from the storage, then starts without the
+    /// missing ones.
+    pub prices_ready_timeout_seconds: u64,
+    /// Events the indexer can queue for the monitoring service. Beyond that,
+    /// the indexer waits for the monitoring to catch up.
+    pub indexer_channel_capacity: usize,
+    /// All the positions are checked at this interval. In between, the checks
+    /// only visit the shards of the assets whose price moved, 0 sweeping them
+    /// all at every check.
+    pub full_sweep_interval_seconds: u64,
+    /// The positions & the on-chain prices are read at the pre-confirmed
+    /// block & the indexer streams the pending blocks, reacting to the
+    /// position updates & price moves a block earlier. Otherwise, only the
+    /// blocks accepted on L2 are followed.
+    pub use_pending_block: bool,
+    /// Block tag the positions are refreshed at, overriding
+    /// `use_pending_block`.
+    pub positions_block_tag: Option<ReadBlockTag>,
+    /// Block tag the on-chain prices are read at, overriding
+    /// `use_pending_block`, e.g. `latest` for prices safe from reorgs.
+    pub oracle_block_tag: Option<ReadBlockTag>,
+}
+
+impl MonitoringConfig {
+    /// Block the positions & the on-chain prices are read at by default, see
+    /// `use_pending_block`.
+    pub fn block_id(&self) -> BlockId {
+        self.read_tag(None).block_id()
+    }
+
+    /// Block the positions are refreshed at, see `positions_block_tag`.
+    pub fn positions_block_id(&self) -> BlockId {
+        self.read_tag(self.positions_block_tag).block_id()
+    }
+
+    /// Block the on-chain prices are read at, see `oracle_block_tag`.
+    pub fn oracle_block_id(&self) -> BlockId {
+        self.read_tag(self.oracle_block_tag).block_id()
+    }
+
+    /// Returns true if the positions & the on-chain prices are read at
+    /// different block tags.
+    pub fn mixes_block_tags(&self) -> bool {
+        self.read_tag(self.positions_block_tag) != self.read_tag(self.oracle_block_tag)
+    }
+
+    /// Returns the tag if set, the one of `use_pending_block` otherwise.
+    fn read_tag(&self, tag: Option<ReadBlockTag>) -> ReadBlockTag {
+        tag.unwrap_or(if self.use_pending_block {
+            ReadBlockTag::PreConfirmed
+        } else {
+            ReadBlockTag::Latest
+        })
+    }
+}
+
+/// Block tag of the reads of a category of calls: the freshest state, or the
+/// state of the last block accepted on L2, safer from reorgs.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadBlockTag {
+    PreConfirmed,
+    Latest,
+}
+
+impl ReadBlockTag {
+    pub fn block_id(self) -> BlockId {
+        match self {
+            ReadBlockTag::PreConfirmed => BlockId::Tag(BlockTag::PreConfirmed),
+            ReadBlockTag::Latest => BlockId::Tag(BlockTag::Latest),
+        }
+    }
+}
+
+/// Source of the debt repaid by a liquidation, so the bot doesn't need to
+/// hold the debt token: the loan is repaid from the seized collateral in the
+/// same transaction.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FlashLoanSource {
+    /// Borrowed from the Ekubo pools by the Liquidate contract, swapping the
+    /// seized collateral back to the debt.
+    #[default]
+    Ekubo,
+    /// Borrowed from the Vesu singleton by the `flash_loan_receiver_address`
+    /// contract, running the liquidation call passed as data.
+    Vesu,
+}
+
+impl Default for MonitoringConfig {
+    fn default() -> Self {
+        Self {
+            price_move_threshold_bps: 10,
+            full_check_interval_seconds: 30,
+            min_check_interval_seconds: 5,
+            near_liquidation_positions: 1,
+            watchlist_margin_bps: 500,
+            watchlist_check_interval_seconds: 2,
+            at_risk_margin_bps: 0,
+            prune_interval_seconds: 600,
+            prune_closed_after_blocks: 1_000,
+            flash_loan: FlashLoanSource::Ekubo,
+            pause_on_low_fee_balance: false,
+            reconcile_on_startup: true,
+            prices_ready_timeout_seconds: 30,
+            indexer_channel_capacity: 10_000,
+            full_sweep_interval_seconds: 300,
+            use_pending_block: true,
+            positions_block_tag: None,
+            oracle_block_tag: None,
+        }
+    }
+}
+
+/// Periodic reconciliation of the payout ledger against the outgoing ERC20
+/// transfers of the bot account.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ReconciliationConfig {
+    pub interval_seconds: u64,
+    /// Blocks scanned at most per round, the rest is left to the next rounds.
+    pub max_blocks_per_round: u64,
+    /// Recipients of transfers of the bot that are not payouts, never flagged
+    /// as unexpected outflows, e.g. the exchange contract of AVNU pulling the
+    /// tokens sold by the swaps. The contracts pulling the debt repaid by the
+    /// liquidations & `swap.ekubo_router_address` are always ignored.
+    pub ignored_recipients: Vec<String>,
+}
+
+impl Default for ReconciliationConfig {
+    fn default() -> Self {
+        Self {
+            interval_seconds: 300,
+            max_blocks_per_round: 10_000,
+            ignored_recipients: vec![],
+        }
+    }
+}
+
+/// Tracking of the finality on L1 of the liquidations & the payouts of the
+/// bot, see [`crate::services::finality::FinalityService`].
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct FinalityConfig {
+    pub enabled: bool,
+    /// Interval at which the transactions accepted on L2 are checked.
+    pub interval_seconds: u64,
+    /// Holds the transfers to the world of each distribution until the
+    /// liquidations it pays are accepted on L1, sending them in a transaction
+    /// of their own.
+    pub hold_world_share: bool,
+}
+
+impl Default for FinalityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_seconds: 600,
+            hold_world_share: false,
+        }
+    }
+}
+
+/// Watchdog of the indexer, comparing the last block it processed to the head
+/// of the chain so a stalled stream doesn't silently stop the new positions
+/// from being tracked.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    /// Interval of the checks, 0 disables the watchdog.
+    pub interval_seconds: u64,
+    /// The indexer is lagging beyond this many blocks behind the head.
+    pub max_lag_blocks: u64,
+    /// Restarts the indexer when it lags without processing any block since
+    /// the previous check, resuming from its last block.
+    pub restart_indexer: bool,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            interval_seconds: 60,
+            max_lag_blocks: 20,
+            restart_indexer: false,
+        }
+    }
+}
+
+/// Election of the instance sending the transactions among replicas of the
+/// bot run in hot standby, through a lease they share in Redis.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct LeaderElectionConfig {
+    pub enabled: bool,
+    /// Redis server holding the lease, e.g. `redis://:password@host:6379/0`,
+    /// or `rediss://` over TLS.
+    pub redis_url: Option<String>,
+    /// Key of the lease in Redis.
+    pub redis_key: String,
+    /// Holder of the lease, the hostname & the pid of the process if unset.
+    pub instance_id: Option<String>,
+    pub heartbeat_interval_seconds: u64,
+    /// A follower takes over once the lease was not renewed for this long.
+    pub lease_ttl_seconds: u64,
+}
+
+impl Default for LeaderElectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            redis_url: None,
+            redis_key: "vesu-liquidator:leader".to_string(),
+            instance_id: None,
+            heartbeat_interval_seconds: 2,
+            lease_ttl_seconds: 10,
+        }
+    }
+}
+
+/// Handling of the transactions that are not accepted in time, e.g. when
+/// underpriced or dropped: they are re-sent with the same nonce & gas prices
+/// bumped by `fee_bump_factor`, at most `max_resubmissions` times.
+///
+/// With extra accounts (`--extra-account`), the liquidations are rotated
+/// across the accounts so a pending transaction doesn't hold the others.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct TransactionsConfig {
+    pub timeout_seconds: u64,
+    pub fee_bump_factor: f64,
+    pub max_resubmissions: u32,
+    /// Accounts with less STRK than this are left out of the rotation while
+    /// another one is funded.
+    pub min_account_fee_balance: f64,
+    /// Interval at which the nonces & STRK balances of the accounts are
+    /// refreshed from the chain.
+    pub account_refresh_seconds: u64,
+    /// Token the fees are preferably paid in.
+    pub fee_token: FeeToken,
+    /// Pays the fees in the other token while the balance of `fee_token` is
+    /// low & the other one isn't.
+    pub fee_token_fallback: bool,
+}
+
+impl Default for TransactionsConfig {
+    fn default() -> Self {
+        Self {
+            timeout_seconds: 30,
+            fee_bump_factor: 1.25,
+            max_resubmissions: 3,
+            min_account_fee_balance: 1.0,
+            account_refresh_seconds: 300,
+            fee_token: FeeToken::default(),
+            fee_token_fallback: false,
+        }
+    }
+}
+
+/// Token the transaction fees are paid in. The fees in STRK are paid by the
+/// v3 transactions of the account & the fees in ETH through the AVNU
+/// paymaster, see [`PaymasterConfig`], as the network no longer accepts the
+/// v1 transactions paying their fees in ETH.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeToken {
+    #[default]
+    Strk,
+    Eth,
+}
+
+impl FeeToken {
+    pub fn ticker(&self) -> &'static str {
+        match self {
+            FeeToken::Strk => "STRK",
+            FeeToken::Eth => "ETH",
+        }
+    }
+
+    /// Returns the token the fees fall back to.
+    pub fn other(&self) -> FeeToken {
+        match self {
+            FeeToken::Strk => FeeToken::Eth,
+            FeeToken::Eth => FeeToken::Strk,
+        }
+    }
+}
+
+/// Swap of the earned collateral to a single payout token, e.g. USDC, before
+/// paying the players. The swap is sent in the same multicall as the payout.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct SwapConfig {
+    /// Ticker of the token the players are paid in, `None` pays the
+    /// liquidated collateral as is.
+    pub payout_token: Option<String>,
+    /// Providers quoting the swap, in order of preference: the next one is
+    /// tried when a provider is down or its quote is too wide.
+    pub providers: Vec<SwapProviderKind>,
+    /// The swap reverts if it receives less than the quote minus this many
+    /// basis points.
+    pub max_slippage_bps: u16,
+    /// Quotes losing more than this many basis points of the market value
+    /// are skipped, when the provider reports it.
+    pub max_price_impact_bps: u16,
+    pub avnu_api_url: String,
+    /// Quoter API of the Ekubo routes, for the liquidation swaps & the direct
+    /// Ekubo payout swaps.
+    pub ekubo_quoter_url: String,
+    /// Router of the direct Ekubo swaps.
+    pub ekubo_router_address: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapProviderKind {
+    /// The AVNU aggregator.
+    Avnu,
+    /// A direct swap through the pools of the Ekubo router.
+    Ekubo,
+}
+
+/// Payment of the transaction fees in an asset, e.g. USDC, through the AVNU
+/// paymaster instead of STRK. While the paymaster fails, the transactions are
+/// executed normally, paying their fees in STRK.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct PaymasterConfig {
+    pub enabled: bool,
+    pub api_url: String,
+    /// Key of a sponsored AVNU account, the fees being paid by the sponsor.
+    pub api_key: Option<String>,
+    /// Ticker of the asset the fees are paid in.
+    pub gas_token: String,
+    /// Max amount of the gas token paid per transaction, in tokens.
+    pub max_gas_token_amount: Option<BigDecimal>,
+    /// After a failure of the paymaster, it is skipped for this long.
+    pub retry_after_seconds: u64,
+}
+
+impl Default for PaymasterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            api_url: "https://starknet.api.avnu.fi".to_string(),
+            api_key: None,
+            gas_token: "USDC".to_string(),
+            max_gas_token_amount: None,
+            retry_after_seconds: 300,
+        }
+    }
+}
+
+/// Protection of the liquidations against the fee spikes: the L2 gas price of
+/// the latest blocks is tracked, and while its median over `window_blocks`
+/// exceeds `max_l2_gas_price` the non-urgent liquidations are held back
+/// according to `action`, until the fees normalize.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct GasGuardConfig {
+    pub enabled: bool,
+    /// Cap of the L2 gas price, in fri per unit of gas.
+    pub max_l2_gas_price: u64,
+    /// Number of latest blocks whose median gas price is compared to the cap.
+    pub window_blocks: usize,
+    pub poll_interval_seconds: u64,
+    pub action: GasGuardAction,
+    /// Positions whose LTV exceeds their LLTV by at least this much are
+    /// liquidated whatever the fees, before they turn into bad debt.
+    pub urgent_ltv_excess: BigDecimal,
+    /// With the `raise_min_profit` action, min expected profit in USD of the
+    /// non-urgent liquidations during a fee spike.
+    pub spike_min_expected_profit_usd: BigDecimal,
+}
+
+impl Default for GasGuardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_l2_gas_price: 20_000_000_000,
+            window_blocks: 10,
+            poll_interval_seconds: 6,
+            action: GasGuardAction::default(),
+            urgent_ltv_excess: BigDecimal::new(5.into(), 2),
+            spike_min_expected_profit_usd: BigDecimal::from(1_000),
+        }
+    }
+}
+
+/// What happens to the non-urgent liquidations during a fee spike.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GasGuardAction {
+    /// Deferred until the fees normalize.
+    #[default]
+    Defer,
+    /// Only sent if their expected profit reaches
+    /// `spike_min_expected_profit_usd`.
+    RaiseMinProfit,
+}
+
+/// Budgets of the requests to the external HTTP APIs (Torii, prices, swap
+/// quotes), by host, so that a burst of liquidations can't trip their rate
+/// limits. The hosts without a budget are not limited.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct RateLimitsConfig {
+    #[serde(serialize_with = "sorted_map")]
+    pub hosts: HashMap<String, RateLimit>,
+    /// A request failing to get in the budget of its host within this delay
+    /// fails instead of waiting.
+    pub max_wait_ms: u64,
+}
+
+impl Default for RateLimitsConfig {
+    fn default() -> Self {
+        Self {
+            hosts: HashMap::new(),
+            max_wait_ms: 5_000,
+        }
+    }
+}
+
+/// Token bucket of a host: `burst` requests at once, refilled at
+/// `requests_per_second`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RateLimit {
+    pub requests_per_second: f64,
+    pub burst: u32,
+}
+
+/// Lending protocols whose positions are indexed, monitored & liquidated.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct ProtocolsConfig {
+    pub vesu: VesuProtocolConfig,
+    pub zklend: ZkLendProtocolConfig,
+}
+
+/// Vesu, through its singleton & the Liquidate contract of the network.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct VesuProtocolConfig {
+    pub enabled: bool,
+}
+
+impl Default for VesuProtocolConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// zkLend, through its market & the zTokens of its reserves. The debt of the
+/// liquidations is repaid from the bot's balance of the debt token.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct ZkLendProtocolConfig {
+    pub enabled: bool,
+    pub market_address: Option<String>,
+    /// zToken of each reserve tracked, by ticker of the underlying asset.
+    pub z_tokens: HashMap<String, String>,
+}
+
+impl ZkLendProtocolConfig {
+    fn validate(&self, asset_map: &HashMap<Felt, Asset>) -> Result<()> {
+        let market_address = self
+            .market_address
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("protocols.zklend.market_address is required"))?;
+        Felt::from_hex(market_address)
+            .map_err(|e| anyhow::anyhow!("Invalid protocols.zklend.market_address: {e}"))?;
+        anyhow::ensure!(
+            !self.z_tokens.is_empty(),
+            "protocols.zklend.z_tokens can't be empty"
+        );
+        for (ticker, z_token) in &self.z_tokens {
+            anyhow::ensure!(
+                asset_map
+                    .values()
+                    .any(|asset| asset.ticker.eq_ignore_ascii_case(ticker)),
+                "zkLend reserve {ticker} is not an asset of the network"
+            );
+            Felt::from_hex(z_token)
+                .map_err(|e| anyhow::anyhow!("Invalid zToken address of {ticker}: {e}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Vesu pools & assets whose positions are tracked by the indexer & the
+/// monitoring, e.g. to avoid exotic assets that can't be priced or
+/// liquidated profitably. Empty allowlists accept everything.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct FiltersConfig {
+    /// Ids of the pools tracked.
+    pub pools: Vec<String>,
+    pub excluded_pools: Vec<String>,
+    /// Tickers of the assets tracked, as collateral or debt.
+    pub assets: Vec<String>,
+    pub excluded_assets: Vec<String>,
+    /// Minimum value in USD of the collateral of a new position for it to be
+    /// tracked, valued with the oracle prices when it's indexed. Keeps the
+    /// RPC usage & the storage small by ignoring the small positions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_position_value_usd: Option<BigDecimal>,
+}
+
+impl FiltersConfig {
+    fn resolve(&self, asset_map: &HashMap<Felt, Asset>) -> Result<PositionFilter> {
+        let pools = |ids: &[String]| {
+            ids.iter()
+                .map(|id| {
+                    Felt::from_hex(id).map_err(|e| anyhow::anyhow!("Invalid pool id {id}: {e}"))
+                })
+                .collect::<Result<_>>()
+        };
+        let assets = |tickers: &[String]| {
+            tickers
+                .iter()
+                .map(|ticker| {
+                    asset_map
+                        .iter()
+                        .find(|(_, asset)| asset.ticker.eq_ignore_ascii_case(ticker))
+                        .map(|(address, _)| *address)
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "Filtered asset {ticker} is not an asset of the network"
+                            )
+                        })
+                })
+                .collect::<Result<_>>()
+        };
+        Ok(PositionFilter {
+            pools: pools(&self.pools)?,
+            excluded_pools: pools(&self.excluded_pools)?,
+            assets: assets(&self.assets)?,
+            excluded_assets: assets(&self.excluded_assets)?,
+            min_value_usd: self.min_position_value_usd.clone(),
+        })
+    }
+}
+
+/// Name of a pool, e.g. the Vesu "Genesis" pool, tagging its positions in the
+/// storage & in the exposure views.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PoolConfig {
+    pub id: String,
+    pub name: String,
+}
+
+/// Allowances of the bot to the Liquidate contract on the debt tokens. They
+/// are checked at startup & before each liquidation, an approval being added
+/// to the liquidation multicall when the allowance is below the debt to repay.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct AllowancesConfig {
+    pub enabled: bool,
+    /// Approves the max amount instead of the `approval_amount` of the asset
+    /// or the debt to repay, saving the approvals of the next liquidations.
+    pub unlimited: bool,
+}
+
+impl Default for AllowancesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            unlimited: false,
+        }
+    }
+}
+
+impl Default for SwapConfig {
+    fn default() -> Self {
+        Self {
+            payout_token: None,
+            providers: vec![SwapProviderKind::Avnu, SwapProviderKind::Ekubo],
+            max_slippage_bps: 100,
+            max_price_impact_bps: 300,
+            avnu_api_url: "https://starknet.api.avnu.fi".to_string(),
+            ekubo_quoter_url: "https://quoter-mainnet-api.ekubo.org".to_string(),
+            ekubo_router_address:
+                "0x0199741822c2dc722f6f605204f35e56dbc23bceed54818168c4c49e4fb8737e".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde_json::json;
+
+    use super::{apply_env_overrides, load_raw_config, redact_secrets};
+    use crate::cli::NetworkName;
+
+    fn override_with(mut config: serde_json::Value, vars: &[(&str, &str)]) -> serde_json::Value {
+        let vars = vars
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()));
+        apply_env_overrides(&mut config, vars);
+        config
+    }
+
+    #[test]
+    fn test_network_profile_layering() {
+        let path = std::env::temp_dir().join(format!(
+            "vesu-liquidator-{}-config-layering.yaml",
+            std::process::id()
+        ));
+        std::fs::write(
+            &path,
+            "vesu:\n  mainnet:\n    torii_graphql_url: \"https://my-torii.xyz/graphql\"\n  katana:\nassets: []\n",
+        )
+        .unwrap();
+
+        // The file wins over the profile, which fills the rest.
+        let config = load_raw_config(&path, NetworkName::Mainnet).unwrap();
+        let mainnet = &config["vesu"]["mainnet"];
+        assert_eq!(mainnet["torii_graphql_url"], "https://my-torii.xyz/graphql");
+        assert_eq!(mainnet["apibara_url"], "https://mainnet.starknet.a5a.ch");
+        // An empty section is filled too.
+        let config = load_raw_config(&path, NetworkName::Katana).unwrap();
+        assert_eq!(
+            config["vesu"]["katana"]["apibara_url"],
+            "http://localhost:7171"
+        );
+        // As well as a missing one.
+        let config = load_raw_config(&path, NetworkName::Sepolia).unwrap();
+        assert_eq!(
+            config["vesu"]["sepolia"]["apibara_url"],
+            "https://sepolia.starknet.a5a.ch"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let config = json!({
+            "notifications": { "stale_price_seconds": 300, "enabled": true },
+            "vesu": { "mainnet": { "world_address": "0x1" } },
+            "filters": { "pools": [] },
+            "operator": { "api_token": null },
+            "assets": [{ "ticker": "ETH", "min_payout": "0.1" }],
+        });
+        let config = override_with(
+            config,
+            &[
+                ("LIQUIDATOR__NOTIFICATIONS__STALE_PRICE_SECONDS", "120"),
+                ("LIQUIDATOR__NOTIFICATIONS__ENABLED", "false"),
+                // Kept as a string, like the field.
+                ("LIQUIDATOR__VESU__MAINNET__WORLD_ADDRESS", "123"),
+                ("LIQUIDATOR__FILTERS__POOLS", r#"["0x2"]"#),
+                ("LIQUIDATOR__OPERATOR__API_TOKEN", "s3cret"),
+                ("LIQUIDATOR__ASSETS__0__MIN_PAYOUT", "0.5"),
+                ("OTHER__NOTIFICATIONS__ENABLED", "true"),
+            ],
+        );
+        assert_eq!(
+            config,
+            json!({
+                "notifications": { "stale_price_seconds": 120, "enabled": false },
+                "vesu": { "mainnet": { "world_address": "123" } },
+                "filters": { "pools": ["0x2"] },
+                "operator": { "api_token": "s3cret" },
+                "assets": [{ "ticker": "ETH", "min_payout": "0.5" }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_env_overrides_ignored() {
+        let config = json!({
+            "notifications": { "stale_price_seconds": 300 },
+            "filters": { "pools": [] },
+            "assets": [{ "ticker": "ETH" }],
+        });
+        let overridden = override_with(
+            config.clone(),
+            &[
+                // Typos.
+                ("LIQUIDATOR__NOTIFICATIONS__STALE_PRICE_SECOND", "120"),
+                ("LIQUIDATOR__NOTIFICATION__STALE_PRICE_SECONDS", "120"),
+                // Below a scalar, or out of an array.
+                (
+                    "LIQUIDATOR__NOTIFICATIONS__STALE_PRICE_SECONDS__VALUE",
+                    "120",
+                ),
+                ("LIQUIDATOR__ASSETS__1__TICKER", "STRK"),
+                // Not matching the shape of the field.
+                (
+                    "LIQUIDATOR__NOTIFICATIONS__STALE_PRICE_SECONDS",
+                    r#"{"value": 120}"#,
+                ),
+                ("LIQUIDATOR__FILTERS__POOLS", "0x2"),
+            ],
+        );
+        assert_eq!(overridden, config);
+    }
+
+    #[test]
+    fn test_redact_secrets() {
+        let mut config = json!({
+            "notifications": {
+                "channels": [
+                    { "telegram": { "bot_token": "123:abc", "chat_id": "42" } },
+                    { "discord": { "webhook_url": "https://discord.com/api/webhooks/1" } },
+                ],
+            },
+            "oracle": { "pragma_api_key": "key", "max_price_age_seconds": 60 },
+            "operator": { "api_token": "s3cret", "api_token_hint": "ask ops" },
+        });
+        redact_secrets(&mut config);
+        assert_eq!(
+            config,
+            json!({
+                "notifications": {
+                    "channels": [
+                        { "telegram": { "bot_token": "<redacted>", "chat_id": "42" } },
+                        { "discord": { "webhook_url": "<redacted>" } },
+                    ],
+                },
+                "oracle": { "pragma_api_key": "<redacted>", "max_price_age_seconds": 60 },
+                "operator": { "api_token": "<redacted>", "api_token_hint": "ask ops" },
+            })
+        );
+    }
+
+    #[test]
+    fn test_toml_sample_matches_yaml() {
+        for network in [
+            NetworkName::Mainnet,
+            NetworkName::Sepolia,
+            NetworkName::Katana,
+        ] {
+            let yaml = load_raw_config(Path::new("./config.yaml"), network).unwrap();
+            let toml = load_raw_config(Path::new("./config.toml"), network).unwrap();
+            assert_eq!(toml, yaml, "config.toml differs from config.yaml");
+        }
+    }
 }
@@ -0,0 +1,10 @@
+#[rustfmt::skip]
+pub mod bindings;
+pub mod cli;
+pub mod commands;
+pub mod config;
+pub mod protocols;
+pub mod services;
+pub mod storages;
+pub mod types;
+pub mod utils;
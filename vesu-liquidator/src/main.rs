@@ -1,55 +1,46 @@
-#[rustfmt::skip]
-pub mod bindings;
-pub mod cli;
-pub mod config;
-pub mod services;
-pub mod storages;
-pub mod types;
-pub mod utils;
-
-use std::sync::Arc;
-
 use anyhow::Result;
 use clap::Parser;
-use starknet::{
-    core::types::Felt,
-    providers::{JsonRpcClient, jsonrpc::HttpTransport},
-};
 
-use cli::{NetworkName, RunCmd};
-use config::Config;
-use services::start_all_services;
-use types::account::StarknetAccount;
-use utils::setup_tracing;
+use vesu_liquidator::{
+    cli::{Cli, Command},
+    commands,
+    utils::{setup_tracing, telemetry::shutdown_telemetry},
+};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let _ = dotenvy::dotenv();
-    setup_tracing();
+    let cli = Cli::parse();
+    setup_tracing(cli.log_format, cli.otlp_endpoint.as_deref())?;
 
-    let mut run_cmd = RunCmd::parse();
-    run_cmd.validate()?;
-
-    print_app_title(run_cmd.account_params.account_address, run_cmd.network);
-
-    let rpc_url = run_cmd.rpc_url.clone();
-    let rpc_client = Arc::new(JsonRpcClient::new(HttpTransport::new(rpc_url)));
-    let account = StarknetAccount::from_cli(rpc_client.clone(), run_cmd.clone())?;
-
-    let config = Config::from_cli(&run_cmd)?;
-    start_all_services(config, rpc_client, account, run_cmd).await
+    let result = run_command(cli.command).await;
+    shutdown_telemetry();
+    result
 }
 
-/// Prints information about the bot parameters.
-fn print_app_title(account_address: Felt, network: NetworkName) {
-    println!("\n
-██╗   ██╗███████╗███████╗██╗   ██╗    ██╗     ██╗ ██████╗ ██╗   ██╗██╗██████╗  █████╗ ████████╗ ██████╗ ██████╗
-██║   ██║██╔════╝██╔════╝██║   ██║    ██║     ██║██╔═══██╗██║   ██║██║██╔══██╗██╔══██╗╚══██╔══╝██╔═══██╗██╔══██╗
-██║   ██║█████╗  ███████╗██║   ██║    ██║     ██║██║   ██║██║   ██║██║██║  ██║███████║   ██║   ██║   ██║██████╔╝
-╚██╗ ██╔╝██╔══╝  ╚════██║██║   ██║    ██║     ██║██║▄▄ ██║██║   ██║██║██║  ██║██╔══██║   ██║   ██║   ██║██╔══██╗
- ╚████╔╝ ███████╗███████║╚██████╔╝    ███████╗██║╚██████╔╝╚██████╔╝██║██████╔╝██║  ██║   ██║   ╚██████╔╝██║  ██║
-  ╚═══╝  ╚══════╝╚══════╝ ╚═════╝     ╚══════╝╚═╝ ╚══▀▀═╝  ╚═════╝ ╚═╝╚═════╝ ╚═╝  ╚═╝   ╚═╝    ╚═════╝ ╚═╝  ╚═╝
-
-  🤖 Liquidator 👉 0x{:x}
-  🎯 On {}", account_address, network);
+async fn run_command(command: Command) -> Result<()> {
+    match command {
+        Command::Run(run_cmd) => {
+            #[cfg(windows)]
+            if run_cmd.windows_service {
+                return vesu_liquidator::utils::windows::run_as_service(run_cmd).await;
+            }
+            commands::run::run(run_cmd).await
+        }
+        Command::Status(status_cmd) => commands::status::status(status_cmd).await,
+        Command::Replay(replay_cmd) => commands::replay::replay(replay_cmd).await,
+        Command::Backfill(backfill_cmd) => commands::backfill::backfill(backfill_cmd).await,
+        Command::Backtest(backtest_cmd) => commands::backtest::backtest(backtest_cmd).await,
+        Command::Liquidate(liquidate_cmd) => commands::liquidate::liquidate(liquidate_cmd).await,
+        Command::Distribute(distribute_cmd) => {
+            commands::distribute::distribute(distribute_cmd).await
+        }
+        Command::Scan(scan_cmd) => commands::scan::scan(scan_cmd).await,
+        Command::Exposure(exposure_cmd) => commands::exposure::exposure(exposure_cmd).await,
+        Command::Pnl(pnl_cmd) => commands::pnl::pnl(pnl_cmd).await,
+        Command::History(history_cmd) => commands::history::history(history_cmd).await,
+        Command::Export(export_cmd) => commands::export::export(export_cmd).await,
+        Command::Snapshot(snapshot_cmd) => commands::snapshot::snapshot(snapshot_cmd).await,
+        Command::Operator(operator_cmd) => commands::operator::operator(operator_cmd).await,
+    }
 }
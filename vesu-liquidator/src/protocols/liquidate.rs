@@ -0,0 +1,259 @@
+use cainome::cairo_serde::{CairoSerde, ContractAddress, U256};
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{Call, Event, Felt};
+
+use crate::{
+    bindings::{
+        liquidate::{Event as LiquidateEventV1, LiquidateParams as LiquidateParamsV1, Swap},
+        liquidate_v2::{
+            Event as LiquidateEventV2, LiquidateParams as LiquidateParamsV2, Swap as SwapV2,
+        },
+    },
+    config::{LIQUIDATE_POSITION_EVENT, VESU_LIQUIDATE_SELECTOR},
+    utils::constants::U256_ZERO,
+};
+
+/// Version of the Vesu Liquidate periphery contract of a network, selecting
+/// the bindings its calls are encoded & its events decoded with. Each version
+/// has its own bindings, generated by the build script from its ABI in
+/// `abis/`, so a network can move to another version from the config.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LiquidateVersion {
+    /// `liquidate(LiquidateParams)`, repaying the debt through weighted Ekubo
+    /// routes & emitting `LiquidatePosition`.
+    #[default]
+    V1,
+    /// `liquidate(LiquidateParams)` of the periphery of the pools deployed as
+    /// contracts: the pool is its address & the collateral is not swapped on
+    /// withdrawal.
+    V2,
+}
+
+/// Liquidation of a position through the Liquidate contract, whatever its
+/// version.
+#[derive(Debug, Clone)]
+pub struct LiquidateRequest {
+    pub pool_id: Felt,
+    pub collateral: Felt,
+    pub debt: Felt,
+    pub user: Felt,
+    /// Receives the collateral left once the debt is repaid.
+    pub recipient: Felt,
+    /// Zero repays the whole debt.
+    pub debt_to_repay: U256,
+    pub swap: Vec<Swap>,
+    pub swap_weights: Vec<u128>,
+}
+
+/// A `LiquidatePosition` event of the Liquidate contract, whatever its
+/// version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiquidatedPosition {
+    pub pool_id: Felt,
+    pub user: Felt,
+    pub collateral: Felt,
+    pub debt: Felt,
+    pub collateral_seized: U256,
+    pub debt_repaid: U256,
+    /// Collateral left to the recipient once the debt is repaid.
+    pub residual: U256,
+}
+
+impl LiquidateVersion {
+    /// Selector of the event emitted by the contract for each liquidation.
+    pub fn liquidation_event(&self) -> Felt {
+        match self {
+            LiquidateVersion::V1 | LiquidateVersion::V2 => *LIQUIDATE_POSITION_EVENT,
+        }
+    }
+
+    /// Returns the call of `contract` liquidating the position of `request`.
+    pub fn liquidate_call(&self, contract: Felt, request: LiquidateRequest) -> Call {
+        match self {
+            LiquidateVersion::V1 => {
+                let params = LiquidateParamsV1 {
+                    pool_id: request.pool_id,
+                    collateral_asset: ContractAddress(request.collateral),
+                    debt_asset: ContractAddress(request.debt),
+                    user: ContractAddress(request.user),
+                    recipient: ContractAddress(request.recipient),
+                    min_collateral_to_receive: U256_ZERO,
+                    debt_to_repay: request.debt_to_repay,
+                    liquidate_swap: request.swap,
+                    liquidate_swap_weights: request.swap_weights,
+                    liquidate_swap_limit_amount: u128::MAX,
+                    withdraw_swap: vec![],
+                    withdraw_swap_limit_amount: 0,
+                    withdraw_swap_weights: vec![],
+                };
+                Call {
+                    to: contract,
+                    selector: *VESU_LIQUIDATE_SELECTOR,
+                    calldata: LiquidateParamsV1::cairo_serialize(&params),
+                }
+            }
+            LiquidateVersion::V2 => {
+                // The swaps are quoted with the bindings of the first version,
+                // their layout being the same in both.
+                let swap = Vec::<SwapV2>::cairo_deserialize(
+                    &Vec::<Swap>::cairo_serialize(&request.swap),
+                    0,
+                )
+                .expect("the swaps have the same layout in both versions");
+                let params = LiquidateParamsV2 {
+                    pool: ContractAddress(request.pool_id),
+                    collateral_asset: ContractAddress(request.collateral),
+                    debt_asset: ContractAddress(request.debt),
+                    user: ContractAddress(request.user),
+                    recipient: ContractAddress(request.recipient),
+                    min_collateral_to_receive: U256_ZERO,
+                    debt_to_repay: request.debt_to_repay,
+                    liquidate_swap: swap,
+                    liquidate_swap_weights: request.swap_weights,
+                    liquidate_swap_limit_amount: u128::MAX,
+                };
+                Call {
+                    to: contract,
+                    selector: *VESU_LIQUIDATE_SELECTOR,
+                    calldata: LiquidateParamsV2::cairo_serialize(&params),
+                }
+            }
+        }
+    }
+
+    /// Decodes a liquidation event of the contract, `None` for the other
+    /// events or if its layout is not the one of this version: a contract
+    /// upgrade changing the event fails to decode instead of misrouting the
+    /// earnings.
+    pub fn decode_liquidation(&self, event: &Event) -> Option<LiquidatedPosition> {
+        match self {
+            LiquidateVersion::V1 => match LiquidateEventV1::try_from(event).ok()? {
+                LiquidateEventV1::LiquidatePosition(liquidation) => Some(LiquidatedPosition {
+                    pool_id: liquidation.pool_id,
+                    user: liquidation.user.0,
+                    collateral: liquidation.collateral_asset.0,
+                    debt: liquidation.debt_asset.0,
+                    collateral_seized: liquidation.collateral_delta,
+                    debt_repaid: liquidation.debt_delta,
+                    residual: liquidation.residual,
+                }),
+            },
+            LiquidateVersion::V2 => match LiquidateEventV2::try_from(event).ok()? {
+                LiquidateEventV2::LiquidatePosition(liquidation) => Some(LiquidatedPosition {
+                    pool_id: liquidation.pool.0,
+                    user: liquidation.user.0,
+                    collateral: liquidation.collateral_asset.0,
+                    debt: liquidation.debt_asset.0,
+                    collateral_seized: liquidation.collateral_delta,
+                    debt_repaid: liquidation.debt_delta,
+                    residual: liquidation.residual,
+                }),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cainome::cairo_serde::{ContractAddress, U256};
+    use starknet::core::{
+        types::{Event, Felt},
+        utils::get_selector_from_name,
+    };
+
+    use super::{LiquidateRequest, LiquidateVersion, LiquidatedPosition};
+    use crate::bindings::liquidate::{I129, PoolKey, RouteNode, Swap, TokenAmount};
+
+    fn request(swap: Vec<Swap>) -> LiquidateRequest {
+        LiquidateRequest {
+            pool_id: Felt::ONE,
+            collateral: Felt::from(0x10_u64),
+            debt: Felt::from(0x20_u64),
+            user: Felt::from(0xabc_u64),
+            recipient: Felt::from(0x999_u64),
+            debt_to_repay: U256 { low: 0, high: 0 },
+            swap,
+            swap_weights: vec![1],
+        }
+    }
+
+    #[test]
+    fn test_liquidate_call() {
+        let contract = Felt::from(0x1234_u64);
+        let v1 = LiquidateVersion::V1.liquidate_call(contract, request(vec![]));
+        let v2 = LiquidateVersion::V2.liquidate_call(contract, request(vec![]));
+        assert_eq!(v1.to, contract);
+        assert_eq!(v1.selector, v2.selector);
+
+        // The first version ends with the empty withdrawal swap, its limit &
+        // its weights, dropped by the second one.
+        assert_eq!(v1.calldata.len(), v2.calldata.len() + 3);
+        assert_eq!(&v1.calldata[..v2.calldata.len()], v2.calldata.as_slice());
+        assert_eq!(v2.calldata[0], Felt::ONE);
+    }
+
+    #[test]
+    fn test_liquidate_call_swaps() {
+        let swap = Swap {
+            route: vec![RouteNode {
+                pool_key: PoolKey {
+                    token0: ContractAddress(Felt::from(0x10_u64)),
+                    token1: ContractAddress(Felt::from(0x20_u64)),
+                    fee: 3,
+                    tick_spacing: 200,
+                    extension: ContractAddress(Felt::ZERO),
+                },
+                sqrt_ratio_limit: U256 { low: 42, high: 1 },
+                skip_ahead: 0,
+            }],
+            token_amount: TokenAmount {
+                token: ContractAddress(Felt::from(0x20_u64)),
+                amount: I129 { mag: 5, sign: true },
+            },
+        };
+        let v1 = LiquidateVersion::V1.liquidate_call(Felt::ONE, request(vec![swap.clone()]));
+        let v2 = LiquidateVersion::V2.liquidate_call(Felt::ONE, request(vec![swap]));
+        assert_eq!(&v1.calldata[..v2.calldata.len()], v2.calldata.as_slice());
+    }
+
+    #[test]
+    fn test_decode_liquidation() {
+        let event = Event {
+            from_address: Felt::from(0x1234_u64),
+            keys: vec![
+                get_selector_from_name("LiquidatePosition").unwrap(),
+                Felt::ONE,
+                Felt::from(0x10_u64),
+                Felt::from(0x20_u64),
+                Felt::from(0xabc_u64),
+            ],
+            // residual, collateral_delta & debt_delta, as u256s.
+            data: vec![
+                Felt::from(7_u64),
+                Felt::ZERO,
+                Felt::from(100_u64),
+                Felt::ZERO,
+                Felt::from(93_u64),
+                Felt::ZERO,
+            ],
+        };
+        let expected = LiquidatedPosition {
+            pool_id: Felt::ONE,
+            user: Felt::from(0xabc_u64),
+            collateral: Felt::from(0x10_u64),
+            debt: Felt::from(0x20_u64),
+            collateral_seized: U256 { low: 100, high: 0 },
+            debt_repaid: U256 { low: 93, high: 0 },
+            residual: U256 { low: 7, high: 0 },
+        };
+        for version in [LiquidateVersion::V1, LiquidateVersion::V2] {
+            assert_eq!(version.liquidation_event(), event.keys[0]);
+            assert_eq!(version.decode_liquidation(&event), Some(expected.clone()));
+        }
+
+        let mut truncated = event;
+        truncated.data.truncate(3);
+        assert_eq!(LiquidateVersion::V2.decode_liquidation(&truncated), None);
+    }
+}
@@ -0,0 +1,193 @@
+pub mod liquidate;
+pub mod vesu;
+pub mod zklend;
+
+use std::{fmt, str::FromStr, sync::Arc};
+
+use anyhow::{Result, anyhow};
+use bigdecimal::BigDecimal;
+use cainome::cairo_serde::U256;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{Call, Event, Felt, FunctionCall};
+
+use crate::{
+    config::Config,
+    types::position::{Position, PositionKey},
+    utils::http::HttpClient,
+};
+
+use vesu::VesuProtocol;
+use zklend::ZkLendProtocol;
+
+/// Lending protocol of a position.
+#[derive(
+    Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ProtocolKind {
+    #[default]
+    Vesu,
+    #[serde(rename = "zklend")]
+    ZkLend,
+}
+
+impl ProtocolKind {
+    pub fn is_vesu(&self) -> bool {
+        *self == ProtocolKind::Vesu
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProtocolKind::Vesu => "vesu",
+            ProtocolKind::ZkLend => "zklend",
+        }
+    }
+}
+
+impl fmt::Display for ProtocolKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ProtocolKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "vesu" => Ok(ProtocolKind::Vesu),
+            "zklend" => Ok(ProtocolKind::ZkLend),
+            _ => Err(anyhow!("Unknown lending protocol {s}")),
+        }
+    }
+}
+
+/// What a liquidation seized & repaid, decoded from the events of its
+/// transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiquidationOutcome {
+    pub pool_id: Felt,
+    pub user: Felt,
+    pub collateral: Felt,
+    pub debt: Felt,
+    pub collateral_seized: U256,
+    pub debt_repaid: U256,
+    /// Collateral left to the liquidator once the debt is repaid.
+    pub earnings: U256,
+}
+
+/// A money market whose positions are indexed, monitored & liquidated by the
+/// bot. The amounts of the positions & their LLTV are read from the protocol,
+/// their health being computed the same way for all protocols.
+#[async_trait::async_trait]
+pub trait LendingProtocol: Send + Sync {
+    fn kind(&self) -> ProtocolKind;
+
+    /// Contract emitting the events of the positions.
+    fn events_address(&self) -> Felt;
+
+    /// Selectors of the events creating or updating a position.
+    fn event_selectors(&self) -> Vec<Felt>;
+
+    /// Returns the positions an event is about, none if the event is not
+    /// about a position with known assets.
+    fn positions_from_event(&self, config: &Config, keys: &[Felt], data: &[Felt]) -> Vec<Position>;
+
+    /// Returns the calls reading the amounts & the LLTV of the position.
+    fn update_requests(&self, position: &Position) -> Result<Vec<FunctionCall>>;
+
+    /// Sets the amounts & the LLTV of the position from the results of its
+    /// [`Self::update_requests`], in order.
+    fn apply_update(&self, position: &mut Position, results: &[Vec<Felt>]) -> Result<()>;
+
+    /// Contract pulling the debt repaid by the liquidator, approved on the
+    /// debt token before the liquidations.
+    fn debt_spender(&self) -> Felt;
+
+    /// Returns the calls liquidating the position, repaying `debt_to_repay` -
+    /// the whole debt if it is at least the debt of the position - & sending
+    /// the earnings to `liquidator`.
+    async fn liquidation_calls(
+        &self,
+        position: &Position,
+        http_client: &HttpClient,
+        liquidator: Felt,
+        debt_to_repay: &BigDecimal,
+    ) -> Result<Vec<Call>>;
+
+    /// Finds the outcome of a liquidation in the events of its transaction.
+    fn find_liquidation(&self, events: &[Event]) -> Option<LiquidationOutcome>;
+
+    /// Returns the position liquidated & the liquidator of one of the
+    /// [`Self::event_selectors`], if it is a liquidation, by the bot or by
+    /// anyone else.
+    fn liquidation_from_event(&self, keys: &[Felt], data: &[Felt]) -> Option<(PositionKey, Felt)>;
+}
+
+/// The lending protocols enabled in the config, see
+/// [`crate::config::ProtocolsConfig`].
+#[derive(Clone)]
+pub struct Protocols(Arc<Vec<Arc<dyn LendingProtocol>>>);
+
+impl Protocols {
+    pub fn from_config(config: &Config) -> Self {
+        let mut protocols: Vec<Arc<dyn LendingProtocol>> = vec![];
+        if config.protocols.vesu.enabled {
+            protocols.push(Arc::new(VesuProtocol::from_config(config)));
+        }
+        if config.protocols.zklend.enabled {
+            protocols.push(Arc::new(ZkLendProtocol::from_config(config)));
+        }
+        Self(Arc::new(protocols))
+    }
+
+    /// Returns the protocol of the positions of this kind, failing if it is
+    /// not enabled.
+    pub fn get(&self, kind: ProtocolKind) -> Result<&dyn LendingProtocol> {
+        self.0
+            .iter()
+            .find(|protocol| protocol.kind() == kind)
+            .map(|protocol| protocol.as_ref())
+            .ok_or_else(|| anyhow!("Lending protocol {kind} is not enabled"))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn LendingProtocol> {
+        self.0.iter().map(|protocol| protocol.as_ref())
+    }
+
+    /// Returns the protocol emitting an event, if it is one of the events of
+    /// its positions.
+    pub fn for_event(&self, from_address: Felt, selector: Felt) -> Option<&dyn LendingProtocol> {
+        self.iter().find(|protocol| {
+            protocol.events_address() == from_address
+                && protocol.event_selectors().contains(&selector)
+        })
+    }
+
+    /// Returns the positions an event is about, from the protocol emitting it.
+    pub fn positions_from_event(
+        &self,
+        config: &Config,
+        from_address: Felt,
+        keys: &[Felt],
+        data: &[Felt],
+    ) -> Vec<Position> {
+        keys.first()
+            .and_then(|selector| self.for_event(from_address, *selector))
+            .map(|protocol| protocol.positions_from_event(config, keys, data))
+            .unwrap_or_default()
+    }
+
+    /// Returns the position liquidated & the liquidator of an event, from the
+    /// protocol emitting it, if it is a liquidation.
+    pub fn liquidation_from_event(
+        &self,
+        from_address: Felt,
+        keys: &[Felt],
+        data: &[Felt],
+    ) -> Option<(PositionKey, Felt)> {
+        keys.first()
+            .and_then(|selector| self.for_event(from_address, *selector))
+            .and_then(|protocol| protocol.liquidation_from_event(keys, data))
+    }
+}
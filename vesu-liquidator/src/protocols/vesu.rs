@@ -0,0 +1,416 @@
+use anyhow::Result;
+use bigdecimal::BigDecimal;
+use starknet::core::types::{Call, Event, Felt, FunctionCall};
+
+use crate::{
+    config::{
+        Config, LIQUIDATE_POSITION_EVENT, MIGRATE_POSITION_EVENT, MODIFY_POSITION_EVENT,
+        VESU_LTV_CONFIG_SELECTOR, VESU_POSITION_UNSAFE_SELECTOR,
+    },
+    types::{
+        asset::Asset,
+        position::{Position, PositionKey},
+    },
+    utils::{
+        constants::{U256_ZERO, VESU_RESPONSE_DECIMALS},
+        conversions::big_decimal_to_u256,
+        ekubo::get_ekubo_route,
+        http::HttpClient,
+    },
+};
+
+use super::{
+    LendingProtocol, LiquidationOutcome, ProtocolKind,
+    liquidate::{LiquidateRequest, LiquidateVersion, LiquidatedPosition},
+};
+
+/// Vesu: positions are read from the singleton & liquidated through the
+/// Liquidate contract, swapping the seized collateral on Ekubo.
+pub struct VesuProtocol {
+    singleton_address: Felt,
+    liquidate_address: Felt,
+    liquidate_version: LiquidateVersion,
+    ekubo_quoter_url: String,
+}
+
+impl VesuProtocol {
+    pub fn new(singleton_address: Felt, liquidate_address: Felt, ekubo_quoter_url: String) -> Self {
+        Self {
+            singleton_address,
+            liquidate_address,
+            liquidate_version: LiquidateVersion::default(),
+            ekubo_quoter_url,
+        }
+    }
+
+    /// Encodes the liquidations & decodes their events with the bindings of
+    /// this version of the Liquidate contract.
+    pub fn with_liquidate_version(mut self, liquidate_version: LiquidateVersion) -> Self {
+        self.liquidate_version = liquidate_version;
+        self
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(
+            config.singleton_address,
+            config.liquidate_address,
+            config.swap.ekubo_quoter_url.clone(),
+        )
+        .with_liquidate_version(config.liquidate_version)
+    }
+}
+
+#[async_trait::async_trait]
+impl LendingProtocol for VesuProtocol {
+    fn kind(&self) -> ProtocolKind {
+        ProtocolKind::Vesu
+    }
+
+    fn events_address(&self) -> Felt {
+        self.singleton_address
+    }
+
+    fn event_selectors(&self) -> Vec<Felt> {
+        vec![
+            *MODIFY_POSITION_EVENT,
+            *MIGRATE_POSITION_EVENT,
+            *LIQUIDATE_POSITION_EVENT,
+        ]
+    }
+
+    /// The keys of the ModifyPosition, MigratePosition & LiquidatePosition
+    /// events are: selector, pool id, collateral, debt & user.
+    fn positions_from_event(
+        &self,
+        config: &Config,
+        keys: &[Felt],
+        _data: &[Felt],
+    ) -> Vec<Position> {
+        // Corresponds to event associated with the extension contract - we ignore them.
+        if keys.len() < 5 || keys[3] == Felt::ZERO {
+            return vec![];
+        }
+        let collateral = Asset::from_address(config, keys[2]);
+        let debt = Asset::from_address(config, keys[3]);
+        let (Some(collateral), Some(debt)) = (collateral, debt) else {
+            tracing::info!("{keys:?}");
+            tracing::warn!("collat & debt is none :/");
+            return vec![];
+        };
+
+        vec![Position {
+            pool_id: keys[1],
+            pool_name: config.get_pool_name(&keys[1]),
+            collateral,
+            debt,
+            user_address: keys[4],
+            lltv: BigDecimal::default(),
+            protocol: ProtocolKind::Vesu,
+        }]
+    }
+
+    fn update_requests(&self, position: &Position) -> Result<Vec<FunctionCall>> {
+        Ok(vec![
+            FunctionCall {
+                contract_address: self.singleton_address,
+                entry_point_selector: *VESU_POSITION_UNSAFE_SELECTOR,
+                calldata: vec![
+                    position.pool_id,
+                    position.collateral.address,
+                    position.debt.address,
+                    position.user_address,
+                ],
+            },
+            FunctionCall {
+                contract_address: self.singleton_address,
+                entry_point_selector: *VESU_LTV_CONFIG_SELECTOR,
+                calldata: vec![
+                    position.pool_id,
+                    position.collateral.address,
+                    position.debt.address,
+                ],
+            },
+        ])
+    }
+
+    /// Reads the amounts from the result of `position_unsafe` & the LLTV from
+    /// the result of `ltv_config`.
+    fn apply_update(&self, position: &mut Position, results: &[Vec<Felt>]) -> Result<()> {
+        let [amounts, ltv_config] = results else {
+            anyhow::bail!("Expected the results of position_unsafe & ltv_config");
+        };
+        anyhow::ensure!(
+            amounts.len() > 6,
+            "Unexpected position_unsafe result {amounts:?}"
+        );
+        let max_ltv = ltv_config
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("Empty ltv_config result"))?;
+        position.collateral.amount =
+            BigDecimal::new(amounts[4].to_bigint(), position.collateral.decimals);
+        position.debt.amount = BigDecimal::new(amounts[6].to_bigint(), position.debt.decimals);
+        position.lltv = BigDecimal::new(max_ltv.to_bigint(), VESU_RESPONSE_DECIMALS);
+        Ok(())
+    }
+
+    fn debt_spender(&self) -> Felt {
+        self.liquidate_address
+    }
+
+    async fn liquidation_calls(
+        &self,
+        position: &Position,
+        http_client: &HttpClient,
+        liquidator: Felt,
+        debt_to_repay: &BigDecimal,
+    ) -> Result<Vec<Call>> {
+        let is_full = *debt_to_repay >= position.debt.amount;
+        let debt_to_repay = if is_full {
+            position.debt.amount.clone()
+        } else {
+            debt_to_repay.with_scale(position.debt.decimals)
+        };
+        let (liquidate_swap, liquidate_swap_weights) = get_ekubo_route(
+            http_client,
+            &self.ekubo_quoter_url,
+            position.debt.address,
+            position.collateral.address,
+            &debt_to_repay,
+        )
+        .await?;
+
+        let request = LiquidateRequest {
+            pool_id: position.pool_id,
+            collateral: position.collateral.address,
+            debt: position.debt.address,
+            user: position.user_address,
+            recipient: liquidator,
+            // Zero repays the whole debt.
+            debt_to_repay: if is_full {
+                U256_ZERO
+            } else {
+                big_decimal_to_u256(debt_to_repay)
+            },
+            swap: liquidate_swap,
+            swap_weights: liquidate_swap_weights,
+        };
+        Ok(vec![
+            self.liquidate_version
+                .liquidate_call(self.liquidate_address, request),
+        ])
+    }
+
+    fn find_liquidation(&self, events: &[Event]) -> Option<LiquidationOutcome> {
+        let liquidation =
+            find_liquidation_event(events, self.liquidate_address, self.liquidate_version)?;
+        Some(LiquidationOutcome {
+            pool_id: liquidation.pool_id,
+            user: liquidation.user,
+            collateral: liquidation.collateral,
+            debt: liquidation.debt,
+            collateral_seized: liquidation.collateral_seized,
+            debt_repaid: liquidation.debt_repaid,
+            earnings: liquidation.residual,
+        })
+    }
+
+    /// The liquidator is the last key of the LiquidatePosition event of the
+    /// singleton, after the ones of the position.
+    fn liquidation_from_event(&self, keys: &[Felt], _data: &[Felt]) -> Option<(PositionKey, Felt)> {
+        match keys {
+            [selector, pool_id, collateral, debt, user, liquidator, ..]
+                if *selector == *LIQUIDATE_POSITION_EVENT =>
+            {
+                let key = PositionKey {
+                    pool_id: *pool_id,
+                    collateral: *collateral,
+                    debt: *debt,
+                    user: *user,
+                    protocol: ProtocolKind::Vesu,
+                };
+                Some((key, *liquidator))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Finds the `LiquidatePosition` event emitted by the liquidate contract in
+/// the events of a transaction, decoded with the bindings of its version, see
+/// [`LiquidateVersion::decode_liquidation`].
+///
+/// # Arguments
+/// * `events` - A slice of `Event` from a transaction receipt.
+/// * `contract_address` - The address of the contract that is expected to emit the event.
+/// * `version` - The version of the contract.
+fn find_liquidation_event(
+    events: &[Event],
+    contract_address: Felt,
+    version: LiquidateVersion,
+) -> Option<LiquidatedPosition> {
+    events
+        .iter()
+        .filter(|event| event.from_address == contract_address)
+        .find_map(|event| version.decode_liquidation(event))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+    use cainome::cairo_serde::U256;
+    use starknet::core::{
+        types::{Event, Felt},
+        utils::get_selector_from_name,
+    };
+
+    use super::{VesuProtocol, find_liquidation_event};
+    use crate::{
+        config::SwapConfig,
+        protocols::{LendingProtocol, LiquidationOutcome, liquidate::LiquidateVersion},
+        types::{asset::Asset, position::Position},
+    };
+
+    const SINGLETON: Felt = Felt::from_hex_unchecked("0x5678");
+    const LIQUIDATE_CONTRACT: Felt = Felt::from_hex_unchecked("0x1234");
+    const COLLATERAL: Felt = Felt::from_hex_unchecked("0xc0");
+
+    fn liquidate_position_event(from_address: Felt) -> Event {
+        Event {
+            from_address,
+            keys: vec![
+                get_selector_from_name("LiquidatePosition").unwrap(),
+                Felt::ONE,
+                COLLATERAL,
+                Felt::from_hex_unchecked("0xd0"),
+                Felt::from_hex_unchecked("0xabc"),
+            ],
+            // residual, collateral_delta & debt_delta, as u256s.
+            data: vec![
+                Felt::from(7_u64),
+                Felt::ZERO,
+                Felt::from(100_u64),
+                Felt::ZERO,
+                Felt::from(93_u64),
+                Felt::ZERO,
+            ],
+        }
+    }
+
+    #[test]
+    fn test_find_liquidation_event() {
+        let transfer = Event {
+            from_address: COLLATERAL,
+            keys: vec![get_selector_from_name("Transfer").unwrap()],
+            data: vec![Felt::ONE, Felt::TWO, Felt::ZERO],
+        };
+        let events = [transfer, liquidate_position_event(LIQUIDATE_CONTRACT)];
+
+        let liquidation =
+            find_liquidation_event(&events, LIQUIDATE_CONTRACT, LiquidateVersion::V1).unwrap();
+        assert_eq!(liquidation.collateral, COLLATERAL);
+        assert_eq!(liquidation.residual, U256 { low: 7, high: 0 });
+    }
+
+    #[test]
+    fn test_find_liquidation_event_ignores_other_emitters_and_layouts() {
+        let from_other_contract = liquidate_position_event(Felt::from_hex_unchecked("0x999"));
+        assert!(
+            find_liquidation_event(
+                &[from_other_contract],
+                LIQUIDATE_CONTRACT,
+                LiquidateVersion::V1
+            )
+            .is_none()
+        );
+
+        let mut truncated = liquidate_position_event(LIQUIDATE_CONTRACT);
+        truncated.data.truncate(3);
+        assert!(
+            find_liquidation_event(&[truncated], LIQUIDATE_CONTRACT, LiquidateVersion::V1)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_find_liquidation() {
+        let vesu = VesuProtocol::new(
+            SINGLETON,
+            LIQUIDATE_CONTRACT,
+            SwapConfig::default().ekubo_quoter_url,
+        );
+        let outcome = vesu
+            .find_liquidation(&[liquidate_position_event(LIQUIDATE_CONTRACT)])
+            .unwrap();
+        assert_eq!(
+            outcome,
+            LiquidationOutcome {
+                pool_id: Felt::ONE,
+                user: Felt::from_hex_unchecked("0xabc"),
+                collateral: COLLATERAL,
+                debt: Felt::from_hex_unchecked("0xd0"),
+                collateral_seized: U256 { low: 100, high: 0 },
+                debt_repaid: U256 { low: 93, high: 0 },
+                earnings: U256 { low: 7, high: 0 },
+            }
+        );
+    }
+
+    #[test]
+    fn test_liquidation_from_event() {
+        let vesu = VesuProtocol::new(
+            SINGLETON,
+            LIQUIDATE_CONTRACT,
+            SwapConfig::default().ekubo_quoter_url,
+        );
+        let mut keys = vec![
+            get_selector_from_name("LiquidatePosition").unwrap(),
+            Felt::ONE,
+            COLLATERAL,
+            Felt::from_hex_unchecked("0xd0"),
+            Felt::from_hex_unchecked("0xabc"),
+            Felt::from_hex_unchecked("0x999"),
+        ];
+        let (key, liquidator) = vesu.liquidation_from_event(&keys, &[]).unwrap();
+        assert_eq!(key.user, Felt::from_hex_unchecked("0xabc"));
+        assert_eq!(key.collateral, COLLATERAL);
+        assert_eq!(liquidator, Felt::from_hex_unchecked("0x999"));
+
+        keys[0] = get_selector_from_name("ModifyPosition").unwrap();
+        assert!(vesu.liquidation_from_event(&keys, &[]).is_none());
+    }
+
+    #[test]
+    fn test_apply_update() {
+        let vesu = VesuProtocol::new(
+            SINGLETON,
+            LIQUIDATE_CONTRACT,
+            SwapConfig::default().ekubo_quoter_url,
+        );
+        let mut position = Position {
+            collateral: Asset::new("ETH".to_string(), COLLATERAL, 18),
+            debt: Asset::new("USDC".to_string(), Felt::from_hex_unchecked("0xd0"), 6),
+            ..Default::default()
+        };
+        let requests = vesu.update_requests(&position).unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|r| r.contract_address == SINGLETON));
+
+        let amounts = [0, 0, 0, 0, 2_000_000_000_000_000_000, 0, 1_500_000]
+            .map(|amount: u128| Felt::from(amount))
+            .to_vec();
+        let ltv_config = vec![Felt::from(800_000_000_000_000_000_u64)];
+        vesu.apply_update(&mut position, &[amounts.clone(), ltv_config.clone()])
+            .unwrap();
+        assert_eq!(position.collateral.amount, BigDecimal::from(2));
+        assert_eq!(position.debt.amount, BigDecimal::from_str("1.5").unwrap());
+        assert_eq!(position.lltv, BigDecimal::from_str("0.8").unwrap());
+
+        assert!(vesu.apply_update(&mut position, &[amounts]).is_err());
+        assert!(
+            vesu.apply_update(&mut position, &[vec![Felt::ONE], ltv_config])
+                .is_err()
+        );
+    }
+}
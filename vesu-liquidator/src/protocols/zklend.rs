@@ -0,0 +1,540 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use bigdecimal::{BigDecimal, num_bigint::BigUint};
+use cainome::cairo_serde::U256;
+use dashmap::DashMap;
+use starknet::core::types::{Call, Event, Felt, FunctionCall};
+
+use crate::{
+    config::{
+        BALANCE_OF_SELECTOR, Config, ZKLEND_BORROWING_EVENT, ZKLEND_COLLATERAL_DISABLED_EVENT,
+        ZKLEND_IS_COLLATERAL_ENABLED_SELECTOR, ZKLEND_LIQUIDATE_SELECTOR, ZKLEND_LIQUIDATION_EVENT,
+        ZKLEND_REPAYMENT_EVENT, ZKLEND_RESERVE_DATA_SELECTOR, ZKLEND_USER_DEBT_SELECTOR,
+        ZKLEND_WITHDRAW_ALL_SELECTOR, ZKLEND_WITHDRAWAL_EVENT,
+    },
+    types::{
+        asset::Asset,
+        position::{Position, PositionKey},
+    },
+    utils::{
+        constants::U256_ZERO,
+        conversions::{big_decimal_to_felt, big_uint_to_u256, felt_to_u256, u256_to_big_uint},
+        http::HttpClient,
+    },
+};
+
+use super::{LendingProtocol, LiquidationOutcome, ProtocolKind};
+
+/// Decimals of the factors of the zkLend reserves.
+const ZKLEND_SCALE_DECIMALS: i64 = 27;
+
+/// Offsets of the fields of `MarketReserveData` in a `get_reserve_data`
+/// result.
+const COLLATERAL_FACTOR_OFFSET: usize = 4;
+const BORROW_FACTOR_OFFSET: usize = 5;
+const LIQUIDATION_BONUS_OFFSET: usize = 14;
+
+/// zkLend: the accounts of its market are cross-collateralized, each pair of
+/// collateral & debt reserves of a user being monitored as a position. The
+/// LTV of a pair is liquidable above the collateral factor of its collateral
+/// times the borrow factor of its debt, exact for the accounts with a single
+/// collateral & debt; the market rejects the liquidation of the healthy
+/// accounts with more, the simulation skipping them.
+pub struct ZkLendProtocol {
+    market_address: Felt,
+    /// zToken of each reserve tracked, by underlying asset.
+    z_tokens: HashMap<Felt, Felt>,
+    /// Liquidation bonus of the reserves, scaled by [`ZKLEND_SCALE_DECIMALS`],
+    /// read with the positions.
+    liquidation_bonuses: DashMap<Felt, Felt>,
+}
+
+impl ZkLendProtocol {
+    pub fn new(market_address: Felt, z_tokens: HashMap<Felt, Felt>) -> Self {
+        Self {
+            market_address,
+            z_tokens,
+            liquidation_bonuses: DashMap::new(),
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        let zklend = &config.protocols.zklend;
+        let market_address = zklend
+            .market_address
+            .as_deref()
+            .and_then(|address| Felt::from_hex(address).ok())
+            .expect("zklend market_address is validated when loading the config");
+        let z_tokens = zklend
+            .z_tokens
+            .iter()
+            .map(|(ticker, z_token)| {
+                let asset = config
+                    .get_asset_address_for_ticker(ticker)
+                    .expect("zklend reserves are validated when loading the config");
+                let z_token = Felt::from_hex(z_token)
+                    .expect("zklend zTokens are validated when loading the config");
+                (asset, z_token)
+            })
+            .collect();
+        Self::new(market_address, z_tokens)
+    }
+
+    fn position(
+        &self,
+        config: &Config,
+        user: Felt,
+        collateral: Felt,
+        debt: Felt,
+    ) -> Option<Position> {
+        Some(Position {
+            user_address: user,
+            pool_id: self.market_address,
+            pool_name: config.get_pool_name(&self.market_address),
+            collateral: Asset::from_address(config, collateral)?,
+            debt: Asset::from_address(config, debt)?,
+            lltv: BigDecimal::default(),
+            protocol: ProtocolKind::ZkLend,
+        })
+    }
+
+    /// Returns the positions of `user` borrowing `debt` against each of the
+    /// other reserves tracked.
+    fn positions_with_debt(&self, config: &Config, user: Felt, debt: Felt) -> Vec<Position> {
+        if !self.z_tokens.contains_key(&debt) {
+            return vec![];
+        }
+        self.z_tokens
+            .keys()
+            .filter(|collateral| **collateral != debt)
+            .filter_map(|collateral| self.position(config, user, *collateral, debt))
+            .collect()
+    }
+
+    /// Returns the positions of `user` against `collateral`, for each of the
+    /// other reserves tracked as debt.
+    fn positions_with_collateral(
+        &self,
+        config: &Config,
+        user: Felt,
+        collateral: Felt,
+    ) -> Vec<Position> {
+        if !self.z_tokens.contains_key(&collateral) {
+            return vec![];
+        }
+        self.z_tokens
+            .keys()
+            .filter(|debt| **debt != collateral)
+            .filter_map(|debt| self.position(config, user, collateral, *debt))
+            .collect()
+    }
+
+    /// Returns the part of the seized collateral paid as liquidation bonus,
+    /// zero if the bonus of the reserve was not read yet.
+    fn bonus_part(&self, collateral: Felt, seized: &U256) -> U256 {
+        let Some(bonus) = self
+            .liquidation_bonuses
+            .get(&collateral)
+            .map(|bonus| *bonus)
+        else {
+            tracing::warn!(
+                "[🔭 Monitoring] Liquidation bonus of zkLend reserve {:#x} unknown, no earnings recorded",
+                collateral
+            );
+            return U256_ZERO;
+        };
+        let scale = BigUint::from(10_u32).pow(ZKLEND_SCALE_DECIMALS as u32);
+        let bonus = bonus.to_biguint();
+        big_uint_to_u256(&(u256_to_big_uint(seized) * &bonus / (scale + bonus)))
+    }
+}
+
+#[async_trait::async_trait]
+impl LendingProtocol for ZkLendProtocol {
+    fn kind(&self) -> ProtocolKind {
+        ProtocolKind::ZkLend
+    }
+
+    fn events_address(&self) -> Felt {
+        self.market_address
+    }
+
+    fn event_selectors(&self) -> Vec<Felt> {
+        vec![
+            *ZKLEND_BORROWING_EVENT,
+            *ZKLEND_REPAYMENT_EVENT,
+            *ZKLEND_WITHDRAWAL_EVENT,
+            *ZKLEND_COLLATERAL_DISABLED_EVENT,
+            *ZKLEND_LIQUIDATION_EVENT,
+        ]
+    }
+
+    /// The fields of the market events are all in their data:
+    /// - `Borrowing`: user, token, raw_amount, face_amount
+    /// - `Repayment`: repayer, beneficiary, token, raw_amount, face_amount
+    /// - `Withdrawal`: user, token, face_amount
+    /// - `CollateralDisabled`: user, token
+    /// - `Liquidation`: liquidator, user, debt_token, debt_raw_amount,
+    ///   debt_face_amount, collateral_token, collateral_amount
+    fn positions_from_event(&self, config: &Config, keys: &[Felt], data: &[Felt]) -> Vec<Position> {
+        let Some(selector) = keys.first() else {
+            return vec![];
+        };
+        match data {
+            [user, token, ..] if *selector == *ZKLEND_BORROWING_EVENT => {
+                self.positions_with_debt(config, *user, *token)
+            }
+            [_, beneficiary, token, ..] if *selector == *ZKLEND_REPAYMENT_EVENT => {
+                self.positions_with_debt(config, *beneficiary, *token)
+            }
+            [user, token, ..]
+                if *selector == *ZKLEND_WITHDRAWAL_EVENT
+                    || *selector == *ZKLEND_COLLATERAL_DISABLED_EVENT =>
+            {
+                self.positions_with_collateral(config, *user, *token)
+            }
+            [_, user, debt, _, _, collateral, ..] if *selector == *ZKLEND_LIQUIDATION_EVENT => self
+                .position(config, *user, *collateral, *debt)
+                .into_iter()
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Reads the zToken balance of the collateral - counted if it is enabled
+    /// as collateral - the debt, & the factors of both reserves.
+    fn update_requests(&self, position: &Position) -> Result<Vec<FunctionCall>> {
+        let z_token = self
+            .z_tokens
+            .get(&position.collateral.address)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No zToken tracked for zkLend reserve {}",
+                    position.collateral.name
+                )
+            })?;
+        let market_call = |selector: Felt, calldata: Vec<Felt>| FunctionCall {
+            contract_address: self.market_address,
+            entry_point_selector: selector,
+            calldata,
+        };
+        Ok(vec![
+            FunctionCall {
+                contract_address: *z_token,
+                entry_point_selector: *BALANCE_OF_SELECTOR,
+                calldata: vec![position.user_address],
+            },
+            market_call(
+                *ZKLEND_IS_COLLATERAL_ENABLED_SELECTOR,
+                vec![position.user_address, position.collateral.address],
+            ),
+            market_call(
+                *ZKLEND_USER_DEBT_SELECTOR,
+                vec![position.user_address, position.debt.address],
+            ),
+            market_call(
+                *ZKLEND_RESERVE_DATA_SELECTOR,
+                vec![position.collateral.address],
+            ),
+            market_call(*ZKLEND_RESERVE_DATA_SELECTOR, vec![position.debt.address]),
+        ])
+    }
+
+    fn apply_update(&self, position: &mut Position, results: &[Vec<Felt>]) -> Result<()> {
+        let [
+            balance,
+            collateral_enabled,
+            debt,
+            collateral_reserve,
+            debt_reserve,
+        ] = results
+        else {
+            anyhow::bail!("Expected the results of the 5 zkLend position calls");
+        };
+        let (Some(balance), Some(collateral_enabled), Some(debt)) =
+            (balance.first(), collateral_enabled.first(), debt.first())
+        else {
+            anyhow::bail!("Empty zkLend position result");
+        };
+        anyhow::ensure!(
+            collateral_reserve.len() > LIQUIDATION_BONUS_OFFSET
+                && debt_reserve.len() > BORROW_FACTOR_OFFSET,
+            "Unexpected get_reserve_data result"
+        );
+
+        // The zToken balances fit in the low part of their u256.
+        position.collateral.amount = if *collateral_enabled == Felt::ZERO {
+            BigDecimal::default()
+        } else {
+            BigDecimal::new(balance.to_bigint(), position.collateral.decimals)
+        };
+        position.debt.amount = BigDecimal::new(debt.to_bigint(), position.debt.decimals);
+        let collateral_factor = BigDecimal::new(
+            collateral_reserve[COLLATERAL_FACTOR_OFFSET].to_bigint(),
+            ZKLEND_SCALE_DECIMALS,
+        );
+        let borrow_factor = BigDecimal::new(
+            debt_reserve[BORROW_FACTOR_OFFSET].to_bigint(),
+            ZKLEND_SCALE_DECIMALS,
+        );
+        position.lltv = collateral_factor * borrow_factor;
+        self.liquidation_bonuses.insert(
+            position.collateral.address,
+            collateral_reserve[LIQUIDATION_BONUS_OFFSET],
+        );
+        Ok(())
+    }
+
+    fn debt_spender(&self) -> Felt {
+        self.market_address
+    }
+
+    /// Repays the debt with the bot's balance of the debt token & withdraws
+    /// the zTokens of the seized collateral. The market pays the collateral
+    /// to the account sending the liquidation, not to `liquidator`.
+    async fn liquidation_calls(
+        &self,
+        position: &Position,
+        _http_client: &HttpClient,
+        _liquidator: Felt,
+        debt_to_repay: &BigDecimal,
+    ) -> Result<Vec<Call>> {
+        let debt_to_repay = if *debt_to_repay >= position.debt.amount {
+            position.debt.amount.clone()
+        } else {
+            debt_to_repay.with_scale(position.debt.decimals)
+        };
+        Ok(vec![
+            Call {
+                to: self.market_address,
+                selector: *ZKLEND_LIQUIDATE_SELECTOR,
+                calldata: vec![
+                    position.user_address,
+                    position.debt.address,
+                    big_decimal_to_felt(debt_to_repay),
+                    position.collateral.address,
+                ],
+            },
+            Call {
+                to: self.market_address,
+                selector: *ZKLEND_WITHDRAW_ALL_SELECTOR,
+                calldata: vec![position.collateral.address],
+            },
+        ])
+    }
+
+    /// Earnings are the liquidation bonus part of the seized collateral, the
+    /// rest paying back the debt repaid by the bot.
+    fn find_liquidation(&self, events: &[Event]) -> Option<LiquidationOutcome> {
+        events
+            .iter()
+            .filter(|event| event.from_address == self.market_address)
+            .filter(|event| event.keys.first() == Some(&*ZKLEND_LIQUIDATION_EVENT))
+            .find_map(|event| {
+                let [_, user, debt, _, debt_repaid, collateral, seized, ..] = event.data[..] else {
+                    return None;
+                };
+                let collateral_seized = felt_to_u256(seized);
+                Some(LiquidationOutcome {
+                    pool_id: self.market_address,
+                    user,
+                    collateral,
+                    debt,
+                    earnings: self.bonus_part(collateral, &collateral_seized),
+                    collateral_seized,
+                    debt_repaid: felt_to_u256(debt_repaid),
+                })
+            })
+    }
+
+    /// The liquidator is the first field of the data of the `Liquidation`
+    /// event, see [`Self::positions_from_event`].
+    fn liquidation_from_event(&self, keys: &[Felt], data: &[Felt]) -> Option<(PositionKey, Felt)> {
+        match data {
+            [liquidator, user, debt, _, _, collateral, ..]
+                if keys.first() == Some(&*ZKLEND_LIQUIDATION_EVENT) =>
+            {
+                let key = PositionKey {
+                    pool_id: self.market_address,
+                    collateral: *collateral,
+                    debt: *debt,
+                    user: *user,
+                    protocol: ProtocolKind::ZkLend,
+                };
+                Some((key, *liquidator))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::PathBuf, str::FromStr};
+
+    use bigdecimal::BigDecimal;
+    use cainome::cairo_serde::U256;
+    use starknet::core::types::{Event, Felt};
+
+    use super::ZkLendProtocol;
+    use crate::{
+        cli::NetworkName,
+        config::{
+            Config, LiquidationMode, TRANSFER_EVENT, ZKLEND_BORROWING_EVENT,
+            ZKLEND_LIQUIDATION_EVENT,
+        },
+        protocols::{LendingProtocol, ProtocolKind},
+        utils::conversions::big_decimal_to_felt,
+    };
+
+    const MARKET: Felt = Felt::from_hex_unchecked("0x4c0a");
+    const ETH: Felt = Felt::from_hex_unchecked(
+        "0x49d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7",
+    );
+    const USDC: Felt = Felt::from_hex_unchecked(
+        "0x53c91253bc9682c04929ca02ed00b3e423f6710d2ee7e0d5ebb06f3ecf368a8",
+    );
+    const USER: Felt = Felt::from_hex_unchecked("0xabc");
+
+    fn config() -> Config {
+        Config::new(
+            NetworkName::Mainnet,
+            LiquidationMode::Full,
+            &PathBuf::from("./config.yaml"),
+        )
+        .unwrap()
+    }
+
+    fn zklend() -> ZkLendProtocol {
+        ZkLendProtocol::new(
+            MARKET,
+            HashMap::from([(ETH, Felt::from(0xe_u64)), (USDC, Felt::from(0xd_u64))]),
+        )
+    }
+
+    #[test]
+    fn test_positions_from_event() {
+        let (config, zklend) = (config(), zklend());
+
+        let borrowing = [USER, USDC, Felt::from(10_u64), Felt::from(10_u64)];
+        let positions =
+            zklend.positions_from_event(&config, &[*ZKLEND_BORROWING_EVENT], &borrowing);
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].key().collateral, ETH);
+        assert_eq!(positions[0].key().debt, USDC);
+        assert_eq!(positions[0].pool_id, MARKET);
+        assert_eq!(positions[0].protocol, ProtocolKind::ZkLend);
+
+        let liquidation = [Felt::ONE, USER, ETH, Felt::ONE, Felt::ONE, USDC, Felt::ONE];
+        let positions =
+            zklend.positions_from_event(&config, &[*ZKLEND_LIQUIDATION_EVENT], &liquidation);
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].collateral.address, USDC);
+        let (key, liquidator) = zklend
+            .liquidation_from_event(&[*ZKLEND_LIQUIDATION_EVENT], &liquidation)
+            .unwrap();
+        assert_eq!(key, positions[0].key());
+        assert_eq!(liquidator, Felt::ONE);
+        assert!(
+            zklend
+                .liquidation_from_event(&[*ZKLEND_BORROWING_EVENT], &liquidation)
+                .is_none()
+        );
+
+        // Reserves that are not tracked & other events are ignored.
+        let untracked = [USER, Felt::from(0x99_u64), Felt::ONE, Felt::ONE];
+        assert!(
+            zklend
+                .positions_from_event(&config, &[*ZKLEND_BORROWING_EVENT], &untracked)
+                .is_empty()
+        );
+        assert!(
+            zklend
+                .positions_from_event(&config, &[*TRANSFER_EVENT], &borrowing)
+                .is_empty()
+        );
+    }
+
+    /// Scales a factor of a zkLend reserve.
+    fn factor(value: &str) -> Felt {
+        let scale = BigDecimal::from_str("1e27").unwrap();
+        big_decimal_to_felt((BigDecimal::from_str(value).unwrap() * scale).with_scale(0))
+    }
+
+    fn reserve(collateral_factor: &str, borrow_factor: &str, bonus: &str) -> Vec<Felt> {
+        let mut reserve = vec![Felt::ZERO; 16];
+        reserve[4] = factor(collateral_factor);
+        reserve[5] = factor(borrow_factor);
+        reserve[14] = factor(bonus);
+        reserve
+    }
+
+    #[test]
+    fn test_apply_update_and_find_liquidation() {
+        let (config, zklend) = (config(), zklend());
+        let borrowing = [USER, USDC, Felt::ONE, Felt::ONE];
+        let mut position = zklend
+            .positions_from_event(&config, &[*ZKLEND_BORROWING_EVENT], &borrowing)
+            .remove(0);
+        assert_eq!(zklend.update_requests(&position).unwrap().len(), 5);
+
+        let results = |collateral_enabled: Felt| {
+            vec![
+                vec![Felt::from(2_000_000_000_000_000_000_u128), Felt::ZERO],
+                vec![collateral_enabled],
+                vec![Felt::from(1_500_000_000_u64)],
+                reserve("0.8", "1", "0.1"),
+                reserve("0.8", "0.9", "0.05"),
+            ]
+        };
+        zklend
+            .apply_update(&mut position, &results(Felt::ONE))
+            .unwrap();
+        assert_eq!(position.collateral.amount, BigDecimal::from(2));
+        assert_eq!(position.debt.amount, BigDecimal::from(1_500));
+        assert_eq!(position.lltv, BigDecimal::from_str("0.72").unwrap());
+
+        zklend
+            .apply_update(&mut position, &results(Felt::ZERO))
+            .unwrap();
+        assert_eq!(position.collateral.amount, BigDecimal::from(0));
+        assert!(
+            zklend
+                .apply_update(&mut position, &results(Felt::ONE)[..4])
+                .is_err()
+        );
+
+        // 1.1 ETH seized with a bonus of 10%, 0.1 ETH earned.
+        let liquidation = Event {
+            from_address: MARKET,
+            keys: vec![*ZKLEND_LIQUIDATION_EVENT],
+            data: vec![
+                Felt::ONE,
+                USER,
+                USDC,
+                Felt::from(1_000_u64),
+                Felt::from(1_000_000_000_u64),
+                ETH,
+                Felt::from(1_100_000_000_000_000_000_u128),
+            ],
+        };
+        let outcome = zklend.find_liquidation(&[liquidation]).unwrap();
+        assert_eq!(outcome.user, USER);
+        assert_eq!(outcome.collateral, ETH);
+        assert_eq!(
+            outcome.debt_repaid,
+            U256 {
+                low: 1_000_000_000,
+                high: 0
+            }
+        );
+        assert_eq!(
+            outcome.earnings,
+            U256 {
+                low: 100_000_000_000_000_000,
+                high: 0
+            }
+        );
+    }
+}
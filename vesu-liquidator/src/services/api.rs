@@ -0,0 +1,1008 @@
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+
+use anyhow::Result;
+use axum::{
+    Json, Router,
+    extract::{
+        Path, Query, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::{HeaderMap, StatusCode, header::AUTHORIZATION},
+    response::Response,
+    routing::{get, post},
+};
+use bigdecimal::BigDecimal;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+use tokio::{net::TcpListener, sync::broadcast::error::RecvError, task::JoinSet};
+
+use crate::{
+    config::Config,
+    services::{
+        config_watcher::{ConfigReloader, ReloadReport},
+        operator::Operator,
+        oracle::LatestOraclePrices,
+        tenants::DEFAULT_TENANT,
+    },
+    storages::SharedStorage,
+    types::{
+        accounting::PnlSummary,
+        attestation::PayoutAttestation,
+        balance::{BotBalances, TokenBalance},
+        claims::ClaimProof,
+        competition::CompetitionStats,
+        distribution::PendingDistribution,
+        exposure::ExposureReport,
+        feed::EventFeed,
+        history::{HistoryFilter, LiquidationRecord},
+        indexer::{IndexerLagMetrics, IndexerQueueMetrics, IndexerQueueMonitor},
+        ledger::{Finality, HeldWorldShare, PayoutRecord},
+        operator::OperatorAction,
+        position::{
+            FailedLiquidation, FailedLiquidationStatus, HealthFactorBucket, HealthFactors,
+            Position, PositionKey, PositionsMap,
+        },
+    },
+    utils::{
+        fee_tokens::{FeeTokenMetrics, FeeTokens},
+        serialization::{optional_plain_decimal, plain_decimal},
+        services::Service,
+        shutdown::Shutdown,
+        torii::RedeemModel,
+    },
+};
+
+/// Storages of the tenant games, by tenant name.
+type TenantStorages = Arc<HashMap<String, SharedStorage>>;
+
+/// State of the routes: the live state of the bot, its storage & the ones of
+/// the tenant games.
+#[derive(Clone)]
+struct ApiState {
+    storage: SharedStorage,
+    tenants: TenantStorages,
+    positions: PositionsMap,
+    oracle_prices: LatestOraclePrices,
+    feed: EventFeed,
+    balances: BotBalances,
+    fee_tokens: FeeTokens,
+    health_factors: HealthFactors,
+    indexer_queue: IndexerQueueMonitor,
+    reloader: ConfigReloader,
+    operator: Operator,
+    shutdown: Shutdown,
+}
+
+/// Local HTTP API exposing the state of the bot, e.g. its positions, the
+/// oracle prices or the payouts & claims of the players. Each tenant game is
+/// served under `/tenants/{tenant}`, the routes at the root serving the
+/// [`DEFAULT_TENANT`]. The events of the bot are pushed live on `/events`.
+/// A `POST /reload` reloads the config, see [`ConfigReloader`], & a
+/// `POST /prices/{asset}/resume` trusts a suspect price again. The runbook
+/// actions of the [`Operator`] are served under `/operator` when
+/// `operator.api_token` is set, behind its bearer token.
+#[derive(Clone)]
+pub struct ApiService {
+    config: Config,
+    storage: SharedStorage,
+    tenants: TenantStorages,
+    positions: PositionsMap,
+    oracle_prices: LatestOraclePrices,
+    feed: EventFeed,
+    balances: BotBalances,
+    fee_tokens: FeeTokens,
+    health_factors: HealthFactors,
+    indexer_queue: IndexerQueueMonitor,
+    reloader: ConfigReloader,
+    operator: Operator,
+}
+
+#[async_trait::async_trait]
+impl Service for ApiService {
+    async fn start(
+        &mut self,
+        join_set: &mut JoinSet<anyhow::Result<()>>,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<()> {
+        let service = self.clone();
+        join_set.spawn(async move {
+            tracing::info!("🌐 API service started");
+            service.run_forever(shutdown).await?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+impl ApiService {
+    pub fn new(
+        config: Config,
+        storage: SharedStorage,
+        tenants: HashMap<String, SharedStorage>,
+        positions: PositionsMap,
+        oracle_prices: LatestOraclePrices,
+        feed: EventFeed,
+        balances: BotBalances,
+        fee_tokens: FeeTokens,
+        health_factors: HealthFactors,
+        indexer_queue: IndexerQueueMonitor,
+        reloader: ConfigReloader,
+        operator: Operator,
+    ) -> Self {
+        Self {
+            config,
+            storage,
+            tenants: Arc::new(tenants),
+            positions,
+            oracle_prices,
+            feed,
+            balances,
+            fee_tokens,
+            health_factors,
+            indexer_queue,
+            reloader,
+            operator,
+        }
+    }
+
+    /// Serves the API on `api.listen_address` until the bot shuts down.
+    pub async fn run_forever(&self, shutdown: Shutdown) -> Result<()> {
+        let listen_address: SocketAddr = self.config.api.listen_address.parse()?;
+        let listener = TcpListener::bind(listen_address).await?;
+        tracing::info!("[🌐 API] Listening on {}", listen_address);
+
+        axum::serve(listener, self.router(shutdown.clone()))
+            .with_graceful_shutdown(async move { shutdown.wait().await })
+            .await?;
+        tracing::info!("[🌐 API] 🛑 Stopped");
+        Ok(())
+    }
+
+    fn router(&self, shutdown: Shutdown) -> Router {
+        Router::new()
+            .route("/metrics", get(get_bot_metrics))
+            .route("/positions", get(get_positions))
+            .route("/exposure", get(get_exposure))
+            .route("/prices", get(get_prices))
+            .route("/prices/{asset}/resume", post(resume_price))
+            .route("/balances", get(get_balances))
+            .route("/failed-liquidations", get(get_failed_liquidations))
+            .route("/liquidations", get(get_liquidations))
+            .route("/events", get(subscribe_events))
+            .route("/claims/{address}", get(get_default_claims))
+            .route("/payouts", get(get_default_payouts))
+            .route("/attestations", get(get_default_attestations))
+            .route("/redeem-queue", get(get_default_redeem_queue))
+            .route("/reload", post(reload_config))
+            .route("/operator/pause", post(pause_transactions))
+            .route("/operator/resume", post(resume_transactions))
+            .route("/operator/sweep", post(sweep_funds))
+            .route("/operator/actions", get(get_operator_actions))
+            .route("/tenants/{tenant}/claims/{address}", get(get_claims))
+            .route("/tenants/{tenant}/metrics", get(get_metrics))
+            .route("/tenants/{tenant}/payouts", get(get_payouts))
+            .route("/tenants/{tenant}/attestations", get(get_attestations))
+            .route("/tenants/{tenant}/redeem-queue", get(get_redeem_queue))
+            .with_state(ApiState {
+                storage: self.storage.clone(),
+                tenants: self.tenants.clone(),
+                positions: self.positions.clone(),
+                oracle_prices: self.oracle_prices.clone(),
+                feed: self.feed.clone(),
+                balances: self.balances.clone(),
+                fee_tokens: self.fee_tokens.clone(),
+                health_factors: self.health_factors.clone(),
+                indexer_queue: self.indexer_queue.clone(),
+                reloader: self.reloader.clone(),
+                operator: self.operator.clone(),
+                shutdown,
+            })
+    }
+}
+
+/// Figures of the bot, from its storage.
+#[derive(Serialize)]
+struct BotMetrics {
+    last_block_indexed: u64,
+    positions: usize,
+    closed_positions: usize,
+    dead_letter_positions: usize,
+    /// Liquidations waiting for a retry, & the ones given up.
+    failed_liquidations: usize,
+    abandoned_liquidations: usize,
+    positions_pruned: u64,
+    last_pruned_block: u64,
+    /// Health factors of the positions at their last check.
+    health_factors: Vec<HealthFactorBucket>,
+    /// Backpressure of the events sent by the indexer to the monitoring.
+    indexer_queue: IndexerQueueMetrics,
+    /// Lag of the indexer behind the head of the chain, see `watchdog`.
+    indexer_lag: IndexerLagMetrics,
+    /// Cumulative PnL of the liquidations & of the distributions of all the
+    /// tenant games.
+    pnl: PnlSummary,
+    /// Liquidations of the tracked positions won & lost to the competitors.
+    competition: CompetitionStats,
+    /// Token the transaction fees are paid in.
+    fee_token: FeeTokenMetrics,
+}
+
+/// Figures of a tenant game.
+#[derive(Serialize)]
+struct TenantMetrics {
+    pending_earnings: usize,
+    payouts_recorded: usize,
+    payouts_unchecked: usize,
+    /// Payouts whose transaction is not yet accepted on L1.
+    payouts_accepted_on_l2: usize,
+    held_world_shares: usize,
+    claim_sets: usize,
+    redeem_queue: usize,
+    game_synced_at: u64,
+}
+
+/// A monitored position, with its health at the current oracle prices.
+#[derive(Serialize)]
+struct PositionView {
+    key: PositionKey,
+    #[serde(flatten)]
+    position: Position,
+    closed: bool,
+    /// `None` if a price is missing or stale.
+    #[serde(serialize_with = "optional_plain_decimal")]
+    ltv: Option<BigDecimal>,
+    /// `None` if the position has no debt, or if a price is missing or stale.
+    #[serde(serialize_with = "optional_plain_decimal")]
+    health_factor: Option<BigDecimal>,
+}
+
+/// Latest price of a monitored asset.
+#[derive(Serialize)]
+struct PriceView {
+    asset: String,
+    #[serde(serialize_with = "plain_decimal")]
+    price: BigDecimal,
+    last_updated: u64,
+    /// False if the price is too old to be used to liquidate.
+    fresh: bool,
+    /// True if the price jumped without being corroborated, its liquidations
+    /// being paused.
+    suspect: bool,
+}
+
+/// Balance of the bot in a watched token.
+#[derive(Serialize)]
+struct BalanceView {
+    #[serde(flatten)]
+    balance: TokenBalance,
+    low: bool,
+}
+
+/// Earnings waiting to be distributed & the transfers already sent.
+#[derive(Serialize)]
+struct Payouts {
+    pending: PendingDistribution,
+    past: Vec<PayoutRecord>,
+    /// World shares waiting for their liquidations to be accepted on L1.
+    held: Vec<HeldWorldShare>,
+}
+
+/// Query of the `/payouts` routes.
+#[derive(Deserialize)]
+struct PayoutsQuery {
+    /// Only the payouts sent during this season of the game.
+    season: Option<u32>,
+    /// Only the payouts with this finality, e.g. `accepted_on_l1`.
+    finality: Option<Finality>,
+}
+
+/// Query of the `/attestations` routes.
+#[derive(Deserialize)]
+struct AttestationsQuery {
+    /// Only the payouts of this player, as a hex address.
+    player: Option<String>,
+}
+
+/// Query of the `/exposure` route.
+#[derive(Deserialize)]
+struct ExposureQuery {
+    /// Number of owners listed, the ones with the most debt.
+    #[serde(default = "default_exposure_top")]
+    top: usize,
+}
+
+fn default_exposure_top() -> usize {
+    20
+}
+
+/// Last snapshot of the redeem queue mirrored from Torii.
+#[derive(Serialize)]
+struct RedeemQueueSnapshot {
+    redeem_queue: Vec<RedeemModel>,
+    highest_score: Option<u128>,
+    synced_at: u64,
+}
+
+fn tenant_storage(tenants: &TenantStorages, tenant: &str) -> Result<SharedStorage, StatusCode> {
+    tenants.get(tenant).cloned().ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Returns the figures of the bot, e.g. the positions pruned so far.
+async fn get_bot_metrics(State(state): State<ApiState>) -> Json<BotMetrics> {
+    let pnl = pnl_summary(&state).await;
+    let storage = state.storage.lock().await;
+    let pruning = storage.get_pruning_state();
+    let failed_liquidations = storage.get_failed_liquidations();
+    let abandoned_liquidations = failed_liquidations
+        .iter()
+        .filter(|failed| failed.status == FailedLiquidationStatus::Abandoned)
+        .count();
+    let competition = CompetitionStats::new(
+        &storage.get_liquidation_history(),
+        &storage.get_competitor_liquidations(),
+    );
+    Json(BotMetrics {
+        last_block_indexed: storage.get_last_block_indexed(),
+        positions: storage.get_positions().len(),
+        closed_positions: pruning.closed_since.len(),
+        dead_letter_positions: storage.get_dead_letter_positions().len(),
+        failed_liquidations: failed_liquidations.len() - abandoned_liquidations,
+        abandoned_liquidations,
+        positions_pruned: pruning.positions_pruned,
+        last_pruned_block: pruning.last_pruned_block,
+        health_factors: state.health_factors.histogram(),
+        indexer_queue: state.indexer_queue.metrics(),
+        indexer_lag: state.indexer_queue.lag_metrics(),
+        pnl,
+        competition,
+        fee_token: state.fee_tokens.metrics(),
+    })
+}
+
+/// Sums the PnL of the liquidations, recorded in the main storage, & of the
+/// distributions, recorded in the storage of their tenant game. Without
+/// tenants, the default game shares the main storage.
+async fn pnl_summary(state: &ApiState) -> PnlSummary {
+    let liquidations = state.storage.lock().await.get_pnl_ledger().liquidations;
+    let mut distributions = vec![];
+    for storage in state.tenants.values() {
+        distributions.extend(storage.lock().await.get_pnl_ledger().distributions);
+    }
+    PnlSummary::new(&liquidations, &distributions)
+}
+
+/// Returns the monitored positions, the closest to a liquidation first.
+async fn get_positions(State(state): State<ApiState>) -> Json<Vec<PositionView>> {
+    let positions: Vec<Position> = state
+        .positions
+        .by_key
+        .iter()
+        .map(|entry| entry.value().clone())
+        .collect();
+    let mut views = Vec::with_capacity(positions.len());
+    for position in positions {
+        let ltv = position.ltv(&state.oracle_prices).await.ok();
+        let health_factor = position
+            .health_factor(&state.oracle_prices)
+            .await
+            .ok()
+            .flatten();
+        views.push(PositionView {
+            key: position.key(),
+            closed: position.is_closed(),
+            position,
+            ltv,
+            health_factor,
+        });
+    }
+    // Positions without a health factor come last.
+    views.sort_by(|a, b| match (&a.health_factor, &b.health_factor) {
+        (Some(a), Some(b)) => a.cmp(b),
+        (a, b) => b.is_some().cmp(&a.is_some()),
+    });
+    Json(views)
+}
+
+/// Returns the exposure to the monitored positions per owner & per pool,
+/// e.g. `/exposure?top=5`.
+async fn get_exposure(
+    State(state): State<ApiState>,
+    Query(query): Query<ExposureQuery>,
+) -> Json<ExposureReport> {
+    let positions: Vec<Position> = state
+        .positions
+        .by_key
+        .iter()
+        .map(|entry| entry.value().clone())
+        .collect();
+    Json(ExposureReport::from_prices(
+        &positions,
+        &state.oracle_prices,
+        query.top,
+    ))
+}
+
+/// Returns the latest prices of the monitored assets.
+async fn get_prices(State(state): State<ApiState>) -> Json<Vec<PriceView>> {
+    let prices = state
+        .oracle_prices
+        .snapshot()
+        .into_iter()
+        .map(|(asset, price)| PriceView {
+            fresh: state.oracle_prices.fresh_price(&asset).is_ok(),
+            suspect: state.oracle_prices.is_suspect(&asset),
+            asset,
+            price: price.value,
+            last_updated: price.last_updated,
+        })
+        .collect();
+    Json(prices)
+}
+
+/// Trusts the suspect price of an asset again, resuming its liquidations.
+/// Fails with a 404 if the price of the asset is not suspect.
+async fn resume_price(
+    State(state): State<ApiState>,
+    Path(asset): Path<String>,
+) -> Result<Json<PriceView>, StatusCode> {
+    let asset = asset.to_lowercase();
+    if !state.oracle_prices.resume(&asset) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    tracing::warn!("[🌐 API] {} price resumed manually", asset.to_uppercase());
+    let price = state
+        .oracle_prices
+        .snapshot()
+        .into_iter()
+        .find_map(|(ticker, price)| (ticker == asset).then_some(price))
+        .unwrap_or_default();
+    Ok(Json(PriceView {
+        fresh: state.oracle_prices.fresh_price(&asset).is_ok(),
+        suspect: false,
+        asset,
+        price: price.value,
+        last_updated: price.last_updated,
+    }))
+}
+
+/// Returns the liquidations that failed for a transient reason, waiting for
+/// a retry or abandoned.
+async fn get_failed_liquidations(State(state): State<ApiState>) -> Json<Vec<FailedLiquidation>> {
+    Json(state.storage.lock().await.get_failed_liquidations())
+}
+
+/// Returns the liquidations attempted by the bot matching the filters of the
+/// query, most recent first, e.g. `/liquidations?status=failed&limit=10`.
+async fn get_liquidations(
+    State(state): State<ApiState>,
+    Query(filter): Query<HistoryFilter>,
+) -> Json<Vec<LiquidationRecord>> {
+    let history = state.storage.lock().await.get_liquidation_history();
+    Json(filter.apply(&history))
+}
+
+/// Returns the last known balances of the bot, flagged low below their
+/// threshold.
+async fn get_balances(State(state): State<ApiState>) -> Json<Vec<BalanceView>> {
+    let balances = state
+        .balances
+        .snapshot()
+        .into_iter()
+        .map(|balance| BalanceView {
+            low: balance.is_low(),
+            balance,
+        })
+        .collect();
+    Json(balances)
+}
+
+/// Upgrades the connection to a websocket pushing the events of the feed.
+async fn subscribe_events(State(state): State<ApiState>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| push_events(socket, state.feed, state.shutdown))
+}
+
+/// Pushes the events of the feed as JSON until the client leaves or the bot
+/// shuts down. A client lagging behind misses the oldest events.
+async fn push_events(mut socket: WebSocket, feed: EventFeed, shutdown: Shutdown) {
+    let mut events = feed.subscribe();
+    loop {
+        tokio::select! {
+            _ = shutdown.wait() => break,
+
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Pings are answered by axum, other messages are ignored.
+                Some(Ok(_)) => {}
+            },
+
+            event = events.recv() => {
+                let message = match event {
+                    Ok(message) => message,
+                    Err(RecvError::Lagged(missed)) => {
+                        tracing::warn!("[🌐 API] Events subscriber lagging, {missed} events missed");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                let Ok(json) = serde_json::to_string(&message) else {
+                    continue;
+                };
+                if socket.send(Message::Text(json.into())).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+async fn get_default_claims(
+    State(state): State<ApiState>,
+    Path(address): Path<String>,
+) -> Result<Json<Vec<ClaimProof>>, StatusCode> {
+    get_claims(State(state), Path((DEFAULT_TENANT.to_string(), address))).await
+}
+
+/// Returns the claims of a player with the merkle proofs to submit to the
+/// claims contract.
+async fn get_claims(
+    State(state): State<ApiState>,
+    Path((tenant, address)): Path<(String, String)>,
+) -> Result<Json<Vec<ClaimProof>>, StatusCode> {
+    let storage = tenant_storage(&state.tenants, &tenant)?;
+    let address = Felt::from_hex(&address).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let claim_sets = storage.lock().await.get_claim_sets();
+    let proofs = claim_sets
+        .iter()
+        .flat_map(|claim_set| claim_set.proofs_for(address))
+        .collect();
+    Ok(Json(proofs))
+}
+
+/// Returns the figures of a tenant game, from its storage.
+async fn get_metrics(
+    State(state): State<ApiState>,
+    Path(tenant): Path<String>,
+) -> Result<Json<TenantMetrics>, StatusCode> {
+    let storage = tenant_storage(&state.tenants, &tenant)?;
+    let storage = storage.lock().await;
+    let ledger = storage.get_payout_ledger();
+    let game_state = storage.get_game_state();
+    Ok(Json(TenantMetrics {
+        pending_earnings: storage.get_pending_distribution().earnings.len(),
+        payouts_recorded: ledger.records.len(),
+        payouts_unchecked: ledger
+            .records
+            .iter()
+            .filter(|record| !record.checked)
+            .count(),
+        payouts_accepted_on_l2: ledger
+            .records
+            .iter()
+            .filter(|record| record.finality == Finality::AcceptedOnL2)
+            .count(),
+        held_world_shares: storage.get_held_world_shares().len(),
+        claim_sets: storage.get_claim_sets().len(),
+        redeem_queue: game_state.redeem_queue.len(),
+        game_synced_at: game_state.synced_at,
+    }))
+}
+
+async fn get_default_payouts(
+    State(state): State<ApiState>,
+    query: Query<PayoutsQuery>,
+) -> Result<Json<Payouts>, StatusCode> {
+    get_payouts(State(state), Path(DEFAULT_TENANT.to_string()), query).await
+}
+
+/// Returns the earnings waiting to be distributed to the players of a tenant
+/// game & the payouts already sent to them, of a season if `?season=` is set.
+async fn get_payouts(
+    State(state): State<ApiState>,
+    Path(tenant): Path<String>,
+    Query(query): Query<PayoutsQuery>,
+) -> Result<Json<Payouts>, StatusCode> {
+    let storage = tenant_storage(&state.tenants, &tenant)?;
+    let storage = storage.lock().await;
+    let mut past = storage.get_payout_ledger().records;
+    if let Some(season_id) = query.season {
+        past.retain(|record| record.season_id == Some(season_id));
+    }
+    if let Some(finality) = query.finality {
+        past.retain(|record| record.finality == finality);
+    }
+    Ok(Json(Payouts {
+        pending: storage.get_pending_distribution(),
+        past,
+        held: storage.get_held_world_shares(),
+    }))
+}
+
+async fn get_default_attestations(
+    State(state): State<ApiState>,
+    query: Query<AttestationsQuery>,
+) -> Result<Json<Vec<PayoutAttestation>>, StatusCode> {
+    get_attestations(State(state), Path(DEFAULT_TENANT.to_string()), query).await
+}
+
+/// Returns the signed log of the payouts of a tenant game, oldest first, the
+/// ones of a player if `?player=` is set.
+async fn get_attestations(
+    State(state): State<ApiState>,
+    Path(tenant): Path<String>,
+    Query(query): Query<AttestationsQuery>,
+) -> Result<Json<Vec<PayoutAttestation>>, StatusCode> {
+    let storage = tenant_storage(&state.tenants, &tenant)?;
+    let player = query
+        .player
+        .as_deref()
+        .map(Felt::from_hex)
+        .transpose()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let mut attestations = storage.lock().await.get_payout_attestations();
+    if let Some(player) = player {
+        attestations.retain(|attestation| attestation.payout.player == player);
+    }
+    Ok(Json(attestations))
+}
+
+async fn get_default_redeem_queue(
+    State(state): State<ApiState>,
+) -> Result<Json<RedeemQueueSnapshot>, StatusCode> {
+    get_redeem_queue(State(state), Path(DEFAULT_TENANT.to_string())).await
+}
+
+/// Returns the last snapshot of the redeem queue of a tenant game.
+async fn get_redeem_queue(
+    State(state): State<ApiState>,
+    Path(tenant): Path<String>,
+) -> Result<Json<RedeemQueueSnapshot>, StatusCode> {
+    let storage = tenant_storage(&state.tenants, &tenant)?;
+    let game_state = storage.lock().await.get_game_state();
+    Ok(Json(RedeemQueueSnapshot {
+        redeem_queue: game_state.redeem_queue,
+        highest_score: game_state.highest_score,
+        synced_at: game_state.synced_at,
+    }))
+}
+
+/// Reloads the config file, returning the changes applied & the ones waiting
+/// for a restart. An invalid config file is refused.
+async fn reload_config(
+    State(state): State<ApiState>,
+) -> Result<Json<ReloadReport>, (StatusCode, String)> {
+    state
+        .reloader
+        .reload()
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))
+}
+
+/// Body of the runbook actions of the operators.
+#[derive(Deserialize)]
+struct OperatorRequest {
+    #[serde(default)]
+    reason: String,
+}
+
+/// Checks the bearer token of the `/operator` routes, a 404 if they are not
+/// enabled & a 401 if the token is wrong.
+fn authorize_operator(state: &ApiState, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+    if !state.operator.is_enabled() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "No operator.api_token configured".to_string(),
+        ));
+    }
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !state.operator.is_authorized(token) {
+        tracing::warn!("[🌐 API] Unauthorized operator request refused");
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            "Invalid operator token".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Halts all the transactions of the bot, see [`Operator::pause`].
+async fn pause_transactions(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Json(request): Json<OperatorRequest>,
+) -> Result<Json<OperatorAction>, (StatusCode, String)> {
+    authorize_operator(&state, &headers)?;
+    state
+        .operator
+        .pause(request.reason)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Resumes the transactions of the bot, see [`Operator::resume`].
+async fn resume_transactions(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Json(request): Json<OperatorRequest>,
+) -> Result<Json<OperatorAction>, (StatusCode, String)> {
+    authorize_operator(&state, &headers)?;
+    state
+        .operator
+        .resume(request.reason)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::CONFLICT, e.to_string()))
+}
+
+/// Halts the transactions & sweeps the funds of the bot to the cold address,
+/// see [`Operator::sweep`].
+async fn sweep_funds(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Json(request): Json<OperatorRequest>,
+) -> Result<Json<OperatorAction>, (StatusCode, String)> {
+    authorize_operator(&state, &headers)?;
+    state
+        .operator
+        .sweep(request.reason)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Returns the actions of the operators, oldest first.
+async fn get_operator_actions(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<OperatorAction>>, (StatusCode, String)> {
+    authorize_operator(&state, &headers)?;
+    Ok(Json(state.operator.actions().await))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+    use cainome::cairo_serde::U256;
+    use futures_util::lock::Mutex;
+    use serde_json::Value;
+    use starknet::{
+        core::types::Felt,
+        providers::{JsonRpcClient, jsonrpc::HttpTransport},
+    };
+    use tokio::{net::TcpListener, sync::mpsc::unbounded_channel};
+    use url::Url;
+
+    use super::ApiService;
+    use crate::{
+        cli::NetworkName,
+        config::{Config, LiquidationMode, LiveConfig},
+        services::{
+            config_watcher::ConfigReloader, control::Controls, leader::Leadership,
+            notifier::Notifier, operator::Operator, oracle::LatestOraclePrices,
+            tenants::DEFAULT_TENANT,
+        },
+        storages::{SharedStorage, Storage, json::JsonStorage},
+        types::{
+            account::StarknetAccountBuilder,
+            balance::BotBalances,
+            claims::{Claim, ClaimSet, MerkleTree, verify_proof},
+            feed::EventFeed,
+            indexer::indexer_channel,
+            operator::is_halted,
+            position::{HealthFactors, PositionsMap},
+        },
+        utils::{audit::AuditLog, fee_tokens::FeeTokens, shutdown::Shutdown},
+    };
+
+    /// API of a bot whose storage is a fresh file named after the test, with
+    /// the mainnet config changed by `configure`.
+    fn api_service(name: &str, configure: impl FnOnce(&mut Config)) -> (ApiService, SharedStorage) {
+        let config_path = PathBuf::from("./config.yaml");
+        let mut config =
+            Config::new(NetworkName::Mainnet, LiquidationMode::Full, &config_path).unwrap();
+        configure(&mut config);
+        let dir =
+            std::env::temp_dir().join(format!("vesu-liquidator-{}-{name}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let storage: Box<dyn Storage> =
+            Box::new(JsonStorage::new(dir.join("storage.json").to_str().unwrap()));
+        let storage: SharedStorage = Arc::new(Mutex::new(storage));
+        // No node listens there: the reads of the chain fail.
+        let rpc_client = Arc::new(JsonRpcClient::new(HttpTransport::new(
+            Url::parse("http://localhost:5050").unwrap(),
+        )));
+        let account = StarknetAccountBuilder::new()
+            .on_mainnet()
+            .as_account(Felt::ONE)
+            .with_provider(rpc_client.clone())
+            .from_secret(Felt::ONE)
+            .unwrap();
+        let notifier = Notifier::new(unbounded_channel().0);
+        let audit_log = AuditLog::new(dir.join("audit.log"));
+        let operator = Operator::new(
+            config.clone(),
+            Controls::default(),
+            rpc_client,
+            account,
+            Leadership::default(),
+            storage.clone(),
+            notifier.clone(),
+            audit_log.clone(),
+        );
+        let reloader = ConfigReloader::new(
+            config_path,
+            LiveConfig::new(config.clone()),
+            notifier,
+            audit_log,
+        )
+        .unwrap();
+        let balances = BotBalances::default();
+        let service = ApiService::new(
+            config.clone(),
+            storage.clone(),
+            HashMap::from([(DEFAULT_TENANT.to_string(), storage.clone())]),
+            PositionsMap::new(),
+            LatestOraclePrices::from_config(&config),
+            EventFeed::new(),
+            balances.clone(),
+            FeeTokens::from_config(&config, balances),
+            HealthFactors::default(),
+            indexer_channel(1).0.monitor(),
+            reloader,
+            operator,
+        );
+        (service, storage)
+    }
+
+    /// Serves the API on a free local port, returning its URL.
+    async fn serve(service: &ApiService) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let router = service.router(Shutdown::default());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        Url::parse(&format!("http://{address}/")).unwrap()
+    }
+
+    async fn get(url: Url) -> (reqwest::StatusCode, Value) {
+        decode(reqwest::get(url).await.unwrap()).await
+    }
+
+    /// Sends a runbook action, authenticated with the token if any.
+    async fn post_action(url: Url, token: Option<&str>) -> (reqwest::StatusCode, Value) {
+        let mut request = reqwest::Client::new()
+            .post(url)
+            .json(&serde_json::json!({ "reason": "test" }));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        decode(request.send().await.unwrap()).await
+    }
+
+    async fn decode(response: reqwest::Response) -> (reqwest::StatusCode, Value) {
+        let status = response.status();
+        (status, response.json().await.unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn test_claims_routes() {
+        let (service, storage) = api_service("api-claims", |_| {});
+        let claim = |recipient: u64, token: u64, low| Claim {
+            recipient: Felt::from(recipient),
+            token: Felt::from(token),
+            amount: U256 { low, high: 0 },
+        };
+        let claims = vec![claim(1, 10, 100), claim(2, 10, 200), claim(1, 11, 300)];
+        let root = MerkleTree::new(claims.iter().map(Claim::leaf).collect()).root();
+        storage
+            .lock()
+            .await
+            .save_claim_set(&ClaimSet {
+                root,
+                tx_hash: Felt::ONE,
+                published_at: 0,
+                claims: claims.clone(),
+            })
+            .await
+            .unwrap();
+        let url = serve(&service).await;
+
+        // The claims of the player, with the proofs verified by the contract.
+        for route in ["claims/0x1", "tenants/default/claims/0x1"] {
+            let (status, proofs) = get(url.join(route).unwrap()).await;
+            assert_eq!(status, reqwest::StatusCode::OK);
+            let proofs = proofs.as_array().unwrap();
+            assert_eq!(proofs.len(), 2);
+            for (proof, expected) in proofs.iter().zip([&claims[0], &claims[2]]) {
+                let claim: Claim = serde_json::from_value(proof["claim"].clone()).unwrap();
+                let proof_root: Felt = serde_json::from_value(proof["root"].clone()).unwrap();
+                let siblings: Vec<Felt> = serde_json::from_value(proof["proof"].clone()).unwrap();
+                assert_eq!(&claim, expected);
+                assert_eq!(proof_root, root);
+                assert!(verify_proof(root, claim.leaf(), &siblings));
+            }
+        }
+
+        let (status, proofs) = get(url.join("claims/0x3").unwrap()).await;
+        assert_eq!(status, reqwest::StatusCode::OK);
+        assert_eq!(proofs, Value::Array(vec![]));
+        let (status, _) = get(url.join("claims/not-an-address").unwrap()).await;
+        assert_eq!(status, reqwest::StatusCode::BAD_REQUEST);
+        let (status, _) = get(url.join("tenants/unknown/claims/0x1").unwrap()).await;
+        assert_eq!(status, reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_operator_routes() {
+        let (service, _) = api_service("api-operator-disabled", |_| {});
+        let url = serve(&service).await;
+        let (status, _) = post_action(url.join("operator/pause").unwrap(), Some("s3cret")).await;
+        assert_eq!(status, reqwest::StatusCode::NOT_FOUND);
+
+        let (service, _) = api_service("api-operator", |config| {
+            config.operator.api_token = Some("s3cret".to_string());
+        });
+        let url = serve(&service).await;
+        for token in [None, Some("wrong")] {
+            for action in ["pause", "resume", "sweep"] {
+                let route = url.join(&format!("operator/{action}")).unwrap();
+                let (status, _) = post_action(route, token).await;
+                assert_eq!(status, reqwest::StatusCode::UNAUTHORIZED);
+            }
+        }
+
+        let (status, action) =
+            post_action(url.join("operator/pause").unwrap(), Some("s3cret")).await;
+        assert_eq!(status, reqwest::StatusCode::OK);
+        assert_eq!(action["kind"], "pause");
+        let (status, action) =
+            post_action(url.join("operator/resume").unwrap(), Some("s3cret")).await;
+        assert_eq!(status, reqwest::StatusCode::OK);
+        assert_eq!(action["kind"], "resume");
+        // Nowhere to sweep the funds to.
+        let (status, _) = post_action(url.join("operator/sweep").unwrap(), Some("s3cret")).await;
+        assert_eq!(status, reqwest::StatusCode::INTERNAL_SERVER_ERROR);
+
+        let actions = reqwest::Client::new()
+            .get(url.join("operator/actions").unwrap())
+            .bearer_auth("s3cret")
+            .send()
+            .await
+            .unwrap();
+        let (status, actions) = decode(actions).await;
+        assert_eq!(status, reqwest::StatusCode::OK);
+        let kinds: Vec<_> = actions
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|action| action["kind"].clone())
+            .collect();
+        assert_eq!(kinds, vec!["pause", "resume"]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_sweep_is_recorded() {
+        let (service, storage) = api_service("api-operator-sweep", |config| {
+            config.operator.api_token = Some("s3cret".to_string());
+            config.cold_address = Some(Felt::from(0xc01d_u64));
+        });
+        let url = serve(&service).await;
+
+        // The balances can't be read without a node.
+        let (status, _) = post_action(url.join("operator/sweep").unwrap(), Some("s3cret")).await;
+        assert_eq!(status, reqwest::StatusCode::INTERNAL_SERVER_ERROR);
+        let actions = storage.lock().await.get_operator_actions();
+        assert_eq!(actions.len(), 1);
+        assert!(actions[0].error.is_some());
+        // Nothing was swept, nothing is expected by the reconciliation.
+        assert!(storage.lock().await.get_payout_ledger().records.is_empty());
+        // The transactions stay halted until resumed.
+        assert!(is_halted(&actions));
+    }
+}
@@ -0,0 +1,343 @@
+use std::{path::PathBuf, sync::Arc, time::Duration, time::SystemTime};
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::{sync::Mutex, task::JoinSet, time::interval};
+
+use crate::{
+    cli::NetworkName,
+    config::{Config, LiveConfig, is_reloadable, load_raw_config},
+    services::notifier::Notifier,
+    types::notification::Severity,
+    utils::{
+        audit::{AuditEvent, AuditLog},
+        config_diff::{ConfigChange, diff_configs},
+        services::Service,
+        shutdown::{ReloadSignal, Shutdown},
+    },
+};
+
+/// Interval at which the config file is checked for modifications.
+const CHECK_CONFIG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Watches the config file & reports what changed in it: each modification
+/// is logged, notified & recorded in the audit log, with its secrets redacted.
+/// The changes are applied by the next restart of the bot, or by a reload on
+/// SIGHUP for the reloadable ones, see [`ConfigReloader`].
+#[derive(Clone)]
+pub struct ConfigWatcherService {
+    config_path: PathBuf,
+    network: NetworkName,
+    notifier: Notifier,
+    audit_log: AuditLog,
+    reloader: ConfigReloader,
+}
+
+#[async_trait::async_trait]
+impl Service for ConfigWatcherService {
+    async fn start(
+        &mut self,
+        join_set: &mut JoinSet<anyhow::Result<()>>,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<()> {
+        let service = self.clone();
+        join_set.spawn(async move {
+            tracing::info!("📝 Config watcher service started");
+            service.run_forever(shutdown).await?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+impl ConfigWatcherService {
+    pub fn new(
+        config_path: PathBuf,
+        network: NetworkName,
+        notifier: Notifier,
+        audit_log: AuditLog,
+        reloader: ConfigReloader,
+    ) -> Self {
+        Self {
+            config_path,
+            network,
+            notifier,
+            audit_log,
+            reloader,
+        }
+    }
+
+    pub async fn run_forever(&self, shutdown: Shutdown) -> Result<()> {
+        let mut check_interval = interval(CHECK_CONFIG_INTERVAL);
+        let mut modified_at = self.modified_at();
+        let mut current = load_raw_config(&self.config_path, self.network)?;
+        let mut reload_signal = ReloadSignal::new()?;
+
+        loop {
+            tokio::select! {
+                _ = shutdown.wait() => {
+                    tracing::info!("[📝 Config] 🛑 Stopped watching the config");
+                    return Ok(());
+                }
+
+                _ = reload_signal.recv() => {
+                    tracing::info!("[📝 Config] SIGHUP received, reloading the config");
+                    // A failed reload is reported & the current config kept.
+                    let _ = self.reloader.reload().await;
+                }
+
+                _ = check_interval.tick() => {
+                    let last_modified_at = self.modified_at();
+                    if last_modified_at == modified_at {
+                        continue;
+                    }
+                    modified_at = last_modified_at;
+
+                    match load_raw_config(&self.config_path, self.network) {
+                        Ok(new) => {
+                            self.report_changes(&current, &new)?;
+                            current = new;
+                        }
+                        Err(e) => {
+                            tracing::warn!(error = %e, "[📝 Config] Ignoring invalid config file modification");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn modified_at(&self) -> Option<SystemTime> {
+        std::fs::metadata(&self.config_path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    /// Logs, notifies & records in the audit log the changed fields.
+    fn report_changes(&self, old: &Value, new: &Value) -> Result<()> {
+        let changes = diff_configs(old, new);
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        for change in changes.iter() {
+            tracing::info!("[📝 Config] Changed {}", change);
+        }
+        let summary: Vec<String> = changes.iter().map(ToString::to_string).collect();
+        self.notifier.notify(
+            Severity::Warning,
+            format!(
+                "Config file {} changed:\n{}",
+                self.config_path.display(),
+                summary.join("\n")
+            ),
+        );
+        self.audit_log.record(&AuditEvent::ConfigChanged {
+            config_path: self.config_path.display().to_string(),
+            changes,
+        })
+    }
+}
+
+/// Fields changed by a reload: the ones applied & the ones waiting for the
+/// next restart of the bot.
+#[derive(Debug, Serialize)]
+pub struct ReloadReport {
+    pub applied: Vec<ConfigChange>,
+    pub pending_restart: Vec<ConfigChange>,
+}
+
+/// Reloads the config file into the [`LiveConfig`] of the running bot, on
+/// SIGHUP or on a `POST /reload` of the API. Only the
+/// [`crate::config::RELOADABLE_FIELDS`] are applied, without restarting the
+/// services nor interrupting the in-flight liquidations. An invalid config
+/// file is refused as a whole.
+#[derive(Clone)]
+pub struct ConfigReloader {
+    config_path: PathBuf,
+    live: LiveConfig,
+    /// Config file when the bot started, the other changes waiting for a
+    /// restart.
+    started: Arc<Value>,
+    /// Config file as of the last reload, locked while reloading.
+    reloaded: Arc<Mutex<Value>>,
+    notifier: Notifier,
+    audit_log: AuditLog,
+}
+
+impl ConfigReloader {
+    pub fn new(
+        config_path: PathBuf,
+        live: LiveConfig,
+        notifier: Notifier,
+        audit_log: AuditLog,
+    ) -> Result<Self> {
+        let started = load_raw_config(&config_path, live.current().network)?;
+        Ok(Self {
+            config_path,
+            live,
+            reloaded: Arc::new(Mutex::new(started.clone())),
+            started: Arc::new(started),
+            notifier,
+            audit_log,
+        })
+    }
+
+    /// Reads the config file & applies its reloadable fields, reporting the
+    /// changes. The current config is kept if the file is invalid.
+    pub async fn reload(&self) -> Result<ReloadReport> {
+        let mut reloaded = self.reloaded.lock().await;
+        let current = self.live.current();
+        let loaded = load_raw_config(&self.config_path, current.network).and_then(|raw| {
+            let config = Config::new(current.network, current.liquidation_mode, &self.config_path)?;
+            Ok((raw, config))
+        });
+        let (raw, config) = match loaded {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                tracing::warn!(error = %e, "[📝 Config] Invalid config file, reload refused");
+                self.notifier.notify(
+                    Severity::Warning,
+                    format!("Config reload refused, the current config is kept: {e}"),
+                );
+                return Err(e);
+            }
+        };
+        self.live.reload(&config);
+
+        let report = ReloadReport {
+            applied: diff_configs(&reloaded, &raw)
+                .into_iter()
+                .filter(|change| is_reloadable(&change.key))
+                .collect(),
+            pending_restart: diff_configs(&self.started, &raw)
+                .into_iter()
+                .filter(|change| !is_reloadable(&change.key))
+                .collect(),
+        };
+        *reloaded = raw;
+        self.report(&report)?;
+        Ok(report)
+    }
+
+    /// Logs, notifies & records in the audit log the reloaded fields.
+    fn report(&self, report: &ReloadReport) -> Result<()> {
+        for change in report.applied.iter() {
+            tracing::info!("[📝 Config] Reloaded {}", change);
+        }
+        for change in report.pending_restart.iter() {
+            tracing::warn!("[📝 Config] Waiting for a restart: {}", change);
+        }
+        let summary: Vec<String> = report.applied.iter().map(ToString::to_string).collect();
+        let mut message = format!(
+            "Config {} reloaded, {} change(s) applied",
+            self.config_path.display(),
+            report.applied.len()
+        );
+        if !summary.is_empty() {
+            message.push_str(&format!(":\n{}", summary.join("\n")));
+        }
+        if !report.pending_restart.is_empty() {
+            message.push_str(&format!(
+                "\n{} change(s) waiting for a restart",
+                report.pending_restart.len()
+            ));
+        }
+        self.notifier.notify(Severity::Info, message);
+        self.audit_log.record(&AuditEvent::ConfigReloaded {
+            config_path: self.config_path.display().to_string(),
+            applied: report.applied.clone(),
+            pending_restart: report.pending_restart.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::ConfigReloader;
+    use crate::{
+        cli::NetworkName,
+        config::{Config, LiquidationMode, LiveConfig, is_reloadable},
+        services::notifier::Notifier,
+        utils::audit::AuditLog,
+    };
+
+    #[test]
+    fn test_is_reloadable() {
+        assert!(is_reloadable("distribution.player_bps"));
+        assert!(is_reloadable("filters.excluded_assets.0"));
+        assert!(is_reloadable("filters"));
+        assert!(is_reloadable("liquidation_cooldown.cooldown_seconds"));
+        assert!(!is_reloadable("filters_extra"));
+        assert!(!is_reloadable("distribution.strategy"));
+        assert!(!is_reloadable("api.listen_address"));
+    }
+
+    #[tokio::test]
+    async fn test_reload_config() {
+        let dir =
+            std::env::temp_dir().join(format!("vesu-liquidator-{}-reload", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("config.yaml");
+        std::fs::copy("./config.yaml", &config_path).unwrap();
+        let config =
+            Config::new(NetworkName::Mainnet, LiquidationMode::Full, &config_path).unwrap();
+        let live = LiveConfig::new(config);
+        let changes = live.subscribe();
+        let reloader = ConfigReloader::new(
+            config_path.clone(),
+            live.clone(),
+            Notifier::new(unbounded_channel().0),
+            AuditLog::new(dir.join("audit.log")),
+        )
+        .unwrap();
+
+        let edit = |edit: &dyn Fn(&mut serde_yaml::Value)| {
+            let mut raw: serde_yaml::Value =
+                serde_yaml::from_str(&std::fs::read_to_string(&config_path).unwrap()).unwrap();
+            edit(&mut raw);
+            std::fs::write(&config_path, serde_yaml::to_string(&raw).unwrap()).unwrap();
+        };
+        edit(&|raw: &mut serde_yaml::Value| {
+            raw["distribution"]["player_bps"] = 9_000.into();
+            raw["distribution"]["world_bps"] = 1_000.into();
+            raw["filters"]["excluded_assets"] = vec!["eth"].into();
+            raw["api"]["listen_address"] = "127.0.0.1:4000".into();
+        });
+        let report = reloader.reload().await.unwrap();
+        let keys = |changes: &[crate::utils::config_diff::ConfigChange]| {
+            changes.iter().map(|c| c.key.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            keys(&report.applied),
+            [
+                "distribution.player_bps",
+                "distribution.world_bps",
+                "filters.excluded_assets.0"
+            ]
+        );
+        assert_eq!(keys(&report.pending_restart), ["api.listen_address"]);
+        assert!(changes.has_changed().unwrap());
+        let current = live.current();
+        assert_eq!(current.distribution.player_bps, 9_000);
+        assert_eq!(current.position_filter.excluded_assets.len(), 1);
+        // The other fields wait for a restart.
+        assert_eq!(current.api.listen_address, "127.0.0.1:3000");
+
+        // An invalid split is refused as a whole.
+        edit(&|raw: &mut serde_yaml::Value| {
+            raw["distribution"]["player_bps"] = 5_000.into();
+            raw["monitoring"]["watchlist_margin_bps"] = 100.into();
+        });
+        assert!(reloader.reload().await.is_err());
+        assert_eq!(live.current().distribution.player_bps, 9_000);
+        assert_eq!(live.current().monitoring.watchlist_margin_bps, 500);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,440 @@
+use std::{
+    net::SocketAddr,
+    pin::Pin,
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use anyhow::Result;
+use bigdecimal::BigDecimal;
+use tokio::{sync::Notify, task::JoinSet};
+use tokio_stream::{
+    Stream, StreamExt,
+    wrappers::{BroadcastStream, errors::BroadcastStreamRecvError},
+};
+use tonic::{Request, Response, Status, transport::Server};
+
+use crate::{
+    config::{Config, LiveConfig},
+    services::notifier::Notifier,
+    types::{
+        feed::{EventFeed, FeedEvent, FeedMessage},
+        notification::Severity,
+        position::PositionsMap,
+    },
+    utils::{conversions::u256_to_big_uint, services::Service, shutdown::Shutdown},
+};
+
+#[allow(clippy::all)]
+pub mod proto {
+    tonic::include_proto!("vesu_liquidator.control.v1");
+}
+
+use proto::{
+    ControlState, Event, GetStateRequest, Liquidation, PauseRequest, Payout, ResumeRequest,
+    ScanRequest, ScanResponse, SetThresholdsRequest, StreamEventsRequest, Thresholds,
+    control_server::{Control, ControlServer},
+    event,
+};
+
+/// Switches of the operators over the running bot, shared by the control
+/// plane & the monitoring.
+#[derive(Clone, Default)]
+pub struct Controls {
+    paused: Arc<AtomicBool>,
+    halted: Arc<AtomicBool>,
+    scans: Arc<Notify>,
+}
+
+impl Controls {
+    /// Whether the liquidations are held back, the positions still being
+    /// monitored.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Returns true if the state changed.
+    pub fn set_paused(&self, paused: bool) -> bool {
+        self.paused.swap(paused, Ordering::Relaxed) != paused
+    }
+
+    /// Whether all the transactions of the bot are held back by an operator,
+    /// see [`crate::services::operator::Operator`].
+    pub fn is_halted(&self) -> bool {
+        self.halted.load(Ordering::Relaxed)
+    }
+
+    /// Returns true if the state changed.
+    pub fn set_halted(&self, halted: bool) -> bool {
+        self.halted.swap(halted, Ordering::Relaxed) != halted
+    }
+
+    pub fn request_scan(&self) {
+        self.scans.notify_one();
+    }
+
+    /// Completes once a scan is requested, a request made while nobody waits
+    /// being kept for the next call.
+    pub async fn scan_requested(&self) {
+        self.scans.notified().await;
+    }
+}
+
+/// gRPC control plane of the bot on `grpc.listen_address`, for the backend
+/// services of the game: it pauses & resumes the liquidations, adjusts the
+/// thresholds of the monitoring, triggers a scan of all the positions &
+/// streams the liquidations & payouts of the [`EventFeed`].
+#[derive(Clone)]
+pub struct ControlService {
+    config: Config,
+    controls: Controls,
+    live: LiveConfig,
+    positions: PositionsMap,
+    feed: EventFeed,
+    notifier: Notifier,
+}
+
+#[async_trait::async_trait]
+impl Service for ControlService {
+    async fn start(
+        &mut self,
+        join_set: &mut JoinSet<anyhow::Result<()>>,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<()> {
+        let service = self.clone();
+        join_set.spawn(async move {
+            tracing::info!("🕹️ Control service started");
+            service.run_forever(shutdown).await?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+impl ControlService {
+    pub fn new(
+        config: Config,
+        controls: Controls,
+        live: LiveConfig,
+        positions: PositionsMap,
+        feed: EventFeed,
+        notifier: Notifier,
+    ) -> Self {
+        Self {
+            config,
+            controls,
+            live,
+            positions,
+            feed,
+            notifier,
+        }
+    }
+
+    /// Serves the control plane until the bot shuts down.
+    pub async fn run_forever(&self, shutdown: Shutdown) -> Result<()> {
+        let listen_address: SocketAddr = self.config.grpc.listen_address.parse()?;
+        tracing::info!("[🕹️ Control] Listening on {}", listen_address);
+
+        Server::builder()
+            .add_service(ControlServer::new(self.clone()))
+            .serve_with_shutdown(listen_address, async move { shutdown.wait().await })
+            .await?;
+        tracing::info!("[🕹️ Control] 🛑 Stopped");
+        Ok(())
+    }
+
+    fn state(&self) -> ControlState {
+        ControlState {
+            paused: self.controls.is_paused(),
+            thresholds: Some(thresholds(&self.live.current())),
+        }
+    }
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<Event, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Control for ControlService {
+    type StreamEventsStream = EventStream;
+
+    async fn pause(
+        &self,
+        request: Request<PauseRequest>,
+    ) -> Result<Response<ControlState>, Status> {
+        if self.controls.set_paused(true) {
+            let reason = request.into_inner().reason;
+            tracing::warn!("[🕹️ Control] ⏸️ Liquidations paused: {}", reason);
+            self.notifier.notify(
+                Severity::Warning,
+                format!("⏸️ Liquidations paused by the control plane: {reason}"),
+            );
+        }
+        Ok(Response::new(self.state()))
+    }
+
+    async fn resume(
+        &self,
+        _request: Request<ResumeRequest>,
+    ) -> Result<Response<ControlState>, Status> {
+        if self.controls.set_paused(false) {
+            tracing::info!("[🕹️ Control] ▶️ Liquidations resumed");
+            self.notifier.notify(
+                Severity::Info,
+                "▶️ Liquidations resumed by the control plane",
+            );
+        }
+        Ok(Response::new(self.state()))
+    }
+
+    async fn get_state(
+        &self,
+        _request: Request<GetStateRequest>,
+    ) -> Result<Response<ControlState>, Status> {
+        Ok(Response::new(self.state()))
+    }
+
+    async fn set_thresholds(
+        &self,
+        request: Request<SetThresholdsRequest>,
+    ) -> Result<Response<Thresholds>, Status> {
+        let request = request.into_inner();
+        let changes = ThresholdChanges::parse(request)?;
+        let config = self.live.update(|config| changes.apply(config));
+        let thresholds = thresholds(&config);
+        tracing::info!("[🕹️ Control] Thresholds set: {:?}", thresholds);
+        Ok(Response::new(thresholds))
+    }
+
+    async fn scan(&self, _request: Request<ScanRequest>) -> Result<Response<ScanResponse>, Status> {
+        self.controls.request_scan();
+        let positions = self.positions.len() as u64;
+        tracing::info!(
+            "[🕹️ Control] Scan of the {} position(s) requested",
+            positions
+        );
+        Ok(Response::new(ScanResponse { positions }))
+    }
+
+    async fn stream_events(
+        &self,
+        request: Request<StreamEventsRequest>,
+    ) -> Result<Response<Self::StreamEventsStream>, Status> {
+        let tenant = request.into_inner().tenant;
+        let events =
+            BroadcastStream::new(self.feed.subscribe()).filter_map(move |message| match message {
+                Ok(message) if tenant.is_none() || message.tenant == tenant => {
+                    to_event(message).map(Ok)
+                }
+                Ok(_) => None,
+                Err(BroadcastStreamRecvError::Lagged(missed)) => {
+                    tracing::warn!(
+                        "[🕹️ Control] Events subscriber lagging, {} event(s) missed",
+                        missed
+                    );
+                    None
+                }
+            });
+        Ok(Response::new(Box::pin(events) as Self::StreamEventsStream))
+    }
+}
+
+fn thresholds(config: &Config) -> Thresholds {
+    Thresholds {
+        watchlist_margin_bps: config.monitoring.watchlist_margin_bps,
+        at_risk_margin_bps: config.monitoring.at_risk_margin_bps,
+        urgent_ltv_excess: config.gas_guard.urgent_ltv_excess.to_plain_string(),
+        spike_min_expected_profit_usd: config
+            .gas_guard
+            .spike_min_expected_profit_usd
+            .to_plain_string(),
+    }
+}
+
+/// Thresholds of a [`SetThresholdsRequest`], validated before any is applied.
+#[derive(Debug, Default, PartialEq)]
+struct ThresholdChanges {
+    watchlist_margin_bps: Option<u32>,
+    at_risk_margin_bps: Option<u32>,
+    urgent_ltv_excess: Option<BigDecimal>,
+    spike_min_expected_profit_usd: Option<BigDecimal>,
+}
+
+impl ThresholdChanges {
+    fn parse(request: SetThresholdsRequest) -> Result<Self, Status> {
+        for (name, bps) in [
+            ("watchlist_margin_bps", request.watchlist_margin_bps),
+            ("at_risk_margin_bps", request.at_risk_margin_bps),
+        ] {
+            if bps.is_some_and(|bps| bps > 10_000) {
+                return Err(Status::invalid_argument(format!(
+                    "{name} must be at most 10000"
+                )));
+            }
+        }
+        Ok(Self {
+            watchlist_margin_bps: request.watchlist_margin_bps,
+            at_risk_margin_bps: request.at_risk_margin_bps,
+            urgent_ltv_excess: parse_decimal("urgent_ltv_excess", request.urgent_ltv_excess)?,
+            spike_min_expected_profit_usd: parse_decimal(
+                "spike_min_expected_profit_usd",
+                request.spike_min_expected_profit_usd,
+            )?,
+        })
+    }
+
+    fn apply(self, config: &mut Config) {
+        if let Some(bps) = self.watchlist_margin_bps {
+            config.monitoring.watchlist_margin_bps = bps;
+        }
+        if let Some(bps) = self.at_risk_margin_bps {
+            config.monitoring.at_risk_margin_bps = bps;
+        }
+        if let Some(excess) = self.urgent_ltv_excess {
+            config.gas_guard.urgent_ltv_excess = excess;
+        }
+        if let Some(profit) = self.spike_min_expected_profit_usd {
+            config.gas_guard.spike_min_expected_profit_usd = profit;
+        }
+    }
+}
+
+/// Parses a non-negative decimal threshold.
+fn parse_decimal(name: &str, value: Option<String>) -> Result<Option<BigDecimal>, Status> {
+    value
+        .map(|value| {
+            BigDecimal::from_str(&value)
+                .ok()
+                .filter(|value| *value >= BigDecimal::from(0))
+                .ok_or_else(|| {
+                    Status::invalid_argument(format!(
+                        "{name} must be a non-negative decimal, got {value:?}"
+                    ))
+                })
+        })
+        .transpose()
+}
+
+/// The liquidations & payouts of the feed, the other events not being streamed.
+fn to_event(message: FeedMessage) -> Option<Event> {
+    let usd = |usd: Option<BigDecimal>| usd.map(|usd| usd.to_plain_string()).unwrap_or_default();
+    let event = match message.event {
+        FeedEvent::LiquidationExecuted {
+            position_key,
+            tx_hash,
+            token,
+            amount,
+            usd: value,
+        } => event::Event::Liquidation(Liquidation {
+            position_key: format!("{position_key:?}"),
+            tx_hash: format!("{tx_hash:#x}"),
+            token: format!("{token:#x}"),
+            amount: u256_to_big_uint(&amount).to_string(),
+            usd: usd(value),
+        }),
+        FeedEvent::PlayerRewarded {
+            player,
+            amount,
+            token,
+            usd: value,
+            tx_hash,
+        } => event::Event::Payout(Payout {
+            player: format!("{player:#x}"),
+            token: format!("{token:#x}"),
+            amount: u256_to_big_uint(&amount).to_string(),
+            usd: usd(value),
+            tx_hash: format!("{tx_hash:#x}"),
+        }),
+        FeedEvent::HighScoreUsed { .. } | FeedEvent::PositionAtRisk { .. } => return None,
+    };
+    Some(Event {
+        tenant: message.tenant,
+        timestamp: message.timestamp,
+        event: Some(event),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+    use cainome::cairo_serde::U256;
+    use starknet::core::types::Felt;
+
+    use super::{
+        ThresholdChanges,
+        proto::{SetThresholdsRequest, event},
+        to_event,
+    };
+    use crate::types::feed::{FeedEvent, FeedMessage};
+
+    #[test]
+    fn test_threshold_changes_validated() {
+        let changes = ThresholdChanges::parse(SetThresholdsRequest {
+            watchlist_margin_bps: Some(300),
+            spike_min_expected_profit_usd: Some("2.5".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            changes,
+            ThresholdChanges {
+                watchlist_margin_bps: Some(300),
+                spike_min_expected_profit_usd: Some("2.5".parse::<BigDecimal>().unwrap()),
+                ..Default::default()
+            }
+        );
+
+        for request in [
+            SetThresholdsRequest {
+                at_risk_margin_bps: Some(10_001),
+                ..Default::default()
+            },
+            SetThresholdsRequest {
+                urgent_ltv_excess: Some("-0.1".to_string()),
+                ..Default::default()
+            },
+            SetThresholdsRequest {
+                spike_min_expected_profit_usd: Some("a lot".to_string()),
+                ..Default::default()
+            },
+        ] {
+            assert!(ThresholdChanges::parse(request).is_err());
+        }
+    }
+
+    #[test]
+    fn test_only_liquidations_and_payouts_streamed() {
+        let payout = to_event(FeedMessage {
+            tenant: Some("arcade".to_string()),
+            timestamp: 1_718_000_000,
+            event: FeedEvent::PlayerRewarded {
+                player: Felt::ONE,
+                amount: U256 { low: 1500, high: 0 },
+                token: Felt::TWO,
+                usd: None,
+                tx_hash: Felt::from(0xabc_u64),
+            },
+        })
+        .unwrap();
+        assert_eq!(payout.tenant.as_deref(), Some("arcade"));
+        let Some(event::Event::Payout(payout)) = payout.event else {
+            panic!("expected a payout");
+        };
+        assert_eq!(payout.player, "0x1");
+        assert_eq!(payout.amount, "1500");
+        assert_eq!(payout.usd, "");
+        assert_eq!(payout.tx_hash, "0xabc");
+
+        let high_score = to_event(FeedMessage {
+            tenant: None,
+            timestamp: 1_718_000_000,
+            event: FeedEvent::HighScoreUsed {
+                score: 42,
+                tx_hash: Felt::from(0xabc_u64),
+            },
+        });
+        assert_eq!(high_score, None);
+    }
+}
@@ -0,0 +1,1111 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::{Result, anyhow};
+use bigdecimal::BigDecimal;
+use cainome::cairo_serde::{ContractAddress, U256};
+use futures_util::lock::Mutex;
+use starknet::{
+    core::types::{Call, ExecutionResult, FeePayment, Felt},
+    providers::{JsonRpcClient, jsonrpc::HttpTransport},
+};
+use tokio::{sync::mpsc::UnboundedReceiver, task::JoinSet, time::interval};
+
+use crate::{
+    bindings::actions::Actions,
+    config::{Config, LiveConfig, PayoutRouting, TRANSFER_SELECTOR},
+    services::{
+        leader::Leadership,
+        notifier::Notifier,
+        oracle::LatestOraclePrices,
+        rewards::{PlayerPayout, RewardContext, RewardPlan, RewardStrategy, reward_strategy},
+        tokens::TokenRegistry,
+    },
+    storages::SharedStorage,
+    types::{
+        account::{BotAccount, StarknetAccount},
+        accounting::{DistributionPnl, PayoutPnl, SwapPnl, Valuator},
+        attestation::AttestedPayout,
+        claims::ClaimSet,
+        distribution::{LiquidationEarnings, PendingDistribution, is_valid_recipient},
+        feed::{EventFeed, FeedEvent},
+        game::GameMirror,
+        ledger::{DustLedger, Finality, HeldWorldShare, PayoutRecord, RecentPayouts},
+        notification::Severity,
+    },
+    utils::{
+        attestations::PayoutAttester,
+        constants::U256_ZERO,
+        conversions::big_uint_to_u256,
+        fee_tokens::FeeTokens,
+        http::HttpClient,
+        is_accepted_on_l1,
+        messages::{PayoutMessage, PayoutMessenger},
+        paymaster::PaymasterClient,
+        services::Service,
+        shutdown::Shutdown,
+        slo::SloTracker,
+        swap::{SwapProvider, quote_swap, swap_providers},
+        tx_manager::{Simulation, TxManager},
+        unix_now,
+    },
+};
+
+/// Bindings of the actions contract of the game, building its calls.
+type ActionsContract = Actions<Arc<BotAccount>>;
+
+/// Interval at which we check if the batching window is closed.
+const CHECK_WINDOW_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Distributes the liquidations earnings to the players of the game.
+/// Earnings are batched according to the configured distribution window.
+#[derive(Clone)]
+pub struct DistributionService {
+    config: Config,
+    /// The reloadable fields of the config, e.g. the split of the earnings.
+    live: LiveConfig,
+    rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+    tx_manager: TxManager,
+    earnings_receiver: Arc<Mutex<UnboundedReceiver<LiquidationEarnings>>>,
+    pending: Arc<Mutex<PendingDistribution>>,
+    storage: SharedStorage,
+    notifier: Notifier,
+    slo: SloTracker,
+    strategy: Arc<dyn RewardStrategy>,
+    context: RewardContext,
+    feed: EventFeed,
+    swap_providers: Arc<Vec<Box<dyn SwapProvider>>>,
+    account_address: Felt,
+    tokens: TokenRegistry,
+    valuator: Valuator,
+    messenger: Option<PayoutMessenger>,
+    attester: PayoutAttester,
+    leadership: Leadership,
+    controls: Controls,
+}
+
+#[async_trait::async_trait]
+impl Service for DistributionService {
+    async fn start(
+        &mut self,
+        join_set: &mut JoinSet<anyhow::Result<()>>,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<()> {
+        let service = self.clone();
+        join_set.spawn(async move {
+            tracing::info!("💸 Distribution service started");
+            service.run_forever(shutdown).await?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+impl DistributionService {
+    pub fn new(
+        config: Config,
+        rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+        account: StarknetAccount,
+        earnings_receiver: UnboundedReceiver<LiquidationEarnings>,
+        pending: PendingDistribution,
+        storage: SharedStorage,
+        http_client: HttpClient,
+        notifier: Notifier,
+        slo: SloTracker,
+        mirror: GameMirror,
+        feed: EventFeed,
+        latest_oracle_prices: LatestOraclePrices,
+        tokens: TokenRegistry,
+    ) -> Self {
+        let account_address = account.account_address();
+        let messenger = PayoutMessenger::from_config(http_client.clone(), account.clone(), &config);
+        let attester = PayoutAttester::new(account.clone());
+        let tx_manager = TxManager::new(account, rpc_client.clone(), config.transactions.clone())
+            .with_paymaster(PaymasterClient::from_config(&config));
+        let valuator = Valuator::new(config.clone(), latest_oracle_prices, tokens.clone());
+        Self {
+            swap_providers: Arc::new(swap_providers(&config, &http_client)),
+            account_address,
+            strategy: Arc::from(reward_strategy(&config)),
+            context: RewardContext::new(
+                config.clone(),
+                http_client,
+                mirror,
+                notifier.clone(),
+                valuator.clone(),
+            ),
+            tokens,
+            valuator,
+            messenger,
+            attester,
+            live: LiveConfig::new(config.clone()),
+            config,
+            rpc_client,
+            tx_manager,
+            earnings_receiver: Arc::new(Mutex::new(earnings_receiver)),
+            pending: Arc::new(Mutex::new(pending)),
+            storage,
+            notifier,
+            slo,
+            feed,
+            leadership: Leadership::default(),
+            controls: Controls::default(),
+        }
+    }
+
+    /// Applies the reloads of the config, see [`LiveConfig`].
+    pub fn with_live_config(mut self, live: LiveConfig) -> Self {
+        self.live = live;
+        self
+    }
+
+    /// Pays the fees of the payouts in the token selected by the monitoring,
+    /// see [`FeeTokens`].
+    pub fn with_fee_tokens(mut self, fee_tokens: FeeTokens) -> Self {
+        self.tx_manager = self.tx_manager.with_fee_tokens(fee_tokens);
+        self
+    }
+
+    /// Only distributes while the instance leads, the earnings of a follower
+    /// being kept pending, see [`crate::services::leader::LeaderElectionService`].
+    pub fn with_leadership(mut self, leadership: Leadership) -> Self {
+        self.tx_manager = self.tx_manager.with_leadership(leadership.clone());
+        self.leadership = leadership;
+        self
+    }
+
+    /// Keeps the earnings pending while the transactions are halted by an
+    /// operator, see [`crate::services::operator::Operator`].
+    pub fn with_controls(mut self, controls: Controls) -> Self {
+        self.tx_manager = self.tx_manager.with_controls(controls.clone());
+        self.controls = controls;
+        self
+    }
+
+    /// Returns the context of the reward strategy, with the reloaded split of
+    /// the earnings.
+    fn context(&self) -> RewardContext {
+        let mut context = self.context.clone();
+        context.config = context.config.reloaded(&self.live.current());
+        context
+    }
+
+    /// Collects the liquidations earnings & distributes them when the window closes.
+    /// Pending earnings are persisted so they survive restarts.
+    pub async fn run_forever(&self, shutdown: Shutdown) -> Result<()> {
+        let mut check_interval = interval(CHECK_WINDOW_INTERVAL);
+        let mut sweep_interval =
+            interval(Duration::from_secs(self.config.finality.interval_seconds));
+
+        loop {
+            let mut receiver = self.earnings_receiver.lock().await;
+
+            tokio::select! {
+                _ = shutdown.wait() => {
+                    drop(receiver);
+                    let pending = self.pending.lock().await;
+                    self.storage.lock().await.save_pending_distribution(&pending).await?;
+                    tracing::info!("[💸 Distribution] 🛑 Stopped, {} pending earnings kept", pending.earnings.len());
+                    return Ok(());
+                }
+
+                _ = check_interval.tick() => {
+                    drop(receiver);
+                    self.distribute_if_ready().await?;
+                }
+
+                _ = sweep_interval.tick(), if self.config.finality.hold_world_share => {
+                    drop(receiver);
+                    if let Err(e) = self.sweep_world_shares().await {
+                        tracing::warn!(error = %e, "[💸 Distribution] Could not send the held world shares, retrying later");
+                    }
+                }
+
+                maybe_earnings = receiver.recv() => {
+                    drop(receiver);
+                    match maybe_earnings {
+                        Some(earnings) => {
+                            let mut pending = self.pending.lock().await;
+                            pending.push(earnings, unix_now());
+                            self.storage.lock().await.save_pending_distribution(&pending).await?;
+                            drop(pending);
+                            self.distribute_if_ready().await?;
+                        }
+                        None => {
+                            return Err(anyhow!("Distribution stopped unexpectedly"));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Distributes the pending earnings if the batching window is closed.
+    /// Failures are logged & the earnings are kept for the next attempt.
+    async fn distribute_if_ready(&self) -> Result<()> {
+        if !self.leadership.is_leader() || self.controls.is_halted() {
+            return Ok(());
+        }
+        let mut pending = self.pending.lock().await;
+        let now = unix_now();
+        if !pending.is_ready(&self.config.distribution, now) || pending.is_backing_off(now) {
+            return Ok(());
+        }
+
+        match self.distribute(&pending).await {
+            Ok(true) => {
+                pending.clear();
+                self.storage
+                    .lock()
+                    .await
+                    .save_pending_distribution(&pending)
+                    .await?;
+            }
+            Ok(false) => {}
+            Err(e) => {
+                let attempts =
+                    pending.record_failure(e.to_string(), unix_now(), &self.config.distribution);
+                self.storage
+                    .lock()
+                    .await
+                    .save_pending_distribution(&pending)
+                    .await?;
+                tracing::error!(
+                    error = %e,
+                    attempts,
+                    "[💸 Distribution] 😨 Could not distribute earnings, retrying later"
+                );
+                let severity = if attempts >= self.config.distribution.alert_after_failures {
+                    Severity::Critical
+                } else {
+                    Severity::Warning
+                };
+                self.notifier.notify(
+                    severity,
+                    format!("Could not distribute earnings ({attempts} failure(s) in a row): {e}"),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Distributes the pending earnings to the players chosen by the configured
+    /// [`RewardStrategy`], in a single multicall along with the world share &
+    /// the operator fee. Returns false if nothing could be distributed yet.
+    #[tracing::instrument(
+        skip_all,
+        fields(
+            strategy = self.strategy.name(),
+            liquidations = pending.earnings.len(),
+            tx_hash = tracing::field::Empty
+        )
+    )]
+    pub async fn distribute(&self, pending: &PendingDistribution) -> Result<bool> {
+        // Links the distribution to the liquidations it pays.
+        for span in pending.earnings.iter().filter_map(|e| e.span.as_ref()) {
+            tracing::Span::current().follows_from(span);
+        }
+        // The payouts are computed with the decimals of the tokens paid.
+        let tokens: Vec<Felt> = pending.total_per_token().into_keys().collect();
+        let swapped_to =
+            tokens
+                .iter()
+                .filter_map(|token| match self.config.payout_routing(*token) {
+                    PayoutRouting::SwapTo(payout_token) => Some(payout_token),
+                    _ => None,
+                });
+        self.tokens
+            .resolve(swapped_to.chain(tokens.iter().copied()))
+            .await;
+        let (swapped, swap_calls, swaps) = self.route_earnings(pending).await?;
+        if swapped.is_empty() {
+            tracing::info!("[💸 Distribution] All the earnings are retained, nothing to pay");
+            return Ok(true);
+        }
+        let context = self.context();
+        let Some(plan) = self.strategy.plan(&context, &swapped).await? else {
+            return Ok(false);
+        };
+        // The liquidations of the batch paying the same players are merged
+        // into a single transfer per player & token.
+        let plan = plan.merged();
+        self.guard_plan(&plan, &swapped.total_per_token())?;
+
+        let (mut dust_ledger, mut recent_payouts) = {
+            let storage = self.storage.lock().await;
+            (storage.get_dust_ledger(), storage.get_recent_payouts())
+        };
+        let season_id = context.active_season();
+        let forfeited_transfers =
+            self.rollover_season(season_id, &mut dust_ledger, &mut recent_payouts);
+        let payouts = self.withhold_dust(&mut dust_ledger, &plan.payouts);
+        let (payouts, invalid_transfers) = self.reject_invalid_recipients(payouts);
+        let (payouts, excess_transfers) =
+            self.apply_daily_caps(&mut recent_payouts, payouts, unix_now());
+        let mut transfers: Vec<(Felt, Felt, U256)> = payouts
+            .iter()
+            .map(|payout| (payout.token, payout.recipient, payout.amount))
+            .collect();
+        transfers.extend(plan.transfers);
+        transfers.extend(excess_transfers);
+        transfers.extend(invalid_transfers);
+        transfers.extend(forfeited_transfers);
+        let (sent_transfers, mut held_shares) =
+            self.hold_world_share(pending, &transfers, season_id);
+        let mut rewards = plan.rewards;
+        rewards.extend(
+            payouts
+                .iter()
+                .map(|payout| (payout.player, payout.token, payout.amount)),
+        );
+
+        let mut calls = swap_calls;
+        calls.extend(sent_transfers.iter().map(build_erc20_transfer_call));
+        calls.extend(plan.calls);
+        calls.extend(self.consume_redeem_calls(&plan.redeemed_players));
+        calls.extend(self.record_payout_calls(pending, &payouts));
+        if calls.is_empty() {
+            tracing::info!(
+                "[💸 Distribution] Only the world share to pay, held until the liquidations are accepted on L1"
+            );
+            let mut storage = self.storage.lock().await;
+            storage.save_dust_ledger(&dust_ledger).await?;
+            storage.save_recent_payouts(&recent_payouts).await?;
+            drop(storage);
+            self.save_held_world_shares(held_shares).await?;
+            return Ok(true);
+        }
+
+        if !self.reserve_redeems(&plan.redeemed_players).await? {
+            return Ok(false);
+        }
+        tracing::info!(
+            "[💸 Distribution] Executing {} distribution multicall for {} liquidation(s)...",
+            self.strategy.name(),
+            pending.earnings.len()
+        );
+        let receipt = match self.tx_manager.execute(&calls).await {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                self.release_redeems(&plan.redeemed_players).await;
+                return Err(e);
+            }
+        };
+        let dist_tx_hash = *receipt.receipt.transaction_hash();
+        tracing::Span::current().record("tx_hash", format!("{dist_tx_hash:#064x}"));
+        tracing::info!(
+            tx_hash = format!("{dist_tx_hash:#064x}"),
+            "[💸 Distribution] ✅ Distribution complete! (tx {:#x})",
+            dist_tx_hash
+        );
+        self.record_payouts(
+            dist_tx_hash,
+            receipt.block.block_number(),
+            &sent_transfers,
+            season_id,
+        )
+        .await?;
+        for share in held_shares.iter_mut() {
+            share.distribution_tx = Some(dist_tx_hash);
+        }
+        self.save_held_world_shares(held_shares).await?;
+        self.attest_payouts(pending, dist_tx_hash, &payouts).await;
+        self.record_distribution_pnl(
+            pending,
+            dist_tx_hash,
+            receipt.block.block_number(),
+            receipt.receipt.actual_fee(),
+            swaps,
+            &transfers,
+        )
+        .await;
+        {
+            let mut storage = self.storage.lock().await;
+            storage.save_dust_ledger(&dust_ledger).await?;
+            storage.save_recent_payouts(&recent_payouts).await?;
+        }
+        if let Some((root, claims)) = plan.claims {
+            let claim_set = ClaimSet {
+                root,
+                tx_hash: dist_tx_hash,
+                published_at: unix_now(),
+                claims,
+            };
+            self.storage.lock().await.save_claim_set(&claim_set).await?;
+        }
+        self.record_payout_latencies(pending);
+        self.publish_rewards(dist_tx_hash, plan.highest_score, &rewards);
+        self.announce_payouts(dist_tx_hash, &payouts);
+        self.notifier.notify(
+            Severity::Info,
+            format!(
+                "Paid {} liquidation(s) earnings to {} (tx {:#x})",
+                pending.earnings.len(),
+                plan.summary,
+                dist_tx_hash
+            ),
+        );
+        Ok(true)
+    }
+
+    /// Routes the earnings of each token as configured, see
+    /// [`Config::payout_routing`]: paid as is, swapped or retained by the bot.
+    /// Returns the earnings to pay, the swapped ones converted to the minimum
+    /// amounts received given the max slippage, the calls of the swaps, sent
+    /// before the transfers, & the swaps valued for the PnL.
+    async fn route_earnings(
+        &self,
+        pending: &PendingDistribution,
+    ) -> Result<(PendingDistribution, Vec<Call>, Vec<SwapPnl>)> {
+        let mut swapped = pending.clone();
+        let mut calls = vec![];
+        let mut swaps = vec![];
+        for (token, amount) in pending.total_per_token() {
+            let payout_token = match self.config.payout_routing(token) {
+                PayoutRouting::AsIs => continue,
+                PayoutRouting::Retain => {
+                    tracing::info!(
+                        token = format!("{token:#x}"),
+                        "[💸 Distribution] Retaining {} as treasury",
+                        self.valuator.value(token, &amount)
+                    );
+                    swapped = swapped.without_token(token);
+                    continue;
+                }
+                PayoutRouting::SwapTo(payout_token) => payout_token,
+            };
+            if amount == U256_ZERO {
+                continue;
+            }
+            let (provider, quote) = quote_swap(
+                &self.swap_providers,
+                token,
+                payout_token,
+                amount,
+                self.account_address,
+                &self.config,
+            )
+            .await?;
+            let swap = SwapPnl {
+                sold: self.valuator.value(token, &amount),
+                bought: self.valuator.value(payout_token, &quote.min_buy_amount),
+            };
+            tracing::info!(
+                token = format!("{token:#x}"),
+                "[💸 Distribution] Swapping {} to at least {} on {} (quoted {})",
+                swap.sold,
+                swap.bought,
+                provider,
+                self.valuator.value(payout_token, &quote.buy_amount)
+            );
+            swaps.push(swap);
+            swapped = swapped.converted(token, payout_token, quote.min_buy_amount);
+            calls.extend(quote.calls);
+        }
+        Ok((swapped, calls, swaps))
+    }
+
+    /// Refuses the plan & alerts if its amounts are anomalous, see
+    /// [`RewardPlan::check_amounts`], or if the players' share is worth more
+    /// than `distribution.max_player_share_usd`. The earnings are kept pending.
+    fn guard_plan(&self, plan: &RewardPlan, earnings: &HashMap<Felt, U256>) -> Result<()> {
+        let checked = plan
+            .check_amounts(earnings)
+            .and_then(|()| self.check_player_share_value(plan));
+        if let Err(e) = &checked {
+            self.notifier
+                .notify(Severity::Critical, format!("Distribution refused: {e}"));
+        }
+        checked
+    }
+
+    /// Checks the value in USD of the players' share against the cap, the
+    /// tokens without a price being left out.
+    fn check_player_share_value(&self, plan: &RewardPlan) -> Result<()> {
+        let Some(cap) = &self.config.distribution.max_player_share_usd else {
+            return Ok(());
+        };
+        let mut total_usd = BigDecimal::from(0);
+        for (token, amount) in plan.players_per_token() {
+            let share = self.valuator.value(token, &big_uint_to_u256(&amount));
+            match &share.usd {
+                Some(usd) => total_usd += usd,
+                None => tracing::warn!(
+                    token = format!("{token:#x}"),
+                    "[💸 Distribution] The players' share of {share} has no price, left out of the USD cap"
+                ),
+            }
+        }
+        anyhow::ensure!(
+            total_usd <= *cap,
+            "The players' share is worth ${}, above the cap of ${}",
+            total_usd.round(2).to_plain_string(),
+            cap.to_plain_string()
+        );
+        Ok(())
+    }
+
+    /// Resets the dust & the daily payout caps of the players once the season
+    /// changed, see `distribution.seasons`. Returns the transfers of the dust
+    /// of the ended season to the world, one per token.
+    fn rollover_season(
+        &self,
+        season_id: Option<u32>,
+        dust_ledger: &mut DustLedger,
+        recent_payouts: &mut RecentPayouts,
+    ) -> Vec<(Felt, Felt, U256)> {
+        let forfeited = dust_ledger.rollover(season_id);
+        if !recent_payouts.rollover(season_id) && forfeited.is_empty() {
+            return vec![];
+        }
+        tracing::info!(
+            season_id = ?season_id,
+            "[💸 Distribution] Season changed to {:?}, resetting the dust & the daily payout caps",
+            season_id
+        );
+        let mut transfers: Vec<(Felt, Felt, U256)> = vec![];
+        for dust in forfeited {
+            match transfers
+                .iter_mut()
+                .find(|(token, _, _)| *token == dust.token)
+            {
+                Some((_, _, total)) => *total = *total + dust.amount,
+                None => transfers.push((dust.token, self.config.world_address, dust.amount)),
+            }
+        }
+        for (token, _, amount) in transfers.iter() {
+            tracing::info!(
+                token = format!("{token:#x}"),
+                "[💸 Distribution] Sending the dust of the ended season, {}, to the world",
+                self.valuator.value(*token, amount)
+            );
+        }
+        transfers
+    }
+
+    /// Returns the payouts to send now: the shares below the `min_payout` of
+    /// their token are added to the dust of the player instead, paid along
+    /// with one of its next shares once their total reaches it.
+    fn withhold_dust(
+        &self,
+        ledger: &mut DustLedger,
+        payouts: &[PlayerPayout],
+    ) -> Vec<PlayerPayout> {
+        payouts
+            .iter()
+            .filter_map(|payout| {
+                let min_payout = self.tokens.min_payout(&payout.token);
+                match ledger.accrue(payout.player, payout.token, payout.amount, min_payout) {
+                    Some(amount) => Some(PlayerPayout {
+                        amount,
+                        ..payout.clone()
+                    }),
+                    None => {
+                        tracing::info!(
+                            player_address = format!("{:#x}", payout.player),
+                            token = format!("{:#x}", payout.token),
+                            "[💸 Distribution] Share of {} below the min payout of {}, kept as dust",
+                            self.valuator.value(payout.token, &payout.amount),
+                            self.valuator.value(payout.token, &min_payout)
+                        );
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Clamps the payouts to the `max_daily_payout` of their token, given what
+    /// the players received over the last 24 hours, & records them. Returns
+    /// the payouts to send & the transfers of the excess to the world.
+    fn apply_daily_caps(
+        &self,
+        recent_payouts: &mut RecentPayouts,
+        payouts: Vec<PlayerPayout>,
+        now: u64,
+    ) -> (Vec<PlayerPayout>, Vec<(Felt, Felt, U256)>) {
+        let mut capped_payouts = Vec::with_capacity(payouts.len());
+        let mut excess_transfers = vec![];
+        for payout in payouts {
+            let amount = match self.tokens.max_daily_payout(&payout.token) {
+                Some(max_payout) => recent_payouts.capped(
+                    payout.player,
+                    payout.token,
+                    payout.amount,
+                    max_payout,
+                    now,
+                ),
+                None => payout.amount,
+            };
+            if amount != payout.amount {
+                let excess = payout.amount - amount;
+                tracing::warn!(
+                    player_address = format!("{:#x}", payout.player),
+                    token = format!("{:#x}", payout.token),
+                    "[💸 Distribution] Player {:#x} reached its daily payout cap, sending {} to the world",
+                    payout.player,
+                    excess
+                );
+                excess_transfers.push((payout.token, self.config.world_address, excess));
+                if amount == U256_ZERO {
+                    continue;
+                }
+            }
+            recent_payouts.record(payout.player, payout.token, amount, now);
+            capped_payouts.push(PlayerPayout { amount, ..payout });
+        }
+        (capped_payouts, excess_transfers)
+    }
+
+    /// Returns the payouts to valid recipients: the shares of the players
+    /// whose recipient could not receive a transfer are sent to the world
+    /// instead, so they cannot revert the whole distribution.
+    fn reject_invalid_recipients(
+        &self,
+        payouts: Vec<PlayerPayout>,
+    ) -> (Vec<PlayerPayout>, Vec<(Felt, Felt, U256)>) {
+        let (valid, invalid): (Vec<PlayerPayout>, Vec<PlayerPayout>) = payouts
+            .into_iter()
+            .partition(|payout| is_valid_recipient(payout.recipient));
+        let world_transfers = invalid
+            .into_iter()
+            .map(|payout| {
+                tracing::warn!(
+                    player_address = format!("{:#x}", payout.player),
+                    "[💸 Distribution] Invalid recipient {:#x} for player {:#x}, sending {} to the world",
+                    payout.recipient,
+                    payout.player,
+                    payout.amount
+                );
+                (payout.token, self.config.world_address, payout.amount)
+            })
+            .collect();
+        (valid, world_transfers)
+    }
+
+    /// Returns the calls clearing the redeem entries of the paid players, so
+    /// they are not paid again by the next distribution. Without an actions
+    /// contract configured, the entries are left to the game.
+    fn consume_redeem_calls(&self, players: &[Felt]) -> Vec<Call> {
+        let Some(actions) = self.actions() else {
+            return vec![];
+        };
+        players
+            .iter()
+            .map(|player| actions.consume_redeem_getcall(&ContractAddress(*player)))
+            .collect()
+    }
+
+    /// Reserves the redeem entries of the players on the actions contract with
+    /// `distribution.reserve_redeems`, before paying them, so another replica
+    /// of the bot doesn't pay them too. Returns false if an entry is already
+    /// reserved, the earnings being kept for the next distribution.
+    async fn reserve_redeems(&self, players: &[Felt]) -> Result<bool> {
+        let calls = self.redeem_reservation_calls(players, ActionsContract::reserve_redeem_getcall);
+        if calls.is_empty() {
+            return Ok(true);
+        }
+        let lease = self.tx_manager.acquire();
+        let reverted = match self.tx_manager.simulate_as(lease.account(), &calls).await? {
+            Simulation::Reverted(reason) => Some(reason),
+            Simulation::Succeeded(_) => {
+                let receipt = self.tx_manager.execute_as(&lease, &calls).await?;
+                match receipt.receipt.execution_result() {
+                    ExecutionResult::Reverted { reason } => Some(reason.clone()),
+                    ExecutionResult::Succeeded => None,
+                }
+            }
+        };
+        match reverted {
+            Some(reason) => {
+                tracing::warn!(
+                    "[💸 Distribution] Could not reserve the redeem entries of {} player(s), keeping earnings pending: {}",
+                    players.len(),
+                    reason
+                );
+                Ok(false)
+            }
+            None => {
+                tracing::info!(
+                    "[💸 Distribution] Reserved the redeem entries of {} player(s)",
+                    players.len()
+                );
+                Ok(true)
+            }
+        }
+    }
+
+    /// Releases the redeem entries reserved for a payout that failed, so they
+    /// can be paid by the next distribution of any replica. A failure is only
+    /// logged: the reservations are left to expire on the actions contract.
+    async fn release_redeems(&self, players: &[Felt]) {
+        let calls = self.redeem_reservation_calls(players, ActionsContract::release_redeem_getcall);
+        if calls.is_empty() {
+            return;
+        }
+        if let Err(e) = self.tx_manager.execute(&calls).await {
+            tracing::error!(
+                error = %e,
+                "[💸 Distribution] Could not release the redeem entries of {} player(s)",
+                players.len()
+            );
+        }
+    }
+
+    /// Returns the `reserve_redeem` or `release_redeem` calls of the players
+    /// with `distribution.reserve_redeems`, none otherwise.
+    fn redeem_reservation_calls(
+        &self,
+        players: &[Felt],
+        getcall: fn(&ActionsContract, &ContractAddress) -> Call,
+    ) -> Vec<Call> {
+        let Some(actions) = self.actions() else {
+            return vec![];
+        };
+        if !self.config.distribution.reserve_redeems {
+            return vec![];
+        }
+        players
+            .iter()
+            .map(|player| getcall(&actions, &ContractAddress(*player)))
+            .collect()
+    }
+
+    /// Returns the bindings of the actions contract of the game, if any. The
+    /// calls are only built through them, the account sending them being
+    /// leased by the transaction manager.
+    fn actions(&self) -> Option<ActionsContract> {
+        let actions_address = self.config.actions_address?;
+        Some(Actions::new(
+            actions_address,
+            self.tx_manager.accounts().primary().0.clone(),
+        ))
+    }
+
+    /// Returns the calls recording the payouts of the players on the actions
+    /// contract with `distribution.record_payouts_onchain`, so the game can
+    /// show a verified payout history. Each payout is linked to the last
+    /// liquidation it pays.
+    fn record_payout_calls(
+        &self,
+        pending: &PendingDistribution,
+        payouts: &[PlayerPayout],
+    ) -> Vec<Call> {
+        let Some(actions) = self.actions() else {
+            return vec![];
+        };
+        let Some(liquidation) = pending.earnings.last() else {
+            return vec![];
+        };
+        if !self.config.distribution.record_payouts_onchain {
+            return vec![];
+        }
+        payouts
+            .iter()
+            .map(|payout| {
+                actions.record_payout_getcall(
+                    &ContractAddress(payout.player),
+                    &ContractAddress(payout.token),
+                    &payout.amount,
+                    &liquidation.liquidation_tx,
+                )
+            })
+            .collect()
+    }
+
+    /// Publishes the highest score used by a distribution & the rewards of the
+    /// players, as `(player, token, amount)`, on the event feed.
+    fn publish_rewards(&self, tx_hash: Felt, highest_score: u128, rewards: &[(Felt, Felt, U256)]) {
+        self.feed.publish(FeedEvent::HighScoreUsed {
+            score: highest_score,
+            tx_hash,
+        });
+        for (player, token, amount) in rewards.iter() {
+            let paid = self.valuator.value(*token, amount);
+            tracing::info!(
+                player_address = format!("{player:#x}"),
+                token = format!("{token:#x}"),
+                amount = %paid.amount,
+                usd = %paid.usd_string(),
+                "[💸 Distribution] Rewarded player {:#x} with {}",
+                player,
+                paid
+            );
+            self.feed.publish(FeedEvent::PlayerRewarded {
+                player: *player,
+                amount: *amount,
+                token: *token,
+                usd: paid.usd,
+                tx_hash,
+            });
+        }
+    }
+
+    /// Publishes a message to each player paid, through the relay of Torii,
+    /// in the background.
+    fn announce_payouts(&self, tx_hash: Felt, payouts: &[PlayerPayout]) {
+        let Some(messenger) = self.messenger.clone() else {
+            return;
+        };
+        let messages: Vec<PayoutMessage> = payouts
+            .iter()
+            .map(|payout| PayoutMessage {
+                player: payout.player,
+                token: payout.token,
+                amount: payout.amount,
+                tx_hash,
+            })
+            .collect();
+        tokio::spawn(async move { messenger.publish_all(&messages).await });
+    }
+
+    /// Signs an attestation of each payout & appends them to the log of the
+    /// storage, see [`PayoutAttestation`]. A failure is only logged & alerted:
+    /// the payouts went through.
+    async fn attest_payouts(
+        &self,
+        pending: &PendingDistribution,
+        tx_hash: Felt,
+        payouts: &[PlayerPayout],
+    ) {
+        let Some(liquidation) = pending.earnings.last() else {
+            return;
+        };
+        if payouts.is_empty() {
+            return;
+        }
+        let timestamp = unix_now();
+        let attested = payouts
+            .iter()
+            .map(|payout| AttestedPayout {
+                player: payout.player,
+                token: payout.token,
+                amount: payout.amount,
+                liquidation_tx: liquidation.liquidation_tx,
+                payout_tx: tx_hash,
+                timestamp,
+            })
+            .collect();
+        let mut storage = self.storage.lock().await;
+        let previous = storage
+            .get_payout_attestations()
+            .last()
+            .map_or(Felt::ZERO, |attestation| attestation.hash);
+        let saved = match self.attester.attest_all(attested, previous).await {
+            Ok(attestations) => storage.save_payout_attestations(&attestations).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = saved {
+            tracing::error!(
+                error = %e,
+                "[💸 Distribution] Could not attest the payouts of distribution {:#x}",
+                tx_hash
+            );
+            self.notifier.notify(
+                Severity::Warning,
+                format!("Could not attest the payouts of distribution {tx_hash:#x}: {e}"),
+            );
+        }
+    }
+
+    /// Returns the transfers to send with the distribution & the ones to the
+    /// world held until the liquidations it pays are accepted on L1, with
+    /// `finality.hold_world_share`, see [`Self::sweep_world_shares`].
+    fn hold_world_share(
+        &self,
+        pending: &PendingDistribution,
+        transfers: &[(Felt, Felt, U256)],
+        season_id: Option<u32>,
+    ) -> (Vec<(Felt, Felt, U256)>, Vec<HeldWorldShare>) {
+        if !self.config.finality.hold_world_share {
+            return (transfers.to_vec(), vec![]);
+        }
+        let (held, sent): (Vec<_>, Vec<_>) = transfers
+            .iter()
+            .copied()
+            .partition(|(_, recipient, _)| *recipient == self.config.world_address);
+        let liquidation_txs: Vec<Felt> =
+            pending.earnings.iter().map(|e| e.liquidation_tx).collect();
+        let held_at = unix_now();
+        let held = held
+            .into_iter()
+            .map(|(token, recipient, amount)| HeldWorldShare {
+                token,
+                recipient,
+                amount,
+                liquidation_txs: liquidation_txs.clone(),
+                distribution_tx: None,
+                season_id,
+                held_at,
+            })
+            .collect();
+        (sent, held)
+    }
+
+    async fn save_held_world_shares(&self, shares: Vec<HeldWorldShare>) -> Result<()> {
+        if shares.is_empty() {
+            return Ok(());
+        }
+        let mut storage = self.storage.lock().await;
+        let mut held = storage.get_held_world_shares();
+        held.extend(shares);
+        storage.save_held_world_shares(&held).await
+    }
+
+    /// Sends the world shares held until the liquidations they come from are
+    /// accepted on L1, in a single multicall, & records them in the payout
+    /// ledger.
+    async fn sweep_world_shares(&self) -> Result<()> {
+        if !self.leadership.is_leader() || self.controls.is_halted() {
+            return Ok(());
+        }
+        let held = self.storage.lock().await.get_held_world_shares();
+        let mut final_txs: HashMap<Felt, bool> = HashMap::new();
+        let mut ready = vec![];
+        let mut kept = vec![];
+        for share in held {
+            let mut accepted_on_l1 = true;
+            for tx_hash in share.liquidation_txs.iter() {
+                let accepted = match final_txs.get(tx_hash) {
+                    Some(accepted) => *accepted,
+                    None => {
+                        let accepted = is_accepted_on_l1(&self.rpc_client, *tx_hash).await?;
+                        final_txs.insert(*tx_hash, accepted);
+                        accepted
+                    }
+                };
+                if !accepted {
+                    accepted_on_l1 = false;
+                    break;
+                }
+            }
+            if accepted_on_l1 {
+                ready.push(share);
+            } else {
+                kept.push(share);
+            }
+        }
+        if ready.is_empty() {
+            return Ok(());
+        }
+
+        let transfers: Vec<(Felt, Felt, U256)> = ready
+            .iter()
+            .map(|share| (share.token, share.recipient, share.amount))
+            .collect();
+        let calls: Vec<Call> = transfers.iter().map(build_erc20_transfer_call).collect();
+        let receipt = self.tx_manager.execute(&calls).await?;
+        let tx_hash = *receipt.receipt.transaction_hash();
+        for share in ready.iter() {
+            self.record_payouts(
+                tx_hash,
+                receipt.block.block_number(),
+                &[(share.token, share.recipient, share.amount)],
+                share.season_id,
+            )
+            .await?;
+        }
+        self.storage
+            .lock()
+            .await
+            .save_held_world_shares(&kept)
+            .await?;
+        tracing::info!(
+            tx_hash = format!("{tx_hash:#064x}"),
+            "[💸 Distribution] ✅ Sent {} world share(s) accepted on L1 (tx {:#x})",
+            ready.len(),
+            tx_hash
+        );
+        self.notifier.notify(
+            Severity::Info,
+            format!(
+                "Sent {} world share(s) once their liquidations were accepted on L1 (tx {:#x})",
+                ready.len(),
+                tx_hash
+            ),
+        );
+        Ok(())
+    }
+
+    /// Records the transfers of a distribution in the payout ledger, to be
+    /// reconciled against the chain.
+    async fn record_payouts(
+        &self,
+        tx_hash: Felt,
+        block_number: u64,
+        transfers: &[(Felt, Felt, U256)],
+        season_id: Option<u32>,
+    ) -> Result<()> {
+        let mut storage = self.storage.lock().await;
+        let mut ledger = storage.get_payout_ledger();
+        ledger.records.extend(
+            transfers
+                .iter()
+                .map(|&(token, recipient, amount)| PayoutRecord {
+                    tx_hash,
+                    block_number,
+                    token,
+                    recipient,
+                    amount,
+                    usd: self.valuator.value(token, &amount).usd,
+                    checked: false,
+                    season_id,
+                    finality: Finality::AcceptedOnL2,
+                }),
+        );
+        storage.save_payout_ledger(&ledger).await
+    }
+
+    /// Records what the distribution swapped, paid & cost, valued at the
+    /// current oracle prices. A failure is only logged: the distribution went
+    /// through.
+    async fn record_distribution_pnl(
+        &self,
+        pending: &PendingDistribution,
+        tx_hash: Felt,
+        block_number: u64,
+        fee: &FeePayment,
+        swaps: Vec<SwapPnl>,
+        transfers: &[(Felt, Felt, U256)],
+    ) {
+        let pnl = DistributionPnl {
+            tx_hash,
+            block_number,
+            executed_at: unix_now(),
+            liquidation_txs: pending.earnings.iter().map(|e| e.liquidation_tx).collect(),
+            swaps,
+            payouts: transfers
+                .iter()
+                .map(|(token, recipient, amount)| PayoutPnl {
+                    recipient: *recipient,
+                    paid: self.valuator.value(*token, amount),
+                })
+                .collect(),
+            gas: self.valuator.gas(fee),
+        };
+        if let Err(e) = self.storage.lock().await.save_distribution_pnl(&pnl).await {
+            tracing::error!(
+                error = %e,
+                "[💸 Distribution] Could not record the PnL of distribution {:#x}",
+                tx_hash
+            );
+        }
+    }
+
+    fn record_payout_latencies(&self, pending: &PendingDistribution) {
+        let now = unix_now();
+        // Earnings persisted by older versions have no liquidation time.
+        for earnings in pending.earnings.iter().filter(|e| e.liquidated_at > 0) {
+            let latency = now.saturating_sub(earnings.liquidated_at);
+            self.slo.record_payout(Duration::from_secs(latency));
+        }
+    }
+}
+
+/// Builds the call transferring a given amount of an ERC20 token to a recipient.
+fn build_erc20_transfer_call(&(token_address, recipient, amount): &(Felt, Felt, U256)) -> Call {
+    Call {
+        to: token_address,
+        selector: *TRANSFER_SELECTOR,
+        calldata: vec![recipient, amount.low.into(), amount.high.into()], // recipient, amount_low, amount_high
+    }
+}
@@ -0,0 +1,191 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use starknet::{
+    core::types::{BlockId, EmittedEvent, Event, EventFilter},
+    providers::{JsonRpcClient, Provider, jsonrpc::HttpTransport},
+};
+use tokio::task::JoinSet;
+
+use crate::{
+    config::Config,
+    protocols::liquidate::LiquidateVersion,
+    services::notifier::Notifier,
+    storages::SharedStorage,
+    types::{
+        asset::Asset,
+        downtime::{DowntimeReport, MissedLiquidation},
+        game::GameMirror,
+        notification::Severity,
+    },
+    utils::{services::Service, shutdown::Shutdown, unix_now},
+};
+
+/// Events fetched per RPC call.
+const EVENTS_CHUNK_SIZE: u64 = 1_000;
+
+/// Runs once at startup: scans the blocks missed while the bot was offline
+/// for the liquidations made by others through the liquidate contract, and
+/// reports what the downtime cost.
+#[derive(Clone)]
+pub struct DowntimeService {
+    config: Config,
+    rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+    storage: SharedStorage,
+    game_mirror: GameMirror,
+    notifier: Notifier,
+    /// Last block indexed before the bot stopped, 0 on a first run.
+    last_block_indexed: u64,
+}
+
+#[async_trait::async_trait]
+impl Service for DowntimeService {
+    async fn start(
+        &mut self,
+        join_set: &mut JoinSet<anyhow::Result<()>>,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<()> {
+        let service = self.clone();
+        join_set.spawn(async move {
+            tracing::info!("💤 Downtime service started");
+            tokio::select! {
+                _ = shutdown.wait() => {
+                    tracing::info!("[💤 Downtime] 🛑 Stopped scanning the missed blocks");
+                }
+                res = service.report() => res?,
+            }
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+impl DowntimeService {
+    pub fn new(
+        config: Config,
+        rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+        storage: SharedStorage,
+        game_mirror: GameMirror,
+        notifier: Notifier,
+        last_block_indexed: u64,
+    ) -> Self {
+        Self {
+            config,
+            rpc_client,
+            storage,
+            game_mirror,
+            notifier,
+            last_block_indexed,
+        }
+    }
+
+    /// Builds, stores & notifies the downtime report. Nothing is reported on a
+    /// first run, as there was no downtime.
+    async fn report(&self) -> Result<()> {
+        if self.last_block_indexed == 0 {
+            return Ok(());
+        }
+        let from_block = self.last_block_indexed + 1;
+        let to_block = self.rpc_client.block_number().await?;
+        if from_block > to_block {
+            return Ok(());
+        }
+
+        let missed_liquidations = self.missed_liquidations(from_block, to_block).await?;
+        let game_state = self.game_mirror.snapshot();
+        let player_score = game_state
+            .redeem_queue
+            .first()
+            .map(|redeemer| redeemer.score)
+            .unwrap_or_default();
+        let report = DowntimeReport::new(
+            from_block,
+            to_block,
+            missed_liquidations,
+            player_score,
+            game_state.highest_score.unwrap_or_default(),
+            unix_now(),
+        );
+        self.storage
+            .lock()
+            .await
+            .save_downtime_report(&report)
+            .await?;
+
+        if report.is_empty() {
+            tracing::info!(
+                "[💤 Downtime] No liquidation missed from block {} to {}",
+                from_block,
+                to_block
+            );
+        } else {
+            tracing::warn!("[💤 Downtime] {}", report);
+            self.notifier
+                .notify(Severity::Warning, format!("Downtime cost: {report}"));
+        }
+        Ok(())
+    }
+
+    /// Returns the liquidations of positions the bot could have liquidated,
+    /// i.e. with both assets configured, made between the two blocks.
+    async fn missed_liquidations(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<MissedLiquidation>> {
+        let filter = EventFilter {
+            from_block: Some(BlockId::Number(from_block)),
+            to_block: Some(BlockId::Number(to_block)),
+            address: Some(self.config.liquidate_address),
+            keys: Some(vec![vec![
+                self.config.liquidate_version.liquidation_event(),
+            ]]),
+        };
+        let mut missed = vec![];
+        let mut continuation_token = None;
+        loop {
+            let page = self
+                .rpc_client
+                .get_events(filter.clone(), continuation_token, EVENTS_CHUNK_SIZE)
+                .await?;
+            missed.extend(
+                page.events
+                    .iter()
+                    .filter_map(|event| {
+                        parse_missed_liquidation(self.config.liquidate_version, event)
+                    })
+                    .filter(|missed| {
+                        Asset::from_address(&self.config, missed.collateral).is_some()
+                            && Asset::from_address(&self.config, missed.debt).is_some()
+                    }),
+            );
+            continuation_token = page.continuation_token;
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+        Ok(missed)
+    }
+}
+
+/// Decodes a `LiquidatePosition` event with the bindings of the version of
+/// the liquidate contract.
+fn parse_missed_liquidation(
+    version: LiquidateVersion,
+    event: &EmittedEvent,
+) -> Option<MissedLiquidation> {
+    let liquidation = version.decode_liquidation(&Event {
+        from_address: event.from_address,
+        keys: event.keys.clone(),
+        data: event.data.clone(),
+    })?;
+    Some(MissedLiquidation {
+        tx_hash: event.transaction_hash,
+        block_number: event.block_number?,
+        pool_id: liquidation.pool_id,
+        user: liquidation.user,
+        collateral: liquidation.collateral,
+        debt: liquidation.debt,
+        residual: liquidation.residual,
+    })
+}
@@ -0,0 +1,319 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc, Mutex, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
+use starknet::{
+    core::types::{BlockId, BlockTag, MaybePreConfirmedBlockWithTxHashes},
+    providers::{JsonRpcClient, Provider, jsonrpc::HttpTransport},
+};
+use tokio::task::JoinSet;
+
+use crate::{
+    config::{Config, GasGuardAction, GasGuardConfig},
+    services::notifier::Notifier,
+    types::{notification::Severity, position::LiquidationCandidate},
+    utils::{services::Service, shutdown::Shutdown},
+};
+
+/// L2 gas prices of the latest blocks, in fri, & whether their median spikes
+/// above the `gas_guard.max_l2_gas_price`, see [`GasGuardConfig`]. Disabled,
+/// it never holds back a liquidation.
+#[derive(Clone, Default)]
+pub struct FeeOracle {
+    config: Arc<RwLock<GasGuardConfig>>,
+    /// Block number & L2 gas price of the latest `window_blocks` blocks.
+    prices: Arc<Mutex<VecDeque<(u64, u128)>>>,
+    spiking: Arc<AtomicBool>,
+}
+
+impl FeeOracle {
+    pub fn new(config: GasGuardConfig) -> Self {
+        Self {
+            config: Arc::new(RwLock::new(config)),
+            ..Default::default()
+        }
+    }
+
+    /// Applies the reloaded thresholds of `config`, see
+    /// [`crate::config::RELOADABLE_FIELDS`].
+    pub fn reload(&self, config: &GasGuardConfig) {
+        let mut current = self.config.write().expect("fee oracle lock poisoned");
+        current.action = config.action;
+        current.urgent_ltv_excess = config.urgent_ltv_excess.clone();
+        current.spike_min_expected_profit_usd = config.spike_min_expected_profit_usd.clone();
+    }
+
+    fn config(&self) -> GasGuardConfig {
+        self.config
+            .read()
+            .expect("fee oracle lock poisoned")
+            .clone()
+    }
+
+    /// Records the L2 gas price of a block, returning the new state when the
+    /// fees start or stop spiking.
+    pub fn record(&self, block_number: u64, price: u128) -> Option<bool> {
+        let config = self.config();
+        let median = {
+            let mut prices = self.prices.lock().expect("fee oracle lock poisoned");
+            if prices
+                .back()
+                .is_some_and(|(latest, _)| *latest >= block_number)
+            {
+                return None;
+            }
+            prices.push_back((block_number, price));
+            while prices.len() > config.window_blocks {
+                prices.pop_front();
+            }
+            median(prices.iter().map(|(_, price)| *price))?
+        };
+        let spiking = median > u128::from(config.max_l2_gas_price);
+        (self.spiking.swap(spiking, Ordering::Relaxed) != spiking).then_some(spiking)
+    }
+
+    /// Median L2 gas price of the latest blocks, in fri.
+    pub fn median(&self) -> Option<u128> {
+        let prices = self.prices.lock().expect("fee oracle lock poisoned");
+        median(prices.iter().map(|(_, price)| *price))
+    }
+
+    /// Returns if the fees are above the cap.
+    pub fn is_spiking(&self) -> bool {
+        self.config().enabled && self.spiking.load(Ordering::Relaxed)
+    }
+
+    /// Returns if the liquidation of `candidate` is held back by a fee spike.
+    /// The urgent liquidations never are.
+    pub fn defers(&self, candidate: &LiquidationCandidate) -> bool {
+        let config = self.config();
+        if !self.is_spiking() || candidate.ltv_excess >= config.urgent_ltv_excess {
+            return false;
+        }
+        match config.action {
+            GasGuardAction::Defer => true,
+            GasGuardAction::RaiseMinProfit => {
+                candidate.expected_profit < config.spike_min_expected_profit_usd
+            }
+        }
+    }
+}
+
+fn median(prices: impl Iterator<Item = u128>) -> Option<u128> {
+    let mut prices: Vec<u128> = prices.collect();
+    if prices.is_empty() {
+        return None;
+    }
+    prices.sort_unstable();
+    let middle = prices.len() / 2;
+    if prices.len() % 2 == 0 {
+        Some(prices[middle - 1] / 2 + prices[middle] / 2)
+    } else {
+        Some(prices[middle])
+    }
+}
+
+/// Feeds the [`FeeOracle`] with the L2 gas price of the latest block every
+/// `gas_guard.poll_interval_seconds`, notifying when the fees start & stop
+/// spiking.
+#[derive(Clone)]
+pub struct FeeOracleService {
+    config: Config,
+    rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+    fees: FeeOracle,
+    notifier: Notifier,
+}
+
+#[async_trait::async_trait]
+impl Service for FeeOracleService {
+    async fn start(
+        &mut self,
+        join_set: &mut JoinSet<anyhow::Result<()>>,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<()> {
+        let service = self.clone();
+        join_set.spawn(async move {
+            tracing::info!("⛽ Fee oracle service started");
+            service.run_forever(shutdown).await?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+impl FeeOracleService {
+    pub fn new(
+        config: Config,
+        rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+        fees: FeeOracle,
+        notifier: Notifier,
+    ) -> Self {
+        Self {
+            config,
+            rpc_client,
+            fees,
+            notifier,
+        }
+    }
+
+    pub async fn run_forever(self, shutdown: Shutdown) -> Result<()> {
+        let sleep_duration = Duration::from_secs(self.config.gas_guard.poll_interval_seconds);
+        loop {
+            if let Err(e) = self.update_gas_price().await {
+                tracing::warn!(error = %e, "[⛽ Fees] Could not read the latest gas price");
+            }
+            tokio::select! {
+                _ = shutdown.wait() => {
+                    tracing::info!("[⛽ Fees] 🛑 Stopped tracking the gas price");
+                    return Ok(());
+                }
+                _ = tokio::time::sleep(sleep_duration) => {}
+            }
+        }
+    }
+
+    async fn update_gas_price(&self) -> Result<()> {
+        let block = self
+            .rpc_client
+            .get_block_with_tx_hashes(BlockId::Tag(BlockTag::Latest))
+            .await?;
+        let MaybePreConfirmedBlockWithTxHashes::Block(block) = block else {
+            return Ok(());
+        };
+        let price = u128::try_from(block.l2_gas_price.price_in_fri)?;
+        let Some(spiking) = self.fees.record(block.block_number, price) else {
+            return Ok(());
+        };
+        let median = self.fees.median().unwrap_or_default();
+        let cap = self.config.gas_guard.max_l2_gas_price;
+        if spiking {
+            tracing::warn!(
+                median = %median,
+                cap,
+                "[⛽ Fees] Fee spike: median L2 gas price of {median} fri above {cap}, holding back the non-urgent liquidations"
+            );
+            self.notifier.notify(
+                Severity::Warning,
+                format!(
+                    "Fee spike: median L2 gas price of {median} fri above {cap}, the non-urgent liquidations are held back"
+                ),
+            );
+        } else {
+            tracing::info!(
+                median = %median,
+                cap,
+                "[⛽ Fees] Fees normalized: median L2 gas price of {median} fri, resuming the liquidations"
+            );
+            self.notifier.notify(
+                Severity::Info,
+                format!(
+                    "Fees normalized: median L2 gas price of {median} fri, liquidations resumed"
+                ),
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+    use starknet::core::types::Felt;
+
+    use super::FeeOracle;
+    use crate::{
+        config::{GasGuardAction, GasGuardConfig},
+        types::position::{LiquidationCandidate, PositionKey},
+    };
+
+    fn candidate(expected_profit: u64, ltv_excess: &str) -> LiquidationCandidate {
+        LiquidationCandidate {
+            key: PositionKey {
+                pool_id: Felt::ONE,
+                collateral: Felt::TWO,
+                debt: Felt::THREE,
+                user: Felt::from(expected_profit),
+                protocol: Default::default(),
+            },
+            expected_profit: BigDecimal::from(expected_profit),
+            ltv_excess: ltv_excess.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_fee_oracle_spikes() {
+        let fees = FeeOracle::new(GasGuardConfig {
+            enabled: true,
+            max_l2_gas_price: 100,
+            window_blocks: 3,
+            ..Default::default()
+        });
+        assert_eq!(fees.record(1, 50), None);
+        assert_eq!(fees.record(2, 90), None);
+        // One expensive block does not move the median.
+        assert_eq!(fees.record(3, 500), None);
+        assert!(!fees.is_spiking());
+
+        assert_eq!(fees.record(4, 300), Some(true));
+        assert_eq!(fees.median(), Some(300));
+        assert!(fees.is_spiking());
+        // The same block is recorded once.
+        assert_eq!(fees.record(4, 10), None);
+
+        assert_eq!(fees.record(5, 60), None);
+        assert_eq!(fees.record(6, 70), Some(false));
+        assert!(!fees.is_spiking());
+    }
+
+    #[test]
+    fn test_fee_oracle_defers_non_urgent_liquidations() {
+        let config = GasGuardConfig {
+            enabled: true,
+            max_l2_gas_price: 100,
+            window_blocks: 1,
+            ..Default::default()
+        };
+        let fees = FeeOracle::new(config.clone());
+        let (small, large, urgent) = (
+            candidate(10, "0.01"),
+            candidate(5_000, "0.01"),
+            candidate(10, "0.2"),
+        );
+        assert!(!fees.defers(&small));
+
+        fees.record(1, 1_000);
+        assert!(fees.defers(&small) && fees.defers(&large));
+        assert!(!fees.defers(&urgent));
+
+        let fees = FeeOracle::new(GasGuardConfig {
+            action: GasGuardAction::RaiseMinProfit,
+            ..config.clone()
+        });
+        fees.record(1, 1_000);
+        assert!(fees.defers(&small));
+        assert!(!fees.defers(&large) && !fees.defers(&urgent));
+
+        // The thresholds are reloaded, not the rest of the config.
+        fees.reload(&GasGuardConfig {
+            action: GasGuardAction::RaiseMinProfit,
+            spike_min_expected_profit_usd: BigDecimal::from(10_000),
+            enabled: false,
+            ..config.clone()
+        });
+        assert!(fees.defers(&large));
+
+        // Disabled, the fees never hold back a liquidation.
+        let fees = FeeOracle::new(GasGuardConfig {
+            enabled: false,
+            ..config
+        });
+        fees.record(1, 1_000);
+        assert!(!fees.defers(&small));
+    }
+}
@@ -0,0 +1,160 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use starknet::{
+    core::types::Felt,
+    providers::{JsonRpcClient, jsonrpc::HttpTransport},
+};
+use tokio::{task::JoinSet, time::interval};
+
+use crate::{
+    config::Config,
+    storages::SharedStorage,
+    types::ledger::Finality,
+    utils::{is_accepted_on_l1, services::Service, shutdown::Shutdown},
+};
+
+/// Tracks the finality of the transactions of the bot: the liquidations of
+/// the history & the payouts of the ledgers accepted on L2 are checked every
+/// `finality.interval_seconds` until they are accepted on L1, i.e. their
+/// block is proven on Ethereum. With several tenant games, the payouts of
+/// each one are tracked in its storage.
+#[derive(Clone)]
+pub struct FinalityService {
+    config: Config,
+    rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+    /// Storage holding the liquidation history.
+    storage: SharedStorage,
+    /// Storages holding the payout ledgers, one per tenant.
+    ledgers: Vec<SharedStorage>,
+}
+
+#[async_trait::async_trait]
+impl Service for FinalityService {
+    async fn start(
+        &mut self,
+        join_set: &mut JoinSet<anyhow::Result<()>>,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<()> {
+        let service = self.clone();
+        join_set.spawn(async move {
+            tracing::info!("⛓️ Finality service started");
+            service.run_forever(shutdown).await?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+impl FinalityService {
+    pub fn new(
+        config: Config,
+        rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+        storage: SharedStorage,
+        ledgers: Vec<SharedStorage>,
+    ) -> Self {
+        Self {
+            config,
+            rpc_client,
+            storage,
+            ledgers,
+        }
+    }
+
+    /// Checks the transactions every `interval_seconds`. RPC failures are only
+    /// logged: the transactions are checked again by the next round.
+    pub async fn run_forever(&self, shutdown: Shutdown) -> Result<()> {
+        let mut check_interval =
+            interval(Duration::from_secs(self.config.finality.interval_seconds));
+
+        loop {
+            tokio::select! {
+                _ = shutdown.wait() => {
+                    tracing::info!("[⛓️ Finality] 🛑 Stopped tracking the finality");
+                    return Ok(());
+                }
+
+                _ = check_interval.tick() => {
+                    if let Err(e) = self.check().await {
+                        tracing::warn!(error = %e, "[⛓️ Finality] Could not check the finality of the transactions");
+                    }
+                }
+            }
+        }
+    }
+
+    async fn check(&self) -> Result<()> {
+        // A transaction is checked once per round, e.g. a distribution
+        // recorded in several ledgers.
+        let mut statuses = HashMap::new();
+
+        let liquidations: Vec<Felt> = self
+            .storage
+            .lock()
+            .await
+            .get_liquidation_history()
+            .iter()
+            .filter(|record| record.finality == Some(Finality::AcceptedOnL2))
+            .filter_map(|record| record.tx_hash)
+            .collect();
+        let finalized_liquidations = self.accepted_on_l1(&liquidations, &mut statuses).await?;
+        if !finalized_liquidations.is_empty() {
+            self.storage
+                .lock()
+                .await
+                .save_liquidations_accepted_on_l1(&finalized_liquidations)
+                .await?;
+        }
+
+        let mut finalized_payouts = 0;
+        for storage in self.ledgers.iter() {
+            let pending = storage
+                .lock()
+                .await
+                .get_payout_ledger()
+                .txs_accepted_on_l2();
+            let finalized = self.accepted_on_l1(&pending, &mut statuses).await?;
+            if finalized.is_empty() {
+                continue;
+            }
+            // The ledger is re-read as payouts may have been recorded meanwhile.
+            let mut storage = storage.lock().await;
+            let mut ledger = storage.get_payout_ledger();
+            ledger.mark_accepted_on_l1(&finalized);
+            storage.save_payout_ledger(&ledger).await?;
+            finalized_payouts += finalized.len();
+        }
+
+        if !finalized_liquidations.is_empty() || finalized_payouts > 0 {
+            tracing::info!(
+                "[⛓️ Finality] {} liquidation(s) & {} payout transaction(s) accepted on L1",
+                finalized_liquidations.len(),
+                finalized_payouts
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the transactions accepted on L1 among the given ones.
+    async fn accepted_on_l1(
+        &self,
+        tx_hashes: &[Felt],
+        statuses: &mut HashMap<Felt, bool>,
+    ) -> Result<Vec<Felt>> {
+        let mut finalized = vec![];
+        for tx_hash in tx_hashes {
+            let accepted = match statuses.get(tx_hash) {
+                Some(accepted) => *accepted,
+                None => {
+                    let accepted = is_accepted_on_l1(&self.rpc_client, *tx_hash).await?;
+                    statuses.insert(*tx_hash, accepted);
+                    accepted
+                }
+            };
+            if accepted {
+                finalized.push(*tx_hash);
+            }
+        }
+        Ok(finalized)
+    }
+}
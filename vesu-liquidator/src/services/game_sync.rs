@@ -0,0 +1,253 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use starknet::core::types::Felt;
+use tokio::{task::JoinSet, time::interval};
+
+use crate::{
+    config::{Config, DistributionStrategy},
+    storages::SharedStorage,
+    types::game::{GameMirror, GameState},
+    utils::{
+        http::HttpClient,
+        services::Service,
+        shutdown::Shutdown,
+        torii::{ToriiApi, ToriiClient},
+        unix_now,
+    },
+};
+
+/// Keeps a local mirror of the game models indexed by Torii, persisted in the
+/// storage so restarts don't begin with an empty view.
+#[derive(Clone)]
+pub struct GameSyncService {
+    config: Config,
+    torii: Arc<dyn ToriiApi>,
+    mirror: GameMirror,
+    storage: SharedStorage,
+}
+
+#[async_trait::async_trait]
+impl Service for GameSyncService {
+    async fn start(
+        &mut self,
+        join_set: &mut JoinSet<anyhow::Result<()>>,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<()> {
+        let service = self.clone();
+        join_set.spawn(async move {
+            tracing::info!("🎮 Game sync service started");
+            service.run_forever(shutdown).await?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+impl GameSyncService {
+    pub fn new(
+        config: Config,
+        mirror: GameMirror,
+        storage: SharedStorage,
+        http_client: HttpClient,
+    ) -> Self {
+        Self {
+            torii: Arc::new(ToriiClient::from_config(http_client, &config)),
+            config,
+            mirror,
+            storage,
+        }
+    }
+
+    /// Syncs the mirror every `interval_seconds`. Torii failures are only
+    /// logged: the mirror keeps its last known state.
+    pub async fn run_forever(&self, shutdown: Shutdown) -> Result<()> {
+        let mut sync_interval =
+            interval(Duration::from_secs(self.config.game_sync.interval_seconds));
+
+        loop {
+            tokio::select! {
+                _ = shutdown.wait() => {
+                    tracing::info!("[🎮 Game Sync] 🛑 Stopped syncing");
+                    return Ok(());
+                }
+
+                _ = sync_interval.tick() => {
+                    match self.sync().await {
+                        Ok(state) => {
+                            self.storage.lock().await.save_game_state(&state).await?;
+                            self.mirror.replace(state);
+                        }
+                        Err(e) => {
+                            tracing::warn!(error = %e, "[🎮 Game Sync] Could not sync game state from Torii");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fetches the latest game state. Seasons are synced incrementally, the
+    /// redeem queue, the leaderboard (of the running season with
+    /// `distribution.seasons`) & the payout addresses of their players are
+    /// refreshed.
+    async fn sync(&self) -> Result<GameState> {
+        let mut state = self.mirror.snapshot();
+
+        let new_seasons = self.torii.get_seasons(state.last_season_id()).await?;
+        state.seasons.extend(new_seasons);
+        state.redeem_queue = self.torii.get_redeem_queue().await?;
+        state.highest_score = self.torii.get_highest_score().await?;
+        let distribution = &self.config.distribution;
+        state.leaderboard = match distribution.strategy {
+            DistributionStrategy::Leaderboard => {
+                let season_id = distribution
+                    .seasons
+                    .then(|| state.active_season(unix_now()))
+                    .flatten();
+                self.torii
+                    .get_leaderboard(distribution.leaderboard_size, season_id)
+                    .await?
+            }
+            _ => vec![],
+        };
+
+        state.payout_addresses.clear();
+        let players = state
+            .redeem_queue
+            .iter()
+            .map(|redeemer| &redeemer.player)
+            .chain(state.leaderboard.iter().map(|entry| &entry.player));
+        for player in players {
+            let player = Felt::from_hex(player)?;
+            if let Some(payout) = self.torii.get_payout_address(player).await? {
+                state
+                    .payout_addresses
+                    .insert(format!("{player:#x}"), payout);
+            }
+        }
+
+        state.synced_at = unix_now();
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, sync::Arc};
+
+    use anyhow::Result;
+    use futures_util::lock::Mutex;
+    use starknet::core::types::Felt;
+
+    use super::GameSyncService;
+    use crate::{
+        cli::{LiquidationMode, NetworkName},
+        config::Config,
+        storages::{Storage, json::JsonStorage},
+        types::game::{GameMirror, GameState},
+        utils::{
+            torii::{PayoutAddressModel, PlayerScoreModel, RedeemModel, SeasonModel, ToriiApi},
+            unix_now,
+        },
+    };
+
+    fn season(season_id: u32) -> SeasonModel {
+        SeasonModel {
+            season_id,
+            start_time: 0,
+            end_time: 0,
+        }
+    }
+
+    /// Torii with a single season after the first one, a single player in the
+    /// queue & the payout address of player `0x2`.
+    struct MockTorii;
+
+    #[async_trait::async_trait]
+    impl ToriiApi for MockTorii {
+        async fn get_redeem_queue(&self) -> Result<Vec<RedeemModel>> {
+            Ok(vec![RedeemModel {
+                player: "0x2".to_string(),
+                score: 10,
+                timestamp: 0,
+            }])
+        }
+
+        async fn get_seasons(&self, after_season_id: Option<u32>) -> Result<Vec<SeasonModel>> {
+            assert_eq!(after_season_id, Some(1));
+            Ok(vec![season(2)])
+        }
+
+        async fn get_highest_score(&self) -> Result<Option<u128>> {
+            Ok(Some(50))
+        }
+
+        async fn get_leaderboard(
+            &self,
+            _size: usize,
+            _season_id: Option<u32>,
+        ) -> Result<Vec<PlayerScoreModel>> {
+            Ok(vec![])
+        }
+
+        async fn get_payout_address(&self, player: Felt) -> Result<Option<PayoutAddressModel>> {
+            Ok((player == Felt::TWO).then(|| PayoutAddressModel {
+                player: "0x2".to_string(),
+                payout_address: "0x22".to_string(),
+                set_by: "0x2".to_string(),
+            }))
+        }
+
+        async fn get_account_created_at(&self, _player: Felt) -> Result<Option<u64>> {
+            Ok(None)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sync() {
+        let config = Config::new(
+            NetworkName::Mainnet,
+            LiquidationMode::Full,
+            &PathBuf::from("./config.yaml"),
+        )
+        .unwrap();
+        let storage_path = std::env::temp_dir()
+            .join(format!("vesu-liquidator-{}-game-sync", std::process::id()))
+            .display()
+            .to_string();
+        let storage: Box<dyn Storage> = Box::new(JsonStorage::new(&storage_path));
+        let stale_payout = PayoutAddressModel {
+            player: "0x3".to_string(),
+            payout_address: "0x33".to_string(),
+            set_by: "0x3".to_string(),
+        };
+        let mirror = GameMirror::new(GameState {
+            seasons: vec![season(1)],
+            payout_addresses: [("0x3".to_string(), stale_payout)].into(),
+            ..Default::default()
+        });
+        let service = GameSyncService {
+            config,
+            torii: Arc::new(MockTorii),
+            mirror: mirror.clone(),
+            storage: Arc::new(Mutex::new(storage)),
+        };
+
+        let state = service.sync().await.unwrap();
+        // The seasons are synced incrementally.
+        let season_ids: Vec<_> = state.seasons.iter().map(|s| s.season_id).collect();
+        assert_eq!(season_ids, vec![1, 2]);
+        assert_eq!(state.redeem_queue[0].player, "0x2");
+        assert_eq!(state.highest_score, Some(50));
+        // Only the payout addresses of the players in the queue are kept.
+        assert_eq!(state.payout_addresses.len(), 1);
+        assert_eq!(
+            state.payout_address(Felt::TWO).unwrap().payout_address,
+            "0x22"
+        );
+        assert!(state.is_fresh(0, unix_now()));
+        // The mirror is only replaced by the sync loop.
+        assert_eq!(mirror.snapshot().synced_at, 0);
+    }
+}
@@ -1,4 +1,6 @@
-use anyhow::Result;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::{Result, anyhow};
 use apibara_core::starknet::v1alpha2::Event;
 use apibara_core::{
     node::v1alpha2::DataFinality,
@@ -7,37 +9,85 @@ use apibara_core::{
 use apibara_sdk::{ClientBuilder, Configuration, Uri, configuration};
 use dashmap::DashSet;
 use futures_util::TryStreamExt;
-use starknet::core::types::Felt;
-use tokio::sync::mpsc::UnboundedSender;
+use starknet::core::types::{BlockId, EventFilter, Felt};
+use starknet::providers::{JsonRpcClient, Provider, jsonrpc::HttpTransport};
 use tokio::task::JoinSet;
+use tokio::time::{Interval, MissedTickBehavior, interval};
 
-use crate::cli::NetworkName;
-use crate::config::{Config, MIGRATE_POSITION_EVENT, MODIFY_POSITION_EVENT};
+use crate::config::{Config, LiveConfig};
+use crate::protocols::Protocols;
+use crate::services::notifier::Notifier;
+use crate::services::oracle::LatestOraclePrices;
+use crate::types::notification::Severity;
+use crate::utils::rpc::StarknetRpc;
 use crate::utils::services::Service;
+use crate::utils::shutdown::Shutdown;
 use crate::{
-    types::position::Position,
+    types::{
+        competition::ObservedLiquidation,
+        indexer::{IndexerEvent, IndexerSender},
+        position::{Position, PositionKey, PositionsMap},
+    },
     utils::conversions::{apibara_field_as_felt, felt_as_apibara_field},
 };
 
 const INDEXING_STREAM_CHUNK_SIZE: usize = 1;
 
+/// Events requested per `getEvents` page when backfilling.
+const BACKFILL_EVENTS_CHUNK_SIZE: u64 = 1_000;
+
+/// What the indexer values the new positions with, to discard the ones below
+/// `filters.min_position_value_usd`.
+#[derive(Clone)]
+struct PositionSampler {
+    rpc: Arc<dyn StarknetRpc>,
+    oracle_prices: LatestOraclePrices,
+    /// The positions monitored, kept whatever their value.
+    tracked: PositionsMap,
+}
+
+/// What the watchdog of the indexer compares its progress to the head of the
+/// chain with, see [`crate::config::WatchdogConfig`].
+#[derive(Clone)]
+struct Watchdog {
+    rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+    notifier: Notifier,
+}
+
+/// Progress of the indexer at the previous check of its watchdog.
+#[derive(Default)]
+struct LagState {
+    last_block: u64,
+    /// The lag was alerted & hasn't recovered yet.
+    alerted: bool,
+}
+
 #[derive(Clone)]
 pub struct IndexerService {
     config: Config,
+    /// The reloadable fields of the config, e.g. the position filters.
+    live: LiveConfig,
     uri: Uri,
     apibara_api_key: String,
     stream_config: Configuration<Filter>,
-    positions_sender: UnboundedSender<(u64, Position)>,
-    seen_positions: DashSet<u64>,
+    events_sender: IndexerSender,
+    seen_positions: DashSet<PositionKey>,
+    protocols: Protocols,
+    sampler: Option<PositionSampler>,
+    watchdog: Option<Watchdog>,
 }
 
 #[async_trait::async_trait]
 impl Service for IndexerService {
-    async fn start(&mut self, join_set: &mut JoinSet<anyhow::Result<()>>) -> anyhow::Result<()> {
+    async fn start(
+        &mut self,
+        join_set: &mut JoinSet<anyhow::Result<()>>,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<()> {
         let service = self.clone();
         join_set.spawn(async move {
             tracing::info!("🔍 Indexer service started");
-            service.run_forever().await?;
+            service.run_forever(shutdown).await?;
             Ok(())
         });
         Ok(())
@@ -48,50 +98,115 @@ impl IndexerService {
     pub fn new(
         config: Config,
         apibara_api_key: String,
-        positions_sender: UnboundedSender<(u64, Position)>,
+        events_sender: IndexerSender,
         from_block: u64,
     ) -> IndexerService {
-        let uri = match config.network {
-            NetworkName::Mainnet => Uri::from_static("https://mainnet.starknet.a5a.ch"),
-            NetworkName::Sepolia => Uri::from_static("https://sepolia.starknet.a5a.ch"),
-        };
+        let uri: Uri = config
+            .apibara_url
+            .parse()
+            .expect("apibara_url is validated when loading the config");
+
+        let protocols = Protocols::from_config(&config);
+        let position_events: Vec<(Felt, Felt)> = protocols
+            .iter()
+            .flat_map(|protocol| {
+                let address = protocol.events_address();
+                protocol
+                    .event_selectors()
+                    .into_iter()
+                    .map(move |selector| (address, selector))
+            })
+            .collect();
 
         let stream_config = Configuration::<Filter>::default()
             .with_starting_block(from_block)
-            .with_finality(DataFinality::DataStatusPending)
+            .with_finality(stream_finality(&config))
             .with_filter(|mut filter| {
-                filter
-                    .with_header(HeaderFilter::weak())
-                    .add_event(|event| {
-                        event
-                            .with_from_address(felt_as_apibara_field(&config.singleton_address))
-                            .with_keys(vec![felt_as_apibara_field(&MODIFY_POSITION_EVENT)])
-                    })
-                    .add_event(|event| {
+                filter.with_header(HeaderFilter::weak());
+                for (address, selector) in &position_events {
+                    filter.add_event(|event| {
                         event
-                            .with_from_address(felt_as_apibara_field(&config.singleton_address))
-                            .with_keys(vec![felt_as_apibara_field(&MIGRATE_POSITION_EVENT)])
-                    })
-                    .build()
+                            .with_from_address(felt_as_apibara_field(address))
+                            .with_keys(vec![felt_as_apibara_field(selector)])
+                    });
+                }
+                filter.build()
             });
 
         IndexerService {
+            live: LiveConfig::new(config.clone()),
             config,
+            protocols,
             uri,
             apibara_api_key,
             stream_config,
-            positions_sender,
+            events_sender,
             seen_positions: DashSet::default(),
+            sampler: None,
+            watchdog: None,
         }
     }
 
-    /// Retrieve all the ModifyPosition events emitted from the Vesu Singleton Contract.
-    pub async fn run_forever(mut self) -> Result<()> {
+    /// Applies the reloads of the config, see [`LiveConfig`].
+    pub fn with_live_config(mut self, live: LiveConfig) -> Self {
+        self.live = live;
+        self
+    }
+
+    /// Values the new positions with the RPC & the oracle prices, to discard
+    /// the small ones when `filters.min_position_value_usd` is set.
+    pub fn with_sampler(
+        mut self,
+        rpc: Arc<dyn StarknetRpc>,
+        oracle_prices: LatestOraclePrices,
+        tracked: PositionsMap,
+    ) -> Self {
+        self.sampler = Some(PositionSampler {
+            rpc,
+            oracle_prices,
+            tracked,
+        });
+        self
+    }
+
+    /// Checks the lag of the indexer behind the head of the chain with the
+    /// RPC, alerting through the notifier, see `watchdog` in the config.
+    pub fn with_watchdog(
+        mut self,
+        rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+        notifier: Notifier,
+    ) -> Self {
+        self.watchdog = Some(Watchdog {
+            rpc_client,
+            notifier,
+        });
+        self
+    }
+
+    /// Retrieve all the events of the positions of the lending protocols, e.g.
+    /// the ModifyPosition events emitted from the Vesu Singleton Contract.
+    /// Each processed block is reported once its positions are sent, & the
+    /// blocks invalidated by a reorg are reported to be rolled back.
+    /// A restarted indexer resumes from the last block it processed.
+    pub async fn run_forever(mut self, shutdown: Shutdown) -> Result<()> {
         let (config_client, config_stream) = configuration::channel(INDEXING_STREAM_CHUNK_SIZE);
 
         let mut reached_pending_block: bool = false;
 
-        config_client.send(self.stream_config.clone()).await?;
+        let stream_config = match self.events_sender.last_block() {
+            0 => self.stream_config.clone(),
+            last_block => self.stream_config.clone().with_starting_block(last_block),
+        };
+        config_client.send(stream_config).await?;
+
+        let watchdog_enabled = self.watchdog.is_some() && self.config.watchdog.interval_seconds > 0;
+        let mut watchdog_interval = interval(Duration::from_secs(
+            self.config.watchdog.interval_seconds.max(1),
+        ));
+        watchdog_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // The first tick completes immediately.
+        watchdog_interval.tick().await;
+        let mut lag_state = LagState::default();
 
         let mut stream = ClientBuilder::default()
             .with_bearer_token(Some(self.apibara_api_key.clone()))
@@ -103,7 +218,18 @@ impl IndexerService {
             .unwrap();
 
         loop {
-            match stream.try_next().await {
+            let next_message = tokio::select! {
+                _ = shutdown.wait() => {
+                    tracing::info!("[🔍 Indexer] 🛑 Stopped indexing");
+                    return Ok(());
+                }
+                _ = watchdog_interval.tick(), if watchdog_enabled => {
+                    self.check_lag(&mut lag_state, reached_pending_block).await?;
+                    continue;
+                }
+                next_message = stream.try_next() => next_message,
+            };
+            match next_message {
                 Ok(Some(response)) => match response {
                     apibara_sdk::DataMessage::Data {
                         cursor: _,
@@ -111,28 +237,53 @@ impl IndexerService {
                         finality,
                         batch,
                     } => {
-                        if finality == DataFinality::DataStatusPending && !reached_pending_block {
-                            tracing::info!("[🔍 Indexer] 🥳🎉 Reached pending block!");
+                        if finality == stream_finality(&self.config) && !reached_pending_block {
+                            tracing::info!("[🔍 Indexer] 🥳🎉 Reached the head of the chain!");
                             reached_pending_block = true;
                         }
                         for block in batch {
+                            let (block_number, block_hash) = match block.header.clone() {
+                                Some(hdr) => (
+                                    hdr.block_number,
+                                    hdr.block_hash
+                                        .as_ref()
+                                        .map(apibara_field_as_felt)
+                                        .unwrap_or_default(),
+                                ),
+                                None => (0, Felt::ZERO),
+                            };
                             for event in block.events {
+                                let tx_hash = event
+                                    .transaction
+                                    .as_ref()
+                                    .and_then(|tx| tx.meta.as_ref())
+                                    .and_then(|meta| meta.hash.as_ref())
+                                    .map(apibara_field_as_felt);
                                 if let Some(event) = event.event {
-                                    let block_number = match block.header.clone() {
-                                        Some(hdr) => hdr.block_number,
-                                        None => 0,
-                                    };
-                                    self.create_position_from_event(block_number, event).await?;
+                                    self.create_position_from_event(block_number, tx_hash, event)
+                                        .await?;
                                 }
                             }
+                            self.events_sender
+                                .send(IndexerEvent::Block {
+                                    number: block_number,
+                                    hash: block_hash,
+                                })
+                                .await?;
                         }
                     }
                     apibara_sdk::DataMessage::Invalidate { cursor } => match cursor {
                         Some(c) => {
-                            return Err(anyhow::anyhow!(
-                                "Received an invalidate request data at {}",
-                                &c.order_key
-                            ));
+                            tracing::warn!(
+                                block_number = c.order_key,
+                                "[🔍 Indexer] ⛓️ Reorg detected, blocks after {} are invalidated",
+                                c.order_key
+                            );
+                            self.events_sender
+                                .send(IndexerEvent::Reorg {
+                                    last_valid_block: c.order_key,
+                                })
+                                .await?;
                         }
                         None => {
                             return Err(anyhow::anyhow!(
@@ -150,34 +301,260 @@ impl IndexerService {
         }
     }
 
-    /// Index the provided event & creates a new position.
-    async fn create_position_from_event(&mut self, block_number: u64, event: Event) -> Result<()> {
-        if event.from_address.is_none() {
+    /// Index the provided event & creates the positions it is about. The
+    /// liquidations are reported first, so the monitoring service knows who
+    /// liquidated the positions before refreshing them.
+    async fn create_position_from_event(
+        &mut self,
+        block_number: u64,
+        tx_hash: Option<Felt>,
+        event: Event,
+    ) -> Result<()> {
+        let Some(from_address) = event.from_address.as_ref().map(apibara_field_as_felt) else {
             return Ok(());
-        }
+        };
+        let keys: Vec<Felt> = event.keys.iter().map(apibara_field_as_felt).collect();
+        let data: Vec<Felt> = event.data.iter().map(apibara_field_as_felt).collect();
 
-        let debt_address = apibara_field_as_felt(&event.keys[3]);
-        // Corresponds to event associated with the extension contract - we ignore them.
-        if debt_address == Felt::ZERO {
-            return Ok(());
+        if let (Some(tx_hash), Some((key, liquidator))) = (
+            tx_hash,
+            self.protocols
+                .liquidation_from_event(from_address, &keys, &data),
+        ) {
+            self.events_sender
+                .send(IndexerEvent::Liquidation(
+                    block_number,
+                    ObservedLiquidation {
+                        key,
+                        tx_hash,
+                        liquidator,
+                    },
+                ))
+                .await?;
         }
 
-        // Create the new position & sends it to the monitoring service.
-        if let Some(new_position) = Position::from_event(&self.config, &event.keys) {
+        // Create the new positions & sends them to the monitoring service.
+        // Events about no position, e.g. from the Vesu extension contract,
+        // are ignored.
+        for new_position in
+            self.protocols
+                .positions_from_event(&self.config, from_address, &keys, &data)
+        {
             let position_key = new_position.key();
+            if !self.live.current().position_filter.accepts(&position_key) {
+                tracing::debug!(
+                    "[🔍 Indexer] Position #{} is filtered out, skipping it",
+                    position_key
+                );
+                continue;
+            }
+            if self.is_below_min_value(&new_position).await {
+                tracing::debug!(
+                    "[🔍 Indexer] Position #{} is below the min value, skipping it",
+                    position_key
+                );
+                continue;
+            }
             if self.seen_positions.insert(position_key) {
                 tracing::info!(
+                    position_key = ?position_key,
+                    pool_id = format!("{:#x}", position_key.pool_id),
+                    block_number,
                     "[🔍 Indexer] Found new/updated position at block {}",
                     block_number
                 );
             }
-            match self.positions_sender.send((block_number, new_position)) {
-                Ok(_) => {}
-                Err(e) => panic!("[🔍 Indexer] 😱 Could not send position: {}", e),
+            self.events_sender
+                .send(IndexerEvent::Position(block_number, new_position))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Compares the last block processed to the head of the chain. A lag
+    /// beyond `watchdog.max_lag_blocks` is alerted once the indexer reached
+    /// the head, or while catching up if it processed no block since the
+    /// previous check: then, with `watchdog.restart_indexer`, the indexer
+    /// fails to be restarted by its supervisor.
+    async fn check_lag(&self, state: &mut LagState, reached_head: bool) -> Result<()> {
+        let Some(watchdog) = &self.watchdog else {
+            return Ok(());
+        };
+        let head_block = match watchdog.rpc_client.block_number().await {
+            Ok(head_block) => head_block,
+            Err(e) => {
+                tracing::warn!(error = %e, "[🔍 Indexer] Watchdog could not fetch the head of the chain");
+                return Ok(());
+            }
+        };
+        let last_block = self.events_sender.last_block();
+        let lag = head_block.saturating_sub(last_block);
+        let progressed = last_block > state.last_block;
+        state.last_block = last_block;
+
+        let lagging = lag > self.config.watchdog.max_lag_blocks && (reached_head || !progressed);
+        self.events_sender
+            .record_head(head_block, lagging && !progressed);
+        if !lagging {
+            if state.alerted && lag <= self.config.watchdog.max_lag_blocks {
+                state.alerted = false;
+                watchdog.notifier.notify(
+                    Severity::Info,
+                    format!(
+                        "Indexer caught up at block {last_block}, {lag} block(s) behind the head"
+                    ),
+                );
             }
-        } else {
-            tracing::error!("Could not create position from event :/");
+            return Ok(());
+        }
+        tracing::warn!(
+            last_block,
+            head_block,
+            "[🔍 Indexer] ⏳ Lagging {} blocks behind the head of the chain",
+            lag
+        );
+        if !state.alerted {
+            state.alerted = true;
+            watchdog.notifier.notify(
+                Severity::Warning,
+                format!(
+                    "Indexer lagging: last block {last_block} is {lag} blocks behind the head {head_block}{}",
+                    if progressed { "" } else { ", no block processed since the last check" }
+                ),
+            );
+        }
+        if !progressed && self.config.watchdog.restart_indexer {
+            return Err(anyhow!(
+                "Indexer stalled at block {last_block}, {lag} blocks behind the head"
+            ));
         }
         Ok(())
     }
+
+    /// Returns if the position is new & its collateral is worth less than
+    /// `filters.min_position_value_usd`, fetching its amounts. The positions
+    /// already tracked are kept, so the monitoring sees them shrink or close,
+    /// as well as the ones that can't be valued yet.
+    async fn is_below_min_value(&self, position: &Position) -> bool {
+        let live_config = self.live.current();
+        let position_filter = &live_config.position_filter;
+        let (Some(sampler), Some(_)) = (&self.sampler, &position_filter.min_value_usd) else {
+            return false;
+        };
+        let key = position.key();
+        if self.seen_positions.contains(&key) || sampler.tracked.by_key.contains_key(&key) {
+            return false;
+        }
+        let mut position = position.clone();
+        let value = position
+            .update(
+                sampler.rpc.as_ref(),
+                &self.protocols,
+                &self.config.position_update,
+            )
+            .await
+            .and_then(|()| position.collateral_value(&sampler.oracle_prices));
+        match value {
+            Ok(value) => !position_filter.accepts_value(&value),
+            Err(e) => {
+                tracing::debug!(
+                    "[🔍 Indexer] Could not value position #{}, tracking it: {}",
+                    key,
+                    e
+                );
+                false
+            }
+        }
+    }
+}
+
+/// Block range scanned by [`backfill_positions`].
+#[derive(Debug, Clone, Copy)]
+pub struct BackfillRange {
+    pub from_block: u64,
+    pub to_block: u64,
+    /// Blocks scanned per chunk of paginated `getEvents` requests.
+    pub blocks_per_chunk: u64,
+    /// Maximum number of RPC requests sent per second.
+    pub requests_per_second: u32,
+}
+
+/// Scans the events creating or updating the positions of the lending
+/// protocols over a historical range with `getEvents`, chunk by chunk, e.g.
+/// the ModifyPosition & MigratePosition events emitted from the Vesu
+/// Singleton Contract. Returns the positions found, with the block of their
+/// last event.
+pub async fn backfill_positions(
+    config: &Config,
+    rpc_client: &JsonRpcClient<HttpTransport>,
+    range: BackfillRange,
+) -> Result<HashMap<PositionKey, (u64, Position)>> {
+    let mut rate_limiter = rate_limiter(range.requests_per_second);
+    let mut positions = HashMap::new();
+    let protocols = Protocols::from_config(config);
+
+    let mut chunk_start = range.from_block;
+    while chunk_start <= range.to_block {
+        let chunk_end = chunk_start
+            .saturating_add(range.blocks_per_chunk.max(1) - 1)
+            .min(range.to_block);
+        for protocol in protocols.iter() {
+            let filter = EventFilter {
+                from_block: Some(BlockId::Number(chunk_start)),
+                to_block: Some(BlockId::Number(chunk_end)),
+                address: Some(protocol.events_address()),
+                keys: Some(vec![protocol.event_selectors()]),
+            };
+            let mut continuation_token = None;
+            loop {
+                rate_limiter.tick().await;
+                let page = rpc_client
+                    .get_events(
+                        filter.clone(),
+                        continuation_token,
+                        BACKFILL_EVENTS_CHUNK_SIZE,
+                    )
+                    .await?;
+                for event in page.events {
+                    let block_number = event.block_number.unwrap_or(chunk_end);
+                    for position in protocol.positions_from_event(config, &event.keys, &event.data)
+                    {
+                        if config.position_filter.accepts(&position.key()) {
+                            positions.insert(position.key(), (block_number, position));
+                        }
+                    }
+                }
+                continuation_token = page.continuation_token;
+                if continuation_token.is_none() {
+                    break;
+                }
+            }
+        }
+        tracing::info!(
+            "[🔍 Indexer] ⏮️ Backfilled blocks {} to {}, {} position(s) found",
+            chunk_start,
+            chunk_end,
+            positions.len()
+        );
+        chunk_start = chunk_end + 1;
+    }
+    Ok(positions)
+}
+
+/// Finality of the streamed blocks: the pending ones are streamed only with
+/// `monitoring.use_pending_block`, the accepted ones otherwise.
+fn stream_finality(config: &Config) -> DataFinality {
+    if config.monitoring.use_pending_block {
+        DataFinality::DataStatusPending
+    } else {
+        DataFinality::DataStatusAccepted
+    }
+}
+
+/// Returns an interval ticking at most `requests_per_second` times per second,
+/// to be awaited before each request.
+pub fn rate_limiter(requests_per_second: u32) -> Interval {
+    let mut rate_limiter = interval(Duration::from_secs(1) / requests_per_second.max(1));
+    rate_limiter.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    rate_limiter
 }
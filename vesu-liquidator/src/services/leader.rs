@@ -0,0 +1,269 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
+use redis::{Client, Script, aio::ConnectionManager};
+use tokio::{
+    sync::OnceCell,
+    task::JoinSet,
+    time::{interval, timeout},
+};
+
+use crate::{
+    config::{Config, LeaderElectionConfig},
+    services::notifier::Notifier,
+    types::notification::Severity,
+    utils::{services::Service, shutdown::Shutdown},
+};
+
+/// Whether this instance sends the transactions. Without leader election, the
+/// instance always leads.
+#[derive(Clone)]
+pub struct Leadership(Arc<AtomicBool>);
+
+impl Default for Leadership {
+    fn default() -> Self {
+        Self(Arc::new(AtomicBool::new(true)))
+    }
+}
+
+impl Leadership {
+    /// A follower until it acquires the lease, see [`LeaderElectionService`].
+    pub fn follower() -> Self {
+        Self(Arc::new(AtomicBool::new(false)))
+    }
+
+    /// The leadership of the instance under `leader_election`.
+    pub fn from_config(config: &Config) -> Self {
+        if config.leader_election.enabled {
+            Self::follower()
+        } else {
+            Self::default()
+        }
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Returns true if the leadership changed.
+    fn set(&self, leader: bool) -> bool {
+        self.0.swap(leader, Ordering::Relaxed) != leader
+    }
+}
+
+/// Claims the lease if it is free or held by the instance, renewing it for
+/// `ttl` seconds. Returns 1 if the instance holds it.
+const REDIS_HEARTBEAT_SCRIPT: &str = "local holder = redis.call('GET', KEYS[1]) \
+    if holder and holder ~= ARGV[1] then return 0 end \
+    redis.call('SET', KEYS[1], ARGV[1], 'EX', ARGV[2]) \
+    return 1";
+
+/// Deletes the lease if the instance holds it.
+const REDIS_RELEASE_SCRIPT: &str = "if redis.call('GET', KEYS[1]) == ARGV[1] then \
+    return redis.call('DEL', KEYS[1]) end \
+    return 0";
+
+/// Lease kept in a Redis key expiring after the TTL, claimed & renewed by
+/// scripts run atomically by the server: an instance leads as soon as it
+/// claims a free lease, whatever the clocks of the instances.
+struct RedisLeaseStore {
+    client: Client,
+    /// Kept alive across the heartbeats, reconnected when it drops.
+    connection: OnceCell<ConnectionManager>,
+    key: String,
+    heartbeat_script: Script,
+    release_script: Script,
+    /// Bound of each round trip, so a stuck server doesn't hold a leader
+    /// past its lease.
+    timeout: Duration,
+}
+
+impl RedisLeaseStore {
+    fn new(client: Client, key: String, timeout: Duration) -> Self {
+        Self {
+            client,
+            connection: OnceCell::new(),
+            key,
+            heartbeat_script: Script::new(REDIS_HEARTBEAT_SCRIPT),
+            release_script: Script::new(REDIS_RELEASE_SCRIPT),
+            timeout,
+        }
+    }
+
+    async fn connection(&self) -> Result<ConnectionManager> {
+        let connection = self
+            .connection
+            .get_or_try_init(|| ConnectionManager::new(self.client.clone()))
+            .await?;
+        Ok(connection.clone())
+    }
+
+    /// Renews or claims the lease for the instance, returns true if the
+    /// instance holds it.
+    async fn heartbeat(&self, instance: &str, ttl: u64) -> Result<bool> {
+        timeout(self.timeout, async {
+            let held: i64 = self
+                .heartbeat_script
+                .key(&self.key)
+                .arg(instance)
+                .arg(ttl)
+                .invoke_async(&mut self.connection().await?)
+                .await?;
+            anyhow::Ok(held == 1)
+        })
+        .await?
+    }
+
+    /// Removes the lease if the instance holds it.
+    async fn release(&self, instance: &str) -> Result<()> {
+        timeout(self.timeout, async {
+            let _: i64 = self
+                .release_script
+                .key(&self.key)
+                .arg(instance)
+                .invoke_async(&mut self.connection().await?)
+                .await?;
+            anyhow::Ok(())
+        })
+        .await?
+    }
+}
+
+/// Elects the instance sending the transactions among the replicas of the bot
+/// through a lease they share in Redis, see [`LeaderElectionConfig`]. The
+/// followers keep indexing & monitoring the positions, and take over once the
+/// lease of the leader expires.
+#[derive(Clone)]
+pub struct LeaderElectionService {
+    config: LeaderElectionConfig,
+    instance_id: String,
+    store: Arc<RedisLeaseStore>,
+    leadership: Leadership,
+    notifier: Notifier,
+}
+
+#[async_trait::async_trait]
+impl Service for LeaderElectionService {
+    async fn start(
+        &mut self,
+        join_set: &mut JoinSet<anyhow::Result<()>>,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<()> {
+        let service = self.clone();
+        join_set.spawn(async move {
+            tracing::info!(
+                "👑 Leader election service started for instance {}",
+                service.instance_id
+            );
+            service.run_forever(shutdown).await?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+impl LeaderElectionService {
+    pub fn new(config: &Config, leadership: Leadership, notifier: Notifier) -> Self {
+        let config = config.leader_election.clone();
+        let instance_id = config.instance_id.clone().unwrap_or_else(|| {
+            let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "bot".to_string());
+            format!("{host}-{}", std::process::id())
+        });
+        let store = Arc::new(RedisLeaseStore::new(
+            Client::open(config.redis_url.as_deref().unwrap_or_default())
+                .expect("redis_url is validated when loading the config"),
+            config.redis_key.clone(),
+            Duration::from_secs(config.heartbeat_interval_seconds),
+        ));
+        Self {
+            config,
+            instance_id,
+            store,
+            leadership,
+            notifier,
+        }
+    }
+
+    /// Renews or claims the lease every `heartbeat_interval_seconds`. On
+    /// shutdown, the leader releases it so a follower takes over at once.
+    pub async fn run_forever(&self, shutdown: Shutdown) -> Result<()> {
+        let mut heartbeat_interval =
+            interval(Duration::from_secs(self.config.heartbeat_interval_seconds));
+
+        loop {
+            tokio::select! {
+                _ = shutdown.wait() => {
+                    if self.leadership.is_leader() {
+                        self.leadership.set(false);
+                        self.store.release(&self.instance_id).await?;
+                    }
+                    tracing::info!("[👑 Leader] 🛑 Stopped");
+                    return Ok(());
+                }
+
+                _ = heartbeat_interval.tick() => {
+                    let leader = match self
+                        .store
+                        .heartbeat(&self.instance_id, self.config.lease_ttl_seconds)
+                        .await
+                    {
+                        Ok(leader) => leader,
+                        // The lease can't be renewed, a follower may take over.
+                        Err(e) => {
+                            tracing::warn!(error = %e, "[👑 Leader] Could not read or write the lease");
+                            false
+                        }
+                    };
+                    if self.leadership.set(leader) {
+                        self.report(leader);
+                    }
+                }
+            }
+        }
+    }
+
+    fn report(&self, leader: bool) {
+        let message = if leader {
+            format!("Instance {} is now the leader", self.instance_id)
+        } else {
+            format!(
+                "Instance {} is no longer the leader, its transactions stop",
+                self.instance_id
+            )
+        };
+        tracing::warn!("[👑 Leader] {message}");
+        self.notifier.notify(Severity::Warning, message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use redis::Client;
+    use tokio::net::TcpListener;
+
+    use super::RedisLeaseStore;
+
+    #[tokio::test]
+    async fn test_redis_lease_store() {
+        // A server that can't be reached fails the heartbeat & the release.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+        let store = RedisLeaseStore::new(
+            Client::open(format!("redis://{address}")).unwrap(),
+            "leader".to_string(),
+            Duration::from_secs(2),
+        );
+
+        assert!(store.heartbeat("bot-1", 10).await.is_err());
+        assert!(store.release("bot-1").await.is_err());
+    }
+}
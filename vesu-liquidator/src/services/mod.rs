@@ -1,10 +1,27 @@
+pub mod api;
+pub mod config_watcher;
+pub mod control;
+pub mod distribution;
+pub mod downtime;
+pub mod fees;
+pub mod finality;
+pub mod game_sync;
 pub mod indexer;
+pub mod leader;
 pub mod monitoring;
+pub mod notifier;
+pub mod operator;
 pub mod oracle;
+pub mod price_feed;
+pub mod reconciliation;
+pub mod rewards;
+pub mod tenants;
+pub mod tokens;
 
-use std::{cmp, sync::Arc};
+use std::{cmp, collections::HashMap, sync::Arc};
 
 use anyhow::Result;
+use futures_util::lock::Mutex;
 use starknet::providers::{JsonRpcClient, jsonrpc::HttpTransport};
 use tokio::sync::mpsc::unbounded_channel;
 
@@ -12,66 +29,402 @@ use oracle::{LatestOraclePrices, OracleService};
 
 use crate::{
     cli::RunCmd,
-    config::Config,
-    services::{indexer::IndexerService, monitoring::MonitoringService},
-    storages::{Storage, json::JsonStorage},
-    types::{account::StarknetAccount, position::Position},
-    utils::services::{Service, ServiceGroup},
+    config::{Config, LiveConfig},
+    services::{
+        api::ApiService,
+        config_watcher::{ConfigReloader, ConfigWatcherService},
+        control::{ControlService, Controls},
+        distribution::DistributionService,
+        downtime::DowntimeService,
+        fees::{FeeOracle, FeeOracleService},
+        finality::FinalityService,
+        game_sync::GameSyncService,
+        indexer::IndexerService,
+        leader::{LeaderElectionService, Leadership},
+        monitoring::MonitoringService,
+        notifier::{Notifier, NotifierService},
+        operator::Operator,
+        price_feed::PriceFeedService,
+        reconciliation::ReconciliationService,
+        tenants::{DEFAULT_TENANT, TenantsService, tenant_storage_path},
+    },
+    storages::{SharedStorage, Storage, json::JsonStorage},
+    types::{
+        account::{AccountPool, StarknetAccount},
+        balance::BotBalances,
+        distribution::LiquidationEarnings,
+        feed::EventFeed,
+        game::GameMirror,
+        indexer::indexer_channel,
+        notification::Notification,
+    },
+    utils::{
+        audit::AuditLog,
+        http::HttpClient,
+        profile::TickProfiler,
+        rpc::BlockRpc,
+        services::{Service, ServiceGroup},
+        shutdown::{Shutdown, wait_for_os_signal},
+        slo::SloTracker,
+    },
 };
 
 /// Starts all the services needed by the Liquidator Bot.
 /// This include:
 /// - the indexer service, that indexes blocks & send positions,
-/// - the monitoring service, that monitors & liquidates positions.
+/// - the oracle service, that polls the prices & the price feed service, that
+///   streams them if configured,
+/// - the monitoring service, that monitors & liquidates positions,
+/// - for each tenant game, the game sync service, that mirrors the game models
+///   indexed by Torii & the distribution service, that distributes its share of
+///   the liquidations earnings,
+/// - the tenants service, that splits the earnings between the tenant games,
+/// - the leader election service, that elects the instance sending the
+///   transactions among the replicas of the bot, if enabled,
+/// - the reconciliation service, that checks the payouts against the chain,
+/// - the downtime service, that reports the liquidations missed while offline,
+/// - the notifier service, that posts notifications on the configured channels,
+/// - the API service, that exposes the state of the bot & the runbook actions
+///   of the operators, see [`Operator`],
+/// - the config watcher service, that reports the changes of the config file
+///   & reloads it on SIGHUP, see [`ConfigReloader`].
+///
+/// Failing services are restarted by a supervisor, see [`crate::config::SupervisorConfig`].
+/// On SIGINT/SIGTERM, the services are notified and stopped gracefully.
 pub async fn start_all_services(
     config: Config,
     rpc_client: Arc<JsonRpcClient<HttpTransport>>,
     account: StarknetAccount,
+    extra_accounts: Vec<StarknetAccount>,
     run_cmd: RunCmd,
 ) -> Result<()> {
-    let (positions_sender, position_receiver) = unbounded_channel::<(u64, Position)>();
+    let (indexer_sender, indexer_receiver) =
+        indexer_channel(config.monitoring.indexer_channel_capacity);
+    let indexer_queue = indexer_sender.monitor();
+    let (earnings_sender, earnings_receiver) = unbounded_channel::<LiquidationEarnings>();
+    let (notifications_sender, notifications_receiver) = unbounded_channel::<Notification>();
+    let notifier = Notifier::new(notifications_sender);
+    let slo = SloTracker::new(config.slo.clone(), notifier.clone());
+    // The reloadable fields of the config, reloaded on SIGHUP or by the API.
+    let live_config = LiveConfig::new(config.clone());
+    let audit_log = AuditLog::new(run_cmd.bot_params.audit_log_path.clone());
+    let config_reloader = ConfigReloader::new(
+        run_cmd.bot_params.config_path.clone().unwrap_or_default(),
+        live_config.clone(),
+        notifier.clone(),
+        audit_log.clone(),
+    )?;
 
     // TODO: Add new methods of storage (s3, postgres, sqlite) and be able to define them in CLI
-    let mut storage = JsonStorage::new(
-        run_cmd
-            .storage_path
-            .unwrap_or_default()
-            .as_path()
-            .to_str()
-            .unwrap_or_default(),
+    let storage_path = run_cmd.bot_params.storage_path.clone().unwrap_or_default();
+    let mut storage = JsonStorage::new(storage_path.as_path().to_str().unwrap_or_default());
+    storage.load().await?;
+
+    let leadership = Leadership::from_config(&config);
+    // The prices of the previous run are restored until they are updated.
+    let latest_oracle_prices = LatestOraclePrices::from_config(&config);
+    latest_oracle_prices.restore(storage.get_oracle_prices());
+    let feed = EventFeed::new();
+    // Switched by the gRPC control plane & the operators.
+    let controls = Controls::default();
+    let balances = BotBalances::default();
+    // Shared by the services so the budgets of the hosts hold for the bot.
+    let http_client = HttpClient::new(&config.rate_limits);
+    let price_feed_service = config
+        .oracle
+        .stream_url
+        .clone()
+        .map(|stream_url| PriceFeedService::new(stream_url, latest_oracle_prices.clone()));
+    let fee_oracle = FeeOracle::new(config.gas_guard.clone());
+    let fee_oracle_service = config.gas_guard.enabled.then(|| {
+        FeeOracleService::new(
+            config.clone(),
+            rpc_client.clone(),
+            fee_oracle.clone(),
+            notifier.clone(),
+        )
+    });
+    let notifier_service = NotifierService::new(
+        config.clone(),
+        rpc_client.clone(),
+        account.account_address(),
+        notifications_receiver,
+        notifier.clone(),
+        reqwest::Client::new(),
+        balances.clone(),
+    )
+    .with_live_config(live_config.clone());
+    let monitoring_service = MonitoringService::new(
+        config.clone(),
+        rpc_client.clone(),
+        http_client.clone(),
+        AccountPool::new(
+            account.clone(),
+            extra_accounts,
+            config.transactions.min_account_fee_balance,
+        ),
+        indexer_receiver,
+        latest_oracle_prices.clone(),
+        Box::new(storage),
+        earnings_sender,
+        notifier.clone(),
+        slo.clone(),
+        feed.clone(),
+        balances.clone(),
+    )
+    .with_profiler(TickProfiler::new(run_cmd.profile))
+    .with_fee_oracle(fee_oracle)
+    .with_live_config(live_config.clone())
+    .with_leadership(leadership.clone())
+    .with_controls(controls.clone());
+    let oracle_service = OracleService::new(
+        config.clone(),
+        rpc_client.clone(),
+        http_client.clone(),
+        latest_oracle_prices.clone(),
+        notifier.clone(),
+    )
+    .with_storage(monitoring_service.storage());
+    let operator = Operator::new(
+        config.clone(),
+        controls.clone(),
+        rpc_client.clone(),
+        account.clone(),
+        leadership.clone(),
+        monitoring_service.storage(),
+        notifier.clone(),
+        audit_log.clone(),
     );
-    let (last_block_indexed, _) = storage.load().await?;
+    // A halt of the operators holds across restarts, until resumed.
+    operator.restore().await;
 
+    // Blocks orphaned while the bot was offline are rolled back before resuming.
+    let last_block_indexed = monitoring_service.rollback_orphaned_blocks().await?;
     let starting_block = cmp::max(run_cmd.starting_block, last_block_indexed);
     println!("  🥡 Starting from block {}\n\n", starting_block);
 
     let indexer_service = IndexerService::new(
         config.clone(),
         run_cmd.apibara_api_key.unwrap(),
-        positions_sender,
+        indexer_sender,
         starting_block,
+    )
+    .with_live_config(live_config.clone())
+    .with_sampler(
+        Arc::new(BlockRpc::new(
+            rpc_client.clone(),
+            config.monitoring.positions_block_id(),
+        )),
+        latest_oracle_prices.clone(),
+        monitoring_service.positions(),
+    )
+    .with_watchdog(rpc_client.clone(), notifier.clone());
+    let supervisor_config = config.supervisor.clone();
+    let config_watcher_service = ConfigWatcherService::new(
+        run_cmd.bot_params.config_path.clone().unwrap_or_default(),
+        config.network,
+        notifier.clone(),
+        audit_log,
+        config_reloader.clone(),
     );
-    let latest_oracle_prices = LatestOraclePrices::from_config(&config);
-    let oracle_service = OracleService::new(
-        config.pragma_oracle_address,
+
+    // Without tenants, the game of the network config gets all the earnings &
+    // shares the main storage.
+    let mut games: Vec<(String, Config, SharedStorage, _)> = vec![];
+    let tenants_service = if config.tenants.is_empty() {
+        games.push((
+            DEFAULT_TENANT.to_string(),
+            config.clone(),
+            monitoring_service.storage(),
+            earnings_receiver,
+        ));
+        None
+    } else {
+        let mut routes = vec![];
+        for tenant in config.tenants.iter() {
+            let (tenant_sender, tenant_receiver) = unbounded_channel::<LiquidationEarnings>();
+            let mut tenant_storage =
+                JsonStorage::new(&tenant_storage_path(&storage_path, &tenant.name));
+            tenant_storage.load().await?;
+            let tenant_storage: SharedStorage =
+                Arc::new(Mutex::new(Box::new(tenant_storage) as Box<dyn Storage>));
+            games.push((
+                tenant.name.clone(),
+                config.for_tenant(tenant)?,
+                tenant_storage,
+                tenant_receiver,
+            ));
+            routes.push((tenant.name.clone(), tenant.share_percentage, tenant_sender));
+        }
+        Some(TenantsService::new(earnings_receiver, routes))
+    };
+
+    let mut game_services = vec![];
+    let mut game_mirrors = vec![];
+    let mut tenant_storages = HashMap::new();
+    for (name, game_config, game_storage, game_earnings_receiver) in games {
+        let (pending_distribution, game_state) = {
+            let game_storage = game_storage.lock().await;
+            (
+                game_storage.get_pending_distribution(),
+                game_storage.get_game_state(),
+            )
+        };
+        let game_mirror = GameMirror::new(game_state);
+        let game_sync_service = GameSyncService::new(
+            game_config.clone(),
+            game_mirror.clone(),
+            game_storage.clone(),
+            http_client.clone(),
+        );
+        let distribution_service = DistributionService::new(
+            game_config,
+            rpc_client.clone(),
+            account.clone(),
+            game_earnings_receiver,
+            pending_distribution,
+            game_storage.clone(),
+            http_client.clone(),
+            notifier.clone(),
+            slo.clone(),
+            game_mirror.clone(),
+            if config.tenants.is_empty() {
+                feed.clone()
+            } else {
+                feed.for_tenant(&name)
+            },
+            latest_oracle_prices.clone(),
+            monitoring_service.tokens(),
+        )
+        .with_live_config(live_config.clone())
+        .with_leadership(leadership.clone())
+        .with_controls(controls.clone())
+        .with_fee_tokens(monitoring_service.fee_tokens());
+        game_services.push((name.clone(), game_sync_service, distribution_service));
+        game_mirrors.push(game_mirror);
+        tenant_storages.insert(name, game_storage);
+    }
+
+    let leader_election_service = config
+        .leader_election
+        .enabled
+        .then(|| LeaderElectionService::new(&config, leadership, notifier.clone()));
+    let control_service = config.grpc.enabled.then(|| {
+        ControlService::new(
+            config.clone(),
+            controls,
+            live_config.clone(),
+            monitoring_service.positions(),
+            feed.clone(),
+            notifier.clone(),
+        )
+    });
+    let finality_service = config.finality.enabled.then(|| {
+        FinalityService::new(
+            config.clone(),
+            rpc_client.clone(),
+            monitoring_service.storage(),
+            tenant_storages.values().cloned().collect(),
+        )
+    });
+    // The sweeps of the operators are recorded in the main storage, which is
+    // the storage of the game without tenants.
+    let mut ledger_storages: Vec<SharedStorage> = tenant_storages.values().cloned().collect();
+    if !config.tenants.is_empty() {
+        ledger_storages.push(monitoring_service.storage());
+    }
+    let reconciliation_service = ReconciliationService::new(
+        config.clone(),
         rpc_client.clone(),
-        latest_oracle_prices.clone(),
+        account.account_address(),
+        ledger_storages,
+        notifier.clone(),
     );
-    let monitoring_service = MonitoringService::new(
-        config,
+    let downtime_service = DowntimeService::new(
+        config.clone(),
         rpc_client,
-        account,
-        position_receiver,
+        monitoring_service.storage(),
+        game_mirrors.swap_remove(0),
+        notifier,
+        last_block_indexed,
+    );
+    let api_service = ApiService::new(
+        config,
+        monitoring_service.storage(),
+        tenant_storages,
+        monitoring_service.positions(),
         latest_oracle_prices,
-        Box::new(storage),
+        feed,
+        balances,
+        monitoring_service.fee_tokens(),
+        monitoring_service.health_factors(),
+        indexer_queue,
+        config_reloader,
+        operator,
     );
 
-    ServiceGroup::default()
-        .with(indexer_service)
-        .with(oracle_service)
-        .with(monitoring_service)
-        .start_and_drive_to_end()
+    let shutdown = Shutdown::default();
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            if let Err(e) = wait_for_os_signal().await {
+                tracing::error!("Could not listen for shutdown signals: {e}");
+                return;
+            }
+            tracing::info!("🛑 Shutdown requested, stopping services...");
+            shutdown.trigger();
+        }
+    });
+
+    let mut services = ServiceGroup::default();
+    if let Some(price_feed_service) = price_feed_service {
+        services = services.with_supervised("price feed", price_feed_service, &supervisor_config);
+    }
+    if let Some(fee_oracle_service) = fee_oracle_service {
+        services = services.with_supervised("fee oracle", fee_oracle_service, &supervisor_config);
+    }
+    if let Some(leader_election_service) = leader_election_service {
+        services = services.with_supervised(
+            "leader election",
+            leader_election_service,
+            &supervisor_config,
+        );
+    }
+    if let Some(control_service) = control_service {
+        services = services.with_supervised("control", control_service, &supervisor_config);
+    }
+    if let Some(finality_service) = finality_service {
+        services = services.with_supervised("finality", finality_service, &supervisor_config);
+    }
+    if let Some(tenants_service) = tenants_service {
+        services = services.with_supervised("tenants", tenants_service, &supervisor_config);
+    }
+    for (name, game_sync_service, distribution_service) in game_services {
+        services = services
+            .with_supervised(
+                format!("{name} game sync"),
+                game_sync_service,
+                &supervisor_config,
+            )
+            .with_supervised(
+                format!("{name} distribution"),
+                distribution_service,
+                &supervisor_config,
+            );
+    }
+    services
+        .with_supervised("indexer", indexer_service, &supervisor_config)
+        .with_supervised("oracle", oracle_service, &supervisor_config)
+        .with_supervised("monitoring", monitoring_service, &supervisor_config)
+        .with_supervised("reconciliation", reconciliation_service, &supervisor_config)
+        .with_supervised("downtime", downtime_service, &supervisor_config)
+        .with_supervised("notifier", notifier_service, &supervisor_config)
+        .with_supervised("api", api_service, &supervisor_config)
+        .with_supervised("config watcher", config_watcher_service, &supervisor_config)
+        .start_and_drive_to_end(shutdown)
         .await?;
 
+    tracing::info!("👋 All services stopped");
+
     Ok(())
 }
@@ -1,65 +1,162 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::{BinaryHeap, HashSet},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
 use anyhow::{Result, anyhow};
+use bigdecimal::BigDecimal;
+use dashmap::{DashMap, DashSet};
 use futures_util::lock::Mutex;
-use starknet::{core::{types::{BlockId, BlockTag, Event, FieldElement, FunctionCall}, utils::get_selector_from_name}, providers::{JsonRpcClient, jsonrpc::HttpTransport}};
+use starknet::{
+    core::types::{Call, Event, Felt},
+    providers::{JsonRpcClient, jsonrpc::HttpTransport},
+};
 use tokio::task::JoinSet;
 use tokio::{
-    sync::mpsc::UnboundedReceiver,
-    time::{interval, sleep},
+    sync::{
+        broadcast::error::RecvError,
+        mpsc::{Receiver, UnboundedSender},
+    },
+    time::{interval, sleep_until},
 };
+use tracing::Instrument;
 
-use crate::bindings::liquidate::Liquidate;
-use crate::types::StarknetSingleOwnerAccount;
 use crate::{
-    config::Config,
-    services::oracle::LatestOraclePrices,
-    storages::Storage,
+    config::{Config, FlashLoanSource, LiveConfig, PositionUpdateConfig},
+    protocols::{LiquidationOutcome, Protocols},
+    services::{
+        control::Controls, fees::FeeOracle, leader::Leadership, notifier::Notifier,
+        oracle::LatestOraclePrices, tokens::TokenRegistry,
+    },
+    storages::{SharedStorage, Storage},
     types::{
-        account::StarknetAccount,
-        position::{Position, PositionsMap},
+        account::{AccountPool, StarknetAccount},
+        accounting::{LiquidationPnl, Valuator, ValuedAmount},
+        balance::BotBalances,
+        competition::{CompetitorLiquidation, ObservedLiquidation},
+        distribution::LiquidationEarnings,
+        feed::{EventFeed, FeedEvent},
+        history::{LiquidationRecord, LiquidationStatus},
+        indexer::{IndexedBlock, IndexedBlocks, IndexerEvent},
+        ledger::Finality,
+        notification::Severity,
+        position::{
+            DeadLetterPosition, FailedLiquidation, FailedLiquidationStatus, HealthFactors,
+            LiquidationCandidate, Position, PositionKey, PositionsMap, PruningState,
+        },
+    },
+    utils::{
+        allowances::AllowanceManager,
+        conversions::big_decimal_to_u256,
+        errors::{LiquidatorError, is_retryable},
+        fee_tokens::FeeTokens,
+        http::HttpClient,
+        paymaster::PaymasterClient,
+        price_impact::estimate_liquidation_impact,
+        profile::{TickPhase, TickProfiler},
+        rpc::{BlockRpc, StarknetRpc},
+        services::Service,
+        shutdown::Shutdown,
+        slo::SloTracker,
+        swap::{BPS_DENOMINATOR, SwapProvider, swap_providers},
+        tx_manager::{Simulation, TxManager},
+        unix_now,
     },
-    utils::{services::Service, wait_for_tx},
 };
 
-/// Represents the structure of a Redeem model from Torii's GraphQL response.
-#[derive(serde::Deserialize, Debug)]
-struct RedeemModel {
-    player: String,
-    score: u128, // Assuming score fits in u128 for simplicity in Rust.
-}
-
-/// Represents the structure of a HighestScore model from Torii.
-#[derive(serde::Deserialize, Debug)]
-struct HighestScoreModel {
-    id: u8,
-    score: u128, // Assuming score fits in u128.
+/// What to do with a position after an attempt to liquidate it.
+#[derive(Debug, PartialEq)]
+enum AfterLiquidation {
+    /// Refreshed from the chain, to see what is left of it.
+    Refresh,
+    /// Left as is until its next check.
+    Skip,
+    /// No longer monitored, it was not undercollateralized.
+    Forget,
 }
 
 #[derive(Clone)]
 pub struct MonitoringService {
-    liquidate_contract: Arc<Liquidate<StarknetSingleOwnerAccount>>,
     config: Config,
+    /// The reloadable fields of the config, see [`LiveConfig`].
+    live: LiveConfig,
+    protocols: Protocols,
     rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+    /// Reads of the positions & blocks, mocked in the tests.
+    rpc: Arc<dyn StarknetRpc>,
     account: Arc<StarknetAccount>,
-    positions_receiver: Arc<Mutex<UnboundedReceiver<(u64, Position)>>>,
+    tx_manager: TxManager,
+    indexer_receiver: Arc<Mutex<Receiver<IndexerEvent>>>,
     // This map is kept to manage ongoing liquidations or complex state if needed in the future.
     positions: PositionsMap,
     latest_oracle_prices: LatestOraclePrices,
-    storage: Arc<Mutex<Box<dyn Storage>>>,
-    http_client: reqwest::Client,
+    storage: SharedStorage,
+    last_block_indexed: Arc<AtomicU64>,
+    /// The last indexed blocks, rolled back on reorgs.
+    indexed_blocks: Arc<Mutex<IndexedBlocks>>,
+    /// Positions first seen in the block being indexed.
+    new_positions: Arc<DashSet<PositionKey>>,
+    /// Closed positions waiting to be pruned.
+    pruning: Arc<Mutex<PruningState>>,
+    earnings_sender: UnboundedSender<LiquidationEarnings>,
+    notifier: Notifier,
+    slo: SloTracker,
+    feed: EventFeed,
+    /// When each liquidable position was first seen liquidable.
+    liquidable_since: Arc<DashMap<PositionKey, Instant>>,
+    /// Positions from the indexer that could not be updated, retried on each round.
+    dead_letters: Arc<DashMap<PositionKey, DeadLetterPosition>>,
+    /// Positions with a liquidation tx pending, never submitted twice.
+    liquidating: Arc<DashSet<PositionKey>>,
+    http_client: HttpClient,
+    /// Quote the collateral seized by the large liquidations, see
+    /// [`crate::config::PriceImpactConfig`].
+    swap_providers: Arc<Vec<Box<dyn SwapProvider>>>,
+    allowances: AllowanceManager,
+    /// Health factors of the positions at their last check.
+    health_factors: HealthFactors,
+    /// Positions reported at risk, not reported again until they recover.
+    at_risk: Arc<DashSet<PositionKey>>,
+    /// Liquidations that failed for a transient reason, retried with a backoff.
+    failed_liquidations: Arc<DashMap<PositionKey, FailedLiquidation>>,
+    /// Transactions of the liquidations already accounted for, by the bot or
+    /// by a competitor.
+    seen_liquidations: Arc<DashSet<Felt>>,
+    tokens: TokenRegistry,
+    valuator: Valuator,
+    profiler: TickProfiler,
+    fees: FeeOracle,
+    leadership: Leadership,
+    controls: Controls,
 }
 
 #[async_trait::async_trait]
 impl Service for MonitoringService {
-    async fn start(&mut self, join_set: &mut JoinSet<anyhow::Result<()>>) -> anyhow::Result<()> {
+    async fn start(
+        &mut self,
+        join_set: &mut JoinSet<anyhow::Result<()>>,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<()> {
         let service = self.clone();
-        // We wait a few seconds before starting the monitoring service to be sure that we have prices
-        // + indexed a few positions.
-        sleep(Duration::from_secs(4)).await;
+        let timeout = Duration::from_secs(self.config.monitoring.prices_ready_timeout_seconds);
+        if !self.latest_oracle_prices.wait_until_ready(timeout).await {
+            tracing::warn!(
+                "[🔭 Monitoring] No price for {} after {:?}, starting without them",
+                self.latest_oracle_prices.missing().join(", "),
+                timeout
+            );
+        }
+        self.allowances.check_all().await;
+        if self.config.monitoring.reconcile_on_startup {
+            self.reconcile_stored_positions().await?;
+        }
         join_set.spawn(async move {
             tracing::info!("🔭 Monitoring service started");
-            service.run_forever().await?;
+            service.run_forever(shutdown).await?;
             Ok(())
         });
         Ok(())
@@ -70,53 +167,316 @@ impl MonitoringService {
     pub fn new(
         config: Config,
         rpc_client: Arc<JsonRpcClient<HttpTransport>>,
-        account: StarknetAccount,
-        positions_receiver: UnboundedReceiver<(u64, Position)>,
+        http_client: HttpClient,
+        accounts: AccountPool,
+        indexer_receiver: Receiver<IndexerEvent>,
         latest_oracle_prices: LatestOraclePrices,
         storage: Box<dyn Storage>,
+        earnings_sender: UnboundedSender<LiquidationEarnings>,
+        notifier: Notifier,
+        slo: SloTracker,
+        feed: EventFeed,
+        balances: BotBalances,
     ) -> MonitoringService {
+        let account = accounts.primary().clone();
+        let tx_manager =
+            TxManager::with_accounts(accounts, rpc_client.clone(), config.transactions.clone())
+                .with_paymaster(PaymasterClient::from_config(&config))
+                .with_fee_tokens(FeeTokens::from_config(&config, balances.clone()));
+        let allowances = AllowanceManager::new(
+            config.clone(),
+            rpc_client.clone(),
+            account.account_address(),
+            config.liquidate_address,
+        );
+        let token_metadata = storage.get_token_metadata();
+        let positions = PositionsMap::from_storage(storage.as_ref());
+        let dead_letters = storage
+            .get_dead_letter_positions()
+            .into_iter()
+            .map(|dead_letter| (dead_letter.position.key(), dead_letter))
+            .collect();
+        let last_block_indexed = storage.get_last_block_indexed();
+        let indexed_blocks = storage.get_indexed_blocks();
+        let pruning = storage.get_pruning_state();
+        let failed_liquidations = storage
+            .get_failed_liquidations()
+            .into_iter()
+            .map(|failed| (failed.key, failed))
+            .collect();
+        let seen_liquidations = storage
+            .get_liquidation_history()
+            .into_iter()
+            .filter_map(|record| record.tx_hash)
+            .chain(
+                storage
+                    .get_competitor_liquidations()
+                    .into_iter()
+                    .map(|liquidation| liquidation.tx_hash),
+            )
+            .collect();
+        let storage: SharedStorage = Arc::new(Mutex::new(storage));
+        let tokens = TokenRegistry::new(config.clone(), rpc_client.clone(), token_metadata)
+            .with_storage(storage.clone());
+        let valuator = Valuator::new(config.clone(), latest_oracle_prices.clone(), tokens.clone());
+        let swap_providers = Arc::new(swap_providers(&config, &http_client));
         MonitoringService {
-            liquidate_contract: Arc::new(Liquidate::new(
-                config.liquidate_address,
-                account.0.clone(),
-            )),
+            protocols: Protocols::from_config(&config),
+            live: LiveConfig::new(config.clone()),
             config,
+            rpc: Arc::new(BlockRpc::new(
+                rpc_client.clone(),
+                config.monitoring.positions_block_id(),
+            )),
             rpc_client,
+            tx_manager,
             account: Arc::new(account),
-            positions_receiver: Arc::new(Mutex::new(positions_receiver)),
-            positions: PositionsMap::from_storage(storage.as_ref()),
+            indexer_receiver: Arc::new(Mutex::new(indexer_receiver)),
+            positions,
+            dead_letters: Arc::new(dead_letters),
             latest_oracle_prices,
-            storage: Arc::new(Mutex::new(storage)),
-            http_client: reqwest::Client::new(),
+            last_block_indexed: Arc::new(AtomicU64::new(last_block_indexed)),
+            indexed_blocks: Arc::new(Mutex::new(indexed_blocks)),
+            new_positions: Arc::new(DashSet::new()),
+            pruning: Arc::new(Mutex::new(pruning)),
+            failed_liquidations: Arc::new(failed_liquidations),
+            seen_liquidations: Arc::new(seen_liquidations),
+            storage,
+            earnings_sender,
+            notifier,
+            slo,
+            feed,
+            liquidable_since: Arc::new(DashMap::new()),
+            liquidating: Arc::new(DashSet::new()),
+            http_client,
+            swap_providers,
+            allowances,
+            health_factors: HealthFactors::default(),
+            at_risk: Arc::new(DashSet::new()),
+            tokens,
+            valuator,
+            profiler: TickProfiler::default(),
+            fees: FeeOracle::default(),
+            leadership: Leadership::default(),
+            controls: Controls::default(),
         }
     }
 
+    /// Records the timings of the checks, see [`TickProfiler`].
+    pub fn with_profiler(mut self, profiler: TickProfiler) -> Self {
+        self.profiler = profiler;
+        self
+    }
+
+    /// Holds back the non-urgent liquidations during the fee spikes, see
+    /// [`FeeOracle`].
+    pub fn with_fee_oracle(mut self, fees: FeeOracle) -> Self {
+        self.fees = fees;
+        self
+    }
+
+    /// Applies the reloads of the config, see [`LiveConfig`].
+    pub fn with_live_config(mut self, live: LiveConfig) -> Self {
+        self.live = live;
+        self
+    }
+
+    /// Only liquidates while the instance leads, a follower keeping its
+    /// positions up to date to take over, see [`LeaderElectionService`].
+    ///
+    /// [`LeaderElectionService`]: crate::services::leader::LeaderElectionService
+    pub fn with_leadership(mut self, leadership: Leadership) -> Self {
+        self.tx_manager = self.tx_manager.with_leadership(leadership.clone());
+        self.leadership = leadership;
+        self
+    }
+
+    /// Pauses the liquidations & scans the positions on the requests of the
+    /// control plane, see [`ControlService`], the transactions being also
+    /// halted by the operators.
+    ///
+    /// [`ControlService`]: crate::services::control::ControlService
+    pub fn with_controls(mut self, controls: Controls) -> Self {
+        self.tx_manager = self.tx_manager.with_controls(controls.clone());
+        self.controls = controls;
+        self
+    }
+
+    /// Returns the storage used by the service, so it can be shared.
+    pub fn storage(&self) -> SharedStorage {
+        self.storage.clone()
+    }
+
+    /// Returns the selection of the fee token of the service, so it can be
+    /// shared.
+    pub fn fee_tokens(&self) -> FeeTokens {
+        self.tx_manager.fee_tokens().clone()
+    }
+
+    /// Returns the token registry of the service, so it can be shared.
+    pub fn tokens(&self) -> TokenRegistry {
+        self.tokens.clone()
+    }
+
+    /// Returns the positions monitored by the service, so they can be shared.
+    pub fn positions(&self) -> PositionsMap {
+        self.positions.clone()
+    }
+
+    /// Returns the health factors of the positions, so they can be shared.
+    pub fn health_factors(&self) -> HealthFactors {
+        self.health_factors.clone()
+    }
+
     /// Starts the monitoring service.
-    pub async fn run_forever(&self) -> Result<()> {
-        const CHECK_POSITIONS_INTERVAL: u64 = 3500;
-        let mut update_interval = interval(Duration::from_millis(CHECK_POSITIONS_INTERVAL));
+    /// Positions are checked when the price of one of their assets moves, the
+    /// shards of the moved assets at each check & all of them at every full
+    /// sweep. Closed positions are pruned periodically too.
+    /// Any in-flight liquidation is completed before the shutdown is handled.
+    /// The intervals are reset when the config is reloaded.
+    pub async fn run_forever(&self, shutdown: Shutdown) -> Result<()> {
+        let mut config_changes = self.live.subscribe();
+        let config = config_changes.borrow_and_update().clone();
+        let mut check_interval = AdaptiveInterval::from_config(&config);
+        let mut next_full_check = tokio::time::Instant::now();
+        let mut price_moves_seen = 0;
+        // Assets whose price moved since the last check: between the full
+        // sweeps, only the positions of their shards are checked.
+        let mut moved_assets: HashSet<String> = HashSet::new();
+        let mut full_sweep_interval =
+            Duration::from_secs(config.monitoring.full_sweep_interval_seconds);
+        let mut next_full_sweep = tokio::time::Instant::now();
+        let mut prune_interval = interval(Duration::from_secs(
+            config.monitoring.prune_interval_seconds,
+        ));
+        let mut watchlist_interval = interval(Duration::from_secs(
+            config.monitoring.watchlist_check_interval_seconds,
+        ));
+        let mut accounts_interval = interval(Duration::from_secs(
+            self.config.transactions.account_refresh_seconds,
+        ));
+        let mut price_moves = self.latest_oracle_prices.subscribe_moves();
 
         loop {
-            let mut receiver = self.positions_receiver.lock().await;
+            let mut receiver = self.indexer_receiver.lock().await;
 
             tokio::select! {
-                _ = update_interval.tick() => {
+                _ = shutdown.wait() => {
+                    drop(receiver);
+                    self.flush_state().await?;
+                    tracing::info!("[🔭 Monitoring] 🛑 Stopped monitoring, state flushed to storage");
+                    return Ok(());
+                }
+
+                _ = sleep_until(next_full_check) => {
                     drop(receiver);
+                    let started_at = Instant::now();
+                    if tokio::time::Instant::now() >= next_full_sweep {
+                        self.monitor_positions_liquidability().await?;
+                        next_full_sweep = tokio::time::Instant::now() + full_sweep_interval;
+                        self.profiler.finish("full sweep", started_at);
+                    } else {
+                        self.check_moved_assets(&moved_assets).await?;
+                        self.profiler.finish("moved assets", started_at);
+                    }
+                    moved_assets.clear();
+                    let near_liquidation = self.count_near_liquidation().await;
+                    let delay = check_interval.next(
+                        price_moves_seen > 0
+                            || near_liquidation
+                                >= self.live.current().monitoring.near_liquidation_positions,
+                    );
+                    tracing::debug!(
+                        "[🔭 Monitoring] {} price move(s) & {} position(s) near liquidation, next full check in {:?}",
+                        price_moves_seen,
+                        near_liquidation,
+                        delay
+                    );
+                    price_moves_seen = 0;
+                    next_full_check = tokio::time::Instant::now() + delay;
+                }
+
+                _ = self.controls.scan_requested() => {
+                    drop(receiver);
+                    let started_at = Instant::now();
                     self.monitor_positions_liquidability().await?;
+                    next_full_sweep = tokio::time::Instant::now() + full_sweep_interval;
+                    self.profiler.finish("requested scan", started_at);
+                }
+
+                _ = prune_interval.tick() => {
+                    drop(receiver);
+                    self.prune_closed_positions().await?;
+                }
+
+                _ = config_changes.changed() => {
+                    drop(receiver);
+                    let config = config_changes.borrow_and_update().clone();
+                    check_interval = AdaptiveInterval::from_config(&config);
+                    next_full_check = next_full_check
+                        .min(tokio::time::Instant::now() + check_interval.current);
+                    full_sweep_interval =
+                        Duration::from_secs(config.monitoring.full_sweep_interval_seconds);
+                    prune_interval = interval(Duration::from_secs(
+                        config.monitoring.prune_interval_seconds,
+                    ));
+                    watchlist_interval = interval(Duration::from_secs(
+                        config.monitoring.watchlist_check_interval_seconds,
+                    ));
+                    self.fees.reload(&config.gas_guard);
+                    tracing::info!("[🔭 Monitoring] Config reloaded, intervals reset");
+                }
+
+                _ = watchlist_interval.tick() => {
+                    drop(receiver);
+                    let started_at = Instant::now();
+                    self.check_watchlist().await?;
+                    self.retry_failed_liquidations().await?;
+                    self.profiler.finish("watchlist", started_at);
                 }
 
-                maybe_position = receiver.recv() => {
+                _ = accounts_interval.tick() => {
                     drop(receiver);
-                    match maybe_position {
-                        Some((block_number, mut new_position)) => {
-                            new_position
-                                .update(&self.rpc_client, &self.config.singleton_address)
+                    self.refresh_accounts().await;
+                }
+
+                price_move = price_moves.recv() => {
+                    drop(receiver);
+                    price_moves_seen += 1;
+                    let started_at = Instant::now();
+                    match price_move {
+                        Ok(asset) => {
+                            self.check_positions_with_asset(&asset).await?;
+                            moved_assets.insert(asset);
+                        }
+                        Err(RecvError::Lagged(_)) => {
+                            // The moves missed are unknown: all the shards
+                            // are swept at the next check.
+                            self.monitor_positions_liquidability().await?;
+                            next_full_sweep = tokio::time::Instant::now();
+                        }
+                        Err(RecvError::Closed) => {
+                            return Err(anyhow!("Price moves stopped unexpectedly"));
+                        }
+                    }
+                    self.profiler.finish("price move", started_at);
+                }
+
+                maybe_event = receiver.recv() => {
+                    drop(receiver);
+                    match maybe_event {
+                        Some(IndexerEvent::Position(block_number, new_position)) => {
+                            self.ingest_position(block_number, new_position).await?;
+                        }
+                        Some(IndexerEvent::Liquidation(block_number, liquidation)) => {
+                            self.record_observed_liquidation(block_number, liquidation)
                                 .await?;
-                            if new_position.is_closed() {
-                                continue;
-                            }
-                            self.positions.0.insert(new_position.key(), new_position);
-                            self.storage.lock().await.save(&self.positions.0, block_number).await?;
+                        }
+                        Some(IndexerEvent::Block { number, hash }) => {
+                            self.record_block(number, hash).await?;
+                        }
+                        Some(IndexerEvent::Reorg { last_valid_block }) => {
+                            self.rollback(last_valid_block).await?;
                         }
                         None => {
                             return Err(anyhow!("Monitoring stopped unexpectedly"));
@@ -127,261 +487,1869 @@ impl MonitoringService {
         }
     }
 
-    /// Update all monitored positions and check if it's worth to liquidate any.
-    async fn monitor_positions_liquidability(&self) -> Result<()> {
-        if self.positions.0.is_empty() {
+    /// Updates a position received from the indexer & starts monitoring it.
+    /// A position that can't be updated is moved to the dead-letter list
+    /// instead of stopping the service, & is no longer monitored until it
+    /// recovers, see [`Self::retry_dead_letters`].
+    async fn ingest_position(&self, block_number: u64, mut position: Position) -> Result<()> {
+        let key = position.key();
+        if !self.live.current().position_filter.accepts(&key) {
             return Ok(());
         }
+        if !self.positions.by_key.contains_key(&key) && !self.dead_letters.contains_key(&key) {
+            self.new_positions.insert(key);
+        }
+        let update = position
+            .update(
+                self.rpc.as_ref(),
+                &self.protocols,
+                &self.config.position_update,
+            )
+            .await;
 
-        let position_keys: Vec<u64> = self.positions.0.iter().map(|entry| *entry.key()).collect();
-        let mut positions_to_delete = vec![];
+        match update {
+            Ok(()) => {
+                self.dead_letters.remove(&key);
+                if position.is_closed() {
+                    // Closed positions are only kept if they were monitored.
+                    if self.positions.by_key.contains_key(&key) {
+                        self.positions.insert(position);
+                        self.mark_closed(key).await;
+                    }
+                } else {
+                    self.pruning.lock().await.closed_since.remove(&key);
+                    self.positions.insert(position);
+                    self.check_positions(vec![key]).await?;
+                }
+            }
+            Err(e) => {
+                tracing::error!(
+                    error = %e,
+                    position_key = ?key,
+                    pool_id = format!("{:#x}", key.pool_id),
+                    "[🔭 Monitoring] 😨 Could not update position #{}, moved to the dead-letter list",
+                    key
+                );
+                self.notifier.notify(
+                    Severity::Warning,
+                    format!("Could not update position #{key}, it will be retried: {e}"),
+                );
+                // Its last known state is stale, so it is not liquidated
+                // until its update is retried successfully.
+                self.positions.remove(&key);
+                self.liquidable_since.remove(&key);
+                self.health_factors.remove(&key);
+                self.dead_letters.insert(
+                    key,
+                    DeadLetterPosition {
+                        block_number,
+                        position,
+                        error: e.to_string(),
+                        failed_at: unix_now(),
+                    },
+                );
+            }
+        }
+        self.flush_state().await
+    }
 
-        for key in position_keys {
-            if let Some(mut entry) = self.positions.0.get_mut(&key) {
-                let position = entry.value_mut();
+    /// Records a liquidation of a tracked position seen by the indexer. The
+    /// ones not sent by the bot are lost to a competitor: they are stored for
+    /// the win rate, see [`crate::types::competition::CompetitionStats`], &
+    /// the position is no longer considered liquidable until refreshed.
+    async fn record_observed_liquidation(
+        &self,
+        block_number: u64,
+        liquidation: ObservedLiquidation,
+    ) -> Result<()> {
+        let key = liquidation.key;
+        if !self.positions.by_key.contains_key(&key)
+            || !self.seen_liquidations.insert(liquidation.tx_hash)
+        {
+            return Ok(());
+        }
+        let competitor = match self.rpc.transaction_sender(liquidation.tx_hash).await {
+            Ok(sender) => sender.unwrap_or(liquidation.liquidator),
+            Err(e) => {
+                tracing::warn!(
+                    error = %e,
+                    tx_hash = format!("{:#x}", liquidation.tx_hash),
+                    "[🔭 Monitoring] Could not read the sender of liquidation {:#x}",
+                    liquidation.tx_hash
+                );
+                liquidation.liquidator
+            }
+        };
+        if self.tx_manager.accounts().contains(competitor) {
+            return Ok(());
+        }
+        tracing::warn!(
+            position_key = ?key,
+            competitor = format!("{:#x}", competitor),
+            tx_hash = format!("{:#x}", liquidation.tx_hash),
+            "[🔭 Monitoring] 🥈 Position #{} was liquidated by competitor {:#x}",
+            key,
+            competitor
+        );
+        self.liquidable_since.remove(&key);
+        self.clear_failed_liquidation(&key).await?;
+        self.storage
+            .lock()
+            .await
+            .save_competitor_liquidation(&CompetitorLiquidation {
+                key,
+                tx_hash: liquidation.tx_hash,
+                block_number,
+                competitor,
+                seen_at: unix_now(),
+            })
+            .await
+    }
 
-                if !position.is_liquidable(&self.latest_oracle_prices).await? {
-                    continue;
-                }
-                tracing::info!(
-                    "[🔭 Monitoring] Liquidatable position found #{}!",
-                    position.key()
+    /// Records a block fully processed by the indexer, the indexing resuming
+    /// from the last one after a restart.
+    async fn record_block(&self, number: u64, hash: Felt) -> Result<()> {
+        let new_positions: Vec<PositionKey> = self.new_positions.iter().map(|key| *key).collect();
+        self.new_positions.clear();
+        self.indexed_blocks.lock().await.record(IndexedBlock {
+            number,
+            hash,
+            new_positions,
+        });
+        self.last_block_indexed.fetch_max(number, Ordering::Relaxed);
+        self.flush_state().await
+    }
+
+    /// Stops monitoring the positions first seen in the blocks after
+    /// `last_valid_block`, orphaned by a reorg. The indexer then re-sends the
+    /// positions still on the canonical chain.
+    async fn rollback(&self, last_valid_block: u64) -> Result<()> {
+        let mut orphaned = self.indexed_blocks.lock().await.rollback(last_valid_block);
+        orphaned.extend(self.new_positions.iter().map(|key| *key));
+        self.new_positions.clear();
+        for key in orphaned.iter() {
+            self.positions.remove(key);
+            self.dead_letters.remove(key);
+            self.liquidable_since.remove(key);
+        }
+        self.last_block_indexed
+            .fetch_min(last_valid_block, Ordering::Relaxed);
+        tracing::warn!(
+            "[🔭 Monitoring] ⛓️ Rolled back to block {}, {} orphaned position(s) dropped",
+            last_valid_block,
+            orphaned.len()
+        );
+        self.flush_state().await
+    }
+
+    /// Compares the last indexed blocks with the chain & rolls back the ones
+    /// orphaned while the bot was offline. Returns the block to resume the
+    /// indexing from.
+    pub async fn rollback_orphaned_blocks(&self) -> Result<u64> {
+        let indexed_blocks = self.indexed_blocks.lock().await.clone();
+        let mut last_valid_block = None;
+        let mut orphaned_blocks = 0;
+        for (number, hash) in indexed_blocks.accepted() {
+            if self.rpc.block_hash(number).await? == Some(hash) {
+                last_valid_block = Some(number);
+                break;
+            }
+            orphaned_blocks += 1;
+        }
+
+        let last_valid_block = match last_valid_block {
+            Some(number) => number,
+            // All the indexed blocks were orphaned, the reorg is deeper than
+            // what is kept: resume before the oldest one.
+            None => match indexed_blocks.oldest() {
+                Some(oldest) => oldest.saturating_sub(1),
+                None => return Ok(self.last_block_indexed.load(Ordering::Relaxed)),
+            },
+        };
+        // Pending blocks are rolled back too, to be indexed again.
+        if indexed_blocks.latest() > Some(last_valid_block) {
+            if orphaned_blocks > 0 {
+                self.notifier.notify(
+                    Severity::Warning,
+                    format!(
+                        "{orphaned_blocks} indexed block(s) orphaned while offline, rolled back to block {last_valid_block}"
+                    ),
                 );
+            }
+            self.rollback(last_valid_block).await?;
+        }
+        Ok(self.last_block_indexed.load(Ordering::Relaxed))
+    }
 
-                tracing::info!("[🔭 Monitoring] 🔫 Liquidating position...");
-                if let Err(e) = self.liquidate_position(position).await {
-                    if e.to_string().contains("not-undercollateralized") {
-                        tracing::warn!("[🔭 Monitoring] Position was not under collateralized!");
-                        positions_to_delete.push(key);
-                        continue;
-                    } else {
-                        tracing::error!(
-                            error = %e,
-                            "[🔭 Monitoring] 😨 Could not liquidate position #{:x}",
-                            position.key(),
-                        );
+    /// Retries once the update of each dead-letter position, the ones
+    /// succeeding are monitored again.
+    async fn retry_dead_letters(&self) -> Result<()> {
+        let keys: Vec<PositionKey> = self.dead_letters.iter().map(|entry| *entry.key()).collect();
+        let single_attempt = PositionUpdateConfig {
+            max_attempts: 1,
+            ..self.config.position_update.clone()
+        };
+        let mut recovered = false;
+
+        for key in keys {
+            let Some(mut position) = self
+                .dead_letters
+                .get(&key)
+                .map(|entry| entry.position.clone())
+            else {
+                continue;
+            };
+            match position
+                .update(self.rpc.as_ref(), &self.protocols, &single_attempt)
+                .await
+            {
+                Ok(()) => {
+                    tracing::info!(
+                        position_key = ?key,
+                        "[🔭 Monitoring] Position #{} recovered from the dead-letter list",
+                        key
+                    );
+                    self.dead_letters.remove(&key);
+                    if !position.is_closed() {
+                        self.positions.insert(position);
+                    }
+                    recovered = true;
+                }
+                Err(e) => {
+                    if let Some(mut dead_letter) = self.dead_letters.get_mut(&key) {
+                        dead_letter.error = e.to_string();
+                        dead_letter.failed_at = unix_now();
                     }
                 }
-
-                position
-                    .update(&self.rpc_client, &self.config.singleton_address)
-                    .await?;
             }
         }
 
-        for to_delete in positions_to_delete {
-            self.positions.0.remove(&to_delete);
+        if recovered {
+            self.flush_state().await?;
         }
-
         Ok(())
     }
 
-    /// Queries the Torii GraphQL endpoint for Redeem models within a block range.
-    async fn find_next_player_in_queue(&self) -> Result<Option<RedeemModel>> {
-        let query = format!(
-            r#"
-            query {{
-                redeemModels(first: 1) {{
-                    edges {{
-                        node {{
-                            player, score
-                        }}
-                    }}
-                }}
-            }}
-            "#
+    /// Records the block at which a position was first seen closed, from
+    /// which it is pruned after `monitoring.prune_closed_after_blocks` blocks.
+    async fn mark_closed(&self, key: PositionKey) {
+        let block = self.last_block_indexed.load(Ordering::Relaxed);
+        self.pruning
+            .lock()
+            .await
+            .closed_since
+            .entry(key)
+            .or_insert(block);
+    }
+
+    /// Refreshes every stored position against the singleton before the
+    /// monitoring begins: the positions closed while the bot was down, e.g.
+    /// liquidated by a competitor, are removed & the changed ones logged. The
+    /// batches that can't be fetched are left to the next checks.
+    async fn reconcile_stored_positions(&self) -> Result<()> {
+        let stored: Vec<Position> = self
+            .positions
+            .by_key
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        if stored.is_empty() {
+            return Ok(());
+        }
+        tracing::info!(
+            "[🔭 Monitoring] Reconciling {} stored position(s) with the chain...",
+            stored.len()
         );
 
-        let response: serde_json::Value = self
-            .http_client
-            .post(&self.config.torii_graphql_url)
-            .json(&serde_json::json!({ "query": query }))
-            .send()
-            .await?
-            .json()
-            .await?;
+        let (mut closed, mut changed, mut failed) = (0, 0, 0);
+        let batch_size = self.config.position_update.batch_size.max(1);
+        for batch in stored.chunks(batch_size) {
+            let mut refreshed = batch.to_vec();
+            if let Err(e) = Position::update_batch(
+                &mut refreshed,
+                self.rpc.as_ref(),
+                &self.protocols,
+                batch_size,
+            )
+            .await
+            {
+                tracing::warn!(
+                    error = %e,
+                    "[🔭 Monitoring] Could not reconcile {} stored position(s)",
+                    batch.len()
+                );
+                failed += batch.len();
+                continue;
+            }
 
-        let models: Vec<RedeemModel> = serde_json::from_value(
-            response["data"]["redeemModels"]["edges"]
-                .as_array()
-                .ok_or_else(|| anyhow!("Invalid GraphQL response format"))?
-                .iter()
-                .map(|edge| edge["node"].clone())
-                .collect::<serde_json::Value>(),
-        )?;
-
-        Ok(models.into_iter().next())
-    }
-
-    /// Queries Torii for the global highest score.
-    async fn get_highest_score(&self) -> Result<Option<u128>> {
-        let query = r#"
-            query {
-                highestScoreModels(first: 1) {
-                    edges {
-                        node {
-                            id, score
-                        }
-                    }
+            for (before, after) in batch.iter().zip(refreshed) {
+                let key = after.key();
+                if after.is_closed() {
+                    tracing::info!(
+                        position_key = ?key,
+                        "[🔭 Monitoring] Position #{} was closed while the bot was down, removing it",
+                        key
+                    );
+                    self.positions.remove(&key);
+                    self.liquidable_since.remove(&key);
+                    self.health_factors.remove(&key);
+                    self.pruning.lock().await.closed_since.remove(&key);
+                    closed += 1;
+                    continue;
+                }
+                if before.collateral.amount != after.collateral.amount
+                    || before.debt.amount != after.debt.amount
+                {
+                    tracing::info!(
+                        position_key = ?key,
+                        "[🔭 Monitoring] Position #{} changed while the bot was down: {} {} of collateral & {} {} of debt, was {} & {}",
+                        key,
+                        after.collateral.amount,
+                        after.collateral.name,
+                        after.debt.amount,
+                        after.debt.name,
+                        before.collateral.amount,
+                        before.debt.amount
+                    );
+                    changed += 1;
+                }
+                if let Some(mut entry) = self.positions.by_key.get_mut(&key) {
+                    *entry = after;
                 }
             }
-        "#;
+        }
 
-        let response: serde_json::Value = self
-            .http_client
-            .post(&self.config.torii_graphql_url)
-            .json(&serde_json::json!({ "query": query }))
-            .send()
-            .await?
-            .json()
+        tracing::info!(
+            "[🔭 Monitoring] Reconciled {} stored position(s): {} closed & removed, {} changed, {} not reachable",
+            stored.len(),
+            closed,
+            changed,
+            failed
+        );
+        self.flush_state().await
+    }
+
+    /// Removes the positions closed for more than
+    /// `monitoring.prune_closed_after_blocks` blocks & compacts the stored
+    /// snapshot.
+    async fn prune_closed_positions(&self) -> Result<()> {
+        let current_block = self.last_block_indexed.load(Ordering::Relaxed);
+        let pruned = {
+            let mut pruning = self.pruning.lock().await;
+            // Reopened or removed positions are not waiting to be pruned anymore.
+            pruning.closed_since.retain(|key, _| {
+                self.positions
+                    .by_key
+                    .get(key)
+                    .is_some_and(|position| position.is_closed())
+            });
+            pruning.take_prunable(
+                current_block,
+                self.config.monitoring.prune_closed_after_blocks,
+            )
+        };
+        for key in pruned.iter() {
+            self.positions.remove(key);
+            self.liquidable_since.remove(key);
+            self.health_factors.remove(key);
+        }
+        if !pruned.is_empty() {
+            tracing::info!(
+                "[🔭 Monitoring] 🧹 Pruned {} position(s) closed before block {}",
+                pruned.len(),
+                current_block.saturating_sub(self.config.monitoring.prune_closed_after_blocks)
+            );
+        }
+        self.flush_state().await
+    }
+
+    /// Persists the monitored positions, the dead-letter positions, the last
+    /// processed blocks & the closed positions.
+    async fn flush_state(&self) -> Result<()> {
+        let last_block_indexed = self.last_block_indexed.load(Ordering::Relaxed);
+        let indexed_blocks = self.indexed_blocks.lock().await.clone();
+        let pruning = self.pruning.lock().await.clone();
+        let dead_letters: Vec<DeadLetterPosition> = self
+            .dead_letters
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        let mut storage = self.storage.lock().await;
+        storage
+            .save(&self.positions.by_key, last_block_indexed)
             .await?;
+        storage.save_dead_letter_positions(&dead_letters).await?;
+        storage.save_indexed_blocks(&indexed_blocks).await?;
+        storage.save_pruning_state(&pruning).await
+    }
 
-        let models: Vec<HighestScoreModel> = serde_json::from_value(
-            response["data"]["highestScoreModels"]["edges"]
-                .as_array()
-                .ok_or_else(|| anyhow!("Invalid GraphQL response format for HighestScore"))?
-                .iter()
-                .map(|edge| edge["node"].clone())
-                .collect::<serde_json::Value>(),
-        )?;
+    /// Returns the number of monitored positions liquidable or almost.
+    async fn count_near_liquidation(&self) -> usize {
+        let positions: Vec<Position> = self
+            .positions
+            .by_key
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        let mut count = 0;
+        for position in positions.iter().filter(|p| !p.is_closed()) {
+            if position
+                .is_near_liquidation(&self.latest_oracle_prices)
+                .await
+            {
+                count += 1;
+            }
+        }
+        count
+    }
 
-        Ok(models.first().map(|m| m.score))
+    /// Update all monitored positions and check if it's worth to liquidate any.
+    async fn monitor_positions_liquidability(&self) -> Result<()> {
+        self.retry_dead_letters().await?;
+        let position_keys: Vec<PositionKey> = self
+            .positions
+            .by_key
+            .iter()
+            .map(|entry| *entry.key())
+            .collect();
+        self.refresh_positions(&position_keys).await;
+        self.check_positions(position_keys).await
     }
 
-    /// Transfers a given amount of an ERC20 token to a recipient.
-    fn build_erc20_transfer_call(&self, token_address: FieldElement, recipient: FieldElement, amount: U256) -> Result<FunctionCall> {
-        Ok(FunctionCall {
-            contract_address: token_address,
-            entry_point_selector: get_selector_from_name("transfer")?,
-            calldata: vec![recipient, amount.low.into(), amount.high.into()], // recipient, amount_low, amount_high
-        })
+    /// Refreshes & checks the positions in the shards of the assets whose
+    /// price moved since the last check, see [`PositionsMap::keys_with_assets`].
+    async fn check_moved_assets(&self, moved_assets: &HashSet<String>) -> Result<()> {
+        self.retry_dead_letters().await?;
+        if moved_assets.is_empty() {
+            return Ok(());
+        }
+        let position_keys = self
+            .positions
+            .keys_with_assets(moved_assets.iter().map(String::as_str));
+        tracing::debug!(
+            "[🔭 Monitoring] Checking {} position(s) of {} moved asset(s) out of {} shard(s)",
+            position_keys.len(),
+            moved_assets.len(),
+            self.positions.shards()
+        );
+        self.refresh_positions(&position_keys).await;
+        self.check_positions(position_keys).await
     }
 
-    /// Check if a position is liquidable, finds a recipient from the redeem queue,
-    /// and if it's worth it, liquidates it.
-    async fn liquidate_position(&self, position: &Position) -> Result<()> {
-        let started_at = std::time::Instant::now();
-        
-        // The liquidator bot's address will be the initial recipient of all earnings.
-        let bot_address = self.account.account_address();
+    /// Refreshes & checks the positions of the watchlist, the ones whose
+    /// health factor was within `monitoring.watchlist_margin_bps` of a
+    /// liquidation at their last check.
+    async fn check_watchlist(&self) -> Result<()> {
+        let margin = BigDecimal::from(self.live.current().monitoring.watchlist_margin_bps)
+            / BigDecimal::from(BPS_DENOMINATOR);
+        let watchlist = self.health_factors.watchlist(&margin);
+        if watchlist.is_empty() {
+            return Ok(());
+        }
+        tracing::debug!(
+            "[🔭 Monitoring] Checking {} position(s) of the watchlist",
+            watchlist.len()
+        );
+        self.refresh_positions(&watchlist).await;
+        self.check_positions(watchlist).await
+    }
 
-        let liquidation_tx = position
-            .get_vesu_liquidate_tx(&self.liquidate_contract, &self.http_client, &bot_address)
-            .await?;
-        
-        let tx_hash = self.account.execute_txs(&[liquidation_tx]).await?;
-        let receipt = wait_for_tx(&self.rpc_client, tx_hash).await?;
-
-        // --- Proportional Reward Logic ---
-        // After a successful liquidation, distribute the earnings based on player scores.
-        // After a successful liquidation, we find the next player and distribute the earnings.
-        if let Some(redeemer) = self.find_next_player_in_queue().await? {
-            tracing::info!("[💸 Distribution] Found player in queue: {}", redeemer.player);
-
-            let highest_score = self.get_highest_score().await?.unwrap_or(redeemer.score); // Fallback to player's score if no global high score.
-            if highest_score == 0 {
-                tracing::warn!("[💸 Distribution] Highest score is 0, cannot calculate proportion.");
-                return Ok(());
-            }
-
-            // 1. Parse the actual liquidation earnings from the transaction events.
-            let (collateral_token_address, total_earnings) =
-                match parse_liquidation_event(&receipt.events, self.liquidate_contract.address()) {
-                    Some(data) => data,
-                    None => {
-                        tracing::error!("[💸 Distribution] Could not find or parse Liquidation event in tx {:#x}", tx_hash);
-                        return Ok(());
-                    }
-                };
+    /// Refreshes & checks the positions whose failed liquidation is due for
+    /// a retry, see [`FailedLiquidation`].
+    async fn retry_failed_liquidations(&self) -> Result<()> {
+        let now = unix_now();
+        let due: Vec<PositionKey> = self
+            .failed_liquidations
+            .iter()
+            .filter(|entry| entry.value().is_due(now))
+            .map(|entry| *entry.key())
+            .collect();
+        if due.is_empty() {
+            return Ok(());
+        }
+        tracing::info!(
+            "[🔭 Monitoring] Retrying {} failed liquidation(s)",
+            due.len()
+        );
+        self.refresh_positions(&due).await;
+        self.check_positions(due).await
+    }
 
-            // 2. Calculate the player's proportional share of the earnings.
-            // The `total_earnings` is a u256, but for the f64 calculation, we'll convert it.
-            // This is safe for any reasonable token amount.
-            let total_earnings_f64 = (total_earnings.low as f64) + ((total_earnings.high as f64) * 2.0_f64.powi(128));
+    /// Records a transient failure of the liquidation of the position, &
+    /// alerts when it is abandoned.
+    async fn record_failed_liquidation(
+        &self,
+        key: PositionKey,
+        error: &anyhow::Error,
+    ) -> Result<()> {
+        let mut failed = FailedLiquidation::record_failure(
+            self.failed_liquidations.get(&key).as_deref(),
+            key,
+            error.to_string(),
+            unix_now(),
+            &self.config.liquidation_retry,
+        );
+        // Retrying can't fix e.g. an amount that can't be computed.
+        if !is_retryable(error) {
+            failed.status = FailedLiquidationStatus::Abandoned;
+        }
+        match failed.status {
+            FailedLiquidationStatus::Retrying => {
+                self.notifier.notify(
+                    Severity::Warning,
+                    format!(
+                        "Could not liquidate position #{key} (attempt {}), retrying: {error}",
+                        failed.attempts
+                    ),
+                );
+            }
+            FailedLiquidationStatus::Abandoned => {
+                self.notifier.notify(
+                    Severity::Critical,
+                    format!(
+                        "Liquidation of position #{key} abandoned after {} attempts: {error}",
+                        failed.attempts
+                    ),
+                );
+            }
+        }
+        self.failed_liquidations.insert(key, failed);
+        self.save_failed_liquidations().await
+    }
 
-            // The player's score is also a u128.
-            let player_score_f64 = redeemer.score as f64;
-            let highest_score_f64 = highest_score as f64;
+    /// Forgets the failed liquidation of the position, liquidated or healthy
+    /// again.
+    async fn clear_failed_liquidation(&self, key: &PositionKey) -> Result<()> {
+        if self.failed_liquidations.remove(key).is_some() {
+            self.save_failed_liquidations().await?;
+        }
+        Ok(())
+    }
 
-            // The proportion is (player_score / highest_score).
-            let player_share_f64 = total_earnings_f64 * (player_score_f64 / highest_score_f64);
-            let player_share_u128 = player_share_f64 as u128;
+    async fn save_failed_liquidations(&self) -> Result<()> {
+        let failed_liquidations: Vec<FailedLiquidation> = self
+            .failed_liquidations
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        self.storage
+            .lock()
+            .await
+            .save_failed_liquidations(&failed_liquidations)
+            .await
+    }
 
-            let player_share = U256 { low: player_share_u128, high: 0 };
-            let world_share = total_earnings - player_share;
+    /// Refreshes the amounts & LLTVs of the given positions with batched
+    /// RPC requests, see [`Position::update_batch`]. On failure, the positions
+    /// left are checked with their last known state.
+    async fn refresh_positions(&self, keys: &[PositionKey]) {
+        let mut positions: Vec<Position> = keys
+            .iter()
+            .filter_map(|key| {
+                self.positions
+                    .by_key
+                    .get(key)
+                    .map(|entry| entry.value().clone())
+            })
+            .collect();
+        let refreshed = self.profiler.time(
+            TickPhase::Refresh,
+            Position::update_batch(
+                &mut positions,
+                self.rpc.as_ref(),
+                &self.protocols,
+                self.config.position_update.batch_size,
+            ),
+        );
+        if let Err(e) = refreshed.await {
+            tracing::warn!(
+                error = %e,
+                "[🔭 Monitoring] Could not refresh all the positions, checking their last known state"
+            );
+        }
 
+        for position in positions {
+            let key = position.key();
+            let closed = position.is_closed();
+            if let Some(mut entry) = self.positions.by_key.get_mut(&key) {
+                *entry = position;
+            }
+            if closed {
+                self.mark_closed(key).await;
+            } else {
+                self.pruning.lock().await.closed_since.remove(&key);
+            }
+        }
+    }
+
+    /// Checks the positions whose collateral or debt is the given asset.
+    async fn check_positions_with_asset(&self, asset: &str) -> Result<()> {
+        let position_keys = self.positions.keys_with_assets([asset]);
+        if !position_keys.is_empty() {
+            tracing::debug!(
+                "[🔭 Monitoring] {} price moved, checking {} position(s)",
+                asset.to_uppercase(),
+                position_keys.len()
+            );
+        }
+        self.check_positions(position_keys).await
+    }
+
+    /// Checks if the given positions are liquidable & liquidates them, most
+    /// valuable first (see [`LiquidationCandidate`]). The failed liquidations
+    /// are only retried when due.
+    async fn check_positions(&self, position_keys: Vec<PositionKey>) -> Result<()> {
+        let now = unix_now();
+        let config = self.live.current();
+        let mut candidates = BinaryHeap::new();
+        let mut suspect_skipped = 0;
+        for key in position_keys {
+            let Some(position) = self
+                .positions
+                .by_key
+                .get(&key)
+                .map(|entry| entry.value().clone())
+            else {
+                continue;
+            };
+            // Positions stored before they were filtered out are kept, but
+            // not liquidated.
+            if position.is_closed() || !config.position_filter.accepts(&key) {
+                self.health_factors.remove(&key);
+                continue;
+            }
+            let health_factor = position
+                .health_factor(&self.latest_oracle_prices)
+                .await
+                .ok()
+                .flatten();
+            self.report_at_risk(&config, &position, health_factor.as_ref());
+            self.health_factors.record(key, health_factor);
+            if self.liquidating.contains(&key) {
+                tracing::debug!(
+                    "[🔭 Monitoring] Position #{} is already being liquidated",
+                    key
+                );
+                continue;
+            }
+            let liquidable = self.profiler.time(
+                TickPhase::IsLiquidable,
+                position.is_liquidable(&self.latest_oracle_prices),
+            );
+            if !liquidable.await? {
+                self.liquidable_since.remove(&key);
+                self.positions.clear_cooldown(&key);
+                self.clear_failed_liquidation(&key).await?;
+                continue;
+            }
+            if self
+                .failed_liquidations
+                .get(&key)
+                .is_some_and(|failed| !failed.is_due(now))
+                || self
+                    .positions
+                    .in_cooldown(&key, now, &config.liquidation_cooldown)
+            {
+                continue;
+            }
+            if self.has_suspect_price(&position) {
+                suspect_skipped += 1;
+                continue;
+            }
+            self.liquidable_since
+                .entry(key)
+                .or_insert_with(Instant::now);
+            candidates.push(
+                position
+                    .as_liquidation_candidate(&self.latest_oracle_prices)
+                    .await?,
+            );
+        }
+
+        if suspect_skipped > 0 {
+            tracing::warn!(
+                "[🔭 Monitoring] ⏸️ {} liquidable position(s) skipped, the price of their collateral or debt is suspect",
+                suspect_skipped
+            );
+        }
+        if !candidates.is_empty() && self.liquidations_paused() {
+            tracing::warn!(
+                "[🔭 Monitoring] ⏸️ {} liquidable position(s) skipped, the {} balance of the bot is too low to pay the fees",
+                candidates.len(),
+                self.tx_manager.fee_tokens().current().ticker()
+            );
+            return Ok(());
+        }
+        if !candidates.is_empty() && self.controls.is_paused() {
+            tracing::warn!(
+                "[🔭 Monitoring] ⏸️ {} liquidable position(s) skipped, the liquidations are paused by the control plane",
+                candidates.len()
+            );
+            return Ok(());
+        }
+        if !candidates.is_empty() && self.controls.is_halted() {
+            tracing::warn!(
+                "[🔭 Monitoring] ⏸️ {} liquidable position(s) skipped, the transactions are halted by an operator",
+                candidates.len()
+            );
+            return Ok(());
+        }
+        if !candidates.is_empty() && !self.leadership.is_leader() {
+            tracing::debug!(
+                "[🔭 Monitoring] {} liquidable position(s) left to the leader instance",
+                candidates.len()
+            );
+            return Ok(());
+        }
+        let found = candidates.len();
+        candidates.retain(|candidate| !self.fees.defers(candidate));
+        if candidates.len() < found {
+            tracing::warn!(
+                "[🔭 Monitoring] ⛽ {} liquidable position(s) deferred, the fees are spiking (median L2 gas price of {} fri)",
+                found - candidates.len(),
+                self.fees.median().unwrap_or_default()
+            );
+        }
+
+        let mut positions_to_delete = vec![];
+        while let Some(candidate) = candidates.pop() {
+            let key = candidate.key;
+            // Cloned, not to hold a shard of the map across the liquidation.
+            let Some(mut position) = self
+                .positions
+                .by_key
+                .get(&key)
+                .map(|entry| entry.value().clone())
+            else {
+                continue;
+            };
+            let liquidable_since = *self
+                .liquidable_since
+                .entry(key)
+                .or_insert_with(Instant::now);
             tracing::info!(
-                "[💸 Distribution] Player Score: {}, Highest Score: {}, Total Earnings: {}",
-                redeemer.score, highest_score, total_earnings_f64
+                position_key = ?key,
+                pool_id = format!("{:#x}", key.pool_id),
+                "[🔭 Monitoring] Liquidatable position found #{}!",
+                position.key()
+            );
+
+            tracing::info!("[🔭 Monitoring] 🔫 Liquidating position...");
+            let liquidation_span = tracing::info_span!(
+                "liquidation",
+                position_key = ?key,
+                pool_id = format!("{:#x}", key.pool_id),
+                liquidable_for_ms = liquidable_since.elapsed().as_millis() as u64,
             );
-            tracing::info!("[💸 Distribution] Player Share: {}, World Share: {}", player_share.low, world_share.low);
+            let result = self
+                .profiler
+                .time(
+                    TickPhase::Submit,
+                    self.liquidate_position(&position)
+                        .instrument(liquidation_span),
+                )
+                .await;
+            self.record_liquidation_attempt(&config, &key);
+            match self
+                .handle_liquidation_result(key, liquidable_since, result)
+                .await?
+            {
+                AfterLiquidation::Refresh => {}
+                AfterLiquidation::Skip => continue,
+                AfterLiquidation::Forget => {
+                    positions_to_delete.push(key);
+                    continue;
+                }
+            }
 
-            // 3. Distribute the funds: player's share to the player, remainder to the world contract.
-            let player_address = FieldElement::from_hex_be(&redeemer.player)?;
-            let world_address = self.config.world_address;
+            if let Err(e) = position
+                .update(
+                    self.rpc.as_ref(),
+                    &self.protocols,
+                    &self.config.position_update,
+                )
+                .await
+            {
+                tracing::warn!(
+                    error = %e,
+                    position_key = ?key,
+                    "[🔭 Monitoring] Could not refresh position #{} after liquidation",
+                    key
+                );
+            }
+            // Unless it was dropped meanwhile, e.g. by a rollback.
+            if let Some(mut entry) = self.positions.by_key.get_mut(&key) {
+                *entry = position;
+            }
+        }
+
+        for to_delete in positions_to_delete {
+            self.forget_position(&to_delete);
+        }
 
-            let player_transfer_call = self.build_erc20_transfer_call(collateral_token_address, player_address, player_share)?;
-            let world_transfer_call = self.build_erc20_transfer_call(collateral_token_address, world_address, world_share)?;
+        Ok(())
+    }
 
-            tracing::info!("[💸 Distribution] Executing distribution multicall...");
-            let dist_tx_hash = self.account.execute_txs(&[player_transfer_call, world_transfer_call]).await?;
-            wait_for_tx(&self.rpc_client, dist_tx_hash).await?;
-            tracing::info!("[💸 Distribution] ✅ Distribution complete! (tx {:#x})", dist_tx_hash);
+    /// Records the result of the liquidation of a position & returns what to
+    /// do with the position next.
+    async fn handle_liquidation_result(
+        &self,
+        key: PositionKey,
+        liquidable_since: Instant,
+        result: Result<()>,
+    ) -> Result<AfterLiquidation> {
+        match result {
+            Ok(()) => {
+                self.liquidable_since.remove(&key);
+                self.slo.record_liquidation(liquidable_since.elapsed());
+                self.clear_failed_liquidation(&key).await?;
+                Ok(AfterLiquidation::Refresh)
             }
+            Err(e) => match LiquidatorError::of(&e) {
+                Some(LiquidatorError::NotUndercollateralized(_)) => {
+                    tracing::warn!(
+                        position_key = ?key,
+                        "[🔭 Monitoring] Position was not under collateralized!"
+                    );
+                    self.liquidable_since.remove(&key);
+                    self.clear_failed_liquidation(&key).await?;
+                    Ok(AfterLiquidation::Forget)
+                }
+                Some(LiquidatorError::Reverted(_)) => {
+                    tracing::warn!(
+                        error = %e,
+                        position_key = ?key,
+                        "[🔭 Monitoring] Position #{} skipped, its liquidation would revert",
+                        key
+                    );
+                    Ok(AfterLiquidation::Skip)
+                }
+                Some(LiquidatorError::PriceImpact(_)) => {
+                    tracing::warn!(
+                        error = %e,
+                        position_key = ?key,
+                        "[🔭 Monitoring] Position #{} skipped, selling its collateral would move the price too much",
+                        key
+                    );
+                    Ok(AfterLiquidation::Skip)
+                }
+                _ => {
+                    tracing::error!(
+                        error = %e,
+                        position_key = ?key,
+                        "[🔭 Monitoring] 😨 Could not liquidate position #{}",
+                        key,
+                    );
+                    self.record_failed_liquidation(key, &e).await?;
+                    Ok(AfterLiquidation::Refresh)
+                }
+            },
+        }
+    }
+
+    /// Warns the owner of the position through the notifications & the feed
+    /// when its health factor falls within `monitoring.at_risk_margin_bps` of
+    /// a liquidation, once until it gets back above.
+    fn report_at_risk(
+        &self,
+        config: &Config,
+        position: &Position,
+        health_factor: Option<&BigDecimal>,
+    ) {
+        let key = position.key();
+        let margin_bps = config.monitoring.at_risk_margin_bps;
+        let threshold =
+            BigDecimal::from(1) + BigDecimal::from(margin_bps) / BigDecimal::from(BPS_DENOMINATOR);
+        let Some(health_factor) = health_factor.filter(|hf| margin_bps > 0 && *hf < &threshold)
+        else {
+            self.at_risk.remove(&key);
+            return;
+        };
+        if !self.at_risk.insert(key) {
+            return;
+        }
+        let shortfall_usd = position
+            .collateral_shortfall(&self.latest_oracle_prices, health_factor, &threshold)
+            .ok();
+        tracing::warn!(
+            position_key = ?key,
+            user = format!("{:#x}", position.user_address),
+            health_factor = %health_factor.round(4),
+            "[🔭 Monitoring] ⚠️ Position #{} is at risk, health factor of {}",
+            key,
+            health_factor.round(4)
+        );
+        self.notifier.notify(
+            Severity::Warning,
+            format!(
+                "Position #{} of {:#x} is at risk: health factor of {}, ${} of {} missing to get back above {}",
+                key,
+                position.user_address,
+                health_factor.round(4),
+                shortfall_usd
+                    .as_ref()
+                    .map(|shortfall| shortfall.round(2).to_plain_string())
+                    .unwrap_or_else(|| "?".to_string()),
+                position.collateral.name,
+                threshold
+            ),
+        );
+        self.feed.publish(FeedEvent::PositionAtRisk {
+            position_key: key,
+            owner: position.user_address,
+            health_factor: health_factor.clone(),
+            threshold,
+            shortfall_usd,
+        });
+    }
+
+    /// Starts the cooldown of the position after a liquidation attempt, failed
+    /// or partial, alerting once it reached `liquidation_cooldown.max_attempts`.
+    fn record_liquidation_attempt(&self, config: &Config, key: &PositionKey) {
+        let config = &config.liquidation_cooldown;
+        let cooldown = self.positions.record_attempt(key, unix_now(), config);
+        if config.max_attempts > 0 && cooldown.attempts == config.max_attempts {
+            tracing::warn!(
+                position_key = ?key,
+                "[🔭 Monitoring] Position #{} reached {} liquidation attempts, left alone until healthy",
+                key,
+                cooldown.attempts
+            );
+            self.notifier.notify(
+                Severity::Warning,
+                format!(
+                    "Position #{key} reached {} liquidation attempts, left alone until healthy again",
+                    cooldown.attempts
+                ),
+            );
+        }
+    }
+
+    /// Stops monitoring the position.
+    fn forget_position(&self, key: &PositionKey) {
+        self.positions.remove(key);
+        self.health_factors.remove(key);
+        self.at_risk.remove(key);
+    }
+
+    /// Refreshes the nonces & the balances of the current fee token of the
+    /// accounts the liquidations are rotated across.
+    async fn refresh_accounts(&self) {
+        let fee_token = self.tx_manager.fee_tokens().current();
+        let Some(token_address) = self.tx_manager.fee_tokens().address(fee_token) else {
+            return;
+        };
+        let accounts = self.tx_manager.accounts();
+        accounts
+            .refresh(&self.rpc_client, fee_token, token_address)
+            .await;
+        if accounts.len() > 1 {
+            for (address, state) in accounts.states() {
+                tracing::debug!(
+                    account = format!("{address:#x}"),
+                    pending = state.pending,
+                    sent = state.sent,
+                    "[🔭 Monitoring] Account {:#x}: {} {}, next nonce {}",
+                    address,
+                    state
+                        .fee_balance
+                        .map(|balance| balance.to_plain_string())
+                        .unwrap_or_else(|| "?".to_string()),
+                    fee_token.ticker(),
+                    state
+                        .next_nonce
+                        .map(|nonce| format!("{nonce:#x}"))
+                        .unwrap_or_else(|| "?".to_string())
+                );
+            }
+        }
+    }
+
+    /// Returns if the liquidations are paused for lack of the fee token to
+    /// pay their fees, see `monitoring.pause_on_low_fee_balance`.
+    fn liquidations_paused(&self) -> bool {
+        self.config.monitoring.pause_on_low_fee_balance && !self.tx_manager.fee_tokens().can_pay()
+    }
+
+    /// Returns if the price of the collateral or of the debt of the position
+    /// jumped without being corroborated, see `oracle.max_jump_percent`.
+    fn has_suspect_price(&self, position: &Position) -> bool {
+        [&position.collateral.name, &position.debt.name]
+            .into_iter()
+            .any(|asset| self.latest_oracle_prices.is_suspect(&asset.to_lowercase()))
+    }
+
+    /// Liquidates the position & sends the earnings to the distribution service.
+    /// Fails if a liquidation of the position is already pending.
+    #[tracing::instrument(
+        skip_all,
+        fields(position_key = ?position.key(), tx_hash = tracing::field::Empty)
+    )]
+    pub async fn liquidate_position(&self, position: &Position) -> Result<()> {
+        let key = position.key();
+        let Some(_liquidating) = LiquidatingGuard::acquire(&self.liquidating, key) else {
+            return Err(anyhow!("Position #{key} already has a pending liquidation"));
+        };
+        let started_at = Instant::now();
+        let mut record = LiquidationRecord {
+            key,
+            pool_id: position.pool_id,
+            user: position.user_address,
+            collateral: position.collateral.name.clone(),
+            debt: position.debt.name.clone(),
+            started_at: unix_now(),
+            duration_ms: 0,
+            status: LiquidationStatus::Succeeded,
+            tx_hash: None,
+            block_number: None,
+            collateral_seized: None,
+            debt_repaid: None,
+            earnings: None,
+            gas: None,
+            error: None,
+            finality: None,
+        };
+        let res = self
+            .send_liquidation(position, started_at, &mut record)
+            .await;
+        record.duration_ms = started_at.elapsed().as_millis() as u64;
+        if let Err(e) = &res {
+            record.status = match LiquidatorError::of(e) {
+                Some(
+                    LiquidatorError::NotUndercollateralized(_)
+                    | LiquidatorError::Reverted(_)
+                    | LiquidatorError::PriceImpact(_),
+                ) => LiquidationStatus::Skipped,
+                _ => LiquidationStatus::Failed,
+            };
+            record.error = Some(e.to_string());
+        }
+        self.record_liquidation(&record).await;
+        res
+    }
+
+    /// Adds the liquidation to the history of the storage. A failure is only
+    /// logged, not to hide the result of the liquidation.
+    async fn record_liquidation(&self, record: &LiquidationRecord) {
+        if let Some(tx_hash) = record.tx_hash {
+            self.seen_liquidations.insert(tx_hash);
+        }
+        if let Err(e) = self
+            .storage
+            .lock()
+            .await
+            .save_liquidation_record(record)
+            .await
+        {
+            tracing::error!(
+                error = %e,
+                position_key = ?record.key,
+                "[🔭 Monitoring] Could not record the liquidation of position #{} in the history",
+                record.key
+            );
+        }
+    }
+
+    /// Checks the price impact of selling the collateral seized by a large
+    /// liquidation, see [`crate::config::PriceImpactConfig`]: returns the
+    /// amounts of debt to try, only the largest tranche within
+    /// `max_impact_bps` when the whole liquidation isn't, or an error when no
+    /// tranche is.
+    async fn limit_price_impact(
+        &self,
+        position: &Position,
+        amounts: Vec<BigDecimal>,
+        taker: Felt,
+    ) -> Result<Vec<BigDecimal>> {
+        let config = &self.config.price_impact;
+        if !config.enabled {
+            return Ok(amounts);
+        }
+        let debt_value = position.debt_value(&self.latest_oracle_prices)?;
+        if debt_value < config.min_debt_value_usd {
+            return Ok(amounts);
+        }
+        let max_impact_bps = u32::from(config.max_impact_bps);
+        let impact = estimate_liquidation_impact(
+            &self.swap_providers,
+            position,
+            &position.debt.amount,
+            &self.latest_oracle_prices,
+            taker,
+        )
+        .await?;
+        let impact_usd = &debt_value * BigDecimal::from(impact) / BigDecimal::from(BPS_DENOMINATOR);
         tracing::info!(
+            position_key = ?position.key(),
+            impact_bps = impact,
+            impact_usd = %impact_usd.round(2),
+            "[🔭 Monitoring] Selling the collateral of position #{} would lose {} bps (${})",
+            position.key(),
+            impact,
+            impact_usd.round(2)
+        );
+        if impact <= max_impact_bps {
+            return Ok(amounts);
+        }
+        for tranche in position.tranche_amounts(config.max_tranches) {
+            let tranche_impact = estimate_liquidation_impact(
+                &self.swap_providers,
+                position,
+                &tranche,
+                &self.latest_oracle_prices,
+                taker,
+            )
+            .await?;
+            if tranche_impact <= max_impact_bps {
+                tracing::info!(
+                    position_key = ?position.key(),
+                    impact_bps = tranche_impact,
+                    "[🔭 Monitoring] Liquidating position #{} in tranches of {} {} ({} bps)",
+                    position.key(),
+                    tranche,
+                    position.debt.name,
+                    tranche_impact
+                );
+                return Ok(vec![tranche]);
+            }
+        }
+        Err(LiquidatorError::PriceImpact(format!(
+            "selling the collateral of position #{} would lose {impact} bps, above {max_impact_bps} bps",
+            position.key()
+        ))
+        .into())
+    }
+
+    /// Builds the liquidation of the position repaying `debt_to_repay` &
+    /// simulates it as sent by `sender`, returning its calls - preceded by an
+    /// approval of the debt if the allowance of the sender is too low - & the
+    /// simulated events. A liquidation that would revert is not sent, e.g.
+    /// when the position was liquidated by someone else meanwhile.
+    async fn simulate_liquidation(
+        &self,
+        position: &Position,
+        sender: &StarknetAccount,
+        bot_address: &Felt,
+        debt_to_repay: &BigDecimal,
+    ) -> Result<(Vec<Call>, Vec<Event>)> {
+        let protocol = self.protocols.get(position.protocol)?;
+        let mut liquidation_calls = protocol
+            .liquidation_calls(position, &self.http_client, *bot_address, debt_to_repay)
+            .await?;
+        // The flash loan receiver runs the liquidations of the Liquidate contract.
+        if self.config.monitoring.flash_loan == FlashLoanSource::Vesu && position.protocol.is_vesu()
+        {
+            // Validated to be set with the vesu flash loans.
+            if let Some(receiver_address) = self.config.flash_loan_receiver_address {
+                liquidation_calls = liquidation_calls
+                    .into_iter()
+                    .map(|call| {
+                        position.wrap_in_vesu_flash_loan(
+                            call,
+                            self.config.singleton_address,
+                            receiver_address,
+                            debt_to_repay,
+                        )
+                    })
+                    .collect();
+            }
+        }
+
+        let mut calls = self
+            .allowances
+            .for_owner(sender.account_address())
+            .for_spender(protocol.debt_spender())
+            .approval_calls(
+                position.debt.address,
+                big_decimal_to_u256(debt_to_repay.with_scale(position.debt.decimals)),
+            )
+            .await?;
+        calls.extend(liquidation_calls);
+
+        match self.tx_manager.simulate_as(sender, &calls).await? {
+            Simulation::Succeeded(events) => Ok((calls, events)),
+            Simulation::Reverted(reason) => Err(LiquidatorError::reverted(reason).into()),
+        }
+    }
+
+    /// Sends the liquidation of the position, filling `record` with its
+    /// transaction & what it seized, repaid & earned.
+    async fn send_liquidation(
+        &self,
+        position: &Position,
+        started_at: Instant,
+        record: &mut LiquidationRecord,
+    ) -> Result<()> {
+        // The liquidator bot's address will be the initial recipient of all
+        // earnings, whichever account of the rotation sends the liquidation.
+        let bot_address = self.account.account_address();
+        let lease = self.tx_manager.acquire();
+
+        // In partial mode, smaller liquidations are tried when the whole
+        // debt can't be routed or repaid, the rest being liquidated the next
+        // rounds while the position is unhealthy.
+        let amounts = position.liquidation_amounts(self.config.liquidation_mode);
+        let amounts = self
+            .limit_price_impact(position, amounts, bot_address)
+            .await?;
+        let mut last_error = None;
+        let mut liquidation = None;
+        for debt_to_repay in amounts.iter() {
+            match self
+                .simulate_liquidation(position, lease.account(), &bot_address, debt_to_repay)
+                .await
+            {
+                Ok(simulated) => {
+                    liquidation = Some((debt_to_repay, simulated));
+                    break;
+                }
+                Err(e)
+                    if matches!(
+                        LiquidatorError::of(&e),
+                        Some(LiquidatorError::NotUndercollateralized(_))
+                    ) =>
+                {
+                    return Err(e);
+                }
+                Err(e) => {
+                    if amounts.len() > 1 {
+                        tracing::warn!(
+                            error = %e,
+                            position_key = ?position.key(),
+                            "[🔭 Monitoring] Liquidation of {} {} of position #{} failed, trying a smaller one",
+                            debt_to_repay,
+                            position.debt.name,
+                            position.key()
+                        );
+                    }
+                    last_error = Some(e);
+                }
+            }
+        }
+        let Some((debt_to_repay, (liquidation_calls, simulated_events))) = liquidation else {
+            return Err(last_error.unwrap_or_else(|| anyhow!("No liquidation amount to try")));
+        };
+        if *debt_to_repay < position.debt.amount {
+            tracing::info!(
+                position_key = ?position.key(),
+                "[🔭 Monitoring] Partially liquidating position #{}: {} of {} {}",
+                position.key(),
+                debt_to_repay,
+                position.debt.amount,
+                position.debt.name
+            );
+        }
+        let protocol = self.protocols.get(position.protocol)?;
+        if let Some(LiquidationOutcome {
+            collateral: token,
+            earnings: amount,
+            ..
+        }) = protocol.find_liquidation(&simulated_events)
+        {
+            let earnings = self.valuator.value(token, &amount);
+            tracing::info!(
+                position_key = ?position.key(),
+                amount = %earnings.amount,
+                usd = %earnings.usd_string(),
+                token = format!("{token:#x}"),
+                "[🔭 Monitoring] Liquidation of position #{} simulated, projected earnings: {}",
+                position.key(),
+                earnings
+            );
+        }
+
+        let receipt = self
+            .tx_manager
+            .execute_as(&lease, &liquidation_calls)
+            .await?;
+        drop(lease);
+        let tx_hash = *receipt.receipt.transaction_hash();
+        record.tx_hash = Some(tx_hash);
+        record.block_number = Some(receipt.block.block_number());
+        record.finality = Some(Finality::AcceptedOnL2);
+        // Paid even if the liquidation event can't be decoded.
+        let gas = self.valuator.gas(receipt.receipt.actual_fee());
+        record.gas = Some(gas.clone());
+        tracing::Span::current().record("tx_hash", format!("{tx_hash:#064x}"));
+        tracing::info!(
+            position_key = ?position.key(),
+            pool_id = format!("{:#x}", position.pool_id),
+            tx_hash = format!("{tx_hash:#064x}"),
             "[🔭 Monitoring] ✅ Liquidated position #{}! (tx {tx_hash:#064x}) - ⌛ {:?}",
             position.key(),
             started_at.elapsed()
         );
+        self.notifier.notify(
+            Severity::Info,
+            format!(
+                "Liquidated position #{} (tx {tx_hash:#064x})",
+                position.key()
+            ),
+        );
+
+        // Parse the actual liquidation earnings from the transaction events.
+        match protocol.find_liquidation(receipt.receipt.events()) {
+            Some(liquidation) => {
+                let (token, amount) = (liquidation.collateral, liquidation.earnings);
+                self.tokens.resolve([token]).await;
+                let earnings = self.valuator.value(token, &amount);
+                tracing::info!(
+                    position_key = ?position.key(),
+                    tx_hash = format!("{tx_hash:#064x}"),
+                    token = format!("{token:#x}"),
+                    amount = %earnings.amount,
+                    usd = %earnings.usd_string(),
+                    "[🔭 Monitoring] 💰 Liquidation of position #{} earned {}",
+                    position.key(),
+                    earnings
+                );
+                let pnl = self
+                    .record_liquidation_pnl(
+                        tx_hash,
+                        receipt.block.block_number(),
+                        gas,
+                        &liquidation,
+                    )
+                    .await;
+                record.collateral_seized = Some(pnl.collateral_seized);
+                record.debt_repaid = Some(pnl.debt_repaid);
+                record.earnings = Some(pnl.earnings);
+                self.feed.publish(FeedEvent::LiquidationExecuted {
+                    position_key: position.key(),
+                    tx_hash,
+                    token,
+                    amount,
+                    usd: earnings.usd,
+                });
+                self.earnings_sender.send(LiquidationEarnings {
+                    liquidation_tx: tx_hash,
+                    token,
+                    amount,
+                    liquidated_at: unix_now(),
+                    span: Some(tracing::Span::current()),
+                })?;
+            }
+            None => {
+                tracing::error!(
+                    tx_hash = format!("{tx_hash:#064x}"),
+                    "[💸 Distribution] Could not find or decode the liquidation event in tx {:#x}",
+                    tx_hash
+                );
+            }
+        }
         Ok(())
     }
+
+    /// Records what the liquidation seized, repaid, earned & cost, valued at
+    /// the current oracle prices, & returns it. A failure is only logged: the
+    /// liquidation went through.
+    async fn record_liquidation_pnl(
+        &self,
+        tx_hash: Felt,
+        block_number: u64,
+        gas: ValuedAmount,
+        liquidation: &LiquidationOutcome,
+    ) -> LiquidationPnl {
+        let pnl = LiquidationPnl {
+            tx_hash,
+            block_number,
+            executed_at: unix_now(),
+            pool_id: liquidation.pool_id,
+            user: liquidation.user,
+            collateral_seized: self
+                .valuator
+                .value(liquidation.collateral, &liquidation.collateral_seized),
+            debt_repaid: self
+                .valuator
+                .value(liquidation.debt, &liquidation.debt_repaid),
+            earnings: self
+                .valuator
+                .value(liquidation.collateral, &liquidation.earnings),
+            gas,
+        };
+        if let Err(e) = self.storage.lock().await.save_liquidation_pnl(&pnl).await {
+            tracing::error!(
+                error = %e,
+                tx_hash = format!("{tx_hash:#064x}"),
+                "[🔭 Monitoring] Could not record the PnL of liquidation {:#x}",
+                tx_hash
+            );
+        }
+        pnl
+    }
 }
 
-/// A simple struct to hold a u256 value.
-#[derive(Debug, Clone, Copy)]
-pub struct U256 {
-    pub low: u128,
-    pub high: u128,
+/// Flags a position as being liquidated until dropped, so the flag is cleared
+/// even if the liquidation panics or its future is cancelled.
+struct LiquidatingGuard {
+    liquidating: Arc<DashSet<PositionKey>>,
+    key: PositionKey,
 }
 
-impl std::ops::Sub for U256 {
-    type Output = Self;
+impl LiquidatingGuard {
+    /// Returns `None` if the position is already being liquidated.
+    fn acquire(liquidating: &Arc<DashSet<PositionKey>>, key: PositionKey) -> Option<Self> {
+        liquidating.insert(key).then(|| Self {
+            liquidating: liquidating.clone(),
+            key,
+        })
+    }
+}
+
+impl Drop for LiquidatingGuard {
+    fn drop(&mut self) {
+        self.liquidating.remove(&self.key);
+    }
+}
 
-    fn sub(self, rhs: Self) -> Self::Output {
-        let (low, borrow) = self.low.overflowing_sub(rhs.low);
-        let high = if borrow {
-            // We also need to subtract the borrow from the high part.
-            self.high.saturating_sub(rhs.high).saturating_sub(1)
+/// Interval of the full checks of the positions, halved while the market is
+/// busy & doubled while it is calm, within its bounds.
+struct AdaptiveInterval {
+    min: Duration,
+    max: Duration,
+    current: Duration,
+}
+
+impl AdaptiveInterval {
+    /// Starts relaxed, at the max interval.
+    fn new(min: Duration, max: Duration) -> Self {
+        Self {
+            min,
+            max,
+            current: max,
+        }
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self::new(
+            Duration::from_secs(config.monitoring.min_check_interval_seconds),
+            Duration::from_secs(config.monitoring.full_check_interval_seconds),
+        )
+    }
+
+    /// Returns the delay until the next check, tightened if `busy`.
+    fn next(&mut self, busy: bool) -> Duration {
+        self.current = if busy {
+            (self.current / 2).max(self.min)
         } else {
-            self.high.saturating_sub(rhs.high)
+            (self.current * 2).min(self.max)
         };
-        Self { low, high }
+        self.current
     }
 }
 
-/// Parses the events from a transaction receipt to find the `Liquidation` event
-/// and extracts the collateral asset and amount.
-///
-/// # Arguments
-/// * `events` - A slice of `Event` from a transaction receipt.
-/// * `contract_address` - The address of the contract that is expected to emit the event.
-///
-/// # Returns
-/// An `Option` containing a tuple of `(collateral_asset_address, liquidated_amount)`.
-fn parse_liquidation_event(events: &[Event], contract_address: FieldElement) -> Option<(FieldElement, U256)> {
-    let event_key = get_selector_from_name("Liquidation").ok()?;
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        path::PathBuf,
+        sync::Arc,
+        time::{Duration, Instant},
+    };
 
-    for event in events {
-        if event.from_address == contract_address && !event.keys.is_empty() && event.keys[0] == event_key {
-            // Assuming event structure: `collateral_asset: ContractAddress`, `liquidated_collateral_amount: u256`
-            if event.data.len() >= 3 {
-                let collateral_asset = event.data[0];
-                let amount_low = event.data[1].try_into().ok()?;
-                let amount_high = event.data[2].try_into().ok()?;
-                return Some((collateral_asset, U256 { low: amount_low, high: amount_high }));
+    use anyhow::{Result, anyhow};
+    use bigdecimal::BigDecimal;
+    use starknet::{
+        core::types::{Felt, FunctionCall},
+        providers::{JsonRpcClient, jsonrpc::HttpTransport},
+    };
+    use tokio::sync::mpsc::unbounded_channel;
+    use url::Url;
+
+    use super::{AdaptiveInterval, AfterLiquidation, LiquidatingGuard, MonitoringService};
+    use crate::{
+        cli::NetworkName,
+        config::{Config, LiquidationMode, VESU_LTV_CONFIG_SELECTOR},
+        services::{notifier::Notifier, oracle::LatestOraclePrices},
+        storages::json::JsonStorage,
+        types::{
+            account::{AccountPool, StarknetAccountBuilder},
+            balance::BotBalances,
+            competition::ObservedLiquidation,
+            feed::{EventFeed, FeedEvent},
+            fixtures,
+            position::{FailedLiquidation, FailedLiquidationStatus, Position},
+        },
+        utils::{errors::LiquidatorError, http::HttpClient, rpc::StarknetRpc, slo::SloTracker},
+    };
+
+    /// Serves the same amounts for every position, the given block hashes &
+    /// transaction senders.
+    #[derive(Default)]
+    struct MockRpc {
+        collateral: u64,
+        debt: u64,
+        block_hashes: HashMap<u64, Felt>,
+        senders: HashMap<Felt, Felt>,
+        down: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl StarknetRpc for MockRpc {
+        async fn call(&self, request: FunctionCall) -> Result<Vec<Felt>> {
+            if self.down {
+                return Err(anyhow!("RPC is down"));
+            }
+            if request.entry_point_selector == *VESU_LTV_CONFIG_SELECTOR {
+                return Ok(vec![Felt::from(800_000_000_000_000_000_u64)]);
             }
+            // position_unsafe: shares, nominal debt, collateral & debt as u256.
+            let (collateral, debt) = (Felt::from(self.collateral), Felt::from(self.debt));
+            Ok(vec![
+                collateral,
+                Felt::ZERO,
+                debt,
+                Felt::ZERO,
+                collateral,
+                Felt::ZERO,
+                debt,
+                Felt::ZERO,
+            ])
+        }
+
+        async fn batch_call(&self, requests: Vec<FunctionCall>) -> Result<Vec<Vec<Felt>>> {
+            let mut results = vec![];
+            for request in requests {
+                results.push(self.call(request).await?);
+            }
+            Ok(results)
+        }
+
+        async fn block_hash(&self, number: u64) -> Result<Option<Felt>> {
+            Ok(self.block_hashes.get(&number).copied())
+        }
+
+        async fn transaction_sender(&self, tx_hash: Felt) -> Result<Option<Felt>> {
+            Ok(self.senders.get(&tx_hash).copied())
+        }
+    }
+
+    fn monitoring_service(rpc: MockRpc, name: &str) -> MonitoringService {
+        let config = Config::new(
+            NetworkName::Mainnet,
+            LiquidationMode::Full,
+            &PathBuf::from("./config.yaml"),
+        )
+        .unwrap();
+        // Never reached: the reads go through the mock & nothing is sent.
+        let rpc_client = Arc::new(JsonRpcClient::new(HttpTransport::new(
+            Url::parse("http://localhost:5050").unwrap(),
+        )));
+        let account = StarknetAccountBuilder::new()
+            .on_mainnet()
+            .as_account(Felt::ONE)
+            .with_provider(rpc_client.clone())
+            .from_secret(Felt::ONE)
+            .unwrap();
+        let storage_path = std::env::temp_dir()
+            .join(format!("vesu-liquidator-{}-{name}", std::process::id()))
+            .display()
+            .to_string();
+        let (notifications_sender, _) = unbounded_channel();
+        let notifier = Notifier::new(notifications_sender);
+        let service = MonitoringService::new(
+            config.clone(),
+            rpc_client,
+            HttpClient::default(),
+            AccountPool::single(account),
+            tokio::sync::mpsc::channel(1).1,
+            LatestOraclePrices::from_config(&config),
+            Box::new(JsonStorage::new(&storage_path)),
+            unbounded_channel().0,
+            notifier.clone(),
+            SloTracker::new(config.slo.clone(), notifier),
+            EventFeed::new(),
+            BotBalances::default(),
+        );
+        MonitoringService {
+            rpc: Arc::new(rpc),
+            ..service
         }
     }
 
-    None
+    /// The fixture position, on the ETH & USDC of the mainnet config.
+    fn position(service: &MonitoringService, user: u64) -> Position {
+        let config = &service.config;
+        let mut position = fixtures::position(user, "0");
+        position.collateral.address = config.get_asset_address_for_ticker("eth").unwrap();
+        position.debt.address = config.get_asset_address_for_ticker("usdc").unwrap();
+        position
+    }
+
+    #[tokio::test]
+    async fn test_ingest_position() {
+        let rpc = MockRpc {
+            collateral: 2_000_000_000_000_000_000,
+            debt: 1_000_000,
+            ..Default::default()
+        };
+        let service = monitoring_service(rpc, "ingest-position");
+        let position = position(&service, 1);
+        let key = position.key();
+        service.ingest_position(10, position).await.unwrap();
+
+        let monitored = service.positions.by_key.get(&key).unwrap().clone();
+        assert_eq!(monitored.collateral.amount, BigDecimal::from(2));
+        assert_eq!(monitored.debt.amount, BigDecimal::from(1));
+        assert_eq!(monitored.lltv, "0.8".parse::<BigDecimal>().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_ingest_position_closed_or_failing() {
+        // A closed position is not monitored.
+        let service = monitoring_service(MockRpc::default(), "ingest-closed");
+        let position = position(&service, 1);
+        let key = position.key();
+        service.ingest_position(10, position.clone()).await.unwrap();
+        assert!(!service.positions.by_key.contains_key(&key));
+        assert!(service.dead_letters.is_empty());
+
+        // A position that can't be updated is moved to the dead letters.
+        let rpc = MockRpc {
+            down: true,
+            ..Default::default()
+        };
+        let service = monitoring_service(rpc, "ingest-failing");
+        service.ingest_position(10, position).await.unwrap();
+        assert!(!service.positions.by_key.contains_key(&key));
+        assert_eq!(service.dead_letters.get(&key).unwrap().block_number, 10);
+    }
+
+    #[tokio::test]
+    async fn test_liquidating_guard() {
+        let mut service = monitoring_service(MockRpc::default(), "liquidating-guard");
+        // Fails before sending anything: no price to value the debt.
+        service.config.price_impact.enabled = true;
+        let position = position(&service, 1);
+        let key = position.key();
+
+        let guard = LiquidatingGuard::acquire(&service.liquidating, key).unwrap();
+        assert!(LiquidatingGuard::acquire(&service.liquidating, key).is_none());
+        let error = service.liquidate_position(&position).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("Position #{key} already has a pending liquidation")
+        );
+        // The refused liquidation doesn't release the pending one.
+        assert!(service.liquidating.contains(&key));
+        drop(guard);
+        assert!(service.liquidating.is_empty());
+
+        // Released when the liquidation fails.
+        let error = service.liquidate_position(&position).await.unwrap_err();
+        assert!(!error.to_string().contains("pending liquidation"));
+        assert!(service.liquidating.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dead_letter_evicted_until_retried() {
+        let healthy = || MockRpc {
+            collateral: 2_000_000_000_000_000_000,
+            debt: 1_000_000,
+            ..Default::default()
+        };
+        let service = monitoring_service(healthy(), "dead-letter-retry");
+        let position = position(&service, 1);
+        let key = position.key();
+        service.ingest_position(10, position.clone()).await.unwrap();
+        assert!(service.positions.by_key.contains_key(&key));
+
+        // The update of a monitored position fails: its stale state is no
+        // longer liquidable.
+        let service = MonitoringService {
+            rpc: Arc::new(MockRpc {
+                down: true,
+                ..Default::default()
+            }),
+            ..service
+        };
+        service.liquidable_since.insert(key, Instant::now());
+        service.ingest_position(11, position).await.unwrap();
+        assert!(!service.positions.by_key.contains_key(&key));
+        assert!(!service.liquidable_since.contains_key(&key));
+        assert_eq!(service.dead_letters.get(&key).unwrap().block_number, 11);
+
+        // Still failing.
+        service.retry_dead_letters().await.unwrap();
+        assert!(service.dead_letters.contains_key(&key));
+        assert!(!service.positions.by_key.contains_key(&key));
+
+        // Monitored again once its update succeeds.
+        let service = MonitoringService {
+            rpc: Arc::new(healthy()),
+            ..service
+        };
+        service.retry_dead_letters().await.unwrap();
+        assert!(service.dead_letters.is_empty());
+        let monitored = service.positions.by_key.get(&key).unwrap().clone();
+        assert_eq!(monitored.collateral.amount, BigDecimal::from(2));
+    }
+
+    #[tokio::test]
+    async fn test_rollback_orphaned_blocks() {
+        let rpc = MockRpc {
+            block_hashes: HashMap::from([(10, Felt::from(10_u64)), (11, Felt::from(0xbad_u64))]),
+            ..Default::default()
+        };
+        let service = monitoring_service(rpc, "rollback-orphaned");
+        service.record_block(10, Felt::from(10_u64)).await.unwrap();
+        service.record_block(11, Felt::from(11_u64)).await.unwrap();
+        service.record_block(12, Felt::from(12_u64)).await.unwrap();
+
+        assert_eq!(service.rollback_orphaned_blocks().await.unwrap(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_not_undercollateralized_position_is_forgotten() {
+        let service = monitoring_service(MockRpc::default(), "not-undercollateralized");
+        let key = position(&service, 1).key();
+        service.positions.insert(position(&service, 1));
+        service.liquidable_since.insert(key, Instant::now());
+        service.failed_liquidations.insert(
+            key,
+            FailedLiquidation::record_failure(
+                None,
+                key,
+                "rpc".to_string(),
+                0,
+                &service.config.liquidation_retry,
+            ),
+        );
+
+        // Other reverts are only skipped until the next check.
+        let reverted = Err(LiquidatorError::reverted("'insufficient-liquidity'").into());
+        assert_eq!(
+            service
+                .handle_liquidation_result(key, Instant::now(), reverted)
+                .await
+                .unwrap(),
+            AfterLiquidation::Skip
+        );
+        assert!(service.failed_liquidations.contains_key(&key));
+
+        let reverted = Err(LiquidatorError::reverted("'not-undercollateralized'").into());
+        assert_eq!(
+            service
+                .handle_liquidation_result(key, Instant::now(), reverted)
+                .await
+                .unwrap(),
+            AfterLiquidation::Forget
+        );
+        assert!(!service.liquidable_since.contains_key(&key));
+        assert!(!service.failed_liquidations.contains_key(&key));
+
+        // Retrying can't fix a fatal error, the liquidation is abandoned.
+        let fatal = Err(LiquidatorError::Math("zero price".to_string()).into());
+        assert_eq!(
+            service
+                .handle_liquidation_result(key, Instant::now(), fatal)
+                .await
+                .unwrap(),
+            AfterLiquidation::Refresh
+        );
+        assert_eq!(
+            service.failed_liquidations.get(&key).unwrap().status,
+            FailedLiquidationStatus::Abandoned
+        );
+
+        service.forget_position(&key);
+        assert!(!service.positions.by_key.contains_key(&key));
+    }
+
+    #[tokio::test]
+    async fn test_position_at_risk_is_reported_once() {
+        let service = monitoring_service(MockRpc::default(), "at-risk");
+        let mut config = service.config.clone();
+        config.monitoring.at_risk_margin_bps = 1_000;
+        let mut events = service.feed.subscribe();
+        service
+            .latest_oracle_prices
+            .update("eth", BigDecimal::from(2_000));
+        service
+            .latest_oracle_prices
+            .update("usdc", BigDecimal::from(1));
+        let mut position = position(&service, 1);
+        position.collateral.amount = BigDecimal::from(1);
+        position.debt.amount = BigDecimal::from(1_700);
+        position.lltv = "0.9".parse().unwrap();
+        let health_factor = position
+            .health_factor(&service.latest_oracle_prices)
+            .await
+            .unwrap();
+
+        service.report_at_risk(&config, &position, health_factor.as_ref());
+        let FeedEvent::PositionAtRisk {
+            owner,
+            shortfall_usd,
+            ..
+        } = events.try_recv().unwrap().event
+        else {
+            panic!("Expected a PositionAtRisk event");
+        };
+        assert_eq!(owner, Felt::ONE);
+        // 1.1 * $1700 / 0.9 of collateral are needed, $2000 are held.
+        assert_eq!(shortfall_usd.unwrap().round(2), "77.78".parse().unwrap());
+
+        // Not reported again until it recovers.
+        service.report_at_risk(&config, &position, health_factor.as_ref());
+        assert!(events.try_recv().is_err());
+        service.report_at_risk(&config, &position, Some(&BigDecimal::from(2)));
+        service.report_at_risk(&config, &position, health_factor.as_ref());
+        assert!(events.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_competitor_liquidation_is_recorded_once() {
+        let competitor = Felt::from(0xbeef);
+        let rpc = MockRpc {
+            senders: HashMap::from([
+                (Felt::from(0x10), competitor),
+                (Felt::from(0x11), Felt::ONE),
+            ]),
+            ..MockRpc::default()
+        };
+        let service = monitoring_service(rpc, "competitor");
+        let position = position(&service, 1);
+        let key = position.key();
+        service.positions.insert(position);
+        service.liquidable_since.insert(key, Instant::now());
+        let observed = |tx_hash: u64| ObservedLiquidation {
+            key,
+            tx_hash: Felt::from(tx_hash),
+            liquidator: Felt::TWO,
+        };
+
+        service
+            .record_observed_liquidation(10, observed(0x10))
+            .await
+            .unwrap();
+        // Re-sent by the indexer, e.g. once the pending block is accepted.
+        service
+            .record_observed_liquidation(11, observed(0x10))
+            .await
+            .unwrap();
+        // Sent by the bot itself.
+        service
+            .record_observed_liquidation(11, observed(0x11))
+            .await
+            .unwrap();
+
+        let lost = service.storage.lock().await.get_competitor_liquidations();
+        assert_eq!(lost.len(), 1);
+        assert_eq!(lost[0].competitor, competitor);
+        assert_eq!(lost[0].block_number, 10);
+        assert!(!service.liquidable_since.contains_key(&key));
+    }
+
+    #[test]
+    fn test_adaptive_interval() {
+        let mut interval = AdaptiveInterval::new(Duration::from_secs(5), Duration::from_secs(30));
+        assert_eq!(interval.next(true), Duration::from_secs(15));
+        assert_eq!(interval.next(true), Duration::from_millis(7_500));
+        assert_eq!(interval.next(true), Duration::from_secs(5));
+        assert_eq!(interval.next(true), Duration::from_secs(5));
+        assert_eq!(interval.next(false), Duration::from_secs(10));
+        assert_eq!(interval.next(false), Duration::from_secs(20));
+        assert_eq!(interval.next(false), Duration::from_secs(30));
+    }
 }
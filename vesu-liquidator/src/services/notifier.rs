@@ -0,0 +1,311 @@
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use anyhow::{Result, anyhow};
+use bigdecimal::{BigDecimal, FromPrimitive};
+use futures_util::lock::Mutex;
+use starknet::{
+    core::types::{BlockId, BlockTag, Felt, FunctionCall},
+    providers::{JsonRpcClient, Provider, jsonrpc::HttpTransport},
+};
+use tokio::{
+    sync::mpsc::{UnboundedReceiver, UnboundedSender},
+    task::JoinSet,
+    time::interval,
+};
+
+use crate::{
+    config::{BALANCE_OF_SELECTOR, Config, LiveConfig, NotificationBackendConfig},
+    types::{
+        balance::{BotBalances, TokenBalance},
+        notification::{Notification, Severity},
+    },
+    utils::{conversions::hex_str_to_big_decimal, services::Service, shutdown::Shutdown, unix_now},
+};
+
+/// Interval at which the balances of the bot are checked.
+const CHECK_BALANCE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Handle used by the services to send notifications.
+#[derive(Clone)]
+pub struct Notifier(UnboundedSender<Notification>);
+
+impl Notifier {
+    pub fn new(sender: UnboundedSender<Notification>) -> Self {
+        Self(sender)
+    }
+
+    pub fn notify(&self, severity: Severity, message: impl Into<String>) {
+        let notification = Notification {
+            severity,
+            message: message.into(),
+        };
+        // The notifier service may already be stopped during shutdown.
+        let _ = self.0.send(notification);
+    }
+}
+
+/// Backend able to post a message on a notification channel.
+#[async_trait::async_trait]
+pub trait NotificationBackend: Send + Sync {
+    async fn send(&self, http_client: &reqwest::Client, message: &str) -> Result<()>;
+}
+
+pub struct TelegramBackend {
+    bot_token: String,
+    chat_id: String,
+}
+
+#[async_trait::async_trait]
+impl NotificationBackend for TelegramBackend {
+    async fn send(&self, http_client: &reqwest::Client, message: &str) -> Result<()> {
+        http_client
+            .post(format!(
+                "https://api.telegram.org/bot{}/sendMessage",
+                self.bot_token
+            ))
+            .json(&serde_json::json!({ "chat_id": self.chat_id, "text": message }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+pub struct DiscordBackend {
+    webhook_url: String,
+}
+
+#[async_trait::async_trait]
+impl NotificationBackend for DiscordBackend {
+    async fn send(&self, http_client: &reqwest::Client, message: &str) -> Result<()> {
+        http_client
+            .post(&self.webhook_url)
+            .json(&serde_json::json!({ "content": message }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+impl From<&NotificationBackendConfig> for Box<dyn NotificationBackend> {
+    fn from(config: &NotificationBackendConfig) -> Self {
+        match config {
+            NotificationBackendConfig::Telegram { bot_token, chat_id } => {
+                Box::new(TelegramBackend {
+                    bot_token: bot_token.clone(),
+                    chat_id: chat_id.clone(),
+                })
+            }
+            NotificationBackendConfig::Discord { webhook_url } => Box::new(DiscordBackend {
+                webhook_url: webhook_url.clone(),
+            }),
+        }
+    }
+}
+
+/// A backend & the severities routed to it.
+struct Channel {
+    backend: Box<dyn NotificationBackend>,
+    severities: Vec<Severity>,
+}
+
+impl Channel {
+    /// Returns the channels of `notifications.channels`.
+    fn from_config(config: &Config) -> Vec<Channel> {
+        config
+            .notifications
+            .channels
+            .iter()
+            .map(|channel| Channel {
+                backend: (&channel.backend).into(),
+                severities: channel.severities.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Posts the notifications of the bot on the configured channels & warns
+/// when a balance of the bot is getting low, see [`BotBalances`].
+#[derive(Clone)]
+pub struct NotifierService {
+    config: Config,
+    /// The reloadable fields of the config, e.g. the channels.
+    live: LiveConfig,
+    rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+    account_address: Felt,
+    http_client: reqwest::Client,
+    notifications_receiver: Arc<Mutex<UnboundedReceiver<Notification>>>,
+    notifier: Notifier,
+    balances: BotBalances,
+}
+
+#[async_trait::async_trait]
+impl Service for NotifierService {
+    async fn start(
+        &mut self,
+        join_set: &mut JoinSet<anyhow::Result<()>>,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<()> {
+        let service = self.clone();
+        join_set.spawn(async move {
+            tracing::info!("📣 Notifier service started");
+            service.run_forever(shutdown).await?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+impl NotifierService {
+    pub fn new(
+        config: Config,
+        rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+        account_address: Felt,
+        notifications_receiver: UnboundedReceiver<Notification>,
+        notifier: Notifier,
+        http_client: reqwest::Client,
+        balances: BotBalances,
+    ) -> Self {
+        Self {
+            live: LiveConfig::new(config.clone()),
+            config,
+            rpc_client,
+            account_address,
+            http_client,
+            notifications_receiver: Arc::new(Mutex::new(notifications_receiver)),
+            notifier,
+            balances,
+        }
+    }
+
+    /// Applies the reloads of the config, see [`LiveConfig`].
+    pub fn with_live_config(mut self, live: LiveConfig) -> Self {
+        self.live = live;
+        self
+    }
+
+    /// Dispatches the received notifications & periodically checks the bot's balances.
+    /// The channels are rebuilt when the config is reloaded.
+    pub async fn run_forever(&self, shutdown: Shutdown) -> Result<()> {
+        let mut balance_interval = interval(CHECK_BALANCE_INTERVAL);
+        // Tokens whose low balance was notified, until they are refilled.
+        let mut low_balances = HashSet::new();
+        let mut config_changes = self.live.subscribe();
+        let mut channels = Channel::from_config(&config_changes.borrow_and_update());
+
+        loop {
+            let mut receiver = self.notifications_receiver.lock().await;
+
+            tokio::select! {
+                _ = shutdown.wait() => {
+                    drop(receiver);
+                    tracing::info!("[📣 Notifier] 🛑 Stopped notifying");
+                    return Ok(());
+                }
+
+                _ = balance_interval.tick() => {
+                    drop(receiver);
+                    self.check_balances(&mut low_balances).await;
+                }
+
+                _ = config_changes.changed() => {
+                    drop(receiver);
+                    channels = Channel::from_config(&config_changes.borrow_and_update());
+                    tracing::info!("[📣 Notifier] Config reloaded, {} channel(s)", channels.len());
+                }
+
+                maybe_notification = receiver.recv() => {
+                    drop(receiver);
+                    match maybe_notification {
+                        Some(notification) => self.dispatch(&channels, &notification).await,
+                        None => {
+                            return Err(anyhow!("Notifier stopped unexpectedly"));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Posts the notification on every channel accepting its severity.
+    /// Failures are only logged, notifications are best effort.
+    async fn dispatch(&self, channels: &[Channel], notification: &Notification) {
+        let message = notification.format();
+        for channel in channels
+            .iter()
+            .filter(|c| c.severities.contains(&notification.severity))
+        {
+            if let Err(e) = channel.backend.send(&self.http_client, &message).await {
+                tracing::error!(error = %e, "[📣 Notifier] Could not send notification");
+            }
+        }
+    }
+
+    /// Refreshes the balances of the bot in STRK, paying the fees, & in the
+    /// assets with a `low_balance_threshold`, warning once when one of them
+    /// goes below its threshold.
+    async fn check_balances(&self, low_balances: &mut HashSet<Felt>) {
+        for (token, asset) in self.config.asset_map.iter() {
+            let threshold = match &asset.low_balance_threshold {
+                Some(threshold) => threshold.clone(),
+                None if asset.ticker.eq_ignore_ascii_case("strk") => {
+                    BigDecimal::from_f64(self.live.current().notifications.low_balance_threshold)
+                        .unwrap_or_default()
+                }
+                None => continue,
+            };
+            let balance = match self.fetch_balance(*token, asset.decimals).await {
+                Ok(balance) => balance,
+                Err(e) => {
+                    tracing::error!(
+                        error = %e,
+                        "[📣 Notifier] Could not fetch the bot balance in {}",
+                        asset.ticker
+                    );
+                    continue;
+                }
+            };
+            tracing::debug!(
+                token = asset.ticker,
+                balance = %balance,
+                "[📣 Notifier] Bot balance: {} {}",
+                balance,
+                asset.ticker
+            );
+
+            let balance = TokenBalance {
+                ticker: asset.ticker.clone(),
+                balance,
+                threshold: Some(threshold),
+                checked_at: unix_now(),
+            };
+            if !balance.is_low() {
+                low_balances.remove(token);
+            } else if low_balances.insert(*token) {
+                self.notifier.notify(
+                    Severity::Warning,
+                    format!("Low bot balance: {} {} left", balance.balance, asset.ticker),
+                );
+            }
+            self.balances.update(*token, balance);
+        }
+    }
+
+    /// Returns the balance of the bot in the token.
+    async fn fetch_balance(&self, token: Felt, decimals: i64) -> Result<BigDecimal> {
+        let balance_request = FunctionCall {
+            contract_address: token,
+            entry_point_selector: *BALANCE_OF_SELECTOR,
+            calldata: vec![self.account_address],
+        };
+        let call_result = self
+            .rpc_client
+            .call(balance_request, BlockId::Tag(BlockTag::PreConfirmed))
+            .await?;
+        Ok(hex_str_to_big_decimal(
+            &call_result[0].to_hex_string(),
+            decimals,
+        ))
+    }
+}
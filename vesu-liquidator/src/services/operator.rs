@@ -0,0 +1,335 @@
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use cainome::cairo_serde::{CairoSerde, U256};
+use starknet::{
+    core::types::{BlockId, BlockTag, Call, Felt, FunctionCall},
+    providers::{JsonRpcClient, Provider, jsonrpc::HttpTransport},
+};
+use tokio::sync::Mutex;
+
+use crate::{
+    config::{BALANCE_OF_SELECTOR, Config, TRANSFER_SELECTOR},
+    services::{control::Controls, leader::Leadership, notifier::Notifier},
+    storages::SharedStorage,
+    types::{
+        account::StarknetAccount,
+        ledger::{Finality, PayoutRecord},
+        notification::Severity,
+        operator::{OperatorAction, OperatorActionKind, SweptAmount, is_halted},
+    },
+    utils::{
+        audit::{AuditEvent, AuditLog},
+        constants::U256_ZERO,
+        conversions::{big_uint_to_u256, to_token_amount, u256_to_big_uint},
+        tx_manager::TxManager,
+        unix_now,
+    },
+};
+
+/// Runbook actions of the operators, served by the `/operator` routes of the
+/// API: a pause halts all the transactions of the bot, the liquidations &
+/// the payouts, until a resume, & a sweep halts them & transfers the funds
+/// of the bot to `operator.cold_address`. Every action is logged, notified,
+/// written to the audit log & recorded in the storage, the transactions
+/// staying halted across restarts. The swept transfers are recorded in the
+/// payout ledger, so the reconciliation expects them.
+#[derive(Clone)]
+pub struct Operator {
+    config: Config,
+    controls: Controls,
+    rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+    tx_manager: TxManager,
+    account_address: Felt,
+    storage: SharedStorage,
+    notifier: Notifier,
+    audit_log: AuditLog,
+    /// Held by the running sweep, one at a time.
+    sweeping: Arc<Mutex<()>>,
+}
+
+impl Operator {
+    pub fn new(
+        config: Config,
+        controls: Controls,
+        rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+        account: StarknetAccount,
+        leadership: Leadership,
+        storage: SharedStorage,
+        notifier: Notifier,
+        audit_log: AuditLog,
+    ) -> Self {
+        let account_address = account.account_address();
+        let tx_manager = TxManager::new(account, rpc_client.clone(), config.transactions.clone())
+            .with_leadership(leadership)
+            .with_controls(controls.clone());
+        Self {
+            config,
+            controls,
+            rpc_client,
+            tx_manager,
+            account_address,
+            storage,
+            notifier,
+            audit_log,
+            sweeping: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Halts the transactions again if they were halted before the restart.
+    pub async fn restore(&self) {
+        let actions = self.storage.lock().await.get_operator_actions();
+        if is_halted(&actions) {
+            self.controls.set_halted(true);
+            tracing::warn!(
+                "[🧑‍✈️ Operator] ⏸️ The transactions are still halted by an operator, resume them with `operator resume`"
+            );
+        }
+    }
+
+    /// Whether the `/operator` routes are served, i.e. `operator.api_token`
+    /// is configured.
+    pub fn is_enabled(&self) -> bool {
+        self.config.operator.api_token.is_some()
+    }
+
+    /// Whether the bearer token is the one of the `/operator` routes. Always
+    /// false if no `operator.api_token` is configured.
+    pub fn is_authorized(&self, token: &str) -> bool {
+        self.config
+            .operator
+            .api_token
+            .as_ref()
+            .is_some_and(|api_token| constant_time_eq(api_token.as_bytes(), token.as_bytes()))
+    }
+
+    pub async fn actions(&self) -> Vec<OperatorAction> {
+        self.storage.lock().await.get_operator_actions()
+    }
+
+    /// Halts all the transactions of the bot.
+    pub async fn pause(&self, reason: String) -> Result<OperatorAction> {
+        self.controls.set_halted(true);
+        let action = OperatorAction::new(OperatorActionKind::Pause, reason, unix_now());
+        self.record(&action).await?;
+        Ok(action)
+    }
+
+    /// Resumes the transactions of the bot.
+    pub async fn resume(&self, reason: String) -> Result<OperatorAction> {
+        let _sweeping = self
+            .sweeping
+            .try_lock()
+            .map_err(|_| anyhow!("A sweep is running, the transactions can't be resumed"))?;
+        self.controls.set_halted(false);
+        let action = OperatorAction::new(OperatorActionKind::Resume, reason, unix_now());
+        self.record(&action).await?;
+        Ok(action)
+    }
+
+    /// Halts the transactions & transfers the balances of the bot to the
+    /// cold address in a single multicall, keeping `strk_fee_reserve` STRK
+    /// to pay the fees. A failed sweep is recorded too, the transactions
+    /// staying halted.
+    pub async fn sweep(&self, reason: String) -> Result<OperatorAction> {
+        let cold_address = self
+            .config
+            .cold_address
+            .ok_or_else(|| anyhow!("No operator.cold_address configured to sweep the funds to"))?;
+        let _sweeping = self
+            .sweeping
+            .try_lock()
+            .map_err(|_| anyhow!("A sweep is already running"))?;
+        self.controls.set_halted(true);
+
+        let mut action = OperatorAction::new(OperatorActionKind::Sweep, reason, unix_now());
+        match self.transfer_balances(cold_address).await {
+            Ok((tx_hash, swept)) => {
+                action.tx_hash = tx_hash;
+                action.swept = swept;
+                self.record(&action).await?;
+                Ok(action)
+            }
+            Err(e) => {
+                action.error = Some(e.to_string());
+                self.record(&action).await?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns the transaction of the transfers, if anything was swept.
+    async fn transfer_balances(
+        &self,
+        cold_address: Felt,
+    ) -> Result<(Option<Felt>, Vec<SweptAmount>)> {
+        let sweep_assets = &self.config.operator.sweep_assets;
+        let mut assets: Vec<_> = self
+            .config
+            .asset_map
+            .iter()
+            .filter(|(_, asset)| {
+                sweep_assets.is_empty()
+                    || sweep_assets
+                        .iter()
+                        .any(|ticker| asset.ticker.eq_ignore_ascii_case(ticker))
+            })
+            .collect();
+        assets.sort_by(|(_, a), (_, b)| a.ticker.cmp(&b.ticker));
+
+        let mut calls = vec![];
+        let mut swept = vec![];
+        for (token, asset) in assets {
+            let mut amount = self.balance_of(*token).await?;
+            if asset.ticker.eq_ignore_ascii_case("strk") {
+                let reserve =
+                    to_token_amount(&self.config.operator.strk_fee_reserve, asset.decimals);
+                let (balance, reserve) = (u256_to_big_uint(&amount), u256_to_big_uint(&reserve));
+                amount = if balance > reserve {
+                    big_uint_to_u256(&(balance - reserve))
+                } else {
+                    U256_ZERO
+                };
+            }
+            if amount == U256_ZERO {
+                continue;
+            }
+            calls.push(Call {
+                to: *token,
+                selector: *TRANSFER_SELECTOR,
+                calldata: vec![cold_address, amount.low.into(), amount.high.into()],
+            });
+            swept.push(SweptAmount {
+                token: *token,
+                ticker: asset.ticker.clone(),
+                amount,
+            });
+        }
+        if calls.is_empty() {
+            return Ok((None, swept));
+        }
+        let receipt = self.tx_manager.execute_while_halted(&calls).await?;
+        let tx_hash = *receipt.receipt.transaction_hash();
+        let records = swept_payouts(tx_hash, receipt.block.block_number(), cold_address, &swept);
+        if let Err(e) = self.record_payouts(records).await {
+            tracing::error!(error = %e, "[🧑‍✈️ Operator] Could not record the swept transfers in the payout ledger");
+        }
+        Ok((Some(tx_hash), swept))
+    }
+
+    /// Records the transfers of a sweep in the payout ledger, to be
+    /// reconciled against the chain.
+    async fn record_payouts(&self, records: Vec<PayoutRecord>) -> Result<()> {
+        let mut storage = self.storage.lock().await;
+        let mut ledger = storage.get_payout_ledger();
+        ledger.records.extend(records);
+        storage.save_payout_ledger(&ledger).await
+    }
+
+    async fn balance_of(&self, token: Felt) -> Result<U256> {
+        let balance_request = FunctionCall {
+            contract_address: token,
+            entry_point_selector: *BALANCE_OF_SELECTOR,
+            calldata: vec![self.account_address],
+        };
+        let call_result = self
+            .rpc_client
+            .call(balance_request, BlockId::Tag(BlockTag::PreConfirmed))
+            .await?;
+        Ok(U256::cairo_deserialize(&call_result, 0)?)
+    }
+
+    /// Logs, notifies & records the action in the audit log & the storage.
+    async fn record(&self, action: &OperatorAction) -> Result<()> {
+        tracing::warn!("[🧑‍✈️ Operator] {action}");
+        let severity = match (action.kind, &action.error) {
+            (_, Some(_)) => Severity::Critical,
+            (OperatorActionKind::Resume, None) => Severity::Info,
+            _ => Severity::Warning,
+        };
+        self.notifier
+            .notify(severity, format!("Operator action: {action}"));
+        if let Err(e) = self.audit_log.record(&AuditEvent::OperatorAction {
+            action: action.clone(),
+        }) {
+            tracing::error!(error = %e, "[🧑‍✈️ Operator] Could not write the action to the audit log");
+        }
+        self.storage.lock().await.save_operator_action(action).await
+    }
+}
+
+/// Ledger entries of the transfers of a sweep to the cold address.
+fn swept_payouts(
+    tx_hash: Felt,
+    block_number: u64,
+    cold_address: Felt,
+    swept: &[SweptAmount],
+) -> Vec<PayoutRecord> {
+    swept
+        .iter()
+        .map(|swept| PayoutRecord {
+            tx_hash,
+            block_number,
+            token: swept.token,
+            recipient: cold_address,
+            amount: swept.amount,
+            usd: None,
+            checked: false,
+            season_id: None,
+            finality: Finality::AcceptedOnL2,
+        })
+        .collect()
+}
+
+/// Compares the tokens without returning early, not to leak the length of the
+/// matching prefix through the timing.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use cainome::cairo_serde::U256;
+    use starknet::core::types::Felt;
+
+    use super::{constant_time_eq, swept_payouts};
+    use crate::types::{
+        ledger::{ObservedTransfer, PayoutLedger, reconcile_ledgers},
+        operator::SweptAmount,
+    };
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"s3cret", b"s3cret"));
+        assert!(!constant_time_eq(b"s3cret", b"s3cres"));
+        assert!(!constant_time_eq(b"s3cret", b"s3cret!"));
+        assert!(!constant_time_eq(b"", b"s3cret"));
+    }
+
+    #[test]
+    fn test_swept_payouts_are_reconciled() {
+        let cold_address = Felt::from(0xc01d_u64);
+        let swept = [Felt::ONE, Felt::TWO].map(|token| SweptAmount {
+            token,
+            ticker: format!("TOKEN{token}"),
+            amount: U256 { low: 100, high: 0 },
+        });
+        let mut ledgers = [PayoutLedger {
+            records: swept_payouts(Felt::THREE, 10, cold_address, &swept),
+            last_block_reconciled: 0,
+        }];
+
+        let transfers: Vec<_> = swept
+            .iter()
+            .map(|swept| ObservedTransfer {
+                tx_hash: Felt::THREE,
+                block_number: 10,
+                token: swept.token,
+                recipient: cold_address,
+                amount: swept.amount,
+            })
+            .collect();
+        assert!(reconcile_ledgers(&mut ledgers, &transfers, &[], 20).is_empty());
+        assert!(ledgers[0].records.iter().all(|record| record.checked));
+    }
+}
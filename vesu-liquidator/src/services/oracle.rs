@@ -1,21 +1,32 @@
+use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
-use bigdecimal::BigDecimal;
-use dashmap::DashMap;
+use anyhow::{Result, anyhow};
+use bigdecimal::{BigDecimal, FromPrimitive, Zero, num_bigint::BigInt};
+use dashmap::{DashMap, DashSet};
 use futures_util::future::join_all;
-use starknet::core::types::{BlockId, BlockTag, Felt, FunctionCall};
+use starknet::core::types::{BlockId, Felt, FunctionCall};
 use starknet::core::utils::{cairo_short_string_to_felt, get_selector_from_name};
 use starknet::providers::jsonrpc::HttpTransport;
 use starknet::providers::{JsonRpcClient, Provider};
-use tokio::task::JoinSet;
+use tokio::{
+    sync::{Notify, broadcast},
+    task::JoinSet,
+};
 
-use crate::config::Config;
+use crate::config::{Config, PriceSourceConfig};
+use crate::services::notifier::Notifier;
+use crate::storages::SharedStorage;
+use crate::types::{asset::StoredPrice, notification::Severity};
 use crate::utils::conversions::hex_str_to_big_decimal;
+use crate::utils::http::HttpClient;
 use crate::utils::services::Service;
+use crate::utils::shutdown::Shutdown;
+use crate::utils::unix_now;
 
-const LST_ASSETS: [&str; 3] = ["xstrk", "sstrk", "kstrk"];
+/// Liquid staking tokens, priced with their conversion rate.
+pub const LST_ASSETS: [&str; 3] = ["xstrk", "sstrk", "kstrk"];
 
 /// Aggregations possible using the Pragma Oracle contract.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -35,34 +46,233 @@ impl AggregationMode {
     }
 }
 
+/// Capacity of the channel of the price moves, lagging subscribers re-check
+/// all their positions.
+const PRICE_MOVES_CAPACITY: usize = 256;
+
+/// Latest price in dollars of an asset.
+#[derive(Debug, Default, Clone)]
+pub struct OraclePrice {
+    pub value: BigDecimal,
+    /// Unix timestamp (in seconds) of the last update, 0 if never updated.
+    pub last_updated: u64,
+    /// Price at the last move broadcasted for the asset.
+    pub reference: BigDecimal,
+}
+
 /// Map contaning the price in dollars for a list of monitored assets.
-#[derive(Default, Clone)]
-pub struct LatestOraclePrices(pub Arc<DashMap<String, BigDecimal>>);
+/// Prices older than `max_price_age` are not used to liquidate.
+/// A price move is broadcasted each time a price moves by more than
+/// `move_threshold_bps` since the last move.
+/// The assets whose price is suspect are not liquidated, see
+/// `oracle.max_jump_percent`.
+#[derive(Clone)]
+pub struct LatestOraclePrices {
+    prices: Arc<DashMap<String, OraclePrice>>,
+    max_price_age: u64,
+    move_threshold_bps: u32,
+    moves: broadcast::Sender<String>,
+    suspects: Arc<DashSet<String>>,
+    /// Notified at each update, see [`LatestOraclePrices::wait_until_ready`].
+    updated: Arc<Notify>,
+}
 
 impl LatestOraclePrices {
     pub fn from_config(config: &Config) -> Self {
         let prices = DashMap::new();
         for asset in config.assets.iter() {
-            prices.insert(asset.ticker.to_lowercase(), BigDecimal::default());
+            prices.insert(asset.ticker.to_lowercase(), OraclePrice::default());
         }
-        LatestOraclePrices(Arc::new(prices))
+        LatestOraclePrices {
+            prices: Arc::new(prices),
+            max_price_age: config.oracle.max_price_age_seconds,
+            move_threshold_bps: config.monitoring.price_move_threshold_bps,
+            moves: broadcast::channel(PRICE_MOVES_CAPACITY).0,
+            suspects: Arc::new(DashSet::new()),
+            updated: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Subscribes to the price moves, receiving the (lowercase) ticker of the
+    /// assets whose price moved.
+    pub fn subscribe_moves(&self) -> broadcast::Receiver<String> {
+        self.moves.subscribe()
+    }
+
+    /// Returns the (lowercase) tickers of the monitored assets.
+    pub fn assets(&self) -> Vec<String> {
+        self.prices
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
+    /// Sets the price of a monitored asset, unknown assets are ignored.
+    pub fn update(&self, asset: &str, value: BigDecimal) {
+        let Some(mut price) = self.prices.get_mut(asset) else {
+            return;
+        };
+        let moved = moved_beyond(&price.reference, &value, self.move_threshold_bps);
+        price.value = value;
+        price.last_updated = unix_now();
+        if moved {
+            price.reference = price.value.clone();
+            drop(price);
+            // No subscriber is not an error.
+            let _ = self.moves.send(asset.to_string());
+        }
+        self.updated.notify_waiters();
+    }
+
+    /// Restores the prices kept in the storage at startup, the ones already
+    /// updated being kept. They keep their update time, so they are only used
+    /// to liquidate while fresh enough.
+    pub fn restore(&self, stored: HashMap<String, StoredPrice>) {
+        for (asset, stored) in stored {
+            let Some(mut price) = self.prices.get_mut(&asset) else {
+                continue;
+            };
+            if price.last_updated >= stored.updated_at {
+                continue;
+            }
+            price.value = stored.value.clone();
+            price.reference = stored.value;
+            price.last_updated = stored.updated_at;
+        }
+        self.updated.notify_waiters();
+    }
+
+    /// Returns the prices updated at least once, to be kept in the storage.
+    pub fn stored(&self) -> HashMap<String, StoredPrice> {
+        self.prices
+            .iter()
+            .filter(|entry| entry.last_updated > 0)
+            .map(|entry| {
+                let stored = StoredPrice {
+                    value: entry.value.clone(),
+                    updated_at: entry.last_updated,
+                };
+                (entry.key().clone(), stored)
+            })
+            .collect()
+    }
+
+    /// Returns the (lowercase) tickers of the monitored assets without a
+    /// price yet, sorted.
+    pub fn missing(&self) -> Vec<String> {
+        let mut missing: Vec<String> = self
+            .prices
+            .iter()
+            .filter(|entry| entry.last_updated == 0)
+            .map(|entry| entry.key().clone())
+            .collect();
+        missing.sort();
+        missing
+    }
+
+    /// Waits until every monitored asset has a price, updated or restored
+    /// from the storage, for up to `timeout`. Returns false on timeout.
+    pub async fn wait_until_ready(&self, timeout: Duration) -> bool {
+        tokio::time::timeout(timeout, async {
+            loop {
+                let updated = self.updated.notified();
+                tokio::pin!(updated);
+                // Registered before the check, so no update is missed.
+                updated.as_mut().enable();
+                if self.missing().is_empty() {
+                    return;
+                }
+                updated.await;
+            }
+        })
+        .await
+        .is_ok()
+    }
+
+    /// Returns the latest prices of the monitored assets, sorted by ticker.
+    pub fn snapshot(&self) -> Vec<(String, OraclePrice)> {
+        let mut prices: Vec<(String, OraclePrice)> = self
+            .prices
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+        prices.sort_by(|a, b| a.0.cmp(&b.0));
+        prices
+    }
+
+    /// Flags the price of the asset as suspect, pausing its liquidations.
+    /// Returns false if it already was.
+    pub fn suspect(&self, asset: &str) -> bool {
+        self.suspects.insert(asset.to_string())
+    }
+
+    /// Trusts the price of the asset again. Returns false if it was not
+    /// suspect.
+    pub fn resume(&self, asset: &str) -> bool {
+        self.suspects.remove(asset).is_some()
+    }
+
+    pub fn is_suspect(&self, asset: &str) -> bool {
+        self.suspects.contains(asset)
+    }
+
+    pub fn last_updated(&self, asset: &str) -> Option<u64> {
+        self.prices.get(asset).map(|price| price.last_updated)
+    }
+
+    /// Returns the price of the asset, failing if it is older than the
+    /// configured maximum age.
+    pub fn fresh_price(&self, asset: &str) -> Result<BigDecimal> {
+        let price = self
+            .prices
+            .get(asset)
+            .ok_or_else(|| anyhow!("Price not found for {asset}"))?;
+        let age = unix_now().saturating_sub(price.last_updated);
+        anyhow::ensure!(
+            age <= self.max_price_age,
+            "Price of {asset} is stale, last updated {age}s ago"
+        );
+        Ok(price.value.clone())
     }
 }
 
+/// Price returned by the Pragma API.
+#[derive(Debug, serde::Deserialize)]
+struct PragmaApiPrice {
+    price: String,
+    decimals: i64,
+}
+
+/// Fetches the prices of the monitored assets from their configured sources,
+/// see [`crate::config::OracleConfig`].
 #[derive(Clone)]
 pub struct OracleService {
-    pragma_address: Felt,
+    config: Config,
     rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+    http_client: HttpClient,
     latest_prices: LatestOraclePrices,
+    notifier: Notifier,
+    stale_price_after: Duration,
+    started_at: Instant,
+    stale_assets: Arc<DashSet<String>>,
+    /// Price of the assets at their previous update, the jumps being measured
+    /// from it.
+    previous_prices: Arc<DashMap<String, BigDecimal>>,
+    /// Storage the prices are saved to after each update, if any.
+    storage: Option<SharedStorage>,
 }
 
 #[async_trait::async_trait]
 impl Service for OracleService {
-    async fn start(&mut self, join_set: &mut JoinSet<anyhow::Result<()>>) -> anyhow::Result<()> {
+    async fn start(
+        &mut self,
+        join_set: &mut JoinSet<anyhow::Result<()>>,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<()> {
         let service = self.clone();
         join_set.spawn(async move {
             tracing::info!("🔮 Oracle service started");
-            service.run_forever().await?;
+            service.run_forever(shutdown).await?;
             Ok(())
         });
         Ok(())
@@ -71,54 +281,268 @@ impl Service for OracleService {
 
 impl OracleService {
     pub fn new(
-        pragma_address: Felt,
+        config: Config,
         rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+        http_client: HttpClient,
         latest_prices: LatestOraclePrices,
+        notifier: Notifier,
     ) -> Self {
+        let stale_price_after = Duration::from_secs(config.notifications.stale_price_seconds);
         Self {
-            pragma_address,
+            config,
             rpc_client,
+            http_client,
             latest_prices,
+            notifier,
+            stale_price_after,
+            started_at: Instant::now(),
+            stale_assets: Arc::new(DashSet::new()),
+            previous_prices: Arc::new(DashMap::new()),
+            storage: None,
         }
     }
 
+    /// Saves the prices to the storage after each update, restored at the
+    /// next startup, see [`LatestOraclePrices::restore`].
+    pub fn with_storage(mut self, storage: SharedStorage) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
     /// Starts the oracle service that will fetch the latest oracle prices every
     /// PRICES_UPDATE_INTERVAL seconds.
-    pub async fn run_forever(self) -> Result<()> {
+    pub async fn run_forever(self, shutdown: Shutdown) -> Result<()> {
         const PRICES_UPDATE_INTERVAL: u64 = 3;
         let sleep_duration = Duration::from_secs(PRICES_UPDATE_INTERVAL);
         loop {
             self.update_prices().await?;
-            tokio::time::sleep(sleep_duration).await;
+            tokio::select! {
+                _ = shutdown.wait() => {
+                    tracing::info!("[🔮 Oracle] 🛑 Stopped fetching prices");
+                    return Ok(());
+                }
+                _ = tokio::time::sleep(sleep_duration) => {}
+            }
         }
     }
 
     /// Update all the monitored assets with their latest USD price asynchronously.
-    async fn update_prices(&self) -> Result<()> {
-        let assets: Vec<String> = self
-            .latest_prices
-            .0
-            .iter()
-            .map(|entry| entry.key().clone())
-            .collect();
+    pub async fn update_prices(&self) -> Result<()> {
+        let assets = self.latest_prices.assets();
 
         let fetch_tasks = assets.into_iter().map(|asset| async move {
-            let price = self.get_price_in_dollars(&asset).await;
+            let price = self.get_aggregated_price(&asset).await;
             (asset, price)
         });
 
         let results = join_all(fetch_tasks).await;
 
         for (asset, price_result) in results {
-            if let Ok(price) = price_result {
-                self.latest_prices.0.insert(asset, price);
+            if let Ok((price, agreeing_sources)) = price_result {
+                self.check_jump(&asset, &price, agreeing_sources);
+                self.latest_prices.update(&asset, price);
+                if self.stale_assets.remove(&asset).is_some() {
+                    self.notifier.notify(
+                        Severity::Info,
+                        format!("{} price is updated again", asset.to_uppercase()),
+                    );
+                }
+            } else {
+                self.check_staleness(&asset);
             }
         }
 
+        if let Some(storage) = &self.storage {
+            let prices = self.latest_prices.stored();
+            if let Err(e) = storage.lock().await.save_oracle_prices(&prices).await {
+                tracing::warn!(error = %e, "[🔮 Oracle] Could not save the prices");
+            }
+        }
         Ok(())
     }
 
-    async fn get_price_in_dollars(&self, base_asset: &str) -> Result<BigDecimal> {
+    /// Notifies once when the price of an asset has not been updated for too
+    /// long, by any of the price sources.
+    fn check_staleness(&self, asset: &str) {
+        let Some(last_updated) = self.latest_prices.last_updated(asset) else {
+            return;
+        };
+        let since_update = Duration::from_secs(unix_now().saturating_sub(last_updated))
+            .min(self.started_at.elapsed());
+        if since_update >= self.stale_price_after && self.stale_assets.insert(asset.to_string()) {
+            self.notifier.notify(
+                Severity::Warning,
+                format!(
+                    "Stale oracle price: {} not updated for {:?}",
+                    asset.to_uppercase(),
+                    since_update
+                ),
+            );
+        }
+    }
+
+    /// Flags the asset as suspect when its price jumps by more than
+    /// `oracle.max_jump_percent` since the previous update & no other source
+    /// agrees with it, pausing its liquidations. A suspect asset is trusted
+    /// again once two sources agree on its price.
+    fn check_jump(&self, asset: &str, price: &BigDecimal, agreeing_sources: usize) {
+        let previous = self
+            .previous_prices
+            .insert(asset.to_string(), price.clone());
+        let corroborated = agreeing_sources >= 2;
+        if self.latest_prices.is_suspect(asset) {
+            if corroborated && self.latest_prices.resume(asset) {
+                self.notifier.notify(
+                    Severity::Info,
+                    format!(
+                        "{} price corroborated by {} sources at {}, liquidations resumed",
+                        asset.to_uppercase(),
+                        agreeing_sources,
+                        price
+                    ),
+                );
+            }
+            return;
+        }
+        let (Some(max_jump), Some(previous)) = (self.config.oracle.max_jump_percent, previous)
+        else {
+            return;
+        };
+        let Some(jump) = jump_percent(&previous, price) else {
+            return;
+        };
+        if jump <= BigDecimal::from_f64(max_jump).unwrap_or_default() {
+            return;
+        }
+        if corroborated {
+            tracing::warn!(
+                "[🔮 Oracle] {} price jumped by {}% to {}, corroborated by {} sources",
+                asset.to_uppercase(),
+                jump.round(2),
+                price,
+                agreeing_sources
+            );
+        } else if self.latest_prices.suspect(asset) {
+            self.notifier.notify(
+                Severity::Warning,
+                format!(
+                    "Suspect oracle price: {} jumped by {}% from {} to {}, its liquidations are paused until the price is corroborated or resumed",
+                    asset.to_uppercase(),
+                    jump.round(2),
+                    previous,
+                    price
+                ),
+            );
+        }
+    }
+
+    /// Fetches the price of the asset from all its sources & returns their
+    /// median, with the number of sources agreeing with it. The primary
+    /// (first) source is left out when it fails or deviates too much from the
+    /// others.
+    async fn get_aggregated_price(&self, asset: &str) -> Result<(BigDecimal, usize)> {
+        let sources = self.sources_for(asset);
+        let prices = join_all(
+            sources
+                .iter()
+                .map(|source| self.get_price_from_source(source, asset)),
+        )
+        .await;
+
+        let fetched: Vec<BigDecimal> = prices.iter().flatten().cloned().collect();
+        let mut prices = prices.into_iter();
+        let primary = match prices.next() {
+            Some(Ok(price)) => Some(price),
+            Some(Err(e)) => {
+                tracing::warn!(error = %e, "[🔮 Oracle] Primary price source failed for {}, falling back", asset.to_uppercase());
+                None
+            }
+            None => None,
+        };
+        let others: Vec<BigDecimal> = prices.filter_map(Result::ok).collect();
+
+        let max_deviation = self.config.oracle.max_deviation_percent;
+        let (price, primary_used) = aggregate_prices(primary, others, max_deviation)
+            .ok_or_else(|| anyhow!("No price source available for {asset}"))?;
+        if !primary_used && sources.len() > 1 {
+            tracing::warn!(
+                "[🔮 Oracle] Primary price of {} not used, aggregated the fallback sources",
+                asset.to_uppercase()
+            );
+        }
+        let agreeing_sources = agreeing_prices(&fetched, &price, max_deviation);
+        Ok((price, agreeing_sources))
+    }
+
+    /// Returns the price sources of the asset, the default ones if it has no
+    /// specific sources.
+    fn sources_for(&self, asset: &str) -> Vec<PriceSourceConfig> {
+        self.config
+            .assets
+            .iter()
+            .find(|a| a.ticker.eq_ignore_ascii_case(asset))
+            .and_then(|a| a.price_sources.clone())
+            .unwrap_or_else(|| self.config.oracle.sources.clone())
+    }
+
+    async fn get_price_from_source(
+        &self,
+        source: &PriceSourceConfig,
+        asset: &str,
+    ) -> Result<BigDecimal> {
+        match source {
+            PriceSourceConfig::PragmaOnchain => {
+                self.get_onchain_price(asset, self.config.monitoring.oracle_block_id())
+                    .await
+            }
+            PriceSourceConfig::PragmaApi => self.get_api_price(asset).await,
+            PriceSourceConfig::Static { price } => Ok(price.clone()),
+        }
+    }
+
+    async fn get_api_price(&self, base_asset: &str) -> Result<BigDecimal> {
+        let url = format!(
+            "{}/node/v1/data/{}/usd?interval=1min&aggregation=median",
+            self.config.oracle.pragma_api_url.trim_end_matches('/'),
+            base_asset
+        );
+        let mut request = self.http_client.get(url);
+        if let Some(api_key) = &self.config.oracle.pragma_api_key {
+            request = request.header("x-api-key", api_key);
+        }
+        let response: PragmaApiPrice = self
+            .http_client
+            .send(request)
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let price = BigInt::parse_bytes(response.price.trim_start_matches("0x").as_bytes(), 16)
+            .ok_or_else(|| anyhow!("Invalid price {} for {base_asset}", response.price))?;
+        Ok(BigDecimal::new(price, response.decimals))
+    }
+
+    /// Returns the price of the asset as of a past block: its static price if
+    /// it has one, else the Pragma onchain price at the block, the offchain
+    /// API only serving the latest prices.
+    pub async fn get_historical_price(&self, asset: &str, block_number: u64) -> Result<BigDecimal> {
+        let static_price = self
+            .sources_for(asset)
+            .into_iter()
+            .find_map(|source| match source {
+                PriceSourceConfig::Static { price } => Some(price),
+                _ => None,
+            });
+        match static_price {
+            Some(price) => Ok(price),
+            None => {
+                self.get_onchain_price(asset, BlockId::Number(block_number))
+                    .await
+            }
+        }
+    }
+
+    async fn get_onchain_price(&self, base_asset: &str, block_id: BlockId) -> Result<BigDecimal> {
         let pair = format!("{}/USD", base_asset.to_ascii_uppercase());
 
         let aggregation_mode = if LST_ASSETS.contains(&base_asset) {
@@ -128,7 +552,7 @@ impl OracleService {
         };
 
         let price_request = FunctionCall {
-            contract_address: self.pragma_address,
+            contract_address: self.config.pragma_oracle_address,
             entry_point_selector: get_selector_from_name("get_data")?,
             calldata: vec![
                 Felt::ZERO,
@@ -137,10 +561,7 @@ impl OracleService {
             ],
         };
 
-        let call_result = self
-            .rpc_client
-            .call(price_request, BlockId::Tag(BlockTag::PreConfirmed))
-            .await?;
+        let call_result = self.rpc_client.call(price_request, block_id).await?;
 
         let asset_price = hex_str_to_big_decimal(
             &call_result[0].to_hex_string(),
@@ -150,3 +571,238 @@ impl OracleService {
         Ok(asset_price)
     }
 }
+
+/// Aggregates the prices of the sources of an asset: the median of all the
+/// prices, leaving out the primary one if it deviates from the median of the
+/// others by more than `max_deviation_percent`.
+/// Returns the price & whether the primary price was used.
+fn aggregate_prices(
+    primary: Option<BigDecimal>,
+    others: Vec<BigDecimal>,
+    max_deviation_percent: f64,
+) -> Option<(BigDecimal, bool)> {
+    let Some(primary) = primary else {
+        return median(others).map(|price| (price, false));
+    };
+    let Some(others_median) = median(others.clone()) else {
+        return Some((primary, true));
+    };
+
+    let deviation = if others_median == BigDecimal::zero() {
+        if primary == BigDecimal::zero() {
+            BigDecimal::zero()
+        } else {
+            BigDecimal::from(100)
+        }
+    } else {
+        ((&primary - &others_median).abs() / &others_median) * BigDecimal::from(100)
+    };
+    let max_deviation = BigDecimal::from_f64(max_deviation_percent).unwrap_or_default();
+    if deviation > max_deviation {
+        return Some((others_median, false));
+    }
+
+    let mut prices = others;
+    prices.push(primary);
+    median(prices).map(|price| (price, true))
+}
+
+/// Returns how many of the prices are within `max_deviation_percent` of the
+/// aggregated price.
+fn agreeing_prices(prices: &[BigDecimal], price: &BigDecimal, max_deviation_percent: f64) -> usize {
+    let max_deviation = BigDecimal::from_f64(max_deviation_percent).unwrap_or_default();
+    prices
+        .iter()
+        .filter(|source_price| {
+            jump_percent(price, source_price).is_some_and(|deviation| deviation <= max_deviation)
+        })
+        .count()
+}
+
+/// Returns by how many percents the price moved from the previous one, `None`
+/// if there was no previous price.
+fn jump_percent(previous: &BigDecimal, price: &BigDecimal) -> Option<BigDecimal> {
+    if *previous == BigDecimal::zero() {
+        return None;
+    }
+    Some(((price - previous).abs() / previous) * BigDecimal::from(100))
+}
+
+/// Returns true if the price moved by more than `threshold_bps` basis points
+/// from the reference price. Any price moves from an unset reference.
+fn moved_beyond(reference: &BigDecimal, price: &BigDecimal, threshold_bps: u32) -> bool {
+    if *reference == BigDecimal::zero() {
+        return *price != BigDecimal::zero();
+    }
+    let move_bps = ((price - reference).abs() / reference) * BigDecimal::from(10_000);
+    move_bps > BigDecimal::from(threshold_bps)
+}
+
+fn median(mut prices: Vec<BigDecimal>) -> Option<BigDecimal> {
+    if prices.is_empty() {
+        return None;
+    }
+    prices.sort();
+    let middle = prices.len() / 2;
+    if prices.len() % 2 == 0 {
+        Some((&prices[middle - 1] + &prices[middle]) / BigDecimal::from(2))
+    } else {
+        Some(prices[middle].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::PathBuf, str::FromStr, time::Duration};
+
+    use bigdecimal::BigDecimal;
+
+    use super::{
+        LatestOraclePrices, aggregate_prices, agreeing_prices, jump_percent, moved_beyond,
+    };
+    use crate::{
+        cli::{LiquidationMode, NetworkName},
+        config::Config,
+        types::asset::StoredPrice,
+        utils::unix_now,
+    };
+
+    fn price(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_aggregate_prices() {
+        // Median of all the sources.
+        assert_eq!(
+            aggregate_prices(Some(price("100")), vec![price("101"), price("99.5")], 5.0),
+            Some((price("100"), true))
+        );
+        assert_eq!(
+            aggregate_prices(Some(price("100")), vec![price("102")], 5.0),
+            Some((price("101"), true))
+        );
+        // Primary deviating from the fallbacks.
+        assert_eq!(
+            aggregate_prices(Some(price("150")), vec![price("101"), price("99")], 5.0),
+            Some((price("100"), false))
+        );
+        // Primary failing, or alone.
+        assert_eq!(
+            aggregate_prices(None, vec![price("101"), price("99"), price("98")], 5.0),
+            Some((price("99"), false))
+        );
+        assert_eq!(
+            aggregate_prices(Some(price("100")), vec![], 5.0),
+            Some((price("100"), true))
+        );
+        assert_eq!(aggregate_prices(None, vec![], 5.0), None);
+    }
+
+    #[test]
+    fn test_jumps() {
+        assert_eq!(
+            jump_percent(&price("100"), &price("130")),
+            Some(price("30"))
+        );
+        assert_eq!(jump_percent(&price("100"), &price("70")), Some(price("30")));
+        assert_eq!(jump_percent(&price("0"), &price("70")), None);
+
+        // A single source can't corroborate a jump.
+        assert_eq!(agreeing_prices(&[price("130")], &price("130"), 5.0), 1);
+        assert_eq!(
+            agreeing_prices(
+                &[price("130"), price("101"), price("128")],
+                &price("128"),
+                5.0
+            ),
+            2
+        );
+    }
+
+    #[test]
+    fn test_moved_beyond() {
+        assert!(moved_beyond(&price("0"), &price("2000"), 10));
+        assert!(!moved_beyond(&price("2000"), &price("2001"), 10));
+        assert!(moved_beyond(&price("2000"), &price("2003"), 10));
+        assert!(moved_beyond(&price("2000"), &price("1997"), 10));
+        assert!(!moved_beyond(&price("0"), &price("0"), 10));
+    }
+
+    #[tokio::test]
+    async fn test_prices_restored_until_ready() {
+        let config = Config::new(
+            NetworkName::Mainnet,
+            LiquidationMode::Full,
+            &PathBuf::from("./config.yaml"),
+        )
+        .unwrap();
+        let prices = LatestOraclePrices::from_config(&config);
+        assert!(!prices.wait_until_ready(Duration::from_millis(10)).await);
+
+        // Every price but the first one is restored from the storage.
+        let mut assets = prices.missing();
+        let first = assets.remove(0);
+        let stored: HashMap<String, StoredPrice> = assets
+            .iter()
+            .map(|asset| {
+                let stored = StoredPrice {
+                    value: price("2"),
+                    updated_at: unix_now() - 10,
+                };
+                (asset.clone(), stored)
+            })
+            .collect();
+        prices.restore(stored.clone());
+        assert_eq!(prices.missing(), vec![first.clone()]);
+        assert_eq!(prices.stored(), stored);
+
+        // Ready once the last one is updated.
+        let updater = prices.clone();
+        let updated = first.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            updater.update(&updated, price("3"));
+        });
+        assert!(prices.wait_until_ready(Duration::from_secs(5)).await);
+
+        // An older stored price doesn't replace an update.
+        let older = StoredPrice {
+            value: price("1"),
+            updated_at: 1,
+        };
+        prices.restore(HashMap::from([(first.clone(), older)]));
+        assert_eq!(prices.fresh_price(&first).unwrap(), price("3"));
+    }
+
+    #[test]
+    fn test_fresh_price_refuses_stale_prices() {
+        let config = Config::new(
+            NetworkName::Mainnet,
+            LiquidationMode::Full,
+            &PathBuf::from("./config.yaml"),
+        )
+        .unwrap();
+        let max_age = config.oracle.max_price_age_seconds;
+        let prices = LatestOraclePrices::from_config(&config);
+        let asset = prices.missing().remove(0);
+
+        // Never updated.
+        assert!(prices.fresh_price(&asset).is_err());
+        assert!(prices.fresh_price("unknown").is_err());
+
+        let stored = |age| StoredPrice {
+            value: price("2"),
+            updated_at: unix_now() - age,
+        };
+        prices.restore(HashMap::from([(asset.clone(), stored(max_age + 5))]));
+        let error = prices.fresh_price(&asset).unwrap_err().to_string();
+        assert!(error.starts_with(&format!("Price of {asset} is stale")));
+
+        prices.restore(HashMap::from([(asset.clone(), stored(max_age / 2))]));
+        assert_eq!(prices.fresh_price(&asset).unwrap(), price("2"));
+
+        prices.update(&asset, price("3"));
+        assert_eq!(prices.fresh_price(&asset).unwrap(), price("3"));
+    }
+}
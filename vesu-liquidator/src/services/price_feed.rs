@@ -0,0 +1,202 @@
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use bigdecimal::{BigDecimal, num_bigint::BigInt};
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::task::JoinSet;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+use crate::{
+    services::oracle::{LST_ASSETS, LatestOraclePrices},
+    utils::{services::Service, shutdown::Shutdown},
+};
+
+/// Delay before reconnecting to the price stream after a disconnection.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// Decimals of the streamed prices when not specified.
+const DEFAULT_PRICE_DECIMALS: i64 = 8;
+
+/// Prices pushed by the stream.
+#[derive(Debug, Deserialize)]
+struct PriceStreamMessage {
+    oracle_prices: Vec<StreamedPrice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamedPrice {
+    /// e.g. `ETH/USD`
+    pair_id: String,
+    /// Hex encoded price.
+    price: String,
+    #[serde(default)]
+    decimals: Option<i64>,
+}
+
+/// Updates the oracle prices in real time from a websocket stream, see
+/// [`crate::config::OracleConfig`]. The LSTs are left to the oracle service
+/// as they are priced with their conversion rate.
+#[derive(Clone)]
+pub struct PriceFeedService {
+    stream_url: String,
+    latest_prices: LatestOraclePrices,
+}
+
+#[async_trait::async_trait]
+impl Service for PriceFeedService {
+    async fn start(
+        &mut self,
+        join_set: &mut JoinSet<anyhow::Result<()>>,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<()> {
+        let service = self.clone();
+        join_set.spawn(async move {
+            tracing::info!("📡 Price feed service started");
+            service.run_forever(shutdown).await?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+impl PriceFeedService {
+    pub fn new(stream_url: String, latest_prices: LatestOraclePrices) -> Self {
+        Self {
+            stream_url,
+            latest_prices,
+        }
+    }
+
+    /// Listens to the stream, reconnecting when it drops. Stream outages are
+    /// only logged: prices keep being polled by the oracle service meanwhile.
+    pub async fn run_forever(&self, shutdown: Shutdown) -> Result<()> {
+        loop {
+            tokio::select! {
+                _ = shutdown.wait() => {
+                    tracing::info!("[📡 Price Feed] 🛑 Stopped listening to prices");
+                    return Ok(());
+                }
+
+                res = self.listen() => {
+                    if let Err(e) = res {
+                        tracing::warn!(error = %e, "[📡 Price Feed] Price stream disconnected, reconnecting in {:?}", RECONNECT_DELAY);
+                    }
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            }
+        }
+    }
+
+    /// Subscribes to the prices of the monitored assets & applies the updates
+    /// until the stream ends.
+    async fn listen(&self) -> Result<()> {
+        let (mut stream, _) = connect_async(self.stream_url.as_str()).await?;
+        let pairs: Vec<String> = self
+            .latest_prices
+            .assets()
+            .into_iter()
+            .filter(|asset| !LST_ASSETS.contains(&asset.as_str()))
+            .map(|asset| format!("{}/USD", asset.to_uppercase()))
+            .collect();
+        let subscribe = serde_json::json!({ "msg_type": "subscribe", "pairs": pairs });
+        stream.send(Message::text(subscribe.to_string())).await?;
+        tracing::info!(
+            "[📡 Price Feed] Subscribed to {} pairs on {}",
+            pairs.len(),
+            self.stream_url
+        );
+
+        while let Some(message) = stream.next().await {
+            let Message::Text(text) = message? else {
+                continue;
+            };
+            let Ok(message) = serde_json::from_str::<PriceStreamMessage>(&text) else {
+                tracing::debug!("[📡 Price Feed] Ignoring message: {}", text.as_str());
+                continue;
+            };
+            for streamed in message.oracle_prices {
+                match parse_streamed_price(&streamed) {
+                    Ok((asset, price)) => self.latest_prices.update(&asset, price),
+                    Err(e) => tracing::warn!(error = %e, "[📡 Price Feed] Invalid price"),
+                }
+            }
+        }
+        Err(anyhow!("Price stream closed"))
+    }
+}
+
+/// Returns the (lowercase) asset & the price of a streamed `<ASSET>/USD` pair.
+fn parse_streamed_price(streamed: &StreamedPrice) -> Result<(String, BigDecimal)> {
+    let asset = streamed
+        .pair_id
+        .strip_suffix("/USD")
+        .ok_or_else(|| anyhow!("Unexpected pair {}", streamed.pair_id))?;
+    let price = BigInt::parse_bytes(streamed.price.trim_start_matches("0x").as_bytes(), 16)
+        .ok_or_else(|| anyhow!("Invalid price {} for {}", streamed.price, streamed.pair_id))?;
+    let decimals = streamed.decimals.unwrap_or(DEFAULT_PRICE_DECIMALS);
+    Ok((asset.to_lowercase(), BigDecimal::new(price, decimals)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+
+    use super::{PriceStreamMessage, parse_streamed_price};
+
+    fn parse_frame(frame: &str) -> Vec<anyhow::Result<(String, BigDecimal)>> {
+        let message: PriceStreamMessage = serde_json::from_str(frame).unwrap();
+        message
+            .oracle_prices
+            .iter()
+            .map(parse_streamed_price)
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_streamed_price() {
+        let prices = parse_frame(
+            r#"{"oracle_prices": [
+                {"pair_id": "ETH/USD", "price": "0x3a35294400"},
+                {"pair_id": "USDC/USD", "price": "0xf4240", "decimals": 6}
+            ]}"#,
+        );
+        let prices: Vec<_> = prices.into_iter().map(Result::unwrap).collect();
+        assert_eq!(
+            prices,
+            vec![
+                ("eth".to_string(), BigDecimal::from_str("2500").unwrap()),
+                ("usdc".to_string(), BigDecimal::from_str("1").unwrap()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_malformed_frames() {
+        // Not a price message, ignored by the stream.
+        assert!(serde_json::from_str::<PriceStreamMessage>(r#"{"msg_type": "ack"}"#).is_err());
+        assert!(serde_json::from_str::<PriceStreamMessage>("not json").is_err());
+
+        let prices = parse_frame(
+            r#"{"oracle_prices": [
+                {"pair_id": "ETH/BTC", "price": "0x1"},
+                {"pair_id": "ETH/USD", "price": "0xnope"},
+                {"pair_id": "STRK/USD", "price": "0x2faf080"}
+            ]}"#,
+        );
+        assert_eq!(
+            prices[0].as_ref().unwrap_err().to_string(),
+            "Unexpected pair ETH/BTC"
+        );
+        assert_eq!(
+            prices[1].as_ref().unwrap_err().to_string(),
+            "Invalid price 0xnope for ETH/USD"
+        );
+        // A malformed price doesn't drop the others of the frame.
+        assert_eq!(
+            prices[2].as_ref().unwrap(),
+            &("strk".to_string(), BigDecimal::from_str("0.5").unwrap())
+        );
+    }
+}
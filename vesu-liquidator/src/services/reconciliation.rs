@@ -0,0 +1,301 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use cainome::cairo_serde::U256;
+use starknet::{
+    core::types::{BlockId, EmittedEvent, EventFilter, Felt, MaybePreConfirmedBlockWithTxHashes},
+    providers::{JsonRpcClient, Provider, jsonrpc::HttpTransport},
+};
+use tokio::{task::JoinSet, time::interval};
+
+use crate::{
+    config::{Config, TRANSFER_EVENT},
+    protocols::Protocols,
+    services::notifier::Notifier,
+    storages::SharedStorage,
+    types::{
+        ledger::{Discrepancy, ObservedTransfer, PayoutLedger, reconcile_ledgers},
+        notification::Severity,
+    },
+    utils::{services::Service, shutdown::Shutdown},
+};
+
+/// Recent blocks are left to the next round, so the payouts being recorded
+/// by the distribution service are not flagged.
+const RECENT_BLOCKS_MARGIN: u64 = 20;
+/// Events fetched per RPC call.
+const EVENTS_CHUNK_SIZE: u64 = 1_000;
+
+/// Reconciles the outgoing ERC20 transfers of the bot account with the payout
+/// ledger: transfers with no ledger entry are flagged as unexpected outflows,
+/// ledger entries with no transfer as silent failures.
+/// The fees paid to the sequencer are not payouts and are ignored, as are
+/// the transfers to the [`non_payout_recipients`].
+/// With several tenant games, their ledgers are reconciled together as they
+/// are paid by the same account.
+#[derive(Clone)]
+pub struct ReconciliationService {
+    config: Config,
+    rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+    account_address: Felt,
+    /// Storages holding the payout ledgers: the one of each tenant game & the
+    /// main storage, where the sweeps of the operators are recorded.
+    storages: Vec<SharedStorage>,
+    ignored_recipients: Arc<Vec<Felt>>,
+    notifier: Notifier,
+}
+
+#[async_trait::async_trait]
+impl Service for ReconciliationService {
+    async fn start(
+        &mut self,
+        join_set: &mut JoinSet<anyhow::Result<()>>,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<()> {
+        let service = self.clone();
+        join_set.spawn(async move {
+            tracing::info!("🧾 Reconciliation service started");
+            service.run_forever(shutdown).await?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+impl ReconciliationService {
+    pub fn new(
+        config: Config,
+        rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+        account_address: Felt,
+        storages: Vec<SharedStorage>,
+        notifier: Notifier,
+    ) -> Self {
+        Self {
+            ignored_recipients: Arc::new(non_payout_recipients(&config)),
+            config,
+            rpc_client,
+            account_address,
+            storages,
+            notifier,
+        }
+    }
+
+    /// Reconciles the new blocks every `interval_seconds`. RPC failures are
+    /// only logged: the blocks are reconciled by the next round.
+    pub async fn run_forever(&self, shutdown: Shutdown) -> Result<()> {
+        let mut reconcile_interval = interval(Duration::from_secs(
+            self.config.reconciliation.interval_seconds,
+        ));
+
+        loop {
+            tokio::select! {
+                _ = shutdown.wait() => {
+                    tracing::info!("[🧾 Reconciliation] 🛑 Stopped reconciling");
+                    return Ok(());
+                }
+
+                _ = reconcile_interval.tick() => {
+                    if let Err(e) = self.reconcile().await {
+                        tracing::warn!(error = %e, "[🧾 Reconciliation] Could not reconcile the payouts");
+                    }
+                }
+            }
+        }
+    }
+
+    async fn reconcile(&self) -> Result<()> {
+        let latest_block = self
+            .rpc_client
+            .block_number()
+            .await?
+            .saturating_sub(RECENT_BLOCKS_MARGIN);
+        let mut from_block = u64::MAX;
+        for storage in self.storages.iter() {
+            let ledger = storage.lock().await.get_payout_ledger();
+            from_block = from_block.min(first_block_to_reconcile(&ledger, latest_block));
+        }
+        if from_block > latest_block {
+            return Ok(());
+        }
+        let to_block =
+            latest_block.min(from_block + self.config.reconciliation.max_blocks_per_round - 1);
+
+        let transfers = self.outgoing_transfers(from_block, to_block).await?;
+        let transfers = self.without_fee_transfers(transfers).await?;
+
+        // The ledgers are re-read as payouts may have been recorded meanwhile.
+        let mut storages = Vec::with_capacity(self.storages.len());
+        for storage in self.storages.iter() {
+            storages.push(storage.lock().await);
+        }
+        let mut ledgers: Vec<PayoutLedger> = storages
+            .iter()
+            .map(|storage| storage.get_payout_ledger())
+            .collect();
+        let discrepancies =
+            reconcile_ledgers(&mut ledgers, &transfers, &self.ignored_recipients, to_block);
+        for (storage, ledger) in storages.iter_mut().zip(ledgers.iter()) {
+            storage.save_payout_ledger(ledger).await?;
+        }
+        drop(storages);
+
+        tracing::info!(
+            "[🧾 Reconciliation] Reconciled {} transfer(s) from block {} to {}",
+            transfers.len(),
+            from_block,
+            to_block
+        );
+        for discrepancy in discrepancies {
+            self.flag(discrepancy);
+        }
+        Ok(())
+    }
+
+    /// Returns the ERC20 transfers sent by the bot account between the two
+    /// blocks, for every configured asset.
+    async fn outgoing_transfers(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<ObservedTransfer>> {
+        let mut transfers = vec![];
+        for token in self.config.asset_map.keys() {
+            let filter = EventFilter {
+                from_block: Some(BlockId::Number(from_block)),
+                to_block: Some(BlockId::Number(to_block)),
+                address: Some(*token),
+                keys: Some(vec![vec![*TRANSFER_EVENT], vec![self.account_address]]),
+            };
+            let mut continuation_token = None;
+            loop {
+                let page = self
+                    .rpc_client
+                    .get_events(filter.clone(), continuation_token, EVENTS_CHUNK_SIZE)
+                    .await?;
+                transfers.extend(page.events.iter().filter_map(parse_transfer_event));
+                continuation_token = page.continuation_token;
+                if continuation_token.is_none() {
+                    break;
+                }
+            }
+        }
+        Ok(transfers)
+    }
+
+    /// Removes the fees, paid with a transfer to the sequencer of the block.
+    async fn without_fee_transfers(
+        &self,
+        transfers: Vec<ObservedTransfer>,
+    ) -> Result<Vec<ObservedTransfer>> {
+        let mut sequencers: HashMap<u64, Felt> = HashMap::new();
+        let mut payouts = vec![];
+        for transfer in transfers {
+            let sequencer = match sequencers.get(&transfer.block_number) {
+                Some(sequencer) => *sequencer,
+                None => {
+                    let block = self
+                        .rpc_client
+                        .get_block_with_tx_hashes(BlockId::Number(transfer.block_number))
+                        .await?;
+                    let MaybePreConfirmedBlockWithTxHashes::Block(block) = block else {
+                        anyhow::bail!("Block {} is not confirmed yet", transfer.block_number);
+                    };
+                    sequencers.insert(transfer.block_number, block.sequencer_address);
+                    block.sequencer_address
+                }
+            };
+            if transfer.recipient != sequencer {
+                payouts.push(transfer);
+            }
+        }
+        Ok(payouts)
+    }
+
+    fn flag(&self, discrepancy: Discrepancy) {
+        let message = match discrepancy {
+            Discrepancy::UnexpectedOutflow(transfer) => format!(
+                "Unexpected outflow: {} of token {:#x} sent to {:#x} with no payout recorded (tx {:#x})",
+                transfer.amount, transfer.token, transfer.recipient, transfer.tx_hash
+            ),
+            Discrepancy::MissingTransfer(record) => format!(
+                "Silent payout failure: {} of token {:#x} to {:#x} recorded with no transfer on chain (tx {:#x})",
+                record.amount, record.token, record.recipient, record.tx_hash
+            ),
+        };
+        tracing::error!("[🧾 Reconciliation] 😨 {}", message);
+        self.notifier.notify(Severity::Critical, message);
+    }
+}
+
+/// Recipients of the transfers of the bot that are not payouts: the contracts
+/// pulling the debt repaid by the liquidations, the Ekubo router the direct
+/// swaps send the sold tokens to & the configured
+/// `reconciliation.ignored_recipients`.
+fn non_payout_recipients(config: &Config) -> Vec<Felt> {
+    let mut recipients = config.ignored_recipients.clone();
+    recipients.push(config.ekubo_router_address);
+    recipients.extend(
+        Protocols::from_config(config)
+            .iter()
+            .map(|protocol| protocol.debt_spender()),
+    );
+    recipients
+}
+
+/// Returns the first block with payouts of the ledger left to reconcile.
+fn first_block_to_reconcile(ledger: &PayoutLedger, latest_block: u64) -> u64 {
+    match ledger.last_block_reconciled {
+        0 => ledger.first_unchecked_block().unwrap_or(latest_block),
+        last_block_reconciled => last_block_reconciled + 1,
+    }
+}
+
+/// Parses a `Transfer(from, to, value)` event, with `from` & `to` as keys.
+fn parse_transfer_event(event: &EmittedEvent) -> Option<ObservedTransfer> {
+    let [_, _, recipient] = event.keys.as_slice() else {
+        return None;
+    };
+    let [low, high] = event.data.as_slice() else {
+        return None;
+    };
+    Some(ObservedTransfer {
+        tx_hash: event.transaction_hash,
+        block_number: event.block_number?,
+        token: event.from_address,
+        recipient: *recipient,
+        amount: U256 {
+            low: (*low).try_into().ok()?,
+            high: (*high).try_into().ok()?,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::non_payout_recipients;
+    use crate::{
+        cli::NetworkName,
+        config::{Config, LiquidationMode},
+    };
+
+    #[test]
+    fn test_non_payout_recipients() {
+        let config = Config::new(
+            NetworkName::Mainnet,
+            LiquidationMode::Full,
+            &PathBuf::from("./config.yaml"),
+        )
+        .unwrap();
+        let recipients = non_payout_recipients(&config);
+        assert!(recipients.contains(&config.ekubo_router_address));
+        assert!(recipients.contains(&config.liquidate_address));
+        assert!(
+            config
+                .ignored_recipients
+                .iter()
+                .all(|recipient| recipients.contains(recipient))
+        );
+    }
+}
@@ -0,0 +1,1362 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Result;
+use bigdecimal::num_bigint::BigUint;
+use cainome::cairo_serde::U256;
+use futures_util::lock::Mutex;
+use starknet::core::types::{Call, Felt};
+
+use crate::{
+    config::{Config, DistributionStrategy, PUBLISH_ROOT_SELECTOR},
+    services::notifier::Notifier,
+    types::{
+        accounting::Valuator,
+        claims::{Claim, MerkleTree},
+        distribution::{
+            EarningsSplit, HighestScoreCheck, HighestScoreGuard, PendingDistribution,
+            decayed_score, draw_raffle, proportional_share, remainder,
+        },
+        game::{GameMirror, GameState},
+        notification::Severity,
+    },
+    utils::{
+        constants::U256_ZERO,
+        conversions::u256_to_big_uint,
+        http::HttpClient,
+        torii::{PlayerScoreModel, RedeemModel, ToriiApi, ToriiClient},
+        unix_now,
+    },
+};
+
+/// Share of a player sent by transfer to its payout address.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerPayout {
+    pub player: Felt,
+    pub recipient: Felt,
+    pub token: Felt,
+    pub amount: U256,
+}
+
+/// Payout planned by a [`RewardStrategy`], sent in a single multicall by the
+/// distribution service.
+#[derive(Debug, Default)]
+pub struct RewardPlan {
+    /// Shares of the players paid by transfer, the small ones being held as
+    /// dust by the distribution service.
+    pub payouts: Vec<PlayerPayout>,
+    /// Other ERC20 transfers, as `(token, recipient, amount)`.
+    pub transfers: Vec<(Felt, Felt, U256)>,
+    /// Calls sent after the transfers.
+    pub calls: Vec<Call>,
+    /// Root & claims published on the claims contract, if any.
+    pub claims: Option<(Felt, Vec<Claim>)>,
+    /// Rewards of the players not paid by transfer, as `(player, token,
+    /// amount)`.
+    pub rewards: Vec<(Felt, Felt, U256)>,
+    /// Players whose redeem entry is consumed by the payout.
+    pub redeemed_players: Vec<Felt>,
+    pub highest_score: u128,
+    /// Who was paid, for the notification.
+    pub summary: String,
+}
+
+impl RewardPlan {
+    /// Returns the plan with the payouts of a player in a token & the
+    /// transfers of a token to a recipient merged, e.g. when a batch of
+    /// liquidations is won by the same player, so each is sent once.
+    pub fn merged(mut self) -> Self {
+        let mut payouts: Vec<PlayerPayout> = vec![];
+        for payout in self.payouts {
+            match payouts.iter_mut().find(|merged| {
+                (merged.player, merged.recipient, merged.token)
+                    == (payout.player, payout.recipient, payout.token)
+            }) {
+                Some(merged) => merged.amount = merged.amount + payout.amount,
+                None => payouts.push(payout),
+            }
+        }
+        self.payouts = payouts;
+        self.transfers = merge_amounts(self.transfers);
+        self.rewards = merge_amounts(self.rewards);
+        self
+    }
+
+    /// Returns the share of the players per token, paid by transfer or not.
+    pub fn players_per_token(&self) -> HashMap<Felt, BigUint> {
+        sum_per_token(
+            self.payouts
+                .iter()
+                .map(|payout| (payout.token, payout.amount))
+                .chain(
+                    self.rewards
+                        .iter()
+                        .map(|(_, token, amount)| (*token, *amount)),
+                ),
+        )
+    }
+
+    /// Checks the amounts of the plan against the `earnings` per token before
+    /// it is sent, so a bug, e.g. in the parsing of the liquidation events,
+    /// can't drain the balance of the bot: neither the players' share nor all
+    /// the amounts paid can exceed the earnings.
+    pub fn check_amounts(&self, earnings: &HashMap<Felt, U256>) -> Result<()> {
+        let paid = sum_per_token(
+            self.payouts
+                .iter()
+                .map(|payout| (payout.token, payout.amount))
+                .chain(
+                    self.transfers
+                        .iter()
+                        .map(|(token, _, amount)| (*token, *amount)),
+                ),
+        );
+        for (what, amounts) in [
+            ("players' share", self.players_per_token()),
+            ("payouts", paid),
+        ] {
+            for (token, amount) in amounts {
+                let total = u256_to_big_uint(&earnings.get(&token).copied().unwrap_or(U256_ZERO));
+                anyhow::ensure!(
+                    amount <= total,
+                    "The {what} in token {token:#x} ({amount}) exceeds the earnings ({total})"
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sums the amounts per token, without overflowing.
+fn sum_per_token(amounts: impl Iterator<Item = (Felt, U256)>) -> HashMap<Felt, BigUint> {
+    let mut sums: HashMap<Felt, BigUint> = HashMap::new();
+    for (token, amount) in amounts {
+        *sums.entry(token).or_default() += u256_to_big_uint(&amount);
+    }
+    sums
+}
+
+/// Sums the amounts of the same `(a, b)` pair, in the order of their first
+/// occurrence.
+fn merge_amounts(amounts: Vec<(Felt, Felt, U256)>) -> Vec<(Felt, Felt, U256)> {
+    let mut merged: Vec<(Felt, Felt, U256)> = vec![];
+    for (a, b, amount) in amounts {
+        match merged.iter_mut().find(|(ma, mb, _)| (*ma, *mb) == (a, b)) {
+            Some((_, _, total)) => *total = *total + amount,
+            None => merged.push((a, b, amount)),
+        }
+    }
+    merged
+}
+
+/// Decides who receives the pending earnings. New game modes are added as new
+/// strategies, selected with `distribution.strategy`.
+#[async_trait::async_trait]
+pub trait RewardStrategy: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Plans the payout of the pending earnings, `None` if nothing can be
+    /// distributed yet.
+    async fn plan(
+        &self,
+        context: &RewardContext,
+        pending: &PendingDistribution,
+    ) -> Result<Option<RewardPlan>>;
+}
+
+/// Returns the strategy configured with `distribution.strategy`.
+pub fn reward_strategy(config: &Config) -> Box<dyn RewardStrategy> {
+    let claims = config.claims_address.map(ClaimsStrategy);
+    match config.distribution.strategy {
+        DistributionStrategy::Auto => Box::new(AutoStrategy {
+            claims,
+            queue_size_threshold: config.claims.queue_size_threshold,
+        }),
+        DistributionStrategy::Transfers => Box::new(QueueStrategy),
+        // Validated to have a claims address.
+        DistributionStrategy::Claims => match claims {
+            Some(claims) => Box::new(claims),
+            None => Box::new(QueueStrategy),
+        },
+        DistributionStrategy::Leaderboard => Box::new(LeaderboardStrategy),
+        DistributionStrategy::Raffle => Box::new(RaffleStrategy),
+        DistributionStrategy::Epoch => Box::new(EpochStrategy),
+    }
+}
+
+/// Access to the game state for the strategies: Torii, with the local mirror
+/// as fallback during short outages.
+#[derive(Clone)]
+pub struct RewardContext {
+    pub config: Config,
+    torii: Arc<dyn ToriiApi>,
+    mirror: GameMirror,
+    score_guard: Arc<Mutex<HighestScoreGuard>>,
+    /// Creation time of the accounts checked for eligibility, by player.
+    account_created_at: Arc<Mutex<HashMap<Felt, u64>>>,
+    notifier: Notifier,
+    /// Values the shares in USD for the logs.
+    valuator: Valuator,
+}
+
+impl RewardContext {
+    pub fn new(
+        config: Config,
+        http_client: HttpClient,
+        mirror: GameMirror,
+        notifier: Notifier,
+        valuator: Valuator,
+    ) -> Self {
+        let torii = Arc::new(ToriiClient::from_config(http_client, &config));
+        Self::with_torii(config, torii, mirror, notifier, valuator)
+    }
+
+    /// Reads the game state from the given Torii API instead of a client of
+    /// the Torii endpoints of the config.
+    pub fn with_torii(
+        config: Config,
+        torii: Arc<dyn ToriiApi>,
+        mirror: GameMirror,
+        notifier: Notifier,
+        valuator: Valuator,
+    ) -> Self {
+        let score_guard = HighestScoreGuard::new(
+            config.distribution.max_highest_score_jump,
+            mirror.snapshot().highest_score,
+        );
+        Self {
+            torii,
+            config,
+            mirror,
+            score_guard: Arc::new(Mutex::new(score_guard)),
+            account_created_at: Arc::new(Mutex::new(HashMap::new())),
+            notifier,
+            valuator,
+        }
+    }
+
+    /// Reads the redeem queue & the highest score from Torii, falling back
+    /// to the local mirror during short Torii outages. The players not
+    /// eligible to a payout are skipped, see [`Self::is_eligible`]. `None` if
+    /// the highest score is not trusted, see [`Self::is_highest_score_trusted`].
+    pub async fn read_queue(&self) -> Result<Option<(Vec<RedeemModel>, Option<u128>)>> {
+        let from_torii = async {
+            if let Some(state) = self.cached_mirror() {
+                return anyhow::Ok((state.redeem_queue, state.highest_score));
+            }
+            let queue = self.torii.get_redeem_queue().await?;
+            let highest_score = self.torii.get_highest_score().await?;
+            anyhow::Ok((queue, highest_score))
+        };
+
+        let (queue, highest_score) = match from_torii.await {
+            Ok(queue) => queue,
+            Err(e) => {
+                let state = self.fresh_mirror().ok_or(e)?;
+                tracing::warn!(
+                    "[💸 Distribution] Torii unavailable, using the game state mirrored at {}",
+                    state.synced_at
+                );
+                (state.redeem_queue, state.highest_score)
+            }
+        };
+        if let Some(score) = highest_score {
+            if !self.is_highest_score_trusted(score).await {
+                return Ok(None);
+            }
+        }
+
+        let mut eligible_queue = Vec::with_capacity(queue.len());
+        for mut redeemer in queue {
+            if self.is_eligible(&redeemer).await? {
+                redeemer.score = self.effective_score(&redeemer);
+                eligible_queue.push(redeemer);
+            }
+        }
+        Ok(Some((eligible_queue, highest_score)))
+    }
+
+    /// Returns true if the player meets the eligibility rules of the
+    /// distribution: `distribution.min_score` &
+    /// `distribution.min_account_age_seconds`, to blunt sybil farming.
+    async fn is_eligible(&self, redeemer: &RedeemModel) -> Result<bool> {
+        let distribution = &self.config.distribution;
+        if redeemer.score < distribution.min_score {
+            tracing::info!(
+                player_address = %redeemer.player,
+                "[💸 Distribution] Skipping player {} with a score of {} below the min score",
+                redeemer.player,
+                redeemer.score
+            );
+            return Ok(false);
+        }
+        if distribution.min_account_age_seconds == 0 {
+            return Ok(true);
+        }
+
+        let player = Felt::from_hex(&redeemer.player)?;
+        let cached = self.account_created_at.lock().await.get(&player).copied();
+        let created_at = match cached {
+            Some(created_at) => Some(created_at),
+            None => {
+                let created_at = self.torii.get_account_created_at(player).await?;
+                if let Some(created_at) = created_at {
+                    self.account_created_at
+                        .lock()
+                        .await
+                        .insert(player, created_at);
+                }
+                created_at
+            }
+        };
+        let age = created_at
+            .map(|created_at| unix_now().saturating_sub(created_at))
+            .unwrap_or(0);
+        if age < distribution.min_account_age_seconds {
+            tracing::info!(
+                player_address = %redeemer.player,
+                "[💸 Distribution] Skipping player {} with an account of {}s, younger than the min age",
+                redeemer.player,
+                age
+            );
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Returns the score of a redeem entry after its decay, see
+    /// `distribution.score_decay_after_seconds`.
+    fn effective_score(&self, redeemer: &RedeemModel) -> u128 {
+        let distribution = &self.config.distribution;
+        if redeemer.timestamp == 0 {
+            return redeemer.score;
+        }
+        let age = unix_now().saturating_sub(redeemer.timestamp);
+        let score = decayed_score(
+            redeemer.score,
+            age,
+            distribution.score_decay_after_seconds,
+            distribution.score_decay_half_life_seconds,
+        );
+        if score < redeemer.score {
+            tracing::info!(
+                player_address = %redeemer.player,
+                "[💸 Distribution] Score of player {} decayed from {} to {} after {}s in queue",
+                redeemer.player,
+                redeemer.score,
+                score,
+                age
+            );
+        }
+        score
+    }
+
+    /// Reads the `distribution.leaderboard_size` best players from Torii, of
+    /// the running season with `distribution.seasons`.
+    pub async fn read_leaderboard(&self) -> Result<Vec<PlayerScoreModel>> {
+        if let Some(state) = self.cached_mirror() {
+            if !state.leaderboard.is_empty() {
+                return Ok(state.leaderboard);
+            }
+        }
+        self.torii
+            .get_leaderboard(
+                self.config.distribution.leaderboard_size,
+                self.active_season(),
+            )
+            .await
+    }
+
+    /// Returns the season running now with `distribution.seasons`, read from
+    /// the seasons mirrored from Torii.
+    pub fn active_season(&self) -> Option<u32> {
+        if !self.config.distribution.seasons {
+            return None;
+        }
+        self.mirror.snapshot().active_season(unix_now())
+    }
+
+    /// Returns false & alerts if the highest score jumped too much since the
+    /// last distribution, see [`HighestScoreGuard`].
+    pub async fn is_highest_score_trusted(&self, score: u128) -> bool {
+        let HighestScoreCheck::Held {
+            trusted,
+            first_seen,
+        } = self.score_guard.lock().await.check(score)
+        else {
+            return true;
+        };
+
+        tracing::warn!(
+            "[💸 Distribution] Highest score jumped from {} to {}, keeping earnings pending.",
+            trusted,
+            score
+        );
+        if first_seen {
+            self.notifier.notify(
+                Severity::Critical,
+                format!(
+                    "Distributions held: the highest score jumped from {trusted} to {score}. \
+                     Restart the bot to accept it once checked."
+                ),
+            );
+        }
+        false
+    }
+
+    /// Returns the mirrored game state if it is recent enough to be read
+    /// instead of Torii, see `game_sync.cache_ttl_seconds`.
+    fn cached_mirror(&self) -> Option<GameState> {
+        let ttl = self.config.game_sync.cache_ttl_seconds;
+        if ttl == 0 {
+            return None;
+        }
+        let state = self.mirror.snapshot();
+        state.is_fresh(ttl, unix_now()).then_some(state)
+    }
+
+    /// Returns the mirrored game state if it is recent enough to be trusted.
+    fn fresh_mirror(&self) -> Option<GameState> {
+        let state = self.mirror.snapshot();
+        let max_age = self.config.game_sync.max_mirror_age_seconds;
+        state.is_fresh(max_age, unix_now()).then_some(state)
+    }
+
+    /// Returns the address where the player's share must be sent: the payout
+    /// address registered by the player if any, the player's account otherwise.
+    pub async fn resolve_payout_address(&self, player: &str) -> Result<Felt> {
+        let player = Felt::from_hex(player)?;
+        // The mirror holds the payout addresses of the players it synced.
+        let cached = self
+            .cached_mirror()
+            .filter(|state| state.is_synced_player(player));
+        let payout = match cached {
+            Some(state) => Ok(state.payout_address(player).cloned()),
+            None => self.torii.get_payout_address(player).await,
+        };
+        let payout = match payout {
+            Ok(payout) => payout,
+            Err(e) => {
+                let state = self.fresh_mirror().ok_or(e)?;
+                tracing::warn!(
+                    "[💸 Distribution] Torii unavailable, using mirrored payout address"
+                );
+                state.payout_address(player).cloned()
+            }
+        };
+        let Some(payout) = payout else {
+            return Ok(player);
+        };
+
+        match payout.verified_for(player)? {
+            Some(payout_address) => {
+                tracing::info!(
+                    player_address = format!("{player:#x}"),
+                    payout_address = format!("{payout_address:#x}"),
+                    "[💸 Distribution] Paying player {:#x} on its payout address {:#x}",
+                    player,
+                    payout_address
+                );
+                Ok(payout_address)
+            }
+            None => {
+                tracing::warn!(
+                    player_address = format!("{player:#x}"),
+                    "[💸 Distribution] Ignoring payout address of player {:#x} set by {}",
+                    player,
+                    payout.set_by
+                );
+                Ok(player)
+            }
+        }
+    }
+
+    /// Returns the transfer of the operator fee, if any.
+    pub fn operator_transfer(&self, token: Felt, amount: U256) -> Option<(Felt, Felt, U256)> {
+        let operator_address = self.config.operator_address?;
+        (amount != U256_ZERO).then_some((token, operator_address, amount))
+    }
+
+    /// Logs how the earnings in `token` are split, valued in USD.
+    pub fn log_split(&self, token: Felt, players: &U256, world: &U256, operator: &U256) {
+        let players = self.valuator.value(token, players);
+        let world = self.valuator.value(token, world);
+        let operator = self.valuator.value(token, operator);
+        tracing::info!(
+            token = format!("{token:#x}"),
+            players_amount = %players.amount,
+            players_usd = %players.usd_string(),
+            world_amount = %world.amount,
+            world_usd = %world.usd_string(),
+            operator_amount = %operator.amount,
+            operator_usd = %operator.usd_string(),
+            "[💸 Distribution] Token {:#x} - Players Share: {}, World Share: {}, Operator Fee: {}",
+            token,
+            players,
+            world,
+            operator
+        );
+    }
+}
+
+/// Pays the next player of the redeem queue: the player's share goes to the
+/// player, weighted by its score over the highest score, the remainder to the
+/// world contract.
+pub struct QueueStrategy;
+
+#[async_trait::async_trait]
+impl RewardStrategy for QueueStrategy {
+    fn name(&self) -> &'static str {
+        "queue"
+    }
+
+    async fn plan(
+        &self,
+        context: &RewardContext,
+        pending: &PendingDistribution,
+    ) -> Result<Option<RewardPlan>> {
+        let Some((queue, highest_score)) = context.read_queue().await? else {
+            return Ok(None);
+        };
+        self.plan_for_queue(context, pending, queue, highest_score)
+            .await
+    }
+}
+
+impl QueueStrategy {
+    async fn plan_for_queue(
+        &self,
+        context: &RewardContext,
+        pending: &PendingDistribution,
+        queue: Vec<RedeemModel>,
+        highest_score: Option<u128>,
+    ) -> Result<Option<RewardPlan>> {
+        let Some(redeemer) = queue.into_iter().next() else {
+            tracing::warn!("[💸 Distribution] No player in queue, keeping earnings pending.");
+            return Ok(None);
+        };
+        tracing::info!(
+            player_address = %redeemer.player,
+            "[💸 Distribution] Found player in queue: {}",
+            redeemer.player
+        );
+
+        // Fallback to player's score if no global high score.
+        let highest_score = highest_score.unwrap_or(redeemer.score);
+        if highest_score == 0 {
+            tracing::warn!("[💸 Distribution] Highest score is 0, cannot calculate proportion.");
+            return Ok(None);
+        }
+
+        let player = Felt::from_hex(&redeemer.player)?;
+        let player_address = context.resolve_payout_address(&redeemer.player).await?;
+        let world_address = context.config.world_address;
+
+        let mut plan = RewardPlan {
+            redeemed_players: vec![player],
+            highest_score,
+            summary: format!("player {player_address:#x}"),
+            ..Default::default()
+        };
+        for (token, total_earnings) in pending.total_per_token() {
+            // The player's part is weighted by (player_score / highest_score).
+            let split = EarningsSplit::new(
+                &context.config.distribution,
+                total_earnings,
+                redeemer.score,
+                highest_score,
+            );
+
+            let total = context.valuator.value(token, &total_earnings);
+            tracing::info!(
+                token = format!("{token:#x}"),
+                amount = %total.amount,
+                usd = %total.usd_string(),
+                "[💸 Distribution] Token {:#x} - Player Score: {}, Highest Score: {}, Total Earnings: {}",
+                token,
+                redeemer.score,
+                highest_score,
+                total
+            );
+            context.log_split(token, &split.player, &split.world, &split.operator);
+
+            plan.payouts.push(PlayerPayout {
+                player,
+                recipient: player_address,
+                token,
+                amount: split.player,
+            });
+            plan.transfers.push((token, world_address, split.world));
+            plan.transfers
+                .extend(context.operator_transfer(token, split.operator));
+        }
+        Ok(Some(plan))
+    }
+}
+
+/// Pays every player of the queue at once: the players' part of the earnings
+/// is split evenly between the `n` queued players, each keeping the part of
+/// its slot matching its score, the rest going to the world. A player gets
+/// what the head of the queue gets with [`QueueStrategy`], on `1/n` of the
+/// earnings: paying each of them `score / highest_score` of the whole part
+/// would exceed the earnings as soon as two players are queued, and the
+/// queue is cleared in a single transaction instead of `n` distributions.
+/// Rather than one transfer per player, the players' shares are sent to the
+/// claims contract along with the merkle root of the claims, and the players
+/// claim them with the proofs served by the API.
+pub struct ClaimsStrategy(Felt);
+
+#[async_trait::async_trait]
+impl RewardStrategy for ClaimsStrategy {
+    fn name(&self) -> &'static str {
+        "claims"
+    }
+
+    async fn plan(
+        &self,
+        context: &RewardContext,
+        pending: &PendingDistribution,
+    ) -> Result<Option<RewardPlan>> {
+        let Some((queue, highest_score)) = context.read_queue().await? else {
+            return Ok(None);
+        };
+        if queue.is_empty() {
+            tracing::warn!("[💸 Distribution] No player in queue, keeping earnings pending.");
+            return Ok(None);
+        }
+        self.plan_for_queue(context, pending, &queue, highest_score)
+            .await
+    }
+}
+
+impl ClaimsStrategy {
+    async fn plan_for_queue(
+        &self,
+        context: &RewardContext,
+        pending: &PendingDistribution,
+        queue: &[RedeemModel],
+        highest_score: Option<u128>,
+    ) -> Result<Option<RewardPlan>> {
+        let claims_address = self.0;
+        let highest_score = highest_score
+            .or(queue.iter().map(|redeemer| redeemer.score).max())
+            .unwrap_or(0);
+        if highest_score == 0 {
+            tracing::warn!("[💸 Distribution] Highest score is 0, cannot calculate proportion.");
+            return Ok(None);
+        }
+        tracing::info!(
+            "[💸 Distribution] {} players in queue, distributing through the claims contract",
+            queue.len()
+        );
+
+        let mut recipients = Vec::with_capacity(queue.len());
+        for redeemer in queue {
+            let player = Felt::from_hex(&redeemer.player)?;
+            let payout_address = context.resolve_payout_address(&redeemer.player).await?;
+            recipients.push((player, payout_address, redeemer.score));
+        }
+
+        let mut claims = vec![];
+        let mut plan = RewardPlan {
+            redeemed_players: recipients.iter().map(|(player, _, _)| *player).collect(),
+            highest_score,
+            ..Default::default()
+        };
+        for (token, total_earnings) in pending.total_per_token() {
+            // The players' part of the split, before weighting it by score.
+            let split = EarningsSplit::new(&context.config.distribution, total_earnings, 1, 1);
+            let player_part = proportional_share(split.player, 1, queue.len() as u128);
+            let mut players_total = U256_ZERO;
+            for (player, recipient, score) in recipients.iter() {
+                let amount = proportional_share(player_part, *score, highest_score);
+                if amount == U256_ZERO {
+                    continue;
+                }
+                players_total = players_total + amount;
+                claims.push(Claim {
+                    recipient: *recipient,
+                    token,
+                    amount,
+                });
+                plan.rewards.push((*player, token, amount));
+            }
+            let world_share = world_share(token, total_earnings, split.operator, players_total)?;
+
+            context.log_split(token, &players_total, &world_share, &split.operator);
+            plan.transfers.push((token, claims_address, players_total));
+            plan.transfers
+                .push((token, context.config.world_address, world_share));
+            plan.transfers
+                .extend(context.operator_transfer(token, split.operator));
+        }
+
+        let root = MerkleTree::new(claims.iter().map(Claim::leaf).collect()).root();
+        tracing::info!(
+            "[💸 Distribution] Publishing claims root {:#x} for {} claim(s)...",
+            root,
+            claims.len()
+        );
+        plan.calls.push(Call {
+            to: claims_address,
+            selector: *PUBLISH_ROOT_SELECTOR,
+            calldata: vec![root],
+        });
+        plan.summary = format!(
+            "{} players through the claims root {:#x}",
+            queue.len(),
+            root
+        );
+        plan.claims = Some((root, claims));
+        Ok(Some(plan))
+    }
+}
+
+/// Pays the queue through the claims contract once it holds at least
+/// `claims.queue_size_threshold` players, the next player by transfers
+/// otherwise.
+pub struct AutoStrategy {
+    claims: Option<ClaimsStrategy>,
+    queue_size_threshold: usize,
+}
+
+#[async_trait::async_trait]
+impl RewardStrategy for AutoStrategy {
+    fn name(&self) -> &'static str {
+        "auto"
+    }
+
+    async fn plan(
+        &self,
+        context: &RewardContext,
+        pending: &PendingDistribution,
+    ) -> Result<Option<RewardPlan>> {
+        let Some((queue, highest_score)) = context.read_queue().await? else {
+            return Ok(None);
+        };
+        match &self.claims {
+            Some(claims) if queue.len() >= self.queue_size_threshold => {
+                claims
+                    .plan_for_queue(context, pending, &queue, highest_score)
+                    .await
+            }
+            _ => {
+                QueueStrategy
+                    .plan_for_queue(context, pending, queue, highest_score)
+                    .await
+            }
+        }
+    }
+}
+
+/// Pays the best players of the leaderboard regardless of the redeem queue,
+/// each receiving a part of the players' share proportional to its score.
+pub struct LeaderboardStrategy;
+
+#[async_trait::async_trait]
+impl RewardStrategy for LeaderboardStrategy {
+    fn name(&self) -> &'static str {
+        "leaderboard"
+    }
+
+    async fn plan(
+        &self,
+        context: &RewardContext,
+        pending: &PendingDistribution,
+    ) -> Result<Option<RewardPlan>> {
+        let leaderboard = context.read_leaderboard().await?;
+        let best_score = leaderboard.first().map(|entry| entry.score).unwrap_or(0);
+        if !context.is_highest_score_trusted(best_score).await {
+            return Ok(None);
+        }
+        let total_score: u128 = leaderboard
+            .iter()
+            .fold(0_u128, |total, entry| total.saturating_add(entry.score));
+        if total_score == 0 {
+            tracing::warn!(
+                "[💸 Distribution] No score on the leaderboard, keeping earnings pending."
+            );
+            return Ok(None);
+        }
+        tracing::info!(
+            "[💸 Distribution] Distributing to the {} best players of the leaderboard",
+            leaderboard.len()
+        );
+
+        let mut recipients = Vec::with_capacity(leaderboard.len());
+        for entry in leaderboard.iter().filter(|entry| entry.score > 0) {
+            let player = Felt::from_hex(&entry.player)?;
+            let payout_address = context.resolve_payout_address(&entry.player).await?;
+            recipients.push((player, payout_address, entry.score));
+        }
+
+        let mut plan = RewardPlan {
+            highest_score: best_score,
+            summary: format!("{} players of the leaderboard", recipients.len()),
+            ..Default::default()
+        };
+        plan_score_weighted_transfers(context, pending, &recipients, total_score, &mut plan)?;
+        Ok(Some(plan))
+    }
+}
+
+/// Adds the transfers paying each recipient, as `(player, payout_address,
+/// score)`, a part of the players' share proportional to its score over
+/// `total_score`, the remainder going to the world.
+fn plan_score_weighted_transfers(
+    context: &RewardContext,
+    pending: &PendingDistribution,
+    recipients: &[(Felt, Felt, u128)],
+    total_score: u128,
+    plan: &mut RewardPlan,
+) -> Result<()> {
+    for (token, total_earnings) in pending.total_per_token() {
+        let split = EarningsSplit::new(&context.config.distribution, total_earnings, 1, 1);
+        let mut players_total = U256_ZERO;
+        for (player, payout_address, score) in recipients.iter() {
+            let amount = proportional_share(split.player, *score, total_score);
+            if amount == U256_ZERO {
+                continue;
+            }
+            players_total = players_total + amount;
+            plan.payouts.push(PlayerPayout {
+                player: *player,
+                recipient: *payout_address,
+                token,
+                amount,
+            });
+        }
+        let world_share = world_share(token, total_earnings, split.operator, players_total)?;
+
+        context.log_split(token, &players_total, &world_share, &split.operator);
+        plan.transfers
+            .push((token, context.config.world_address, world_share));
+        plan.transfers
+            .extend(context.operator_transfer(token, split.operator));
+    }
+    Ok(())
+}
+
+/// Returns the share of the world, what is left of the earnings in `token`
+/// once the operator & the players are paid. Fails if they would receive more
+/// than the earnings, instead of wrapping the share around.
+fn world_share(token: Felt, total_earnings: U256, operator: U256, players: U256) -> Result<U256> {
+    remainder(total_earnings, &[operator, players]).ok_or_else(|| {
+        anyhow::anyhow!(
+            "The world share of token {token:#x} underflows: the players & the operator would receive more than the earnings"
+        )
+    })
+}
+
+/// Awards the players' share of each liquidation to a single player of the
+/// queue, drawn with a probability proportional to its score. The draw is
+/// seeded with the liquidation tx hash & its inputs are logged, so anyone can
+/// verify it, see [`draw_raffle`].
+pub struct RaffleStrategy;
+
+#[async_trait::async_trait]
+impl RewardStrategy for RaffleStrategy {
+    fn name(&self) -> &'static str {
+        "raffle"
+    }
+
+    async fn plan(
+        &self,
+        context: &RewardContext,
+        pending: &PendingDistribution,
+    ) -> Result<Option<RewardPlan>> {
+        let Some((queue, highest_score)) = context.read_queue().await? else {
+            return Ok(None);
+        };
+        let scores: Vec<u128> = queue.iter().map(|redeemer| redeemer.score).collect();
+        let mut plan = RewardPlan {
+            highest_score: highest_score.or(scores.iter().max().copied()).unwrap_or(0),
+            ..Default::default()
+        };
+        let mut payout_addresses = HashMap::new();
+        for earnings in pending.earnings.iter() {
+            let Some(draw) = draw_raffle(earnings.liquidation_tx, &scores) else {
+                tracing::warn!("[💸 Distribution] No score in queue, keeping earnings pending.");
+                return Ok(None);
+            };
+            let winner = &queue[draw.winner];
+            tracing::info!(
+                tx_hash = format!("{:#064x}", earnings.liquidation_tx),
+                player_address = %winner.player,
+                "[💸 Distribution] 🎲 Raffle of liquidation {:#x}: seed {:#x}, ticket {} of {} over {} players, won by {} (score {})",
+                earnings.liquidation_tx,
+                draw.seed,
+                draw.ticket,
+                draw.total_score,
+                queue.len(),
+                winner.player,
+                winner.score
+            );
+
+            let player = Felt::from_hex(&winner.player)?;
+            let payout_address = match payout_addresses.get(&player) {
+                Some(payout_address) => *payout_address,
+                None => {
+                    let payout_address = context.resolve_payout_address(&winner.player).await?;
+                    payout_addresses.insert(player, payout_address);
+                    plan.redeemed_players.push(player);
+                    payout_address
+                }
+            };
+            let split = EarningsSplit::new(&context.config.distribution, earnings.amount, 1, 1);
+            plan.payouts.push(PlayerPayout {
+                player,
+                recipient: payout_address,
+                token: earnings.token,
+                amount: split.player,
+            });
+            plan.transfers
+                .push((earnings.token, context.config.world_address, split.world));
+            plan.transfers
+                .extend(context.operator_transfer(earnings.token, split.operator));
+        }
+        plan.summary = format!("{} raffle winner(s)", plan.redeemed_players.len());
+        Ok(Some(plan))
+    }
+}
+
+/// Settles the earnings accrued by the bot during an epoch at once, between
+/// all the players of the queue, each receiving a part of the players' share
+/// proportional to its score. The settlement waits for the end of the epoch,
+/// see [`PendingDistribution::is_ready`].
+pub struct EpochStrategy;
+
+#[async_trait::async_trait]
+impl RewardStrategy for EpochStrategy {
+    fn name(&self) -> &'static str {
+        "epoch"
+    }
+
+    async fn plan(
+        &self,
+        context: &RewardContext,
+        pending: &PendingDistribution,
+    ) -> Result<Option<RewardPlan>> {
+        let Some((queue, highest_score)) = context.read_queue().await? else {
+            return Ok(None);
+        };
+        let total_score: u128 = queue.iter().fold(0_u128, |total, redeemer| {
+            total.saturating_add(redeemer.score)
+        });
+        if total_score == 0 {
+            tracing::warn!("[💸 Distribution] No score in queue, keeping earnings pending.");
+            return Ok(None);
+        }
+        let epoch = pending.opened_at / context.config.distribution.epoch_seconds;
+        tracing::info!(
+            "[💸 Distribution] Settling epoch {} ({} liquidation(s)) between {} players",
+            epoch,
+            pending.earnings.len(),
+            queue.len()
+        );
+
+        let mut recipients = Vec::with_capacity(queue.len());
+        for redeemer in queue.iter().filter(|redeemer| redeemer.score > 0) {
+            let player = Felt::from_hex(&redeemer.player)?;
+            let payout_address = context.resolve_payout_address(&redeemer.player).await?;
+            recipients.push((player, payout_address, redeemer.score));
+        }
+
+        let mut plan = RewardPlan {
+            redeemed_players: recipients.iter().map(|(player, _, _)| *player).collect(),
+            highest_score: highest_score
+                .or(queue.iter().map(|redeemer| redeemer.score).max())
+                .unwrap_or(0),
+            summary: format!("{} players for epoch {}", recipients.len(), epoch),
+            ..Default::default()
+        };
+        plan_score_weighted_transfers(context, pending, &recipients, total_score, &mut plan)?;
+        Ok(Some(plan))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+    use anyhow::Result;
+    use cainome::cairo_serde::U256;
+    use starknet::{
+        core::types::Felt,
+        providers::{JsonRpcClient, jsonrpc::HttpTransport},
+    };
+    use url::Url;
+
+    use crate::{
+        cli::{LiquidationMode, NetworkName},
+        config::Config,
+        services::{notifier::Notifier, oracle::LatestOraclePrices, tokens::TokenRegistry},
+        types::{
+            accounting::Valuator,
+            distribution::{LiquidationEarnings, PendingDistribution},
+            game::{GameMirror, GameState},
+        },
+        utils::{
+            torii::{PayoutAddressModel, PlayerScoreModel, RedeemModel, SeasonModel, ToriiApi},
+            unix_now,
+        },
+    };
+
+    use super::{
+        ClaimsStrategy, PlayerPayout, QueueStrategy, RewardContext, RewardPlan, RewardStrategy,
+        world_share,
+    };
+
+    /// Torii serving a fixed redeem queue & highest score.
+    struct MockTorii {
+        queue: Vec<RedeemModel>,
+        highest_score: Option<u128>,
+    }
+
+    #[async_trait::async_trait]
+    impl ToriiApi for MockTorii {
+        async fn get_redeem_queue(&self) -> Result<Vec<RedeemModel>> {
+            Ok(self.queue.clone())
+        }
+
+        async fn get_seasons(&self, _after_season_id: Option<u32>) -> Result<Vec<SeasonModel>> {
+            Ok(vec![])
+        }
+
+        async fn get_highest_score(&self) -> Result<Option<u128>> {
+            Ok(self.highest_score)
+        }
+
+        async fn get_leaderboard(
+            &self,
+            _size: usize,
+            _season_id: Option<u32>,
+        ) -> Result<Vec<PlayerScoreModel>> {
+            Ok(vec![])
+        }
+
+        async fn get_payout_address(&self, _player: Felt) -> Result<Option<PayoutAddressModel>> {
+            Ok(None)
+        }
+
+        async fn get_account_created_at(&self, _player: Felt) -> Result<Option<u64>> {
+            Ok(None)
+        }
+    }
+
+    /// Torii failing every request, as during an outage.
+    struct DownTorii;
+
+    #[async_trait::async_trait]
+    impl ToriiApi for DownTorii {
+        async fn get_redeem_queue(&self) -> Result<Vec<RedeemModel>> {
+            anyhow::bail!("Torii is down")
+        }
+
+        async fn get_seasons(&self, _after_season_id: Option<u32>) -> Result<Vec<SeasonModel>> {
+            anyhow::bail!("Torii is down")
+        }
+
+        async fn get_highest_score(&self) -> Result<Option<u128>> {
+            anyhow::bail!("Torii is down")
+        }
+
+        async fn get_leaderboard(
+            &self,
+            _size: usize,
+            _season_id: Option<u32>,
+        ) -> Result<Vec<PlayerScoreModel>> {
+            anyhow::bail!("Torii is down")
+        }
+
+        async fn get_payout_address(&self, _player: Felt) -> Result<Option<PayoutAddressModel>> {
+            anyhow::bail!("Torii is down")
+        }
+
+        async fn get_account_created_at(&self, _player: Felt) -> Result<Option<u64>> {
+            anyhow::bail!("Torii is down")
+        }
+    }
+
+    fn context(score: u128, highest_score: Option<u128>) -> RewardContext {
+        context_with_torii(Arc::new(MockTorii {
+            queue: vec![RedeemModel {
+                player: "0x123".to_string(),
+                score,
+                timestamp: 0,
+            }],
+            highest_score,
+        }))
+    }
+
+    fn context_with_torii(torii: Arc<dyn ToriiApi>) -> RewardContext {
+        let config = Config::new(
+            NetworkName::Mainnet,
+            LiquidationMode::Full,
+            &PathBuf::from("./config.yaml"),
+        )
+        .unwrap();
+        let (sender, _) = tokio::sync::mpsc::unbounded_channel();
+        // Never reached: the configured assets are known without reading them.
+        let rpc_client = Arc::new(JsonRpcClient::new(HttpTransport::new(
+            Url::parse("http://localhost:5050").unwrap(),
+        )));
+        let tokens = TokenRegistry::new(config.clone(), rpc_client, Default::default());
+        let valuator = Valuator::new(
+            config.clone(),
+            LatestOraclePrices::from_config(&config),
+            tokens,
+        );
+        RewardContext::with_torii(
+            config,
+            torii,
+            GameMirror::new(GameState::default()),
+            Notifier::new(sender),
+            valuator,
+        )
+    }
+
+    fn pending(amount: U256) -> PendingDistribution {
+        let mut pending = PendingDistribution::default();
+        pending.push(
+            LiquidationEarnings {
+                liquidation_tx: Felt::ONE,
+                token: Felt::TWO,
+                amount,
+                liquidated_at: 0,
+                span: None,
+            },
+            0,
+        );
+        pending
+    }
+
+    #[tokio::test]
+    async fn test_queue_strategy() {
+        let huge = U256 {
+            low: u128::MAX,
+            high: u128::MAX,
+        };
+        let context = context(u128::MAX / 3, Some(u128::MAX));
+        let plan = QueueStrategy
+            .plan(&context, &pending(huge))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let player = Felt::from_hex("0x123").unwrap();
+        assert_eq!(plan.redeemed_players, vec![player]);
+        assert_eq!(plan.payouts.len(), 1);
+        assert_eq!(plan.payouts[0].recipient, player);
+        let (token, recipient, world) = plan.transfers[0];
+        assert_eq!(token, Felt::TWO);
+        assert_eq!(recipient, context.config.world_address);
+        // A third to the player, the rest to the world & nothing lost.
+        let player_amount = plan.payouts[0].amount;
+        assert_eq!(player_amount.high, u128::MAX / 3);
+        assert_eq!(player_amount + world, huge);
+    }
+
+    #[test]
+    fn test_merged_plan() {
+        let amount = |low| U256 { low, high: 0 };
+        let payout = |player: u64, token: u64, low| PlayerPayout {
+            player: Felt::from(player),
+            recipient: Felt::from(player),
+            token: Felt::from(token),
+            amount: amount(low),
+        };
+        let world = Felt::from(0x999_u64);
+        let plan = RewardPlan {
+            payouts: vec![payout(1, 10, 5), payout(2, 10, 3), payout(1, 10, 7)],
+            transfers: vec![
+                (Felt::from(10), world, amount(1)),
+                (Felt::from(11), world, amount(2)),
+                (Felt::from(10), world, amount(4)),
+            ],
+            ..Default::default()
+        }
+        .merged();
+
+        assert_eq!(plan.payouts, vec![payout(1, 10, 12), payout(2, 10, 3)]);
+        assert_eq!(
+            plan.transfers,
+            vec![
+                (Felt::from(10), world, amount(5)),
+                (Felt::from(11), world, amount(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_amounts_checked() {
+        let amount = |low| U256 { low, high: 0 };
+        let token = Felt::from(10);
+        let world = Felt::from(0x999_u64);
+        let plan = |player, world_share| RewardPlan {
+            payouts: vec![PlayerPayout {
+                player: Felt::ONE,
+                recipient: Felt::ONE,
+                token,
+                amount: amount(player),
+            }],
+            transfers: vec![(token, world, amount(world_share))],
+            ..Default::default()
+        };
+        let earnings = HashMap::from([(token, amount(100))]);
+        assert!(plan(70, 30).check_amounts(&earnings).is_ok());
+
+        // More than the earnings to the players, or to everyone.
+        assert!(plan(101, 0).check_amounts(&earnings).is_err());
+        assert!(plan(70, 31).check_amounts(&earnings).is_err());
+        // A token that was not earned.
+        assert!(plan(70, 30).check_amounts(&HashMap::new()).is_err());
+
+        // The world share fails instead of wrapping around.
+        assert_eq!(
+            world_share(token, amount(100), amount(5), amount(95)).unwrap(),
+            amount(0)
+        );
+        assert!(world_share(token, amount(100), amount(5), amount(96)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_claims_strategy() {
+        let redeemer = |player: &str, score| RedeemModel {
+            player: player.to_string(),
+            score,
+            timestamp: 0,
+        };
+        let context = context_with_torii(Arc::new(MockTorii {
+            queue: vec![redeemer("0x123", 50), redeemer("0x456", 100)],
+            highest_score: Some(100),
+        }));
+        let claims_address = Felt::from(0xc1a1_u64);
+        let amount = |low| U256 { low, high: 0 };
+        let plan = ClaimsStrategy(claims_address)
+            .plan(&context, &pending(amount(1_000)))
+            .await
+            .unwrap()
+            .unwrap();
+
+        // Each player keeps the part of its half matching its score.
+        let (root, claims) = plan.claims.unwrap();
+        let claimed: Vec<_> = claims
+            .iter()
+            .map(|claim| (claim.recipient, claim.amount))
+            .collect();
+        assert_eq!(
+            claimed,
+            vec![
+                (Felt::from_hex("0x123").unwrap(), amount(250)),
+                (Felt::from_hex("0x456").unwrap(), amount(500)),
+            ]
+        );
+        assert_eq!(plan.calls[0].calldata, vec![root]);
+        // The claims are funded, the world gets the rest.
+        assert_eq!(
+            plan.transfers,
+            vec![
+                (Felt::TWO, claims_address, amount(750)),
+                (Felt::TWO, context.config.world_address, amount(250)),
+            ]
+        );
+        assert_eq!(plan.redeemed_players.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_queue_strategy_zero_scores() {
+        let amount = U256 {
+            low: 1_000,
+            high: 0,
+        };
+
+        // No proportion without a highest score.
+        let plan = QueueStrategy
+            .plan(&context(0, Some(0)), &pending(amount))
+            .await
+            .unwrap();
+        assert!(plan.is_none());
+        let plan = QueueStrategy
+            .plan(&context(0, None), &pending(amount))
+            .await
+            .unwrap();
+        assert!(plan.is_none());
+
+        // A player without score gets nothing, the world everything.
+        let plan = QueueStrategy
+            .plan(&context(0, Some(100)), &pending(amount))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(plan.payouts[0].amount, U256 { low: 0, high: 0 });
+        assert_eq!(plan.transfers[0].2, amount);
+    }
+
+    #[tokio::test]
+    async fn test_read_queue_from_cache() {
+        let context = context(10, Some(100));
+        let cached = GameState {
+            redeem_queue: vec![RedeemModel {
+                player: "0x456".to_string(),
+                score: 20,
+                timestamp: 0,
+            }],
+            highest_score: Some(40),
+            ..Default::default()
+        };
+
+        // A stale mirror is not read.
+        context.mirror.replace(GameState {
+            synced_at: unix_now() - context.config.game_sync.cache_ttl_seconds - 1,
+            ..cached.clone()
+        });
+        let (queue, highest_score) = context.read_queue().await.unwrap().unwrap();
+        assert_eq!(queue[0].player, "0x123");
+        assert_eq!(highest_score, Some(100));
+
+        context.mirror.replace(GameState {
+            synced_at: unix_now(),
+            ..cached
+        });
+        let (queue, highest_score) = context.read_queue().await.unwrap().unwrap();
+        assert_eq!(queue[0].player, "0x456");
+        assert_eq!(highest_score, Some(40));
+    }
+
+    #[tokio::test]
+    async fn test_read_queue_falls_back_to_mirror() {
+        let context = context_with_torii(Arc::new(DownTorii));
+        let game_sync = context.config.game_sync.clone();
+        let mirrored = GameState {
+            redeem_queue: vec![RedeemModel {
+                player: "0x456".to_string(),
+                score: 20,
+                timestamp: 0,
+            }],
+            highest_score: Some(40),
+            ..Default::default()
+        };
+
+        // Never synced: the Torii error is returned.
+        let error = context.read_queue().await.unwrap_err();
+        assert_eq!(error.to_string(), "Torii is down");
+
+        // Too old to be trusted.
+        context.mirror.replace(GameState {
+            synced_at: unix_now() - game_sync.max_mirror_age_seconds - 1,
+            ..mirrored.clone()
+        });
+        assert!(context.read_queue().await.is_err());
+
+        // Too old to be read instead of Torii, but recent enough to replace
+        // it during the outage.
+        context.mirror.replace(GameState {
+            synced_at: unix_now() - game_sync.cache_ttl_seconds - 1,
+            ..mirrored
+        });
+        assert!(context.cached_mirror().is_none());
+        let (queue, highest_score) = context.read_queue().await.unwrap().unwrap();
+        assert_eq!(queue[0].player, "0x456");
+        assert_eq!(highest_score, Some(40));
+    }
+}
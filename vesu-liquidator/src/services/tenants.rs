@@ -0,0 +1,179 @@
+use std::{path::Path, sync::Arc};
+
+use anyhow::{Result, anyhow};
+use futures_util::lock::Mutex;
+use tokio::{
+    sync::mpsc::{UnboundedReceiver, UnboundedSender},
+    task::JoinSet,
+};
+
+use crate::{
+    types::distribution::{LiquidationEarnings, proportional_share},
+    utils::{services::Service, shutdown::Shutdown},
+};
+
+/// Name of the game of the network config, used when no tenant is configured.
+pub const DEFAULT_TENANT: &str = "default";
+
+/// Splits the earnings of each liquidation between the tenant games,
+/// according to their `share_percentage`, see [`crate::config::TenantConfig`].
+#[derive(Clone)]
+pub struct TenantsService {
+    earnings_receiver: Arc<Mutex<UnboundedReceiver<LiquidationEarnings>>>,
+    /// Earnings sender of the distribution service of each tenant, with its share.
+    tenants: Vec<(String, u8, UnboundedSender<LiquidationEarnings>)>,
+}
+
+#[async_trait::async_trait]
+impl Service for TenantsService {
+    async fn start(
+        &mut self,
+        join_set: &mut JoinSet<anyhow::Result<()>>,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<()> {
+        let service = self.clone();
+        join_set.spawn(async move {
+            tracing::info!("🎮 Tenants service started");
+            service.run_forever(shutdown).await?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+impl TenantsService {
+    pub fn new(
+        earnings_receiver: UnboundedReceiver<LiquidationEarnings>,
+        tenants: Vec<(String, u8, UnboundedSender<LiquidationEarnings>)>,
+    ) -> Self {
+        Self {
+            earnings_receiver: Arc::new(Mutex::new(earnings_receiver)),
+            tenants,
+        }
+    }
+
+    pub async fn run_forever(&self, shutdown: Shutdown) -> Result<()> {
+        let mut receiver = self.earnings_receiver.lock().await;
+        loop {
+            tokio::select! {
+                _ = shutdown.wait() => {
+                    tracing::info!("[🎮 Tenants] 🛑 Stopped");
+                    return Ok(());
+                }
+
+                maybe_earnings = receiver.recv() => {
+                    let Some(earnings) = maybe_earnings else {
+                        return Err(anyhow!("Tenants service stopped unexpectedly"));
+                    };
+                    self.split(earnings)?;
+                }
+            }
+        }
+    }
+
+    fn split(&self, earnings: LiquidationEarnings) -> Result<()> {
+        for (name, share_percentage, sender) in self.tenants.iter() {
+            let share = LiquidationEarnings {
+                amount: proportional_share(earnings.amount, u128::from(*share_percentage), 100),
+                ..earnings.clone()
+            };
+            tracing::info!(
+                "[🎮 Tenants] {} of token {:#x} from liquidation {:#x} go to {}",
+                share.amount,
+                share.token,
+                share.liquidation_tx,
+                name
+            );
+            sender.send(share)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the storage path of a tenant, next to the main storage: e.g.
+/// `data.my-game.json` for `data.json`.
+pub fn tenant_storage_path(storage_path: &Path, tenant: &str) -> String {
+    storage_path
+        .with_extension(format!("{tenant}.json"))
+        .display()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::tenant_storage_path;
+    use crate::{
+        cli::{LiquidationMode, NetworkName},
+        config::{Config, DistributionStrategy, EarningsSplit, TenantConfig},
+    };
+
+    #[test]
+    fn test_tenant_storage_path() {
+        assert_eq!(
+            tenant_storage_path(Path::new("data.json"), "my-game"),
+            "data.my-game.json"
+        );
+        assert_eq!(
+            tenant_storage_path(Path::new("/var/lib/vesu/state"), "jam-2"),
+            "/var/lib/vesu/state.jam-2.json"
+        );
+    }
+
+    #[test]
+    fn test_tenant_split_and_models() {
+        let config = Config::new(
+            NetworkName::Mainnet,
+            LiquidationMode::Full,
+            &PathBuf::from("./config.yaml"),
+        )
+        .unwrap();
+        let split = EarningsSplit {
+            player_bps: 7_000,
+            world_bps: 2_500,
+            operator_bps: 500,
+        };
+        let tenant = TenantConfig {
+            name: "arcade".to_string(),
+            world_address: "0x123".to_string(),
+            torii_graphql_url: "https://arcade.example/graphql".to_string(),
+            torii_fallback_urls: vec![],
+            claims_address: None,
+            actions_address: None,
+            strategy: DistributionStrategy::default(),
+            share_percentage: 40,
+            split: Some(split),
+            redeem_model: Some("arcadeRedeemModels".to_string()),
+            leaderboard_model: None,
+            season_leaderboard_model: None,
+        };
+        let tenant_config = config.for_tenant(&tenant).unwrap();
+        assert_eq!(tenant_config.distribution.player_bps, 7_000);
+        assert_eq!(tenant_config.torii.redeem_model, "arcadeRedeemModels");
+        assert_eq!(
+            tenant_config.torii.leaderboard_model,
+            config.torii.leaderboard_model
+        );
+
+        // A reload of the split of the config leaves the one of the tenant.
+        let mut reloaded = config.clone();
+        reloaded.distribution.player_bps = 9_000;
+        reloaded.distribution.world_bps = 1_000;
+        reloaded.distribution.operator_bps = 0;
+        assert_eq!(
+            tenant_config.reloaded(&reloaded).distribution.world_bps,
+            2_500
+        );
+        let without_split = config
+            .for_tenant(&TenantConfig {
+                split: None,
+                ..tenant
+            })
+            .unwrap();
+        assert_eq!(
+            without_split.reloaded(&reloaded).distribution.player_bps,
+            9_000
+        );
+    }
+}
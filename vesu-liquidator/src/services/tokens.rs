@@ -0,0 +1,303 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::{Result, anyhow};
+use bigdecimal::BigDecimal;
+use cainome::cairo_serde::U256;
+use dashmap::DashMap;
+use starknet::core::{
+    types::{Felt, FunctionCall},
+    utils::parse_cairo_short_string,
+};
+
+use crate::{
+    config::{Config, DECIMALS_SELECTOR, SYMBOL_SELECTOR},
+    storages::SharedStorage,
+    types::token::TokenMetadata,
+    utils::{constants::U256_ZERO, conversions::to_token_amount, rpc::StarknetRpc},
+};
+
+/// Symbol & decimals of the tokens met by the bot (collaterals, payout
+/// tokens...), read from their contract the first time they are met, cached &
+/// persisted in the storage. Until then, the configured asset is used.
+#[derive(Clone)]
+pub struct TokenRegistry {
+    config: Config,
+    rpc: Arc<dyn StarknetRpc>,
+    storage: Option<SharedStorage>,
+    metadata: Arc<DashMap<Felt, TokenMetadata>>,
+}
+
+impl TokenRegistry {
+    pub fn new(
+        config: Config,
+        rpc: Arc<dyn StarknetRpc>,
+        stored: HashMap<Felt, TokenMetadata>,
+    ) -> Self {
+        Self {
+            config,
+            rpc,
+            storage: None,
+            metadata: Arc::new(stored.into_iter().collect()),
+        }
+    }
+
+    /// Persists the metadata read from the chain in `storage`.
+    pub fn with_storage(mut self, storage: SharedStorage) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Returns the metadata of `token`, read from its contract if it is not
+    /// known yet.
+    pub async fn metadata(&self, token: Felt) -> Result<TokenMetadata> {
+        if let Some(metadata) = self.metadata.get(&token) {
+            return Ok(metadata.clone());
+        }
+        let metadata = self.fetch(token).await?;
+        if let Some(asset) = self.config.asset_map.get(&token) {
+            if asset.decimals != metadata.decimals {
+                tracing::warn!(
+                    token = format!("{token:#x}"),
+                    configured = asset.decimals,
+                    onchain = metadata.decimals,
+                    "{} has {} decimals on-chain but {} in the config, using the on-chain ones",
+                    asset.ticker,
+                    metadata.decimals,
+                    asset.decimals
+                );
+            }
+        }
+        self.metadata.insert(token, metadata.clone());
+        if let Some(storage) = self.storage.as_ref() {
+            storage
+                .lock()
+                .await
+                .save_token_metadata(token, &metadata)
+                .await?;
+        }
+        Ok(metadata)
+    }
+
+    /// Reads the metadata of the tokens not known yet. The tokens that could
+    /// not be read keep the configured asset, if any.
+    pub async fn resolve(&self, tokens: impl IntoIterator<Item = Felt>) {
+        for token in tokens {
+            if let Err(e) = self.metadata(token).await {
+                tracing::warn!(
+                    token = format!("{token:#x}"),
+                    "Could not read the metadata of the token: {e}"
+                );
+            }
+        }
+    }
+
+    /// Returns the known metadata of `token`, without reading the chain: the
+    /// one read from its contract, else the configured asset.
+    pub fn cached(&self, token: &Felt) -> Option<TokenMetadata> {
+        if let Some(metadata) = self.metadata.get(token) {
+            return Some(metadata.clone());
+        }
+        self.config.asset_map.get(token).map(|asset| TokenMetadata {
+            symbol: asset.ticker.clone(),
+            decimals: asset.decimals,
+        })
+    }
+
+    /// Returns the `min_payout` of a token in its smallest unit, zero if the
+    /// token has none.
+    pub fn min_payout(&self, token: &Felt) -> U256 {
+        self.config
+            .asset_map
+            .get(token)
+            .and_then(|asset| asset.min_payout.as_ref())
+            .and_then(|min_payout| self.to_token_amount(token, min_payout))
+            .unwrap_or(U256_ZERO)
+    }
+
+    /// Returns the `max_daily_payout` of a token in its smallest unit, if any.
+    pub fn max_daily_payout(&self, token: &Felt) -> Option<U256> {
+        let max_payout = self
+            .config
+            .asset_map
+            .get(token)?
+            .max_daily_payout
+            .as_ref()?;
+        self.to_token_amount(token, max_payout)
+    }
+
+    /// Converts an amount in tokens to the smallest unit of `token`, `None`
+    /// if its decimals are unknown.
+    fn to_token_amount(&self, token: &Felt, amount: &BigDecimal) -> Option<U256> {
+        let decimals = self.cached(token)?.decimals;
+        Some(to_token_amount(amount, decimals))
+    }
+
+    /// Reads `decimals()` & `symbol()` from the contract of the token.
+    async fn fetch(&self, token: Felt) -> Result<TokenMetadata> {
+        let request = |selector: Felt| FunctionCall {
+            contract_address: token,
+            entry_point_selector: selector,
+            calldata: vec![],
+        };
+        let results = self
+            .rpc
+            .batch_call(vec![request(*DECIMALS_SELECTOR), request(*SYMBOL_SELECTOR)])
+            .await?;
+        let [decimals, symbol] = results.as_slice() else {
+            return Err(anyhow!("Expected the decimals & the symbol of {token:#x}"));
+        };
+        let decimals = decimals
+            .first()
+            .and_then(|decimals| u8::try_from(*decimals).ok())
+            .ok_or_else(|| anyhow!("Invalid decimals for {token:#x}: {decimals:?}"))?;
+        Ok(TokenMetadata {
+            symbol: parse_symbol(symbol)?,
+            decimals: decimals.into(),
+        })
+    }
+}
+
+/// Parses the `symbol()` of an ERC20, either a short string for the older
+/// tokens or a `ByteArray`: full 31 bytes words, a pending word & its length.
+fn parse_symbol(felts: &[Felt]) -> Result<String> {
+    if let [short_string] = felts {
+        return Ok(parse_cairo_short_string(short_string)?);
+    }
+    let words = felts
+        .first()
+        .and_then(|len| usize::try_from(*len).ok())
+        .ok_or_else(|| anyhow!("Invalid symbol: {felts:?}"))?;
+    if felts.len() != words + 3 {
+        return Err(anyhow!("Invalid symbol: {felts:?}"));
+    }
+    let mut symbol = String::new();
+    for word in &felts[1..=words + 1] {
+        symbol.push_str(&parse_cairo_short_string(word)?);
+    }
+    Ok(symbol)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        path::PathBuf,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+    };
+
+    use anyhow::Result;
+    use cainome::cairo_serde::U256;
+    use futures_util::lock::Mutex;
+    use starknet::core::{
+        types::{Felt, FunctionCall},
+        utils::cairo_short_string_to_felt,
+    };
+
+    use super::{TokenRegistry, parse_symbol};
+    use crate::{
+        cli::NetworkName,
+        config::{Config, DECIMALS_SELECTOR, LiquidationMode},
+        storages::{Storage, json::JsonStorage},
+        types::token::TokenMetadata,
+        utils::rpc::StarknetRpc,
+    };
+
+    /// A token with 6 decimals & a `ByteArray` symbol.
+    #[derive(Default)]
+    struct MockRpc {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl StarknetRpc for MockRpc {
+        async fn call(&self, request: FunctionCall) -> Result<Vec<Felt>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if request.entry_point_selector == *DECIMALS_SELECTOR {
+                return Ok(vec![Felt::from(6)]);
+            }
+            let symbol = cairo_short_string_to_felt("USDC").unwrap();
+            Ok(vec![Felt::ZERO, symbol, Felt::from(4)])
+        }
+
+        async fn batch_call(&self, requests: Vec<FunctionCall>) -> Result<Vec<Vec<Felt>>> {
+            let mut results = vec![];
+            for request in requests {
+                results.push(self.call(request).await?);
+            }
+            Ok(results)
+        }
+
+        async fn block_hash(&self, _: u64) -> Result<Option<Felt>> {
+            Ok(None)
+        }
+
+        async fn transaction_sender(&self, _: Felt) -> Result<Option<Felt>> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn test_parse_symbol() {
+        let short = cairo_short_string_to_felt("ETH").unwrap();
+        assert_eq!(parse_symbol(&[short]).unwrap(), "ETH");
+
+        let word = cairo_short_string_to_felt(&"A".repeat(31)).unwrap();
+        let pending = cairo_short_string_to_felt("BC").unwrap();
+        let byte_array = [Felt::ONE, word, pending, Felt::TWO];
+        assert_eq!(
+            parse_symbol(&byte_array).unwrap(),
+            format!("{}BC", "A".repeat(31))
+        );
+        assert!(parse_symbol(&[Felt::TWO, word]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_token_registry() {
+        let mut config = Config::new(
+            NetworkName::Mainnet,
+            LiquidationMode::Full,
+            &PathBuf::from("./config.yaml"),
+        )
+        .unwrap();
+        let eth = config.get_asset_address_for_ticker("eth").unwrap();
+        let unknown = Felt::from(0xabc_u64);
+        // The decimals read on-chain win over the configured ones.
+        config.asset_map.get_mut(&eth).unwrap().min_payout = Some("0.5".parse().unwrap());
+        let storage_path = std::env::temp_dir()
+            .join(format!("vesu-liquidator-{}-tokens", std::process::id()))
+            .display()
+            .to_string();
+        let storage: Box<dyn Storage> = Box::new(JsonStorage::new(&storage_path));
+        let storage = Arc::new(Mutex::new(storage));
+        let rpc = Arc::new(MockRpc::default());
+        let registry = TokenRegistry::new(config.clone(), rpc.clone(), HashMap::new())
+            .with_storage(storage.clone());
+
+        // Unknown tokens have no metadata until they are read.
+        assert_eq!(registry.cached(&unknown), None);
+        assert_eq!(registry.cached(&eth).unwrap().decimals, 18);
+        assert_eq!(registry.min_payout(&eth).low, 500_000_000_000_000_000);
+
+        let usdc = TokenMetadata {
+            symbol: "USDC".to_string(),
+            decimals: 6,
+        };
+        registry.resolve([unknown, eth]).await;
+        assert_eq!(registry.metadata(unknown).await.unwrap(), usdc);
+        assert_eq!(registry.cached(&eth), Some(usdc.clone()));
+        assert_eq!(registry.min_payout(&eth).low, 500_000);
+        assert_eq!(registry.max_daily_payout(&eth), None);
+        assert_eq!(registry.min_payout(&unknown), U256 { low: 0, high: 0 });
+        // Each token is read once.
+        assert_eq!(rpc.calls.load(Ordering::SeqCst), 4);
+
+        let stored = storage.lock().await.get_token_metadata();
+        assert_eq!(stored.len(), 2);
+        let reloaded = TokenRegistry::new(config, Arc::new(MockRpc::default()), stored);
+        assert_eq!(reloaded.metadata(unknown).await.unwrap(), usdc);
+        std::fs::remove_file(storage_path).unwrap();
+    }
+}
@@ -2,89 +2,688 @@ use std::{fs::File, io::Write, path::PathBuf};
 
 use anyhow::Result;
 use dashmap::DashMap;
+use serde::de::DeserializeOwned;
 use serde_json::Value;
+use starknet::core::types::Felt;
 use std::collections::HashMap;
 
-use crate::types::position::{self, Position};
+use crate::types::{
+    accounting::{DistributionPnl, LiquidationPnl, PnlLedger},
+    asset::StoredPrice,
+    attestation::PayoutAttestation,
+    claims::ClaimSet,
+    competition::CompetitorLiquidation,
+    distribution::PendingDistribution,
+    downtime::DowntimeReport,
+    game::GameState,
+    history::LiquidationRecord,
+    indexer::IndexedBlocks,
+    ledger::{DustLedger, Finality, HeldWorldShare, PayoutLedger, RecentPayouts},
+    operator::OperatorAction,
+    position::{DeadLetterPosition, FailedLiquidation, Position, PositionKey, PruningState},
+    token::TokenMetadata,
+};
+use crate::utils::errors::LiquidatorError;
 
-use super::{Storage, StoredData};
+use super::{
+    Storage, StoredData,
+    migrations::{self, SCHEMA_VERSION},
+    wal::{WalEntry, WalRecord, WriteAheadLog},
+};
 
+/// Entries of the write-ahead log after which the storage is compacted.
+const WAL_COMPACTION_ENTRIES: usize = 1_000;
+
+/// Storage of the whole state in a json file. The mutations of the positions
+/// & of the payouts, the most frequent ones, are appended to a write-ahead
+/// log next to it instead of rewriting the file, which is compacted every
+/// [`WAL_COMPACTION_ENTRIES`] entries.
 pub struct JsonStorage {
     file_path: PathBuf,
     data: StoredData,
+    wal: WriteAheadLog,
 }
 
 impl JsonStorage {
     pub fn new(path: &str) -> Self {
+        let file_path = PathBuf::from(path);
         JsonStorage {
-            file_path: PathBuf::from(path),
+            wal: WriteAheadLog::for_storage(&file_path),
+            file_path,
             data: StoredData::default(),
         }
     }
+
+    /// Writes the current data into the json file & truncates the
+    /// write-ahead log, whose entries it now holds. The file is replaced
+    /// atomically, a crash mid-write leaving the previous one.
+    fn write(&mut self) -> Result<()> {
+        self.write_file().map_err(|e| {
+            LiquidatorError::Storage(format!("Could not write {}: {e}", self.file_path.display()))
+                .into()
+        })
+    }
+
+    fn write_file(&mut self) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.data)?;
+        let mut tmp_path = self.file_path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(json.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, &self.file_path)?;
+        self.wal.truncate()
+    }
+
+    /// Appends a mutation to the write-ahead log, then applies it.
+    fn log(&mut self, record: WalRecord) -> Result<()> {
+        let entry = WalEntry {
+            sequence: self.data.wal_sequence + 1,
+            record,
+        };
+        self.wal.append(&entry).map_err(|e| {
+            LiquidatorError::Storage(format!("Could not append to the write-ahead log: {e}"))
+        })?;
+        self.data.apply(entry);
+        if self.wal.len() >= WAL_COMPACTION_ENTRIES {
+            self.write()?;
+        }
+        Ok(())
+    }
+
+    /// Applies the entries of the write-ahead log not yet in the loaded file,
+    /// then compacts them into it.
+    fn replay_wal(&mut self) -> Result<()> {
+        let entries: Vec<WalEntry> = self
+            .wal
+            .read()?
+            .into_iter()
+            .filter(|entry| entry.sequence > self.data.wal_sequence)
+            .collect();
+        if !entries.is_empty() {
+            tracing::info!(
+                "💾 Replayed {} entries of the write-ahead log",
+                entries.len()
+            );
+            for entry in entries {
+                self.data.apply(entry);
+            }
+        }
+        if !self.wal.is_empty() {
+            self.write()?;
+        }
+        Ok(())
+    }
+}
+
+impl StoredData {
+    fn apply(&mut self, entry: WalEntry) {
+        match entry.record {
+            WalRecord::Positions {
+                last_block_indexed,
+                upserted,
+                removed,
+            } => {
+                for key in removed.iter() {
+                    self.positions.remove(key);
+                }
+                for position in upserted {
+                    self.positions.insert(position.key(), position);
+                }
+                self.last_block_indexed = last_block_indexed;
+            }
+            WalRecord::PendingDistribution(pending) => self.pending_distribution = pending,
+            WalRecord::PayoutLedger(ledger) => self.payout_ledger = ledger,
+            WalRecord::DustLedger(ledger) => self.dust_ledger = ledger,
+            WalRecord::RecentPayouts(recent_payouts) => self.recent_payouts = recent_payouts,
+            WalRecord::OraclePrices(prices) => self.oracle_prices = prices,
+        }
+        self.wal_sequence = entry.sequence;
+    }
 }
 
 #[async_trait::async_trait]
 impl Storage for JsonStorage {
-    async fn load(&mut self) -> Result<(u64, HashMap<u64, Position>)> {
+    async fn load(&mut self) -> Result<(u64, HashMap<PositionKey, Position>)> {
         if !self.file_path.exists() {
             self.data = StoredData::new(0, HashMap::new());
+            self.replay_wal()?;
             return Ok(self.data.as_tuple());
         }
-        let json_value: Value = serde_json::from_reader(File::open(self.file_path.clone())?)?;
-        let last_block_indexed: u64 = match json_value.get("last_block_indexed") {
-            Some(Value::Number(lbi)) => {
-                if lbi.is_u64() {
-                    lbi.as_u64().unwrap()
-                } else {
-                    0_u64
-                }
-            }
-            _ => 0_u64,
-        };
-        // no need to go further if last block indexed is genesis
-        if last_block_indexed == 0 {
-            self.data = StoredData::new(0, HashMap::new());
-            return Ok(self.data.as_tuple());
+        let mut json_value: Value = serde_json::from_reader(File::open(self.file_path.clone())?)?;
+        let version = migrations::migrate(&mut json_value)?;
+        self.data = parse_stored_data(&json_value);
+        if version < SCHEMA_VERSION {
+            // The file of the previous version is kept, to downgrade the bot.
+            let mut backup_path = self.file_path.clone().into_os_string();
+            backup_path.push(format!(".v{version}.bak"));
+            let backup_path = PathBuf::from(backup_path);
+            std::fs::copy(&self.file_path, &backup_path)?;
+            self.write()?;
+            tracing::info!(
+                "💾 Storage migrated to schema version {SCHEMA_VERSION}, the previous one is kept in {}",
+                backup_path.display()
+            );
         }
-        let positions: HashMap<u64, Position> = match json_value.get("positions") {
-            Some(Value::Object(map)) => map
-                .iter()
-                .filter_map(|(key, value)| {
-                    let key = key.parse::<u64>().ok()?;
-                    let position: Position = serde_json::from_value(value.clone()).ok()?;
-                    Some((key, position))
-                })
-                .collect(),
-            _ => HashMap::new(),
-        };
-        self.data = StoredData::new(last_block_indexed, positions);
+        self.replay_wal()?;
         Ok(self.data.as_tuple())
     }
 
     async fn save(
         &mut self,
-        positions: &DashMap<u64, position::Position>,
+        positions: &DashMap<PositionKey, Position>,
         last_block_indexed: u64,
     ) -> Result<()> {
-        let file_path = self.file_path.clone();
-        // Convert DashMap to HashMap for serialization
-        let positions_map: HashMap<u64, position::Position> = positions
+        // Only the positions that changed since the previous save are logged.
+        let upserted: Vec<Position> = positions
             .iter()
-            .map(|entry| (*entry.key(), entry.value().clone()))
+            .filter(|entry| self.data.positions.get(entry.key()) != Some(entry.value()))
+            .map(|entry| entry.value().clone())
             .collect();
-
-        let map = StoredData {
+        let removed: Vec<PositionKey> = self
+            .data
+            .positions
+            .keys()
+            .filter(|key| !positions.contains_key(*key))
+            .copied()
+            .collect();
+        if upserted.is_empty()
+            && removed.is_empty()
+            && last_block_indexed == self.data.last_block_indexed
+        {
+            return Ok(());
+        }
+        self.log(WalRecord::Positions {
             last_block_indexed,
-            positions: positions_map,
+            upserted,
+            removed,
+        })
+    }
+
+    fn get_positions(&self) -> HashMap<PositionKey, Position> {
+        self.data.positions.clone()
+    }
+
+    fn get_last_block_indexed(&self) -> u64 {
+        self.data.last_block_indexed
+    }
+
+    fn get_pending_distribution(&self) -> PendingDistribution {
+        self.data.pending_distribution.clone()
+    }
+
+    async fn save_pending_distribution(&mut self, pending: &PendingDistribution) -> Result<()> {
+        self.log(WalRecord::PendingDistribution(pending.clone()))
+    }
+
+    fn get_game_state(&self) -> GameState {
+        self.data.game_state.clone()
+    }
+
+    async fn save_game_state(&mut self, game_state: &GameState) -> Result<()> {
+        self.data.game_state = game_state.clone();
+        self.write()
+    }
+
+    fn get_claim_sets(&self) -> Vec<ClaimSet> {
+        self.data.claim_sets.clone()
+    }
+
+    async fn save_claim_set(&mut self, claim_set: &ClaimSet) -> Result<()> {
+        self.data.claim_sets.push(claim_set.clone());
+        self.write()
+    }
+
+    fn get_dead_letter_positions(&self) -> Vec<DeadLetterPosition> {
+        self.data.dead_letter_positions.clone()
+    }
+
+    async fn save_dead_letter_positions(
+        &mut self,
+        dead_letters: &[DeadLetterPosition],
+    ) -> Result<()> {
+        self.data.dead_letter_positions = dead_letters.to_vec();
+        self.write()
+    }
+
+    fn get_failed_liquidations(&self) -> Vec<FailedLiquidation> {
+        self.data.failed_liquidations.clone()
+    }
+
+    async fn save_failed_liquidations(
+        &mut self,
+        failed_liquidations: &[FailedLiquidation],
+    ) -> Result<()> {
+        self.data.failed_liquidations = failed_liquidations.to_vec();
+        self.write()
+    }
+
+    fn get_payout_ledger(&self) -> PayoutLedger {
+        self.data.payout_ledger.clone()
+    }
+
+    async fn save_payout_ledger(&mut self, ledger: &PayoutLedger) -> Result<()> {
+        self.log(WalRecord::PayoutLedger(ledger.clone()))
+    }
+
+    fn get_dust_ledger(&self) -> DustLedger {
+        self.data.dust_ledger.clone()
+    }
+
+    async fn save_dust_ledger(&mut self, ledger: &DustLedger) -> Result<()> {
+        self.log(WalRecord::DustLedger(ledger.clone()))
+    }
+
+    fn get_recent_payouts(&self) -> RecentPayouts {
+        self.data.recent_payouts.clone()
+    }
+
+    async fn save_recent_payouts(&mut self, recent_payouts: &RecentPayouts) -> Result<()> {
+        self.log(WalRecord::RecentPayouts(recent_payouts.clone()))
+    }
+
+    fn get_downtime_reports(&self) -> Vec<DowntimeReport> {
+        self.data.downtime_reports.clone()
+    }
+
+    async fn save_downtime_report(&mut self, report: &DowntimeReport) -> Result<()> {
+        self.data.downtime_reports.push(report.clone());
+        self.write()
+    }
+
+    fn get_indexed_blocks(&self) -> IndexedBlocks {
+        self.data.indexed_blocks.clone()
+    }
+
+    async fn save_indexed_blocks(&mut self, indexed_blocks: &IndexedBlocks) -> Result<()> {
+        self.data.indexed_blocks = indexed_blocks.clone();
+        self.write()
+    }
+
+    fn get_pruning_state(&self) -> PruningState {
+        self.data.pruning.clone()
+    }
+
+    async fn save_pruning_state(&mut self, pruning: &PruningState) -> Result<()> {
+        self.data.pruning = pruning.clone();
+        self.write()
+    }
+
+    fn get_pnl_ledger(&self) -> PnlLedger {
+        self.data.pnl_ledger.clone()
+    }
+
+    async fn save_liquidation_pnl(&mut self, liquidation: &LiquidationPnl) -> Result<()> {
+        self.data.pnl_ledger.liquidations.push(liquidation.clone());
+        self.write()
+    }
+
+    async fn save_distribution_pnl(&mut self, distribution: &DistributionPnl) -> Result<()> {
+        self.data
+            .pnl_ledger
+            .distributions
+            .push(distribution.clone());
+        self.write()
+    }
+
+    fn get_token_metadata(&self) -> HashMap<Felt, TokenMetadata> {
+        self.data.token_metadata.clone()
+    }
+
+    async fn save_token_metadata(&mut self, token: Felt, metadata: &TokenMetadata) -> Result<()> {
+        self.data.token_metadata.insert(token, metadata.clone());
+        self.write()
+    }
+
+    fn get_oracle_prices(&self) -> HashMap<String, StoredPrice> {
+        self.data.oracle_prices.clone()
+    }
+
+    /// Logged to the write-ahead log, the prices being saved at each update
+    /// of the oracle service.
+    async fn save_oracle_prices(&mut self, prices: &HashMap<String, StoredPrice>) -> Result<()> {
+        if *prices == self.data.oracle_prices {
+            return Ok(());
+        }
+        self.log(WalRecord::OraclePrices(prices.clone()))
+    }
+
+    fn get_liquidation_history(&self) -> Vec<LiquidationRecord> {
+        self.data.liquidation_history.clone()
+    }
+
+    async fn save_liquidation_record(&mut self, record: &LiquidationRecord) -> Result<()> {
+        self.data.liquidation_history.push(record.clone());
+        self.write()
+    }
+
+    async fn save_liquidations_accepted_on_l1(&mut self, tx_hashes: &[Felt]) -> Result<()> {
+        for record in self
+            .data
+            .liquidation_history
+            .iter_mut()
+            .filter(|record| record.tx_hash.is_some_and(|tx| tx_hashes.contains(&tx)))
+        {
+            record.finality = Some(Finality::AcceptedOnL1);
+        }
+        self.write()
+    }
+
+    fn get_competitor_liquidations(&self) -> Vec<CompetitorLiquidation> {
+        self.data.competitor_liquidations.clone()
+    }
+
+    async fn save_competitor_liquidation(
+        &mut self,
+        liquidation: &CompetitorLiquidation,
+    ) -> Result<()> {
+        self.data.competitor_liquidations.push(liquidation.clone());
+        self.write()
+    }
+
+    fn get_payout_attestations(&self) -> Vec<PayoutAttestation> {
+        self.data.payout_attestations.clone()
+    }
+
+    async fn save_payout_attestations(&mut self, attestations: &[PayoutAttestation]) -> Result<()> {
+        self.data
+            .payout_attestations
+            .extend(attestations.iter().cloned());
+        self.write()
+    }
+
+    fn get_held_world_shares(&self) -> Vec<HeldWorldShare> {
+        self.data.held_world_shares.clone()
+    }
+
+    async fn save_held_world_shares(&mut self, shares: &[HeldWorldShare]) -> Result<()> {
+        self.data.held_world_shares = shares.to_vec();
+        self.write()
+    }
+
+    fn get_operator_actions(&self) -> Vec<OperatorAction> {
+        self.data.operator_actions.clone()
+    }
+
+    async fn save_operator_action(&mut self, action: &OperatorAction) -> Result<()> {
+        self.data.operator_actions.push(action.clone());
+        self.write()
+    }
+
+    fn export_state(&self) -> Result<Value> {
+        Ok(serde_json::to_value(&self.data)?)
+    }
+
+    async fn import_state(&mut self, state: &Value) -> Result<()> {
+        anyhow::ensure!(
+            state.is_object(),
+            "The state to import is not a json object"
+        );
+        let mut state = state.clone();
+        migrations::migrate(&mut state)?;
+        self.data = parse_stored_data(&state);
+        self.write()
+    }
+}
+
+/// Parses the whole stored json, migrated to the current schema, the missing
+/// or invalid fields defaulting.
+fn parse_stored_data(json_value: &Value) -> StoredData {
+    let pending_distribution: PendingDistribution = parse_field(json_value, "pending_distribution");
+    let game_state: GameState = parse_field(json_value, "game_state");
+    let claim_sets: Vec<ClaimSet> = parse_field(json_value, "claim_sets");
+    let dead_letter_positions: Vec<DeadLetterPosition> =
+        parse_field(json_value, "dead_letter_positions");
+    let failed_liquidations: Vec<FailedLiquidation> =
+        parse_field(json_value, "failed_liquidations");
+    let payout_ledger: PayoutLedger = parse_field(json_value, "payout_ledger");
+    let dust_ledger: DustLedger = parse_field(json_value, "dust_ledger");
+    let recent_payouts: RecentPayouts = parse_field(json_value, "recent_payouts");
+    let downtime_reports: Vec<DowntimeReport> = parse_field(json_value, "downtime_reports");
+    let indexed_blocks: IndexedBlocks = parse_field(json_value, "indexed_blocks");
+    let pruning: PruningState = parse_field(json_value, "pruning");
+    let pnl_ledger: PnlLedger = parse_field(json_value, "pnl_ledger");
+    let token_metadata: HashMap<Felt, TokenMetadata> = parse_field(json_value, "token_metadata");
+    let oracle_prices: HashMap<String, StoredPrice> = parse_field(json_value, "oracle_prices");
+    let liquidation_history: Vec<LiquidationRecord> =
+        parse_field(json_value, "liquidation_history");
+    let competitor_liquidations: Vec<CompetitorLiquidation> =
+        parse_field(json_value, "competitor_liquidations");
+    let payout_attestations: Vec<PayoutAttestation> =
+        parse_field(json_value, "payout_attestations");
+    let held_world_shares: Vec<HeldWorldShare> = parse_field(json_value, "held_world_shares");
+    let operator_actions: Vec<OperatorAction> = parse_field(json_value, "operator_actions");
+    let last_block_indexed: u64 = match json_value.get("last_block_indexed") {
+        Some(Value::Number(lbi)) => {
+            if lbi.is_u64() {
+                lbi.as_u64().unwrap()
+            } else {
+                0_u64
+            }
+        }
+        _ => 0_u64,
+    };
+    // Positions are ignored while the last block indexed is genesis.
+    let positions = match json_value.get("positions") {
+        Some(Value::Object(map)) if last_block_indexed > 0 => parse_positions(map),
+        _ => HashMap::new(),
+    };
+    StoredData {
+        schema_version: Default::default(),
+        last_block_indexed,
+        positions,
+        pending_distribution,
+        game_state,
+        claim_sets,
+        dead_letter_positions,
+        failed_liquidations,
+        payout_ledger,
+        dust_ledger,
+        recent_payouts,
+        downtime_reports,
+        indexed_blocks,
+        pruning,
+        pnl_ledger,
+        token_metadata,
+        oracle_prices,
+        liquidation_history,
+        competitor_liquidations,
+        payout_attestations,
+        held_world_shares,
+        operator_actions,
+        wal_sequence: parse_field(json_value, "wal_sequence"),
+    }
+}
+
+/// Parses the stored positions, keyed by their [`PositionKey`] rebuilt from
+/// the positions. The positions that can't be parsed are dropped.
+fn parse_positions(map: &serde_json::Map<String, Value>) -> HashMap<PositionKey, Position> {
+    map.values()
+        .filter_map(|value| {
+            let position: Position = serde_json::from_value(value.clone()).ok()?;
+            Some((position.key(), position))
+        })
+        .collect()
+}
+
+/// Parses an optional field of the stored json, defaulting if missing or invalid.
+fn parse_field<T: DeserializeOwned + Default>(json_value: &Value, field: &str) -> T {
+    json_value
+        .get(field)
+        .and_then(|value| serde_json::from_value(value.clone()).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, io::Write, str::FromStr};
+
+    use bigdecimal::BigDecimal;
+    use dashmap::DashMap;
+
+    use super::JsonStorage;
+    use crate::{
+        storages::{Storage, StoredData},
+        types::{asset::Asset, fixtures, ledger::PayoutLedger, position::Position},
+        utils::torii::PayoutAddressModel,
+    };
+
+    const GOLDEN_STORAGE: &str = include_str!("testdata/storage.golden.json");
+
+    /// Position of the golden file.
+    fn position(user: u64) -> Position {
+        let position = fixtures::position(user, "1500.5");
+        Position {
+            pool_name: Some("genesis".to_string()),
+            collateral: Asset {
+                name: "eth".to_string(),
+                amount: BigDecimal::from_str("1e21").unwrap(),
+                ..position.collateral
+            },
+            debt: Asset {
+                name: "usdc".to_string(),
+                ..position.debt
+            },
+            lltv: BigDecimal::from_str("0.00000001").unwrap(),
+            ..position
+        }
+    }
+
+    fn payout_address(player: &str) -> (String, PayoutAddressModel) {
+        let model = PayoutAddressModel {
+            player: player.to_string(),
+            payout_address: "0xff".to_string(),
+            set_by: player.to_string(),
         };
+        (player.to_string(), model)
+    }
 
-        let json = serde_json::to_string_pretty(&map)?;
-        let mut file = File::create(file_path)?;
-        file.write_all(json.as_bytes())?;
-        Ok(())
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("vesu-liquidator-{}-{name}", std::process::id()))
+            .display()
+            .to_string()
     }
 
-    fn get_positions(&self) -> HashMap<u64, Position> {
-        self.data.positions.clone()
+    #[tokio::test]
+    async fn test_storage_matches_golden_file() {
+        let mut data = StoredData::new(
+            42,
+            HashMap::from([
+                (position(3).key(), position(3)),
+                (position(2).key(), position(2)),
+            ]),
+        );
+        data.game_state.payout_addresses =
+            HashMap::from([payout_address("0xb"), payout_address("0xa")]);
+        let path = temp_path("storage.json");
+        let mut storage = JsonStorage {
+            data,
+            ..JsonStorage::new(&path)
+        };
+        storage.write().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            GOLDEN_STORAGE.trim_end()
+        );
+
+        // Loading & writing back the storage doesn't change it.
+        let mut reloaded = JsonStorage::new(&path);
+        reloaded.load().await.unwrap();
+        reloaded.file_path = temp_path("storage-reloaded.json").into();
+        reloaded.write().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&reloaded.file_path).unwrap(),
+            GOLDEN_STORAGE.trim_end()
+        );
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&reloaded.file_path);
+    }
+
+    #[tokio::test]
+    async fn test_load_migrates_legacy_position_keys() {
+        let path = temp_path("legacy-storage.json");
+        // Written before the schema was versioned, with the legacy keys.
+        let legacy = GOLDEN_STORAGE
+            .replace("  \"schema_version\": 1,\n", "")
+            .replace("0x1:0x10:0x20:0x2", "2")
+            .replace("0x1:0x10:0x20:0x3", "10");
+        std::fs::write(&path, &legacy).unwrap();
+
+        let mut storage = JsonStorage::new(&path);
+        let (_, positions) = storage.load().await.unwrap();
+        assert_eq!(positions.len(), 2);
+        for (key, position) in &positions {
+            assert_eq!(*key, position.key());
+        }
+
+        // The migrated storage is written back, the legacy one kept aside.
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            GOLDEN_STORAGE.trim_end()
+        );
+        let backup_path = format!("{path}.v0.bak");
+        assert_eq!(std::fs::read_to_string(&backup_path).unwrap(), legacy);
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&backup_path);
+    }
+
+    #[tokio::test]
+    async fn test_wal_is_replayed_after_a_crash() {
+        let path = temp_path("wal-storage.json");
+        let wal_path = format!("{path}.wal");
+        let mut storage = JsonStorage::new(&path);
+        storage.load().await.unwrap();
+        let positions = DashMap::from_iter([(position(2).key(), position(2))]);
+        storage.save(&positions, 10).await.unwrap();
+        positions.insert(position(3).key(), position(3));
+        positions.remove(&position(2).key());
+        storage.save(&positions, 11).await.unwrap();
+        let mut ledger = PayoutLedger::default();
+        ledger.last_block_reconciled = 7;
+        storage.save_payout_ledger(&ledger).await.unwrap();
+        // Unchanged, not logged.
+        storage.save(&positions, 11).await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&wal_path).unwrap().lines().count(),
+            3
+        );
+
+        // Crash while appending a 4th entry.
+        let mut wal = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&wal_path)
+            .unwrap();
+        wal.write_all(b"{\"sequence\":4,\"rec").unwrap();
+        drop(storage);
+
+        let mut reloaded = JsonStorage::new(&path);
+        let (last_block_indexed, positions) = reloaded.load().await.unwrap();
+        assert_eq!(last_block_indexed, 11);
+        assert_eq!(
+            positions.keys().collect::<Vec<_>>(),
+            vec![&position(3).key()]
+        );
+        assert_eq!(reloaded.get_payout_ledger().last_block_reconciled, 7);
+        // Compacted into the storage on load.
+        assert_eq!(std::fs::read_to_string(&wal_path).unwrap(), "");
+        assert_eq!(reloaded.data.wal_sequence, 3);
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&wal_path);
+    }
+
+    #[tokio::test]
+    async fn test_load_rejects_newer_schema() {
+        let path = temp_path("newer-storage.json");
+        let newer = GOLDEN_STORAGE.replace("\"schema_version\": 1,", "\"schema_version\": 99,");
+        std::fs::write(&path, newer).unwrap();
+
+        let mut storage = JsonStorage::new(&path);
+        assert!(storage.load().await.is_err());
+
+        let _ = std::fs::remove_file(&path);
     }
 }
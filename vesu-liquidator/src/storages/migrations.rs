@@ -0,0 +1,98 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::types::position::Position;
+
+/// Version of the schema of the stored state, bumped along with a new
+/// migration whenever a change of the stored types breaks the older states.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Migrates a stored state from a version of the schema to the next one.
+type Migration = fn(&mut Map<String, Value>) -> Result<()>;
+
+/// The migrations in order, the one at index `i` migrating the states of
+/// version `i` to version `i + 1`.
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [rekey_positions];
+
+/// Version of the schema a state is stored with, the current one for the
+/// states written by this version of the bot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SchemaVersion(pub u32);
+
+impl Default for SchemaVersion {
+    fn default() -> Self {
+        Self(SCHEMA_VERSION)
+    }
+}
+
+/// Migrates a stored state up to [`SCHEMA_VERSION`] & returns the version it
+/// was stored with, 0 for the states written before the schema was versioned.
+/// Fails on the states written by a newer version of the bot.
+pub fn migrate(state: &mut Value) -> Result<u32> {
+    let Value::Object(fields) = state else {
+        anyhow::bail!("The stored state is not a json object");
+    };
+    let version = match fields.get("schema_version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| anyhow!("Invalid storage schema version {version}"))?,
+    };
+    anyhow::ensure!(
+        version <= SCHEMA_VERSION,
+        "Storage schema version {version} is not supported, expected at most {SCHEMA_VERSION}: it was written by a newer version of the bot"
+    );
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migration(fields).map_err(|e| {
+            anyhow!("Could not migrate the storage from schema version {from}: {e}")
+        })?;
+        tracing::info!(
+            "💾 Migrated the storage from schema version {from} to {}",
+            from + 1
+        );
+    }
+    fields.insert("schema_version".to_string(), Value::from(SCHEMA_VERSION));
+    Ok(version)
+}
+
+/// v0 → v1: the positions were keyed by a legacy `u64`, they are now keyed by
+/// their [`crate::types::position::PositionKey`], rebuilt from the positions.
+/// The positions that can't be parsed are dropped.
+fn rekey_positions(fields: &mut Map<String, Value>) -> Result<()> {
+    let Some(Value::Object(positions)) = fields.get_mut("positions") else {
+        return Ok(());
+    };
+    *positions = positions
+        .values()
+        .filter_map(|value| {
+            let position: Position = serde_json::from_value(value.clone()).ok()?;
+            Some((format!("{:?}", position.key()), value.clone()))
+        })
+        .collect();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{SCHEMA_VERSION, migrate};
+
+    #[test]
+    fn test_migrate() {
+        let mut state = json!({ "last_block_indexed": 42, "positions": {} });
+        assert_eq!(migrate(&mut state).unwrap(), 0);
+        assert_eq!(state["schema_version"], json!(SCHEMA_VERSION));
+        assert_eq!(state["last_block_indexed"], json!(42));
+
+        // Already migrated.
+        assert_eq!(migrate(&mut state).unwrap(), SCHEMA_VERSION);
+
+        let mut newer = json!({ "schema_version": SCHEMA_VERSION + 1 });
+        assert!(migrate(&mut newer).is_err());
+        assert!(migrate(&mut json!([])).is_err());
+    }
+}
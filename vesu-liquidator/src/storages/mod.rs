@@ -1,37 +1,153 @@
 pub mod json;
+pub mod migrations;
+pub mod snapshot;
+pub mod wal;
 
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use anyhow::Result;
 use dashmap::DashMap;
+use futures_util::lock::Mutex;
+use serde_json::Value;
+use starknet::core::types::Felt;
 
-use crate::types::position::{self, Position};
+use crate::{
+    types::{
+        accounting::{DistributionPnl, LiquidationPnl, PnlLedger},
+        asset::StoredPrice,
+        attestation::PayoutAttestation,
+        claims::ClaimSet,
+        competition::CompetitorLiquidation,
+        distribution::PendingDistribution,
+        downtime::DowntimeReport,
+        game::GameState,
+        history::LiquidationRecord,
+        indexer::IndexedBlocks,
+        ledger::{DustLedger, HeldWorldShare, PayoutLedger, RecentPayouts},
+        operator::OperatorAction,
+        position::{DeadLetterPosition, FailedLiquidation, Position, PositionKey, PruningState},
+        token::TokenMetadata,
+    },
+    utils::serialization::sorted_map,
+};
+
+use migrations::SchemaVersion;
+
+/// Storage shared between the services.
+pub type SharedStorage = Arc<Mutex<Box<dyn Storage>>>;
 
 #[derive(serde::Serialize, Default)]
 struct StoredData {
+    schema_version: SchemaVersion,
     last_block_indexed: u64,
-    positions: HashMap<u64, Position>,
+    #[serde(serialize_with = "sorted_map")]
+    positions: HashMap<PositionKey, Position>,
+    pending_distribution: PendingDistribution,
+    game_state: GameState,
+    claim_sets: Vec<ClaimSet>,
+    dead_letter_positions: Vec<DeadLetterPosition>,
+    failed_liquidations: Vec<FailedLiquidation>,
+    payout_ledger: PayoutLedger,
+    dust_ledger: DustLedger,
+    recent_payouts: RecentPayouts,
+    downtime_reports: Vec<DowntimeReport>,
+    indexed_blocks: IndexedBlocks,
+    pruning: PruningState,
+    pnl_ledger: PnlLedger,
+    #[serde(serialize_with = "sorted_map")]
+    token_metadata: HashMap<Felt, TokenMetadata>,
+    /// Latest oracle prices by (lowercase) ticker.
+    #[serde(serialize_with = "sorted_map")]
+    oracle_prices: HashMap<String, StoredPrice>,
+    liquidation_history: Vec<LiquidationRecord>,
+    competitor_liquidations: Vec<CompetitorLiquidation>,
+    /// Signed log of the payouts, see [`PayoutAttestation`].
+    payout_attestations: Vec<PayoutAttestation>,
+    held_world_shares: Vec<HeldWorldShare>,
+    /// Actions of the operators, for audit.
+    operator_actions: Vec<OperatorAction>,
+    /// Last entry of the write-ahead log in the storage, see [`wal`].
+    wal_sequence: u64,
 }
 
 impl StoredData {
-    pub fn new(last_block_indexed: u64, positions: HashMap<u64, Position>) -> Self {
+    pub fn new(last_block_indexed: u64, positions: HashMap<PositionKey, Position>) -> Self {
         StoredData {
             last_block_indexed,
             positions,
+            ..Default::default()
         }
     }
-    pub fn as_tuple(&self) -> (u64, HashMap<u64, Position>) {
+    pub fn as_tuple(&self) -> (u64, HashMap<PositionKey, Position>) {
         (self.last_block_indexed, self.positions.clone())
     }
 }
 
 #[async_trait::async_trait]
 pub trait Storage: Send + Sync {
-    async fn load(&mut self) -> Result<(u64, HashMap<u64, Position>)>;
+    async fn load(&mut self) -> Result<(u64, HashMap<PositionKey, Position>)>;
     async fn save(
         &mut self,
-        positions: &DashMap<u64, position::Position>,
+        positions: &DashMap<PositionKey, Position>,
         last_block_indexed: u64,
     ) -> Result<()>;
-    fn get_positions(&self) -> HashMap<u64, Position>;
+    fn get_positions(&self) -> HashMap<PositionKey, Position>;
+    fn get_last_block_indexed(&self) -> u64;
+    fn get_pending_distribution(&self) -> PendingDistribution;
+    async fn save_pending_distribution(&mut self, pending: &PendingDistribution) -> Result<()>;
+    fn get_game_state(&self) -> GameState;
+    async fn save_game_state(&mut self, game_state: &GameState) -> Result<()>;
+    fn get_claim_sets(&self) -> Vec<ClaimSet>;
+    async fn save_claim_set(&mut self, claim_set: &ClaimSet) -> Result<()>;
+    fn get_dead_letter_positions(&self) -> Vec<DeadLetterPosition>;
+    async fn save_dead_letter_positions(
+        &mut self,
+        dead_letters: &[DeadLetterPosition],
+    ) -> Result<()>;
+    fn get_failed_liquidations(&self) -> Vec<FailedLiquidation>;
+    async fn save_failed_liquidations(
+        &mut self,
+        failed_liquidations: &[FailedLiquidation],
+    ) -> Result<()>;
+    fn get_payout_ledger(&self) -> PayoutLedger;
+    async fn save_payout_ledger(&mut self, ledger: &PayoutLedger) -> Result<()>;
+    fn get_dust_ledger(&self) -> DustLedger;
+    async fn save_dust_ledger(&mut self, ledger: &DustLedger) -> Result<()>;
+    fn get_recent_payouts(&self) -> RecentPayouts;
+    async fn save_recent_payouts(&mut self, recent_payouts: &RecentPayouts) -> Result<()>;
+    fn get_downtime_reports(&self) -> Vec<DowntimeReport>;
+    async fn save_downtime_report(&mut self, report: &DowntimeReport) -> Result<()>;
+    fn get_indexed_blocks(&self) -> IndexedBlocks;
+    async fn save_indexed_blocks(&mut self, indexed_blocks: &IndexedBlocks) -> Result<()>;
+    fn get_pruning_state(&self) -> PruningState;
+    async fn save_pruning_state(&mut self, pruning: &PruningState) -> Result<()>;
+    fn get_pnl_ledger(&self) -> PnlLedger;
+    async fn save_liquidation_pnl(&mut self, liquidation: &LiquidationPnl) -> Result<()>;
+    async fn save_distribution_pnl(&mut self, distribution: &DistributionPnl) -> Result<()>;
+    fn get_token_metadata(&self) -> HashMap<Felt, TokenMetadata>;
+    async fn save_token_metadata(&mut self, token: Felt, metadata: &TokenMetadata) -> Result<()>;
+    fn get_oracle_prices(&self) -> HashMap<String, StoredPrice>;
+    async fn save_oracle_prices(&mut self, prices: &HashMap<String, StoredPrice>) -> Result<()>;
+    fn get_liquidation_history(&self) -> Vec<LiquidationRecord>;
+    async fn save_liquidation_record(&mut self, record: &LiquidationRecord) -> Result<()>;
+    /// Marks the liquidations of the transactions as accepted on L1.
+    async fn save_liquidations_accepted_on_l1(&mut self, tx_hashes: &[Felt]) -> Result<()>;
+    fn get_competitor_liquidations(&self) -> Vec<CompetitorLiquidation>;
+    async fn save_competitor_liquidation(
+        &mut self,
+        liquidation: &CompetitorLiquidation,
+    ) -> Result<()>;
+    fn get_payout_attestations(&self) -> Vec<PayoutAttestation>;
+    /// Appends the attestations to the log.
+    async fn save_payout_attestations(&mut self, attestations: &[PayoutAttestation]) -> Result<()>;
+    fn get_held_world_shares(&self) -> Vec<HeldWorldShare>;
+    async fn save_held_world_shares(&mut self, shares: &[HeldWorldShare]) -> Result<()>;
+    fn get_operator_actions(&self) -> Vec<OperatorAction>;
+    /// Appends the action to the log.
+    async fn save_operator_action(&mut self, action: &OperatorAction) -> Result<()>;
+    /// Returns the whole state of the storage, see [`snapshot::StorageSnapshot`].
+    fn export_state(&self) -> Result<Value>;
+    /// Replaces the whole state of the storage by an exported one, migrated
+    /// to the current schema, see [`migrations`].
+    async fn import_state(&mut self, state: &Value) -> Result<()>;
 }
@@ -0,0 +1,117 @@
+use std::{fs::File, io::Write, path::Path};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{storages::Storage, utils::unix_now};
+
+/// Version of the snapshot format, bumped on breaking changes of the state.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Whole state of a storage written to a versioned file, to migrate between
+/// storage backends or bootstrap a new bot instance.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StorageSnapshot {
+    pub version: u32,
+    pub created_at: u64,
+    pub last_block_indexed: u64,
+    pub positions: usize,
+    pub state: Value,
+}
+
+impl StorageSnapshot {
+    /// Takes a snapshot of the loaded storage.
+    pub fn capture(storage: &dyn Storage) -> Result<Self> {
+        Ok(Self {
+            version: SNAPSHOT_VERSION,
+            created_at: unix_now(),
+            last_block_indexed: storage.get_last_block_indexed(),
+            positions: storage.get_positions().len(),
+            state: storage.export_state()?,
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        File::create(path)?.write_all(json.as_bytes())?;
+        Ok(())
+    }
+
+    /// Reads a snapshot, failing if it was written by a newer version.
+    pub fn read(path: &Path) -> Result<Self> {
+        let snapshot: Self = serde_json::from_reader(File::open(path)?)?;
+        anyhow::ensure!(
+            snapshot.version <= SNAPSHOT_VERSION,
+            "Snapshot version {} is not supported, expected at most {SNAPSHOT_VERSION}",
+            snapshot.version
+        );
+        Ok(snapshot)
+    }
+
+    /// Replaces the whole state of the storage by the one of the snapshot.
+    pub async fn restore(&self, storage: &mut dyn Storage) -> Result<()> {
+        storage.import_state(&self.state).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::storages::{Storage, json::JsonStorage};
+
+    use super::{SNAPSHOT_VERSION, StorageSnapshot};
+
+    const GOLDEN_STORAGE: &str = include_str!("testdata/storage.golden.json");
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("vesu-liquidator-{}-{name}", std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_round_trip() {
+        let source_path = temp_path("snapshot-source.json");
+        std::fs::write(&source_path, GOLDEN_STORAGE).unwrap();
+        let mut source = JsonStorage::new(source_path.to_str().unwrap());
+        source.load().await.unwrap();
+
+        let snapshot_path = temp_path("snapshot.json");
+        let snapshot = StorageSnapshot::capture(&source).unwrap();
+        assert_eq!(snapshot.version, SNAPSHOT_VERSION);
+        assert_eq!(snapshot.last_block_indexed, 42);
+        assert_eq!(snapshot.positions, 2);
+        snapshot.write(&snapshot_path).unwrap();
+
+        let restored_path = temp_path("snapshot-restored.json");
+        let mut restored = JsonStorage::new(restored_path.to_str().unwrap());
+        StorageSnapshot::read(&snapshot_path)
+            .unwrap()
+            .restore(&mut restored)
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&restored_path).unwrap(),
+            GOLDEN_STORAGE.trim_end()
+        );
+
+        for path in [source_path, snapshot_path, restored_path] {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    #[test]
+    fn test_newer_snapshot_is_rejected() {
+        let path = temp_path("snapshot-newer.json");
+        let snapshot = StorageSnapshot {
+            version: SNAPSHOT_VERSION + 1,
+            created_at: 0,
+            last_block_indexed: 0,
+            positions: 0,
+            state: serde_json::Value::Null,
+        };
+        snapshot.write(&path).unwrap();
+        assert!(StorageSnapshot::read(&path).is_err());
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,126 @@
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::types::{
+    asset::StoredPrice,
+    distribution::PendingDistribution,
+    ledger::{DustLedger, PayoutLedger, RecentPayouts},
+    position::{Position, PositionKey},
+};
+
+/// A mutation of the positions, of the payouts or of the oracle prices,
+/// appended to the log instead of rewriting the whole storage.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum WalRecord {
+    /// The positions created or updated & the ones removed since the
+    /// previous save.
+    Positions {
+        last_block_indexed: u64,
+        upserted: Vec<Position>,
+        removed: Vec<PositionKey>,
+    },
+    PendingDistribution(PendingDistribution),
+    PayoutLedger(PayoutLedger),
+    DustLedger(DustLedger),
+    RecentPayouts(RecentPayouts),
+    OraclePrices(HashMap<String, StoredPrice>),
+}
+
+/// A record of the log, numbered so that the records already in the
+/// compacted storage are not replayed twice.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalEntry {
+    pub sequence: u64,
+    pub record: WalRecord,
+}
+
+/// Append-only log of the mutations since the storage was last compacted,
+/// one json entry per line. Each entry is flushed to the disk before being
+/// applied, so a crash loses at most the entry being appended.
+pub struct WriteAheadLog {
+    path: PathBuf,
+    file: Option<File>,
+    len: usize,
+}
+
+impl WriteAheadLog {
+    /// The log of the storage at `storage_path`, next to it.
+    pub fn for_storage(storage_path: &Path) -> Self {
+        let mut path = storage_path.as_os_str().to_owned();
+        path.push(".wal");
+        Self {
+            path: PathBuf::from(path),
+            file: None,
+            len: 0,
+        }
+    }
+
+    /// Number of entries appended since the log was last truncated.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Reads the entries of the log, in order. A last entry cut by a crash
+    /// is ignored.
+    pub fn read(&mut self) -> Result<Vec<WalEntry>> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let mut entries = vec![];
+        for line in BufReader::new(file).lines() {
+            match serde_json::from_str(&line?) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => {
+                    tracing::warn!(
+                        error = %e,
+                        "💾 Ignored the last entry of the write-ahead log, cut by a crash"
+                    );
+                    break;
+                }
+            }
+        }
+        self.len = entries.len();
+        Ok(entries)
+    }
+
+    pub fn append(&mut self, entry: &WalEntry) -> Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        let file = match self.file.take() {
+            Some(file) => file,
+            None => OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?,
+        };
+        let file = self.file.insert(file);
+        file.write_all(&line)?;
+        file.sync_data()?;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Empties the log, once its entries are in the compacted storage.
+    pub fn truncate(&mut self) -> Result<()> {
+        self.file = None;
+        if self.path.exists() {
+            File::create(&self.path)?.sync_all()?;
+        }
+        self.len = 0;
+        Ok(())
+    }
+}
@@ -1,77 +1,277 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{fmt, path::PathBuf, sync::Arc};
 
-use anyhow::Result;
-use bigdecimal::BigDecimal;
-use bigdecimal::num_bigint::ToBigInt;
+use anyhow::{Result, anyhow};
+use bigdecimal::num_bigint::{BigInt, Sign, ToBigInt};
+use bigdecimal::{BigDecimal, FromPrimitive};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
 use starknet::{
-    accounts::{Account, ExecutionEncoding, SingleOwnerAccount},
+    accounts::{
+        Account, AccountFactory, ConnectedAccount, ExecutionEncoder, ExecutionEncoding,
+        OpenZeppelinAccountFactory, RawDeclarationV3, RawExecutionV3, SingleOwnerAccount,
+    },
     core::{
         chain_id,
-        types::{BlockId, BlockTag, Call, Felt},
+        crypto::Signature,
+        types::{
+            BlockId, BlockTag, Call, FeePayment, Felt, FunctionCall, PriceUnit,
+            SimulatedTransaction, StarknetError, TypedData,
+        },
     },
-    providers::{JsonRpcClient, jsonrpc::HttpTransport},
-    signers::{LocalWallet, SigningKey},
+    providers::{JsonRpcClient, Provider, ProviderError, jsonrpc::HttpTransport},
+    signers::{LocalWallet, Signer, SignerInteractivityContext, SigningKey, VerifyingKey},
 };
+use url::Url;
 
 use crate::{
-    cli::{NetworkName, RunCmd},
-    utils::constants::VESU_RESPONSE_DECIMALS,
+    cli::{BotParams, NetworkName},
+    config::{BALANCE_OF_SELECTOR, FeeToken},
+    types::session::Session,
+    utils::{
+        constants::{KATANA_CHAIN_ID, VESU_RESPONSE_DECIMALS},
+        conversions::hex_str_to_big_decimal,
+        errors::LiquidatorError,
+        serialization::optional_plain_decimal,
+        unix_now,
+    },
 };
 
-pub struct StarknetAccount(
-    pub Arc<SingleOwnerAccount<Arc<JsonRpcClient<HttpTransport>>, LocalWallet>>,
-);
+/// Decimals of the fee tokens, STRK & ETH.
+const FEE_DECIMALS: i64 = 18;
+
+/// Account of the bot, along with its signer to sign messages.
+#[derive(Clone)]
+pub struct StarknetAccount(pub Arc<BotAccount>, BotSigner);
 
 impl StarknetAccount {
     /// Creates a StarknetAccount from the CLI args
     pub fn from_cli(
         rpc_client: Arc<JsonRpcClient<HttpTransport>>,
-        run_cmd: RunCmd,
+        bot_params: &BotParams,
     ) -> Result<StarknetAccount> {
-        let mut builder = StarknetAccountBuilder::default();
-
-        builder = match run_cmd.network {
-            NetworkName::Mainnet => builder.on_mainnet(),
-            NetworkName::Sepolia => builder.on_sepolia(),
-        };
-
-        builder = builder
-            .as_account(run_cmd.account_params.account_address)
+        let builder = StarknetAccountBuilder::for_network(bot_params.network)
+            .as_account(bot_params.account_params.account_address)
             .with_provider(rpc_client);
 
-        if let Some(private_key) = run_cmd.account_params.private_key {
+        let account_params = bot_params.account_params.clone();
+        let builder = match &account_params.session_path {
+            Some(session_path) => builder.with_session(Session::load(session_path)?),
+            None => builder,
+        };
+        if let Some(private_key) = account_params.private_key {
             builder.from_secret(private_key)
+        } else if let Some(url) = account_params.remote_signer_url.clone() {
+            let token = account_params.remote_signer_token()?;
+            builder.from_remote_signer(RemoteSigner::new(url, token))
         } else {
-            builder.from_keystore(
-                run_cmd.account_params.keystore_path.unwrap(),
-                &run_cmd.account_params.keystore_password.unwrap(),
-            )
+            let keystore_password = account_params.keystore_password()?;
+            builder.from_keystore(account_params.keystore_path.unwrap(), &keystore_password)
         }
     }
 
+    /// Creates the extra accounts given by `--extra-account`.
+    pub fn extras_from_cli(
+        rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+        bot_params: &BotParams,
+    ) -> Result<Vec<StarknetAccount>> {
+        bot_params
+            .account_params
+            .extra_accounts
+            .iter()
+            .map(|extra_account| {
+                StarknetAccountBuilder::for_network(bot_params.network)
+                    .as_account(extra_account.address)
+                    .with_provider(rpc_client.clone())
+                    .from_secret(extra_account.private_key)
+            })
+            .collect()
+    }
+
     /// Returns the account_address of the Account.
     pub fn account_address(&self) -> Felt {
         self.0.address()
     }
 
+    /// Returns the chain id the transactions of the account are signed for.
+    pub fn chain_id(&self) -> Felt {
+        self.0.chain_id()
+    }
+
+    /// Returns the session the account is executed through, if any.
+    pub fn session(&self) -> Option<&Session> {
+        self.0.session.as_ref()
+    }
+
+    /// Checks that the session, if any, is not expired & belongs to the key
+    /// of the bot.
+    pub async fn check_session(&self) -> Result<()> {
+        let Some(session) = self.session() else {
+            return Ok(());
+        };
+        anyhow::ensure!(
+            !session.is_expired(),
+            "The session expired at {}",
+            session.expires_at
+        );
+        let public_key = self.public_key().await?;
+        anyhow::ensure!(
+            public_key == session.public_key,
+            "The session is authorized for key {:#x}, not for the key of the bot {public_key:#x}",
+            session.public_key
+        );
+        Ok(())
+    }
+
+    /// Whether the account contract is deployed, at the pre-confirmed block.
+    pub async fn is_deployed(&self) -> Result<bool> {
+        let block_id = BlockId::Tag(BlockTag::PreConfirmed);
+        match self
+            .0
+            .provider()
+            .get_class_hash_at(block_id, self.account_address())
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(ProviderError::StarknetError(StarknetError::ContractNotFound)) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns the STRK balance of the account, paying its fees.
+    pub async fn fee_balance(&self, strk: Felt) -> Result<BigDecimal> {
+        fetch_fee_balance(self.0.provider(), self.account_address(), strk).await
+    }
+
+    /// Returns the public key of the signer of the account.
+    pub async fn public_key(&self) -> Result<Felt> {
+        Ok(self.1.get_public_key().await?.scalar())
+    }
+
+    /// Estimates the STRK fee of the deployment of the account, see
+    /// [`StarknetAccount::deploy`].
+    pub async fn estimate_deployment_fee(
+        &self,
+        class_hash: Felt,
+        salt: Felt,
+    ) -> Result<BigDecimal> {
+        let factory = self.account_factory(class_hash).await?;
+        let deployment = factory.deploy_v3(salt);
+        self.ensure_deployed_at(deployment.address())?;
+        let estimation = deployment
+            .estimate_fee()
+            .await
+            .map_err(|e| anyhow!(format!("{:?}", e)))?;
+        Ok(BigDecimal::new(
+            estimation.overall_fee.to_bigint().unwrap(),
+            FEE_DECIMALS,
+        ))
+    }
+
+    /// Deploys the account as an OpenZeppelin account of `class_hash` owned
+    /// by the public key of its signer, its fee paid by the STRK already sent
+    /// to its address. Fails if `class_hash` & `salt` don't lead to the
+    /// address of the account.
+    pub async fn deploy(&self, class_hash: Felt, salt: Felt) -> Result<Felt> {
+        let factory = self.account_factory(class_hash).await?;
+        let deployment = factory.deploy_v3(salt);
+        self.ensure_deployed_at(deployment.address())?;
+        let res = deployment
+            .send()
+            .await
+            .map_err(|e| anyhow!(format!("{:?}", e)))?;
+        Ok(res.transaction_hash)
+    }
+
+    async fn account_factory(
+        &self,
+        class_hash: Felt,
+    ) -> Result<OpenZeppelinAccountFactory<BotSigner, Arc<JsonRpcClient<HttpTransport>>>> {
+        let mut factory = OpenZeppelinAccountFactory::new(
+            class_hash,
+            self.0.chain_id(),
+            self.1.clone(),
+            self.0.provider().clone(),
+        )
+        .await?;
+        factory.set_block_id(BlockId::Tag(BlockTag::PreConfirmed));
+        Ok(factory)
+    }
+
+    fn check_session_calls(&self, txs: &[Call]) -> Result<()> {
+        match self.session() {
+            Some(session) => session.check_calls(txs),
+            None => Ok(()),
+        }
+    }
+
+    fn ensure_deployed_at(&self, address: Felt) -> Result<()> {
+        anyhow::ensure!(
+            address == self.account_address(),
+            "The account would be deployed at {address:#x} instead of {:#x}: check --account-class-hash & --account-salt",
+            self.account_address()
+        );
+        Ok(())
+    }
+
     /// Simulate a set of TXs and return the estimation of the fee necessary
     /// to execute them.
     pub async fn estimate_fees_cost(&self, txs: &[Call]) -> Result<BigDecimal> {
-        let estimation = self.0.execute_v3(txs.to_vec()).estimate_fee().await?;
+        self.check_session_calls(txs)?;
+        let estimation = self
+            .0
+            .execute_v3(txs.to_vec())
+            .estimate_fee()
+            .await
+            .map_err(|e| LiquidatorError::rpc(format!("{:?}", e)))?;
         Ok(BigDecimal::new(
             estimation.overall_fee.to_bigint().unwrap(),
             VESU_RESPONSE_DECIMALS,
         ))
     }
 
-    /// Executes a set of transactions and returns the transaction hash.
-    pub async fn execute_txs(&self, txs: &[Call]) -> Result<Felt> {
+    /// Simulates a set of TXs against the pre-confirmed state, fees & nonce
+    /// included, without sending them.
+    pub async fn simulate_txs(&self, txs: &[Call]) -> Result<SimulatedTransaction> {
+        self.check_session_calls(txs)?;
+        self.0
+            .execute_v3(txs.to_vec())
+            .simulate(false, false)
+            .await
+            .map_err(|e| LiquidatorError::rpc(format!("{:?}", e)).into())
+    }
+
+    /// Signs an off-chain message of the account, e.g. an outside execution.
+    pub async fn sign_typed_data(&self, typed_data: &TypedData) -> Result<Vec<Felt>> {
+        let hash = typed_data.message_hash(self.account_address())?;
+        let signature = self.1.sign_hash(&hash).await?;
+        match self.session() {
+            Some(session) => session.signature(signature),
+            None => Ok(vec![signature.r, signature.s]),
+        }
+    }
+
+    /// Returns the nonce of the next transaction of the account.
+    pub async fn get_nonce(&self) -> Result<Felt> {
+        Ok(self.0.get_nonce().await?)
+    }
+
+    /// Executes a set of transactions with the given nonce, the estimated gas
+    /// prices being multiplied by `gas_price_multiplier`.
+    pub async fn execute_txs_with_nonce(
+        &self,
+        txs: &[Call],
+        nonce: Felt,
+        gas_price_multiplier: f64,
+    ) -> Result<Felt> {
+        self.check_session_calls(txs)?;
         let res = self
             .0
             .execute_v3(txs.to_vec())
+            .nonce(nonce)
+            .gas_price_estimate_multiplier(gas_price_multiplier)
             .send()
             .await
-            .map_err(|e| anyhow::anyhow!(format!("{:?}", e)))?;
+            .map_err(|e| LiquidatorError::rpc(format!("{:?}", e)))?;
         Ok(res.transaction_hash)
     }
 }
@@ -81,6 +281,7 @@ pub struct StarknetAccountBuilder {
     account_address: Option<Felt>,
     chain_id: Option<Felt>,
     rpc_client: Option<Arc<JsonRpcClient<HttpTransport>>>,
+    session: Option<Session>,
 }
 
 impl StarknetAccountBuilder {
@@ -88,6 +289,15 @@ impl StarknetAccountBuilder {
         StarknetAccountBuilder::default()
     }
 
+    pub fn for_network(network: NetworkName) -> Self {
+        let builder = StarknetAccountBuilder::default();
+        match network {
+            NetworkName::Mainnet => builder.on_mainnet(),
+            NetworkName::Sepolia => builder.on_sepolia(),
+            NetworkName::Katana => builder.on_katana(),
+        }
+    }
+
     pub fn on_mainnet(mut self) -> Self {
         self.chain_id = Some(chain_id::MAINNET);
         self
@@ -97,6 +307,12 @@ impl StarknetAccountBuilder {
         self.chain_id = Some(chain_id::SEPOLIA);
         self
     }
+
+    pub fn on_katana(mut self) -> Self {
+        self.chain_id = Some(KATANA_CHAIN_ID);
+        self
+    }
+
     pub fn as_account(mut self, account_address: Felt) -> Self {
         self.account_address = Some(account_address);
         self
@@ -107,10 +323,21 @@ impl StarknetAccountBuilder {
         self
     }
 
+    /// Signs the transactions with the key of the session instead of the
+    /// key of the owner of the account.
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.session = Some(session);
+        self
+    }
+
     pub fn from_secret(self, private_key: Felt) -> Result<StarknetAccount> {
         let signing_key = SigningKey::from_secret_scalar(private_key);
         let signer = LocalWallet::from(signing_key);
-        self.build(signer)
+        self.build(BotSigner::Local(signer))
+    }
+
+    pub fn from_remote_signer(self, remote_signer: RemoteSigner) -> Result<StarknetAccount> {
+        self.build(BotSigner::Remote(remote_signer))
     }
 
     pub fn from_keystore(
@@ -118,15 +345,21 @@ impl StarknetAccountBuilder {
         keystore_path: PathBuf,
         keystore_password: &str,
     ) -> Result<StarknetAccount> {
-        let signing_key = SigningKey::from_keystore(keystore_path, keystore_password)?;
+        let signing_key =
+            SigningKey::from_keystore(&keystore_path, keystore_password).map_err(|e| {
+                anyhow::anyhow!(
+                    "Could not decrypt the keystore {}: {e}",
+                    keystore_path.display()
+                )
+            })?;
         let signer = LocalWallet::from(signing_key);
-        self.build(signer)
+        self.build(BotSigner::Local(signer))
     }
 
-    fn build(self, signer: LocalWallet) -> Result<StarknetAccount> {
+    fn build(self, signer: BotSigner) -> Result<StarknetAccount> {
         let mut account = SingleOwnerAccount::new(
             self.rpc_client.unwrap(),
-            signer,
+            signer.clone(),
             self.account_address.unwrap(),
             self.chain_id.unwrap(),
             ExecutionEncoding::New,
@@ -134,6 +367,524 @@ impl StarknetAccountBuilder {
 
         account.set_block_id(BlockId::Tag(BlockTag::PreConfirmed));
 
-        Ok(StarknetAccount(Arc::new(account)))
+        let account = BotAccount {
+            account,
+            signer: signer.clone(),
+            session: self.session,
+        };
+        Ok(StarknetAccount(Arc::new(account), signer))
+    }
+}
+
+/// Account contract of the bot, its transactions signed by the owner key or,
+/// with a [`Session`], by a session key the account restricts to the calls of
+/// the session.
+pub struct BotAccount {
+    account: SingleOwnerAccount<Arc<JsonRpcClient<HttpTransport>>, BotSigner>,
+    signer: BotSigner,
+    session: Option<Session>,
+}
+
+impl ExecutionEncoder for BotAccount {
+    fn encode_calls(&self, calls: &[Call]) -> Vec<Felt> {
+        self.account.encode_calls(calls)
+    }
+}
+
+#[async_trait::async_trait]
+impl Account for BotAccount {
+    type SignError = SignerError;
+
+    fn address(&self) -> Felt {
+        self.account.address()
+    }
+
+    fn chain_id(&self) -> Felt {
+        self.account.chain_id()
+    }
+
+    async fn sign_execution_v3(
+        &self,
+        execution: &RawExecutionV3,
+        query_only: bool,
+    ) -> Result<Vec<Felt>, Self::SignError> {
+        let Some(session) = &self.session else {
+            return self
+                .account
+                .sign_execution_v3(execution, query_only)
+                .await
+                .map_err(SignerError::new);
+        };
+        let hash = execution.transaction_hash(self.chain_id(), self.address(), query_only, self);
+        let signature = self.signer.sign_hash(&hash).await?;
+        session.signature(signature).map_err(SignerError::new)
+    }
+
+    async fn sign_declaration_v3(
+        &self,
+        declaration: &RawDeclarationV3,
+        query_only: bool,
+    ) -> Result<Vec<Felt>, Self::SignError> {
+        self.account
+            .sign_declaration_v3(declaration, query_only)
+            .await
+            .map_err(SignerError::new)
+    }
+
+    fn is_signer_interactive(&self, context: SignerInteractivityContext<'_>) -> bool {
+        self.account.is_signer_interactive(context)
+    }
+}
+
+#[async_trait::async_trait]
+impl ConnectedAccount for BotAccount {
+    type Provider = Arc<JsonRpcClient<HttpTransport>>;
+
+    fn provider(&self) -> &Self::Provider {
+        self.account.provider()
+    }
+
+    fn block_id(&self) -> BlockId {
+        self.account.block_id()
+    }
+}
+
+/// Signer of the transactions of an account: a key held by the bot, or a
+/// remote signer so the key never sits in the memory of the process.
+#[derive(Debug, Clone)]
+pub enum BotSigner {
+    Local(LocalWallet),
+    Remote(RemoteSigner),
+}
+
+#[async_trait::async_trait]
+impl Signer for BotSigner {
+    type GetPublicKeyError = SignerError;
+    type SignError = SignerError;
+
+    async fn get_public_key(&self) -> Result<VerifyingKey, Self::GetPublicKeyError> {
+        match self {
+            BotSigner::Local(wallet) => wallet.get_public_key().await.map_err(SignerError::new),
+            BotSigner::Remote(remote_signer) => remote_signer.public_key().await,
+        }
+    }
+
+    async fn sign_hash(&self, hash: &Felt) -> Result<Signature, Self::SignError> {
+        match self {
+            BotSigner::Local(wallet) => wallet.sign_hash(hash).await.map_err(SignerError::new),
+            BotSigner::Remote(remote_signer) => remote_signer.sign_hash(hash).await,
+        }
+    }
+
+    /// The remote signer only signs the transactions sent: the simulations &
+    /// fee estimations are run without signature, skipping the validation.
+    fn is_interactive(&self, _context: SignerInteractivityContext<'_>) -> bool {
+        matches!(self, BotSigner::Remote(_))
+    }
+}
+
+/// Error of a [`BotSigner`].
+#[derive(Debug)]
+pub struct SignerError(String);
+
+impl fmt::Display for SignerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Signer error: {}", self.0)
+    }
+}
+
+impl std::error::Error for SignerError {}
+
+impl SignerError {
+    fn new(e: impl fmt::Display) -> Self {
+        SignerError(e.to_string())
+    }
+}
+
+/// Signer holding the key out of the bot, e.g. a signing service backed by
+/// an HSM or a KMS, reached over HTTP:
+/// - `GET {url}/public_key` returns `{"public_key": "0x..."}`,
+/// - `POST {url}/sign` with `{"hash": "0x..."}` returns `{"r": "0x...", "s": "0x..."}`.
+///
+/// Requests are authenticated with the bearer token, if any.
+#[derive(Clone)]
+pub struct RemoteSigner {
+    http_client: reqwest::Client,
+    url: Url,
+    token: Option<String>,
+}
+
+impl fmt::Debug for RemoteSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteSigner")
+            .field("url", &self.url.as_str())
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PublicKeyResponse {
+    public_key: Felt,
+}
+
+#[derive(Debug, Serialize)]
+struct SignRequest {
+    hash: Felt,
+}
+
+#[derive(Debug, Deserialize)]
+struct SignResponse {
+    r: Felt,
+    s: Felt,
+}
+
+impl RemoteSigner {
+    pub fn new(url: Url, token: Option<String>) -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            url,
+            token,
+        }
+    }
+
+    async fn public_key(&self) -> Result<VerifyingKey, SignerError> {
+        let response: PublicKeyResponse = self
+            .request(self.http_client.get(self.endpoint("public_key")?))
+            .await?;
+        Ok(VerifyingKey::from_scalar(response.public_key))
+    }
+
+    async fn sign_hash(&self, hash: &Felt) -> Result<Signature, SignerError> {
+        let request = self
+            .http_client
+            .post(self.endpoint("sign")?)
+            .json(&SignRequest { hash: *hash });
+        let response: SignResponse = self.request(request).await?;
+        Ok(Signature {
+            r: response.r,
+            s: response.s,
+        })
+    }
+
+    fn endpoint(&self, path: &str) -> Result<Url, SignerError> {
+        let base = self.url.as_str().trim_end_matches('/');
+        Url::parse(&format!("{base}/{path}")).map_err(SignerError::new)
+    }
+
+    async fn request<T: serde::de::DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T, SignerError> {
+        let request = match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        };
+        request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(SignerError::new)?
+            .json()
+            .await
+            .map_err(SignerError::new)
+    }
+}
+
+/// Tracked state of an account of the [`AccountPool`].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AccountState {
+    /// Nonce of the next transaction, ahead of the chain while transactions
+    /// are pending. `None` until known or after a failed submission.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_nonce: Option<Felt>,
+    /// Last known balance of the fee token, lowered by the fees of the sent
+    /// transactions paid in that token.
+    #[serde(
+        serialize_with = "optional_plain_decimal",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub fee_balance: Option<BigDecimal>,
+    /// Unit of the fees paid in the token of `fee_balance`.
+    #[serde(skip)]
+    pub fee_unit: Option<PriceUnit>,
+    /// Transactions being sent by the account.
+    pub pending: u32,
+    /// Transactions accepted since the start of the bot.
+    pub sent: u64,
+    pub refreshed_at: Option<u64>,
+}
+
+/// Accounts the transactions are rotated across: each one is sent by the
+/// funded account with the fewest pending transactions, the first account
+/// being the liquidator account receiving the earnings.
+#[derive(Clone)]
+pub struct AccountPool {
+    accounts: Arc<Vec<StarknetAccount>>,
+    states: Arc<DashMap<Felt, AccountState>>,
+    min_fee_balance: BigDecimal,
+}
+
+impl AccountPool {
+    pub fn new(
+        primary: StarknetAccount,
+        extras: Vec<StarknetAccount>,
+        min_fee_balance: f64,
+    ) -> Self {
+        let mut accounts = vec![primary];
+        accounts.extend(extras);
+        let states = accounts
+            .iter()
+            .map(|account| (account.account_address(), AccountState::default()))
+            .collect();
+        Self {
+            accounts: Arc::new(accounts),
+            states: Arc::new(states),
+            min_fee_balance: BigDecimal::from_f64(min_fee_balance).unwrap_or_default(),
+        }
+    }
+
+    /// A pool of the liquidator account only.
+    pub fn single(account: StarknetAccount) -> Self {
+        Self::new(account, vec![], 0.0)
+    }
+
+    /// Returns the liquidator account.
+    pub fn primary(&self) -> &StarknetAccount {
+        &self.accounts[0]
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    /// Whether `address` is one of the accounts of the pool.
+    pub fn contains(&self, address: Felt) -> bool {
+        self.states.contains_key(&address)
+    }
+
+    /// Returns the state of each account, in order.
+    pub fn states(&self) -> Vec<(Felt, AccountState)> {
+        self.accounts
+            .iter()
+            .map(|account| {
+                let address = account.account_address();
+                let state = self
+                    .states
+                    .get(&address)
+                    .map(|state| state.clone())
+                    .unwrap_or_default();
+                (address, state)
+            })
+            .collect()
+    }
+
+    /// Leases the account sending the next transaction, until the lease is
+    /// dropped.
+    pub fn acquire(&self) -> AccountLease {
+        let index = select_account(&self.states(), &self.min_fee_balance);
+        let account = self.accounts[index].clone();
+        if let Some(mut state) = self.states.get_mut(&account.account_address()) {
+            state.pending += 1;
+        }
+        AccountLease {
+            account,
+            states: self.states.clone(),
+        }
+    }
+
+    /// Returns the nonce of the next transaction of the account, the highest
+    /// of the chain & of the transactions pending locally.
+    pub fn reserve_nonce(&self, address: Felt, chain_nonce: Felt) -> Felt {
+        let mut state = self.states.entry(address).or_default();
+        let nonce = state
+            .next_nonce
+            .map_or(chain_nonce, |next_nonce| next_nonce.max(chain_nonce));
+        state.next_nonce = Some(nonce + Felt::ONE);
+        nonce
+    }
+
+    /// Forgets the local nonce of the account, e.g. after a failed
+    /// submission: the next one starts from the nonce of the chain.
+    pub fn reset_nonce(&self, address: Felt) {
+        if let Some(mut state) = self.states.get_mut(&address) {
+            state.next_nonce = None;
+        }
+    }
+
+    /// Records an accepted transaction, its fee lowering the balance if paid
+    /// in the same token. The fees paid by a paymaster are not known.
+    pub fn record_sent(&self, address: Felt, fee: Option<&FeePayment>) {
+        let Some(mut state) = self.states.get_mut(&address) else {
+            return;
+        };
+        state.sent += 1;
+        let fee_unit = state.fee_unit.clone();
+        if let Some(fee) = fee.filter(|fee| fee_unit.as_ref() == Some(&fee.unit)) {
+            let fee = BigDecimal::new(
+                BigInt::from_bytes_be(Sign::Plus, &fee.amount.to_bytes_be()),
+                FEE_DECIMALS,
+            );
+            state.fee_balance = state.fee_balance.take().map(|balance| balance - fee);
+        }
+    }
+
+    /// Refreshes the nonces & the balances of the fee token at
+    /// `token_address` of the accounts from the chain. An account that could
+    /// not be refreshed keeps its previous state.
+    pub async fn refresh(
+        &self,
+        rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
+        fee_token: FeeToken,
+        token_address: Felt,
+    ) {
+        let fee_unit = match fee_token {
+            FeeToken::Strk => PriceUnit::Fri,
+            FeeToken::Eth => PriceUnit::Wei,
+        };
+        for account in self.accounts.iter() {
+            let address = account.account_address();
+            let balance = match fetch_fee_balance(rpc_client, address, token_address).await {
+                Ok(balance) => balance,
+                Err(e) => {
+                    tracing::warn!(
+                        error = %e,
+                        "[🔭 Monitoring] Could not refresh the balance of account {address:#x}"
+                    );
+                    continue;
+                }
+            };
+            let chain_nonce = account.get_nonce().await.ok();
+            let mut state = self.states.entry(address).or_default();
+            if state.pending == 0 {
+                state.next_nonce = chain_nonce;
+            }
+            if balance < self.min_fee_balance && self.accounts.len() > 1 {
+                tracing::warn!(
+                    "[🔭 Monitoring] Account {address:#x} has {balance} {} left, it is left out of the rotation",
+                    fee_token.ticker()
+                );
+            }
+            state.fee_balance = Some(balance);
+            state.fee_unit = Some(fee_unit.clone());
+            state.refreshed_at = Some(unix_now());
+        }
+    }
+}
+
+/// Account leased from an [`AccountPool`] to send a transaction.
+pub struct AccountLease {
+    account: StarknetAccount,
+    states: Arc<DashMap<Felt, AccountState>>,
+}
+
+impl AccountLease {
+    pub fn account(&self) -> &StarknetAccount {
+        &self.account
+    }
+}
+
+impl Drop for AccountLease {
+    fn drop(&mut self) {
+        if let Some(mut state) = self.states.get_mut(&self.account.account_address()) {
+            state.pending = state.pending.saturating_sub(1);
+        }
+    }
+}
+
+/// Returns the index of the account sending the next transaction: the one
+/// with the fewest pending transactions among those with enough STRK - or
+/// with an unknown balance - the first one on ties. Without any funded
+/// account, the least busy one is used anyway.
+fn select_account(states: &[(Felt, AccountState)], min_fee_balance: &BigDecimal) -> usize {
+    let least_pending = |funded_only: bool| {
+        states
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, state))| {
+                !funded_only
+                    || state
+                        .fee_balance
+                        .as_ref()
+                        .is_none_or(|balance| balance >= min_fee_balance)
+            })
+            .min_by_key(|(index, (_, state))| (state.pending, *index))
+            .map(|(index, _)| index)
+    };
+    least_pending(true)
+        .or_else(|| least_pending(false))
+        .unwrap_or_default()
+}
+
+/// Returns the balance of the account in a fee token, STRK or ETH.
+async fn fetch_fee_balance(
+    rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
+    address: Felt,
+    fee_token: Felt,
+) -> Result<BigDecimal> {
+    let balance_request = FunctionCall {
+        contract_address: fee_token,
+        entry_point_selector: *BALANCE_OF_SELECTOR,
+        calldata: vec![address],
+    };
+    let call_result = rpc_client
+        .call(balance_request, BlockId::Tag(BlockTag::PreConfirmed))
+        .await?;
+    Ok(hex_str_to_big_decimal(
+        &call_result[0].to_hex_string(),
+        FEE_DECIMALS,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+    use starknet::core::types::Felt;
+    use url::Url;
+
+    use super::{AccountState, RemoteSigner, select_account};
+
+    fn state(pending: u32, fee_balance: Option<&str>) -> AccountState {
+        AccountState {
+            pending,
+            fee_balance: fee_balance.map(|balance| BigDecimal::from_str(balance).unwrap()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_select_account() {
+        let min = BigDecimal::from(1);
+        let states = vec![
+            (Felt::ONE, state(1, Some("10"))),
+            (Felt::TWO, state(0, Some("0.5"))),
+            (Felt::THREE, state(0, None)),
+        ];
+        // The second one is not funded enough.
+        assert_eq!(select_account(&states, &min), 2);
+
+        let states = vec![
+            (Felt::ONE, state(0, Some("10"))),
+            (Felt::TWO, state(0, Some("10"))),
+        ];
+        assert_eq!(select_account(&states, &min), 0);
+
+        let states = vec![
+            (Felt::ONE, state(2, Some("0"))),
+            (Felt::TWO, state(1, Some("0"))),
+        ];
+        assert_eq!(select_account(&states, &min), 1);
+    }
+
+    #[test]
+    fn test_remote_signer_endpoint() {
+        for url in ["https://signer.xyz/v1", "https://signer.xyz/v1/"] {
+            let signer = RemoteSigner::new(Url::parse(url).unwrap(), None);
+            assert_eq!(
+                signer.endpoint("sign").unwrap().as_str(),
+                "https://signer.xyz/v1/sign"
+            );
+        }
     }
 }
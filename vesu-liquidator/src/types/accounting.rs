@@ -0,0 +1,495 @@
+use std::fmt;
+
+use bigdecimal::{
+    BigDecimal, RoundingMode,
+    num_bigint::{BigInt, Sign},
+};
+use cainome::cairo_serde::U256;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{FeePayment, Felt, PriceUnit};
+
+use crate::{
+    config::Config,
+    services::{oracle::LatestOraclePrices, tokens::TokenRegistry},
+    utils::{
+        conversions::u256_to_big_uint,
+        serialization::{optional_plain_decimal, plain_decimal},
+    },
+};
+
+/// Decimals of the fees paid in STRK (fri) or ETH (wei).
+const FEE_DECIMALS: i64 = 18;
+
+/// Decimals of the USD values in the logs.
+const USD_DISPLAY_DECIMALS: i64 = 2;
+
+/// Amount of a token valued in USD at the oracle price at execution time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValuedAmount {
+    pub token: Felt,
+    pub ticker: String,
+    #[serde(serialize_with = "plain_decimal")]
+    pub amount: BigDecimal,
+    /// `None` if the token had no fresh price.
+    #[serde(serialize_with = "optional_plain_decimal")]
+    pub usd: Option<BigDecimal>,
+}
+
+impl ValuedAmount {
+    /// The USD value in plain notation for the structured log fields, empty
+    /// without a price.
+    pub fn usd_string(&self) -> String {
+        self.usd
+            .as_ref()
+            .map(BigDecimal::to_plain_string)
+            .unwrap_or_default()
+    }
+}
+
+/// e.g. `0.05 ETH ($125.30)`, `$?` without a price.
+impl fmt::Display for ValuedAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let amount = self.amount.normalized().to_plain_string();
+        match &self.usd {
+            Some(usd) => write!(
+                f,
+                "{amount} {} (${})",
+                self.ticker,
+                usd.with_scale_round(USD_DISPLAY_DECIMALS, RoundingMode::HalfEven)
+            ),
+            None => write!(f, "{amount} {} ($?)", self.ticker),
+        }
+    }
+}
+
+/// What a liquidation sent by the bot seized, repaid, earned & cost.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LiquidationPnl {
+    pub tx_hash: Felt,
+    pub block_number: u64,
+    pub executed_at: u64,
+    pub pool_id: Felt,
+    pub user: Felt,
+    pub collateral_seized: ValuedAmount,
+    pub debt_repaid: ValuedAmount,
+    /// Collateral left to the bot once the debt is repaid.
+    pub earnings: ValuedAmount,
+    pub gas: ValuedAmount,
+}
+
+/// A swap of the earnings to the payout token, `bought` being the minimum
+/// amount received given the max slippage.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SwapPnl {
+    pub sold: ValuedAmount,
+    pub bought: ValuedAmount,
+}
+
+/// A transfer of a distribution.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PayoutPnl {
+    pub recipient: Felt,
+    pub paid: ValuedAmount,
+}
+
+/// What a distribution multicall swapped, paid & cost.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DistributionPnl {
+    pub tx_hash: Felt,
+    pub block_number: u64,
+    pub executed_at: u64,
+    /// Liquidations whose earnings were distributed.
+    pub liquidation_txs: Vec<Felt>,
+    pub swaps: Vec<SwapPnl>,
+    pub payouts: Vec<PayoutPnl>,
+    pub gas: ValuedAmount,
+}
+
+/// Liquidations & distributions sent by the bot, recorded for its PnL.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PnlLedger {
+    pub liquidations: Vec<LiquidationPnl>,
+    pub distributions: Vec<DistributionPnl>,
+}
+
+/// Cumulative PnL of the bot in USD: the earnings of the liquidations minus
+/// the gas, the value lost in the swaps & the payouts.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PnlSummary {
+    pub liquidations: usize,
+    pub distributions: usize,
+    #[serde(serialize_with = "plain_decimal")]
+    pub earnings_usd: BigDecimal,
+    #[serde(serialize_with = "plain_decimal")]
+    pub gas_usd: BigDecimal,
+    #[serde(serialize_with = "plain_decimal")]
+    pub swap_costs_usd: BigDecimal,
+    #[serde(serialize_with = "plain_decimal")]
+    pub payouts_usd: BigDecimal,
+    #[serde(serialize_with = "plain_decimal")]
+    pub net_usd: BigDecimal,
+    /// Amounts left out of the totals for lack of a price at execution time.
+    pub unpriced_amounts: usize,
+    /// Fees paid per liquidation & per distribution, read from the receipts,
+    /// to calibrate the min expected profits on the observed costs.
+    pub liquidation_costs: CostStats,
+    pub distribution_costs: CostStats,
+}
+
+/// Distribution of the fees paid by priced transactions, in USD.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CostStats {
+    pub transactions: usize,
+    #[serde(serialize_with = "plain_decimal")]
+    pub mean_usd: BigDecimal,
+    #[serde(serialize_with = "plain_decimal")]
+    pub median_usd: BigDecimal,
+    #[serde(serialize_with = "plain_decimal")]
+    pub p90_usd: BigDecimal,
+    #[serde(serialize_with = "plain_decimal")]
+    pub max_usd: BigDecimal,
+}
+
+impl CostStats {
+    /// The stats of the priced fees, zero without any.
+    pub fn new<'a>(fees: impl IntoIterator<Item = &'a ValuedAmount>) -> Self {
+        let mut costs: Vec<BigDecimal> =
+            fees.into_iter().filter_map(|fee| fee.usd.clone()).collect();
+        if costs.is_empty() {
+            return Self::default();
+        }
+        costs.sort();
+        let percentile = |p: usize| costs[(costs.len() - 1) * p / 100].clone();
+        let total: BigDecimal = costs.iter().sum();
+        Self {
+            transactions: costs.len(),
+            mean_usd: total / BigDecimal::from(costs.len() as u64),
+            median_usd: percentile(50),
+            p90_usd: percentile(90),
+            max_usd: costs[costs.len() - 1].clone(),
+        }
+    }
+}
+
+impl PnlSummary {
+    pub fn new(liquidations: &[LiquidationPnl], distributions: &[DistributionPnl]) -> Self {
+        let mut unpriced_amounts = 0;
+        let mut earnings_usd = BigDecimal::from(0);
+        let mut gas_usd = BigDecimal::from(0);
+        let mut swap_costs_usd = BigDecimal::from(0);
+        let mut payouts_usd = BigDecimal::from(0);
+        for liquidation in liquidations {
+            earnings_usd += usd(&liquidation.earnings, &mut unpriced_amounts);
+            gas_usd += usd(&liquidation.gas, &mut unpriced_amounts);
+        }
+        for distribution in distributions {
+            gas_usd += usd(&distribution.gas, &mut unpriced_amounts);
+            for swap in distribution.swaps.iter() {
+                // Both sides must be priced for the cost to be meaningful.
+                match (&swap.sold.usd, &swap.bought.usd) {
+                    (Some(sold), Some(bought)) => swap_costs_usd += sold - bought,
+                    _ => unpriced_amounts += 1,
+                }
+            }
+            for payout in distribution.payouts.iter() {
+                payouts_usd += usd(&payout.paid, &mut unpriced_amounts);
+            }
+        }
+        let net_usd = &earnings_usd - &gas_usd - &swap_costs_usd - &payouts_usd;
+        PnlSummary {
+            liquidations: liquidations.len(),
+            distributions: distributions.len(),
+            earnings_usd,
+            gas_usd,
+            swap_costs_usd,
+            payouts_usd,
+            net_usd,
+            unpriced_amounts,
+            liquidation_costs: CostStats::new(liquidations.iter().map(|l| &l.gas)),
+            distribution_costs: CostStats::new(distributions.iter().map(|d| &d.gas)),
+        }
+    }
+}
+
+/// Returns the USD value of the amount, zero & counted as unpriced if unknown.
+fn usd(amount: &ValuedAmount, unpriced_amounts: &mut usize) -> BigDecimal {
+    match &amount.usd {
+        Some(usd) => usd.clone(),
+        None => {
+            *unpriced_amounts += 1;
+            BigDecimal::from(0)
+        }
+    }
+}
+
+impl PnlLedger {
+    pub fn summary(&self) -> PnlSummary {
+        PnlSummary::new(&self.liquidations, &self.distributions)
+    }
+
+    /// Exports the ledger to CSV, one line per amount seized, repaid, earned,
+    /// swapped, paid or spent in gas, for offline analysis.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "kind,tx_hash,block_number,executed_at,entry,recipient,token,ticker,amount,usd\n",
+        );
+        for liquidation in self.liquidations.iter() {
+            let entries = [
+                ("collateral_seized", &liquidation.collateral_seized),
+                ("debt_repaid", &liquidation.debt_repaid),
+                ("earnings", &liquidation.earnings),
+                ("gas", &liquidation.gas),
+            ];
+            for (entry, amount) in entries {
+                csv.push_str(&csv_line(
+                    "liquidation",
+                    liquidation.tx_hash,
+                    liquidation.block_number,
+                    liquidation.executed_at,
+                    entry,
+                    None,
+                    amount,
+                ));
+            }
+        }
+        for distribution in self.distributions.iter() {
+            let line = |entry: &str, recipient: Option<Felt>, amount: &ValuedAmount| {
+                csv_line(
+                    "distribution",
+                    distribution.tx_hash,
+                    distribution.block_number,
+                    distribution.executed_at,
+                    entry,
+                    recipient,
+                    amount,
+                )
+            };
+            for swap in distribution.swaps.iter() {
+                csv.push_str(&line("swap_sold", None, &swap.sold));
+                csv.push_str(&line("swap_bought", None, &swap.bought));
+            }
+            for payout in distribution.payouts.iter() {
+                csv.push_str(&line("payout", Some(payout.recipient), &payout.paid));
+            }
+            csv.push_str(&line("gas", None, &distribution.gas));
+        }
+        csv
+    }
+}
+
+fn csv_line(
+    kind: &str,
+    tx_hash: Felt,
+    block_number: u64,
+    executed_at: u64,
+    entry: &str,
+    recipient: Option<Felt>,
+    amount: &ValuedAmount,
+) -> String {
+    format!(
+        "{kind},{tx_hash:#x},{block_number},{executed_at},{entry},{},{:#x},{},{},{}\n",
+        recipient.map(|r| format!("{r:#x}")).unwrap_or_default(),
+        amount.token,
+        amount.ticker,
+        amount.amount.to_plain_string(),
+        amount
+            .usd
+            .as_ref()
+            .map(BigDecimal::to_plain_string)
+            .unwrap_or_default()
+    )
+}
+
+/// Values the amounts of the tokens in USD at the latest oracle prices, with
+/// the symbol & decimals of the [`TokenRegistry`].
+#[derive(Clone)]
+pub struct Valuator {
+    config: Config,
+    prices: LatestOraclePrices,
+    tokens: TokenRegistry,
+}
+
+impl Valuator {
+    pub fn new(config: Config, prices: LatestOraclePrices, tokens: TokenRegistry) -> Self {
+        Self {
+            config,
+            prices,
+            tokens,
+        }
+    }
+
+    /// Values an amount of `token` in its smallest unit. Tokens without
+    /// metadata are kept in their smallest unit, without a price.
+    pub fn value(&self, token: Felt, amount: &U256) -> ValuedAmount {
+        let raw = BigInt::from(u256_to_big_uint(amount));
+        match self.tokens.cached(&token) {
+            Some(metadata) => self.valued(
+                token,
+                &metadata.symbol,
+                BigDecimal::new(raw, metadata.decimals),
+            ),
+            None => ValuedAmount {
+                token,
+                ticker: format!("{token:#x}"),
+                amount: BigDecimal::from(raw),
+                usd: None,
+            },
+        }
+    }
+
+    /// Values the fee paid for a transaction, in STRK or in ETH.
+    pub fn gas(&self, fee: &FeePayment) -> ValuedAmount {
+        let ticker = match fee.unit {
+            PriceUnit::Fri => "STRK",
+            PriceUnit::Wei => "ETH",
+        };
+        let amount = BigDecimal::new(
+            BigInt::from_bytes_be(Sign::Plus, &fee.amount.to_bytes_be()),
+            FEE_DECIMALS,
+        );
+        let token = self
+            .config
+            .get_asset_address_for_ticker(ticker)
+            .unwrap_or_default();
+        self.valued(token, ticker, amount)
+    }
+
+    /// Prices the configured assets by their ticker, the others by their
+    /// symbol.
+    fn valued(&self, token: Felt, ticker: &str, amount: BigDecimal) -> ValuedAmount {
+        let price_ticker = self
+            .config
+            .get_asset_ticker_for_address(&token)
+            .unwrap_or_else(|| ticker.to_string());
+        let usd = self
+            .prices
+            .fresh_price(&price_ticker.to_lowercase())
+            .ok()
+            .map(|price| &amount * price);
+        ValuedAmount {
+            token,
+            ticker: ticker.to_string(),
+            amount,
+            usd,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+    use starknet::core::types::Felt;
+
+    use super::{
+        CostStats, DistributionPnl, LiquidationPnl, PayoutPnl, PnlLedger, PnlSummary, SwapPnl,
+        ValuedAmount,
+    };
+
+    fn valued(amount: &str, usd: Option<&str>) -> ValuedAmount {
+        ValuedAmount {
+            token: Felt::ONE,
+            ticker: "ETH".to_string(),
+            amount: BigDecimal::from_str(amount).unwrap(),
+            usd: usd.map(|usd| BigDecimal::from_str(usd).unwrap()),
+        }
+    }
+
+    fn ledger() -> PnlLedger {
+        PnlLedger {
+            liquidations: vec![LiquidationPnl {
+                tx_hash: Felt::ONE,
+                block_number: 10,
+                executed_at: 1_000,
+                pool_id: Felt::ONE,
+                user: Felt::TWO,
+                collateral_seized: valued("1", Some("2000")),
+                debt_repaid: valued("1900", Some("1900")),
+                earnings: valued("0.05", Some("100")),
+                gas: valued("0.5", Some("0.25")),
+            }],
+            distributions: vec![DistributionPnl {
+                tx_hash: Felt::TWO,
+                block_number: 12,
+                executed_at: 1_010,
+                liquidation_txs: vec![Felt::ONE],
+                swaps: vec![SwapPnl {
+                    sold: valued("0.05", Some("100")),
+                    bought: valued("99", Some("99")),
+                }],
+                payouts: vec![PayoutPnl {
+                    recipient: Felt::THREE,
+                    paid: valued("99", Some("99")),
+                }],
+                gas: valued("0.2", None),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_pnl_summary() {
+        let summary = ledger().summary();
+        assert_eq!(
+            summary,
+            PnlSummary {
+                liquidations: 1,
+                distributions: 1,
+                earnings_usd: BigDecimal::from(100),
+                gas_usd: BigDecimal::from_str("0.25").unwrap(),
+                swap_costs_usd: BigDecimal::from(1),
+                payouts_usd: BigDecimal::from(99),
+                net_usd: BigDecimal::from_str("-0.25").unwrap(),
+                unpriced_amounts: 1,
+                liquidation_costs: CostStats {
+                    transactions: 1,
+                    mean_usd: BigDecimal::from_str("0.25").unwrap(),
+                    median_usd: BigDecimal::from_str("0.25").unwrap(),
+                    p90_usd: BigDecimal::from_str("0.25").unwrap(),
+                    max_usd: BigDecimal::from_str("0.25").unwrap(),
+                },
+                // The only fee is unpriced.
+                distribution_costs: CostStats::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_cost_stats() {
+        let fees: Vec<ValuedAmount> = (1..=10)
+            .map(|usd| valued("1", Some(&usd.to_string())))
+            .chain([valued("1", None)])
+            .collect();
+        let stats = CostStats::new(&fees);
+        assert_eq!(stats.transactions, 10);
+        assert_eq!(stats.mean_usd, BigDecimal::from_str("5.5").unwrap());
+        assert_eq!(stats.median_usd, BigDecimal::from(5));
+        assert_eq!(stats.p90_usd, BigDecimal::from(9));
+        assert_eq!(stats.max_usd, BigDecimal::from(10));
+    }
+
+    #[test]
+    fn test_valued_amount_display() {
+        assert_eq!(
+            valued("0.0500", Some("125.296")).to_string(),
+            "0.05 ETH ($125.30)"
+        );
+        assert_eq!(valued("12", None).to_string(), "12 ETH ($?)");
+    }
+
+    #[test]
+    fn test_pnl_csv() {
+        let csv = ledger().to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 1 + 4 + 4);
+        assert_eq!(
+            lines[3],
+            "liquidation,0x1,10,1000,earnings,,0x1,ETH,0.05,100"
+        );
+        assert_eq!(
+            lines[7],
+            "distribution,0x2,12,1010,payout,0x3,0x1,ETH,99,99"
+        );
+        assert_eq!(lines[8], "distribution,0x2,12,1010,gas,,0x1,ETH,0.2,");
+    }
+}
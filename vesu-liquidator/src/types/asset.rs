@@ -2,12 +2,13 @@ use bigdecimal::BigDecimal;
 use serde::{Deserialize, Serialize};
 use starknet::core::types::Felt;
 
-use crate::config::Config;
+use crate::{config::Config, utils::serialization::plain_decimal};
 
 #[derive(Default, Clone, Hash, Eq, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Asset {
     pub name: String,
     pub address: Felt,
+    #[serde(serialize_with = "plain_decimal")]
     pub amount: BigDecimal,
     pub decimals: i64,
 }
@@ -33,6 +34,16 @@ impl Asset {
     }
 }
 
+/// Latest oracle price of an asset kept in the storage, restored at startup
+/// so the bot doesn't start without prices.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StoredPrice {
+    #[serde(serialize_with = "plain_decimal")]
+    pub value: BigDecimal,
+    /// Unix timestamp (in seconds) of the update.
+    pub updated_at: u64,
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
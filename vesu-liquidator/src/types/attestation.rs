@@ -0,0 +1,170 @@
+use anyhow::Result;
+use cainome::cairo_serde::U256;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use starknet::core::types::{Felt, TypedData};
+
+/// Name of the SNIP-12 domain of the attestations.
+const ATTESTATION_DOMAIN: &str = "vesu-liquidator";
+
+/// A payout of a player, as attested by the bot.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttestedPayout {
+    pub player: Felt,
+    pub token: Felt,
+    pub amount: U256,
+    /// Liquidation whose earnings were paid, the last one for a payout
+    /// batching several liquidations.
+    pub liquidation_tx: Felt,
+    pub payout_tx: Felt,
+    /// Unix timestamp (in seconds) of the payout.
+    pub timestamp: u64,
+}
+
+/// Attestation of a payout signed by the account of the bot: the SNIP-12
+/// typed data of the payout, see [`PayoutAttestation::typed_data`], hashed
+/// for the account & signed with its key. Each attestation holds the hash of
+/// the previous one, so a payout removed from or altered in the log breaks
+/// the chain.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PayoutAttestation {
+    #[serde(flatten)]
+    pub payout: AttestedPayout,
+    /// Hash of the previous attestation of the log, zero for the first one.
+    pub previous: Felt,
+    /// Account of the bot which signed the attestation.
+    pub signer: Felt,
+    /// SNIP-12 message hash of the attestation for the `signer`.
+    pub hash: Felt,
+    /// Signature of the `hash`, checked by `is_valid_signature` of the
+    /// `signer` account.
+    pub signature: Vec<Felt>,
+}
+
+impl PayoutAttestation {
+    /// Returns the typed data of the payout attested after `previous`, on the
+    /// chain `chain_id`, e.g. `SN_MAIN`.
+    pub fn typed_data(payout: &AttestedPayout, previous: Felt, chain_id: &str) -> Value {
+        json!({
+            "types": {
+                "StarknetDomain": [
+                    { "name": "name", "type": "shortstring" },
+                    { "name": "version", "type": "shortstring" },
+                    { "name": "chainId", "type": "shortstring" },
+                    { "name": "revision", "type": "shortstring" }
+                ],
+                "PayoutAttestation": [
+                    { "name": "player", "type": "ContractAddress" },
+                    { "name": "token", "type": "ContractAddress" },
+                    { "name": "amount", "type": "u256" },
+                    { "name": "liquidation_tx", "type": "felt" },
+                    { "name": "payout_tx", "type": "felt" },
+                    { "name": "timestamp", "type": "timestamp" },
+                    { "name": "previous", "type": "felt" }
+                ]
+            },
+            "primaryType": "PayoutAttestation",
+            "domain": {
+                "name": ATTESTATION_DOMAIN,
+                "version": "1",
+                "chainId": chain_id,
+                "revision": "1"
+            },
+            "message": {
+                "player": format!("{:#x}", payout.player),
+                "token": format!("{:#x}", payout.token),
+                "amount": {
+                    "low": format!("{:#x}", payout.amount.low),
+                    "high": format!("{:#x}", payout.amount.high)
+                },
+                "liquidation_tx": format!("{:#x}", payout.liquidation_tx),
+                "payout_tx": format!("{:#x}", payout.payout_tx),
+                "timestamp": payout.timestamp,
+                "previous": format!("{previous:#x}")
+            }
+        })
+    }
+
+    /// Returns the message hash of the payout attested by `signer` after
+    /// `previous`.
+    pub fn message_hash(
+        payout: &AttestedPayout,
+        previous: Felt,
+        signer: Felt,
+        chain_id: &str,
+    ) -> Result<Felt> {
+        let typed_data: TypedData =
+            serde_json::from_value(Self::typed_data(payout, previous, chain_id))?;
+        Ok(typed_data.message_hash(signer)?)
+    }
+}
+
+/// Returns the index of the first attestation of the log whose hash doesn't
+/// match its payout or which doesn't follow the previous one, `None` if the
+/// whole chain is intact. The signatures are checked by the accounts.
+pub fn find_broken_link(attestations: &[PayoutAttestation], chain_id: &str) -> Option<usize> {
+    let mut previous = Felt::ZERO;
+    for (index, attestation) in attestations.iter().enumerate() {
+        let hash = PayoutAttestation::message_hash(
+            &attestation.payout,
+            attestation.previous,
+            attestation.signer,
+            chain_id,
+        );
+        if attestation.previous != previous || hash.ok() != Some(attestation.hash) {
+            return Some(index);
+        }
+        previous = attestation.hash;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use cainome::cairo_serde::U256;
+    use starknet::core::types::Felt;
+
+    use super::{AttestedPayout, PayoutAttestation, find_broken_link};
+
+    fn attest(payout: AttestedPayout, previous: Felt) -> PayoutAttestation {
+        let hash =
+            PayoutAttestation::message_hash(&payout, previous, Felt::ONE, "SN_MAIN").unwrap();
+        PayoutAttestation {
+            payout,
+            previous,
+            signer: Felt::ONE,
+            hash,
+            signature: vec![],
+        }
+    }
+
+    #[test]
+    fn test_attestations_chain() {
+        let payout = |player: u64, amount: u128| AttestedPayout {
+            player: Felt::from(player),
+            token: Felt::from(0x456),
+            amount: U256 {
+                low: amount,
+                high: 0,
+            },
+            liquidation_tx: Felt::from(0x789),
+            payout_tx: Felt::from(0xabc),
+            timestamp: 1_700_000_000,
+        };
+        let first = attest(payout(1, 42), Felt::ZERO);
+        let second = attest(payout(2, 10), first.hash);
+        let third = attest(payout(3, 7), second.hash);
+        assert_ne!(first.hash, second.hash);
+        let mut log = vec![first, second, third];
+        assert_eq!(find_broken_link(&log, "SN_MAIN"), None);
+        // Another chain doesn't share the hashes.
+        assert_eq!(find_broken_link(&log, "SN_SEPOLIA"), Some(0));
+
+        // An altered amount no longer matches its hash...
+        log[1].payout.amount.low = 1_000;
+        assert_eq!(find_broken_link(&log, "SN_MAIN"), Some(1));
+        // ...and a removed payout breaks the chain.
+        log.remove(1);
+        assert_eq!(find_broken_link(&log, "SN_MAIN"), Some(1));
+    }
+}
@@ -0,0 +1,196 @@
+use bigdecimal::{BigDecimal, Zero};
+use serde::Serialize;
+use starknet::core::types::Felt;
+
+use crate::{
+    config::DistributionConfig, types::position::PositionKey, utils::serialization::plain_decimal,
+};
+
+/// Basis points of the whole earnings.
+const BPS_DENOMINATOR: u32 = 10_000;
+
+/// A liquidation the bot would have executed during a backtest, valued at the
+/// oracle prices of the block it was found liquidable at.
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulatedLiquidation {
+    pub block_number: u64,
+    pub key: PositionKey,
+    pub user: Felt,
+    pub collateral: String,
+    pub debt: String,
+    #[serde(serialize_with = "plain_decimal")]
+    pub health_factor: BigDecimal,
+    #[serde(serialize_with = "plain_decimal")]
+    pub collateral_usd: BigDecimal,
+    #[serde(serialize_with = "plain_decimal")]
+    pub debt_usd: BigDecimal,
+    /// See [`estimate_earnings`].
+    #[serde(serialize_with = "plain_decimal")]
+    pub earnings_usd: BigDecimal,
+    #[serde(flatten)]
+    pub split: UsdSplit,
+}
+
+/// Parts of some earnings in USD going to the players, the world & the
+/// operator, the players being at the highest score.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsdSplit {
+    #[serde(serialize_with = "plain_decimal")]
+    pub players_usd: BigDecimal,
+    #[serde(serialize_with = "plain_decimal")]
+    pub world_usd: BigDecimal,
+    #[serde(serialize_with = "plain_decimal")]
+    pub operator_usd: BigDecimal,
+}
+
+impl UsdSplit {
+    /// Splits `earnings` according to the configured basis points, as
+    /// [`crate::types::distribution::EarningsSplit`] does with the amounts.
+    pub fn new(config: &DistributionConfig, earnings: &BigDecimal) -> Self {
+        let share = |bps: u16| earnings * BigDecimal::from(bps) / BigDecimal::from(BPS_DENOMINATOR);
+        let operator_usd = share(config.operator_bps);
+        let players_usd = share(config.player_bps);
+        Self {
+            world_usd: earnings - &operator_usd - &players_usd,
+            players_usd,
+            operator_usd,
+        }
+    }
+}
+
+/// Estimates the earnings of the full liquidation of a position: the
+/// liquidation bonus, `bonus_bps` of the debt repaid, capped by the value of
+/// the collateral left once the debt is repaid. The swap costs are ignored.
+pub fn estimate_earnings(
+    collateral_usd: &BigDecimal,
+    debt_usd: &BigDecimal,
+    bonus_bps: u32,
+) -> BigDecimal {
+    let bonus = debt_usd * BigDecimal::from(bonus_bps) / BigDecimal::from(BPS_DENOMINATOR);
+    let residual = collateral_usd - debt_usd;
+    if residual <= BigDecimal::zero() {
+        return BigDecimal::zero();
+    }
+    bonus.min(residual)
+}
+
+/// Outcome of a backtest over a block range: the liquidations the bot would
+/// have executed & what they would have paid.
+#[derive(Debug, Default, Serialize)]
+pub struct BacktestReport {
+    pub from_block: u64,
+    pub to_block: u64,
+    pub step_blocks: u64,
+    /// Blocks the positions were checked at.
+    pub steps: u64,
+    /// Positions with an event in the range.
+    pub positions: usize,
+    /// Checks skipped for lack of a price at their block.
+    pub unpriced_checks: u64,
+    pub liquidations: Vec<SimulatedLiquidation>,
+    #[serde(serialize_with = "plain_decimal")]
+    pub debt_repaid_usd: BigDecimal,
+    #[serde(serialize_with = "plain_decimal")]
+    pub earnings_usd: BigDecimal,
+    #[serde(flatten)]
+    pub split: UsdSplit,
+}
+
+impl BacktestReport {
+    pub fn new(from_block: u64, to_block: u64, step_blocks: u64, positions: usize) -> Self {
+        Self {
+            from_block,
+            to_block,
+            step_blocks,
+            positions,
+            ..Default::default()
+        }
+    }
+
+    /// Records a simulated liquidation in the totals.
+    pub fn record(&mut self, liquidation: SimulatedLiquidation) {
+        self.debt_repaid_usd += &liquidation.debt_usd;
+        self.earnings_usd += &liquidation.earnings_usd;
+        self.split.players_usd += &liquidation.split.players_usd;
+        self.split.world_usd += &liquidation.split.world_usd;
+        self.split.operator_usd += &liquidation.split.operator_usd;
+        self.liquidations.push(liquidation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+    use starknet::core::types::Felt;
+
+    use super::{BacktestReport, SimulatedLiquidation, UsdSplit, estimate_earnings};
+    use crate::{config::DistributionConfig, types::position::PositionKey};
+
+    fn usd(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_estimate_earnings() {
+        // 5% of the debt...
+        assert_eq!(
+            estimate_earnings(&usd("2000"), &usd("1000"), 500),
+            usd("50")
+        );
+        // ...capped by the collateral left...
+        assert_eq!(
+            estimate_earnings(&usd("1020"), &usd("1000"), 500),
+            usd("20")
+        );
+        // ...nothing from a bad debt.
+        assert_eq!(estimate_earnings(&usd("900"), &usd("1000"), 500), usd("0"));
+    }
+
+    #[test]
+    fn test_backtest_report() {
+        let config = DistributionConfig {
+            player_bps: 7_000,
+            world_bps: 2_500,
+            operator_bps: 500,
+            ..Default::default()
+        };
+        let split = UsdSplit::new(&config, &usd("100"));
+        assert_eq!(
+            split,
+            UsdSplit {
+                players_usd: usd("70"),
+                world_usd: usd("25"),
+                operator_usd: usd("5"),
+            }
+        );
+
+        let mut report = BacktestReport::new(100, 200, 10, 2);
+        for block_number in [110, 150] {
+            report.record(SimulatedLiquidation {
+                block_number,
+                key: PositionKey {
+                    pool_id: Felt::ONE,
+                    collateral: Felt::TWO,
+                    debt: Felt::THREE,
+                    user: Felt::from(block_number),
+                    protocol: Default::default(),
+                },
+                user: Felt::from(block_number),
+                collateral: "ETH".to_string(),
+                debt: "USDC".to_string(),
+                health_factor: usd("0.95"),
+                collateral_usd: usd("2100"),
+                debt_usd: usd("2000"),
+                earnings_usd: usd("100"),
+                split: split.clone(),
+            });
+        }
+        assert_eq!(report.debt_repaid_usd, usd("4000"));
+        assert_eq!(report.earnings_usd, usd("200"));
+        assert_eq!(report.split.players_usd, usd("140"));
+        assert_eq!(report.split.operator_usd, usd("10"));
+        assert_eq!(report.liquidations.len(), 2);
+    }
+}
@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use bigdecimal::BigDecimal;
+use dashmap::DashMap;
+use serde::Serialize;
+use starknet::core::types::Felt;
+
+use crate::utils::serialization::plain_decimal;
+
+/// Last known balance of the bot in a token, with the threshold below which
+/// it is reported as low.
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenBalance {
+    pub ticker: String,
+    #[serde(serialize_with = "plain_decimal")]
+    pub balance: BigDecimal,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<BigDecimal>,
+    pub checked_at: u64,
+}
+
+impl TokenBalance {
+    /// Returns if the balance is below its threshold.
+    pub fn is_low(&self) -> bool {
+        self.threshold
+            .as_ref()
+            .is_some_and(|threshold| self.balance < *threshold)
+    }
+}
+
+/// Balances of the bot in the fee token & the debt tokens, refreshed
+/// periodically by the notifier service & shared with the other services.
+#[derive(Clone, Default)]
+pub struct BotBalances(Arc<DashMap<Felt, TokenBalance>>);
+
+impl BotBalances {
+    pub fn update(&self, token: Felt, balance: TokenBalance) {
+        self.0.insert(token, balance);
+    }
+
+    /// Returns if the last known balance of the token is below its threshold,
+    /// false if it was never checked.
+    pub fn is_low(&self, token: &Felt) -> bool {
+        self.0.get(token).is_some_and(|balance| balance.is_low())
+    }
+
+    /// Returns if the balance of the token was checked & is not below its
+    /// threshold.
+    pub fn is_sufficient(&self, token: &Felt) -> bool {
+        self.0.get(token).is_some_and(|balance| !balance.is_low())
+    }
+
+    /// Returns the last known balances, by ticker.
+    pub fn snapshot(&self) -> Vec<TokenBalance> {
+        let mut balances: Vec<TokenBalance> =
+            self.0.iter().map(|entry| entry.value().clone()).collect();
+        balances.sort_by(|a, b| a.ticker.cmp(&b.ticker));
+        balances
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+    use starknet::core::types::Felt;
+
+    use super::{BotBalances, TokenBalance};
+
+    fn balance(balance: &str, threshold: Option<&str>) -> TokenBalance {
+        TokenBalance {
+            ticker: "STRK".to_string(),
+            balance: BigDecimal::from_str(balance).unwrap(),
+            threshold: threshold.map(|t| BigDecimal::from_str(t).unwrap()),
+            checked_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_low_balances() {
+        let balances = BotBalances::default();
+        assert!(!balances.is_low(&Felt::ONE));
+        assert!(!balances.is_sufficient(&Felt::ONE));
+
+        balances.update(Felt::ONE, balance("9.5", Some("10")));
+        assert!(balances.is_low(&Felt::ONE));
+        assert!(!balances.is_sufficient(&Felt::ONE));
+        balances.update(Felt::ONE, balance("10", Some("10")));
+        assert!(!balances.is_low(&Felt::ONE));
+        assert!(balances.is_sufficient(&Felt::ONE));
+        balances.update(Felt::ONE, balance("0", None));
+        assert!(!balances.is_low(&Felt::ONE));
+    }
+}
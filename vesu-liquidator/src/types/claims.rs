@@ -0,0 +1,178 @@
+use cainome::cairo_serde::U256;
+use serde::{Deserialize, Serialize};
+use starknet::core::{
+    crypto::{compute_hash_on_elements, pedersen_hash},
+    types::Felt,
+};
+
+/// Amount of a token that a player can claim from the claims contract.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Claim {
+    pub recipient: Felt,
+    pub token: Felt,
+    pub amount: U256,
+}
+
+impl Claim {
+    /// Leaf of the claim in the merkle tree: `h(recipient, token, amount_low, amount_high)`.
+    pub fn leaf(&self) -> Felt {
+        compute_hash_on_elements(&[
+            self.recipient,
+            self.token,
+            self.amount.low.into(),
+            self.amount.high.into(),
+        ])
+    }
+}
+
+/// Set of claims whose merkle root has been published to the claims contract.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimSet {
+    pub root: Felt,
+    pub tx_hash: Felt,
+    /// Unix timestamp (in seconds) of the publication.
+    pub published_at: u64,
+    pub claims: Vec<Claim>,
+}
+
+/// A claim with the proof to submit to the claims contract.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClaimProof {
+    pub root: Felt,
+    pub claim: Claim,
+    pub proof: Vec<Felt>,
+}
+
+impl ClaimSet {
+    /// Returns the claims of the recipient along with their proofs.
+    pub fn proofs_for(&self, recipient: Felt) -> Vec<ClaimProof> {
+        let tree = MerkleTree::new(self.claims.iter().map(Claim::leaf).collect());
+        self.claims
+            .iter()
+            .enumerate()
+            .filter(|(_, claim)| claim.recipient == recipient)
+            .map(|(index, claim)| ClaimProof {
+                root: self.root,
+                claim: claim.clone(),
+                proof: tree.proof(index),
+            })
+            .collect()
+    }
+}
+
+/// Merkle tree hashing the pairs of nodes with sorted pedersen, so proofs
+/// don't need to carry the side of each sibling.
+/// A node without sibling is carried as is to the next layer.
+pub struct MerkleTree {
+    layers: Vec<Vec<Felt>>,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: Vec<Felt>) -> Self {
+        let mut layers = vec![leaves];
+        while layers.last().is_some_and(|layer| layer.len() > 1) {
+            let next = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_pair(*left, *right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(next);
+        }
+        Self { layers }
+    }
+
+    /// Root of the tree, zero if the tree is empty.
+    pub fn root(&self) -> Felt {
+        self.layers
+            .last()
+            .and_then(|layer| layer.first())
+            .copied()
+            .unwrap_or(Felt::ZERO)
+    }
+
+    /// Returns the siblings from the leaf at `index` up to the root.
+    pub fn proof(&self, mut index: usize) -> Vec<Felt> {
+        let mut proof = vec![];
+        for layer in self.layers.iter().take(self.layers.len().saturating_sub(1)) {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        proof
+    }
+}
+
+/// Returns true if the proof links the leaf to the root.
+pub fn verify_proof(root: Felt, leaf: Felt, proof: &[Felt]) -> bool {
+    proof
+        .iter()
+        .fold(leaf, |node, sibling| hash_pair(node, *sibling))
+        == root
+}
+
+fn hash_pair(a: Felt, b: Felt) -> Felt {
+    if a < b {
+        pedersen_hash(&a, &b)
+    } else {
+        pedersen_hash(&b, &a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cainome::cairo_serde::U256;
+    use starknet::core::types::Felt;
+
+    use super::{Claim, ClaimSet, MerkleTree, verify_proof};
+
+    fn claim(recipient: u64, low: u128) -> Claim {
+        Claim {
+            recipient: Felt::from(recipient),
+            token: Felt::ONE,
+            amount: U256 { low, high: 0 },
+        }
+    }
+
+    #[test]
+    fn test_merkle_proofs() {
+        for size in 1..=7 {
+            let claims: Vec<Claim> = (0..size).map(|i| claim(i, 10 * i as u128)).collect();
+            let tree = MerkleTree::new(claims.iter().map(Claim::leaf).collect());
+            for (index, claim) in claims.iter().enumerate() {
+                assert!(verify_proof(tree.root(), claim.leaf(), &tree.proof(index)));
+            }
+            // A tampered claim is rejected.
+            let forged = Claim {
+                amount: U256 { low: 1000, high: 0 },
+                ..claims[0].clone()
+            };
+            assert!(!verify_proof(tree.root(), forged.leaf(), &tree.proof(0)));
+        }
+        assert_eq!(MerkleTree::new(vec![]).root(), Felt::ZERO);
+    }
+
+    #[test]
+    fn test_proofs_for_recipient() {
+        let claims = vec![claim(1, 10), claim(2, 20), claim(1, 30)];
+        let root = MerkleTree::new(claims.iter().map(Claim::leaf).collect()).root();
+        let claim_set = ClaimSet {
+            root,
+            tx_hash: Felt::ZERO,
+            published_at: 0,
+            claims,
+        };
+
+        let proofs = claim_set.proofs_for(Felt::from(1));
+        assert_eq!(proofs.len(), 2);
+        for proof in proofs {
+            assert!(verify_proof(root, proof.claim.leaf(), &proof.proof));
+        }
+        assert!(claim_set.proofs_for(Felt::from(3)).is_empty());
+    }
+}
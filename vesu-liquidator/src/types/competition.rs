@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+use crate::types::{
+    history::{LiquidationRecord, LiquidationStatus},
+    position::PositionKey,
+};
+
+/// A liquidation seen by the indexer, sent by the bot or by anyone else.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObservedLiquidation {
+    pub key: PositionKey,
+    pub tx_hash: Felt,
+    /// Liquidator of the event, e.g. the Liquidate contract for the
+    /// liquidations going through it.
+    pub liquidator: Felt,
+}
+
+/// A position tracked by the bot, liquidated by a competitor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompetitorLiquidation {
+    pub key: PositionKey,
+    pub tx_hash: Felt,
+    pub block_number: u64,
+    /// Sender of the liquidation transaction, or the liquidator of the event
+    /// if the transaction could not be read.
+    pub competitor: Felt,
+    /// Unix timestamp (in seconds) at which the liquidation was seen.
+    pub seen_at: u64,
+}
+
+/// Liquidations lost to a competitor.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CompetitorStats {
+    pub competitor: Felt,
+    pub liquidations: usize,
+}
+
+/// Liquidations of the tracked positions won by the bot against the ones
+/// lost to the competitors.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CompetitionStats {
+    pub won: usize,
+    pub lost: usize,
+    /// Share of the liquidations won, `None` before the first one.
+    pub win_rate: Option<f64>,
+    /// The competitors, the one with the most liquidations first.
+    pub competitors: Vec<CompetitorStats>,
+}
+
+impl CompetitionStats {
+    pub fn new(history: &[LiquidationRecord], lost: &[CompetitorLiquidation]) -> Self {
+        let won = history
+            .iter()
+            .filter(|record| record.status == LiquidationStatus::Succeeded)
+            .count();
+        let mut by_competitor: HashMap<Felt, usize> = HashMap::new();
+        for liquidation in lost.iter() {
+            *by_competitor.entry(liquidation.competitor).or_default() += 1;
+        }
+        let mut competitors: Vec<CompetitorStats> = by_competitor
+            .into_iter()
+            .map(|(competitor, liquidations)| CompetitorStats {
+                competitor,
+                liquidations,
+            })
+            .collect();
+        competitors.sort_by(|a, b| {
+            b.liquidations
+                .cmp(&a.liquidations)
+                .then(a.competitor.cmp(&b.competitor))
+        });
+        let total = won + lost.len();
+        Self {
+            won,
+            lost: lost.len(),
+            win_rate: (total > 0).then(|| won as f64 / total as f64),
+            competitors,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+
+    use super::{CompetitionStats, CompetitorLiquidation};
+    use crate::types::{
+        fixtures::key,
+        history::{LiquidationRecord, LiquidationStatus},
+    };
+
+    fn record(status: LiquidationStatus) -> LiquidationRecord {
+        LiquidationRecord {
+            key: key(1),
+            pool_id: Felt::ONE,
+            user: Felt::ONE,
+            collateral: "ETH".to_string(),
+            debt: "USDC".to_string(),
+            started_at: 0,
+            duration_ms: 0,
+            status,
+            tx_hash: None,
+            block_number: None,
+            collateral_seized: None,
+            debt_repaid: None,
+            earnings: None,
+            gas: None,
+            error: None,
+            finality: None,
+        }
+    }
+
+    fn lost(user: u64, competitor: u64) -> CompetitorLiquidation {
+        CompetitorLiquidation {
+            key: key(user),
+            tx_hash: Felt::from(user),
+            block_number: 10,
+            competitor: Felt::from(competitor),
+            seen_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_competition_stats() {
+        assert_eq!(CompetitionStats::new(&[], &[]).win_rate, None);
+
+        let history = [
+            record(LiquidationStatus::Succeeded),
+            record(LiquidationStatus::Failed),
+            record(LiquidationStatus::Succeeded),
+        ];
+        let stats = CompetitionStats::new(&history, &[lost(2, 0xa), lost(3, 0xb), lost(4, 0xb)]);
+        assert_eq!((stats.won, stats.lost), (2, 3));
+        assert_eq!(stats.win_rate, Some(0.4));
+        assert_eq!(stats.competitors[0].competitor, Felt::from(0xb_u64));
+        assert_eq!(stats.competitors[0].liquidations, 2);
+        assert_eq!(stats.competitors[1].liquidations, 1);
+    }
+}
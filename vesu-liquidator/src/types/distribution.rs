@@ -0,0 +1,664 @@
+use std::collections::HashMap;
+
+use bigdecimal::num_bigint::BigUint;
+use cainome::cairo_serde::U256;
+use serde::{Deserialize, Serialize};
+use starknet::core::{crypto::pedersen_hash, types::Felt, utils::cairo_short_string_to_felt};
+
+use crate::{
+    config::{DistributionConfig, DistributionStrategy},
+    utils::{
+        constants::U256_ZERO,
+        conversions::{big_uint_to_u256, u256_to_big_uint},
+    },
+};
+
+/// Earnings of a successful liquidation, waiting to be distributed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiquidationEarnings {
+    pub liquidation_tx: Felt,
+    pub token: Felt,
+    pub amount: U256,
+    /// Unix timestamp (in seconds) of the liquidation.
+    #[serde(default)]
+    pub liquidated_at: u64,
+    /// Span of the liquidation, linked to the span of its distribution.
+    #[serde(skip)]
+    pub span: Option<tracing::Span>,
+}
+
+/// Liquidation earnings collected during the current batching window.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PendingDistribution {
+    /// Unix timestamp (in seconds) of the first liquidation of the window.
+    pub opened_at: u64,
+    /// Unix timestamp (in seconds) of the last liquidation of the window.
+    #[serde(default)]
+    pub last_pushed_at: u64,
+    pub earnings: Vec<LiquidationEarnings>,
+    /// Last failure of the distribution of these earnings, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<DistributionFailure>,
+}
+
+/// Failures of the distribution of the pending earnings, e.g. a reverted
+/// multicall, retried with a backoff.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DistributionFailure {
+    /// Failures in a row.
+    pub attempts: u32,
+    pub error: String,
+    pub failed_at: u64,
+    pub next_retry_at: u64,
+}
+
+impl PendingDistribution {
+    pub fn push(&mut self, earnings: LiquidationEarnings, now: u64) {
+        if self.earnings.is_empty() {
+            self.opened_at = now;
+        }
+        self.last_pushed_at = now;
+        self.earnings.push(earnings);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.earnings.is_empty()
+    }
+
+    /// Returns true if the window is closed, i.e. enough liquidations were
+    /// collected, the window has been opened for long enough or no
+    /// liquidation was collected for `batching_idle_seconds`. With the
+    /// `epoch` strategy, the window closes at the end of the epoch of its
+    /// first liquidation.
+    pub fn is_ready(&self, config: &DistributionConfig, now: u64) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        if config.strategy == DistributionStrategy::Epoch {
+            return now / config.epoch_seconds > self.opened_at / config.epoch_seconds;
+        }
+        self.earnings.len() >= config.batching_max_liquidations
+            || now.saturating_sub(self.opened_at) >= config.batching_window_seconds
+            || (config.batching_idle_seconds > 0
+                && now.saturating_sub(self.last_pushed_at) >= config.batching_idle_seconds)
+    }
+
+    /// Returns the failures in a row of the distribution of these earnings.
+    pub fn failed_attempts(&self) -> u32 {
+        self.failure.as_ref().map_or(0, |failure| failure.attempts)
+    }
+
+    /// Sums the collected earnings per token.
+    pub fn total_per_token(&self) -> HashMap<Felt, U256> {
+        let mut totals: HashMap<Felt, U256> = HashMap::new();
+        for earnings in self.earnings.iter() {
+            let total = totals.entry(earnings.token).or_insert(U256_ZERO);
+            *total = *total + earnings.amount;
+        }
+        totals
+    }
+
+    /// Returns the earnings with the ones in `token` converted to `amount` of
+    /// `to_token`, e.g. after a swap, split between the liquidations pro rata.
+    pub fn converted(&self, token: Felt, to_token: Felt, amount: U256) -> Self {
+        let mut converted = self.clone();
+        let total = self
+            .total_per_token()
+            .get(&token)
+            .copied()
+            .unwrap_or(U256_ZERO);
+        if total == U256_ZERO {
+            return converted;
+        }
+        let total = u256_to_big_uint(&total);
+        for earnings in converted.earnings.iter_mut().filter(|e| e.token == token) {
+            let share = u256_to_big_uint(&amount) * u256_to_big_uint(&earnings.amount) / &total;
+            earnings.token = to_token;
+            earnings.amount = big_uint_to_u256(&share);
+        }
+        converted
+    }
+
+    /// Returns the earnings without the ones in `token`, e.g. kept by the bot
+    /// as treasury.
+    pub fn without_token(&self, token: Felt) -> Self {
+        let mut kept = self.clone();
+        kept.earnings.retain(|e| e.token != token);
+        kept
+    }
+
+    /// Removes the earnings of the liquidation `liquidation_tx`, e.g. once
+    /// distributed by hand. Returns false if they were not pending.
+    pub fn remove(&mut self, liquidation_tx: Felt) -> bool {
+        let pending = self.earnings.len();
+        self.earnings.retain(|e| e.liquidation_tx != liquidation_tx);
+        if self.is_empty() {
+            self.clear();
+        }
+        self.earnings.len() < pending
+    }
+
+    pub fn clear(&mut self) {
+        self.opened_at = 0;
+        self.last_pushed_at = 0;
+        self.earnings.clear();
+        self.failure = None;
+    }
+
+    /// Records a failure of the distribution & schedules its retry. Returns
+    /// the failures in a row.
+    pub fn record_failure(&mut self, error: String, now: u64, config: &DistributionConfig) -> u32 {
+        let attempts = self.failed_attempts() + 1;
+        let backoff = config
+            .retry_backoff_seconds
+            .saturating_mul(2_u64.saturating_pow(attempts - 1))
+            .min(config.max_retry_backoff_seconds);
+        self.failure = Some(DistributionFailure {
+            attempts,
+            error,
+            failed_at: now,
+            next_retry_at: now.saturating_add(backoff),
+        });
+        attempts
+    }
+
+    /// Returns true if the last distribution failed & its retry is not due yet.
+    pub fn is_backing_off(&self, now: u64) -> bool {
+        self.failure
+            .as_ref()
+            .is_some_and(|failure| now < failure.next_retry_at)
+    }
+}
+
+/// Guards the distributions against sudden jumps of the highest score, e.g.
+/// after a score overflow or an exploit in the game, that would skew the share
+/// of every player.
+#[derive(Debug, Clone)]
+pub struct HighestScoreGuard {
+    max_jump: f64,
+    /// Last highest score used by a distribution.
+    trusted: Option<u128>,
+    /// Highest score currently holding the distributions.
+    held: Option<u128>,
+}
+
+/// Outcome of [`HighestScoreGuard::check`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum HighestScoreCheck {
+    Accepted,
+    /// The score jumped from `trusted`; `first_seen` is true the first time
+    /// this score is held, to alert only once.
+    Held {
+        trusted: u128,
+        first_seen: bool,
+    },
+}
+
+impl HighestScoreGuard {
+    pub fn new(max_jump: f64, trusted: Option<u128>) -> Self {
+        Self {
+            max_jump,
+            trusted,
+            held: None,
+        }
+    }
+
+    /// Checks the highest score read before a distribution, trusting it if it
+    /// didn't jump by more than `max_jump` times the last trusted one.
+    pub fn check(&mut self, score: u128) -> HighestScoreCheck {
+        let jumped_from = self.trusted.filter(|&trusted| {
+            self.max_jump > 0.0 && trusted > 0 && score as f64 > trusted as f64 * self.max_jump
+        });
+        if let Some(trusted) = jumped_from {
+            let first_seen = self.held != Some(score);
+            self.held = Some(score);
+            return HighestScoreCheck::Held {
+                trusted,
+                first_seen,
+            };
+        }
+        self.trusted = Some(score);
+        self.held = None;
+        HighestScoreCheck::Accepted
+    }
+}
+
+/// Basis points of the whole earnings.
+const BPS_DENOMINATOR: u128 = 10_000;
+
+/// Parts of some earnings going to the player(s), the world & the operator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EarningsSplit {
+    pub player: U256,
+    pub world: U256,
+    pub operator: U256,
+}
+
+impl EarningsSplit {
+    /// Splits `total` according to the configured basis points: the operator
+    /// takes its fee, the player its part of `player_bps` weighted by
+    /// `score / highest_score`, and the world the rest.
+    pub fn new(config: &DistributionConfig, total: U256, score: u128, highest_score: u128) -> Self {
+        let operator = proportional_share(total, config.operator_bps.into(), BPS_DENOMINATOR);
+        let players = proportional_share(total, config.player_bps.into(), BPS_DENOMINATOR);
+        let player = proportional_share(players, score, highest_score);
+        Self {
+            player,
+            world: total - operator - player,
+            operator,
+        }
+    }
+}
+
+/// Returns what is left of `total` once the `parts` are taken, `None` if they
+/// exceed it.
+pub fn remainder(total: U256, parts: &[U256]) -> Option<U256> {
+    let total = u256_to_big_uint(&total);
+    let taken: BigUint = parts.iter().map(u256_to_big_uint).sum();
+    (taken <= total).then(|| big_uint_to_u256(&(total - taken)))
+}
+
+/// Returns `amount * numerator / denominator`, rounded down.
+/// The ratio is capped to 1 so the share can never exceed `amount`.
+pub fn proportional_share(amount: U256, numerator: u128, denominator: u128) -> U256 {
+    if denominator == 0 {
+        return U256_ZERO;
+    }
+    let numerator = numerator.min(denominator);
+    let share = u256_to_big_uint(&amount) * numerator / denominator;
+    big_uint_to_u256(&share)
+}
+
+/// Precision of the decay factor of the scores.
+const DECAY_SCALE: u128 = 1_000_000_000_000_000_000;
+
+/// Returns the score of a redeem entry of `age` seconds, halved every
+/// `half_life` seconds past `decay_after` (0 disables the decay).
+pub fn decayed_score(score: u128, age: u64, decay_after: u64, half_life: u64) -> u128 {
+    if decay_after == 0 || half_life == 0 || age <= decay_after {
+        return score;
+    }
+    let half_lives = (age - decay_after) as f64 / half_life as f64;
+    let factor = (0.5_f64.powf(half_lives) * DECAY_SCALE as f64) as u128;
+    let decayed = BigUint::from(score) * factor.min(DECAY_SCALE) / DECAY_SCALE;
+    u128::try_from(decayed).expect("decayed score is at most the score")
+}
+
+/// A raffle draw, reproducible by anyone from the liquidation tx hash & the
+/// scores of the candidates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RaffleDraw {
+    /// `pedersen(liquidation_tx, 'raffle')`.
+    pub seed: Felt,
+    pub total_score: BigUint,
+    /// `seed mod total_score`, falling in the score range of the winner.
+    pub ticket: BigUint,
+    /// Index of the winner among the candidates.
+    pub winner: usize,
+}
+
+/// Draws a winner among candidates with the given `scores`, each with a
+/// probability proportional to its score. `None` if all the scores are 0.
+pub fn draw_raffle(liquidation_tx: Felt, scores: &[u128]) -> Option<RaffleDraw> {
+    let total_score: BigUint = scores.iter().map(|score| BigUint::from(*score)).sum();
+    if total_score == BigUint::ZERO {
+        return None;
+    }
+    let domain = cairo_short_string_to_felt("raffle").expect("valid short string");
+    let seed = pedersen_hash(&liquidation_tx, &domain);
+    let ticket = BigUint::from_bytes_be(&seed.to_bytes_be()) % &total_score;
+
+    let mut range_end = BigUint::ZERO;
+    let winner = scores.iter().position(|score| {
+        range_end += *score;
+        ticket < range_end
+    })?;
+    Some(RaffleDraw {
+        seed,
+        total_score,
+        ticket,
+        winner,
+    })
+}
+
+/// Addresses of Starknet contracts are below 2^251 - 256.
+const ADDRESS_UPPER_BOUND: Felt =
+    Felt::from_hex_unchecked("0x7ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff00");
+
+/// Returns if tokens can be sent to `address`: a transfer to the zero address
+/// or beyond the address space would revert the whole distribution multicall.
+pub fn is_valid_recipient(address: Felt) -> bool {
+    address != Felt::ZERO && address < ADDRESS_UPPER_BOUND
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::num_bigint::BigUint;
+    use cainome::cairo_serde::U256;
+    use starknet::core::types::Felt;
+
+    use crate::{
+        config::{DistributionConfig, DistributionStrategy},
+        utils::conversions::u256_to_big_uint,
+    };
+
+    use super::{
+        EarningsSplit, HighestScoreCheck, HighestScoreGuard, LiquidationEarnings,
+        PendingDistribution, decayed_score, draw_raffle, is_valid_recipient, proportional_share,
+    };
+
+    fn earnings(token: u64, low: u128) -> LiquidationEarnings {
+        LiquidationEarnings {
+            liquidation_tx: Felt::ONE,
+            token: Felt::from(token),
+            amount: U256 { low, high: 0 },
+            liquidated_at: 0,
+            span: None,
+        }
+    }
+
+    #[test]
+    fn test_distribution_failures() {
+        let config = DistributionConfig {
+            retry_backoff_seconds: 30,
+            max_retry_backoff_seconds: 100,
+            ..DistributionConfig::default()
+        };
+        let mut pending = PendingDistribution::default();
+        pending.push(earnings(1, 10), 1_000);
+        assert!(!pending.is_backing_off(1_000));
+
+        assert_eq!(pending.record_failure("reverted".into(), 1_000, &config), 1);
+        assert!(pending.is_backing_off(1_029));
+        assert!(!pending.is_backing_off(1_030));
+        assert_eq!(pending.record_failure("reverted".into(), 1_030, &config), 2);
+        assert_eq!(pending.failure.as_ref().unwrap().next_retry_at, 1_090);
+        assert_eq!(pending.record_failure("reverted".into(), 1_090, &config), 3);
+        assert_eq!(pending.failure.as_ref().unwrap().next_retry_at, 1_190);
+
+        pending.clear();
+        assert_eq!(pending.failed_attempts(), 0);
+        assert!(!pending.is_backing_off(1_100));
+    }
+
+    #[test]
+    fn test_remove_pending_earnings() {
+        let mut pending = PendingDistribution::default();
+        pending.push(earnings(1, 10), 1_000);
+        pending.push(
+            LiquidationEarnings {
+                liquidation_tx: Felt::TWO,
+                ..earnings(2, 7)
+            },
+            1_010,
+        );
+        assert!(!pending.remove(Felt::THREE));
+        assert!(pending.remove(Felt::ONE));
+        assert_eq!(pending.earnings.len(), 1);
+        assert_eq!(pending.opened_at, 1_000);
+
+        pending.record_failure("reverted".into(), 1_020, &DistributionConfig::default());
+        assert!(pending.remove(Felt::TWO));
+        assert!(pending.is_empty());
+        assert_eq!((pending.opened_at, pending.failed_attempts()), (0, 0));
+    }
+
+    #[test]
+    fn test_valid_recipients() {
+        assert!(is_valid_recipient(Felt::ONE));
+        assert!(!is_valid_recipient(Felt::ZERO));
+        assert!(!is_valid_recipient(Felt::MAX));
+        assert!(!is_valid_recipient(Felt::from_hex_unchecked(
+            "0x7ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff00"
+        )));
+    }
+
+    #[test]
+    fn test_converted_earnings() {
+        let mut pending = PendingDistribution::default();
+        pending.push(earnings(1, 30), 1_000);
+        pending.push(earnings(2, 7), 1_000);
+        pending.push(earnings(1, 70), 1_000);
+
+        let converted = pending.converted(Felt::from(1), Felt::from(2), U256 { low: 500, high: 0 });
+        let amounts: Vec<(Felt, u128)> = converted
+            .earnings
+            .iter()
+            .map(|e| (e.token, e.amount.low))
+            .collect();
+        assert_eq!(
+            amounts,
+            vec![
+                (Felt::from(2), 150),
+                (Felt::from(2), 7),
+                (Felt::from(2), 350)
+            ]
+        );
+        assert_eq!(converted.opened_at, pending.opened_at);
+
+        let kept = converted.without_token(Felt::from(2));
+        assert!(kept.is_empty());
+        assert_eq!(pending.without_token(Felt::from(2)).earnings.len(), 2);
+    }
+
+    #[test]
+    fn test_proportional_share() {
+        let amount = U256 { low: 1000, high: 0 };
+        assert_eq!(
+            proportional_share(amount, 50, 100),
+            U256 { low: 500, high: 0 }
+        );
+        assert_eq!(proportional_share(amount, 1, 3), U256 { low: 333, high: 0 });
+        // Capped to the full amount & no panic on an empty denominator.
+        assert_eq!(proportional_share(amount, 200, 100), amount);
+        assert_eq!(proportional_share(amount, 1, 0), U256 { low: 0, high: 0 });
+
+        let huge = U256 {
+            low: u128::MAX,
+            high: u128::MAX,
+        };
+        assert_eq!(proportional_share(huge, 1, 1), huge);
+    }
+
+    #[test]
+    fn test_earnings_split() {
+        let total = U256 {
+            low: 10_000,
+            high: 0,
+        };
+        let amount = |low| U256 { low, high: 0 };
+
+        // The whole earnings to the player by default.
+        let config = DistributionConfig::default();
+        assert_eq!(
+            EarningsSplit::new(&config, total, 30, 100),
+            EarningsSplit {
+                player: amount(3_000),
+                world: amount(7_000),
+                operator: amount(0),
+            }
+        );
+
+        let config = DistributionConfig {
+            player_bps: 7_000,
+            world_bps: 2_500,
+            operator_bps: 500,
+            operator_address: Some("0x123".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            EarningsSplit::new(&config, total, 50, 100),
+            EarningsSplit {
+                player: amount(3_500),
+                world: amount(6_000),
+                operator: amount(500),
+            }
+        );
+        assert_eq!(
+            EarningsSplit::new(&config, total, 100, 100),
+            EarningsSplit {
+                player: amount(7_000),
+                world: amount(2_500),
+                operator: amount(500),
+            }
+        );
+    }
+
+    #[test]
+    fn test_earnings_split_edge_cases() {
+        let config = DistributionConfig {
+            player_bps: 7_000,
+            world_bps: 2_500,
+            operator_bps: 500,
+            operator_address: Some("0x123".to_string()),
+            ..Default::default()
+        };
+        let total = U256 {
+            low: 10_000,
+            high: 0,
+        };
+        let zero = U256 { low: 0, high: 0 };
+
+        // Without any score, the part of the player goes to the world.
+        for (score, highest_score) in [(0, 100), (0, 0), (50, 0)] {
+            let split = EarningsSplit::new(&config, total, score, highest_score);
+            assert_eq!(split.player, zero);
+            assert_eq!(
+                split.world,
+                U256 {
+                    low: 9_500,
+                    high: 0
+                }
+            );
+        }
+        // A score above the highest one is capped to the whole part.
+        assert_eq!(
+            EarningsSplit::new(&config, total, u128::MAX, 1).player,
+            U256 {
+                low: 7_000,
+                high: 0
+            }
+        );
+
+        // Nothing is lost nor overflows on huge amounts.
+        let huge = U256 {
+            low: u128::MAX,
+            high: u128::MAX,
+        };
+        let split = EarningsSplit::new(&config, huge, u128::MAX - 1, u128::MAX);
+        assert_eq!(
+            u256_to_big_uint(&split.player)
+                + u256_to_big_uint(&split.world)
+                + u256_to_big_uint(&split.operator),
+            u256_to_big_uint(&huge)
+        );
+        assert!(split.player.high > 0 && split.operator.high > 0);
+    }
+
+    #[test]
+    fn test_decayed_score() {
+        let day = 86_400;
+        // Untouched until `decay_after`, or when disabled.
+        assert_eq!(decayed_score(1_000, day, 0, day), 1_000);
+        assert_eq!(decayed_score(1_000, day, 2 * day, day), 1_000);
+        assert_eq!(decayed_score(1_000, 2 * day, 2 * day, day), 1_000);
+
+        // Halved every half-life past it.
+        assert_eq!(decayed_score(1_000, 3 * day, 2 * day, day), 500);
+        assert_eq!(decayed_score(1_000, 4 * day, 2 * day, day), 250);
+        assert_eq!(decayed_score(1_000, 2 * day + day / 2, 2 * day, day), 707);
+        assert_eq!(decayed_score(1_000, u64::MAX, 1, 1), 0);
+    }
+
+    #[test]
+    fn test_raffle_draw() {
+        // Reproducible from the tx hash, & always in the range of a candidate.
+        let draw = draw_raffle(Felt::from(0xabc_u64), &[10, 0, 30]).unwrap();
+        assert_eq!(
+            draw_raffle(Felt::from(0xabc_u64), &[10, 0, 30]),
+            Some(draw.clone())
+        );
+        assert_eq!(draw.total_score, BigUint::from(40_u32));
+        assert!(draw.ticket < draw.total_score);
+        let expected_winner = if draw.ticket < BigUint::from(10_u32) {
+            0
+        } else {
+            2
+        };
+        assert_eq!(draw.winner, expected_winner);
+
+        // A candidate without score never wins.
+        for tx in 0..50_u64 {
+            assert_eq!(draw_raffle(Felt::from(tx), &[0, 5, 0]).unwrap().winner, 1);
+        }
+        assert_eq!(draw_raffle(Felt::ONE, &[0, 0]), None);
+        assert_eq!(draw_raffle(Felt::ONE, &[]), None);
+    }
+
+    #[test]
+    fn test_pending_distribution_window() {
+        let config = DistributionConfig {
+            batching_window_seconds: 60,
+            batching_max_liquidations: 3,
+            ..Default::default()
+        };
+        let mut pending = PendingDistribution::default();
+        assert!(!pending.is_ready(&config, 1_000));
+
+        pending.push(earnings(1, 10), 1_000);
+        pending.push(earnings(1, 5), 1_010);
+        assert!(!pending.is_ready(&config, 1_030));
+        assert!(pending.is_ready(&config, 1_060));
+
+        // A cascade is paid once no liquidation came for the idle delay.
+        let idle_config = DistributionConfig {
+            batching_idle_seconds: 15,
+            ..config.clone()
+        };
+        assert!(!pending.is_ready(&idle_config, 1_020));
+        assert!(pending.is_ready(&idle_config, 1_025));
+
+        pending.push(earnings(2, 7), 1_020);
+        assert!(pending.is_ready(&config, 1_020));
+
+        // Settled once the epoch of the first liquidation is over.
+        let config = DistributionConfig {
+            strategy: DistributionStrategy::Epoch,
+            epoch_seconds: 3_600,
+            ..config
+        };
+        assert!(!pending.is_ready(&config, 3_599));
+        assert!(pending.is_ready(&config, 3_600));
+
+        let totals = pending.total_per_token();
+        assert_eq!(totals[&Felt::from(1)], U256 { low: 15, high: 0 });
+        assert_eq!(totals[&Felt::from(2)], U256 { low: 7, high: 0 });
+    }
+
+    #[test]
+    fn test_highest_score_guard() {
+        let mut guard = HighestScoreGuard::new(10.0, Some(100));
+        assert_eq!(guard.check(900), HighestScoreCheck::Accepted);
+        assert_eq!(guard.check(500), HighestScoreCheck::Accepted);
+
+        // Held from the last trusted score, alerting once per jumped score.
+        let held = |first_seen| HighestScoreCheck::Held {
+            trusted: 500,
+            first_seen,
+        };
+        assert_eq!(guard.check(5_001), held(true));
+        assert_eq!(guard.check(5_001), held(false));
+        assert_eq!(guard.check(u128::MAX), held(true));
+        assert_eq!(guard.check(4_000), HighestScoreCheck::Accepted);
+
+        // Nothing to compare to on the first read, and 0 disables the guard.
+        assert_eq!(
+            HighestScoreGuard::new(10.0, None).check(u128::MAX),
+            HighestScoreCheck::Accepted
+        );
+        assert_eq!(
+            HighestScoreGuard::new(0.0, Some(1)).check(u128::MAX),
+            HighestScoreCheck::Accepted
+        );
+    }
+}
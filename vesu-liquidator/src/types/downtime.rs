@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+
+use cainome::cairo_serde::U256;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+use crate::{types::distribution::proportional_share, utils::constants::U256_ZERO};
+
+/// A liquidation made by someone else while the bot was offline.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MissedLiquidation {
+    pub tx_hash: Felt,
+    pub block_number: u64,
+    pub pool_id: Felt,
+    pub user: Felt,
+    pub collateral: Felt,
+    pub debt: Felt,
+    /// Collateral left to the liquidator, i.e. the earnings the bot missed.
+    pub residual: U256,
+}
+
+/// What the downtime of the bot cost: the liquidations missed between the last
+/// block indexed before stopping & the block at restart.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DowntimeReport {
+    pub from_block: u64,
+    pub to_block: u64,
+    /// Unix timestamp (in seconds) of the report.
+    pub generated_at: u64,
+    pub missed_liquidations: Vec<MissedLiquidation>,
+    /// Missed earnings per collateral token.
+    pub missed_profit: Vec<(Felt, U256)>,
+    /// Share of the missed earnings that the head of the redeem queue would
+    /// have received, per collateral token.
+    pub missed_player_rewards: Vec<(Felt, U256)>,
+}
+
+impl DowntimeReport {
+    /// Builds the report, the player rewards being computed like a
+    /// distribution to a player with `player_score` out of `highest_score`.
+    pub fn new(
+        from_block: u64,
+        to_block: u64,
+        missed_liquidations: Vec<MissedLiquidation>,
+        player_score: u128,
+        highest_score: u128,
+        generated_at: u64,
+    ) -> Self {
+        let mut totals: BTreeMap<Felt, U256> = BTreeMap::new();
+        for missed in missed_liquidations.iter() {
+            let total = totals.entry(missed.collateral).or_insert(U256_ZERO);
+            *total = *total + missed.residual;
+        }
+        let missed_player_rewards = totals
+            .iter()
+            .map(|(token, total)| {
+                (
+                    *token,
+                    proportional_share(*total, player_score, highest_score),
+                )
+            })
+            .collect();
+        Self {
+            from_block,
+            to_block,
+            generated_at,
+            missed_liquidations,
+            missed_profit: totals.into_iter().collect(),
+            missed_player_rewards,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.missed_liquidations.is_empty()
+    }
+}
+
+impl std::fmt::Display for DowntimeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} liquidation(s) missed from block {} to {}",
+            self.missed_liquidations.len(),
+            self.from_block,
+            self.to_block
+        )?;
+        for ((token, profit), (_, rewards)) in
+            self.missed_profit.iter().zip(&self.missed_player_rewards)
+        {
+            write!(
+                f,
+                ", {profit} of token {token:#x} (player rewards: {rewards})"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cainome::cairo_serde::U256;
+    use starknet::core::types::Felt;
+
+    use super::{DowntimeReport, MissedLiquidation};
+
+    fn missed(collateral: u64, residual: u128) -> MissedLiquidation {
+        MissedLiquidation {
+            tx_hash: Felt::ONE,
+            block_number: 10,
+            pool_id: Felt::ONE,
+            user: Felt::TWO,
+            collateral: Felt::from(collateral),
+            debt: Felt::THREE,
+            residual: U256 {
+                low: residual,
+                high: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn test_downtime_report_totals() {
+        let report = DowntimeReport::new(
+            100,
+            200,
+            vec![missed(0xb, 30), missed(0xa, 100), missed(0xb, 10)],
+            25,
+            100,
+            0,
+        );
+
+        let amount = |low| U256 { low, high: 0 };
+        assert_eq!(
+            report.missed_profit,
+            vec![
+                (Felt::from(0xa), amount(100)),
+                (Felt::from(0xb), amount(40))
+            ]
+        );
+        assert_eq!(
+            report.missed_player_rewards,
+            vec![(Felt::from(0xa), amount(25)), (Felt::from(0xb), amount(10))]
+        );
+        assert_eq!(
+            report.to_string(),
+            "3 liquidation(s) missed from block 100 to 200, 100 of token 0xa (player rewards: 25), \
+             40 of token 0xb (player rewards: 10)"
+        );
+    }
+
+    #[test]
+    fn test_empty_downtime_report() {
+        let report = DowntimeReport::new(100, 200, vec![], 0, 0, 0);
+        assert!(report.is_empty());
+        assert!(report.missed_profit.is_empty());
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bigdecimal::{BigDecimal, ToPrimitive};
+use serde::Serialize;
+use starknet::core::types::Felt;
+
+use crate::{
+    services::oracle::LatestOraclePrices, types::position::Position,
+    utils::serialization::plain_decimal,
+};
+
+/// Values in USD of a position at the oracle prices.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionValue {
+    pub collateral_usd: BigDecimal,
+    pub debt_usd: BigDecimal,
+}
+
+impl PositionValue {
+    /// `None` if a price of the position is missing or stale.
+    pub fn new(position: &Position, oracle_prices: &LatestOraclePrices) -> Option<Self> {
+        Some(Self {
+            collateral_usd: position.collateral_value(oracle_prices).ok()?,
+            debt_usd: position.debt_value(oracle_prices).ok()?,
+        })
+    }
+}
+
+/// Debt of a group of positions in one token.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DebtAmount {
+    pub ticker: String,
+    #[serde(serialize_with = "plain_decimal")]
+    pub amount: BigDecimal,
+}
+
+/// Exposure of the bot to a group of positions, e.g. the ones of an owner or
+/// of a pool.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Exposure {
+    pub positions: usize,
+    #[serde(serialize_with = "plain_decimal")]
+    pub collateral_usd: BigDecimal,
+    #[serde(serialize_with = "plain_decimal")]
+    pub debt_usd: BigDecimal,
+    /// Share of the debt of all the positions, between 0 & 1.
+    pub debt_share: f64,
+    /// Debt per token, the liquidity needed to repay it, sorted by ticker.
+    pub debt: Vec<DebtAmount>,
+    /// Positions left out of the USD values, a price being missing or stale.
+    pub unpriced: usize,
+}
+
+impl Exposure {
+    fn add(&mut self, position: &Position, value: Option<&PositionValue>) {
+        self.positions += 1;
+        match value {
+            Some(value) => {
+                self.collateral_usd += &value.collateral_usd;
+                self.debt_usd += &value.debt_usd;
+            }
+            None => self.unpriced += 1,
+        }
+        let ticker = position.debt.name.to_uppercase();
+        match self.debt.iter_mut().find(|debt| debt.ticker == ticker) {
+            Some(debt) => debt.amount += &position.debt.amount,
+            None => {
+                self.debt.push(DebtAmount {
+                    ticker,
+                    amount: position.debt.amount.clone(),
+                });
+                self.debt.sort_by(|a, b| a.ticker.cmp(&b.ticker));
+            }
+        }
+    }
+
+    fn set_share(&mut self, total_debt_usd: &BigDecimal) {
+        if *total_debt_usd > BigDecimal::from(0) {
+            self.debt_share = (&self.debt_usd / total_debt_usd)
+                .to_f64()
+                .unwrap_or_default();
+        }
+    }
+}
+
+/// Exposure to the positions of an owner.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OwnerExposure {
+    pub owner: Felt,
+    #[serde(flatten)]
+    pub exposure: Exposure,
+}
+
+/// Exposure to the positions of a pool.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PoolExposure {
+    pub pool_id: Felt,
+    /// See [`crate::config::PoolConfig`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pool_name: Option<String>,
+    #[serde(flatten)]
+    pub exposure: Exposure,
+}
+
+/// Exposure to the open positions, in total, per owner & per pool, to spot
+/// the owners dominating the book & the debt tokens to hold.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExposureReport {
+    pub total: Exposure,
+    /// Number of owners with an open position.
+    pub owner_count: usize,
+    /// The owners with the most debt first, up to the `top` requested.
+    pub owners: Vec<OwnerExposure>,
+    /// The pools with the most debt first.
+    pub pools: Vec<PoolExposure>,
+}
+
+impl ExposureReport {
+    /// Aggregates the open positions with their values, `None` for the ones
+    /// that could not be priced.
+    pub fn new<'a>(
+        positions: impl IntoIterator<Item = (&'a Position, Option<PositionValue>)>,
+        top: usize,
+    ) -> Self {
+        let mut total = Exposure::default();
+        let mut owners: HashMap<Felt, Exposure> = HashMap::new();
+        let mut pools: BTreeMap<Felt, (Option<String>, Exposure)> = BTreeMap::new();
+        for (position, value) in positions {
+            if position.is_closed() {
+                continue;
+            }
+            total.add(position, value.as_ref());
+            owners
+                .entry(position.user_address)
+                .or_default()
+                .add(position, value.as_ref());
+            let (pool_name, pool) = pools.entry(position.pool_id).or_default();
+            if pool_name.is_none() {
+                pool_name.clone_from(&position.pool_name);
+            }
+            pool.add(position, value.as_ref());
+        }
+
+        let owner_count = owners.len();
+        let mut owners: Vec<OwnerExposure> = owners
+            .into_iter()
+            .map(|(owner, mut exposure)| {
+                exposure.set_share(&total.debt_usd);
+                OwnerExposure { owner, exposure }
+            })
+            .collect();
+        owners.sort_by(|a, b| {
+            b.exposure
+                .debt_usd
+                .cmp(&a.exposure.debt_usd)
+                .then(a.owner.cmp(&b.owner))
+        });
+        owners.truncate(top);
+
+        let mut pools: Vec<PoolExposure> = pools
+            .into_iter()
+            .map(|(pool_id, (pool_name, mut exposure))| {
+                exposure.set_share(&total.debt_usd);
+                PoolExposure {
+                    pool_id,
+                    pool_name,
+                    exposure,
+                }
+            })
+            .collect();
+        pools.sort_by(|a, b| b.exposure.debt_usd.cmp(&a.exposure.debt_usd));
+
+        total.set_share(&total.debt_usd.clone());
+        Self {
+            total,
+            owner_count,
+            owners,
+            pools,
+        }
+    }
+
+    /// Aggregates the positions valued at the latest oracle prices.
+    pub fn from_prices(
+        positions: &[Position],
+        oracle_prices: &LatestOraclePrices,
+        top: usize,
+    ) -> Self {
+        Self::new(
+            positions
+                .iter()
+                .map(|position| (position, PositionValue::new(position, oracle_prices))),
+            top,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+    use starknet::core::types::Felt;
+
+    use super::{ExposureReport, PositionValue};
+    use crate::types::{asset::Asset, fixtures, position::Position};
+
+    fn position(user: u64, pool: u64, debt: &str) -> Position {
+        Position {
+            pool_id: Felt::from(pool),
+            pool_name: (pool == 1).then(|| "genesis".to_string()),
+            ..fixtures::position(user, debt)
+        }
+    }
+
+    fn value(collateral_usd: u64, debt_usd: u64) -> Option<PositionValue> {
+        Some(PositionValue {
+            collateral_usd: BigDecimal::from(collateral_usd),
+            debt_usd: BigDecimal::from(debt_usd),
+        })
+    }
+
+    #[test]
+    fn test_exposure_per_owner_and_pool() {
+        let positions = [
+            position(1, 1, "600"),
+            position(1, 2, "200"),
+            position(2, 1, "200"),
+            position(3, 2, "50"),
+        ];
+        let closed = Position {
+            collateral: Asset::new("ETH".to_string(), Felt::from(0x10_u64), 18),
+            ..position(4, 1, "0")
+        };
+        let values = [value(1000, 600), value(300, 200), value(300, 200), None];
+        let report = ExposureReport::new(
+            positions.iter().zip(values).chain([(&closed, value(0, 0))]),
+            2,
+        );
+
+        // The closed position is left out, the unpriced one only counted.
+        assert_eq!(report.total.positions, 4);
+        assert_eq!(report.total.unpriced, 1);
+        assert_eq!(report.total.debt_usd, BigDecimal::from(1000));
+        assert_eq!(report.total.debt[0].amount, BigDecimal::from(1050));
+
+        // The owner with the most debt comes first, only the top 2 are kept.
+        assert_eq!(report.owner_count, 3);
+        assert_eq!(report.owners.len(), 2);
+        assert_eq!(report.owners[0].owner, Felt::ONE);
+        assert_eq!(report.owners[0].exposure.positions, 2);
+        assert!((report.owners[0].exposure.debt_share - 0.8).abs() < 1e-9);
+        assert_eq!(report.owners[1].owner, Felt::TWO);
+
+        assert_eq!(report.pools.len(), 2);
+        assert_eq!(report.pools[0].pool_id, Felt::ONE);
+        assert_eq!(report.pools[0].pool_name.as_deref(), Some("genesis"));
+        assert!((report.pools[0].exposure.debt_share - 0.8).abs() < 1e-9);
+        assert_eq!(report.pools[1].pool_name, None);
+        assert_eq!(report.pools[1].exposure.unpriced, 1);
+    }
+}
@@ -0,0 +1,156 @@
+use bigdecimal::BigDecimal;
+use cainome::cairo_serde::U256;
+use serde::Serialize;
+use starknet::core::types::Felt;
+use tokio::sync::broadcast;
+
+use crate::{
+    types::position::PositionKey,
+    utils::{
+        serialization::{optional_plain_decimal, plain_decimal},
+        unix_now,
+    },
+};
+
+/// Capacity of the channel of the feed, lagging subscribers miss the oldest
+/// events.
+const FEED_CAPACITY: usize = 256;
+
+/// Event pushed live to the game clients.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum FeedEvent {
+    /// A position was liquidated by the bot, earning `amount` of `token`,
+    /// worth `usd` at the oracle price if known.
+    LiquidationExecuted {
+        position_key: PositionKey,
+        tx_hash: Felt,
+        token: Felt,
+        amount: U256,
+        #[serde(serialize_with = "optional_plain_decimal")]
+        usd: Option<BigDecimal>,
+    },
+    /// A player received its share of the earnings, sent to its payout
+    /// address or credited on the claims contract.
+    PlayerRewarded {
+        player: Felt,
+        amount: U256,
+        token: Felt,
+        #[serde(serialize_with = "optional_plain_decimal")]
+        usd: Option<BigDecimal>,
+        tx_hash: Felt,
+    },
+    /// The global highest score used to compute the shares of a distribution.
+    HighScoreUsed { score: u128, tx_hash: Felt },
+    /// The health factor of a position fell within `monitoring.at_risk_margin_bps`
+    /// of a liquidation, `shortfall_usd` being the value of the collateral
+    /// its owner has to add to get back above the threshold.
+    PositionAtRisk {
+        position_key: PositionKey,
+        owner: Felt,
+        #[serde(serialize_with = "plain_decimal")]
+        health_factor: BigDecimal,
+        #[serde(serialize_with = "plain_decimal")]
+        threshold: BigDecimal,
+        #[serde(serialize_with = "optional_plain_decimal")]
+        shortfall_usd: Option<BigDecimal>,
+    },
+}
+
+/// An event of the feed, with the tenant game it concerns if any.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeedMessage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// Unix timestamp (in seconds) of the event.
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub event: FeedEvent,
+}
+
+/// Handle used by the services to publish the events of the feed, served
+/// over websocket by the API.
+#[derive(Clone)]
+pub struct EventFeed {
+    sender: broadcast::Sender<FeedMessage>,
+    tenant: Option<String>,
+}
+
+impl EventFeed {
+    pub fn new() -> Self {
+        Self {
+            sender: broadcast::channel(FEED_CAPACITY).0,
+            tenant: None,
+        }
+    }
+
+    /// Returns a handle publishing the events of a tenant game on the same feed.
+    pub fn for_tenant(&self, tenant: &str) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            tenant: Some(tenant.to_string()),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<FeedMessage> {
+        self.sender.subscribe()
+    }
+
+    pub fn publish(&self, event: FeedEvent) {
+        let message = FeedMessage {
+            tenant: self.tenant.clone(),
+            timestamp: unix_now(),
+            event,
+        };
+        // No subscriber is not an error.
+        let _ = self.sender.send(message);
+    }
+}
+
+impl Default for EventFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cainome::cairo_serde::U256;
+    use starknet::core::types::Felt;
+
+    use super::{EventFeed, FeedEvent};
+
+    #[test]
+    fn test_feed_messages_are_tagged_with_the_tenant() {
+        let feed = EventFeed::new();
+        let mut receiver = feed.subscribe();
+        let event = FeedEvent::HighScoreUsed {
+            score: 42,
+            tx_hash: Felt::from(0xabc_u64),
+        };
+        feed.publish(event.clone());
+        feed.for_tenant("arcade")
+            .publish(FeedEvent::PlayerRewarded {
+                player: Felt::ONE,
+                amount: U256 { low: 10, high: 0 },
+                token: Felt::TWO,
+                usd: Some("12.5".parse().unwrap()),
+                tx_hash: Felt::from(0xabc_u64),
+            });
+
+        let message = receiver.try_recv().unwrap();
+        assert_eq!(message.tenant, None);
+        assert_eq!(message.event, event);
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["type"], "HighScoreUsed");
+        assert_eq!(json["score"], 42);
+        assert!(json.get("tenant").is_none());
+
+        let message = receiver.try_recv().unwrap();
+        assert_eq!(message.tenant.as_deref(), Some("arcade"));
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["type"], "PlayerRewarded");
+        assert_eq!(json["tenant"], "arcade");
+        assert_eq!(json["usd"], "12.5");
+    }
+}
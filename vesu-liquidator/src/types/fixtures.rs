@@ -0,0 +1,46 @@
+//! Positions shared by the tests: ETH (0x10) borrowing USDC (0x20) on Vesu,
+//! in the pool 0x1.
+
+use std::str::FromStr;
+
+use bigdecimal::BigDecimal;
+use starknet::core::types::Felt;
+
+use crate::{
+    protocols::ProtocolKind,
+    types::{
+        asset::Asset,
+        position::{Position, PositionKey},
+    },
+};
+
+/// Key of the [`position`] of `user`.
+pub fn key(user: u64) -> PositionKey {
+    PositionKey {
+        pool_id: Felt::ONE,
+        collateral: Felt::from(0x10_u64),
+        debt: Felt::from(0x20_u64),
+        user: Felt::from(user),
+        protocol: ProtocolKind::Vesu,
+    }
+}
+
+/// Position of `user` with 1 ETH of collateral, `debt` USDC of debt & a LLTV
+/// of 0.8.
+pub fn position(user: u64, debt: &str) -> Position {
+    Position {
+        user_address: Felt::from(user),
+        pool_id: Felt::ONE,
+        pool_name: None,
+        collateral: Asset {
+            amount: BigDecimal::from(1),
+            ..Asset::new("ETH".to_string(), Felt::from(0x10_u64), 18)
+        },
+        debt: Asset {
+            amount: BigDecimal::from_str(debt).unwrap(),
+            ..Asset::new("USDC".to_string(), Felt::from(0x20_u64), 6)
+        },
+        lltv: BigDecimal::from_str("0.8").unwrap(),
+        protocol: ProtocolKind::Vesu,
+    }
+}
@@ -0,0 +1,109 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+use crate::utils::{
+    serialization::sorted_map,
+    torii::{PayoutAddressModel, PlayerScoreModel, RedeemModel, SeasonModel},
+};
+
+/// Local mirror of the game models indexed by Torii.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GameState {
+    pub redeem_queue: Vec<RedeemModel>,
+    pub highest_score: Option<u128>,
+    /// Best players, only synced for the `leaderboard` strategy.
+    #[serde(default)]
+    pub leaderboard: Vec<PlayerScoreModel>,
+    pub seasons: Vec<SeasonModel>,
+    /// Payout address overrides of the players in the queue, by player address.
+    #[serde(serialize_with = "sorted_map")]
+    pub payout_addresses: HashMap<String, PayoutAddressModel>,
+    /// Unix timestamp (in seconds) of the last successful sync, 0 if never synced.
+    pub synced_at: u64,
+}
+
+impl GameState {
+    pub fn last_season_id(&self) -> Option<u32> {
+        self.seasons.iter().map(|season| season.season_id).max()
+    }
+
+    /// Returns the season running at `now`, the latest one if they overlap,
+    /// `None` between two seasons.
+    pub fn active_season(&self, now: u64) -> Option<u32> {
+        self.seasons
+            .iter()
+            .filter(|season| season.start_time <= now && now < season.end_time)
+            .map(|season| season.season_id)
+            .max()
+    }
+
+    /// Returns true if the mirror was synced less than `max_age` seconds ago.
+    pub fn is_fresh(&self, max_age: u64, now: u64) -> bool {
+        self.synced_at > 0 && now.saturating_sub(self.synced_at) <= max_age
+    }
+
+    /// Returns true if the payout address of the player is synced, i.e. it is
+    /// in the redeem queue or on the leaderboard.
+    pub fn is_synced_player(&self, player: Felt) -> bool {
+        let is_player = |address: &String| Felt::from_hex(address).ok() == Some(player);
+        self.redeem_queue.iter().any(|r| is_player(&r.player))
+            || self
+                .leaderboard
+                .iter()
+                .any(|entry| is_player(&entry.player))
+    }
+
+    pub fn payout_address(&self, player: Felt) -> Option<&PayoutAddressModel> {
+        self.payout_addresses.get(&format!("{player:#x}"))
+    }
+}
+
+/// Thread-safe wrapper around the game state mirror.
+#[derive(Clone, Default)]
+pub struct GameMirror(pub Arc<RwLock<GameState>>);
+
+impl GameMirror {
+    pub fn new(state: GameState) -> Self {
+        Self(Arc::new(RwLock::new(state)))
+    }
+
+    pub fn snapshot(&self) -> GameState {
+        self.0.read().expect("game mirror lock poisoned").clone()
+    }
+
+    pub fn replace(&self, state: GameState) {
+        *self.0.write().expect("game mirror lock poisoned") = state;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GameState;
+    use crate::utils::torii::SeasonModel;
+
+    fn season(season_id: u32, start_time: u64, end_time: u64) -> SeasonModel {
+        SeasonModel {
+            season_id,
+            start_time,
+            end_time,
+        }
+    }
+
+    #[test]
+    fn test_active_season() {
+        let state = GameState {
+            seasons: vec![season(1, 100, 200), season(2, 200, 300)],
+            ..Default::default()
+        };
+        assert_eq!(state.active_season(50), None);
+        assert_eq!(state.active_season(100), Some(1));
+        assert_eq!(state.active_season(200), Some(2));
+        assert_eq!(state.active_season(300), None);
+        assert_eq!(state.last_season_id(), Some(2));
+    }
+}
@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+use strum::Display;
+
+use crate::types::{accounting::ValuedAmount, ledger::Finality, position::PositionKey};
+
+/// How a liquidation attempted by the bot ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum LiquidationStatus {
+    /// The transaction went through.
+    Succeeded,
+    /// Not sent: the position was healthy or its liquidation would revert.
+    Skipped,
+    /// The transaction could not be built, sent or confirmed.
+    Failed,
+}
+
+/// A liquidation attempted by the bot, kept in the storage to audit what
+/// happened.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LiquidationRecord {
+    pub key: PositionKey,
+    pub pool_id: Felt,
+    pub user: Felt,
+    pub collateral: String,
+    pub debt: String,
+    /// Unix timestamp (in seconds) of the start of the liquidation.
+    pub started_at: u64,
+    pub duration_ms: u64,
+    pub status: LiquidationStatus,
+    /// Unset if no transaction was sent.
+    pub tx_hash: Option<Felt>,
+    pub block_number: Option<u64>,
+    /// Decoded from the liquidation event, unset if it was not found.
+    pub collateral_seized: Option<ValuedAmount>,
+    pub debt_repaid: Option<ValuedAmount>,
+    pub earnings: Option<ValuedAmount>,
+    /// Fee actually paid, read from the receipt.
+    pub gas: Option<ValuedAmount>,
+    pub error: Option<String>,
+    /// Finality of the transaction, unset if none was accepted.
+    #[serde(default)]
+    pub finality: Option<Finality>,
+}
+
+/// Filters of the liquidation history, every set filter having to match.
+/// Also the query of the `/liquidations` route of the API.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HistoryFilter {
+    pub user: Option<Felt>,
+    pub pool_id: Option<Felt>,
+    /// Ticker of the collateral or of the debt, case insensitive.
+    pub asset: Option<String>,
+    pub status: Option<LiquidationStatus>,
+    /// Unix timestamps (in seconds) bounding the start of the liquidations.
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+    /// Maximum number of liquidations returned, the most recent ones.
+    pub limit: Option<usize>,
+}
+
+impl HistoryFilter {
+    pub fn matches(&self, record: &LiquidationRecord) -> bool {
+        self.user.is_none_or(|user| record.user == user)
+            && self.pool_id.is_none_or(|pool_id| record.pool_id == pool_id)
+            && self.asset.as_ref().is_none_or(|asset| {
+                record.collateral.eq_ignore_ascii_case(asset)
+                    || record.debt.eq_ignore_ascii_case(asset)
+            })
+            && self.status.is_none_or(|status| record.status == status)
+            && self.since.is_none_or(|since| record.started_at >= since)
+            && self.until.is_none_or(|until| record.started_at <= until)
+    }
+
+    /// Returns the matching liquidations of the history, most recent first.
+    pub fn apply(&self, history: &[LiquidationRecord]) -> Vec<LiquidationRecord> {
+        history
+            .iter()
+            .rev()
+            .filter(|record| self.matches(record))
+            .take(self.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+
+    use super::{HistoryFilter, LiquidationRecord, LiquidationStatus};
+    use crate::types::position::PositionKey;
+
+    fn record(user: u64, started_at: u64, status: LiquidationStatus) -> LiquidationRecord {
+        LiquidationRecord {
+            key: PositionKey {
+                pool_id: Felt::ONE,
+                collateral: Felt::TWO,
+                debt: Felt::THREE,
+                user: Felt::from(user),
+                protocol: Default::default(),
+            },
+            pool_id: Felt::ONE,
+            user: Felt::from(user),
+            collateral: "ETH".to_string(),
+            debt: "USDC".to_string(),
+            started_at,
+            duration_ms: 1_500,
+            status,
+            tx_hash: None,
+            block_number: None,
+            collateral_seized: None,
+            debt_repaid: None,
+            earnings: None,
+            gas: None,
+            error: None,
+            finality: None,
+        }
+    }
+
+    #[test]
+    fn test_history_filter() {
+        let history = vec![
+            record(1, 100, LiquidationStatus::Succeeded),
+            record(2, 200, LiquidationStatus::Failed),
+            record(1, 300, LiquidationStatus::Skipped),
+            record(1, 400, LiquidationStatus::Succeeded),
+        ];
+        let started_at = |filter: HistoryFilter| -> Vec<u64> {
+            filter
+                .apply(&history)
+                .iter()
+                .map(|record| record.started_at)
+                .collect()
+        };
+
+        assert_eq!(started_at(HistoryFilter::default()), [400, 300, 200, 100]);
+        assert_eq!(
+            started_at(HistoryFilter {
+                user: Some(Felt::ONE),
+                status: Some(LiquidationStatus::Succeeded),
+                ..Default::default()
+            }),
+            [400, 100]
+        );
+        assert_eq!(
+            started_at(HistoryFilter {
+                asset: Some("usdc".to_string()),
+                since: Some(200),
+                until: Some(300),
+                ..Default::default()
+            }),
+            [300, 200]
+        );
+        assert_eq!(
+            started_at(HistoryFilter {
+                pool_id: Some(Felt::ONE),
+                limit: Some(1),
+                ..Default::default()
+            }),
+            [400]
+        );
+        assert!(
+            started_at(HistoryFilter {
+                asset: Some("wbtc".to_string()),
+                ..Default::default()
+            })
+            .is_empty()
+        );
+    }
+}
@@ -0,0 +1,380 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Instant,
+};
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+use tokio::sync::mpsc::{self, Receiver, Sender, WeakSender, error::TrySendError};
+
+use crate::types::{
+    competition::ObservedLiquidation,
+    position::{Position, PositionKey},
+};
+
+/// How many of the last indexed blocks are kept to detect reorgs. A reorg
+/// deeper than this can't be rolled back precisely.
+pub const REORG_WINDOW: usize = 64;
+
+/// What the indexer sends to the monitoring service.
+#[derive(Debug, Clone)]
+pub enum IndexerEvent {
+    /// A position was created or modified in the block.
+    Position(u64, Position),
+    /// A position was liquidated in the block, by the bot or by anyone else.
+    Liquidation(u64, ObservedLiquidation),
+    /// All the events of the block have been sent.
+    Block { number: u64, hash: Felt },
+    /// The blocks after `last_valid_block` have been orphaned by a reorg.
+    Reorg { last_valid_block: u64 },
+}
+
+/// Creates the channel of the [`IndexerEvent`]s, holding at most `capacity`
+/// of them: the indexer waits for the monitoring service beyond that, so a
+/// backfill can't buffer the whole chain in memory.
+pub fn indexer_channel(capacity: usize) -> (IndexerSender, Receiver<IndexerEvent>) {
+    let (sender, receiver) = mpsc::channel(capacity);
+    let sender = IndexerSender {
+        sender,
+        stats: Arc::new(IndexerQueueStats::default()),
+    };
+    (sender, receiver)
+}
+
+/// Counters of the sends parked on a full channel & of the events dropped
+/// because the monitoring service was gone, with the progress of the indexer
+/// checked by its watchdog.
+#[derive(Debug, Default)]
+struct IndexerQueueStats {
+    parked: AtomicU64,
+    parked_ms: AtomicU64,
+    dropped: AtomicU64,
+    last_block: AtomicU64,
+    head_block: AtomicU64,
+    stalls: AtomicU64,
+}
+
+/// Sending side of the [`indexer_channel`].
+#[derive(Debug, Clone)]
+pub struct IndexerSender {
+    sender: Sender<IndexerEvent>,
+    stats: Arc<IndexerQueueStats>,
+}
+
+impl IndexerSender {
+    /// Sends the event, waiting for room while the monitoring service is
+    /// saturated. Fails if the monitoring service is gone.
+    pub async fn send(&self, event: IndexerEvent) -> Result<()> {
+        match &event {
+            IndexerEvent::Block { number, .. } => {
+                self.stats.last_block.store(*number, Ordering::Relaxed);
+            }
+            IndexerEvent::Reorg { last_valid_block } => {
+                self.stats
+                    .last_block
+                    .store(*last_valid_block, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+        let event = match self.sender.try_send(event) {
+            Ok(()) => return Ok(()),
+            Err(TrySendError::Full(event)) => event,
+            Err(TrySendError::Closed(_)) => return Err(self.dropped()),
+        };
+
+        self.stats.parked.fetch_add(1, Ordering::Relaxed);
+        let parked_at = Instant::now();
+        let sent = self.sender.send(event).await;
+        let parked_ms = parked_at.elapsed().as_millis() as u64;
+        self.stats.parked_ms.fetch_add(parked_ms, Ordering::Relaxed);
+        tracing::debug!(
+            parked_ms,
+            "[🔍 Indexer] Monitoring saturated, waited {}ms to send an event",
+            parked_ms
+        );
+        sent.map_err(|_| self.dropped())
+    }
+
+    /// Last block processed by the indexer, 0 if none yet.
+    pub fn last_block(&self) -> u64 {
+        self.stats.last_block.load(Ordering::Relaxed)
+    }
+
+    /// Records the head of the chain the indexer was compared to, & whether
+    /// it was found stalled.
+    pub fn record_head(&self, head_block: u64, stalled: bool) {
+        self.stats.head_block.store(head_block, Ordering::Relaxed);
+        if stalled {
+            self.stats.stalls.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn dropped(&self) -> anyhow::Error {
+        self.stats.dropped.fetch_add(1, Ordering::Relaxed);
+        anyhow!("Could not send indexer event, the monitoring service is gone")
+    }
+
+    /// Handle reading the metrics of the channel, without keeping it open.
+    pub fn monitor(&self) -> IndexerQueueMonitor {
+        IndexerQueueMonitor {
+            sender: self.sender.downgrade(),
+            stats: self.stats.clone(),
+            capacity: self.sender.max_capacity(),
+        }
+    }
+}
+
+/// Reads the metrics of the [`indexer_channel`], see [`IndexerSender::monitor`].
+#[derive(Debug, Clone)]
+pub struct IndexerQueueMonitor {
+    sender: WeakSender<IndexerEvent>,
+    stats: Arc<IndexerQueueStats>,
+    capacity: usize,
+}
+
+impl IndexerQueueMonitor {
+    pub fn metrics(&self) -> IndexerQueueMetrics {
+        // Once the indexer is gone, nothing is left to be queued.
+        let depth = self
+            .sender
+            .upgrade()
+            .map(|sender| self.capacity - sender.capacity())
+            .unwrap_or_default();
+        IndexerQueueMetrics {
+            capacity: self.capacity,
+            depth,
+            parked: self.stats.parked.load(Ordering::Relaxed),
+            parked_ms: self.stats.parked_ms.load(Ordering::Relaxed),
+            dropped: self.stats.dropped.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn lag_metrics(&self) -> IndexerLagMetrics {
+        let last_block = self.stats.last_block.load(Ordering::Relaxed);
+        let head_block = self.stats.head_block.load(Ordering::Relaxed);
+        IndexerLagMetrics {
+            last_block,
+            head_block,
+            lag_blocks: head_block.saturating_sub(last_block),
+            stalls: self.stats.stalls.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Backpressure of the indexer → monitoring channel.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct IndexerQueueMetrics {
+    pub capacity: usize,
+    /// Events waiting for the monitoring service.
+    pub depth: usize,
+    /// Sends that waited for room in the channel, & how long in total.
+    pub parked: u64,
+    pub parked_ms: u64,
+    /// Events lost because the monitoring service was gone.
+    pub dropped: u64,
+}
+
+/// Lag of the indexer behind the head of the chain, at the last check of its
+/// watchdog.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct IndexerLagMetrics {
+    pub last_block: u64,
+    pub head_block: u64,
+    pub lag_blocks: u64,
+    /// Checks that found the indexer lagging without progressing.
+    pub stalls: u64,
+}
+
+/// A block processed by the indexer.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IndexedBlock {
+    pub number: u64,
+    /// Hash of the block, zero while it is pending.
+    pub hash: Felt,
+    /// Positions first seen in this block, dropped if it is orphaned.
+    pub new_positions: Vec<PositionKey>,
+}
+
+/// The last [`REORG_WINDOW`] indexed blocks, from the oldest to the most recent.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IndexedBlocks(pub VecDeque<IndexedBlock>);
+
+impl IndexedBlocks {
+    /// Records a processed block. A block processed again, e.g. once pending
+    /// & then accepted, is merged with its previous record.
+    pub fn record(&mut self, block: IndexedBlock) {
+        match self.0.back_mut() {
+            Some(last) if last.number == block.number => {
+                last.hash = block.hash;
+                for key in block.new_positions {
+                    if !last.new_positions.contains(&key) {
+                        last.new_positions.push(key);
+                    }
+                }
+            }
+            _ => self.0.push_back(block),
+        }
+        while self.0.len() > REORG_WINDOW {
+            self.0.pop_front();
+        }
+    }
+
+    /// Drops the blocks after `last_valid_block` & returns the positions first
+    /// seen in them.
+    pub fn rollback(&mut self, last_valid_block: u64) -> Vec<PositionKey> {
+        let mut orphaned_positions = vec![];
+        while let Some(block) = self.0.pop_back() {
+            if block.number <= last_valid_block {
+                self.0.push_back(block);
+                break;
+            }
+            orphaned_positions.extend(block.new_positions);
+        }
+        orphaned_positions
+    }
+
+    /// The accepted blocks, from the most recent to the oldest.
+    pub fn accepted(&self) -> Vec<(u64, Felt)> {
+        self.0
+            .iter()
+            .rev()
+            .filter(|block| block.hash != Felt::ZERO)
+            .map(|block| (block.number, block.hash))
+            .collect()
+    }
+
+    pub fn oldest(&self) -> Option<u64> {
+        self.0.front().map(|block| block.number)
+    }
+
+    pub fn latest(&self) -> Option<u64> {
+        self.0.back().map(|block| block.number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+
+    use super::{IndexedBlock, IndexedBlocks, IndexerEvent, REORG_WINDOW, indexer_channel};
+    use crate::types::{fixtures::key, position::PositionKey};
+
+    fn block(number: u64, hash: u64, new_positions: Vec<PositionKey>) -> IndexedBlock {
+        IndexedBlock {
+            number,
+            hash: Felt::from(hash),
+            new_positions,
+        }
+    }
+
+    #[test]
+    fn test_record_merges_a_block_processed_again() {
+        let mut blocks = IndexedBlocks::default();
+        blocks.record(block(10, 0xa, vec![]));
+        blocks.record(block(11, 0, vec![key(1)]));
+        blocks.record(block(11, 0xb, vec![key(1), key(2)]));
+
+        assert_eq!(
+            blocks,
+            IndexedBlocks(
+                vec![block(10, 0xa, vec![]), block(11, 0xb, vec![key(1), key(2)])].into()
+            )
+        );
+        assert_eq!(
+            blocks.accepted(),
+            vec![(11, Felt::from(0xb_u64)), (10, Felt::from(0xa_u64))]
+        );
+    }
+
+    #[test]
+    fn test_record_keeps_the_reorg_window() {
+        let mut blocks = IndexedBlocks::default();
+        for number in 0..(REORG_WINDOW as u64 + 10) {
+            blocks.record(block(number, number + 1, vec![]));
+        }
+        assert_eq!(blocks.0.len(), REORG_WINDOW);
+        assert_eq!(blocks.oldest(), Some(10));
+        assert_eq!(blocks.latest(), Some(REORG_WINDOW as u64 + 9));
+    }
+
+    #[test]
+    fn test_rollback_returns_the_orphaned_positions() {
+        let mut blocks = IndexedBlocks::default();
+        blocks.record(block(10, 0xa, vec![key(1)]));
+        blocks.record(block(12, 0xc, vec![key(2)]));
+        blocks.record(block(13, 0, vec![key(3), key(4)]));
+
+        assert_eq!(blocks.rollback(11), vec![key(3), key(4), key(2)]);
+        assert_eq!(blocks.latest(), Some(10));
+        assert!(blocks.rollback(10).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_indexer_channel_backpressure() {
+        let (sender, mut receiver) = indexer_channel(1);
+        let monitor = sender.monitor();
+        let reorg = |last_valid_block| IndexerEvent::Reorg { last_valid_block };
+
+        sender.send(reorg(1)).await.unwrap();
+        assert_eq!(monitor.metrics().depth, 1);
+        assert_eq!(monitor.metrics().parked, 0);
+
+        // The channel is full: the send is parked until the event is received.
+        let parked = tokio::spawn({
+            let sender = sender.clone();
+            async move { sender.send(reorg(2)).await }
+        });
+        tokio::task::yield_now().await;
+        assert!(!parked.is_finished());
+        assert!(matches!(
+            receiver.recv().await,
+            Some(IndexerEvent::Reorg {
+                last_valid_block: 1
+            })
+        ));
+        parked.await.unwrap().unwrap();
+        let metrics = monitor.metrics();
+        assert_eq!((metrics.capacity, metrics.depth), (1, 1));
+        assert_eq!((metrics.parked, metrics.dropped), (1, 0));
+
+        // Without a receiver, the events are dropped.
+        drop(receiver);
+        assert!(sender.send(reorg(3)).await.is_err());
+        assert_eq!(monitor.metrics().dropped, 1);
+    }
+
+    #[tokio::test]
+    async fn test_indexer_lag_metrics() {
+        let (sender, _receiver) = indexer_channel(8);
+        let monitor = sender.monitor();
+        for number in [10, 11] {
+            sender
+                .send(IndexerEvent::Block {
+                    number,
+                    hash: Felt::ONE,
+                })
+                .await
+                .unwrap();
+        }
+        sender.record_head(15, false);
+        assert_eq!(sender.last_block(), 11);
+        assert_eq!(monitor.lag_metrics().lag_blocks, 4);
+
+        // A reorg moves the indexer back.
+        sender
+            .send(IndexerEvent::Reorg {
+                last_valid_block: 9,
+            })
+            .await
+            .unwrap();
+        sender.record_head(15, true);
+        let metrics = monitor.lag_metrics();
+        assert_eq!((metrics.last_block, metrics.lag_blocks), (9, 6));
+        assert_eq!(metrics.stalls, 1);
+    }
+}
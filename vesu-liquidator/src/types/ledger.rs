@@ -0,0 +1,637 @@
+use bigdecimal::BigDecimal;
+use cainome::cairo_serde::U256;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+use crate::utils::{constants::U256_ZERO, serialization::optional_plain_decimal};
+
+/// An ERC20 transfer sent by the bot, recorded when its transaction succeeded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PayoutRecord {
+    pub tx_hash: Felt,
+    pub block_number: u64,
+    pub token: Felt,
+    pub recipient: Felt,
+    pub amount: U256,
+    /// Value of the amount at the oracle price when it was paid, `None` if
+    /// the token had no fresh price.
+    #[serde(default, serialize_with = "optional_plain_decimal")]
+    pub usd: Option<BigDecimal>,
+    /// True once the record was checked against the on-chain transfers.
+    #[serde(default)]
+    pub checked: bool,
+    /// Season of the game running when it was paid, with
+    /// `distribution.seasons`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub season_id: Option<u32>,
+    /// Accepted on L2 when recorded, on L1 once checked by the
+    /// [`crate::services::finality::FinalityService`].
+    #[serde(default)]
+    pub finality: Finality,
+}
+
+/// Finality of a transaction of the bot, see `finality`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Finality {
+    /// Accepted by the sequencer, until its block is proven on L1.
+    #[default]
+    AcceptedOnL2,
+    /// Its block was proven & settled on Ethereum.
+    AcceptedOnL1,
+}
+
+/// Transfer to the world of a distribution, held until the liquidations it
+/// pays are accepted on L1, see `finality.hold_world_share`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeldWorldShare {
+    pub token: Felt,
+    pub recipient: Felt,
+    pub amount: U256,
+    pub liquidation_txs: Vec<Felt>,
+    /// Distribution paying the players, unset if it only had the world share
+    /// to pay.
+    pub distribution_tx: Option<Felt>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub season_id: Option<u32>,
+    /// Unix timestamp (in seconds) of the distribution.
+    pub held_at: u64,
+}
+
+/// An outgoing ERC20 transfer of the bot account observed on chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObservedTransfer {
+    pub tx_hash: Felt,
+    pub block_number: u64,
+    pub token: Felt,
+    pub recipient: Felt,
+    pub amount: U256,
+}
+
+impl PayoutRecord {
+    fn matches(&self, transfer: &ObservedTransfer) -> bool {
+        self.tx_hash == transfer.tx_hash
+            && self.token == transfer.token
+            && self.recipient == transfer.recipient
+            && self.amount == transfer.amount
+    }
+}
+
+/// Mismatch between the payout ledger & the chain.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Discrepancy {
+    /// A transfer of the bot with no ledger entry.
+    UnexpectedOutflow(ObservedTransfer),
+    /// A ledger entry with no transfer on chain.
+    MissingTransfer(PayoutRecord),
+}
+
+/// Payouts sent by the bot, reconciled against the chain by the
+/// [`crate::services::reconciliation::ReconciliationService`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PayoutLedger {
+    pub records: Vec<PayoutRecord>,
+    /// Last block whose transfers were reconciled, 0 if never reconciled.
+    pub last_block_reconciled: u64,
+}
+
+impl PayoutLedger {
+    /// Returns the first block with payouts left to reconcile.
+    pub fn first_unchecked_block(&self) -> Option<u64> {
+        self.records
+            .iter()
+            .filter(|record| !record.checked)
+            .map(|record| record.block_number)
+            .min()
+    }
+
+    /// Matches the transfers observed up to `to_block` with the recorded
+    /// payouts & returns the discrepancies. Each record is checked once.
+    pub fn reconcile(&mut self, transfers: &[ObservedTransfer], to_block: u64) -> Vec<Discrepancy> {
+        let mut discrepancies: Vec<Discrepancy> = transfers
+            .iter()
+            .filter(|transfer| !self.check_transfer(transfer))
+            .map(|transfer| Discrepancy::UnexpectedOutflow(transfer.clone()))
+            .collect();
+        discrepancies.extend(self.close_round(to_block));
+        discrepancies
+    }
+
+    /// Checks the first unchecked record matching the transfer, returns false
+    /// if there is none.
+    pub fn check_transfer(&mut self, transfer: &ObservedTransfer) -> bool {
+        let record = self
+            .records
+            .iter_mut()
+            .find(|record| !record.checked && record.matches(transfer));
+        match record {
+            Some(record) => {
+                record.checked = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Ends a reconciliation round once the transfers up to `to_block` are
+    /// checked: the records left unchecked are returned as missing transfers.
+    pub fn close_round(&mut self, to_block: u64) -> Vec<Discrepancy> {
+        let mut discrepancies = vec![];
+        for record in self
+            .records
+            .iter_mut()
+            .filter(|record| !record.checked && record.block_number <= to_block)
+        {
+            record.checked = true;
+            discrepancies.push(Discrepancy::MissingTransfer(record.clone()));
+        }
+
+        self.last_block_reconciled = self.last_block_reconciled.max(to_block);
+        discrepancies
+    }
+
+    /// Returns the transactions of the records not yet accepted on L1, once
+    /// each.
+    pub fn txs_accepted_on_l2(&self) -> Vec<Felt> {
+        let mut tx_hashes: Vec<Felt> = vec![];
+        for record in self
+            .records
+            .iter()
+            .filter(|record| record.finality == Finality::AcceptedOnL2)
+        {
+            if !tx_hashes.contains(&record.tx_hash) {
+                tx_hashes.push(record.tx_hash);
+            }
+        }
+        tx_hashes
+    }
+
+    /// Marks the records of the transactions as accepted on L1.
+    pub fn mark_accepted_on_l1(&mut self, tx_hashes: &[Felt]) {
+        for record in self
+            .records
+            .iter_mut()
+            .filter(|record| tx_hashes.contains(&record.tx_hash))
+        {
+            record.finality = Finality::AcceptedOnL1;
+        }
+    }
+}
+
+/// Dust of a player in a token, not paid yet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DustBalance {
+    pub player: Felt,
+    pub token: Felt,
+    pub amount: U256,
+}
+
+/// Shares of the players below the `min_payout` of their token, kept in the
+/// bot account until their total is worth a transfer.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DustLedger {
+    pub balances: Vec<DustBalance>,
+    /// Season the dust was accrued in, with `distribution.seasons`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub season_id: Option<u32>,
+}
+
+impl DustLedger {
+    /// Starts the ledger of a new season & returns the dust of the previous
+    /// one, empty if the season didn't change.
+    pub fn rollover(&mut self, season_id: Option<u32>) -> Vec<DustBalance> {
+        if self.season_id == season_id {
+            return vec![];
+        }
+        self.season_id = season_id;
+        std::mem::take(&mut self.balances)
+    }
+
+    /// Adds a share to the dust of the player & returns the amount to pay,
+    /// the share with all the dust of the player, once it reaches
+    /// `min_payout`. `None` if the share is kept as dust.
+    pub fn accrue(
+        &mut self,
+        player: Felt,
+        token: Felt,
+        amount: U256,
+        min_payout: U256,
+    ) -> Option<U256> {
+        let index = self
+            .balances
+            .iter()
+            .position(|balance| balance.player == player && balance.token == token);
+        let total = match index {
+            Some(index) => self.balances[index].amount + amount,
+            None => amount,
+        };
+        if total >= min_payout {
+            if let Some(index) = index {
+                self.balances.swap_remove(index);
+            }
+            return Some(total);
+        }
+        match index {
+            Some(index) => self.balances[index].amount = total,
+            None if total == U256_ZERO => {}
+            None => self.balances.push(DustBalance {
+                player,
+                token,
+                amount: total,
+            }),
+        }
+        None
+    }
+}
+
+/// Window over which the `max_daily_payout` of the players is enforced.
+pub const PAYOUT_CAP_WINDOW_SECONDS: u64 = 24 * 60 * 60;
+
+/// A share sent to a player.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerPayoutRecord {
+    pub player: Felt,
+    pub token: Felt,
+    pub amount: U256,
+    /// Unix timestamp (in seconds) of the payout.
+    pub paid_at: u64,
+}
+
+/// Shares sent to the players over the last [`PAYOUT_CAP_WINDOW_SECONDS`],
+/// to cap what a single player can receive.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecentPayouts {
+    pub records: Vec<PlayerPayoutRecord>,
+    /// Season the payouts were sent in, with `distribution.seasons`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub season_id: Option<u32>,
+}
+
+impl RecentPayouts {
+    /// Starts the caps of a new season, forgetting the payouts of the previous
+    /// one. Returns false if the season didn't change.
+    pub fn rollover(&mut self, season_id: Option<u32>) -> bool {
+        if self.season_id == season_id {
+            return false;
+        }
+        self.season_id = season_id;
+        self.records.clear();
+        true
+    }
+
+    /// Returns the part of `amount` the player can still receive in the
+    /// window without exceeding `max_payout`.
+    pub fn capped(
+        &self,
+        player: Felt,
+        token: Felt,
+        amount: U256,
+        max_payout: U256,
+        now: u64,
+    ) -> U256 {
+        let since = now.saturating_sub(PAYOUT_CAP_WINDOW_SECONDS);
+        let paid = self
+            .records
+            .iter()
+            .filter(|record| {
+                record.player == player && record.token == token && record.paid_at > since
+            })
+            .fold(U256_ZERO, |total, record| total + record.amount);
+        if paid >= max_payout {
+            return U256_ZERO;
+        }
+        let allowance = max_payout - paid;
+        if amount > allowance {
+            allowance
+        } else {
+            amount
+        }
+    }
+
+    /// Records a payout & forgets the ones out of the window.
+    pub fn record(&mut self, player: Felt, token: Felt, amount: U256, now: u64) {
+        let since = now.saturating_sub(PAYOUT_CAP_WINDOW_SECONDS);
+        self.records.retain(|record| record.paid_at > since);
+        self.records.push(PlayerPayoutRecord {
+            player,
+            token,
+            amount,
+            paid_at: now,
+        });
+    }
+}
+
+/// Reconciles the ledgers of several games paid by the same account: a
+/// transfer is expected if any of the ledgers recorded it. The unrecorded
+/// transfers to the `ignored_recipients`, not payouts, are not flagged.
+pub fn reconcile_ledgers(
+    ledgers: &mut [PayoutLedger],
+    transfers: &[ObservedTransfer],
+    ignored_recipients: &[Felt],
+    to_block: u64,
+) -> Vec<Discrepancy> {
+    let Some((last, others)) = ledgers.split_last_mut() else {
+        return vec![];
+    };
+    let unmatched: Vec<ObservedTransfer> = transfers
+        .iter()
+        .filter(|transfer| {
+            !others
+                .iter_mut()
+                .any(|ledger| ledger.check_transfer(transfer))
+        })
+        .cloned()
+        .collect();
+    let mut discrepancies = vec![];
+    for ledger in others {
+        discrepancies.extend(ledger.close_round(to_block));
+    }
+    discrepancies.extend(
+        last.reconcile(&unmatched, to_block)
+            .into_iter()
+            .filter(|discrepancy| {
+                !matches!(discrepancy, Discrepancy::UnexpectedOutflow(transfer)
+                    if ignored_recipients.contains(&transfer.recipient))
+            }),
+    );
+    discrepancies
+}
+
+#[cfg(test)]
+mod tests {
+    use cainome::cairo_serde::U256;
+    use starknet::core::types::Felt;
+
+    use super::{
+        Discrepancy, DustLedger, Finality, ObservedTransfer, PAYOUT_CAP_WINDOW_SECONDS,
+        PayoutLedger, PayoutRecord, RecentPayouts, reconcile_ledgers,
+    };
+
+    fn record(tx_hash: u64, block_number: u64, amount: u128) -> PayoutRecord {
+        PayoutRecord {
+            tx_hash: Felt::from(tx_hash),
+            block_number,
+            token: Felt::ONE,
+            recipient: Felt::TWO,
+            amount: U256 {
+                low: amount,
+                high: 0,
+            },
+            usd: None,
+            checked: false,
+            season_id: None,
+            finality: Finality::AcceptedOnL2,
+        }
+    }
+
+    fn transfer(record: &PayoutRecord) -> ObservedTransfer {
+        ObservedTransfer {
+            tx_hash: record.tx_hash,
+            block_number: record.block_number,
+            token: record.token,
+            recipient: record.recipient,
+            amount: record.amount,
+        }
+    }
+
+    #[test]
+    fn test_reconcile_payout_ledger() {
+        let paid = record(1, 10, 100);
+        let silent_failure = record(2, 11, 50);
+        let not_yet_scanned = record(3, 30, 10);
+        let mut ledger = PayoutLedger {
+            records: vec![
+                paid.clone(),
+                silent_failure.clone(),
+                not_yet_scanned.clone(),
+            ],
+            last_block_reconciled: 0,
+        };
+        assert_eq!(ledger.first_unchecked_block(), Some(10));
+
+        let unexpected = transfer(&record(4, 12, 1_000));
+        let discrepancies = ledger.reconcile(&[transfer(&paid), unexpected.clone()], 20);
+        assert_eq!(
+            discrepancies,
+            vec![
+                Discrepancy::UnexpectedOutflow(unexpected),
+                Discrepancy::MissingTransfer(PayoutRecord {
+                    checked: true,
+                    ..silent_failure
+                }),
+            ]
+        );
+        assert_eq!(ledger.last_block_reconciled, 20);
+        assert_eq!(ledger.first_unchecked_block(), Some(30));
+
+        // A transfer is only matched once.
+        let discrepancies = ledger.reconcile(&[transfer(&paid), transfer(&not_yet_scanned)], 40);
+        assert_eq!(
+            discrepancies,
+            vec![Discrepancy::UnexpectedOutflow(transfer(&paid))]
+        );
+        assert_eq!(ledger.first_unchecked_block(), None);
+    }
+
+    #[test]
+    fn test_reconcile_ledgers() {
+        let first_game = record(1, 10, 100);
+        let second_game = record(1, 10, 200);
+        let silent_failure = record(2, 11, 50);
+        let mut ledgers = [
+            PayoutLedger {
+                records: vec![first_game.clone(), silent_failure.clone()],
+                last_block_reconciled: 0,
+            },
+            PayoutLedger {
+                records: vec![second_game.clone()],
+                last_block_reconciled: 0,
+            },
+        ];
+
+        let unexpected = transfer(&record(3, 12, 1_000));
+        let discrepancies = reconcile_ledgers(
+            &mut ledgers,
+            &[
+                transfer(&second_game),
+                transfer(&first_game),
+                unexpected.clone(),
+            ],
+            &[],
+            20,
+        );
+        assert_eq!(
+            discrepancies,
+            vec![
+                Discrepancy::MissingTransfer(PayoutRecord {
+                    checked: true,
+                    ..silent_failure
+                }),
+                Discrepancy::UnexpectedOutflow(unexpected),
+            ]
+        );
+        assert!(
+            ledgers
+                .iter()
+                .all(|ledger| ledger.last_block_reconciled == 20)
+        );
+        assert!(reconcile_ledgers(&mut [], &[], &[], 30).is_empty());
+    }
+
+    #[test]
+    fn test_reconcile_ignored_recipients() {
+        let payout = record(1, 10, 100);
+        let mut ledgers = [PayoutLedger {
+            records: vec![payout.clone()],
+            last_block_reconciled: 0,
+        }];
+
+        // The tokens sold by a swap, pulled by the exchange in the same
+        // transaction as the payout.
+        let exchange = Felt::from(0xe8c4_u64);
+        let swap = ObservedTransfer {
+            recipient: exchange,
+            ..transfer(&record(1, 10, 1_000))
+        };
+        let unexpected = transfer(&record(2, 11, 1_000));
+        let discrepancies = reconcile_ledgers(
+            &mut ledgers,
+            &[swap.clone(), transfer(&payout), unexpected.clone()],
+            &[exchange],
+            20,
+        );
+        assert_eq!(
+            discrepancies,
+            vec![Discrepancy::UnexpectedOutflow(unexpected)]
+        );
+        assert!(ledgers[0].records.iter().all(|record| record.checked));
+
+        // Flagged when the exchange is not ignored.
+        ledgers[0].records[0].checked = false;
+        let discrepancies =
+            reconcile_ledgers(&mut ledgers, &[swap.clone(), transfer(&payout)], &[], 20);
+        assert_eq!(discrepancies, vec![Discrepancy::UnexpectedOutflow(swap)]);
+    }
+
+    #[test]
+    fn test_payouts_finality() {
+        let mut ledger = PayoutLedger {
+            records: vec![record(1, 10, 100), record(1, 10, 50), record(2, 11, 10)],
+            last_block_reconciled: 0,
+        };
+        assert_eq!(ledger.txs_accepted_on_l2(), vec![Felt::ONE, Felt::TWO]);
+
+        ledger.mark_accepted_on_l1(&[Felt::ONE]);
+        assert_eq!(ledger.txs_accepted_on_l2(), vec![Felt::TWO]);
+        assert_eq!(
+            ledger
+                .records
+                .iter()
+                .map(|record| record.finality)
+                .collect::<Vec<_>>(),
+            vec![
+                Finality::AcceptedOnL1,
+                Finality::AcceptedOnL1,
+                Finality::AcceptedOnL2
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dust_is_paid_once_above_min_payout() {
+        let amount = |low: u128| U256 { low, high: 0 };
+        let mut ledger = DustLedger::default();
+        assert_eq!(
+            ledger.accrue(Felt::ONE, Felt::TWO, amount(4), amount(10)),
+            None
+        );
+        assert_eq!(
+            ledger.accrue(Felt::ONE, Felt::TWO, amount(5), amount(10)),
+            None
+        );
+        // The dust of another player or token is kept apart.
+        assert_eq!(
+            ledger.accrue(Felt::TWO, Felt::TWO, amount(3), amount(10)),
+            None
+        );
+        assert_eq!(ledger.balances[0].amount, amount(9));
+
+        assert_eq!(
+            ledger.accrue(Felt::ONE, Felt::TWO, amount(2), amount(10)),
+            Some(amount(11))
+        );
+        assert_eq!(ledger.balances.len(), 1);
+        assert_eq!(
+            ledger.accrue(Felt::THREE, Felt::TWO, amount(1), amount(0)),
+            Some(amount(1))
+        );
+    }
+
+    #[test]
+    fn test_payouts_are_capped_over_a_rolling_window() {
+        let amount = |low: u128| U256 { low, high: 0 };
+        let mut recent = RecentPayouts::default();
+        assert_eq!(
+            recent.capped(Felt::ONE, Felt::TWO, amount(60), amount(100), 1_000),
+            amount(60)
+        );
+        recent.record(Felt::ONE, Felt::TWO, amount(60), 1_000);
+
+        assert_eq!(
+            recent.capped(Felt::ONE, Felt::TWO, amount(60), amount(100), 2_000),
+            amount(40)
+        );
+        // Other players & tokens have their own cap.
+        assert_eq!(
+            recent.capped(Felt::TWO, Felt::TWO, amount(60), amount(100), 2_000),
+            amount(60)
+        );
+        assert_eq!(
+            recent.capped(Felt::ONE, Felt::ONE, amount(60), amount(100), 2_000),
+            amount(60)
+        );
+        recent.record(Felt::ONE, Felt::TWO, amount(40), 2_000);
+        assert_eq!(
+            recent.capped(Felt::ONE, Felt::TWO, amount(1), amount(100), 3_000),
+            amount(0)
+        );
+
+        // The first payout leaves the window.
+        let later = 1_000 + PAYOUT_CAP_WINDOW_SECONDS;
+        assert_eq!(
+            recent.capped(Felt::ONE, Felt::TWO, amount(80), amount(100), later),
+            amount(60)
+        );
+        recent.record(Felt::THREE, Felt::TWO, amount(1), later);
+        assert_eq!(recent.records.len(), 2);
+    }
+
+    #[test]
+    fn test_season_rollover_resets_dust_and_caps() {
+        let amount = |low: u128| U256 { low, high: 0 };
+        let mut dust = DustLedger::default();
+        let mut recent = RecentPayouts::default();
+        assert!(dust.rollover(Some(1)).is_empty());
+        assert!(recent.rollover(Some(1)));
+        dust.accrue(Felt::ONE, Felt::TWO, amount(4), amount(10));
+        recent.record(Felt::ONE, Felt::TWO, amount(100), 1_000);
+
+        // Same season, nothing is reset.
+        assert!(dust.rollover(Some(1)).is_empty());
+        assert!(!recent.rollover(Some(1)));
+        assert_eq!(
+            recent.capped(Felt::ONE, Felt::TWO, amount(10), amount(100), 1_000),
+            amount(0)
+        );
+
+        let forfeited = dust.rollover(Some(2));
+        assert_eq!(forfeited.len(), 1);
+        assert_eq!(forfeited[0].amount, amount(4));
+        assert!(dust.balances.is_empty());
+        assert!(recent.rollover(Some(2)));
+        assert_eq!(
+            recent.capped(Felt::ONE, Felt::TWO, amount(10), amount(100), 1_000),
+            amount(10)
+        );
+    }
+}
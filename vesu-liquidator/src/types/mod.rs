@@ -3,12 +3,29 @@ use std::sync::Arc;
 use starknet::providers::{JsonRpcClient, jsonrpc::HttpTransport};
 
 pub mod account;
+pub mod accounting;
 pub mod asset;
+pub mod attestation;
+pub mod backtest;
+pub mod balance;
+pub mod claims;
+pub mod competition;
+pub mod distribution;
+pub mod downtime;
+pub mod exposure;
+pub mod feed;
+#[cfg(test)]
+pub mod fixtures;
+pub mod game;
+pub mod history;
+pub mod indexer;
+pub mod ledger;
+pub mod notification;
+pub mod operator;
 pub mod position;
+pub mod session;
+pub mod token;
 
 pub type StarknetSingleOwnerAccount = Arc<
-    starknet::accounts::SingleOwnerAccount<
-        Arc<JsonRpcClient<HttpTransport>>,
-        starknet::signers::LocalWallet,
-    >,
+    starknet::accounts::SingleOwnerAccount<Arc<JsonRpcClient<HttpTransport>>, account::BotSigner>,
 >;
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+/// Severity of a notification, used to route it to the right channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            Severity::Info => "ℹ️",
+            Severity::Warning => "⚠️",
+            Severity::Critical => "🚨",
+        }
+    }
+}
+
+/// A message to send on the notification channels.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Notification {
+    /// Returns the message as posted on the channels.
+    pub fn format(&self) -> String {
+        format!(
+            "{} [Vesu Liquidator] {}",
+            self.severity.emoji(),
+            self.message
+        )
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+
+use cainome::cairo_serde::U256;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+/// Runbook action of an operator over the running bot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperatorActionKind {
+    /// Halts all the transactions of the bot.
+    Pause,
+    /// Resumes the transactions.
+    Resume,
+    /// Halts the transactions & sweeps the funds of the bot to the cold
+    /// address.
+    Sweep,
+}
+
+impl fmt::Display for OperatorActionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OperatorActionKind::Pause => "pause",
+            OperatorActionKind::Resume => "resume",
+            OperatorActionKind::Sweep => "sweep",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// An action of an operator, recorded in the storage for audit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperatorAction {
+    pub kind: OperatorActionKind,
+    /// Why the action was taken, as given by the operator.
+    pub reason: String,
+    /// Unix timestamp (in seconds) of the action.
+    pub at: u64,
+    /// Transaction of a sweep, if anything was swept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<Felt>,
+    /// Amounts transferred by a sweep.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub swept: Vec<SweptAmount>,
+    /// Why a sweep failed, the transactions staying halted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Amount of a token swept to the cold address.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SweptAmount {
+    pub token: Felt,
+    pub ticker: String,
+    pub amount: U256,
+}
+
+impl OperatorAction {
+    pub fn new(kind: OperatorActionKind, reason: String, at: u64) -> Self {
+        Self {
+            kind,
+            reason,
+            at,
+            tx_hash: None,
+            swept: vec![],
+            error: None,
+        }
+    }
+}
+
+impl fmt::Display for OperatorAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.kind, self.reason)?;
+        if let Some(tx_hash) = self.tx_hash {
+            write!(f, ", tx {tx_hash:#x}")?;
+        }
+        for swept in self.swept.iter() {
+            write!(f, ", {} {} swept", swept.amount, swept.ticker)?;
+        }
+        if let Some(error) = &self.error {
+            write!(f, ", failed: {error}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns if the transactions are halted after the actions, i.e. by a pause
+/// or a sweep not resumed since.
+pub fn is_halted(actions: &[OperatorAction]) -> bool {
+    actions
+        .last()
+        .is_some_and(|action| action.kind != OperatorActionKind::Resume)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OperatorAction, OperatorActionKind, is_halted};
+
+    #[test]
+    fn test_is_halted() {
+        let action = |kind| OperatorAction::new(kind, "drill".to_string(), 0);
+        assert!(!is_halted(&[]));
+        assert!(is_halted(&[action(OperatorActionKind::Pause)]));
+        assert!(!is_halted(&[
+            action(OperatorActionKind::Pause),
+            action(OperatorActionKind::Resume)
+        ]));
+        assert!(is_halted(&[
+            action(OperatorActionKind::Resume),
+            action(OperatorActionKind::Sweep)
+        ]));
+    }
+}
@@ -1,63 +1,210 @@
-use anyhow::{Result, anyhow};
-use apibara_core::starknet::v1alpha2::FieldElement;
+use anyhow::Result;
 use bigdecimal::{BigDecimal, FromPrimitive};
 use colored::Colorize;
-use dashmap::DashMap;
-use serde::{Deserialize, Serialize};
-use starknet::core::types::{BlockId, BlockTag, FunctionCall};
+use dashmap::{DashMap, DashSet};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use starknet::core::crypto::compute_hash_on_elements;
+use starknet::core::types::FunctionCall;
 use starknet::core::types::{Call, Felt};
+use starknet::core::utils::cairo_short_string_to_felt;
 use starknet::providers::jsonrpc::HttpTransport;
 use starknet::providers::{JsonRpcClient, Provider};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
-use std::hash::{Hash, Hasher};
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 
-use crate::bindings::liquidate::{Liquidate, LiquidateParams};
-
 use crate::config::{
-    Config, LIQUIDATION_CONFIG_SELECTOR, VESU_LTV_CONFIG_SELECTOR, VESU_POSITION_UNSAFE_SELECTOR,
+    Config, FLASH_LOAN_SELECTOR, LIQUIDATION_CONFIG_SELECTOR, LiquidationCooldownConfig,
+    LiquidationMode, LiquidationRetryConfig, PositionUpdateConfig,
 };
+use crate::protocols::{ProtocolKind, Protocols};
 use crate::services::oracle::LatestOraclePrices;
 use crate::storages::Storage;
-use crate::utils::constants::{U256_ZERO, VESU_RESPONSE_DECIMALS};
-use crate::utils::ekubo::get_ekubo_route;
-use crate::{types::asset::Asset, utils::conversions::apibara_field_as_felt};
-
-use super::StarknetSingleOwnerAccount;
+use crate::utils::constants::VESU_RESPONSE_DECIMALS;
+use crate::utils::errors::{LiquidatorError, is_retryable};
+use crate::utils::rpc::StarknetRpc;
+use crate::utils::serialization::{plain_decimal, sorted_map};
+use crate::{types::asset::Asset, utils::conversions::big_decimal_to_u256};
 
 /// Threshold for which we consider a position almost liquidable.
 const ALMOST_LIQUIDABLE_THRESHOLD: f64 = 0.01;
 
-/// Thread-safe wrapper around the positions.
-/// PositionsMap is a map between position position_key <=> position.
+/// Number of times the repaid debt is halved when looking for a partial
+/// liquidation that can go through.
+const MAX_PARTIAL_LIQUIDATION_STEPS: usize = 4;
+
+/// Thread-safe wrapper around the positions, indexed by key.
 #[derive(Clone)]
-pub struct PositionsMap(pub Arc<DashMap<u64, Position>>);
+pub struct PositionsMap {
+    pub by_key: Arc<DashMap<PositionKey, Position>>,
+    /// Keys by short id, used to detect their collisions.
+    short_ids: Arc<DashMap<u64, PositionKey>>,
+    asset_shards: Arc<AssetShards>,
+    /// Cooldowns of the positions whose liquidation was attempted.
+    cooldowns: Arc<DashMap<PositionKey, LiquidationCooldown>>,
+}
+
+/// Liquidation attempts of a position since it became liquidable, failed or
+/// partial, & until when it is not attempted again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LiquidationCooldown {
+    pub attempts: u32,
+    /// Unix timestamp (in seconds) of the end of the cooldown.
+    pub until: u64,
+}
+
+/// Keys of the positions sharded by (lowercase) collateral ticker, with an
+/// index by debt ticker, so a price move only visits the positions of its
+/// asset instead of scanning them all.
+#[derive(Default)]
+struct AssetShards {
+    by_collateral: DashMap<String, DashSet<PositionKey>>,
+    by_debt: DashMap<String, DashSet<PositionKey>>,
+}
+
+impl AssetShards {
+    fn insert(&self, position: &Position) {
+        let key = position.key();
+        self.by_collateral
+            .entry(position.collateral.name.to_lowercase())
+            .or_default()
+            .insert(key);
+        self.by_debt
+            .entry(position.debt.name.to_lowercase())
+            .or_default()
+            .insert(key);
+    }
+
+    fn remove(&self, position: &Position) {
+        let key = position.key();
+        for (shards, asset) in [
+            (&self.by_collateral, &position.collateral.name),
+            (&self.by_debt, &position.debt.name),
+        ] {
+            let asset = asset.to_lowercase();
+            if let Some(shard) = shards.get(&asset) {
+                shard.remove(&key);
+            }
+            shards.remove_if(&asset, |_, shard| shard.is_empty());
+        }
+    }
+
+    fn keys_with_asset(&self, asset: &str, keys: &mut HashSet<PositionKey>) {
+        let asset = asset.to_lowercase();
+        for shards in [&self.by_collateral, &self.by_debt] {
+            if let Some(shard) = shards.get(&asset) {
+                keys.extend(shard.iter().map(|key| *key));
+            }
+        }
+    }
+}
 
 impl PositionsMap {
     pub fn new() -> Self {
-        Self(Arc::new(DashMap::new()))
+        Self {
+            by_key: Arc::new(DashMap::new()),
+            short_ids: Arc::new(DashMap::new()),
+            asset_shards: Arc::new(AssetShards::default()),
+            cooldowns: Arc::new(DashMap::new()),
+        }
     }
 
     pub fn from_storage(storage: &dyn Storage) -> Self {
-        let positions = storage.get_positions();
-        let dash_map = DashMap::new();
-        for (key, value) in positions {
-            dash_map.insert(key, value);
+        let positions = Self::new();
+        for (_, position) in storage.get_positions() {
+            positions.insert(position);
         }
-        Self(Arc::new(dash_map))
+        positions
     }
 
+    /// Inserts the position under its key. Two positions whose short ids
+    /// collide are both kept, but are logged as ambiguous.
     pub fn insert(&self, position: Position) -> Option<Position> {
-        self.0.insert(position.key(), position)
+        let key = position.key();
+        let colliding_key = *self.short_ids.entry(key.short_id()).or_insert(key);
+        if colliding_key != key {
+            tracing::error!(
+                "Positions {:?} & {:?} share the id #{}, logs may mix them up",
+                colliding_key,
+                key,
+                key
+            );
+        }
+        self.asset_shards.insert(&position);
+        self.by_key.insert(key, position)
+    }
+
+    pub fn remove(&self, key: &PositionKey) -> Option<Position> {
+        self.short_ids
+            .remove_if(&key.short_id(), |_, stored_key| stored_key == key);
+        self.cooldowns.remove(key);
+        let (_, position) = self.by_key.remove(key)?;
+        self.asset_shards.remove(&position);
+        Some(position)
+    }
+
+    /// Keys of the positions whose collateral or debt is one of the given
+    /// assets, read from their shards.
+    pub fn keys_with_assets<'a>(
+        &self,
+        assets: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<PositionKey> {
+        let mut keys = HashSet::new();
+        for asset in assets {
+            self.asset_shards.keys_with_asset(asset, &mut keys);
+        }
+        keys.into_iter().collect()
+    }
+
+    /// Records a liquidation attempt of the position, starting its cooldown.
+    pub fn record_attempt(
+        &self,
+        key: &PositionKey,
+        now: u64,
+        config: &LiquidationCooldownConfig,
+    ) -> LiquidationCooldown {
+        let mut cooldown = self.cooldowns.entry(*key).or_insert(LiquidationCooldown {
+            attempts: 0,
+            until: now,
+        });
+        cooldown.attempts += 1;
+        cooldown.until = now + config.cooldown_seconds;
+        *cooldown
+    }
+
+    /// Returns if the position can't be liquidated yet, or anymore once it
+    /// reached `max_attempts`.
+    pub fn in_cooldown(
+        &self,
+        key: &PositionKey,
+        now: u64,
+        config: &LiquidationCooldownConfig,
+    ) -> bool {
+        self.cooldowns.get(key).is_some_and(|cooldown| {
+            now < cooldown.until
+                || (config.max_attempts > 0 && cooldown.attempts >= config.max_attempts)
+        })
+    }
+
+    /// Forgets the attempts of a position, e.g. healthy again.
+    pub fn clear_cooldown(&self, key: &PositionKey) {
+        self.cooldowns.remove(key);
+    }
+
+    /// Number of collateral assets the positions are sharded by.
+    pub fn shards(&self) -> usize {
+        self.asset_shards.by_collateral.len()
     }
 
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.by_key.len()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.by_key.is_empty()
     }
 }
 
@@ -67,68 +214,434 @@ impl Default for PositionsMap {
     }
 }
 
+/// Upper bounds of the buckets of the health factors histogram, the last
+/// bucket holding the higher ones.
+const HEALTH_FACTOR_BUCKETS: [&str; 6] = ["1", "1.05", "1.1", "1.25", "1.5", "2"];
+
+/// Health factors of the monitored positions at their last check, shared
+/// with the API.
+#[derive(Clone, Default)]
+pub struct HealthFactors(Arc<DashMap<PositionKey, BigDecimal>>);
+
+/// Number of positions with a health factor below `below` & above the bound
+/// of the previous bucket. `None` bounds the last bucket.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthFactorBucket {
+    pub below: Option<&'static str>,
+    pub positions: usize,
+}
+
+impl HealthFactors {
+    /// Caches the health factor of a position, forgotten if `None`.
+    pub fn record(&self, key: PositionKey, health_factor: Option<BigDecimal>) {
+        match health_factor {
+            Some(health_factor) => {
+                self.0.insert(key, health_factor);
+            }
+            None => {
+                self.0.remove(&key);
+            }
+        }
+    }
+
+    pub fn remove(&self, key: &PositionKey) {
+        self.0.remove(key);
+    }
+
+    /// Returns the positions within `margin` of a liquidation, i.e. with a
+    /// health factor below `1 + margin`.
+    pub fn watchlist(&self, margin: &BigDecimal) -> Vec<PositionKey> {
+        let threshold = BigDecimal::from(1) + margin;
+        self.0
+            .iter()
+            .filter(|entry| *entry.value() < threshold)
+            .map(|entry| *entry.key())
+            .collect()
+    }
+
+    /// Returns the histogram of the cached health factors.
+    pub fn histogram(&self) -> Vec<HealthFactorBucket> {
+        let bounds: Vec<BigDecimal> = HEALTH_FACTOR_BUCKETS
+            .iter()
+            .map(|bound| BigDecimal::from_str(bound).expect("valid bucket bound"))
+            .collect();
+        let mut counts = vec![0; bounds.len() + 1];
+        for entry in self.0.iter() {
+            let bucket = bounds
+                .iter()
+                .position(|bound| entry.value() < bound)
+                .unwrap_or(bounds.len());
+            counts[bucket] += 1;
+        }
+        counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, positions)| HealthFactorBucket {
+                below: HEALTH_FACTOR_BUCKETS.get(i).copied(),
+                positions,
+            })
+            .collect()
+    }
+}
+
+/// Identifies a position by its protocol, pool, assets & user. Stored as
+/// `pool:collateral:debt:user` for Vesu & prefixed with the name of the
+/// protocol for the others, e.g. `zklend:pool:collateral:debt:user`, and
+/// displayed with its short id.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct PositionKey {
+    pub pool_id: Felt,
+    pub collateral: Felt,
+    pub debt: Felt,
+    pub user: Felt,
+    pub protocol: ProtocolKind,
+}
+
+impl PositionKey {
+    /// Pedersen hash of the key, stable across builds & machines unlike the
+    /// std hashers. The protocol is only hashed for protocols other than Vesu,
+    /// keeping the ids of the Vesu positions.
+    pub fn hash(&self) -> Felt {
+        let elements = [self.pool_id, self.collateral, self.debt, self.user];
+        if self.protocol.is_vesu() {
+            return compute_hash_on_elements(&elements);
+        }
+        let protocol = cairo_short_string_to_felt(self.protocol.name())
+            .expect("protocol names are short strings");
+        compute_hash_on_elements(&[&[protocol][..], &elements].concat())
+    }
+
+    /// Lowest 64 bits of the hash of the key, used to identify the position
+    /// in the logs.
+    pub fn short_id(&self) -> u64 {
+        let bytes = self.hash().to_bytes_be();
+        u64::from_be_bytes(bytes[24..].try_into().expect("8 bytes"))
+    }
+}
+
+impl fmt::Display for PositionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.short_id())
+    }
+}
+
+impl fmt::Debug for PositionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.protocol.is_vesu() {
+            write!(f, "{}:", self.protocol)?;
+        }
+        write!(
+            f,
+            "{:#x}:{:#x}:{:#x}:{:#x}",
+            self.pool_id, self.collateral, self.debt, self.user
+        )
+    }
+}
+
+impl FromStr for PositionKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (protocol, key) = match s.split_once(':') {
+            Some((protocol, key)) if !protocol.starts_with("0x") => (protocol.parse()?, key),
+            _ => (ProtocolKind::Vesu, s),
+        };
+        let parts = key
+            .split(':')
+            .map(Felt::from_hex)
+            .collect::<Result<Vec<_>, _>>()?;
+        let [pool_id, collateral, debt, user] = parts.as_slice() else {
+            anyhow::bail!(
+                "Invalid position key {s}, expected [protocol:]pool:collateral:debt:user"
+            );
+        };
+        Ok(PositionKey {
+            pool_id: *pool_id,
+            collateral: *collateral,
+            debt: *debt,
+            user: *user,
+            protocol,
+        })
+    }
+}
+
+impl Serialize for PositionKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!("{self:?}"))
+    }
+}
+
+impl<'de> Deserialize<'de> for PositionKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let key = String::deserialize(deserializer)?;
+        key.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Pools & assets whose positions are tracked, see `filters` in the config.
+/// An empty allowlist accepts everything not in the denylist.
+#[derive(Clone, Debug, Default)]
+pub struct PositionFilter {
+    pub pools: HashSet<Felt>,
+    pub excluded_pools: HashSet<Felt>,
+    pub assets: HashSet<Felt>,
+    pub excluded_assets: HashSet<Felt>,
+    /// Minimum value in USD of the collateral of the new positions indexed.
+    pub min_value_usd: Option<BigDecimal>,
+}
+
+impl PositionFilter {
+    /// Returns if the position is in an accepted pool, with an accepted
+    /// collateral & debt.
+    pub fn accepts(&self, key: &PositionKey) -> bool {
+        let accepts_asset = |asset: &Felt| {
+            (self.assets.is_empty() || self.assets.contains(asset))
+                && !self.excluded_assets.contains(asset)
+        };
+        (self.pools.is_empty() || self.pools.contains(&key.pool_id))
+            && !self.excluded_pools.contains(&key.pool_id)
+            && accepts_asset(&key.collateral)
+            && accepts_asset(&key.debt)
+    }
+
+    /// Returns if a new position whose collateral is worth `value` (in USD)
+    /// is large enough to be tracked.
+    pub fn accepts_value(&self, value: &BigDecimal) -> bool {
+        self.min_value_usd.as_ref().is_none_or(|min| value >= min)
+    }
+}
+
 #[derive(Default, Clone, Hash, Eq, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Position {
+    /// Owner of the position.
     pub user_address: Felt,
     pub pool_id: Felt,
+    /// Name of the pool from the `pools` of the config, omitted if unnamed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_name: Option<String>,
     pub collateral: Asset,
     pub debt: Asset,
+    #[serde(serialize_with = "plain_decimal")]
     pub lltv: BigDecimal,
+    /// Lending protocol of the position, omitted for Vesu.
+    #[serde(default, skip_serializing_if = "ProtocolKind::is_vesu")]
+    pub protocol: ProtocolKind,
 }
 
-impl Position {
-    /// Create a new position from the event_keys of a ModifyPosition event.
-    pub fn from_event(config: &Config, event_keys: &[FieldElement]) -> Option<Position> {
-        let event_keys: Vec<Felt> = event_keys.iter().map(apibara_field_as_felt).collect();
-
-        let collateral = Asset::from_address(config, event_keys[2]);
-        let debt = Asset::from_address(config, event_keys[3]);
-        if collateral.is_none() || debt.is_none() {
-            tracing::info!("{event_keys:?}");
-            tracing::warn!("collat & debt is none :/");
-            return None;
+/// A position whose update kept failing when it was received from the indexer.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeadLetterPosition {
+    pub block_number: u64,
+    pub position: Position,
+    pub error: String,
+    /// Unix timestamp (in seconds) of the last failure.
+    pub failed_at: u64,
+}
+
+/// A liquidation that failed for a transient reason, e.g. an RPC failure or
+/// a fee spike, retried with a backoff until it succeeds, the position is
+/// healthy again, or it is abandoned.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FailedLiquidation {
+    pub key: PositionKey,
+    pub attempts: u32,
+    pub error: String,
+    /// Unix timestamp (in seconds) of the last failure.
+    pub failed_at: u64,
+    pub next_retry_at: u64,
+    pub status: FailedLiquidationStatus,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailedLiquidationStatus {
+    Retrying,
+    /// Failed `liquidation_retry.max_attempts` times, or with an error that
+    /// retrying can't fix: not retried anymore.
+    Abandoned,
+}
+
+impl FailedLiquidation {
+    /// Records a new failure of the liquidation of the position, scheduling
+    /// its next retry or abandoning it.
+    pub fn record_failure(
+        previous: Option<&FailedLiquidation>,
+        key: PositionKey,
+        error: String,
+        now: u64,
+        config: &LiquidationRetryConfig,
+    ) -> FailedLiquidation {
+        let attempts = previous.map_or(0, |failed| failed.attempts) + 1;
+        let backoff = config
+            .initial_backoff_seconds
+            .saturating_mul(2_u64.saturating_pow(attempts - 1))
+            .min(config.max_backoff_seconds);
+        FailedLiquidation {
+            key,
+            attempts,
+            error,
+            failed_at: now,
+            next_retry_at: now + backoff,
+            status: if attempts >= config.max_attempts {
+                FailedLiquidationStatus::Abandoned
+            } else {
+                FailedLiquidationStatus::Retrying
+            },
         }
+    }
 
-        let position = Position {
-            pool_id: event_keys[1],
-            collateral: collateral.unwrap(),
-            debt: debt.unwrap(),
-            user_address: event_keys[4],
-            lltv: BigDecimal::default(),
-        };
-        Some(position)
+    /// Returns if the liquidation can be retried.
+    pub fn is_due(&self, now: u64) -> bool {
+        self.status == FailedLiquidationStatus::Retrying && now >= self.next_retry_at
     }
+}
+
+/// Closed positions are kept for a while, in case they are reopened, then
+/// pruned from the storage.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PruningState {
+    /// Block at which each closed position was first seen closed.
+    #[serde(serialize_with = "sorted_map")]
+    pub closed_since: HashMap<PositionKey, u64>,
+    /// Positions pruned since the first run.
+    pub positions_pruned: u64,
+    pub last_pruned_block: u64,
+}
 
+impl PruningState {
+    /// Returns the positions closed for more than `closed_after_blocks` blocks
+    /// at `block`, which are forgotten.
+    pub fn take_prunable(&mut self, block: u64, closed_after_blocks: u64) -> Vec<PositionKey> {
+        let mut prunable: Vec<PositionKey> = self
+            .closed_since
+            .iter()
+            .filter(|(_, closed_since)| block.saturating_sub(**closed_since) > closed_after_blocks)
+            .map(|(key, _)| *key)
+            .collect();
+        prunable.sort();
+        for key in prunable.iter() {
+            self.closed_since.remove(key);
+        }
+        self.positions_pruned += prunable.len() as u64;
+        self.last_pruned_block = block;
+        prunable
+    }
+}
+
+/// A liquidable position, ordered by expected profit then by how far its LTV
+/// is above the liquidation threshold: when many positions become liquidable
+/// at once, the most valuable ones are liquidated first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiquidationCandidate {
+    pub key: PositionKey,
+    /// Value of the debt in USD, the liquidation bonus being a share of it.
+    pub expected_profit: BigDecimal,
+    /// LTV minus LLTV of the position.
+    pub ltv_excess: BigDecimal,
+}
+
+impl Ord for LiquidationCandidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.expected_profit
+            .cmp(&other.expected_profit)
+            .then_with(|| self.ltv_excess.cmp(&other.ltv_excess))
+            .then_with(|| other.key.cmp(&self.key))
+    }
+}
+
+impl PartialOrd for LiquidationCandidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Position {
     /// Computes & returns the LTV Ratio for a position.
     pub async fn ltv(&self, oracle_prices: &LatestOraclePrices) -> Result<BigDecimal> {
         let collateral_name = self.collateral.name.to_lowercase();
         let debt_name = self.debt.name.to_lowercase();
 
-        let collateral_price = oracle_prices
-            .0
-            .get(&collateral_name)
-            .ok_or_else(|| anyhow!("Price not found for collateral: {}", collateral_name))?
-            .clone();
-
-        let debt_price = oracle_prices
-            .0
-            .get(&debt_name)
-            .ok_or_else(|| anyhow!("Price not found for debt: {}", debt_name))?
-            .clone();
-
-        anyhow::ensure!(
-            (collateral_price > BigDecimal::from(0)) && (debt_price > BigDecimal::from(0)),
-            "Oracle prices are zero. Can't compute LTV."
-        );
-        anyhow::ensure!(
-            (self.collateral.amount > BigDecimal::from(0)),
-            "Colateral amount is zero. Can't compute LTV."
-        );
+        let collateral_price = oracle_prices.fresh_price(&collateral_name)?;
+        let debt_price = oracle_prices.fresh_price(&debt_name)?;
+
+        if collateral_price <= BigDecimal::from(0) || debt_price <= BigDecimal::from(0) {
+            return Err(LiquidatorError::Math(
+                "Oracle prices are zero. Can't compute LTV.".to_string(),
+            )
+            .into());
+        }
+        if self.collateral.amount <= BigDecimal::from(0) {
+            return Err(LiquidatorError::Math(
+                "Colateral amount is zero. Can't compute LTV.".to_string(),
+            )
+            .into());
+        }
 
         let ltv = (&self.debt.amount * debt_price) / (&self.collateral.amount * collateral_price);
         Ok(ltv)
     }
 
+    /// Returns the health factor of the position, its LLTV over its LTV: it
+    /// can be liquidated below 1. `None` if the position has no debt.
+    pub async fn health_factor(
+        &self,
+        oracle_prices: &LatestOraclePrices,
+    ) -> Result<Option<BigDecimal>> {
+        let ltv = self.ltv(oracle_prices).await?;
+        if ltv == BigDecimal::from(0) {
+            return Ok(None);
+        }
+        Ok(Some(&self.lltv / ltv))
+    }
+
+    /// Returns the value in USD of the collateral of the position.
+    pub fn collateral_value(&self, oracle_prices: &LatestOraclePrices) -> Result<BigDecimal> {
+        let collateral_price = oracle_prices.fresh_price(&self.collateral.name.to_lowercase())?;
+        Ok(&self.collateral.amount * collateral_price)
+    }
+
+    /// Returns the value in USD of the debt of the position.
+    pub fn debt_value(&self, oracle_prices: &LatestOraclePrices) -> Result<BigDecimal> {
+        let debt_price = oracle_prices.fresh_price(&self.debt.name.to_lowercase())?;
+        Ok(&self.debt.amount * debt_price)
+    }
+
+    /// Returns the value in USD of the collateral missing for the health
+    /// factor of the position to reach `target`, 0 if it is already above.
+    pub fn collateral_shortfall(
+        &self,
+        oracle_prices: &LatestOraclePrices,
+        health_factor: &BigDecimal,
+        target: &BigDecimal,
+    ) -> Result<BigDecimal> {
+        if health_factor >= target {
+            return Ok(BigDecimal::from(0));
+        }
+        if *health_factor <= BigDecimal::from(0) {
+            return Err(LiquidatorError::Math(
+                "Health factor is zero. Can't compute the shortfall.".to_string(),
+            )
+            .into());
+        }
+        let collateral_price = oracle_prices.fresh_price(&self.collateral.name.to_lowercase())?;
+        // The collateral value scales the health factor linearly.
+        Ok(&self.collateral.amount * collateral_price * (target - health_factor) / health_factor)
+    }
+
+    /// Returns the position as a candidate to the liquidation.
+    pub async fn as_liquidation_candidate(
+        &self,
+        oracle_prices: &LatestOraclePrices,
+    ) -> Result<LiquidationCandidate> {
+        let ltv = self.ltv(oracle_prices).await?;
+        let debt_price = oracle_prices.fresh_price(&self.debt.name.to_lowercase())?;
+        Ok(LiquidationCandidate {
+            key: self.key(),
+            expected_profit: &self.debt.amount * debt_price,
+            ltv_excess: ltv - &self.lltv,
+        })
+    }
+
     /// Check if a position is closed.
     pub fn is_closed(&self) -> bool {
         (self.collateral.amount == 0.into()) && (self.debt.amount == 0.into())
@@ -140,9 +653,13 @@ impl Position {
             return Ok(false);
         }
 
+        // Refuses to act on missing or stale prices.
         let ltv_ratio = match self.ltv(oracle_prices).await {
             Result::Ok(ltv) => ltv,
-            Result::Err(_) => return Ok(false),
+            Result::Err(e) => {
+                tracing::debug!("{} is not checked: {}", self, e);
+                return Ok(false);
+            }
         };
 
         let is_liquidable = ltv_ratio >= self.lltv.clone();
@@ -157,6 +674,18 @@ impl Position {
         Ok(is_liquidable)
     }
 
+    /// Returns if the position is liquidable or almost, false if a price is
+    /// missing or stale.
+    pub async fn is_near_liquidation(&self, oracle_prices: &LatestOraclePrices) -> bool {
+        if self.lltv == BigDecimal::default() {
+            return false;
+        }
+        let threshold = &self.lltv - BigDecimal::from_f64(ALMOST_LIQUIDABLE_THRESHOLD).unwrap();
+        self.ltv(oracle_prices)
+            .await
+            .is_ok_and(|ltv_ratio| ltv_ratio > threshold)
+    }
+
     fn logs_liquidation_state(&self, is_liquidable: bool, ltv_ratio: BigDecimal) {
         tracing::info!(
             "{} is at ratio {:.2}%/{:.2}% => {}",
@@ -189,32 +718,38 @@ impl Position {
         let ltv_config = rpc_client
             .call(
                 liquidation_config_request,
-                BlockId::Tag(BlockTag::PreConfirmed),
+                config.monitoring.positions_block_id(),
             )
             .await
             .expect("failed to retrieve");
         BigDecimal::new(ltv_config[0].to_bigint(), VESU_RESPONSE_DECIMALS)
     }
 
+    /// Refreshes the amounts & the LLTV of the position from its protocol,
+    /// retrying with an exponential backoff. Fails after `retry.max_attempts` attempts.
     pub async fn update(
         &mut self,
-        rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
-        singleton_address: &Felt,
+        rpc: &dyn StarknetRpc,
+        protocols: &Protocols,
+        retry: &PositionUpdateConfig,
     ) -> anyhow::Result<()> {
-        const RETRY_DELAY: Duration = Duration::from_secs(2);
+        let mut backoff = Duration::from_millis(retry.initial_backoff_ms);
         let mut attempt = 1;
 
         loop {
-            match self.try_update(rpc_client, singleton_address).await {
+            match self.try_update(rpc, protocols).await {
                 Ok(_) => return Ok(()),
+                Err(e) if attempt >= retry.max_attempts || !is_retryable(&e) => return Err(e),
                 Err(e) => {
-                    tracing::error!(
-                        "[🔭 Monitoring] Position 0x#{:x} update failed (attempt {}), likely due to RPC error: {}",
+                    tracing::warn!(
+                        "[🔭 Monitoring] Position #{} update failed (attempt {}/{}), likely due to RPC error: {}",
                         self.key(),
                         attempt,
+                        retry.max_attempts,
                         e
                     );
-                    tokio::time::sleep(RETRY_DELAY).await;
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(Duration::from_millis(retry.max_backoff_ms));
                     attempt += 1;
                 }
             }
@@ -223,108 +758,128 @@ impl Position {
 
     async fn try_update(
         &mut self,
-        rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
-        singleton_address: &Felt,
-    ) -> anyhow::Result<()> {
-        self.update_amounts(rpc_client, singleton_address).await?;
-        self.update_lltv(rpc_client, singleton_address).await?;
-        Ok(())
-    }
-
-    async fn update_amounts(
-        &mut self,
-        rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
-        singleton_address: &Felt,
+        rpc: &dyn StarknetRpc,
+        protocols: &Protocols,
     ) -> anyhow::Result<()> {
-        let get_position_request = &FunctionCall {
-            contract_address: *singleton_address,
-            entry_point_selector: *VESU_POSITION_UNSAFE_SELECTOR,
-            calldata: self.as_update_calldata(),
-        };
-        let result = rpc_client
-            .call(get_position_request, BlockId::Tag(BlockTag::PreConfirmed))
-            .await?;
-
-        let new_collateral = BigDecimal::new(result[4].to_bigint(), self.collateral.decimals);
-        let new_debt = BigDecimal::new(result[6].to_bigint(), self.debt.decimals);
-        self.collateral.amount = new_collateral;
-        self.debt.amount = new_debt;
-        Ok(())
+        let protocol = protocols.get(self.protocol)?;
+        let mut results = vec![];
+        for request in protocol.update_requests(self)? {
+            results.push(rpc.call(request).await?);
+        }
+        protocol.apply_update(self, &results)
     }
 
-    async fn update_lltv(
-        &mut self,
-        rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
-        singleton_address: &Felt,
+    /// Refreshes the amounts & the LLTVs of many positions, packing the calls
+    /// of `batch_size` positions in a single batch of RPC requests.
+    /// Fails on the first batch that can't be fetched or parsed; the positions
+    /// of the previous batches are refreshed.
+    pub async fn update_batch(
+        positions: &mut [Position],
+        rpc: &dyn StarknetRpc,
+        protocols: &Protocols,
+        batch_size: usize,
     ) -> anyhow::Result<()> {
-        let ltv_config_request = &FunctionCall {
-            contract_address: *singleton_address,
-            entry_point_selector: *VESU_LTV_CONFIG_SELECTOR,
-            calldata: self.as_ltv_calldata(),
-        };
-
-        let ltv_config = rpc_client
-            .call(ltv_config_request, BlockId::Tag(BlockTag::PreConfirmed))
-            .await?;
+        for batch in positions.chunks_mut(batch_size.max(1)) {
+            let mut requests_per_position = Vec::with_capacity(batch.len());
+            let mut requests = vec![];
+            for position in batch.iter() {
+                let position_requests = protocols
+                    .get(position.protocol)?
+                    .update_requests(position)?;
+                requests_per_position.push(position_requests.len());
+                requests.extend(position_requests);
+            }
+            let responses = rpc.batch_call(requests).await?;
 
-        self.lltv = BigDecimal::new(ltv_config[0].to_bigint(), VESU_RESPONSE_DECIMALS);
+            let mut responses = responses.into_iter();
+            for (position, count) in batch.iter_mut().zip(requests_per_position) {
+                let results: Vec<Vec<Felt>> = responses.by_ref().take(count).collect();
+                protocols
+                    .get(position.protocol)?
+                    .apply_update(position, &results)?;
+            }
+        }
         Ok(())
     }
 
-    /// Returns a unique identifier for the position by hashing the update calldata.
-    pub fn key(&self) -> u64 {
-        let mut hasher = std::hash::DefaultHasher::new();
-        self.as_update_calldata().hash(&mut hasher);
-        hasher.finish()
+    /// Returns the unique identifier of the position.
+    pub fn key(&self) -> PositionKey {
+        PositionKey {
+            pool_id: self.pool_id,
+            collateral: self.collateral.address,
+            debt: self.debt.address,
+            user: self.user_address,
+            protocol: self.protocol,
+        }
     }
 
-    /// Returns the TX necessary to liquidate this position using the Vesu Liquidate
-    /// contract.
-    pub async fn get_vesu_liquidate_tx(
-        &self,
-        liquidate_contract: &Arc<Liquidate<StarknetSingleOwnerAccount>>,
-        http_client: &reqwest::Client,
-        liquidator_address: &Felt,
-    ) -> Result<Call> {
-        let (liquidate_swap, liquidate_swap_weights) = get_ekubo_route(
-            http_client,
-            self.debt.address,
-            self.collateral.address,
-            &self.debt.amount,
-        )
-        .await?;
-
-        let liquidate_params = LiquidateParams {
-            pool_id: self.pool_id,
-            collateral_asset: cainome::cairo_serde::ContractAddress(self.collateral.address),
-            debt_asset: cainome::cairo_serde::ContractAddress(self.debt.address),
-            user: cainome::cairo_serde::ContractAddress(self.user_address),
-            recipient: cainome::cairo_serde::ContractAddress(*liquidator_address),
-            min_collateral_to_receive: U256_ZERO,
-            debt_to_repay: U256_ZERO,
-            liquidate_swap,
-            liquidate_swap_weights,
-            liquidate_swap_limit_amount: u128::MAX,
-            withdraw_swap: vec![],
-            withdraw_swap_limit_amount: 0,
-            withdraw_swap_weights: vec![],
-        };
-        Ok(liquidate_contract.liquidate_getcall(&liquidate_params))
+    /// Returns the amounts of debt to try to repay, from the largest: the
+    /// whole debt, then in partial mode the debt halved a few times, for when
+    /// the pools can't absorb the full liquidation. The position stays
+    /// liquidable after a partial one & is liquidated again the next rounds.
+    pub fn liquidation_amounts(&self, liquidation_mode: LiquidationMode) -> Vec<BigDecimal> {
+        let mut amounts = vec![self.debt.amount.clone()];
+        if liquidation_mode == LiquidationMode::Partial {
+            let mut amount = self.debt.amount.clone();
+            for _ in 0..MAX_PARTIAL_LIQUIDATION_STEPS {
+                amount = (amount / BigDecimal::from(2))
+                    .with_scale_round(self.debt.decimals, bigdecimal::RoundingMode::Down);
+                if amount == BigDecimal::from(0) {
+                    break;
+                }
+                amounts.push(amount.clone());
+            }
+        }
+        amounts
     }
 
-    /// Returns the position as a calldata for the LTV config RPC call.
-    fn as_ltv_calldata(&self) -> Vec<Felt> {
-        vec![self.pool_id, self.collateral.address, self.debt.address]
+    /// Returns the amounts of debt of the liquidation split in 2 to
+    /// `max_tranches` equal tranches, from the largest, for when selling the
+    /// collateral of the whole liquidation would move the price too much.
+    pub fn tranche_amounts(&self, max_tranches: usize) -> Vec<BigDecimal> {
+        (2..=max_tranches)
+            .map(|tranches| {
+                (&self.debt.amount / BigDecimal::from(tranches as u64))
+                    .with_scale_round(self.debt.decimals, bigdecimal::RoundingMode::Down)
+            })
+            .filter(|amount| *amount > BigDecimal::from(0))
+            .collect()
     }
 
-    /// Returns the position as a calldata for the Update Position RPC call.
-    fn as_update_calldata(&self) -> Vec<Felt> {
-        vec![
-            self.pool_id,
-            self.collateral.address,
+    /// Wraps the liquidation call in a flash loan of `debt_amount` from the
+    /// Vesu singleton. The receiver contract is lent the debt, runs the call
+    /// passed as data - `[to, selector, calldata_len, ...calldata]` - &
+    /// repays the loan from the seized collateral.
+    pub fn wrap_in_vesu_flash_loan(
+        &self,
+        liquidation_call: Call,
+        singleton_address: Felt,
+        receiver_address: Felt,
+        debt_amount: &BigDecimal,
+    ) -> Call {
+        let debt_amount = big_decimal_to_u256(debt_amount.with_scale(self.debt.decimals));
+        let mut data = vec![
+            liquidation_call.to,
+            liquidation_call.selector,
+            Felt::from(liquidation_call.calldata.len()),
+        ];
+        data.extend(liquidation_call.calldata);
+
+        // receiver, asset, amount (low, high), is_legacy, data
+        let mut calldata = vec![
+            receiver_address,
             self.debt.address,
-            self.user_address,
-        ]
+            debt_amount.low.into(),
+            debt_amount.high.into(),
+            Felt::ZERO,
+            Felt::from(data.len()),
+        ];
+        calldata.extend(data);
+        Call {
+            to: singleton_address,
+            selector: *FLASH_LOAN_SELECTOR,
+            calldata,
+        }
     }
 }
 
@@ -332,12 +887,302 @@ impl fmt::Display for Position {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "Position {} with {} {} of collateral and {} {} of debt",
+            "Position #{} with {} {} of collateral and {} {} of debt",
             self.key(),
-            self.collateral.amount.round(2),
+            self.collateral.amount.round(2).to_plain_string(),
             self.collateral.name,
-            self.debt.amount.round(2),
+            self.debt.amount.round(2).to_plain_string(),
             self.debt.name,
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BinaryHeap, str::FromStr};
+
+    use bigdecimal::BigDecimal;
+    use starknet::core::types::Felt;
+
+    use starknet::core::types::Call;
+
+    use super::{
+        FailedLiquidation, FailedLiquidationStatus, HealthFactorBucket, HealthFactors,
+        LiquidationCandidate, Position, PositionFilter, PositionKey, PositionsMap, PruningState,
+    };
+    use crate::{
+        config::{
+            FLASH_LOAN_SELECTOR, LiquidationCooldownConfig, LiquidationMode, LiquidationRetryConfig,
+        },
+        types::{
+            asset::Asset,
+            fixtures::{key, position},
+        },
+    };
+
+    fn candidate(user: u64, expected_profit: &str, ltv_excess: &str) -> LiquidationCandidate {
+        LiquidationCandidate {
+            key: key(user),
+            expected_profit: BigDecimal::from_str(expected_profit).unwrap(),
+            ltv_excess: BigDecimal::from_str(ltv_excess).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_liquidation_candidates_priority() {
+        let mut candidates = BinaryHeap::from([
+            candidate(1, "100", "0.01"),
+            candidate(2, "5000", "0.001"),
+            candidate(3, "100", "0.2"),
+            candidate(4, "100", "0.2"),
+            candidate(5, "20.5", "0.5"),
+        ]);
+
+        let mut order = vec![];
+        while let Some(candidate) = candidates.pop() {
+            order.push(candidate.key.user);
+        }
+        let expected: Vec<Felt> = [2_u64, 3, 4, 1, 5].into_iter().map(Felt::from).collect();
+        assert_eq!(order, expected);
+    }
+
+    #[test]
+    fn test_position_key_round_trip() {
+        let key = key(0xabc);
+        let stored = serde_json::to_string(&key).unwrap();
+        assert_eq!(stored, "\"0x1:0x10:0x20:0xabc\"");
+        assert_eq!(serde_json::from_str::<PositionKey>(&stored).unwrap(), key);
+
+        assert!("0x1:0x10:0x20".parse::<PositionKey>().is_err());
+        assert!("12345".parse::<PositionKey>().is_err());
+        assert!("aave:0x1:0x10:0x20:0xabc".parse::<PositionKey>().is_err());
+    }
+
+    #[test]
+    fn test_position_key_short_id_is_stable() {
+        assert_eq!(key(0xabc).short_id(), key(0xabc).short_id());
+        assert_ne!(key(0xabc).short_id(), key(0xabd).short_id());
+        assert_eq!(key(0xabc).to_string().len(), 16);
+    }
+
+    #[test]
+    fn test_health_factors_watchlist_and_histogram() {
+        let health_factors = HealthFactors::default();
+        health_factors.record(key(1), Some(BigDecimal::from_str("0.98").unwrap()));
+        health_factors.record(key(2), Some(BigDecimal::from_str("1.04").unwrap()));
+        health_factors.record(key(3), Some(BigDecimal::from_str("3").unwrap()));
+        health_factors.record(key(4), Some(BigDecimal::from_str("1.2").unwrap()));
+        health_factors.record(key(4), None);
+
+        let mut watchlist = health_factors.watchlist(&BigDecimal::from_str("0.05").unwrap());
+        watchlist.sort();
+        assert_eq!(watchlist, vec![key(1), key(2)]);
+
+        let histogram = health_factors.histogram();
+        assert_eq!(histogram.len(), 7);
+        assert_eq!(
+            histogram[0],
+            HealthFactorBucket {
+                below: Some("1"),
+                positions: 1
+            }
+        );
+        assert_eq!(histogram[1].positions, 1);
+        assert_eq!(
+            histogram[6],
+            HealthFactorBucket {
+                below: None,
+                positions: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_failed_liquidation_backoff() {
+        let config = LiquidationRetryConfig {
+            max_attempts: 3,
+            initial_backoff_seconds: 10,
+            max_backoff_seconds: 15,
+        };
+        let first = FailedLiquidation::record_failure(None, key(1), "rpc".into(), 100, &config);
+        assert_eq!(first.attempts, 1);
+        assert_eq!(first.next_retry_at, 110);
+        assert!(!first.is_due(109));
+        assert!(first.is_due(110));
+
+        let second =
+            FailedLiquidation::record_failure(Some(&first), key(1), "fee".into(), 110, &config);
+        assert_eq!(second.next_retry_at, 125);
+        assert_eq!(second.status, FailedLiquidationStatus::Retrying);
+
+        let third =
+            FailedLiquidation::record_failure(Some(&second), key(1), "fee".into(), 125, &config);
+        assert_eq!(third.status, FailedLiquidationStatus::Abandoned);
+        assert!(!third.is_due(u64::MAX));
+    }
+
+    #[test]
+    fn test_position_filter() {
+        let mut filter = PositionFilter::default();
+        assert!(filter.accepts(&key(1)));
+
+        filter.pools.insert(Felt::TWO);
+        assert!(!filter.accepts(&key(1)));
+        filter.pools.insert(Felt::ONE);
+        assert!(filter.accepts(&key(1)));
+
+        filter.excluded_assets.insert(Felt::from(0x20_u64));
+        assert!(!filter.accepts(&key(1)));
+        filter.excluded_assets.clear();
+        filter.assets.insert(Felt::from(0x10_u64));
+        assert!(!filter.accepts(&key(1)));
+        filter.assets.insert(Felt::from(0x20_u64));
+        assert!(filter.accepts(&key(1)));
+
+        filter.excluded_pools.insert(Felt::ONE);
+        assert!(!filter.accepts(&key(1)));
+
+        assert!(filter.accepts_value(&BigDecimal::from(0)));
+        filter.min_value_usd = Some(BigDecimal::from(10_000));
+        assert!(!filter.accepts_value(&BigDecimal::from(9_999)));
+        assert!(filter.accepts_value(&BigDecimal::from(10_000)));
+    }
+
+    #[test]
+    fn test_pruning_takes_positions_closed_long_enough() {
+        let mut pruning = PruningState::default();
+        pruning.closed_since.insert(key(1), 100);
+        pruning.closed_since.insert(key(2), 150);
+        pruning.closed_since.insert(key(3), 90);
+
+        assert_eq!(pruning.take_prunable(150, 50), vec![key(3)]);
+        assert_eq!(pruning.take_prunable(201, 50), vec![key(1)]);
+        assert_eq!(pruning.positions_pruned, 2);
+        assert_eq!(pruning.last_pruned_block, 201);
+        assert_eq!(pruning.closed_since.len(), 1);
+    }
+
+    #[test]
+    fn test_positions_sharded_by_asset() {
+        let positions = PositionsMap::new();
+        let eth_usdc = position(1, "1");
+        let strk_usdc = Position {
+            user_address: Felt::TWO,
+            collateral: Asset::new("STRK".to_string(), Felt::from(0x30_u64), 18),
+            ..position("1")
+        };
+        positions.insert(eth_usdc.clone());
+        positions.insert(strk_usdc.clone());
+        assert_eq!(positions.shards(), 2);
+
+        assert_eq!(positions.keys_with_assets(["eth"]), vec![eth_usdc.key()]);
+        let mut keys = positions.keys_with_assets(["usdc", "strk"]);
+        keys.sort();
+        let mut expected = vec![eth_usdc.key(), strk_usdc.key()];
+        expected.sort();
+        assert_eq!(keys, expected);
+        assert!(positions.keys_with_assets(["wbtc"]).is_empty());
+
+        // The shard of a removed position is dropped once empty.
+        positions.remove(&strk_usdc.key());
+        assert_eq!(positions.shards(), 1);
+        assert!(positions.keys_with_assets(["strk"]).is_empty());
+        assert_eq!(positions.keys_with_assets(["usdc"]), vec![eth_usdc.key()]);
+    }
+
+    #[test]
+    fn test_liquidation_cooldown() {
+        let positions = PositionsMap::new();
+        let config = LiquidationCooldownConfig {
+            cooldown_seconds: 60,
+            max_attempts: 2,
+        };
+        assert!(!positions.in_cooldown(&key(1), 100, &config));
+
+        let first = positions.record_attempt(&key(1), 100, &config);
+        assert_eq!((first.attempts, first.until), (1, 160));
+        assert!(positions.in_cooldown(&key(1), 159, &config));
+        assert!(!positions.in_cooldown(&key(1), 160, &config));
+        assert!(!positions.in_cooldown(&key(2), 100, &config));
+
+        // Left alone after `max_attempts`, until healthy again.
+        positions.record_attempt(&key(1), 160, &config);
+        assert!(positions.in_cooldown(&key(1), u64::MAX, &config));
+        positions.clear_cooldown(&key(1));
+        assert!(!positions.in_cooldown(&key(1), 160, &config));
+
+        // Without a limit, only the delay applies.
+        let unlimited = LiquidationCooldownConfig {
+            max_attempts: 0,
+            ..config
+        };
+        for now in [200, 260, 320] {
+            positions.record_attempt(&key(1), now, &unlimited);
+        }
+        assert!(!positions.in_cooldown(&key(1), 380, &unlimited));
+    }
+
+    #[test]
+    fn test_liquidation_amounts() {
+        let position = position(1, "1.5");
+        assert_eq!(
+            position.liquidation_amounts(LiquidationMode::Full),
+            vec![BigDecimal::from_str("1.5").unwrap()]
+        );
+        let amounts: Vec<String> = position
+            .liquidation_amounts(LiquidationMode::Partial)
+            .iter()
+            .map(|amount| amount.to_string())
+            .collect();
+        assert_eq!(
+            amounts,
+            ["1.5", "0.750000", "0.375000", "0.187500", "0.093750"]
+        );
+
+        // Stops at the smallest unit of the debt token.
+        assert_eq!(
+            position(1, "0.000002")
+                .liquidation_amounts(LiquidationMode::Partial)
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_tranche_amounts() {
+        let amounts: Vec<String> = position(1, "1.5")
+            .tranche_amounts(4)
+            .iter()
+            .map(|amount| amount.to_string())
+            .collect();
+        assert_eq!(amounts, ["0.750000", "0.500000", "0.375000"]);
+        assert!(position(1, "1.5").tranche_amounts(1).is_empty());
+        // Tranches smaller than the smallest unit of the debt token are dropped.
+        assert_eq!(position(1, "0.000002").tranche_amounts(3).len(), 1);
+    }
+
+    #[test]
+    fn test_wrap_in_vesu_flash_loan() {
+        let position = position(1, "1.5");
+        let liquidation_call = Call {
+            to: Felt::from(0x30_u64),
+            selector: Felt::from(0x40_u64),
+            calldata: vec![Felt::from(7_u64), Felt::from(8_u64)],
+        };
+
+        let call = position.wrap_in_vesu_flash_loan(
+            liquidation_call,
+            Felt::from(0x50_u64),
+            Felt::from(0x60_u64),
+            &position.debt.amount,
+        );
+        assert_eq!(call.to, Felt::from(0x50_u64));
+        assert_eq!(call.selector, *FLASH_LOAN_SELECTOR);
+        assert_eq!(
+            call.calldata,
+            [0x60_u64, 0x20, 1_500_000, 0, 0, 5, 0x30, 0x40, 2, 7, 8]
+                .map(Felt::from)
+                .to_vec()
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use starknet::core::{
+    crypto::Signature,
+    types::{Call, Felt},
+    utils::get_selector_from_name,
+};
+
+use crate::utils::unix_now;
+
+/// `session-token` as a short string, prefixing the signatures of a session.
+pub const SESSION_MAGIC: Felt = Felt::from_hex_unchecked("0x73657373696f6e2d746f6b656e");
+
+/// Call the account accepts from a session key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionPolicy {
+    pub contract_address: Felt,
+    /// Name of the entrypoint, e.g. `liquidate` or `transfer`.
+    pub entrypoint: String,
+}
+
+/// Session authorized offline by the owner of the account: its key may only
+/// execute the calls of its policies until it expires, so the owner key never
+/// sits with the bot. Written by the wallet tooling of the owner as json.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    /// Public key of the session key.
+    pub public_key: Felt,
+    pub expires_at: u64,
+    pub policies: Vec<SessionPolicy>,
+    /// Signature of the owner over the session, checked by the account.
+    pub authorization: Vec<Felt>,
+}
+
+impl Session {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Could not read the session {}: {e}", path.display()))?;
+        let session: Session = serde_json::from_str(&content)
+            .map_err(|e| anyhow!("Invalid session {}: {e}", path.display()))?;
+        session.selectors()?;
+        Ok(session)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= unix_now()
+    }
+
+    /// Fails on the first call the policies don't allow, before the account
+    /// rejects it onchain.
+    pub fn check_calls(&self, calls: &[Call]) -> Result<()> {
+        anyhow::ensure!(
+            !self.is_expired(),
+            "The session expired at {}",
+            self.expires_at
+        );
+        let allowed = self.selectors()?;
+        for call in calls {
+            anyhow::ensure!(
+                allowed.contains(&(call.to, call.selector)),
+                "The session doesn't allow calling selector {:#x} of {:#x}",
+                call.selector,
+                call.to
+            );
+        }
+        Ok(())
+    }
+
+    /// Signature of a transaction by the session key:
+    /// `[SESSION_MAGIC, expires_at, public_key, policies_len, (contract, selector)...,
+    /// authorization_len, authorization..., r, s]`.
+    pub fn signature(&self, signature: Signature) -> Result<Vec<Felt>> {
+        let selectors = self.selectors()?;
+        let mut felts = vec![
+            SESSION_MAGIC,
+            Felt::from(self.expires_at),
+            self.public_key,
+            Felt::from(selectors.len()),
+        ];
+        for (contract, selector) in selectors {
+            felts.extend([contract, selector]);
+        }
+        felts.push(Felt::from(self.authorization.len()));
+        felts.extend(self.authorization.iter().copied());
+        felts.extend([signature.r, signature.s]);
+        Ok(felts)
+    }
+
+    fn selectors(&self) -> Result<Vec<(Felt, Felt)>> {
+        self.policies
+            .iter()
+            .map(|policy| {
+                let selector = get_selector_from_name(&policy.entrypoint).map_err(|e| {
+                    anyhow!("Invalid entrypoint {} in session: {e}", policy.entrypoint)
+                })?;
+                Ok((policy.contract_address, selector))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::{
+        crypto::Signature,
+        types::{Call, Felt},
+        utils::get_selector_from_name,
+    };
+
+    use super::{SESSION_MAGIC, Session, SessionPolicy};
+
+    fn session(expires_at: u64) -> Session {
+        Session {
+            public_key: Felt::from(7),
+            expires_at,
+            policies: vec![SessionPolicy {
+                contract_address: Felt::ONE,
+                entrypoint: "liquidate".to_string(),
+            }],
+            authorization: vec![Felt::from(8), Felt::from(9)],
+        }
+    }
+
+    #[test]
+    fn test_check_calls() {
+        let active = session(u64::MAX);
+        let liquidate = Call {
+            to: Felt::ONE,
+            selector: get_selector_from_name("liquidate").unwrap(),
+            calldata: vec![],
+        };
+        assert!(active.check_calls(std::slice::from_ref(&liquidate)).is_ok());
+
+        let other_contract = Call {
+            to: Felt::TWO,
+            ..liquidate.clone()
+        };
+        assert!(
+            active
+                .check_calls(&[liquidate.clone(), other_contract])
+                .is_err()
+        );
+
+        assert!(session(0).check_calls(&[liquidate]).is_err());
+    }
+
+    #[test]
+    fn test_signature() {
+        let signature = session(100)
+            .signature(Signature {
+                r: Felt::from(10),
+                s: Felt::from(11),
+            })
+            .unwrap();
+        assert_eq!(
+            signature,
+            vec![
+                SESSION_MAGIC,
+                Felt::from(100),
+                Felt::from(7),
+                Felt::ONE,
+                Felt::ONE,
+                get_selector_from_name("liquidate").unwrap(),
+                Felt::TWO,
+                Felt::from(8),
+                Felt::from(9),
+                Felt::from(10),
+                Felt::from(11),
+            ]
+        );
+    }
+}
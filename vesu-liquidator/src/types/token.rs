@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+/// Symbol & decimals of an ERC20 token, as read from its contract.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    pub symbol: String,
+    pub decimals: i64,
+}
@@ -0,0 +1,178 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use cainome::cairo_serde::U256;
+use starknet::{
+    core::types::{BlockId, BlockTag, Call, Felt, FunctionCall},
+    providers::{JsonRpcClient, Provider, jsonrpc::HttpTransport},
+};
+
+use crate::{
+    config::{ALLOWANCE_SELECTOR, APPROVE_SELECTOR, Config},
+    utils::{constants::U256_ZERO, conversions::u256_to_big_uint},
+};
+
+/// Max amount of an ERC20 allowance.
+const UNLIMITED_ALLOWANCE: U256 = U256 {
+    low: u128::MAX,
+    high: u128::MAX,
+};
+
+/// Keeps the allowances of the bot to the Liquidate contract on the debt
+/// tokens high enough for the liquidations, see [`crate::config::AllowancesConfig`].
+#[derive(Clone)]
+pub struct AllowanceManager {
+    config: Config,
+    rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+    owner: Felt,
+    spender: Felt,
+}
+
+impl AllowanceManager {
+    pub fn new(
+        config: Config,
+        rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+        owner: Felt,
+        spender: Felt,
+    ) -> Self {
+        Self {
+            config,
+            rpc_client,
+            owner,
+            spender,
+        }
+    }
+
+    /// Returns the manager of the allowances of another account to the same
+    /// spender, e.g. an account of the rotation.
+    pub fn for_owner(&self, owner: Felt) -> Self {
+        Self {
+            owner,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the manager of the allowances of the same account to another
+    /// spender, e.g. the market of another lending protocol.
+    pub fn for_spender(&self, spender: Felt) -> Self {
+        Self {
+            spender,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the allowance of the bot to the spender on `token`.
+    pub async fn allowance(&self, token: Felt) -> Result<U256> {
+        let request = FunctionCall {
+            contract_address: token,
+            entry_point_selector: *ALLOWANCE_SELECTOR,
+            calldata: vec![self.owner, self.spender],
+        };
+        let result = self
+            .rpc_client
+            .call(request, BlockId::Tag(BlockTag::PreConfirmed))
+            .await?;
+        let [low, high, ..] = result[..] else {
+            anyhow::bail!("Unexpected allowance response of token {token:#x}");
+        };
+        Ok(U256 {
+            low: low.try_into()?,
+            high: high.try_into()?,
+        })
+    }
+
+    /// Logs the allowances of the configured assets, warning about the empty
+    /// ones: they are approved along with the next liquidation of their debt.
+    pub async fn check_all(&self) {
+        if !self.config.allowances.enabled {
+            return;
+        }
+        for (token, asset) in self.config.asset_map.iter() {
+            match self.allowance(*token).await {
+                Ok(allowance) if allowance == U256_ZERO => {
+                    tracing::warn!(
+                        "[🔭 Monitoring] No allowance to the Liquidate contract on {}, it will be approved with the next liquidation",
+                        asset.ticker
+                    );
+                }
+                Ok(allowance) => {
+                    tracing::info!(
+                        "[🔭 Monitoring] Allowance to the Liquidate contract on {}: {}",
+                        asset.ticker,
+                        u256_to_big_uint(&allowance)
+                    );
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        error = %e,
+                        "[🔭 Monitoring] Could not check the allowance on {}",
+                        asset.ticker
+                    );
+                }
+            }
+        }
+    }
+
+    /// Returns the approval to prepend to a liquidation repaying `amount` of
+    /// `token`, if the current allowance is below it.
+    pub async fn approval_calls(&self, token: Felt, amount: U256) -> Result<Vec<Call>> {
+        if !self.config.allowances.enabled {
+            return Ok(vec![]);
+        }
+        let allowance = self.allowance(token).await?;
+        if allowance >= amount {
+            return Ok(vec![]);
+        }
+        let approved = approval_amount(
+            amount,
+            self.config.approval_amount(&token),
+            self.config.allowances.unlimited,
+        );
+        tracing::info!(
+            "[🔭 Monitoring] Allowance on {:#x} is too low ({}), approving {}",
+            token,
+            u256_to_big_uint(&allowance),
+            u256_to_big_uint(&approved)
+        );
+        Ok(vec![Call {
+            to: token,
+            selector: *APPROVE_SELECTOR,
+            calldata: vec![self.spender, approved.low.into(), approved.high.into()],
+        }])
+    }
+}
+
+/// Returns the amount to approve for a liquidation repaying `needed`: the
+/// max if `unlimited`, else the configured amount, at least `needed`.
+fn approval_amount(needed: U256, configured: Option<U256>, unlimited: bool) -> U256 {
+    if unlimited {
+        return UNLIMITED_ALLOWANCE;
+    }
+    match configured {
+        Some(configured) if configured > needed => configured,
+        _ => needed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cainome::cairo_serde::U256;
+
+    use super::{UNLIMITED_ALLOWANCE, approval_amount};
+
+    #[test]
+    fn test_approval_amount() {
+        let needed = U256 { low: 100, high: 0 };
+        let configured = U256 {
+            low: 1_000,
+            high: 0,
+        };
+        assert_eq!(approval_amount(needed, None, false), needed);
+        assert_eq!(approval_amount(needed, Some(configured), false), configured);
+        assert_eq!(approval_amount(configured, Some(needed), false), configured);
+        assert_eq!(
+            approval_amount(needed, Some(configured), true),
+            UNLIMITED_ALLOWANCE
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use anyhow::Result;
+use starknet::core::types::{Felt, TypedData};
+
+use crate::{
+    types::{
+        account::StarknetAccount,
+        attestation::{AttestedPayout, PayoutAttestation},
+    },
+    utils::parse_cairo_short_string,
+};
+
+/// Signs the attestations of the payouts with the key of the liquidator
+/// account, see [`PayoutAttestation`].
+#[derive(Clone)]
+pub struct PayoutAttester {
+    account: StarknetAccount,
+}
+
+impl PayoutAttester {
+    pub fn new(account: StarknetAccount) -> Self {
+        Self { account }
+    }
+
+    /// Attests the payouts in order, the first one following the attestation
+    /// hashed `previous`.
+    pub async fn attest_all(
+        &self,
+        payouts: Vec<AttestedPayout>,
+        mut previous: Felt,
+    ) -> Result<Vec<PayoutAttestation>> {
+        let chain_id = parse_cairo_short_string(&self.account.chain_id())?;
+        let signer = self.account.account_address();
+        let mut attestations = Vec::with_capacity(payouts.len());
+        for payout in payouts {
+            let typed_data: TypedData = serde_json::from_value(PayoutAttestation::typed_data(
+                &payout, previous, &chain_id,
+            ))?;
+            let signature = self.account.sign_typed_data(&typed_data).await?;
+            let hash = typed_data.message_hash(signer)?;
+            attestations.push(PayoutAttestation {
+                payout,
+                previous,
+                signer,
+                hash,
+                signature,
+            });
+            previous = hash;
+        }
+        Ok(attestations)
+    }
+}
@@ -0,0 +1,73 @@
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{
+    types::operator::OperatorAction,
+    utils::{config_diff::ConfigChange, unix_now},
+};
+
+/// Operator-relevant events recorded in the audit log.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AuditEvent {
+    ConfigChanged {
+        config_path: String,
+        changes: Vec<ConfigChange>,
+    },
+    /// A reload of the config of the running bot, see
+    /// [`crate::services::config_watcher::ConfigReloader`].
+    ConfigReloaded {
+        config_path: String,
+        applied: Vec<ConfigChange>,
+        pending_restart: Vec<ConfigChange>,
+    },
+    /// A runbook action of an operator, see
+    /// [`crate::services::operator::Operator`].
+    OperatorAction { action: OperatorAction },
+}
+
+#[derive(Serialize)]
+struct AuditRecord<'a> {
+    timestamp: u64,
+    #[serde(flatten)]
+    event: &'a AuditEvent,
+}
+
+/// Append-only log of the events, one json object per line.
+#[derive(Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+    lock: Arc<Mutex<()>>,
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn record(&self, event: &AuditEvent) -> Result<()> {
+        let record = AuditRecord {
+            timestamp: unix_now(),
+            event,
+        };
+        let line = serde_json::to_string(&record)?;
+
+        let _guard = self.lock.lock().expect("audit log lock poisoned");
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{line}")?;
+        Ok(())
+    }
+}
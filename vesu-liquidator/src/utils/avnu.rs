@@ -0,0 +1,207 @@
+use anyhow::{Result, anyhow};
+use cainome::cairo_serde::U256;
+use serde::Deserialize;
+use starknet::core::{
+    types::{Call, Felt},
+    utils::get_selector_from_name,
+};
+
+use crate::utils::{
+    conversions::u256_to_big_uint,
+    http::HttpClient,
+    swap::{BPS_DENOMINATOR, SwapProvider, SwapQuote, min_amount_out},
+};
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct AvnuQuote {
+    quote_id: String,
+    buy_amount: String,
+    #[serde(default)]
+    sell_amount_in_usd: Option<f64>,
+    #[serde(default)]
+    buy_amount_in_usd: Option<f64>,
+}
+
+impl AvnuQuote {
+    /// Returns the loss of the swap in basis points, from the USD values of
+    /// the amounts, if known.
+    fn price_impact_bps(&self) -> Option<u32> {
+        let sell_usd = self.sell_amount_in_usd.filter(|usd| *usd > 0.0)?;
+        let buy_usd = self.buy_amount_in_usd?;
+        let loss = ((sell_usd - buy_usd) / sell_usd).max(0.0);
+        Some((loss * f64::from(BPS_DENOMINATOR)).round() as u32)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct AvnuBuild {
+    calls: Vec<AvnuCall>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct AvnuCall {
+    contract_address: String,
+    entrypoint: String,
+    calldata: Vec<String>,
+}
+
+/// Client of the AVNU swap aggregator API.
+#[derive(Clone)]
+pub struct AvnuClient {
+    http_client: HttpClient,
+    api_url: String,
+}
+
+impl AvnuClient {
+    pub fn new(http_client: HttpClient, api_url: String) -> Self {
+        Self {
+            http_client,
+            api_url,
+        }
+    }
+
+    /// Fetches the best quote of the swap.
+    async fn fetch_quote(
+        &self,
+        sell_token: Felt,
+        buy_token: Felt,
+        sell_amount: U256,
+        taker: Felt,
+    ) -> Result<AvnuQuote> {
+        let api_url = self.api_url.trim_end_matches('/');
+        let sell_amount = format!("{:#x}", u256_to_big_uint(&sell_amount));
+        let request = self
+            .http_client
+            .get(format!("{api_url}/swap/v2/quotes"))
+            .query(&[
+                ("sellTokenAddress", format!("{sell_token:#x}")),
+                ("buyTokenAddress", format!("{buy_token:#x}")),
+                ("sellAmount", sell_amount),
+                ("takerAddress", format!("{taker:#x}")),
+                ("size", "1".to_string()),
+            ]);
+        let quotes: Vec<AvnuQuote> = self
+            .http_client
+            .send(request)
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        quotes
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("No AVNU quote from {sell_token:#x} to {buy_token:#x}"))
+    }
+}
+
+#[async_trait::async_trait]
+impl SwapProvider for AvnuClient {
+    fn name(&self) -> &'static str {
+        "avnu"
+    }
+
+    async fn quote(
+        &self,
+        sell_token: Felt,
+        buy_token: Felt,
+        sell_amount: U256,
+        taker: Felt,
+    ) -> Result<U256> {
+        let quote = self
+            .fetch_quote(sell_token, buy_token, sell_amount, taker)
+            .await?;
+        parse_hex_amount(&quote.buy_amount)
+    }
+
+    async fn build_swap(
+        &self,
+        sell_token: Felt,
+        buy_token: Felt,
+        sell_amount: U256,
+        taker: Felt,
+        max_slippage_bps: u16,
+    ) -> Result<SwapQuote> {
+        let quote = self
+            .fetch_quote(sell_token, buy_token, sell_amount, taker)
+            .await?;
+
+        let api_url = self.api_url.trim_end_matches('/');
+        let request = self
+            .http_client
+            .post(format!("{api_url}/swap/v2/build"))
+            .json(&serde_json::json!({
+                "quoteId": quote.quote_id,
+                "takerAddress": format!("{taker:#x}"),
+                "slippage": f64::from(max_slippage_bps) / f64::from(BPS_DENOMINATOR),
+                "includeApprove": true,
+            }));
+        let build: AvnuBuild = self
+            .http_client
+            .send(request)
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let calls = build
+            .calls
+            .iter()
+            .map(|call| {
+                Ok(Call {
+                    to: Felt::from_hex(&call.contract_address)?,
+                    selector: get_selector_from_name(&call.entrypoint)?,
+                    calldata: call
+                        .calldata
+                        .iter()
+                        .map(|felt| Felt::from_hex(felt))
+                        .collect::<Result<_, _>>()?,
+                })
+            })
+            .collect::<Result<Vec<Call>>>()?;
+
+        let buy_amount = parse_hex_amount(&quote.buy_amount)?;
+        Ok(SwapQuote {
+            calls,
+            buy_amount,
+            min_buy_amount: min_amount_out(buy_amount, max_slippage_bps),
+            price_impact_bps: quote.price_impact_bps(),
+        })
+    }
+}
+
+/// Parses an amount returned by AVNU as an hexadecimal string.
+fn parse_hex_amount(amount: &str) -> Result<U256> {
+    let felt = Felt::from_hex(amount).map_err(|e| anyhow!("Invalid AVNU amount {amount}: {e}"))?;
+    Ok(U256::from(felt))
+}
+
+#[cfg(test)]
+mod tests {
+    use cainome::cairo_serde::U256;
+
+    use super::{AvnuQuote, parse_hex_amount};
+
+    #[test]
+    fn test_parse_avnu_quote() {
+        assert_eq!(
+            parse_hex_amount("0xf4240").unwrap(),
+            U256 {
+                low: 1_000_000,
+                high: 0
+            }
+        );
+        let quote = AvnuQuote {
+            quote_id: "1".to_string(),
+            buy_amount: "0xf4240".to_string(),
+            sell_amount_in_usd: Some(100.0),
+            buy_amount_in_usd: Some(97.5),
+        };
+        assert_eq!(quote.price_impact_bps(), Some(250));
+        let quote = AvnuQuote {
+            buy_amount_in_usd: None,
+            ..quote
+        };
+        assert_eq!(quote.price_impact_bps(), None);
+    }
+}
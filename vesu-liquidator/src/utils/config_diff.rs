@@ -0,0 +1,130 @@
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::config::{is_secret_field, redact_secrets};
+
+/// A config field added, removed or modified, e.g. `slo.target`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigChange {
+    pub key: String,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+impl fmt::Display for ConfigChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |value: &Option<Value>| match value {
+            Some(value) => value.to_string(),
+            None => "<unset>".to_string(),
+        };
+        write!(f, "{}: {} → {}", self.key, show(&self.old), show(&self.new))
+    }
+}
+
+/// Returns the fields that differ between two resolved configs, with their
+/// secrets redacted.
+pub fn diff_configs(old: &Value, new: &Value) -> Vec<ConfigChange> {
+    let mut changes = vec![];
+    diff_at(&mut vec![], Some(old), Some(new), &mut changes);
+    changes
+}
+
+fn diff_at(
+    path: &mut Vec<String>,
+    old: Option<&Value>,
+    new: Option<&Value>,
+    changes: &mut Vec<ConfigChange>,
+) {
+    match (old, new) {
+        (Some(Value::Object(old)), Some(Value::Object(new))) => {
+            let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                path.push(key.clone());
+                diff_at(path, old.get(key), new.get(key), changes);
+                path.pop();
+            }
+        }
+        (Some(Value::Array(old)), Some(Value::Array(new))) => {
+            for index in 0..old.len().max(new.len()) {
+                path.push(index.to_string());
+                diff_at(path, old.get(index), new.get(index), changes);
+                path.pop();
+            }
+        }
+        (old, new) if old != new => {
+            let is_secret = path.iter().any(|segment| is_secret_field(segment));
+            let redact = |value: Option<&Value>| {
+                value.map(|value| {
+                    if is_secret {
+                        return Value::String("<redacted>".to_string());
+                    }
+                    let mut value = value.clone();
+                    redact_secrets(&mut value);
+                    value
+                })
+            };
+            changes.push(ConfigChange {
+                key: path.join("."),
+                old: redact(old),
+                new: redact(new),
+            });
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{ConfigChange, diff_configs};
+
+    #[test]
+    fn test_diff_configs() {
+        let old = json!({
+            "slo": { "target": 0.95, "window_seconds": 3600 },
+            "notifications": { "channels": [{ "kind": "telegram", "bot_token": "secret", "chat_id": "1" }] },
+            "assets": [{ "ticker": "ETH" }],
+        });
+        let new = json!({
+            "slo": { "target": 0.99, "window_seconds": 3600 },
+            "notifications": { "channels": [
+                { "kind": "telegram", "bot_token": "new-secret", "chat_id": "1" },
+                { "kind": "discord", "webhook_url": "https://discord.com/api/webhooks/1" },
+            ] },
+            "assets": [{ "ticker": "ETH" }],
+            "game_sync": { "interval_seconds": 5 },
+        });
+
+        assert_eq!(
+            diff_configs(&old, &new),
+            vec![
+                ConfigChange {
+                    key: "game_sync".to_string(),
+                    old: None,
+                    new: Some(json!({ "interval_seconds": 5 })),
+                },
+                ConfigChange {
+                    key: "notifications.channels.0.bot_token".to_string(),
+                    old: Some(json!("<redacted>")),
+                    new: Some(json!("<redacted>")),
+                },
+                ConfigChange {
+                    key: "notifications.channels.1".to_string(),
+                    old: None,
+                    new: Some(json!({ "kind": "discord", "webhook_url": "<redacted>" })),
+                },
+                ConfigChange {
+                    key: "slo.target".to_string(),
+                    old: Some(json!(0.95)),
+                    new: Some(json!(0.99)),
+                },
+            ]
+        );
+        assert!(diff_configs(&old, &old).is_empty());
+    }
+}
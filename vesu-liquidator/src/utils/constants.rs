@@ -1,4 +1,5 @@
 use cainome::cairo_serde::U256;
+use starknet::core::types::Felt;
 
 use crate::bindings::liquidate::I129;
 
@@ -7,6 +8,13 @@ pub const VESU_RESPONSE_DECIMALS: i64 = 18;
 pub const MAX_RETRIES_VERIFY_TX_FINALITY: usize = 10;
 pub const INTERVAL_CHECK_TX_FINALITY: u64 = 3;
 
+// Default chain id of Katana devnets, `KATANA` as a short string
+pub const KATANA_CHAIN_ID: Felt = Felt::from_hex_unchecked("0x4b4154414e41");
+
+// Class hash of the OpenZeppelin v0.8.1 account, declared on mainnet & Sepolia
+pub const OZ_ACCOUNT_CLASS_HASH: &str =
+    "0x061dac032f228abef9c6626f995015233097ae253a7f72d68552db02f2971b8f";
+
 pub const U256_ZERO: U256 = U256 { low: 0, high: 0 };
 pub const I129_ZERO: I129 = I129 {
     mag: 0,
@@ -1,6 +1,7 @@
 use apibara_core::starknet::v1alpha2::FieldElement;
 use bigdecimal::BigDecimal;
-use bigdecimal::num_bigint::BigInt;
+use bigdecimal::num_bigint::{BigInt, BigUint};
+use cainome::cairo_serde::U256 as CairoU256;
 use starknet::core::types::{Felt, U256};
 
 /// Converts an hexadecimal string with decimals to BigDecimal.
@@ -25,11 +26,37 @@ pub fn big_decimal_to_u256(value: BigDecimal) -> U256 {
     U256::from(big_decimal_to_felt(value))
 }
 
+/// Converts an amount in tokens to the smallest unit of a token with
+/// `decimals` decimals.
+pub fn to_token_amount(amount: &BigDecimal, decimals: i64) -> U256 {
+    let unit = BigDecimal::new(1.into(), -decimals);
+    big_decimal_to_u256((amount * unit).with_scale(0))
+}
+
 pub fn big_decimal_to_felt(value: BigDecimal) -> Felt {
     let (amount, _): (BigInt, _) = value.as_bigint_and_exponent();
     Felt::from(amount.clone())
 }
 
+/// Converts a Felt into a Cairo U256.
+pub fn felt_to_u256(value: Felt) -> CairoU256 {
+    CairoU256::from_bytes_be(&value.to_bytes_be())
+}
+
+/// Converts a Cairo U256 into a BigUint.
+pub fn u256_to_big_uint(value: &CairoU256) -> BigUint {
+    BigUint::from_bytes_be(&value.to_bytes_be())
+}
+
+/// Converts a BigUint into a Cairo U256, truncating anything above 256 bits.
+pub fn big_uint_to_u256(value: &BigUint) -> CairoU256 {
+    let bytes = value.to_bytes_be();
+    let mut padded = [0_u8; 32];
+    let len = bytes.len().min(32);
+    padded[32 - len..].copy_from_slice(&bytes[bytes.len() - len..]);
+    CairoU256::from_bytes_be(&padded)
+}
+
 #[cfg(test)]
 mod test {
     use std::str::FromStr;
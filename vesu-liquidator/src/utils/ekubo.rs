@@ -5,15 +5,15 @@ use serde_json::Value;
 use starknet::core::types::Felt;
 
 use crate::{
-    bindings::liquidate::{PoolKey, RouteNode, Swap, TokenAmount},
-    utils::constants::I129_ZERO,
+    bindings::liquidate::{I129, PoolKey, RouteNode, Swap, TokenAmount},
+    utils::{constants::I129_ZERO, conversions::u256_to_big_uint, http::HttpClient},
 };
 
-const EKUBO_QUOTE_ENDPOINT: &str = "https://quoter-mainnet-api.ekubo.org";
 const SCALE: u128 = 1_000_000_000_000_000_000;
 
 pub async fn get_ekubo_route(
-    http_client: &reqwest::Client,
+    http_client: &HttpClient,
+    quoter_url: &str,
     from_token: Felt,
     to_token: Felt,
     amount: &BigDecimal,
@@ -22,13 +22,15 @@ pub async fn get_ekubo_route(
 
     let ekubo_api_endpoint = format!(
         "{}/-{}/{}/{}",
-        EKUBO_QUOTE_ENDPOINT,
+        quoter_url.trim_end_matches('/'),
         scaled_amount,
         from_token.to_fixed_hex_string(),
         to_token.to_fixed_hex_string()
     );
 
-    let response = http_client.get(ekubo_api_endpoint).send().await?;
+    let response = http_client
+        .send(http_client.get(ekubo_api_endpoint))
+        .await?;
 
     if !response.status().is_success() {
         anyhow::bail!("API request failed with status: {}", response.status());
@@ -118,6 +120,72 @@ pub async fn get_ekubo_route(
     Ok((swaps, weights))
 }
 
+/// Quotes the swap of an exact `amount` of `from_token` to `to_token`.
+/// Returns the swaps to send to the Ekubo router, each selling its split of
+/// the amount, & the total amount received.
+pub async fn get_ekubo_exact_input_route(
+    http_client: &HttpClient,
+    quoter_url: &str,
+    from_token: Felt,
+    to_token: Felt,
+    amount: U256,
+) -> Result<(Vec<Swap>, U256)> {
+    let ekubo_api_endpoint = format!(
+        "{}/{}/{}/{}",
+        quoter_url.trim_end_matches('/'),
+        u256_to_big_uint(&amount),
+        from_token.to_fixed_hex_string(),
+        to_token.to_fixed_hex_string()
+    );
+    let response = http_client
+        .send(http_client.get(ekubo_api_endpoint))
+        .await?;
+    if !response.status().is_success() {
+        anyhow::bail!("API request failed with status: {}", response.status());
+    }
+    let json_value: Value = response.json().await?;
+
+    let splits = json_value["splits"]
+        .as_array()
+        .context("'splits' is not an array")?;
+    if splits.is_empty() {
+        anyhow::bail!("No splits returned from Ekubo API");
+    }
+    let swaps = splits
+        .iter()
+        .map(|split| {
+            let amount_specified = split["amount_specified"]
+                .as_str()
+                .context("amount_specified is not a string")?
+                .parse::<u128>()?;
+            Ok(Swap {
+                route: parse_route(split)?,
+                token_amount: TokenAmount {
+                    token: ContractAddress(from_token),
+                    amount: I129 {
+                        mag: amount_specified,
+                        sign: false,
+                    },
+                },
+            })
+        })
+        .collect::<Result<Vec<Swap>>>()?;
+
+    // The amount received is negative from the point of view of the pools.
+    let total_calculated = json_value["total_calculated"]
+        .as_str()
+        .context("total_calculated is not a string")?
+        .trim_start_matches('-')
+        .parse::<u128>()?;
+    Ok((
+        swaps,
+        U256 {
+            low: total_calculated,
+            high: 0,
+        },
+    ))
+}
+
 fn parse_route(split: &Value) -> Result<Vec<RouteNode>> {
     split["route"]
         .as_array()
@@ -0,0 +1,150 @@
+use std::fmt;
+
+use starknet::{core::types::StarknetError, providers::ProviderError};
+use thiserror::Error;
+
+/// Revert reason of the Vesu singleton liquidating a healthy position.
+const NOT_UNDERCOLLATERALIZED: &str = "not-undercollateralized";
+
+/// Errors of the bot the retries & the cleanups are decided from, carried in
+/// the `anyhow` errors & found back with [`LiquidatorError::of`].
+#[derive(Debug, Error)]
+pub enum LiquidatorError {
+    /// The RPC node failed or could not be reached.
+    #[error("RPC error: {0}")]
+    Rpc(String),
+    /// The position is healthy, its liquidation is rejected by the protocol.
+    #[error("Position is not undercollateralized: {0}")]
+    NotUndercollateralized(String),
+    /// A simulated or sent transaction reverted.
+    #[error("Liquidation simulation reverted: {0}")]
+    Reverted(String),
+    /// Selling the seized collateral would lose more than
+    /// `price_impact.max_impact_bps`.
+    #[error("Price impact too high: {0}")]
+    PriceImpact(String),
+    /// No Torii endpoint could answer a query.
+    #[error("Torii error: {0}")]
+    Torii(String),
+    /// The storage could not be read or written.
+    #[error("Storage error: {0}")]
+    Storage(String),
+    /// An amount, a price or a ratio could not be computed.
+    #[error("Math error: {0}")]
+    Math(String),
+}
+
+/// Whether an operation that failed is tried again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// The failure is transient: the operation is retried.
+    Retryable,
+    /// The operation fails the same way whenever it's retried.
+    Fatal,
+}
+
+impl LiquidatorError {
+    /// Error of a reverted transaction, from its revert reason.
+    pub fn reverted(reason: impl fmt::Display) -> Self {
+        let reason = reason.to_string();
+        if reason.contains(NOT_UNDERCOLLATERALIZED) {
+            LiquidatorError::NotUndercollateralized(reason)
+        } else {
+            LiquidatorError::Reverted(reason)
+        }
+    }
+
+    /// Error of an RPC request, a rejected simulation or fee estimation
+    /// carrying the revert reason of the contract.
+    pub fn rpc(message: impl fmt::Display) -> Self {
+        let message = message.to_string();
+        if message.contains(NOT_UNDERCOLLATERALIZED) {
+            LiquidatorError::NotUndercollateralized(message)
+        } else {
+            LiquidatorError::Rpc(message)
+        }
+    }
+
+    /// Returns the error of the bot carried by `error` or by one of its
+    /// causes, if any.
+    pub fn of(error: &anyhow::Error) -> Option<&LiquidatorError> {
+        error.chain().find_map(|cause| cause.downcast_ref())
+    }
+
+    /// A revert or a price impact too high are retried at the next check, as
+    /// the state of the chain moves; a healthy position or an amount that
+    /// can't be computed are not.
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            LiquidatorError::Rpc(_)
+            | LiquidatorError::Reverted(_)
+            | LiquidatorError::PriceImpact(_)
+            | LiquidatorError::Torii(_)
+            | LiquidatorError::Storage(_) => ErrorClass::Retryable,
+            LiquidatorError::NotUndercollateralized(_) | LiquidatorError::Math(_) => {
+                ErrorClass::Fatal
+            }
+        }
+    }
+}
+
+/// Classifies any error: the errors of the bot by their kind, the errors of
+/// the Starknet provider as retryable unless a contract is missing or failed,
+/// and the other ones as retryable.
+pub fn classify(error: &anyhow::Error) -> ErrorClass {
+    if let Some(error) = LiquidatorError::of(error) {
+        return error.class();
+    }
+    match error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<ProviderError>())
+    {
+        Some(ProviderError::StarknetError(
+            StarknetError::ContractNotFound | StarknetError::ContractError(_),
+        )) => ErrorClass::Fatal,
+        _ => ErrorClass::Retryable,
+    }
+}
+
+/// Whether the operation that failed with `error` is tried again.
+pub fn is_retryable(error: &anyhow::Error) -> bool {
+    classify(error) == ErrorClass::Retryable
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{Context, anyhow};
+    use starknet::{core::types::StarknetError, providers::ProviderError};
+
+    use super::{ErrorClass, LiquidatorError, classify};
+
+    #[test]
+    fn test_classify() {
+        let healthy: anyhow::Error =
+            LiquidatorError::reverted("Failure reason: 'not-undercollateralized'").into();
+        assert!(matches!(
+            LiquidatorError::of(&healthy),
+            Some(LiquidatorError::NotUndercollateralized(_))
+        ));
+        assert_eq!(classify(&healthy), ErrorClass::Fatal);
+
+        let reverted: anyhow::Error = LiquidatorError::reverted("'insufficient-liquidity'").into();
+        assert!(matches!(
+            LiquidatorError::of(&reverted),
+            Some(LiquidatorError::Reverted(_))
+        ));
+        assert_eq!(classify(&reverted), ErrorClass::Retryable);
+
+        // Found behind a context.
+        let math = Err::<(), _>(LiquidatorError::Math("zero price".to_string()))
+            .context("Could not evaluate the position")
+            .unwrap_err();
+        assert_eq!(classify(&math), ErrorClass::Fatal);
+
+        let missing: anyhow::Error =
+            ProviderError::StarknetError(StarknetError::ContractNotFound).into();
+        assert_eq!(classify(&missing), ErrorClass::Fatal);
+
+        assert_eq!(classify(&anyhow!("timeout")), ErrorClass::Retryable);
+    }
+}
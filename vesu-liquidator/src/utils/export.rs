@@ -0,0 +1,244 @@
+use std::{fs::File, path::Path, sync::Arc};
+
+use anyhow::Result;
+use arrow_array::{ArrayRef, RecordBatch, StringArray, UInt64Array};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use starknet::core::types::Felt;
+
+/// Format of the files written by the `export` commands.
+#[derive(Debug, Clone, Copy, clap::ValueEnum, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Parquet,
+}
+
+/// Value of a cell of an exported table.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Integer(u64),
+    Null,
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Text(value)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Value::Integer(value)
+    }
+}
+
+impl From<Felt> for Value {
+    fn from(value: Felt) -> Self {
+        Value::Text(format!("{value:#x}"))
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+/// Column of an exported table, of integers or of text.
+#[derive(Debug, Clone, Copy)]
+pub struct Column {
+    pub name: &'static str,
+    pub integer: bool,
+}
+
+impl Column {
+    pub const fn text(name: &'static str) -> Self {
+        Self {
+            name,
+            integer: false,
+        }
+    }
+
+    pub const fn integer(name: &'static str) -> Self {
+        Self {
+            name,
+            integer: true,
+        }
+    }
+}
+
+/// Rows exported from the storage for offline analytics.
+#[derive(Debug)]
+pub struct Table {
+    columns: &'static [Column],
+    rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    pub fn new(columns: &'static [Column]) -> Self {
+        Self {
+            columns,
+            rows: vec![],
+        }
+    }
+
+    pub fn push(&mut self, row: Vec<Value>) {
+        debug_assert_eq!(row.len(), self.columns.len());
+        self.rows.push(row);
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Writes the table to `path` in the given format.
+    pub fn write(&self, path: &Path, format: ExportFormat) -> Result<()> {
+        match format {
+            ExportFormat::Csv => Ok(std::fs::write(path, self.to_csv())?),
+            ExportFormat::Parquet => self.write_parquet(path),
+        }
+    }
+
+    /// Returns the table in CSV, with a header line.
+    pub fn to_csv(&self) -> String {
+        let header: Vec<&str> = self.columns.iter().map(|column| column.name).collect();
+        let mut csv = header.join(",");
+        csv.push('\n');
+        for row in self.rows.iter() {
+            let cells: Vec<String> = row.iter().map(csv_cell).collect();
+            csv.push_str(&cells.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    fn write_parquet(&self, path: &Path) -> Result<()> {
+        let fields: Vec<Field> = self
+            .columns
+            .iter()
+            .map(|column| {
+                let data_type = if column.integer {
+                    DataType::UInt64
+                } else {
+                    DataType::Utf8
+                };
+                Field::new(column.name, data_type, true)
+            })
+            .collect();
+        let arrays: Vec<ArrayRef> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| {
+                let cells = self.rows.iter().map(|row| &row[index]);
+                if column.integer {
+                    let values: Vec<Option<u64>> = cells
+                        .map(|cell| match cell {
+                            Value::Integer(value) => Some(*value),
+                            _ => None,
+                        })
+                        .collect();
+                    Arc::new(UInt64Array::from(values)) as ArrayRef
+                } else {
+                    let values: Vec<Option<&str>> = cells
+                        .map(|cell| match cell {
+                            Value::Text(value) => Some(value.as_str()),
+                            _ => None,
+                        })
+                        .collect();
+                    Arc::new(StringArray::from(values)) as ArrayRef
+                }
+            })
+            .collect();
+        let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?;
+
+        let mut writer = ArrowWriter::try_new(File::create(path)?, batch.schema(), None)?;
+        writer.write(&batch)?;
+        writer.close()?;
+        Ok(())
+    }
+}
+
+/// Formats a cell, quoted if it holds a separator, a quote or a line break.
+fn csv_cell(value: &Value) -> String {
+    match value {
+        Value::Text(text) if text.contains([',', '"', '\n']) => {
+            format!("\"{}\"", text.replace('"', "\"\""))
+        }
+        Value::Text(text) => text.clone(),
+        Value::Integer(value) => value.to_string(),
+        Value::Null => String::new(),
+    }
+}
+
+/// Rows kept by the `export` commands.
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    pub from_block: Option<u64>,
+    pub to_block: Option<u64>,
+    pub address: Option<Felt>,
+}
+
+impl ExportFilter {
+    /// Returns if a row at `block`, about `address`, is kept. A block range
+    /// only keeps the rows with a block in it.
+    pub fn accepts(&self, block: Option<u64>, address: Felt) -> bool {
+        if self.address.is_some_and(|expected| expected != address) {
+            return false;
+        }
+        if self.from_block.is_none() && self.to_block.is_none() {
+            return true;
+        }
+        block.is_some_and(|block| {
+            self.from_block.is_none_or(|from| block >= from)
+                && self.to_block.is_none_or(|to| block <= to)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+
+    use super::{Column, ExportFilter, Table, Value};
+
+    const COLUMNS: &[Column] = &[Column::text("name"), Column::integer("block_number")];
+
+    #[test]
+    fn test_table_to_csv() {
+        let mut table = Table::new(COLUMNS);
+        table.push(vec![Value::from("plain".to_string()), Value::from(10_u64)]);
+        table.push(vec![
+            Value::from("with, \"quotes\"".to_string()),
+            Value::from(None::<u64>),
+        ]);
+        assert_eq!(
+            table.to_csv(),
+            "name,block_number\nplain,10\n\"with, \"\"quotes\"\"\",\n"
+        );
+    }
+
+    #[test]
+    fn test_export_filter() {
+        let all = ExportFilter::default();
+        assert!(all.accepts(None, Felt::ONE));
+
+        let range = ExportFilter {
+            from_block: Some(10),
+            to_block: Some(20),
+            address: None,
+        };
+        assert!(range.accepts(Some(10), Felt::ONE));
+        assert!(range.accepts(Some(20), Felt::ONE));
+        assert!(!range.accepts(Some(21), Felt::ONE));
+        assert!(!range.accepts(None, Felt::ONE));
+
+        let address = ExportFilter {
+            address: Some(Felt::TWO),
+            ..Default::default()
+        };
+        assert!(address.accepts(None, Felt::TWO));
+        assert!(!address.accepts(None, Felt::ONE));
+    }
+}
@@ -0,0 +1,208 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+use serde::Serialize;
+use starknet::core::types::Felt;
+
+use crate::{
+    config::{Config, FeeToken},
+    types::balance::BotBalances,
+    utils::paymaster::PaymasterClient,
+};
+
+/// Fee token of the transactions, exported at `/metrics`.
+#[derive(Debug, Serialize)]
+pub struct FeeTokenMetrics {
+    pub preferred: FeeToken,
+    pub current: FeeToken,
+    /// Switches between the tokens since the start of the bot.
+    pub switches: u64,
+}
+
+/// Selects the token the transaction fees are paid in, see
+/// `transactions.fee_token`: with `fee_token_fallback`, the fees are paid in
+/// the other token while the balance of the preferred one is low & the other
+/// one isn't, according to the [`BotBalances`] checked by the notifier.
+#[derive(Clone, Default)]
+pub struct FeeTokens {
+    preferred: FeeToken,
+    fallback: bool,
+    strk: Option<Felt>,
+    eth: Option<Felt>,
+    /// Paymaster paying the fees in ETH, if they can be.
+    eth_paymaster: Option<PaymasterClient>,
+    balances: BotBalances,
+    using_fallback: Arc<AtomicBool>,
+    switches: Arc<AtomicU64>,
+}
+
+impl FeeTokens {
+    pub fn from_config(config: &Config, balances: BotBalances) -> Self {
+        let preferred = config.transactions.fee_token;
+        let fallback = config.transactions.fee_token_fallback;
+        let eth = config.get_asset_address_for_ticker("eth");
+        let pays_in_eth = preferred == FeeToken::Eth || fallback;
+        Self {
+            preferred,
+            fallback,
+            strk: config.get_asset_address_for_ticker("strk"),
+            eth,
+            eth_paymaster: eth
+                .filter(|_| pays_in_eth)
+                .map(|eth| PaymasterClient::for_gas_token(config, eth, None)),
+            balances,
+            using_fallback: Arc::new(AtomicBool::new(false)),
+            switches: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Returns the token the next transaction would pay its fees in, without
+    /// recording a switch, see [`Self::select`].
+    pub fn current(&self) -> FeeToken {
+        let other = self.preferred.other();
+        if self.fallback && self.is_low(self.preferred) && self.is_sufficient(other) {
+            other
+        } else {
+            self.preferred
+        }
+    }
+
+    /// Returns the token the next transaction pays its fees in, counting &
+    /// logging the switches between the tokens. Only called when sending a
+    /// transaction.
+    pub fn select(&self) -> FeeToken {
+        let other = self.preferred.other();
+        let fallback = self.current() == other;
+        if self.using_fallback.swap(fallback, Ordering::Relaxed) != fallback {
+            self.switches.fetch_add(1, Ordering::Relaxed);
+            if fallback {
+                tracing::warn!(
+                    "⛽ The {} balance is low, paying the transaction fees in {}",
+                    self.preferred.ticker(),
+                    other.ticker()
+                );
+            } else {
+                tracing::info!(
+                    "⛽ Paying the transaction fees in {} again",
+                    self.preferred.ticker()
+                );
+            }
+        }
+        if fallback { other } else { self.preferred }
+    }
+
+    /// Returns if the fees can be paid, i.e. the balance of the current fee
+    /// token is not low.
+    pub fn can_pay(&self) -> bool {
+        !self.is_low(self.current())
+    }
+
+    /// Returns the paymaster paying the fees in ETH.
+    pub fn eth_paymaster(&self) -> Option<&PaymasterClient> {
+        self.eth_paymaster.as_ref()
+    }
+
+    pub fn metrics(&self) -> FeeTokenMetrics {
+        FeeTokenMetrics {
+            preferred: self.preferred,
+            current: if self.using_fallback.load(Ordering::Relaxed) {
+                self.preferred.other()
+            } else {
+                self.preferred
+            },
+            switches: self.switches.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn address(&self, token: FeeToken) -> Option<Felt> {
+        match token {
+            FeeToken::Strk => self.strk,
+            FeeToken::Eth => self.eth,
+        }
+    }
+
+    fn is_low(&self, token: FeeToken) -> bool {
+        self.address(token)
+            .is_some_and(|address| self.balances.is_low(&address))
+    }
+
+    fn is_sufficient(&self, token: FeeToken) -> bool {
+        self.address(token)
+            .is_some_and(|address| self.balances.is_sufficient(&address))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+    use starknet::core::types::Felt;
+
+    use super::FeeTokens;
+    use crate::{
+        config::FeeToken,
+        types::balance::{BotBalances, TokenBalance},
+    };
+
+    const STRK: Felt = Felt::ONE;
+    const ETH: Felt = Felt::TWO;
+
+    fn set_balance(balances: &BotBalances, token: Felt, balance: &str) {
+        balances.update(
+            token,
+            TokenBalance {
+                ticker: String::new(),
+                balance: BigDecimal::from_str(balance).unwrap(),
+                threshold: Some(BigDecimal::from(1)),
+                checked_at: 0,
+            },
+        );
+    }
+
+    #[test]
+    fn test_fee_token_fallback() {
+        let balances = BotBalances::default();
+        let fee_tokens = FeeTokens {
+            fallback: true,
+            strk: Some(STRK),
+            eth: Some(ETH),
+            balances: balances.clone(),
+            ..Default::default()
+        };
+        assert_eq!(fee_tokens.select(), FeeToken::Strk);
+
+        // Not switching to an ETH balance never checked...
+        set_balance(&balances, STRK, "0.5");
+        assert_eq!(fee_tokens.select(), FeeToken::Strk);
+        assert!(!fee_tokens.can_pay());
+        // ...nor to a low one.
+        set_balance(&balances, ETH, "0.1");
+        assert_eq!(fee_tokens.select(), FeeToken::Strk);
+
+        // Checking if the fees can be paid doesn't switch the token...
+        set_balance(&balances, ETH, "2");
+        assert!(fee_tokens.can_pay());
+        assert_eq!(fee_tokens.current(), FeeToken::Eth);
+        assert_eq!(fee_tokens.metrics().current, FeeToken::Strk);
+        assert_eq!(fee_tokens.metrics().switches, 0);
+        // ...only sending a transaction does.
+        assert_eq!(fee_tokens.select(), FeeToken::Eth);
+        assert_eq!(fee_tokens.metrics().current, FeeToken::Eth);
+
+        set_balance(&balances, STRK, "100");
+        assert_eq!(fee_tokens.select(), FeeToken::Strk);
+        assert_eq!(fee_tokens.metrics().switches, 2);
+
+        // Without the fallback, the preferred token is kept.
+        let fee_tokens = FeeTokens {
+            fallback: false,
+            ..fee_tokens
+        };
+        set_balance(&balances, STRK, "0.5");
+        assert_eq!(fee_tokens.select(), FeeToken::Strk);
+        assert!(!fee_tokens.can_pay());
+    }
+}
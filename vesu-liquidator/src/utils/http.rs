@@ -0,0 +1,175 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow};
+use reqwest::{IntoUrl, RequestBuilder, Response};
+
+use crate::config::{RateLimit, RateLimitsConfig};
+
+/// Token bucket of a host: refilled by `rate` tokens per second, up to
+/// `capacity`. Each request takes a token, waiting for it when there is none.
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    rate: f64,
+    /// Below 0 when requests are waiting for their token.
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: &RateLimit, now: Instant) -> Self {
+        let capacity = f64::from(limit.burst.max(1));
+        Self {
+            capacity,
+            rate: limit.requests_per_second,
+            tokens: capacity,
+            refilled_at: now,
+        }
+    }
+
+    /// Reserves a token, returning how long to wait for it. `None` if the wait
+    /// would exceed `max_wait`: the token is not taken.
+    fn reserve(&mut self, now: Instant, max_wait: Duration) -> Option<Duration> {
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate).min(self.capacity);
+        self.refilled_at = now;
+
+        let missing = 1.0 - self.tokens;
+        let wait = if missing > 0.0 {
+            Duration::from_secs_f64(missing / self.rate)
+        } else {
+            Duration::ZERO
+        };
+        if wait > max_wait {
+            return None;
+        }
+        self.tokens -= 1.0;
+        Some(wait)
+    }
+}
+
+/// Per-host request budgets of the external HTTP calls, shared by the clones
+/// of the limiter. The hosts without a budget are not limited.
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    buckets: Arc<HashMap<String, Mutex<TokenBucket>>>,
+    max_wait: Duration,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitsConfig) -> Self {
+        let now = Instant::now();
+        let buckets = config
+            .hosts
+            .iter()
+            .map(|(host, limit)| (host.clone(), Mutex::new(TokenBucket::new(limit, now))))
+            .collect();
+        Self {
+            buckets: Arc::new(buckets),
+            max_wait: Duration::from_millis(config.max_wait_ms),
+        }
+    }
+
+    /// Waits until the budget of the host of `url` allows a request. Fails if
+    /// the wait would exceed `rate_limits.max_wait_ms`.
+    pub async fn acquire(&self, url: &reqwest::Url) -> Result<()> {
+        let Some(host) = url.host_str() else {
+            return Ok(());
+        };
+        let Some(bucket) = self.buckets.get(host) else {
+            return Ok(());
+        };
+        let wait = bucket
+            .lock()
+            .expect("rate limiter lock poisoned")
+            .reserve(Instant::now(), self.max_wait)
+            .ok_or_else(|| anyhow!("Request budget of {host} exhausted"))?;
+        if !wait.is_zero() {
+            tracing::debug!(host, "[🚦 Rate Limiter] Waiting {:?} for {}", wait, host);
+            tokio::time::sleep(wait).await;
+        }
+        Ok(())
+    }
+}
+
+/// HTTP client of the external APIs (Torii, prices, swap quotes), sending the
+/// requests within the budget of their host, see [`RateLimitsConfig`].
+#[derive(Clone, Default)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    limiter: RateLimiter,
+}
+
+impl HttpClient {
+    pub fn new(config: &RateLimitsConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            limiter: RateLimiter::new(config),
+        }
+    }
+
+    pub fn get(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn post(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    /// Sends the request once the budget of its host allows it.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let request = request.build()?;
+        self.limiter.acquire(request.url()).await?;
+        Ok(self.client.execute(request).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::config::RateLimit;
+
+    use super::TokenBucket;
+
+    #[test]
+    fn test_token_bucket() {
+        let limit = RateLimit {
+            requests_per_second: 2.0,
+            burst: 2,
+        };
+        let start = Instant::now();
+        let max_wait = Duration::from_secs(1);
+        let mut bucket = TokenBucket::new(&limit, start);
+
+        // The burst goes through, then a token every 500ms.
+        assert_eq!(bucket.reserve(start, max_wait), Some(Duration::ZERO));
+        assert_eq!(bucket.reserve(start, max_wait), Some(Duration::ZERO));
+        assert_eq!(
+            bucket.reserve(start, max_wait),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            bucket.reserve(start, max_wait),
+            Some(Duration::from_secs(1))
+        );
+        // Beyond the max wait, the request is rejected without taking a token.
+        assert_eq!(bucket.reserve(start, max_wait), None);
+
+        let later = start + Duration::from_secs(1);
+        assert_eq!(
+            bucket.reserve(later, max_wait),
+            Some(Duration::from_millis(500))
+        );
+
+        // Refilled up to the burst only.
+        let idle = later + Duration::from_secs(60);
+        assert_eq!(bucket.reserve(idle, max_wait), Some(Duration::ZERO));
+        assert_eq!(bucket.reserve(idle, max_wait), Some(Duration::ZERO));
+        assert!(bucket.reserve(idle, max_wait).unwrap() > Duration::ZERO);
+    }
+}
@@ -0,0 +1,174 @@
+use anyhow::Result;
+use cainome::cairo_serde::U256;
+use serde_json::{Value, json};
+use starknet::core::types::{Felt, TypedData};
+
+use crate::{
+    config::Config,
+    types::account::StarknetAccount,
+    utils::{http::HttpClient, parse_cairo_short_string, unix_now},
+};
+
+/// Payout announced to a player once its transaction is accepted.
+#[derive(Debug, Clone)]
+pub struct PayoutMessage {
+    pub player: Felt,
+    pub token: Felt,
+    pub amount: U256,
+    pub tx_hash: Felt,
+}
+
+/// Publishes the payouts to the players as off-chain messages through the
+/// relay of Torii, so the game client shows them without polling the chain.
+///
+/// Each message is a SNIP-12 typed data of the `torii.payout_message_model`
+/// model declared by the game, signed by the liquidator account:
+/// `{ identity, player, token, amount: u256, tx_hash, timestamp }`.
+#[derive(Clone)]
+pub struct PayoutMessenger {
+    http_client: HttpClient,
+    account: StarknetAccount,
+    url: String,
+    model: String,
+}
+
+impl PayoutMessenger {
+    /// The messenger of `torii.message_url`, none if it is not set.
+    pub fn from_config(
+        http_client: HttpClient,
+        account: StarknetAccount,
+        config: &Config,
+    ) -> Option<Self> {
+        let url = config.torii.message_url.clone()?;
+        Some(Self {
+            http_client,
+            account,
+            url,
+            model: config.torii.payout_message_model.clone(),
+        })
+    }
+
+    /// Publishes the messages, the failures being only logged: the payouts
+    /// went through.
+    pub async fn publish_all(&self, messages: &[PayoutMessage]) {
+        for message in messages {
+            if let Err(e) = self.publish(message).await {
+                tracing::warn!(
+                    error = %e,
+                    player_address = format!("{:#x}", message.player),
+                    tx_hash = format!("{:#064x}", message.tx_hash),
+                    "[💸 Distribution] Could not publish the payout message of player {:#x}",
+                    message.player
+                );
+            }
+        }
+    }
+
+    /// Signs the message & publishes it, as
+    /// `{"message": "<typed data>", "signature": ["0x...", ...]}`.
+    pub async fn publish(&self, message: &PayoutMessage) -> Result<()> {
+        let chain_id = parse_cairo_short_string(&self.account.chain_id())?;
+        let typed_data = payout_typed_data(
+            &self.model,
+            &chain_id,
+            self.account.account_address(),
+            message,
+            unix_now(),
+        );
+        let signature = self
+            .account
+            .sign_typed_data(&serde_json::from_value::<TypedData>(typed_data.clone())?)
+            .await?;
+        let request = self.http_client.post(&self.url).json(&json!({
+            "message": serde_json::to_string(&typed_data)?,
+            "signature": signature
+                .iter()
+                .map(|felt| format!("{felt:#x}"))
+                .collect::<Vec<_>>(),
+        }));
+        self.http_client.send(request).await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Returns the typed data of a payout message of `model`, as
+/// `namespace-Model`, the namespace being the name of the domain.
+fn payout_typed_data(
+    model: &str,
+    chain_id: &str,
+    identity: Felt,
+    message: &PayoutMessage,
+    timestamp: u64,
+) -> Value {
+    let namespace = model
+        .split_once('-')
+        .map_or(model, |(namespace, _)| namespace);
+    json!({
+        "types": {
+            "StarknetDomain": [
+                { "name": "name", "type": "shortstring" },
+                { "name": "version", "type": "shortstring" },
+                { "name": "chainId", "type": "shortstring" },
+                { "name": "revision", "type": "shortstring" }
+            ],
+            model: [
+                { "name": "identity", "type": "ContractAddress" },
+                { "name": "player", "type": "ContractAddress" },
+                { "name": "token", "type": "ContractAddress" },
+                { "name": "amount", "type": "u256" },
+                { "name": "tx_hash", "type": "felt" },
+                { "name": "timestamp", "type": "timestamp" }
+            ]
+        },
+        "primaryType": model,
+        "domain": {
+            "name": namespace,
+            "version": "1",
+            "chainId": chain_id,
+            "revision": "1"
+        },
+        "message": {
+            "identity": format!("{identity:#x}"),
+            "player": format!("{:#x}", message.player),
+            "token": format!("{:#x}", message.token),
+            "amount": {
+                "low": format!("{:#x}", message.amount.low),
+                "high": format!("{:#x}", message.amount.high)
+            },
+            "tx_hash": format!("{:#x}", message.tx_hash),
+            "timestamp": timestamp
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use cainome::cairo_serde::U256;
+    use starknet::core::types::{Felt, TypedData};
+
+    use super::{PayoutMessage, payout_typed_data};
+
+    #[test]
+    fn test_payout_typed_data() {
+        let message = PayoutMessage {
+            player: Felt::from(0x123),
+            token: Felt::from(0x456),
+            amount: U256 { low: 42, high: 0 },
+            tx_hash: Felt::from(0x789),
+        };
+        let typed_data = payout_typed_data(
+            "shoot_it-PayoutMessage",
+            "SN_MAIN",
+            Felt::ONE,
+            &message,
+            1_700_000_000,
+        );
+        assert_eq!(typed_data["domain"]["name"], "shoot_it");
+        assert_eq!(typed_data["primaryType"], "shoot_it-PayoutMessage");
+        assert_eq!(typed_data["message"]["amount"]["low"], "0x2a");
+
+        // A valid SNIP-12 typed data, whose hash can be signed.
+        let typed_data: TypedData = serde_json::from_value(typed_data).unwrap();
+        assert!(typed_data.message_hash(Felt::ONE).is_ok());
+    }
+}
@@ -1,62 +1,126 @@
+pub mod allowances;
+pub mod attestations;
+pub mod audit;
+pub mod avnu;
+pub mod config_diff;
 pub mod constants;
 pub mod conversions;
 pub mod ekubo;
+pub mod errors;
+pub mod export;
+pub mod fee_tokens;
+pub mod http;
+pub mod messages;
+pub mod paymaster;
+pub mod price_impact;
+pub mod profile;
+pub mod rpc;
+pub mod serialization;
 pub mod services;
+pub mod shutdown;
+pub mod slo;
+pub mod swap;
+pub mod telemetry;
+pub mod torii;
+pub mod tx_manager;
+#[cfg(windows)]
+pub mod windows;
 
 use std::{
+    io::IsTerminal,
     sync::Arc,
-    time::{Duration, SystemTime},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::bail;
 use starknet::{
-    core::types::{ExecutionResult, Felt, StarknetError},
+    core::types::{
+        ExecutionResult, Felt, StarknetError, TransactionReceiptWithBlockInfo, TransactionStatus,
+    },
     providers::{JsonRpcClient, Provider, ProviderError, jsonrpc::HttpTransport},
 };
 
-pub fn setup_tracing() {
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
-        .compact()
+use tracing_subscriber::{
+    Layer, filter::LevelFilter, layer::SubscriberExt, util::SubscriberInitExt,
+};
+
+use crate::cli::LogFormat;
+
+/// Logs are colored only in a terminal, so they stay readable when written
+/// to files by a service manager (launchd, Windows SCM, systemd...).
+/// In json, the fields of the events (`position_key`, `pool_id`, `tx_hash`,
+/// `player_address`, amounts...) are written at the top level of each line.
+/// The spans are exported to the OTLP collector at `otlp_endpoint` if set.
+pub fn setup_tracing(log_format: LogFormat, otlp_endpoint: Option<&str>) -> anyhow::Result<()> {
+    let fmt_layer = tracing_subscriber::fmt::layer()
         .with_file(false)
         .with_line_number(false)
         .with_thread_ids(false)
-        .with_target(false)
+        .with_target(false);
+    let fmt_layer = match log_format {
+        LogFormat::Text => fmt_layer
+            .with_ansi(std::io::stdout().is_terminal())
+            .compact()
+            .boxed(),
+        LogFormat::Json => fmt_layer
+            .with_ansi(false)
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .with_span_list(false)
+            .boxed(),
+    };
+    let otel_layer = otlp_endpoint.map(telemetry::otel_layer).transpose()?;
+
+    tracing_subscriber::registry()
+        .with(LevelFilter::INFO)
+        .with(fmt_layer)
+        .with(otel_layer)
         .init();
+    Ok(())
 }
 
-pub async fn wait_for_tx(
+/// Returns the current unix timestamp in seconds.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Returns the receipt of the transaction if it was accepted, None if it is
+/// not known yet, or fails if it was reverted.
+pub async fn get_tx_receipt(
     rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
     tx_hash: Felt,
-) -> anyhow::Result<()> {
-    const WAIT_FOR_TX_TIMEOUT: Duration = Duration::from_secs(15);
-    const CHECK_INTERVAL: Duration = Duration::from_secs(1);
-
-    let start = SystemTime::now();
-
-    loop {
-        if start.elapsed().unwrap() >= WAIT_FOR_TX_TIMEOUT {
-            bail!("Timeout while waiting for transaction {tx_hash:#064x}");
-        }
-
-        match rpc_client.get_transaction_receipt(tx_hash).await {
-            Ok(tx) => match tx.receipt.execution_result() {
-                ExecutionResult::Succeeded => {
-                    return Ok(());
-                }
-                ExecutionResult::Reverted { reason } => {
-                    bail!(format!(
-                        "Transaction {tx_hash:#064x} has been rejected/reverted: {reason}"
-                    ));
-                }
-            },
-            Err(ProviderError::StarknetError(StarknetError::TransactionHashNotFound)) => {
-                tracing::debug!("Waiting for transaction {tx_hash:#064x} to show up");
-                tokio::time::sleep(CHECK_INTERVAL).await;
-            }
-            Err(err) => {
-                bail!("Error while waiting for transaction {tx_hash:#064x}: {err:?}");
+) -> anyhow::Result<Option<TransactionReceiptWithBlockInfo>> {
+    match rpc_client.get_transaction_receipt(tx_hash).await {
+        Ok(tx) => {
+            if let ExecutionResult::Reverted { reason } = tx.receipt.execution_result() {
+                bail!(format!(
+                    "Transaction {tx_hash:#064x} has been rejected/reverted: {reason}"
+                ));
             }
+            Ok(Some(tx))
+        }
+        Err(ProviderError::StarknetError(StarknetError::TransactionHashNotFound)) => {
+            tracing::debug!("Waiting for transaction {tx_hash:#064x} to show up");
+            Ok(None)
+        }
+        Err(err) => {
+            bail!("Error while waiting for transaction {tx_hash:#064x}: {err:?}");
         }
     }
 }
+
+/// Returns if the transaction was accepted on L1, i.e. its block was proven &
+/// settled on Ethereum.
+pub async fn is_accepted_on_l1(
+    rpc_client: &Arc<JsonRpcClient<HttpTransport>>,
+    tx_hash: Felt,
+) -> anyhow::Result<bool> {
+    Ok(matches!(
+        rpc_client.get_transaction_status(tx_hash).await?,
+        TransactionStatus::AcceptedOnL1(_)
+    ))
+}
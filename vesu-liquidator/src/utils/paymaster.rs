@@ -0,0 +1,190 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+use anyhow::{Result, anyhow};
+use cainome::cairo_serde::U256;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use starknet::core::types::{Call, Felt, TypedData};
+
+use crate::{
+    config::Config,
+    types::account::StarknetAccount,
+    utils::{conversions::u256_to_big_uint, unix_now},
+};
+
+/// Call in the format of the AVNU paymaster API.
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct PaymasterCall {
+    contract_address: String,
+    /// The entrypoint, given by its selector.
+    entrypoint: String,
+    calldata: Vec<String>,
+}
+
+impl From<&Call> for PaymasterCall {
+    fn from(call: &Call) -> Self {
+        Self {
+            contract_address: format!("{:#x}", call.to),
+            entrypoint: format!("{:#x}", call.selector),
+            calldata: call
+                .calldata
+                .iter()
+                .map(|felt| format!("{felt:#x}"))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PaymasterExecution {
+    transaction_hash: Felt,
+}
+
+/// Client of the AVNU paymaster, executing the transactions with their fees
+/// paid in the gas token, see [`crate::config::PaymasterConfig`]:
+/// 1. the paymaster builds the typed data of an outside execution of the
+///    calls, along with the transfer of the fees,
+/// 2. the account signs it,
+/// 3. the paymaster sends the transaction & pays its fees in STRK.
+#[derive(Clone)]
+pub struct PaymasterClient {
+    http_client: reqwest::Client,
+    api_url: String,
+    api_key: Option<String>,
+    gas_token: Felt,
+    max_gas_token_amount: Option<U256>,
+    retry_after_seconds: u64,
+    /// Time until which the paymaster is skipped after a failure.
+    unavailable_until: Arc<AtomicU64>,
+}
+
+impl PaymasterClient {
+    /// Returns the client of the paymaster, if enabled.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        if !config.paymaster.enabled {
+            return None;
+        }
+        // Validated to be an asset with the paymaster enabled.
+        let (gas_token, max_gas_token_amount) = config.paymaster_gas_token()?;
+        Some(Self::for_gas_token(config, gas_token, max_gas_token_amount))
+    }
+
+    /// Returns the client of the paymaster paying the fees in `gas_token`,
+    /// whatever the `paymaster.gas_token`, e.g. in ETH for
+    /// `transactions.fee_token`.
+    pub fn for_gas_token(
+        config: &Config,
+        gas_token: Felt,
+        max_gas_token_amount: Option<U256>,
+    ) -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            api_url: config.paymaster.api_url.trim_end_matches('/').to_string(),
+            api_key: config.paymaster.api_key.clone(),
+            gas_token,
+            max_gas_token_amount,
+            retry_after_seconds: config.paymaster.retry_after_seconds,
+            unavailable_until: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Returns if the paymaster is used, i.e. it didn't fail recently.
+    pub fn is_available(&self) -> bool {
+        unix_now() >= self.unavailable_until.load(Ordering::Relaxed)
+    }
+
+    /// Skips the paymaster for `retry_after_seconds`.
+    pub fn mark_unavailable(&self) {
+        self.unavailable_until
+            .store(unix_now() + self.retry_after_seconds, Ordering::Relaxed);
+    }
+
+    pub fn retry_after_seconds(&self) -> u64 {
+        self.retry_after_seconds
+    }
+
+    /// Sends the calls through the paymaster, returning the hash of the
+    /// transaction.
+    pub async fn execute(&self, account: &StarknetAccount, calls: &[Call]) -> Result<Felt> {
+        let user_address = format!("{:#x}", account.account_address());
+        let mut body = serde_json::json!({
+            "userAddress": user_address,
+            "calls": calls.iter().map(PaymasterCall::from).collect::<Vec<_>>(),
+            "gasTokenAddress": format!("{:#x}", self.gas_token),
+        });
+        if let Some(max_amount) = &self.max_gas_token_amount {
+            body["maxGasTokenAmount"] =
+                Value::String(format!("{:#x}", u256_to_big_uint(max_amount)));
+        }
+        let typed_data: Value = self
+            .request(self.http_client.post(self.endpoint("build-typed-data")))
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let signature = account
+            .sign_typed_data(&serde_json::from_value::<TypedData>(typed_data.clone())?)
+            .await?;
+
+        let execution: PaymasterExecution = self
+            .request(self.http_client.post(self.endpoint("execute")))
+            .json(&serde_json::json!({
+                "userAddress": user_address,
+                "typedData": serde_json::to_string(&typed_data)?,
+                "signature": signature
+                    .iter()
+                    .map(|felt| format!("{felt:#x}"))
+                    .collect::<Vec<_>>(),
+            }))
+            .send()
+            .await?
+            .error_for_status()
+            .map_err(|e| anyhow!("Paymaster execution failed: {e}"))?
+            .json()
+            .await?;
+        Ok(execution.transaction_hash)
+    }
+
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}/paymaster/v1/{path}", self.api_url)
+    }
+
+    fn request(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.api_key {
+            Some(api_key) => request.header("api-key", api_key),
+            None => request,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::{Call, Felt};
+
+    use super::PaymasterCall;
+
+    #[test]
+    fn test_paymaster_call() {
+        let call = Call {
+            to: Felt::from(0x1234),
+            selector: Felt::from(0xabc),
+            calldata: vec![Felt::ONE, Felt::from(255)],
+        };
+        assert_eq!(
+            PaymasterCall::from(&call),
+            PaymasterCall {
+                contract_address: "0x1234".to_string(),
+                entrypoint: "0xabc".to_string(),
+                calldata: vec!["0x1".to_string(), "0xff".to_string()],
+            }
+        );
+    }
+}
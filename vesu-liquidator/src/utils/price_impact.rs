@@ -0,0 +1,109 @@
+use anyhow::{Result, anyhow};
+use bigdecimal::{BigDecimal, RoundingMode, ToPrimitive, num_bigint::BigInt};
+use starknet::core::types::Felt;
+
+use crate::{
+    services::oracle::LatestOraclePrices,
+    types::position::Position,
+    utils::{
+        conversions::{to_token_amount, u256_to_big_uint},
+        errors::LiquidatorError,
+        swap::{BPS_DENOMINATOR, SwapProvider},
+    },
+};
+
+/// Returns the loss in basis points of receiving `quoted` instead of the
+/// `expected` amount at the oracle prices, 0 if the quote is better.
+pub fn price_impact_bps(expected: &BigDecimal, quoted: &BigDecimal) -> u32 {
+    if *expected <= BigDecimal::from(0) || quoted >= expected {
+        return 0;
+    }
+    let loss = (expected - quoted) * BigDecimal::from(BPS_DENOMINATOR) / expected;
+    loss.with_scale_round(0, RoundingMode::Up)
+        .to_u32()
+        .unwrap_or(BPS_DENOMINATOR)
+}
+
+/// Estimates the price impact of the liquidation of `debt_to_repay` of the
+/// position: the collateral worth the debt at the oracle prices is quoted
+/// back to the debt by each provider, the best quote being compared to the
+/// debt repaid.
+pub async fn estimate_liquidation_impact(
+    providers: &[Box<dyn SwapProvider>],
+    position: &Position,
+    debt_to_repay: &BigDecimal,
+    oracle_prices: &LatestOraclePrices,
+    taker: Felt,
+) -> Result<u32> {
+    let collateral_price = oracle_prices.fresh_price(&position.collateral.name.to_lowercase())?;
+    let debt_price = oracle_prices.fresh_price(&position.debt.name.to_lowercase())?;
+    if collateral_price <= BigDecimal::from(0) {
+        return Err(LiquidatorError::Math(
+            "Collateral price is zero. Can't estimate the price impact.".to_string(),
+        )
+        .into());
+    }
+    let collateral_sold = (debt_to_repay * debt_price / collateral_price)
+        .with_scale_round(position.collateral.decimals, RoundingMode::Down);
+    let sell_amount = to_token_amount(&collateral_sold, position.collateral.decimals);
+
+    let mut best_quote: Option<BigDecimal> = None;
+    for provider in providers.iter() {
+        match provider
+            .quote(
+                position.collateral.address,
+                position.debt.address,
+                sell_amount,
+                taker,
+            )
+            .await
+        {
+            Ok(buy_amount) => {
+                let quoted = BigDecimal::new(
+                    BigInt::from(u256_to_big_uint(&buy_amount)),
+                    position.debt.decimals,
+                );
+                if best_quote.as_ref().is_none_or(|best| quoted > *best) {
+                    best_quote = Some(quoted);
+                }
+            }
+            Err(e) => {
+                tracing::warn!(
+                    error = %e,
+                    position_key = ?position.key(),
+                    "[🔭 Monitoring] {} could not quote the collateral of position #{}",
+                    provider.name(),
+                    position.key()
+                );
+            }
+        }
+    }
+    let best_quote = best_quote.ok_or_else(|| {
+        anyhow!(
+            "No swap provider could quote {} to {}",
+            position.collateral.name,
+            position.debt.name
+        )
+    })?;
+    Ok(price_impact_bps(debt_to_repay, &best_quote))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+
+    use super::price_impact_bps;
+
+    #[test]
+    fn test_price_impact_bps() {
+        let amount = |value: &str| BigDecimal::from_str(value).unwrap();
+        assert_eq!(price_impact_bps(&amount("1000"), &amount("985")), 150);
+        // Rounded up, not to understate the impact.
+        assert_eq!(price_impact_bps(&amount("1000"), &amount("999.99")), 1);
+        assert_eq!(price_impact_bps(&amount("1000"), &amount("1010")), 0);
+        assert_eq!(price_impact_bps(&amount("1000"), &amount("0")), 10_000);
+        assert_eq!(price_impact_bps(&amount("0"), &amount("5")), 0);
+    }
+}
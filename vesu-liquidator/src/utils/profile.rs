@@ -0,0 +1,133 @@
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Phases of a check of the positions, timed by the [`TickProfiler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickPhase {
+    /// Refresh of the positions from the chain.
+    Refresh,
+    /// Evaluation of the health factors & liquidability of the positions.
+    IsLiquidable,
+    /// Liquidation of the liquidable positions, until their receipt.
+    Submit,
+}
+
+/// Time spent in each phase of a check, & what it went through.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TickTimings {
+    pub refresh: Duration,
+    pub is_liquidable: Duration,
+    pub submit: Duration,
+    pub positions: usize,
+    pub liquidations: usize,
+}
+
+impl TickTimings {
+    fn record(&mut self, phase: TickPhase, elapsed: Duration) {
+        match phase {
+            TickPhase::Refresh => self.refresh += elapsed,
+            TickPhase::IsLiquidable => {
+                self.is_liquidable += elapsed;
+                self.positions += 1;
+            }
+            TickPhase::Submit => {
+                self.submit += elapsed;
+                self.liquidations += 1;
+            }
+        }
+    }
+}
+
+/// Records the timings of the checks of the monitoring service when the bot
+/// runs with `--profile`, logging their breakdown at the end of each check.
+/// Disabled, it only runs the timed futures.
+#[derive(Clone, Default)]
+pub struct TickProfiler {
+    enabled: bool,
+    current: Arc<Mutex<TickTimings>>,
+}
+
+impl TickProfiler {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            current: Arc::default(),
+        }
+    }
+
+    /// Runs `future`, adding its duration to the `phase` of the current check.
+    pub async fn time<F: Future>(&self, phase: TickPhase, future: F) -> F::Output {
+        if !self.enabled {
+            return future.await;
+        }
+        let started_at = Instant::now();
+        let output = future.await;
+        self.current
+            .lock()
+            .expect("profiler lock poisoned")
+            .record(phase, started_at.elapsed());
+        output
+    }
+
+    /// Ends the current check, logging & returning its timings. `None` when
+    /// profiling is disabled.
+    pub fn finish(&self, check: &str, started_at: Instant) -> Option<TickTimings> {
+        if !self.enabled {
+            return None;
+        }
+        let timings = std::mem::take(&mut *self.current.lock().expect("profiler lock poisoned"));
+        let total = started_at.elapsed();
+        tracing::info!(
+            check,
+            total_ms = total.as_millis() as u64,
+            refresh_ms = timings.refresh.as_millis() as u64,
+            is_liquidable_ms = timings.is_liquidable.as_millis() as u64,
+            submit_ms = timings.submit.as_millis() as u64,
+            positions = timings.positions,
+            liquidations = timings.liquidations,
+            "[⏱️ Profile] {} in {:?}: refresh {:?}, is_liquidable {:?} ({} positions), submit {:?} ({} liquidations)",
+            check,
+            total,
+            timings.refresh,
+            timings.is_liquidable,
+            timings.positions,
+            timings.submit,
+            timings.liquidations
+        );
+        Some(timings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{TickPhase, TickProfiler};
+
+    #[tokio::test]
+    async fn test_tick_profiler() {
+        let disabled = TickProfiler::default();
+        assert_eq!(disabled.time(TickPhase::Refresh, async { 1 }).await, 1);
+        assert_eq!(disabled.finish("full sweep", Instant::now()), None);
+
+        let profiler = TickProfiler::new(true);
+        let sleep = |ms| tokio::time::sleep(Duration::from_millis(ms));
+        profiler.time(TickPhase::Refresh, sleep(5)).await;
+        profiler.time(TickPhase::IsLiquidable, async {}).await;
+        profiler.time(TickPhase::IsLiquidable, async {}).await;
+        profiler.time(TickPhase::Submit, sleep(2)).await;
+
+        let timings = profiler.finish("full sweep", Instant::now()).unwrap();
+        assert!(timings.refresh >= Duration::from_millis(5));
+        assert!(timings.submit >= Duration::from_millis(2));
+        assert_eq!((timings.positions, timings.liquidations), (2, 1));
+
+        // The next check starts from scratch.
+        let timings = profiler.finish("price move", Instant::now()).unwrap();
+        assert_eq!(timings.positions, 0);
+        assert_eq!(timings.refresh, Duration::ZERO);
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use starknet::{
+    core::types::{
+        BlockId, BlockTag, Felt, FunctionCall, InvokeTransaction,
+        MaybePreConfirmedBlockWithTxHashes, StarknetError, Transaction, requests::CallRequest,
+    },
+    providers::{
+        JsonRpcClient, Provider, ProviderError, ProviderRequestData, ProviderResponseData,
+        jsonrpc::HttpTransport,
+    },
+};
+
+/// Reads of the chain used to follow the positions, implemented by the JSON
+/// RPC client & mocked in the tests of the services. The transactions are
+/// sent by the accounts, see [`crate::utils::tx_manager::TxManager`].
+#[async_trait::async_trait]
+pub trait StarknetRpc: Send + Sync {
+    /// Calls a view function at the pre-confirmed block.
+    async fn call(&self, request: FunctionCall) -> Result<Vec<Felt>>;
+
+    /// Calls the view functions at the pre-confirmed block in a single batch
+    /// of requests, returning their results in order.
+    async fn batch_call(&self, requests: Vec<FunctionCall>) -> Result<Vec<Vec<Felt>>>;
+
+    /// Returns the hash of the accepted block `number`, `None` if there is no
+    /// such block (yet).
+    async fn block_hash(&self, number: u64) -> Result<Option<Felt>>;
+
+    /// Returns the account that sent the invoke transaction `tx_hash`, `None`
+    /// if there is no such transaction (yet) or if it is not an invoke.
+    async fn transaction_sender(&self, tx_hash: Felt) -> Result<Option<Felt>>;
+}
+
+#[async_trait::async_trait]
+impl StarknetRpc for JsonRpcClient<HttpTransport> {
+    async fn call(&self, request: FunctionCall) -> Result<Vec<Felt>> {
+        Ok(Provider::call(self, request, BlockId::Tag(BlockTag::PreConfirmed)).await?)
+    }
+
+    async fn batch_call(&self, requests: Vec<FunctionCall>) -> Result<Vec<Vec<Felt>>> {
+        batch_call_at(self, requests, BlockId::Tag(BlockTag::PreConfirmed)).await
+    }
+
+    async fn block_hash(&self, number: u64) -> Result<Option<Felt>> {
+        match self.get_block_with_tx_hashes(BlockId::Number(number)).await {
+            Ok(MaybePreConfirmedBlockWithTxHashes::Block(block)) => Ok(Some(block.block_hash)),
+            Ok(_) | Err(ProviderError::StarknetError(StarknetError::BlockNotFound)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn transaction_sender(&self, tx_hash: Felt) -> Result<Option<Felt>> {
+        match self.get_transaction_by_hash(tx_hash).await {
+            Ok(Transaction::Invoke(InvokeTransaction::V0(tx))) => Ok(Some(tx.contract_address)),
+            Ok(Transaction::Invoke(InvokeTransaction::V1(tx))) => Ok(Some(tx.sender_address)),
+            Ok(Transaction::Invoke(InvokeTransaction::V3(tx))) => Ok(Some(tx.sender_address)),
+            Ok(_) | Err(ProviderError::StarknetError(StarknetError::TransactionHashNotFound)) => {
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Reads of the chain as of a given block, e.g. the last accepted one when
+/// the positions are not read at the pre-confirmed block (see
+/// [`crate::config::MonitoringConfig::positions_block_id`]) or a past one to
+/// replay the history of the positions in a backtest.
+pub struct BlockRpc {
+    client: Arc<JsonRpcClient<HttpTransport>>,
+    block_id: BlockId,
+}
+
+impl BlockRpc {
+    pub fn new(client: Arc<JsonRpcClient<HttpTransport>>, block_id: BlockId) -> Self {
+        Self { client, block_id }
+    }
+}
+
+#[async_trait::async_trait]
+impl StarknetRpc for BlockRpc {
+    async fn call(&self, request: FunctionCall) -> Result<Vec<Felt>> {
+        Ok(Provider::call(self.client.as_ref(), request, self.block_id).await?)
+    }
+
+    async fn batch_call(&self, requests: Vec<FunctionCall>) -> Result<Vec<Vec<Felt>>> {
+        batch_call_at(&self.client, requests, self.block_id).await
+    }
+
+    async fn block_hash(&self, number: u64) -> Result<Option<Felt>> {
+        self.client.block_hash(number).await
+    }
+
+    async fn transaction_sender(&self, tx_hash: Felt) -> Result<Option<Felt>> {
+        self.client.transaction_sender(tx_hash).await
+    }
+}
+
+/// Calls the view functions at `block_id` in a single batch of requests,
+/// returning their results in order.
+async fn batch_call_at(
+    client: &JsonRpcClient<HttpTransport>,
+    requests: Vec<FunctionCall>,
+    block_id: BlockId,
+) -> Result<Vec<Vec<Felt>>> {
+    let requests: Vec<ProviderRequestData> = requests
+        .into_iter()
+        .map(|request| ProviderRequestData::Call(CallRequest { request, block_id }))
+        .collect();
+    let responses = client.batch_requests(&requests).await?;
+    anyhow::ensure!(
+        responses.len() == requests.len(),
+        "Expected {} responses to the batch, got {}",
+        requests.len(),
+        responses.len()
+    );
+    responses
+        .into_iter()
+        .map(|response| match response {
+            ProviderResponseData::Call(result) => Ok(result),
+            _ => Err(anyhow::anyhow!(
+                "Unexpected responses to the batch of calls"
+            )),
+        })
+        .collect()
+}
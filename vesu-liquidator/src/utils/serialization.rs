@@ -0,0 +1,74 @@
+//! Serializers making the exported artifacts (storage, audit log, reports)
+//! byte-for-byte reproducible across machines.
+
+use std::collections::{BTreeMap, HashMap};
+
+use bigdecimal::BigDecimal;
+use serde::{Serialize, Serializer};
+
+/// Serializes a decimal in plain notation. The default `Display` switches to
+/// the scientific notation past thresholds set at build time, so the same
+/// value could be written differently by two builds.
+pub fn plain_decimal<S: Serializer>(value: &BigDecimal, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&value.to_plain_string())
+}
+
+/// Serializes an optional decimal in plain notation, see [`plain_decimal`].
+pub fn optional_plain_decimal<S: Serializer>(
+    value: &Option<BigDecimal>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => plain_decimal(value, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Serializes a map with its keys sorted instead of in hash order.
+pub fn sorted_map<K, V, S>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    K: Ord + Serialize,
+    V: Serialize,
+    S: Serializer,
+{
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, str::FromStr};
+
+    use bigdecimal::BigDecimal;
+    use serde::Serialize;
+
+    use super::{plain_decimal, sorted_map};
+
+    #[derive(Serialize)]
+    struct Report {
+        #[serde(serialize_with = "plain_decimal")]
+        amount: BigDecimal,
+        #[serde(serialize_with = "sorted_map")]
+        by_key: HashMap<u64, &'static str>,
+    }
+
+    #[test]
+    fn test_deterministic_serialization() {
+        let report = Report {
+            amount: BigDecimal::from_str("1e21").unwrap(),
+            by_key: HashMap::from([(10, "c"), (2, "b"), (1, "a")]),
+        };
+        assert_eq!(
+            serde_json::to_string(&report).unwrap(),
+            r#"{"amount":"1000000000000000000000","by_key":{"1":"a","2":"b","10":"c"}}"#
+        );
+
+        let small = Report {
+            amount: BigDecimal::from_str("0.00000001").unwrap(),
+            by_key: HashMap::new(),
+        };
+        assert_eq!(
+            serde_json::to_string(&small).unwrap(),
+            r#"{"amount":"0.00000001","by_key":{}}"#
+        );
+    }
+}
@@ -1,6 +1,15 @@
-use anyhow::Context;
-use std::panic;
-use tokio::task::JoinSet;
+use anyhow::{Context, anyhow};
+use std::{
+    panic,
+    time::{Duration, Instant},
+};
+use tokio::{task::JoinSet, time::sleep};
+
+use crate::{config::SupervisorConfig, utils::shutdown::Shutdown};
+
+/// A service running for longer than this is considered healthy again, so its
+/// consecutive failures counter is reset.
+const HEALTHY_RUN_DURATION: Duration = Duration::from_secs(60);
 
 /// Source:
 /// https://github.com/madara-alliance/madara/blob/main/crates/primitives/utils/src/service.rs
@@ -8,18 +17,25 @@ use tokio::task::JoinSet;
 /// The app is divided into services, with each service having a different responsability within the app.
 ///
 /// This trait enables launching nested services and groups.
+///
+/// Every service receives the [`Shutdown`] signal and must return from its
+/// task once it is triggered, so the bot can exit cleanly.
 #[async_trait::async_trait]
 pub trait Service: 'static + Send + Sync {
-    async fn start(&mut self, _join_set: &mut JoinSet<anyhow::Result<()>>) -> anyhow::Result<()> {
+    async fn start(
+        &mut self,
+        _join_set: &mut JoinSet<anyhow::Result<()>>,
+        _shutdown: Shutdown,
+    ) -> anyhow::Result<()> {
         Ok(())
     }
 
-    async fn start_and_drive_to_end(mut self) -> anyhow::Result<()>
+    async fn start_and_drive_to_end(mut self, shutdown: Shutdown) -> anyhow::Result<()>
     where
         Self: Sized,
     {
         let mut join_set = JoinSet::new();
-        self.start(&mut join_set)
+        self.start(&mut join_set, shutdown)
             .await
             .context("Starting service")?;
         drive_joinset(join_set).await
@@ -60,18 +76,32 @@ impl ServiceGroup {
         self.push(value);
         self
     }
+
+    /// Add a new service to the service group, restarted by a [`Supervisor`] when it fails.
+    pub fn with_supervised(
+        self,
+        name: impl Into<String>,
+        value: impl Service + Clone,
+        config: &SupervisorConfig,
+    ) -> Self {
+        self.with(Supervisor::new(name.into(), value, config.clone()))
+    }
 }
 
 #[async_trait::async_trait]
 impl Service for ServiceGroup {
-    async fn start(&mut self, join_set: &mut JoinSet<anyhow::Result<()>>) -> anyhow::Result<()> {
+    async fn start(
+        &mut self,
+        join_set: &mut JoinSet<anyhow::Result<()>>,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<()> {
         // drive the join set as a nested task
         let mut own_join_set = self
             .join_set
             .take()
             .expect("Service has already been started.");
         for svc in self.services.iter_mut() {
-            svc.start(&mut own_join_set)
+            svc.start(&mut own_join_set, shutdown.clone())
                 .await
                 .context("Starting service")?;
         }
@@ -81,6 +111,114 @@ impl Service for ServiceGroup {
     }
 }
 
+/// Restarts the supervised service with an exponential backoff when it returns
+/// an error or panics. Only gives up after `max_consecutive_failures` failures
+/// in a row, bubbling up the last error.
+pub struct Supervisor<S> {
+    name: String,
+    service: S,
+    config: SupervisorConfig,
+}
+
+impl<S: Service + Clone> Supervisor<S> {
+    pub fn new(name: String, service: S, config: SupervisorConfig) -> Self {
+        Self {
+            name,
+            service,
+            config,
+        }
+    }
+
+    async fn supervise(self, shutdown: Shutdown) -> anyhow::Result<()> {
+        let mut backoff = RestartBackoff::new(&self.config);
+
+        loop {
+            let started_at = Instant::now();
+            let run = tokio::spawn(
+                self.service
+                    .clone()
+                    .start_and_drive_to_end(shutdown.clone()),
+            );
+            let error = match run.await {
+                Ok(Ok(())) => return Ok(()),
+                Ok(Err(e)) => e,
+                Err(join_error) => anyhow!("{} service panicked: {join_error}", self.name),
+            };
+
+            if shutdown.is_triggered() {
+                return Err(error);
+            }
+            let (consecutive_failures, delay) = backoff.record_failure(started_at.elapsed());
+            if consecutive_failures >= self.config.max_consecutive_failures {
+                return Err(error.context(format!(
+                    "{} service failed {consecutive_failures} times in a row",
+                    self.name
+                )));
+            }
+
+            tracing::error!(
+                error = format!("{error:#}"),
+                "[🩺 Supervisor] {} service failed ({consecutive_failures}/{}), restarting in {delay:?}",
+                self.name,
+                self.config.max_consecutive_failures,
+            );
+            tokio::select! {
+                _ = shutdown.wait() => return Ok(()),
+                _ = sleep(delay) => {}
+            }
+        }
+    }
+}
+
+/// Consecutive failures of a supervised service & the delay before its next
+/// restart, doubled on every failure up to `max_backoff_ms`.
+struct RestartBackoff {
+    initial: Duration,
+    max: Duration,
+    consecutive_failures: u32,
+    next_delay: Duration,
+}
+
+impl RestartBackoff {
+    fn new(config: &SupervisorConfig) -> Self {
+        let initial = Duration::from_millis(config.initial_backoff_ms);
+        Self {
+            initial,
+            max: Duration::from_millis(config.max_backoff_ms),
+            consecutive_failures: 0,
+            next_delay: initial,
+        }
+    }
+
+    /// Records a failure after a run of `run_duration`, returning the
+    /// consecutive failures & the delay before the restart. A run longer than
+    /// [`HEALTHY_RUN_DURATION`] starts over from the initial backoff.
+    fn record_failure(&mut self, run_duration: Duration) -> (u32, Duration) {
+        if run_duration >= HEALTHY_RUN_DURATION {
+            self.consecutive_failures = 0;
+            self.next_delay = self.initial;
+        }
+        self.consecutive_failures += 1;
+        let delay = self.next_delay;
+        self.next_delay = (self.next_delay * 2).min(self.max);
+        (self.consecutive_failures, delay)
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: Service + Clone> Service for Supervisor<S> {
+    async fn start(
+        &mut self,
+        join_set: &mut JoinSet<anyhow::Result<()>>,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<()> {
+        let supervisor =
+            Supervisor::new(self.name.clone(), self.service.clone(), self.config.clone());
+        join_set.spawn(supervisor.supervise(shutdown));
+        Ok(())
+    }
+}
+
 async fn drive_joinset(mut join_set: JoinSet<anyhow::Result<()>>) -> anyhow::Result<()> {
     while let Some(result) = join_set.join_next().await {
         match result {
@@ -95,3 +233,111 @@ async fn drive_joinset(mut join_set: JoinSet<anyhow::Result<()>>) -> anyhow::Res
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicU32, Ordering},
+        },
+        time::Duration,
+    };
+
+    use anyhow::bail;
+    use tokio::task::JoinSet;
+
+    use crate::{config::SupervisorConfig, utils::shutdown::Shutdown};
+
+    use super::{HEALTHY_RUN_DURATION, RestartBackoff, Service, Supervisor};
+
+    /// Service failing on its first `failures` runs, then returning.
+    #[derive(Clone)]
+    struct FlakyService {
+        runs: Arc<AtomicU32>,
+        failures: u32,
+    }
+
+    #[async_trait::async_trait]
+    impl Service for FlakyService {
+        async fn start(
+            &mut self,
+            join_set: &mut JoinSet<anyhow::Result<()>>,
+            _shutdown: Shutdown,
+        ) -> anyhow::Result<()> {
+            let run = self.runs.fetch_add(1, Ordering::Relaxed) + 1;
+            let failures = self.failures;
+            join_set.spawn(async move {
+                if run <= failures {
+                    bail!("run #{run} failed");
+                }
+                Ok(())
+            });
+            Ok(())
+        }
+    }
+
+    fn config(max_consecutive_failures: u32) -> SupervisorConfig {
+        SupervisorConfig {
+            max_consecutive_failures,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 4,
+        }
+    }
+
+    #[test]
+    fn test_restart_backoff() {
+        let mut backoff = RestartBackoff::new(&config(10));
+        let quick_run = Duration::from_millis(10);
+        let delays: Vec<_> = (0..5).map(|_| backoff.record_failure(quick_run)).collect();
+        assert_eq!(
+            delays,
+            vec![
+                (1, Duration::from_millis(1)),
+                (2, Duration::from_millis(2)),
+                (3, Duration::from_millis(4)),
+                // Capped at max_backoff_ms.
+                (4, Duration::from_millis(4)),
+                (5, Duration::from_millis(4)),
+            ]
+        );
+        // A healthy run starts over.
+        assert_eq!(
+            backoff.record_failure(HEALTHY_RUN_DURATION),
+            (1, Duration::from_millis(1))
+        );
+        assert_eq!(
+            backoff.record_failure(quick_run),
+            (2, Duration::from_millis(2))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_restarts_the_failing_service() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let service = FlakyService {
+            runs: runs.clone(),
+            failures: 2,
+        };
+        Supervisor::new("flaky".to_string(), service, config(3))
+            .supervise(Shutdown::default())
+            .await
+            .unwrap();
+        assert_eq!(runs.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_gives_up_after_max_failures() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let service = FlakyService {
+            runs: runs.clone(),
+            failures: u32::MAX,
+        };
+        let error = Supervisor::new("broken".to_string(), service, config(3))
+            .supervise(Shutdown::default())
+            .await
+            .unwrap_err();
+        assert_eq!(runs.load(Ordering::Relaxed), 3);
+        assert!(format!("{error:#}").contains("broken service failed 3 times in a row"));
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use tokio::sync::{Notify, watch};
+
+/// Shutdown signal broadcasted to every service of the bot.
+///
+/// Services receive a clone when they are started and are expected to stop
+/// their loop (after finishing any in-flight work) once it is triggered.
+#[derive(Clone)]
+pub struct Shutdown(Arc<watch::Sender<bool>>);
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        let (sender, _) = watch::channel(false);
+        Self(Arc::new(sender))
+    }
+}
+
+impl Shutdown {
+    /// Notifies all the listeners that the bot is shutting down.
+    pub fn trigger(&self) {
+        self.0.send_replace(true);
+    }
+
+    /// Returns true if the shutdown has been requested.
+    pub fn is_triggered(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Waits until the shutdown is requested.
+    pub async fn wait(&self) {
+        let mut receiver = self.0.subscribe();
+        // The sender lives as long as `self`, so this can't fail.
+        let _ = receiver.wait_for(|triggered| *triggered).await;
+    }
+}
+
+/// Shutdown requested by the platform service manager, see [`request_shutdown`].
+static SERVICE_STOP: Notify = Notify::const_new();
+
+/// Requests the bot to shut down on behalf of the platform service manager,
+/// e.g. when the Windows service is stopped.
+pub fn request_shutdown() {
+    SERVICE_STOP.notify_one();
+}
+
+/// Waits for the process to be asked to stop, whatever the platform:
+/// - on Unix (incl. launchd & systemd): SIGINT or SIGTERM, SIGHUP reloading
+///   the config instead, see [`ReloadSignal`],
+/// - on Windows: Ctrl+C, Ctrl+Break, console close, logoff or system shutdown,
+/// - everywhere: a stop from the service manager, see [`request_shutdown`].
+pub async fn wait_for_os_signal() -> anyhow::Result<()> {
+    tokio::select! {
+        res = wait_for_platform_signal() => res,
+        _ = SERVICE_STOP.notified() => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_platform_signal() -> anyhow::Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut sigterm = signal(SignalKind::terminate())?;
+    tokio::select! {
+        res = tokio::signal::ctrl_c() => res?,
+        _ = sigterm.recv() => {}
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+async fn wait_for_platform_signal() -> anyhow::Result<()> {
+    use tokio::signal::windows::{ctrl_break, ctrl_close, ctrl_logoff, ctrl_shutdown};
+
+    let mut ctrl_break = ctrl_break()?;
+    let mut ctrl_close = ctrl_close()?;
+    let mut ctrl_logoff = ctrl_logoff()?;
+    let mut ctrl_shutdown = ctrl_shutdown()?;
+    tokio::select! {
+        res = tokio::signal::ctrl_c() => res?,
+        _ = ctrl_break.recv() => {}
+        _ = ctrl_close.recv() => {}
+        _ = ctrl_logoff.recv() => {}
+        _ = ctrl_shutdown.recv() => {}
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+async fn wait_for_platform_signal() -> anyhow::Result<()> {
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
+
+/// Signal asking the bot to reload its config: SIGHUP on Unix. It is never
+/// received on the other platforms, where the config is reloaded by the API.
+pub struct ReloadSignal {
+    #[cfg(unix)]
+    sighup: tokio::signal::unix::Signal,
+}
+
+impl ReloadSignal {
+    #[cfg(unix)]
+    pub fn new() -> anyhow::Result<Self> {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        Ok(Self {
+            sighup: signal(SignalKind::hangup())?,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn new() -> anyhow::Result<Self> {
+        Ok(Self {})
+    }
+
+    /// Waits until a reload is requested.
+    pub async fn recv(&mut self) {
+        #[cfg(unix)]
+        if self.sighup.recv().await.is_some() {
+            return;
+        }
+        std::future::pending::<()>().await
+    }
+}
@@ -0,0 +1,225 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{config::SloConfig, services::notifier::Notifier, types::notification::Severity};
+
+/// Minimum number of events in the window before alerting, so a single slow
+/// liquidation doesn't page anyone.
+const MIN_EVENTS_FOR_ALERT: usize = 5;
+
+/// A latency objective: a share `target` of the events must be faster than `threshold`.
+struct Objective {
+    name: &'static str,
+    threshold: Duration,
+    /// Time of each event in the window & whether it met the threshold.
+    events: VecDeque<(Instant, bool)>,
+    alerting: bool,
+}
+
+impl Objective {
+    fn new(name: &'static str, threshold: Duration) -> Self {
+        Self {
+            name,
+            threshold,
+            events: VecDeque::new(),
+            alerting: false,
+        }
+    }
+
+    fn record(&mut self, latency: Duration, now: Instant, window: Duration) {
+        self.events.push_back((now, latency <= self.threshold));
+        while let Some((at, _)) = self.events.front() {
+            if now.duration_since(*at) <= window {
+                break;
+            }
+            self.events.pop_front();
+        }
+    }
+
+    /// Rate at which the error budget is consumed: 1.0 means the budget is
+    /// exactly exhausted at the end of the window.
+    fn burn_rate(&self, target: f64) -> f64 {
+        if self.events.is_empty() {
+            return 0.0;
+        }
+        let bad = self.events.iter().filter(|(_, good)| !good).count();
+        let error_rate = bad as f64 / self.events.len() as f64;
+        let error_budget = (1.0 - target).max(f64::EPSILON);
+        error_rate / error_budget
+    }
+}
+
+/// Tracks the end-to-end latencies of the pipeline against the configured
+/// SLOs & alerts through the notifier when the error budget burns too fast.
+#[derive(Clone)]
+pub struct SloTracker {
+    config: SloConfig,
+    liquidations: Arc<Mutex<Objective>>,
+    payouts: Arc<Mutex<Objective>>,
+    notifier: Notifier,
+}
+
+impl SloTracker {
+    pub fn new(config: SloConfig, notifier: Notifier) -> Self {
+        Self {
+            liquidations: Arc::new(Mutex::new(Objective::new(
+                "liquidations",
+                Duration::from_secs(config.liquidation_latency_seconds),
+            ))),
+            payouts: Arc::new(Mutex::new(Objective::new(
+                "payouts",
+                Duration::from_secs(config.payout_latency_seconds),
+            ))),
+            config,
+            notifier,
+        }
+    }
+
+    /// Records the time between a position becoming liquidable & its liquidation.
+    pub fn record_liquidation(&self, latency: Duration) {
+        self.record(&self.liquidations, latency);
+    }
+
+    /// Records the time between a liquidation & the payout of its earnings.
+    pub fn record_payout(&self, latency: Duration) {
+        self.record(&self.payouts, latency);
+    }
+
+    fn record(&self, objective: &Mutex<Objective>, latency: Duration) {
+        let mut objective = objective.lock().expect("SLO objective lock poisoned");
+        objective.record(
+            latency,
+            Instant::now(),
+            Duration::from_secs(self.config.window_seconds),
+        );
+
+        let burn_rate = objective.burn_rate(self.config.target);
+        tracing::debug!(
+            "[🎯 SLO] {} latency {latency:?}, burn rate {burn_rate:.2}",
+            objective.name
+        );
+
+        let burning = objective.events.len() >= MIN_EVENTS_FOR_ALERT
+            && burn_rate >= self.config.burn_rate_alert;
+        if burning && !objective.alerting {
+            objective.alerting = true;
+            self.notifier.notify(
+                Severity::Warning,
+                format!(
+                    "SLO at risk: less than {:.1}% of {} within {:?} (burn rate {burn_rate:.2})",
+                    self.config.target * 100.0,
+                    objective.name,
+                    objective.threshold
+                ),
+            );
+        } else if !burning && objective.alerting {
+            objective.alerting = false;
+            self.notifier.notify(
+                Severity::Info,
+                format!("SLO of {} is back on track", objective.name),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::{MIN_EVENTS_FOR_ALERT, Objective, SloTracker};
+    use crate::{config::SloConfig, services::notifier::Notifier, types::notification::Severity};
+
+    #[test]
+    fn test_burn_rate() {
+        let window = Duration::from_secs(60);
+        let start = Instant::now();
+        let mut objective = Objective::new("liquidations", Duration::from_secs(10));
+        assert_eq!(objective.burn_rate(0.95), 0.0);
+
+        for i in 0..9 {
+            objective.record(
+                Duration::from_secs(1),
+                start + Duration::from_secs(i),
+                window,
+            );
+        }
+        objective.record(
+            Duration::from_secs(30),
+            start + Duration::from_secs(9),
+            window,
+        );
+        // 10% of errors for a 5% budget.
+        assert!((objective.burn_rate(0.95) - 2.0).abs() < 1e-9);
+
+        // Old events leave the window.
+        objective.record(
+            Duration::from_secs(1),
+            start + Duration::from_secs(68),
+            window,
+        );
+        assert_eq!(objective.events.len(), 3);
+        assert!((objective.burn_rate(0.95) - 20.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_tracker_alerts_once_then_recovers() {
+        let (sender, mut receiver) = unbounded_channel();
+        let tracker = SloTracker::new(
+            SloConfig {
+                liquidation_latency_seconds: 10,
+                target: 0.5,
+                burn_rate_alert: 1.5,
+                ..Default::default()
+            },
+            Notifier::new(sender),
+        );
+        let (fast, slow) = (Duration::from_secs(1), Duration::from_secs(30));
+
+        // Burning, but not enough events to alert yet.
+        for _ in 0..MIN_EVENTS_FOR_ALERT - 1 {
+            tracker.record_liquidation(slow);
+        }
+        assert!(receiver.try_recv().is_err());
+
+        tracker.record_liquidation(slow);
+        let alert = receiver.try_recv().unwrap();
+        assert_eq!(alert.severity, Severity::Warning);
+        assert!(alert.message.contains("liquidations"));
+
+        // Still burning: no new alert.
+        tracker.record_liquidation(slow);
+        assert!(receiver.try_recv().is_err());
+
+        // 6 slow out of 9 events: burn rate 1.33, back under the alert.
+        for _ in 0..2 {
+            tracker.record_liquidation(fast);
+            assert!(receiver.try_recv().is_err());
+        }
+        tracker.record_liquidation(fast);
+        let recovery = receiver.try_recv().unwrap();
+        assert_eq!(recovery.severity, Severity::Info);
+        assert_eq!(recovery.message, "SLO of liquidations is back on track");
+
+        tracker.record_liquidation(fast);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_tracker_objectives_are_independent() {
+        let (sender, mut receiver) = unbounded_channel();
+        let tracker = SloTracker::new(SloConfig::default(), Notifier::new(sender));
+        for _ in 0..MIN_EVENTS_FOR_ALERT {
+            tracker.record_liquidation(Duration::from_secs(3600));
+            tracker.record_payout(Duration::from_secs(1));
+        }
+        let alert = receiver.try_recv().unwrap();
+        assert!(alert.message.contains("liquidations"));
+        assert!(receiver.try_recv().is_err());
+        assert!(!tracker.payouts.lock().unwrap().alerting);
+    }
+}
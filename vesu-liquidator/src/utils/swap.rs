@@ -0,0 +1,258 @@
+use anyhow::{Result, anyhow};
+use cainome::cairo_serde::{CairoSerde, U256};
+use starknet::core::types::{Call, Felt};
+
+use crate::{
+    bindings::liquidate::Swap,
+    config::{
+        Config, EKUBO_CLEAR_MINIMUM_SELECTOR, EKUBO_CLEAR_SELECTOR,
+        EKUBO_MULTI_MULTIHOP_SWAP_SELECTOR, SwapProviderKind, TRANSFER_SELECTOR,
+    },
+    utils::{
+        avnu::AvnuClient,
+        constants::U256_ZERO,
+        conversions::{big_uint_to_u256, u256_to_big_uint},
+        ekubo::get_ekubo_exact_input_route,
+        http::HttpClient,
+    },
+};
+
+pub const BPS_DENOMINATOR: u32 = 10_000;
+
+/// A swap ready to be sent: its calls, approval included, & the amounts of
+/// the bought token.
+#[derive(Debug, Clone)]
+pub struct SwapQuote {
+    pub calls: Vec<Call>,
+    /// Amount quoted by the provider.
+    pub buy_amount: U256,
+    /// Amount received at worst, the swap reverting below it.
+    pub min_buy_amount: U256,
+    /// Loss of the swap in basis points compared to the market price, if
+    /// known by the provider.
+    pub price_impact_bps: Option<u32>,
+}
+
+/// Quotes & builds the swaps of the earnings to the payout token, see
+/// `swap.providers`.
+#[async_trait::async_trait]
+pub trait SwapProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Quotes the amount of `buy_token` received for `sell_amount` of
+    /// `sell_token` by the `taker` account, without building the swap.
+    async fn quote(
+        &self,
+        sell_token: Felt,
+        buy_token: Felt,
+        sell_amount: U256,
+        taker: Felt,
+    ) -> Result<U256>;
+
+    /// Quotes the swap of `sell_amount` of `sell_token` to `buy_token` by the
+    /// `taker` account & builds its calls, reverting if it receives less than
+    /// the quote minus `max_slippage_bps`.
+    async fn build_swap(
+        &self,
+        sell_token: Felt,
+        buy_token: Felt,
+        sell_amount: U256,
+        taker: Felt,
+        max_slippage_bps: u16,
+    ) -> Result<SwapQuote>;
+}
+
+/// Returns the providers of `swap.providers`, in order of preference.
+pub fn swap_providers(config: &Config, http_client: &HttpClient) -> Vec<Box<dyn SwapProvider>> {
+    config
+        .swap
+        .providers
+        .iter()
+        .map(|kind| -> Box<dyn SwapProvider> {
+            match kind {
+                SwapProviderKind::Avnu => Box::new(AvnuClient::new(
+                    http_client.clone(),
+                    config.swap.avnu_api_url.clone(),
+                )),
+                SwapProviderKind::Ekubo => Box::new(EkuboSwapProvider {
+                    http_client: http_client.clone(),
+                    router_address: config.ekubo_router_address,
+                    quoter_url: config.swap.ekubo_quoter_url.clone(),
+                }),
+            }
+        })
+        .collect()
+}
+
+/// Returns the swap of the first provider able to quote it with a price
+/// impact below `max_price_impact_bps`, with the name of the provider.
+pub async fn quote_swap(
+    providers: &[Box<dyn SwapProvider>],
+    sell_token: Felt,
+    buy_token: Felt,
+    sell_amount: U256,
+    taker: Felt,
+    config: &Config,
+) -> Result<(&'static str, SwapQuote)> {
+    for provider in providers.iter() {
+        let quote = provider
+            .build_swap(
+                sell_token,
+                buy_token,
+                sell_amount,
+                taker,
+                config.swap.max_slippage_bps,
+            )
+            .await;
+        match quote {
+            Ok(quote)
+                if quote
+                    .price_impact_bps
+                    .is_some_and(|impact| impact > u32::from(config.swap.max_price_impact_bps)) =>
+            {
+                tracing::warn!(
+                    "[💸 Distribution] {} quote of {:#x} to {:#x} is too wide ({} bps), trying the next provider",
+                    provider.name(),
+                    sell_token,
+                    buy_token,
+                    quote.price_impact_bps.unwrap_or_default()
+                );
+            }
+            Ok(quote) => return Ok((provider.name(), quote)),
+            Err(e) => {
+                tracing::warn!(
+                    error = %e,
+                    "[💸 Distribution] {} could not quote {:#x} to {:#x}, trying the next provider",
+                    provider.name(),
+                    sell_token,
+                    buy_token
+                );
+            }
+        }
+    }
+    Err(anyhow!(
+        "No swap provider could quote {sell_token:#x} to {buy_token:#x}"
+    ))
+}
+
+/// Returns the amount received at worst for a quote, given the max slippage.
+pub fn min_amount_out(buy_amount: U256, max_slippage_bps: u16) -> U256 {
+    if buy_amount == U256_ZERO {
+        return U256_ZERO;
+    }
+    let kept_bps = BPS_DENOMINATOR.saturating_sub(u32::from(max_slippage_bps));
+    big_uint_to_u256(&(u256_to_big_uint(&buy_amount) * kept_bps / BPS_DENOMINATOR))
+}
+
+/// Swaps directly through the pools of the Ekubo router: the sold tokens are
+/// sent to the router, swapped, then the bought tokens are withdrawn if they
+/// reach the minimum, along with any unsold tokens.
+pub struct EkuboSwapProvider {
+    http_client: HttpClient,
+    router_address: Felt,
+    quoter_url: String,
+}
+
+#[async_trait::async_trait]
+impl SwapProvider for EkuboSwapProvider {
+    fn name(&self) -> &'static str {
+        "ekubo"
+    }
+
+    async fn quote(
+        &self,
+        sell_token: Felt,
+        buy_token: Felt,
+        sell_amount: U256,
+        _taker: Felt,
+    ) -> Result<U256> {
+        let (_, buy_amount) = get_ekubo_exact_input_route(
+            &self.http_client,
+            &self.quoter_url,
+            sell_token,
+            buy_token,
+            sell_amount,
+        )
+        .await?;
+        Ok(buy_amount)
+    }
+
+    async fn build_swap(
+        &self,
+        sell_token: Felt,
+        buy_token: Felt,
+        sell_amount: U256,
+        _taker: Felt,
+        max_slippage_bps: u16,
+    ) -> Result<SwapQuote> {
+        let (swaps, buy_amount) = get_ekubo_exact_input_route(
+            &self.http_client,
+            &self.quoter_url,
+            sell_token,
+            buy_token,
+            sell_amount,
+        )
+        .await?;
+        let min_buy_amount = min_amount_out(buy_amount, max_slippage_bps);
+        let calls = vec![
+            Call {
+                to: sell_token,
+                selector: *TRANSFER_SELECTOR,
+                calldata: vec![
+                    self.router_address,
+                    sell_amount.low.into(),
+                    sell_amount.high.into(),
+                ],
+            },
+            Call {
+                to: self.router_address,
+                selector: *EKUBO_MULTI_MULTIHOP_SWAP_SELECTOR,
+                calldata: Vec::<Swap>::cairo_serialize(&swaps),
+            },
+            Call {
+                to: self.router_address,
+                selector: *EKUBO_CLEAR_MINIMUM_SELECTOR,
+                calldata: vec![
+                    buy_token,
+                    min_buy_amount.low.into(),
+                    min_buy_amount.high.into(),
+                ],
+            },
+            Call {
+                to: self.router_address,
+                selector: *EKUBO_CLEAR_SELECTOR,
+                calldata: vec![sell_token],
+            },
+        ];
+        Ok(SwapQuote {
+            calls,
+            buy_amount,
+            min_buy_amount,
+            price_impact_bps: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cainome::cairo_serde::U256;
+
+    use super::min_amount_out;
+
+    #[test]
+    fn test_min_amount_out() {
+        let amount = U256 {
+            low: 1_000_000,
+            high: 0,
+        };
+        assert_eq!(
+            min_amount_out(amount, 50),
+            U256 {
+                low: 995_000,
+                high: 0
+            }
+        );
+        assert_eq!(min_amount_out(amount, 0), amount);
+        assert_eq!(min_amount_out(amount, 10_000), U256 { low: 0, high: 0 });
+    }
+}
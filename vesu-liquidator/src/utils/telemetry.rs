@@ -0,0 +1,43 @@
+//! Export of the tracing spans to an OpenTelemetry collector (Jaeger, Tempo...)
+//! through OTLP, e.g. to follow a liquidation from its detection to the
+//! confirmation of its distribution.
+
+use anyhow::Result;
+use opentelemetry::{KeyValue, global, trace::TracerProvider as _};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    Resource, runtime,
+    trace::{Tracer, TracerProvider},
+};
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+const SERVICE_NAME: &str = "vesu-liquidator";
+
+/// Returns the layer exporting the spans to the OTLP (gRPC) collector at
+/// `endpoint`, in batches.
+pub fn otel_layer<S>(endpoint: &str) -> Result<OpenTelemetryLayer<S, Tracer>>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let exporter = SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new(vec![KeyValue::new(
+            "service.name",
+            SERVICE_NAME,
+        )]))
+        .build();
+    let tracer = provider.tracer(SERVICE_NAME);
+    global::set_tracer_provider(provider);
+    Ok(tracing_opentelemetry::layer().with_tracer(tracer))
+}
+
+/// Flushes the spans not exported yet, before exiting.
+pub fn shutdown_telemetry() {
+    global::shutdown_tracer_provider();
+}
@@ -0,0 +1,751 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow};
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::json;
+use starknet::core::types::Felt;
+
+use crate::{
+    config::{Config, ToriiConfig},
+    utils::{errors::LiquidatorError, http::HttpClient},
+};
+
+/// Maximum number of players of the redeem queue mirrored locally.
+const REDEEM_QUEUE_SIZE: usize = 100;
+
+/// Represents the structure of a Redeem model from Torii's GraphQL response.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RedeemModel {
+    pub player: String,
+    pub score: u128, // Assuming score fits in u128 for simplicity in Rust.
+    /// Unix timestamp (in seconds) of the redeem, 0 if unknown.
+    #[serde(default)]
+    pub timestamp: u64,
+}
+
+/// Represents the structure of a HighestScore model from Torii.
+#[derive(Deserialize, Debug)]
+struct HighestScoreModel {
+    score: u128, // Assuming score fits in u128.
+}
+
+/// Highest score of a player, as ranked on the leaderboard.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlayerScoreModel {
+    pub player: String,
+    pub score: u128,
+}
+
+/// Represents the structure of a PayoutAddress model from Torii.
+/// Players can register it to get paid on another address than their account.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PayoutAddressModel {
+    pub player: String,
+    pub payout_address: String,
+    /// Account that registered the override.
+    pub set_by: String,
+}
+
+impl PayoutAddressModel {
+    /// Returns the payout address if the override belongs to `player` and was
+    /// set by the player's own account, `None` otherwise.
+    pub fn verified_for(&self, player: Felt) -> Result<Option<Felt>> {
+        let owner = Felt::from_hex(&self.player)?;
+        let set_by = Felt::from_hex(&self.set_by)?;
+        if owner != player || set_by != player {
+            return Ok(None);
+        }
+        Ok(Some(Felt::from_hex(&self.payout_address)?))
+    }
+}
+
+/// Metadata of an event indexed by Torii.
+#[derive(Deserialize, Debug)]
+struct EventMeta {
+    #[serde(rename = "executedAt")]
+    executed_at: String,
+}
+
+/// Metadata of an entity indexed by Torii.
+#[derive(Deserialize, Debug)]
+struct EntityMeta {
+    #[serde(rename = "createdAt")]
+    created_at: String,
+}
+
+/// Represents the structure of a Season model from Torii.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SeasonModel {
+    pub season_id: u32,
+    pub start_time: u64,
+    pub end_time: u64,
+}
+
+/// Response of the Torii GraphQL endpoint, the connections by model.
+#[derive(Deserialize, Debug)]
+struct GraphQLResponse<T> {
+    data: Option<HashMap<String, Option<Connection<T>>>>,
+    #[serde(default)]
+    errors: Vec<GraphQLError>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Connection<T> {
+    edges: Vec<Edge<T>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Edge<T> {
+    node: T,
+}
+
+#[derive(Deserialize, Debug)]
+struct GraphQLError {
+    message: String,
+}
+
+/// Health of a Torii endpoint, from its last check.
+#[derive(Debug, Clone, PartialEq)]
+struct Endpoint {
+    url: String,
+    /// Round trip of the last head query, `None` if it failed.
+    latency: Option<Duration>,
+    /// False once a query failed, or the endpoint lags behind the others,
+    /// until the next check.
+    healthy: bool,
+}
+
+/// Client used to query the game models indexed by Torii. With several
+/// endpoints, the healthy one with the lowest latency is queried first, and
+/// the queries fail over to the next ones.
+#[derive(Clone)]
+pub struct ToriiClient {
+    http_client: HttpClient,
+    config: ToriiConfig,
+    endpoints: Arc<Mutex<Vec<Endpoint>>>,
+    last_check: Arc<tokio::sync::Mutex<Option<Instant>>>,
+}
+
+/// Reads of the game models indexed by Torii, implemented by [`ToriiClient`]
+/// & mocked in the tests of the services.
+#[async_trait::async_trait]
+pub trait ToriiApi: Send + Sync {
+    /// The players of the redeem queue, in order.
+    async fn get_redeem_queue(&self) -> Result<Vec<RedeemModel>>;
+
+    /// The seasons created after `after_season_id`, or all of them if `None`.
+    async fn get_seasons(&self, after_season_id: Option<u32>) -> Result<Vec<SeasonModel>>;
+
+    /// The global highest score, `None` if nobody scored yet.
+    async fn get_highest_score(&self) -> Result<Option<u128>>;
+
+    /// The `size` players with the highest scores, the best first, over the
+    /// whole game or in a season.
+    async fn get_leaderboard(
+        &self,
+        size: usize,
+        season_id: Option<u32>,
+    ) -> Result<Vec<PlayerScoreModel>>;
+
+    /// The payout address override registered by a player, if any.
+    async fn get_payout_address(&self, player: Felt) -> Result<Option<PayoutAddressModel>>;
+
+    /// When the first entity of a player was indexed, `None` if it has none yet.
+    async fn get_account_created_at(&self, player: Felt) -> Result<Option<u64>>;
+}
+
+impl ToriiClient {
+    pub fn new(http_client: HttpClient, graphql_urls: Vec<String>, config: ToriiConfig) -> Self {
+        let endpoints = graphql_urls
+            .into_iter()
+            .map(|url| Endpoint {
+                url,
+                latency: None,
+                healthy: true,
+            })
+            .collect();
+        Self {
+            http_client,
+            config,
+            endpoints: Arc::new(Mutex::new(endpoints)),
+            last_check: Arc::new(tokio::sync::Mutex::new(None)),
+        }
+    }
+
+    /// Client of the `torii_graphql_url` of the config, failing over to its
+    /// `torii_fallback_urls`.
+    pub fn from_config(http_client: HttpClient, config: &Config) -> Self {
+        let mut graphql_urls = vec![config.torii_graphql_url.clone()];
+        graphql_urls.extend(config.torii_fallback_urls.iter().cloned());
+        Self::new(http_client, graphql_urls, config.torii.clone())
+    }
+
+    /// Runs the query with its variables & deserializes the nodes of the
+    /// `model` connection, failing over between the endpoints. The values are
+    /// only ever passed as variables, never formatted into the query: only the
+    /// model names of the config are, validated as GraphQL names.
+    async fn query_models<T: DeserializeOwned>(
+        &self,
+        model: &str,
+        query: &str,
+        variables: serde_json::Value,
+    ) -> Result<Vec<T>> {
+        self.check_endpoints_if_due().await;
+
+        let urls = rank_endpoints(&self.endpoints.lock().expect("endpoints lock poisoned"));
+        let mut last_error = None;
+        for url in urls {
+            match self.query_endpoint(&url, model, query, &variables).await {
+                Ok(models) => return Ok(models),
+                Err(e) => {
+                    tracing::warn!(
+                        torii_url = %url,
+                        error = %e,
+                        "[🎮 Torii] Query of {} failed on {}",
+                        model,
+                        url
+                    );
+                    self.set_unhealthy(&url);
+                    last_error = Some(e);
+                }
+            }
+        }
+        let error = last_error.map_or_else(
+            || "No Torii endpoint configured".to_string(),
+            |e| e.to_string(),
+        );
+        Err(LiquidatorError::Torii(error).into())
+    }
+
+    async fn query_endpoint<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        model: &str,
+        query: &str,
+        variables: &serde_json::Value,
+    ) -> Result<Vec<T>> {
+        let request = self
+            .http_client
+            .post(url)
+            .json(&json!({ "query": query, "variables": variables }));
+        let body = self
+            .http_client
+            .send(request)
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        parse_models(model, &body)
+    }
+
+    fn set_unhealthy(&self, url: &str) {
+        let mut endpoints = self.endpoints.lock().expect("endpoints lock poisoned");
+        if let Some(endpoint) = endpoints.iter_mut().find(|endpoint| endpoint.url == url) {
+            endpoint.healthy = false;
+        }
+    }
+
+    /// Checks the latency & the indexer head of the endpoints if the last
+    /// check is older than `torii.health_check_interval_seconds`. A single
+    /// endpoint is never checked: there is nothing to fail over to.
+    async fn check_endpoints_if_due(&self) {
+        let urls: Vec<String> = {
+            let endpoints = self.endpoints.lock().expect("endpoints lock poisoned");
+            if endpoints.len() < 2 {
+                return;
+            }
+            endpoints
+                .iter()
+                .map(|endpoint| endpoint.url.clone())
+                .collect()
+        };
+        let mut last_check = self.last_check.lock().await;
+        let interval = Duration::from_secs(self.config.health_check_interval_seconds);
+        if last_check.is_some_and(|checked_at| checked_at.elapsed() < interval) {
+            return;
+        }
+
+        let checks = join_all(urls.into_iter().map(|url| async move {
+            let started_at = Instant::now();
+            let head = self.query_head(&url).await;
+            if let Err(e) = &head {
+                tracing::warn!(
+                    torii_url = %url,
+                    error = %e,
+                    "[🎮 Torii] Health check of {} failed",
+                    url
+                );
+            }
+            let check = head.ok().map(|head| (started_at.elapsed(), head));
+            (url, check)
+        }))
+        .await;
+        let endpoints = assess_endpoints(checks, self.config.max_head_lag_seconds);
+        for endpoint in endpoints.iter().filter(|endpoint| !endpoint.healthy) {
+            tracing::warn!(
+                torii_url = %endpoint.url,
+                "[🎮 Torii] Endpoint {} is unhealthy, failing over",
+                endpoint.url
+            );
+        }
+        *self.endpoints.lock().expect("endpoints lock poisoned") = endpoints;
+        *last_check = Some(Instant::now());
+    }
+
+    /// Queries the time of the last event indexed by the endpoint.
+    async fn query_head(&self, url: &str) -> Result<u64> {
+        let query = r#"
+            query IndexerHead {
+                events(last: 1) {
+                    edges {
+                        node {
+                            executedAt
+                        }
+                    }
+                }
+            }
+        "#;
+
+        let events: Vec<EventMeta> = self
+            .query_endpoint(url, "events", query, &json!({}))
+            .await?;
+        let event = events
+            .first()
+            .ok_or_else(|| anyhow!("No event indexed by {url}"))?;
+        parse_torii_timestamp(&event.executed_at)
+    }
+}
+
+/// Returns the health of the endpoints from their checks, as `(url, (latency,
+/// head))`: an endpoint is unhealthy if its check failed, or if its head lags
+/// more than `max_head_lag` seconds behind the most recent one.
+fn assess_endpoints(
+    checks: Vec<(String, Option<(Duration, u64)>)>,
+    max_head_lag: u64,
+) -> Vec<Endpoint> {
+    let best_head = checks
+        .iter()
+        .filter_map(|(_, check)| check.map(|(_, head)| head))
+        .max()
+        .unwrap_or(0);
+    checks
+        .into_iter()
+        .map(|(url, check)| Endpoint {
+            url,
+            latency: check.map(|(latency, _)| latency),
+            healthy: check.is_some_and(|(_, head)| best_head - head <= max_head_lag),
+        })
+        .collect()
+}
+
+/// Returns the urls of the endpoints in the order they are queried: the
+/// healthy ones by latency, the unchecked last, then the unhealthy ones.
+fn rank_endpoints(endpoints: &[Endpoint]) -> Vec<String> {
+    let mut ranked: Vec<&Endpoint> = endpoints.iter().collect();
+    // Stable, so the configured order breaks the ties.
+    ranked.sort_by_key(|endpoint| {
+        (
+            !endpoint.healthy,
+            endpoint.latency.is_none(),
+            endpoint.latency,
+        )
+    });
+    ranked
+        .into_iter()
+        .map(|endpoint| endpoint.url.clone())
+        .collect()
+}
+
+/// Deserializes the nodes of the `model` connection from a GraphQL response,
+/// failing on the errors returned by Torii or nodes not matching `T`.
+fn parse_models<T: DeserializeOwned>(model: &str, body: &str) -> Result<Vec<T>> {
+    let response: GraphQLResponse<T> = serde_json::from_str(body)
+        .map_err(|e| anyhow!("Invalid Torii response for {model}: {e}"))?;
+    if !response.errors.is_empty() {
+        let messages: Vec<_> = response.errors.into_iter().map(|e| e.message).collect();
+        return Err(anyhow!(
+            "Torii query of {model} failed: {}",
+            messages.join("; ")
+        ));
+    }
+
+    let connection = response
+        .data
+        .and_then(|mut data| data.remove(model))
+        .flatten()
+        .ok_or_else(|| anyhow!("Missing {model} in the Torii response"))?;
+    Ok(connection.edges.into_iter().map(|edge| edge.node).collect())
+}
+
+#[async_trait::async_trait]
+impl ToriiApi for ToriiClient {
+    /// Queries the Torii GraphQL endpoint for the players of the redeem queue, in order.
+    #[tracing::instrument(
+        name = "torii_query",
+        skip_all,
+        fields(model = self.config.redeem_model.as_str())
+    )]
+    async fn get_redeem_queue(&self) -> Result<Vec<RedeemModel>> {
+        let model = &self.config.redeem_model;
+        let query = format!(
+            r#"
+            query RedeemQueue($first: Int) {{
+                {model}(first: $first) {{
+                    edges {{
+                        node {{
+                            player, score, timestamp
+                        }}
+                    }}
+                }}
+            }}
+        "#
+        );
+
+        self.query_models(model, &query, json!({ "first": REDEEM_QUEUE_SIZE }))
+            .await
+    }
+
+    /// Queries Torii for the seasons created after `after_season_id`, or all of
+    /// them if `None`.
+    #[tracing::instrument(name = "torii_query", skip_all, fields(model = "seasonModels"))]
+    async fn get_seasons(&self, after_season_id: Option<u32>) -> Result<Vec<SeasonModel>> {
+        let (query, variables) = match after_season_id {
+            Some(season_id) => (
+                r#"
+                query SeasonsAfter($after: u32) {
+                    seasonModels(where: { season_idGT: $after }) {
+                        edges {
+                            node {
+                                season_id, start_time, end_time
+                            }
+                        }
+                    }
+                }
+            "#,
+                json!({ "after": season_id }),
+            ),
+            None => (
+                r#"
+                query Seasons {
+                    seasonModels {
+                        edges {
+                            node {
+                                season_id, start_time, end_time
+                            }
+                        }
+                    }
+                }
+            "#,
+                json!({}),
+            ),
+        };
+
+        self.query_models("seasonModels", query, variables).await
+    }
+
+    /// Queries Torii for the global highest score.
+    #[tracing::instrument(
+        name = "torii_query",
+        skip_all,
+        fields(model = self.config.leaderboard_model.as_str())
+    )]
+    async fn get_highest_score(&self) -> Result<Option<u128>> {
+        let model = &self.config.leaderboard_model;
+        let query = format!(
+            r#"
+            query HighestScore {{
+                {model}(first: 1) {{
+                    edges {{
+                        node {{
+                            score
+                        }}
+                    }}
+                }}
+            }}
+        "#
+        );
+
+        let models: Vec<HighestScoreModel> = self.query_models(model, &query, json!({})).await?;
+        Ok(models.first().map(|m| m.score))
+    }
+
+    /// Queries Torii for the `size` players with the highest scores, the best
+    /// first: their highest scores, or their scores in the season if set.
+    #[tracing::instrument(
+        name = "torii_query",
+        skip_all,
+        fields(model = self.config.leaderboard_model.as_str())
+    )]
+    async fn get_leaderboard(
+        &self,
+        size: usize,
+        season_id: Option<u32>,
+    ) -> Result<Vec<PlayerScoreModel>> {
+        let Some(season_id) = season_id else {
+            let model = &self.config.leaderboard_model;
+            let query = format!(
+                r#"
+                query Leaderboard($first: Int) {{
+                    {model}(first: $first, order: {{ field: SCORE, direction: DESC }}) {{
+                        edges {{
+                            node {{
+                                player, score
+                            }}
+                        }}
+                    }}
+                }}
+            "#
+            );
+            return self
+                .query_models(model, &query, json!({ "first": size }))
+                .await;
+        };
+        let model = &self.config.season_leaderboard_model;
+        let query = format!(
+            r#"
+            query SeasonLeaderboard($first: Int, $season: u32) {{
+                {model}(
+                    where: {{ season_idEQ: $season }},
+                    first: $first,
+                    order: {{ field: SCORE, direction: DESC }}
+                ) {{
+                    edges {{
+                        node {{
+                            player, score
+                        }}
+                    }}
+                }}
+            }}
+        "#
+        );
+
+        self.query_models(model, &query, json!({ "first": size, "season": season_id }))
+            .await
+    }
+
+    /// Queries Torii for the payout address override registered by a player.
+    #[tracing::instrument(
+        name = "torii_query",
+        skip_all,
+        fields(model = "payoutAddressModels", player_address = format!("{player:#x}"))
+    )]
+    async fn get_payout_address(&self, player: Felt) -> Result<Option<PayoutAddressModel>> {
+        let query = r#"
+            query PayoutAddress($player: ContractAddress) {
+                payoutAddressModels(where: { playerEQ: $player }, first: 1) {
+                    edges {
+                        node {
+                            player, payout_address, set_by
+                        }
+                    }
+                }
+            }
+        "#;
+
+        let variables = json!({ "player": format!("{player:#x}") });
+        let models: Vec<PayoutAddressModel> = self
+            .query_models("payoutAddressModels", query, variables)
+            .await?;
+        Ok(models.into_iter().next())
+    }
+
+    /// Queries Torii for the time the first entity of a player was indexed,
+    /// used as the creation time of its account. `None` if the player has no
+    /// entity yet.
+    #[tracing::instrument(
+        name = "torii_query",
+        skip_all,
+        fields(model = "entities", player_address = format!("{player:#x}"))
+    )]
+    async fn get_account_created_at(&self, player: Felt) -> Result<Option<u64>> {
+        let query = r#"
+            query PlayerEntities($keys: [String], $first: Int) {
+                entities(keys: $keys, first: $first) {
+                    edges {
+                        node {
+                            createdAt
+                        }
+                    }
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "keys": [format!("{player:#x}")],
+            "first": REDEEM_QUEUE_SIZE,
+        });
+        let entities: Vec<EntityMeta> = self.query_models("entities", query, variables).await?;
+        let mut created_at = None;
+        for entity in entities.iter() {
+            let timestamp = parse_torii_timestamp(&entity.created_at)?;
+            created_at = Some(created_at.map_or(timestamp, |first: u64| first.min(timestamp)));
+        }
+        Ok(created_at)
+    }
+}
+
+/// Parses a timestamp of Torii, RFC 3339 or without timezone for UTC, into
+/// a unix timestamp (in seconds).
+fn parse_torii_timestamp(timestamp: &str) -> Result<u64> {
+    let datetime = match chrono::DateTime::parse_from_rfc3339(timestamp) {
+        Ok(datetime) => datetime.to_utc(),
+        Err(_) => chrono::NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%S%.f")
+            .map_err(|e| anyhow!("Invalid Torii timestamp {timestamp}: {e}"))?
+            .and_utc(),
+    };
+    u64::try_from(datetime.timestamp())
+        .map_err(|e| anyhow!("Invalid Torii timestamp {timestamp}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use starknet::core::types::Felt;
+
+    use super::{
+        Endpoint, PayoutAddressModel, RedeemModel, assess_endpoints, parse_models,
+        parse_torii_timestamp, rank_endpoints,
+    };
+
+    fn payout_address(player: &str, payout_address: &str, set_by: &str) -> PayoutAddressModel {
+        PayoutAddressModel {
+            player: player.to_string(),
+            payout_address: payout_address.to_string(),
+            set_by: set_by.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_payout_address_verified_for() {
+        let player = Felt::from_hex("0xa").unwrap();
+
+        // Set by the player for itself.
+        let verified = payout_address("0xa", "0xb0b", "0xa");
+        assert_eq!(
+            verified.verified_for(player).unwrap(),
+            Some(Felt::from_hex("0xb0b").unwrap())
+        );
+        // Set by another account for the player.
+        let unverified = payout_address("0xa", "0xb0b", "0xbad");
+        assert_eq!(unverified.verified_for(player).unwrap(), None);
+        // The override of another player.
+        let mismatched = payout_address("0xc", "0xb0b", "0xc");
+        assert_eq!(mismatched.verified_for(player).unwrap(), None);
+        // Set by the player, but for another player.
+        let foreign = payout_address("0xc", "0xb0b", "0xa");
+        assert_eq!(foreign.verified_for(player).unwrap(), None);
+        // Unreadable addresses are refused.
+        let invalid = payout_address("0xa", "not an address", "0xa");
+        assert!(invalid.verified_for(player).is_err());
+    }
+
+    #[test]
+    fn test_parse_torii_timestamp() {
+        assert_eq!(
+            parse_torii_timestamp("2024-05-01T12:00:00Z").unwrap(),
+            1_714_564_800
+        );
+        assert_eq!(
+            parse_torii_timestamp("2024-05-01T12:00:00").unwrap(),
+            1_714_564_800
+        );
+        assert_eq!(
+            parse_torii_timestamp("2024-05-01T14:00:00.250+02:00").unwrap(),
+            1_714_564_800
+        );
+        assert!(parse_torii_timestamp("yesterday").is_err());
+    }
+
+    #[test]
+    fn test_parse_models() {
+        let body = r#"{"data": {"redeemModels": {"edges": [
+            {"node": {"player": "0x1", "score": 10}},
+            {"node": {"player": "0x2", "score": 5}}
+        ]}}}"#;
+        let models: Vec<RedeemModel> = parse_models("redeemModels", body).unwrap();
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[1].player, "0x2");
+        assert_eq!(models[1].score, 5);
+
+        let body = r#"{"data": {"redeemModels": {"edges": []}}}"#;
+        let models: Vec<RedeemModel> = parse_models("redeemModels", body).unwrap();
+        assert!(models.is_empty());
+    }
+
+    #[test]
+    fn test_parse_models_errors() {
+        let parse = |body| parse_models::<RedeemModel>("redeemModels", body).unwrap_err();
+
+        let error = parse(r#"{"data": null, "errors": [{"message": "Unknown field"}]}"#);
+        assert_eq!(
+            error.to_string(),
+            "Torii query of redeemModels failed: Unknown field"
+        );
+        let error = parse(r#"{"data": {"seasonModels": {"edges": []}}}"#);
+        assert_eq!(
+            error.to_string(),
+            "Missing redeemModels in the Torii response"
+        );
+        let error = parse(r#"{"data": {"redeemModels": null}}"#);
+        assert_eq!(
+            error.to_string(),
+            "Missing redeemModels in the Torii response"
+        );
+
+        // The nodes are validated against the model.
+        let error =
+            parse(r#"{"data": {"redeemModels": {"edges": [{"node": {"player": "0x1"}}]}}}"#);
+        assert!(error.to_string().contains("missing field `score`"));
+        let error = parse(
+            r#"{"data": {"redeemModels": {"edges": [{"node": {"player": "0x1", "score": "high"}}]}}}"#,
+        );
+        assert!(
+            error
+                .to_string()
+                .starts_with("Invalid Torii response for redeemModels")
+        );
+    }
+
+    #[test]
+    fn test_endpoints_failover() {
+        let ms = Duration::from_millis;
+        let checks = vec![
+            ("primary".to_string(), Some((ms(300), 1_000))),
+            ("fast".to_string(), Some((ms(50), 990))),
+            ("stale".to_string(), Some((ms(10), 900))),
+            ("down".to_string(), None),
+        ];
+        let endpoints = assess_endpoints(checks, 60);
+        let healthy: Vec<bool> = endpoints.iter().map(|endpoint| endpoint.healthy).collect();
+        assert_eq!(healthy, vec![true, true, false, false]);
+        // The fastest healthy endpoint first, the stale & down ones last.
+        assert_eq!(
+            rank_endpoints(&endpoints),
+            vec!["fast", "primary", "stale", "down"]
+        );
+
+        // Before any check, in the configured order.
+        let unchecked = |url: &str| Endpoint {
+            url: url.to_string(),
+            latency: None,
+            healthy: true,
+        };
+        let endpoints = vec![unchecked("primary"), unchecked("fallback")];
+        assert_eq!(rank_endpoints(&endpoints), vec!["primary", "fallback"]);
+        // A failed query moves the endpoint last.
+        let endpoints = vec![
+            Endpoint {
+                healthy: false,
+                ..unchecked("primary")
+            },
+            unchecked("fallback"),
+        ];
+        assert_eq!(rank_endpoints(&endpoints), vec!["fallback", "primary"]);
+    }
+}
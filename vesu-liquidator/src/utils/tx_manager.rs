@@ -0,0 +1,352 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, bail};
+use starknet::{
+    core::types::{
+        Call, Event, ExecuteInvocation, Felt, FunctionInvocation, TransactionReceiptWithBlockInfo,
+        TransactionTrace,
+    },
+    providers::{JsonRpcClient, jsonrpc::HttpTransport},
+};
+
+use crate::{
+    config::{FeeToken, TransactionsConfig},
+    services::{control::Controls, leader::Leadership},
+    types::account::{AccountLease, AccountPool, StarknetAccount},
+    utils::{fee_tokens::FeeTokens, get_tx_receipt, paymaster::PaymasterClient},
+};
+
+/// Gas price multiplier of the first submission, the default of starknet-rs.
+const BASE_GAS_PRICE_MULTIPLIER: f64 = 1.5;
+/// Interval at which the receipts of the submitted transactions are checked.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Outcome of the simulation of a transaction.
+pub enum Simulation {
+    /// The transaction would succeed & emit these events.
+    Succeeded(Vec<Event>),
+    /// The transaction would revert with this reason.
+    Reverted(String),
+}
+
+/// Sends the transactions of the bot & makes sure they land, see
+/// [`TransactionsConfig`]. The transactions are rotated across the accounts
+/// of the [`AccountPool`].
+#[derive(Clone)]
+pub struct TxManager {
+    accounts: AccountPool,
+    rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+    config: TransactionsConfig,
+    paymaster: Option<PaymasterClient>,
+    fee_tokens: FeeTokens,
+    leadership: Leadership,
+    controls: Controls,
+}
+
+impl TxManager {
+    pub fn new(
+        account: StarknetAccount,
+        rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+        config: TransactionsConfig,
+    ) -> Self {
+        Self::with_accounts(AccountPool::single(account), rpc_client, config)
+    }
+
+    pub fn with_accounts(
+        accounts: AccountPool,
+        rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+        config: TransactionsConfig,
+    ) -> Self {
+        Self {
+            accounts,
+            rpc_client,
+            config,
+            paymaster: None,
+            fee_tokens: FeeTokens::default(),
+            leadership: Leadership::default(),
+            controls: Controls::default(),
+        }
+    }
+
+    /// Pays the fees through the paymaster, if enabled.
+    pub fn with_paymaster(mut self, paymaster: Option<PaymasterClient>) -> Self {
+        self.paymaster = paymaster;
+        self
+    }
+
+    /// Pays the fees in the token selected, see [`FeeTokens`].
+    pub fn with_fee_tokens(mut self, fee_tokens: FeeTokens) -> Self {
+        self.fee_tokens = fee_tokens;
+        self
+    }
+
+    /// Only sends transactions while the instance leads, see
+    /// [`crate::services::leader::LeaderElectionService`].
+    pub fn with_leadership(mut self, leadership: Leadership) -> Self {
+        self.leadership = leadership;
+        self
+    }
+
+    /// Holds back the transactions while halted by an operator.
+    pub fn with_controls(mut self, controls: Controls) -> Self {
+        self.controls = controls;
+        self
+    }
+
+    pub fn fee_tokens(&self) -> &FeeTokens {
+        &self.fee_tokens
+    }
+
+    pub fn accounts(&self) -> &AccountPool {
+        &self.accounts
+    }
+
+    /// Leases the account sending the next transaction, to simulate & execute
+    /// it with the same account.
+    pub fn acquire(&self) -> AccountLease {
+        self.accounts.acquire()
+    }
+
+    /// Executes the calls with the next account of the rotation, see
+    /// [`Self::execute_as`].
+    pub async fn execute(&self, calls: &[Call]) -> Result<TransactionReceiptWithBlockInfo> {
+        let lease = self.acquire();
+        self.execute_as(&lease, calls).await
+    }
+
+    /// Executes the calls with the leased account & returns the receipt once
+    /// the transaction is accepted. The fees are paid through the paymaster if
+    /// enabled or if they are paid in ETH, falling back to a normal execution
+    /// paying them in STRK when it fails. A normal
+    /// transaction not accepted within `timeout_seconds` is re-sent with the
+    /// same nonce, so only one of the submissions can land.
+    pub async fn execute_as(
+        &self,
+        lease: &AccountLease,
+        calls: &[Call],
+    ) -> Result<TransactionReceiptWithBlockInfo> {
+        if self.controls.is_halted() {
+            bail!("The transactions are halted by an operator");
+        }
+        self.send_as(lease, calls).await
+    }
+
+    /// Executes the calls even though the transactions are halted, for the
+    /// emergency sweep of the funds.
+    pub async fn execute_while_halted(
+        &self,
+        calls: &[Call],
+    ) -> Result<TransactionReceiptWithBlockInfo> {
+        let lease = self.acquire();
+        self.send_as(&lease, calls).await
+    }
+
+    async fn send_as(
+        &self,
+        lease: &AccountLease,
+        calls: &[Call],
+    ) -> Result<TransactionReceiptWithBlockInfo> {
+        if !self.leadership.is_leader() {
+            bail!("Not the leader instance, the transactions are sent by the leader");
+        }
+        let account = lease.account();
+        let address = account.account_address();
+
+        let paymaster = match self.fee_tokens.select() {
+            FeeToken::Strk => self.paymaster.as_ref(),
+            FeeToken::Eth => self.fee_tokens.eth_paymaster(),
+        };
+        if let Some(paymaster) = paymaster.filter(|p| p.is_available()) {
+            match paymaster.execute(account, calls).await {
+                // Not re-sent normally once submitted, as both could land.
+                Ok(tx_hash) => {
+                    let Some(receipt) = self.wait_for_any(&[tx_hash]).await? else {
+                        bail!(
+                            "Paymaster transaction {tx_hash:#x} not accepted after {}s",
+                            self.config.timeout_seconds
+                        );
+                    };
+                    self.accounts.record_sent(address, None);
+                    return Ok(receipt);
+                }
+                Err(e) => {
+                    paymaster.mark_unavailable();
+                    tracing::warn!(
+                        error = %e,
+                        "Paymaster failed, paying the fees in STRK for the next {}s",
+                        paymaster.retry_after_seconds()
+                    );
+                }
+            }
+        }
+        let nonce = self
+            .accounts
+            .reserve_nonce(address, account.get_nonce().await?);
+        let mut submitted = vec![];
+
+        for attempt in 0..=self.config.max_resubmissions {
+            let multiplier = gas_price_multiplier(attempt, self.config.fee_bump_factor);
+            match account
+                .execute_txs_with_nonce(calls, nonce, multiplier)
+                .await
+            {
+                Ok(tx_hash) => submitted.push(tx_hash),
+                Err(e) if submitted.is_empty() => {
+                    self.accounts.reset_nonce(address);
+                    return Err(e);
+                }
+                // e.g. the nonce was used by a previous submission landing meanwhile.
+                Err(e) => tracing::warn!(
+                    error = %e,
+                    "Could not resubmit the transaction with nonce {nonce:#x}"
+                ),
+            }
+
+            if let Some(receipt) = self.wait_for_any(&submitted).await? {
+                self.accounts
+                    .record_sent(address, Some(receipt.receipt.actual_fee()));
+                return Ok(receipt);
+            }
+            if attempt < self.config.max_resubmissions {
+                tracing::warn!(
+                    "Transaction with nonce {:#x} not accepted after {}s, resubmitting with gas prices x{:.2}",
+                    nonce,
+                    self.config.timeout_seconds,
+                    gas_price_multiplier(attempt + 1, self.config.fee_bump_factor)
+                );
+            }
+        }
+
+        // The nonce may still be used by a late submission, it is read again
+        // from the chain by the next transaction.
+        self.accounts.reset_nonce(address);
+        bail!(
+            "Transaction of account {address:#x} with nonce {nonce:#x} not accepted after {} submission(s)",
+            submitted.len()
+        )
+    }
+
+    /// Simulates the calls with the liquidator account, see [`Self::simulate_as`].
+    pub async fn simulate(&self, calls: &[Call]) -> Result<Simulation> {
+        self.simulate_as(self.accounts.primary(), calls).await
+    }
+
+    /// Simulates the calls sent by the account, so a transaction that would
+    /// revert is not sent: no nonce nor fee is burnt.
+    pub async fn simulate_as(
+        &self,
+        account: &StarknetAccount,
+        calls: &[Call],
+    ) -> Result<Simulation> {
+        let simulated = account.simulate_txs(calls).await?;
+        let TransactionTrace::Invoke(trace) = simulated.transaction_trace else {
+            bail!("Unexpected trace for an invoke transaction");
+        };
+        Ok(match trace.execute_invocation {
+            ExecuteInvocation::Success(invocation) => {
+                Simulation::Succeeded(invocation_events(&invocation))
+            }
+            ExecuteInvocation::Reverted(reverted) => Simulation::Reverted(reverted.revert_reason),
+        })
+    }
+
+    /// Waits up to `timeout_seconds` for one of the submissions to be accepted.
+    async fn wait_for_any(
+        &self,
+        tx_hashes: &[Felt],
+    ) -> Result<Option<TransactionReceiptWithBlockInfo>> {
+        let deadline = Instant::now() + Duration::from_secs(self.config.timeout_seconds);
+        loop {
+            for tx_hash in tx_hashes {
+                if let Some(receipt) = get_tx_receipt(&self.rpc_client, *tx_hash).await? {
+                    return Ok(Some(receipt));
+                }
+            }
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    }
+}
+
+/// Returns the gas price multiplier of a submission, bumped for each resubmission.
+fn gas_price_multiplier(attempt: u32, fee_bump_factor: f64) -> f64 {
+    BASE_GAS_PRICE_MULTIPLIER * fee_bump_factor.powi(attempt as i32)
+}
+
+/// Returns the events emitted by an invocation & its inner calls, along with
+/// the contracts emitting them.
+fn invocation_events(invocation: &FunctionInvocation) -> Vec<Event> {
+    let mut events: Vec<Event> = invocation
+        .events
+        .iter()
+        .map(|event| Event {
+            from_address: invocation.contract_address,
+            keys: event.keys.clone(),
+            data: event.data.clone(),
+        })
+        .collect();
+    for call in &invocation.calls {
+        events.extend(invocation_events(call));
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::{
+        CallType, EntryPointType, Felt, FunctionInvocation, InnerCallExecutionResources,
+        OrderedEvent,
+    };
+
+    use super::{gas_price_multiplier, invocation_events};
+
+    fn invocation(contract_address: u64, calls: Vec<FunctionInvocation>) -> FunctionInvocation {
+        FunctionInvocation {
+            contract_address: Felt::from(contract_address),
+            entry_point_selector: Felt::ZERO,
+            calldata: vec![],
+            caller_address: Felt::ZERO,
+            class_hash: Felt::ZERO,
+            entry_point_type: EntryPointType::External,
+            call_type: CallType::Call,
+            result: vec![],
+            calls,
+            events: vec![OrderedEvent {
+                order: 0,
+                keys: vec![Felt::from(contract_address)],
+                data: vec![],
+            }],
+            messages: vec![],
+            execution_resources: InnerCallExecutionResources {
+                l1_gas: 0,
+                l2_gas: 0,
+            },
+            is_reverted: false,
+        }
+    }
+
+    #[test]
+    fn test_invocation_events_include_inner_calls() {
+        let root = invocation(1, vec![invocation(2, vec![invocation(3, vec![])])]);
+        let events = invocation_events(&root);
+        let emitters: Vec<Felt> = events.iter().map(|event| event.from_address).collect();
+        assert_eq!(emitters, vec![Felt::ONE, Felt::TWO, Felt::THREE]);
+        assert!(
+            events
+                .iter()
+                .all(|event| event.keys == vec![event.from_address])
+        );
+    }
+
+    #[test]
+    fn test_gas_price_multiplier() {
+        assert_eq!(gas_price_multiplier(0, 1.25), 1.5);
+        assert_eq!(gas_price_multiplier(1, 1.25), 1.875);
+        assert_eq!(gas_price_multiplier(2, 2.0), 6.0);
+    }
+}
@@ -0,0 +1,100 @@
+//! Windows service integration: the bot is run by the Service Control Manager
+//! when registered with `--windows-service`, e.g.
+//! `sc.exe create vesu-liquidator binPath= "C:\...\vesu-liquidator.exe run --windows-service ..."`.
+
+use std::{ffi::OsString, sync::OnceLock, time::Duration};
+
+use anyhow::{Result, anyhow};
+use windows_service::{
+    define_windows_service,
+    service::{
+        ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus,
+        ServiceType,
+    },
+    service_control_handler::{self, ServiceControlHandlerResult, ServiceStatusHandle},
+    service_dispatcher,
+};
+
+use crate::{cli::RunCmd, commands::run::run, utils::shutdown::request_shutdown};
+
+/// Name of the service registered in the Service Control Manager.
+const SERVICE_NAME: &str = "vesu-liquidator";
+/// Time given to the services to finish their in-flight work when stopping.
+const STOP_WAIT_HINT: Duration = Duration::from_secs(60);
+
+/// Parameters of the run command, handed over to the service entrypoint
+/// which is called by the dispatcher without arguments of ours.
+static RUN_CMD: OnceLock<RunCmd> = OnceLock::new();
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Connects to the Service Control Manager & runs the bot until the service
+/// is stopped.
+pub async fn run_as_service(run_cmd: RunCmd) -> Result<()> {
+    RUN_CMD
+        .set(run_cmd)
+        .map_err(|_| anyhow!("The Windows service is already running"))?;
+    // Blocks until the service is stopped.
+    tokio::task::spawn_blocking(|| service_dispatcher::start(SERVICE_NAME, ffi_service_main))
+        .await??;
+    Ok(())
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(e) = run_service() {
+        tracing::error!(error = %e, "😨 Windows service failed");
+    }
+}
+
+fn run_service() -> Result<()> {
+    let status_handle = service_control_handler::register(SERVICE_NAME, |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown | ServiceControl::Preshutdown => {
+            request_shutdown();
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    })?;
+    set_status(
+        &status_handle,
+        ServiceState::Running,
+        ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+        ServiceExitCode::Win32(0),
+    )?;
+
+    let run_cmd = RUN_CMD
+        .get()
+        .cloned()
+        .ok_or_else(|| anyhow!("Missing run parameters"))?;
+    let res = tokio::runtime::Runtime::new()?.block_on(run(run_cmd));
+
+    let exit_code = match res {
+        Ok(()) => ServiceExitCode::Win32(0),
+        Err(_) => ServiceExitCode::ServiceSpecific(1),
+    };
+    set_status(
+        &status_handle,
+        ServiceState::Stopped,
+        ServiceControlAccept::empty(),
+        exit_code,
+    )?;
+    res
+}
+
+fn set_status(
+    status_handle: &ServiceStatusHandle,
+    state: ServiceState,
+    controls_accepted: ServiceControlAccept,
+    exit_code: ServiceExitCode,
+) -> Result<()> {
+    status_handle.set_service_status(ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state: state,
+        controls_accepted,
+        exit_code,
+        checkpoint: 0,
+        wait_hint: STOP_WAIT_HINT,
+        process_id: None,
+    })?;
+    Ok(())
+}
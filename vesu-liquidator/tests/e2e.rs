@@ -0,0 +1,407 @@
+//! End-to-end test of a liquidation & of the distribution of its earnings on
+//! a Katana devnet, with mocks of the Vesu singleton & Liquidate contracts and
+//! a game world indexed by Torii, see `e2e/`. Ignored by `cargo test`: it is
+//! run by `make e2e`, which starts Katana & Torii first.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use axum::{Json, Router, routing::get};
+use serde_json::{Value, json};
+use starknet::{
+    accounts::{Account, ConnectedAccount, ExecutionEncoding, SingleOwnerAccount},
+    contract::ContractFactory,
+    core::{
+        types::{
+            BlockId, BlockTag, Call, Felt, FunctionCall,
+            contract::{CompiledClass, SierraClass},
+        },
+        utils::get_selector_from_name,
+    },
+    providers::{JsonRpcClient, Provider, jsonrpc::HttpTransport},
+    signers::{LocalWallet, SigningKey},
+};
+
+type E2eAccount = SingleOwnerAccount<Arc<JsonRpcClient<HttpTransport>>, LocalWallet>;
+
+// Prefunded accounts of Katana's default seed: the first one migrates the
+// world & plays, the second one runs the bot.
+const PLAYER_ADDRESS: &str = "0x127fd5f1fe78a71f8bcd1fec63e3fe2f0486b6ecd5c86a0466c3a21fa5cfcec";
+const PLAYER_PRIVATE_KEY: &str = "0xc5b2fcab997346f3ea1c00b002ecf6f382c5f9c9659a3894eb783c5320f912";
+const BOT_ADDRESS: &str = "0x13d9ee239f33fea4f8785b9e3870ade909e20a9599ae7cd62c1c292b73af1b7";
+const BOT_PRIVATE_KEY: &str = "0x1c9053c053edf324aec366a34c6901b1095b07af69495bffec7d7fe21effb1b";
+
+// ETH & STRK, deployed with Katana at their mainnet addresses.
+const ETH: Felt =
+    Felt::from_hex_unchecked("0x49d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7");
+const STRK: Felt =
+    Felt::from_hex_unchecked("0x4718f5a0fc34cc1af16a1cdee98ffb20c31f5cd61d6ab07201858f4287c938d");
+
+const POOL_ID: Felt = Felt::from_hex_unchecked("0x1");
+const BORROWER: Felt = Felt::from_hex_unchecked("0xb0b");
+const ONE: u128 = 1_000_000_000_000_000_000;
+// 0.9 of debt for 1 of collateral, at the same static price, against a max
+// LTV of 0.8: the position is undercollateralized.
+const COLLATERAL: u128 = ONE;
+const DEBT: u128 = 9 * ONE / 10;
+const MAX_LTV: u64 = 8 * (ONE as u64 / 10);
+const PLAYER_SCORE: u64 = 100;
+
+#[tokio::test]
+#[ignore = "needs Katana & Torii, run with `make e2e`"]
+async fn test_liquidates_and_distributes_on_katana() -> Result<()> {
+    let rpc_url = env_or("E2E_RPC_URL", "http://localhost:5050");
+    let torii_graphql_url = env_or("E2E_TORII_GRAPHQL_URL", "http://localhost:8080/graphql");
+    let contracts_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("e2e/contracts");
+
+    let provider = Arc::new(JsonRpcClient::new(HttpTransport::new(rpc_url.parse()?)));
+    let player = account(
+        &provider,
+        &env_or("E2E_PLAYER_ADDRESS", PLAYER_ADDRESS),
+        &env_or("E2E_PLAYER_PRIVATE_KEY", PLAYER_PRIVATE_KEY),
+    )
+    .await?;
+    let bot_address = env_or("E2E_BOT_ADDRESS", BOT_ADDRESS);
+    let bot_private_key = env_or("E2E_BOT_PRIVATE_KEY", BOT_PRIVATE_KEY);
+
+    // Contracts: the mocks are deployed by the test, the world by `sozo migrate`.
+    let manifest = read_json(&contracts_dir.join("manifest_dev.json"))?;
+    let world_address = felt_at(&manifest["world"]["address"])?;
+    let game_address = manifest["contracts"]
+        .as_array()
+        .context("No contracts in the manifest")?
+        .iter()
+        .find(|contract| contract["tag"] == "e2e-game")
+        .map(|contract| felt_at(&contract["address"]))
+        .context("No game contract in the manifest")??;
+    let singleton = declare_and_deploy(&player, &contracts_dir, "MockSingleton", vec![]).await?;
+    let liquidate =
+        declare_and_deploy(&player, &contracts_dir, "MockLiquidate", vec![singleton]).await?;
+
+    // The Liquidate mock pays the residual collateral from its own balance.
+    execute(
+        &player,
+        vec![call(
+            ETH,
+            "transfer",
+            vec![liquidate, COLLATERAL.into(), Felt::ZERO],
+        )],
+    )
+    .await?;
+    execute(
+        &player,
+        vec![call(game_address, "redeem", vec![PLAYER_SCORE.into()])],
+    )
+    .await?;
+    execute(
+        &player,
+        vec![call(
+            singleton,
+            "set_position",
+            vec![
+                POOL_ID,
+                ETH,
+                STRK,
+                BORROWER,
+                COLLATERAL.into(),
+                Felt::ZERO,
+                DEBT.into(),
+                Felt::ZERO,
+                MAX_LTV.into(),
+            ],
+        )],
+    )
+    .await?;
+    let latest_block = provider.block_number().await?;
+
+    let quoter_url = serve_ekubo_quoter().await?;
+    let workdir = env::temp_dir().join(format!("vesu-liquidator-e2e-{}", std::process::id()));
+    std::fs::create_dir_all(&workdir)?;
+    let storage_path = workdir.join("data.json");
+    let bot = Bot {
+        workdir: workdir.clone(),
+        common_args: vec![
+            "--network".to_string(),
+            "katana".to_string(),
+            "--rpc-url".to_string(),
+            rpc_url.clone(),
+            "--account-address".to_string(),
+            bot_address,
+            "--private-key".to_string(),
+            bot_private_key,
+            "--config-path".to_string(),
+            concat!(env!("CARGO_MANIFEST_DIR"), "/config.yaml").to_string(),
+            "--storage-path".to_string(),
+            storage_path.display().to_string(),
+            "--audit-log-path".to_string(),
+            workdir.join("audit.log").display().to_string(),
+        ],
+        env: vec![
+            (
+                "LIQUIDATOR__VESU__KATANA__SINGLETON_ADDRESS",
+                format!("{singleton:#x}"),
+            ),
+            (
+                "LIQUIDATOR__VESU__KATANA__EXTENSION_ADDRESS",
+                format!("{singleton:#x}"),
+            ),
+            (
+                "LIQUIDATOR__VESU__KATANA__LIQUIDATE_ADDRESS",
+                format!("{liquidate:#x}"),
+            ),
+            // Not called: the prices are static.
+            (
+                "LIQUIDATOR__VESU__KATANA__PRAGMA_ORACLE_ADDRESS",
+                "0x1".to_string(),
+            ),
+            (
+                "LIQUIDATOR__VESU__KATANA__WORLD_ADDRESS",
+                format!("{world_address:#x}"),
+            ),
+            (
+                "LIQUIDATOR__VESU__KATANA__TORII_GRAPHQL_URL",
+                torii_graphql_url,
+            ),
+            (
+                "LIQUIDATOR__ORACLE__SOURCES",
+                r#"[{"kind": "static", "price": 1.0}]"#.to_string(),
+            ),
+            ("LIQUIDATOR__SWAP__EKUBO_QUOTER_URL", quoter_url),
+        ],
+    };
+
+    // Indexing: the position is found in the events of the singleton.
+    bot.run(&["backfill", "--from", "0", "--to", &latest_block.to_string()])?;
+    let storage = read_json(&storage_path)?;
+    let positions = storage["positions"]
+        .as_object()
+        .context("No positions in the storage")?;
+    ensure!(
+        positions.len() == 1,
+        "Expected 1 position, got {positions:?}"
+    );
+
+    // Liquidation: the position is emptied & the earnings left pending.
+    bot.run(&[
+        "liquidate",
+        "--pool",
+        &format!("{POOL_ID:#x}"),
+        "--user",
+        &format!("{BORROWER:#x}"),
+    ])?;
+    let position = provider
+        .call(
+            FunctionCall {
+                contract_address: singleton,
+                entry_point_selector: get_selector_from_name("position_unsafe")?,
+                calldata: vec![POOL_ID, ETH, STRK, BORROWER],
+            },
+            BlockId::Tag(BlockTag::PreConfirmed),
+        )
+        .await?;
+    ensure!(
+        position.iter().all(|felt| *felt == Felt::ZERO),
+        "The position was not liquidated: {position:?}"
+    );
+    let storage = read_json(&storage_path)?;
+    let liquidation_tx = storage["pending_distribution"]["earnings"][0]["liquidation_tx"]
+        .as_str()
+        .context("No pending earnings after the liquidation")?
+        .to_string();
+
+    // Distribution: the only player of the redeem queue gets all the
+    // residual collateral.
+    let balance_before = balance_of(&provider, ETH, player.address()).await?;
+    bot.run(&["distribute", "--tx-hash", &liquidation_tx])?;
+    let balance_after = balance_of(&provider, ETH, player.address()).await?;
+    ensure!(
+        balance_after - balance_before == COLLATERAL - DEBT,
+        "The player received {} instead of {}",
+        balance_after - balance_before,
+        COLLATERAL - DEBT
+    );
+    // The earnings of a liquidation are not distributed twice.
+    ensure!(
+        bot.run(&["distribute", "--tx-hash", &liquidation_tx])
+            .is_err(),
+        "The earnings of {liquidation_tx} were distributed twice"
+    );
+
+    std::fs::remove_dir_all(&workdir)?;
+    Ok(())
+}
+
+/// The bot binary, run with the network & storage params of the test.
+struct Bot {
+    workdir: PathBuf,
+    common_args: Vec<String>,
+    env: Vec<(&'static str, String)>,
+}
+
+impl Bot {
+    fn run(&self, args: &[&str]) -> Result<()> {
+        let status = Command::new(env!("CARGO_BIN_EXE_vesu-liquidator"))
+            .current_dir(&self.workdir)
+            .args(args)
+            .args(&self.common_args)
+            .envs(self.env.iter().cloned())
+            .status()?;
+        if !status.success() {
+            bail!("`vesu-liquidator {}` failed with {status}", args.join(" "));
+        }
+        Ok(())
+    }
+}
+
+/// Serves a route of a single split for any quote, as the Liquidate mock
+/// doesn't swap. Returns the URL of the quoter.
+async fn serve_ekubo_quoter() -> Result<String> {
+    async fn quote() -> Json<Value> {
+        Json(json!({
+            "total": "0",
+            "splits": [{
+                "amount_specified": "0",
+                "amount_calculated": "0",
+                "route": [{
+                    "pool_key": {
+                        "token0": format!("{ETH:#x}"),
+                        "token1": format!("{STRK:#x}"),
+                        "fee": "0x0",
+                        "tick_spacing": 1,
+                        "extension": "0x0",
+                    },
+                    "sqrt_ratio_limit": "0x1",
+                    "skip_ahead": 0,
+                }],
+            }],
+        }))
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let address = listener.local_addr()?;
+    let app = Router::new().route("/{*quote}", get(quote));
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    Ok(format!("http://{address}"))
+}
+
+async fn account(
+    provider: &Arc<JsonRpcClient<HttpTransport>>,
+    address: &str,
+    private_key: &str,
+) -> Result<E2eAccount> {
+    let mut account = SingleOwnerAccount::new(
+        provider.clone(),
+        LocalWallet::from(SigningKey::from_secret_scalar(Felt::from_hex(private_key)?)),
+        Felt::from_hex(address)?,
+        provider.chain_id().await?,
+        ExecutionEncoding::New,
+    );
+    account.set_block_id(BlockId::Tag(BlockTag::PreConfirmed));
+    Ok(account)
+}
+
+/// Declares the contract built by scarb in `e2e/contracts` & deploys it
+/// through the UDC, returning its address.
+async fn declare_and_deploy(
+    account: &E2eAccount,
+    contracts_dir: &Path,
+    contract: &str,
+    constructor_calldata: Vec<Felt>,
+) -> Result<Felt> {
+    let target = contracts_dir.join("target/dev");
+    let sierra: SierraClass = serde_json::from_value(read_json(
+        &target.join(format!("liquidator_e2e_{contract}.contract_class.json")),
+    )?)?;
+    let casm: CompiledClass = serde_json::from_value(read_json(&target.join(format!(
+        "liquidator_e2e_{contract}.compiled_contract_class.json"
+    )))?)?;
+    let class_hash = sierra.class_hash()?;
+
+    let declared = account
+        .provider()
+        .get_class(BlockId::Tag(BlockTag::PreConfirmed), class_hash)
+        .await
+        .is_ok();
+    if !declared {
+        let result = account
+            .declare_v3(Arc::new(sierra.flatten()?), casm.class_hash()?)
+            .send()
+            .await?;
+        wait_for_tx(account.provider(), result.transaction_hash).await?;
+    }
+
+    let factory = ContractFactory::new(class_hash, account);
+    let deployment = factory.deploy_v3(constructor_calldata, Felt::ZERO, true);
+    let address = deployment.deployed_address();
+    let result = deployment.send().await?;
+    wait_for_tx(account.provider(), result.transaction_hash).await?;
+    Ok(address)
+}
+
+async fn execute(account: &E2eAccount, calls: Vec<Call>) -> Result<()> {
+    let result = account.execute_v3(calls).send().await?;
+    wait_for_tx(account.provider(), result.transaction_hash).await
+}
+
+/// Waits for the transaction to be included, failing if it reverted.
+async fn wait_for_tx(provider: &Arc<JsonRpcClient<HttpTransport>>, tx_hash: Felt) -> Result<()> {
+    for _ in 0..30 {
+        if let Ok(receipt) = provider.get_transaction_receipt(tx_hash).await {
+            if let Some(reason) = receipt.receipt.execution_result().revert_reason() {
+                bail!("Transaction {tx_hash:#x} reverted: {reason}");
+            }
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    Err(anyhow!("Transaction {tx_hash:#x} was not included"))
+}
+
+async fn balance_of(
+    provider: &Arc<JsonRpcClient<HttpTransport>>,
+    token: Felt,
+    owner: Felt,
+) -> Result<u128> {
+    let balance = provider
+        .call(
+            FunctionCall {
+                contract_address: token,
+                entry_point_selector: get_selector_from_name("balance_of")?,
+                calldata: vec![owner],
+            },
+            BlockId::Tag(BlockTag::PreConfirmed),
+        )
+        .await?;
+    let low = balance.first().context("Empty balance_of result")?;
+    Ok(u128::try_from(*low)?)
+}
+
+fn call(to: Felt, selector: &str, calldata: Vec<Felt>) -> Call {
+    Call {
+        to,
+        selector: get_selector_from_name(selector).expect("valid selector"),
+        calldata,
+    }
+}
+
+fn read_json(path: &Path) -> Result<Value> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read {}", path.display()))?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+fn felt_at(value: &Value) -> Result<Felt> {
+    Ok(Felt::from_hex(
+        value.as_str().context("Expected an address")?,
+    )?)
+}
+
+fn env_or(key: &str, default: &str) -> String {
+    env::var(key).unwrap_or_else(|_| default.to_string())
+}